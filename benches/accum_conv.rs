@@ -67,6 +67,8 @@ impl Circuit<Fr> for MyCircuit {
                         Op::Conv {
                             padding: (0, 0),
                             stride: (1, 1),
+                            group: 1,
+                            lowering: ConvLowering::Direct,
                         }
                         .into(),
                     )
@@ -0,0 +1,26 @@
+import onnx
+from onnx import helper, TensorProto
+
+# the Mul of the initializers and the Identity are folded, and the Dropout bypassed
+a = helper.make_tensor("a", TensorProto.FLOAT, [3], [1.0, 2.0, 3.0])
+b = helper.make_tensor("b", TensorProto.FLOAT, [3], [2.0, 2.0, 2.0])
+
+graph = helper.make_graph(
+    [
+        helper.make_node("Mul", ["a", "b"], ["t"], name="mul"),
+        helper.make_node("Identity", ["t"], ["u"], name="identity"),
+        helper.make_node("Add", ["input", "u"], ["y"], name="add"),
+        helper.make_node("Dropout", ["y"], ["z"], name="dropout"),
+        helper.make_node("Relu", ["z"], ["output"], name="relu"),
+    ],
+    "folded",
+    [helper.make_tensor_value_info("input", TensorProto.FLOAT, [3])],
+    [helper.make_tensor_value_info("output", TensorProto.FLOAT, [3])],
+    initializer=[a, b],
+)
+
+model = helper.make_model(
+    graph, producer_name="ezkl", opset_imports=[helper.make_opsetid("", 13)]
+)
+model.ir_version = 8
+onnx.save(model, "network.onnx")
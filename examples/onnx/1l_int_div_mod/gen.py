@@ -0,0 +1,23 @@
+import onnx
+from onnx import helper, TensorProto
+
+# int64 constants divide and take the remainder of an int64 input
+two = helper.make_tensor("two", TensorProto.INT64, [1], [2])
+three = helper.make_tensor("three", TensorProto.INT64, [1], [3])
+
+graph = helper.make_graph(
+    [
+        helper.make_node("Div", ["input", "two"], ["q"], name="div"),
+        helper.make_node("Mod", ["q", "three"], ["output"], name="mod"),
+    ],
+    "int_div_mod",
+    [helper.make_tensor_value_info("input", TensorProto.INT64, [3])],
+    [helper.make_tensor_value_info("output", TensorProto.INT64, [3])],
+    initializer=[two, three],
+)
+
+model = helper.make_model(
+    graph, producer_name="ezkl", opset_imports=[helper.make_opsetid("", 13)]
+)
+model.ir_version = 8
+onnx.save(model, "network.onnx")
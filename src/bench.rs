@@ -0,0 +1,104 @@
+use crate::commands::RunArgs;
+use crate::memory::{self, PhaseMemory};
+use log::info;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::Instant;
+use tabled::{Table, Tabled};
+
+/// The time taken by a phase of a benchmark.
+#[derive(Clone, Debug, Serialize, Tabled)]
+pub struct PhaseTiming {
+    /// The name of the phase
+    pub phase: String,
+    /// The wall-clock time the phase took, in seconds
+    pub secs: f64,
+}
+
+/// A report of the time and memory taken to generate the keys, the witness and a proof of a model
+/// and to verify the proof, along with the sizes of the keys and proof, such that performance can
+/// be compared across versions and machines.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchReport {
+    /// The version of ezkl the benchmark ran on
+    pub version: String,
+    /// The OS the benchmark ran on
+    pub os: String,
+    /// The architecture of the machine the benchmark ran on
+    pub arch: String,
+    /// The number of threads available to the prover
+    pub threads: usize,
+    /// The settings of the circuit
+    pub run_args: RunArgs,
+    /// The time taken by each phase
+    pub timings: Vec<PhaseTiming>,
+    /// The memory used by each phase
+    pub memory: Vec<PhaseMemory>,
+    /// The peak resident set size of the process, in bytes (where the OS reports it)
+    pub peak_rss: Option<usize>,
+    /// The size of the proof, in bytes
+    pub proof_size: usize,
+    /// The size of the verifying key, in bytes
+    pub vk_size: usize,
+    /// The size of the (uncompressed) proving key, in bytes
+    pub pk_size: usize,
+    /// Whether the proof verified
+    pub verified: bool,
+}
+
+impl BenchReport {
+    /// Creates an empty report of a benchmark of a circuit with `run_args`, on this machine.
+    pub fn new(run_args: RunArgs) -> Self {
+        BenchReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            threads: rayon::current_num_threads(),
+            run_args,
+            timings: vec![],
+            memory: vec![],
+            peak_rss: None,
+            proof_size: 0,
+            vk_size: 0,
+            pk_size: 0,
+            verified: false,
+        }
+    }
+
+    /// Runs the phase `name`, recording the time it takes. The memory it uses is measured by the
+    /// phases the steps of `phase` enter (see [memory::enter_phase]).
+    pub fn time<T>(&mut self, name: &str, phase: impl FnOnce() -> T) -> T {
+        let now = Instant::now();
+        let result = phase();
+        let secs = now.elapsed().as_secs_f64();
+        info!("{} took {:.3}s", name, secs);
+        self.timings.push(PhaseTiming {
+            phase: name.to_string(),
+            secs,
+        });
+        result
+    }
+
+    /// Records the memory used by the phases, and logs the timings.
+    pub fn finish(&mut self) {
+        self.memory = memory::phases();
+        self.peak_rss = memory::peak_rss();
+        info!("benchmark:\n{}", Table::new(&self.timings));
+    }
+}
+
+/// A writer counting the bytes written to it without holding them, to measure the serialized
+/// size of the keys.
+#[derive(Debug, Default)]
+pub struct ByteCounter(pub usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -3,14 +3,21 @@ use colored_json::prelude::*;
 use env_logger::Builder;
 use ezkl_lib::commands::Cli;
 use ezkl_lib::execute::run;
+use ezkl_lib::memory::TrackingAllocator;
 use log::{error, info, Level, LevelFilter, Record};
 use rand::seq::SliceRandom;
 use std::env;
 use std::error::Error;
 use std::fmt::Formatter;
 use std::io::Write;
+#[cfg(feature = "profiling")]
+use std::path::Path;
 use std::time::Instant;
 
+// only counts allocations when run with --memory-report
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
 #[allow(dead_code)]
 pub fn level_color(level: &log::Level, msg: &str) -> String {
     match level {
@@ -75,12 +82,32 @@ pub fn init_logger() {
     builder.init();
 }
 
+/// Records the spans of the phases of the command as folded stacks in `path`, which are written
+/// out when the returned guard is dropped.
+#[cfg(feature = "profiling")]
+pub fn init_profiling(
+    path: &Path,
+) -> Result<tracing_flame::FlushGuard<std::io::BufWriter<std::fs::File>>, Box<dyn Error>> {
+    use tracing_subscriber::prelude::*;
+    let (flame_layer, guard) = tracing_flame::FlameLayer::with_file(path)?;
+    // the rayon threads proving in parallel are merged into the stacks of the main thread
+    let flame_layer = flame_layer.with_threads_collapsed(true);
+    // not installed with init(), which would replace the logger with one forwarding to tracing
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(flame_layer))?;
+    Ok(guard)
+}
+
 #[tokio::main(flavor = "current_thread")]
 pub async fn main() -> Result<(), Box<dyn Error>> {
     let args = Cli::create().unwrap();
     init_logger();
     banner();
     info!("command: \n {}", &args.as_json()?.to_colored_json_auto()?);
+    #[cfg(feature = "profiling")]
+    let _trace = match &args.trace_path {
+        Some(path) => Some(init_profiling(path)?),
+        None => None,
+    };
     let res = run(args).await;
     match &res {
         Ok(_) => info!("succeeded"),
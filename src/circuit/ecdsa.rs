@@ -0,0 +1,175 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::{CurveAffine, FieldExt},
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{ConstraintSystem, Error},
+};
+use halo2_wrong_ecc::{
+    integer::{IntegerInstructions, Range},
+    maingate::{
+        MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
+        RegionCtx, Term,
+    },
+    EccConfig, GeneralEccChip,
+};
+use halo2curves::group::{
+    ff::{Field, PrimeField},
+    Curve, Group,
+};
+use halo2curves::secp256k1::{Fp, Fq, Secp256k1, Secp256k1Affine};
+use itertools::Itertools;
+use rand::rngs::OsRng;
+
+const LIMBS: usize = 4;
+const BITS: usize = 68;
+/// The window size of the scalar multiplications.
+const WINDOW_SIZE: usize = 2;
+
+type EccChip<F> = GeneralEccChip<Secp256k1Affine, F, LIMBS, BITS>;
+
+/// Reads a secp256k1 public key from its uncompressed SEC1 encoding (`04 || x || y`, as hex).
+pub fn parse_public_key(encoded: &str) -> Option<Secp256k1Affine> {
+    let bytes = hex::decode(encoded.trim_start_matches("0x")).ok()?;
+    if bytes.len() != 65 || bytes[0] != 4 {
+        return None;
+    }
+    let x: Option<Fp> = Fp::from_repr(be_to_repr(&bytes[1..33])?).into();
+    let y: Option<Fp> = Fp::from_repr(be_to_repr(&bytes[33..])?).into();
+    Secp256k1Affine::from_xy(x?, y?).into()
+}
+
+/// Reads an ECDSA signature from the big endian encodings of its r and s (as hex).
+pub fn parse_signature(encoded: &str) -> Option<(Fq, Fq)> {
+    let bytes = hex::decode(encoded.trim_start_matches("0x")).ok()?;
+    if bytes.len() != 64 {
+        return None;
+    }
+    let r: Option<Fq> = Fq::from_repr(be_to_repr(&bytes[..32])?).into();
+    let s: Option<Fq> = Fq::from_repr(be_to_repr(&bytes[32..])?).into();
+    r.zip(s)
+}
+
+fn be_to_repr(bytes: &[u8]) -> Option<[u8; 32]> {
+    let mut repr: [u8; 32] = bytes.try_into().ok()?;
+    repr.reverse();
+    Some(repr)
+}
+
+/// The integer held by a field element, as an element of the field `W`.
+fn to_field<F: FieldExt, W: FieldExt>(x: &F) -> W {
+    let repr = x.to_repr();
+    let (lower, upper) = repr.as_ref().split_at(16);
+    let half = |bytes: &[u8]| W::from_u128(u128::from_le_bytes(bytes.try_into().unwrap()));
+    let shift = W::from_u128(1u128 << 64).square();
+    half(upper) * shift + half(lower)
+}
+
+/// Configuration for an ECDSA chip verifying secp256k1 signatures, with the integer arithmetic
+/// over the fields of secp256k1 emulated in the native field.
+#[derive(Clone, Debug)]
+pub struct EcdsaConfig<F: FieldExt> {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> EcdsaConfig<F> {
+    /// Configures the chip's main gate and the range checks of the emulated integers.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let (rns_base, rns_scalar) = EccChip::<F>::rns();
+        let main_gate_config = MainGate::<F>::configure(meta);
+        let overflow_bits = rns_base
+            .overflow_lengths()
+            .into_iter()
+            .chain(rns_scalar.overflow_lengths())
+            .collect_vec();
+        let range_config =
+            RangeChip::<F>::configure(meta, &main_gate_config, vec![BITS / LIMBS], overflow_bits);
+        EcdsaConfig {
+            main_gate_config,
+            range_config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Verifies the `signature` of `public_key` over a message hash. The hash is given as
+    /// `message`, cells holding limbs of it (most significant first) which all but the first are
+    /// of 128 bits, and is taken modulo the order of secp256k1 as the message hash of the
+    /// signature.
+    pub fn layout(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        message: &[AssignedCell<F, F>],
+        public_key: Secp256k1Affine,
+        signature: Value<(Fq, Fq)>,
+    ) -> Result<(), Error> {
+        RangeChip::<F>::new(self.range_config.clone()).load_table(layouter)?;
+        let mut ecc_chip = EccChip::<F>::new(EccConfig::new(
+            self.range_config.clone(),
+            self.main_gate_config.clone(),
+        ));
+        layouter.assign_region(
+            || "ecdsa aux",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                let aux_generator = Secp256k1::random(OsRng).to_affine();
+                ecc_chip.assign_aux_generator(ctx, Value::known(aux_generator))?;
+                ecc_chip.assign_aux(ctx, WINDOW_SIZE, 1)
+            },
+        )?;
+
+        layouter.assign_region(
+            || "ecdsa",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                let main_gate = MainGate::<F>::new(self.main_gate_config.clone());
+                let scalar_chip = ecc_chip.scalar_field_chip();
+                let base_chip = ecc_chip.base_field_chip();
+                let shift = F::from_u128(1u128 << 64).square();
+                let fq_shift = Fq::from_u128(1u128 << 64).square();
+
+                // the message hash, whose native value is that of the limbs of the message
+                let mut hash = Value::known(Fq::zero());
+                let mut terms = vec![];
+                for (k, limb) in message.iter().enumerate() {
+                    hash = hash
+                        .zip(limb.value())
+                        .map(|(h, l)| h * fq_shift + to_field::<F, Fq>(l));
+                    terms.push(Term::Assigned(
+                        limb,
+                        shift.pow_vartime([(message.len() - 1 - k) as u64]),
+                    ));
+                }
+                let composed = main_gate.compose(ctx, &terms, F::zero())?;
+                let hash = ecc_chip.new_unassigned_scalar(hash);
+                let hash = scalar_chip.assign_integer(ctx, hash, Range::Remainder)?;
+                main_gate.assert_equal(ctx, hash.native(), &composed)?;
+
+                let r = ecc_chip.new_unassigned_scalar(signature.map(|(r, _)| r));
+                let s = ecc_chip.new_unassigned_scalar(signature.map(|(_, s)| s));
+                let r = scalar_chip.assign_integer(ctx, r, Range::Remainder)?;
+                let s = scalar_chip.assign_integer(ctx, s, Range::Remainder)?;
+                let public_key = ecc_chip.assign_constant(ctx, public_key)?;
+                let generator = ecc_chip.assign_constant(ctx, Secp256k1Affine::generator())?;
+
+                // 0 < r, s < n, as the non zero checks include checks of being within the field
+                scalar_chip.assert_not_zero(ctx, &r)?;
+                scalar_chip.assert_not_zero(ctx, &s)?;
+
+                // u1 = hash / s, u2 = r / s
+                let (s_inv, _) = scalar_chip.invert(ctx, &s)?;
+                let u1 = scalar_chip.mul(ctx, &hash, &s_inv)?;
+                let u2 = scalar_chip.mul(ctx, &r, &s_inv)?;
+
+                // the x coordinate of u1 * G + u2 * Q is r modulo n
+                let g1 = ecc_chip.mul(ctx, &generator, &u1, WINDOW_SIZE)?;
+                let g2 = ecc_chip.mul(ctx, &public_key, &u2, WINDOW_SIZE)?;
+                let point = ecc_chip.add(ctx, &g1, &g2)?;
+                let x = base_chip.reduce(ctx, point.x())?;
+                let x = scalar_chip.reduce_external(ctx, &x)?;
+                scalar_chip.assert_strict_equal(ctx, &x, &r)
+            },
+        )
+    }
+}
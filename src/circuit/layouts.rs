@@ -9,13 +9,28 @@ use crate::{
     fieldutils::i128_to_felt,
     tensor::{
         ops::{
-            accumulated, add, affine as non_accum_affine, convolution as non_accum_conv,
-            dot as non_accum_dot, matmul as non_accum_matmul, mult,
-            nonlinearities::prelu as ref_prelu, pack as non_accum_pack, rescale as ref_rescaled,
-            scale_and_shift as ref_scale_and_shift, sub, sum as non_accum_sum,
-            sumpool as non_accum_sumpool,
+            abs as ref_abs, accumulated, add, affine as non_accum_affine, argmax as ref_argmax,
+            bitwise as ref_bitwise, clip as ref_clip, compare as ref_compare,
+            convolution as non_accum_conv, cumsum as ref_cumsum, deconv_image, deconv_kernel,
+            depth_to_space as ref_depth_to_space, dot as non_accum_dot, embedding as ref_embedding,
+            expand as ref_expand, gather as ref_gather, gather_elements as ref_gather_elements,
+            gather_nd as ref_gather_nd, grid_sample as ref_grid_sample, gru as ref_gru, im2col,
+            logic as ref_logic, lstm as ref_lstm, matmul as non_accum_matmul,
+            max_pool2d as ref_max_pool2d, max_pool3d as ref_max_pool3d, mult,
+            non_max_suppression as ref_non_max_suppression, nonlinearities::prelu as ref_prelu,
+            pack as non_accum_pack, pad3d, pad_with_fill as ref_pad_with_fill,
+            reduce as ref_reduce, rescale as ref_rescaled, resize_linear as ref_resize_linear,
+            resize_linear_taps, resize_nearest as ref_resize_nearest, rnn as ref_rnn,
+            roi_align as ref_roi_align, scale_and_shift as ref_scale_and_shift,
+            scatter_elements as ref_scatter_elements, scatter_nd as ref_scatter_nd,
+            select as ref_select, sign as ref_sign, slice as ref_slice, softmax as ref_softmax,
+            space_to_depth as ref_space_to_depth, sub, sum as non_accum_sum,
+            sumpool as non_accum_sumpool, tile as ref_tile, topk as ref_topk,
+            tree_ensemble as ref_tree_ensemble, winograd_tiles, winograd_transforms, Bitwise,
+            Comparison, DepthToSpaceMode, EinsumIndices, Logic, NmsParams, PadFill, Reduction,
+            RoiTaps, TreeEnsemble, TreeNode,
         },
-        Tensor, TensorError,
+        Tensor, TensorError, ValType,
     },
 };
 
@@ -223,7 +238,8 @@ pub fn pairwise<F: FieldExt + TensorType>(
     let op_result = match op {
         BaseOp::Add => add(&inputs),
         BaseOp::Sub => sub(&inputs),
-        BaseOp::Mult => mult(&inputs),
+        // the sign gate outputs the input times its sign
+        BaseOp::Mult | BaseOp::Sign => mult(&inputs),
         _ => panic!(),
     }
     .map_err(|e| {
@@ -435,6 +451,204 @@ pub fn matmul<F: FieldExt + TensorType>(
     Ok(ValTensor::from(res))
 }
 
+/// Matmul layout of 2D `values` with an element-wise lookup fused into it. The dot product of
+/// each output is accumulated as in [matmul] and followed by a row whose output cell holds the
+/// lookup of the accumulated value above it (see [BaseConfig::configure_fused_lookup]), such that
+/// the dot products are looked up without being copied to the lookup input column. The input
+/// cells of these rows are padded with zeros.
+pub fn matmul_lookup<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    nl: &LookupOp,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut a, mut b) = (values[0].clone(), values[1].clone());
+    if a.dims().len() != 2 || b.dims().len() != 2 || a.dims()[1] != b.dims()[0] {
+        return Err(Box::new(CircuitError::DimMismatch(
+            "fused matmul layout".to_string(),
+        )));
+    }
+    let (m, k, n) = (a.dims()[0], a.dims()[1], b.dims()[1]);
+    // the k products of each output, then the row of its lookup
+    let segment = k + 1;
+
+    // a row of a and a column of b per output
+    b.transpose_2d()?;
+    a.repeat_rows(n)?;
+    a.reshape(&[m * n, k])?;
+    b.tile(m)?;
+    b.reshape(&[m * n, k])?;
+    let zeros: ValTensor<F> =
+        Tensor::new(Some(&vec![Value::known(F::zero()); m * n]), &[m * n, 1])?.into();
+
+    let mut inputs = vec![];
+    let mut assigned_len = 0;
+    for (i, elem) in [a.append_to_row(zeros.clone())?, b.append_to_row(zeros)?]
+        .iter()
+        .enumerate()
+    {
+        let inp = utils::value_muxer(
+            &config.inputs[i],
+            &{
+                let (res, len) = config.inputs[i].assign_with_duplication(
+                    region,
+                    *offset,
+                    elem,
+                    &config.check_mode,
+                )?;
+                assigned_len = len;
+                res.map(|e| e.value_field().evaluate())
+            },
+            elem,
+        );
+        inputs.push(inp);
+    }
+
+    let mut transcripts = vec![];
+    for (a_row, b_row) in inputs[0].chunks(segment).zip(inputs[1].chunks(segment)) {
+        let transcript = accumulated::dot(&[
+            Tensor::from(a_row[..k].iter().cloned()),
+            Tensor::from(b_row[..k].iter().cloned()),
+        ])?;
+        transcripts.push(transcript);
+    }
+
+    let sums: ValTensor<F> = Tensor::from(transcripts.iter().map(|t| t[k - 1])).into();
+    let integer_evals: Tensor<i128> = sums
+        .get_int_evals()
+        .map_err(|e| {
+            error!("{}", e);
+            halo2_proofs::plonk::Error::Synthesis
+        })?
+        .into_iter()
+        .into();
+    // for key generation integer_evals will be empty and we need to return a set of unassigned values
+    let lookups: Tensor<Value<F>> = match integer_evals.len() {
+        0 => Tensor::from((0..m * n).map(|_| Value::unknown())),
+        _ => nl
+            .f(integer_evals)?
+            .map(|elem| Value::known(i128_to_felt(elem))),
+    };
+
+    let output: Tensor<Value<F>> = transcripts
+        .into_iter()
+        .zip(lookups.iter())
+        .flat_map(|(transcript, lookup)| transcript.into_iter().chain([*lookup]))
+        .into();
+    let (output, output_assigned_len) = config.output.assign_with_duplication(
+        region,
+        *offset,
+        &output.into(),
+        &config.check_mode,
+    )?;
+
+    assert_eq!(assigned_len, output_assigned_len);
+
+    let mut idx_wo_duplicates = 0;
+    for i in 0..assigned_len {
+        let (x, y) = config.output.cartesian_coord(*offset + i);
+        // skip over duplicates at start of column
+        if y == 0 && i > 0 {
+            continue;
+        }
+        let selector = match idx_wo_duplicates % segment {
+            0 => config.selectors.get(&(BaseOp::Mult, x)),
+            j if j < k => config.selectors.get(&(BaseOp::Dot, x)),
+            _ => config.fused_lookup_selectors.get(&(nl.clone(), x)),
+        };
+        selector
+            .ok_or(CircuitError::LookupInstantiation)?
+            .enable(region, y)?;
+        idx_wo_duplicates += 1;
+    }
+
+    let mut output = ValTensor::from(output);
+    output.reshape(&[m * n, segment])?;
+    let mut res = output.get_slice(&[0..m * n, k..segment])?;
+    res.reshape(&[m, n])?;
+
+    *offset += assigned_len;
+
+    Ok(res)
+}
+
+/// Einsum layout. Two operand equations are permuted and reshaped into a batched [matmul] of
+/// `B x M x K` and `B x K x N` matrices, the result is then permuted into the output order.
+pub fn einsum<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    equation: &str,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let indices = EinsumIndices::new(equation)?;
+    if !indices.is_matmul() {
+        return Err(Box::new(TensorError::DimMismatch(format!(
+            "einsum {} can't be lowered to a matmul",
+            equation
+        ))));
+    }
+    let sizes = indices.sizes(&values.iter().map(|v| v.dims().to_vec()).collect::<Vec<_>>())?;
+
+    let size_of = |group: &[char]| group.iter().map(|c| sizes[c]).product::<usize>();
+    // the axes of `group` within `axes`
+    let positions = |axes: &[char], group: &[char]| {
+        group
+            .iter()
+            .map(|c| axes.iter().position(|a| a == c).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let (b, m, k, n) = (
+        size_of(&indices.batch),
+        size_of(&indices.lhs_free),
+        size_of(&indices.contracted),
+        size_of(&indices.rhs_free),
+    );
+
+    let mut lhs = values[0].clone();
+    lhs.permute(
+        &[
+            positions(&indices.inputs[0], &indices.batch),
+            positions(&indices.inputs[0], &indices.lhs_free),
+            positions(&indices.inputs[0], &indices.contracted),
+        ]
+        .concat(),
+    )?;
+    lhs.reshape(&[b, m, k])?;
+
+    let mut rhs = values[1].clone();
+    rhs.permute(
+        &[
+            positions(&indices.inputs[1], &indices.batch),
+            positions(&indices.inputs[1], &indices.contracted),
+            positions(&indices.inputs[1], &indices.rhs_free),
+        ]
+        .concat(),
+    )?;
+    rhs.reshape(&[b, k, n])?;
+
+    let mut res = matmul(config, region, &[lhs, rhs], offset)?;
+
+    if indices.output.is_empty() {
+        res.reshape(&[1])?;
+        return Ok(res);
+    }
+
+    // [batch, lhs_free, rhs_free] -> output
+    let axes = [
+        indices.batch.clone(),
+        indices.lhs_free.clone(),
+        indices.rhs_free.clone(),
+    ]
+    .concat();
+    res.reshape(&axes.iter().map(|c| sizes[c]).collect::<Vec<_>>())?;
+    res.permute(&positions(&axes, &indices.output))?;
+
+    Ok(res)
+}
+
 /// Affine operation accumulated layout
 pub fn affine<F: FieldExt + TensorType>(
     config: &mut BaseConfig<F>,
@@ -531,6 +745,7 @@ pub fn sumpool<F: FieldExt + TensorType>(
             &[values[0].get_slice(&[i..i + 1])?, kernel.clone().into()],
             padding,
             stride,
+            ConvLowering::Direct,
             offset,
         )?);
     }
@@ -564,15 +779,354 @@ pub fn sumpool<F: FieldExt + TensorType>(
     Ok(last_elem)
 }
 
-/// Convolution accumulated layout
-pub fn conv<F: FieldExt + TensorType>(
+/// Max pooling layout. The running maximum over each window is computed as `max(a, b) = b + relu(a - b)`,
+/// so every comparison is enforced by a ReLU lookup.
+pub fn max_pool2d<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    pool_dims: (usize, usize),
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let image = values[0].clone();
+
+    if image.dims().len() != 3 {
+        return Err(Box::new(TensorError::DimMismatch("max_pool2d".to_string())));
+    }
+    let image_dims = image.dims();
+
+    let input_channels = image_dims[0];
+    let (image_height, image_width) = (image_dims[1], image_dims[2]);
+
+    let image = image.get_inner_tensor()?;
+
+    let horz_slides = (image_height + 2 * padding.0 - pool_dims.0) / stride.0 + 1;
+    let vert_slides = (image_width + 2 * padding.1 - pool_dims.1) / stride.1 + 1;
+    let output_dims = [input_channels, horz_slides, vert_slides];
+
+    // a padded position takes the nearest element of the image, which is in the same window, such
+    // that the padding is never the max (as if it were -inf)
+    let unpadded = |x: usize, pad: usize, len: usize| x.saturating_sub(pad).min(len - 1);
+
+    // gather the (kh, kw)-th element of every window into its own tensor
+    let mut windows = vec![];
+    for kh in 0..pool_dims.0 {
+        for kw in 0..pool_dims.1 {
+            let mut elems = vec![];
+            for i in 0..input_channels {
+                for j in 0..horz_slides {
+                    for k in 0..vert_slides {
+                        elems.push(image.get(&[
+                            i,
+                            unpadded(j * stride.0 + kh, padding.0, image_height),
+                            unpadded(k * stride.1 + kw, padding.1, image_width),
+                        ]));
+                    }
+                }
+            }
+            let window: ValTensor<F> = Tensor::new(Some(&elems), &output_dims)?.into();
+            windows.push(window);
+        }
+    }
+
+    let max = max_of_windows(config, region, &windows, offset)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(max.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_max_pool =
+                ref_max_pool2d(&int_input, padding, stride, pool_dims)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(max.get_inner()?), ref_max_pool)
+        }
+    };
+
+    Ok(max)
+}
+
+/// Takes the elementwise maximum of equally shaped tensors, where `max(a, b) = b + relu(a - b)`.
+fn max_of_windows<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    windows: &[ValTensor<F>],
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut max = windows[0].clone();
+    for window in windows[1..].iter() {
+        // window - max
+        let diff = pairwise(
+            config,
+            region,
+            &[window.clone(), max.clone()],
+            offset,
+            BaseOp::Sub,
+        )?;
+        // relu(window - max)
+        let relu = nonlinearity(config, region, &[diff], LookupOp::ReLU { scale: 1 }, offset)?;
+        // max + relu(window - max)
+        max = pairwise(config, region, &[max, relu], offset, BaseOp::Add)?;
+    }
+    Ok(max)
+}
+
+/// Gathers the (kd, kh, kw)-th element of every 3D pooling window over a C x D x H x W tensor
+/// into its own tensor of shape C x D' x H' x W'. The padding is zero, or the nearest element of
+/// the image within the window if `max`, such that it is never the max (as if it were -inf).
+fn pool3d_windows<F: FieldExt + TensorType>(
+    image: &ValTensor<F>,
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    pool_dims: (usize, usize, usize),
+    max: bool,
+) -> Result<Vec<ValTensor<F>>, Box<dyn Error>> {
+    if image.dims().len() != 4 {
+        return Err(Box::new(TensorError::DimMismatch("pool3d".to_string())));
+    }
+    let image_dims = image.dims();
+
+    let channels = image_dims[0];
+    let (image_depth, image_height, image_width) = (image_dims[1], image_dims[2], image_dims[3]);
+
+    let padded_image = match max {
+        true => image.get_inner_tensor()?,
+        false => pad3d(&image.get_inner_tensor()?, padding)?,
+    };
+    // the index into the padded image of a position of the padded windows
+    let index = |x: usize, pad: usize, len: usize| match max {
+        true => x.saturating_sub(pad).min(len - 1),
+        false => x,
+    };
+
+    let depth_slides = (image_depth + 2 * padding.0 - pool_dims.0) / stride.0 + 1;
+    let vert_slides = (image_height + 2 * padding.1 - pool_dims.1) / stride.1 + 1;
+    let horz_slides = (image_width + 2 * padding.2 - pool_dims.2) / stride.2 + 1;
+    let output_dims = [channels, depth_slides, vert_slides, horz_slides];
+
+    let mut windows = vec![];
+    for kd in 0..pool_dims.0 {
+        for kh in 0..pool_dims.1 {
+            for kw in 0..pool_dims.2 {
+                let mut elems = vec![];
+                for i in 0..channels {
+                    for d in 0..depth_slides {
+                        for j in 0..vert_slides {
+                            for k in 0..horz_slides {
+                                elems.push(padded_image.get(&[
+                                    i,
+                                    index(d * stride.0 + kd, padding.0, image_depth),
+                                    index(j * stride.1 + kh, padding.1, image_height),
+                                    index(k * stride.2 + kw, padding.2, image_width),
+                                ]));
+                            }
+                        }
+                    }
+                }
+                let window: ValTensor<F> = Tensor::new(Some(&elems), &output_dims)?.into();
+                windows.push(window);
+            }
+        }
+    }
+    Ok(windows)
+}
+
+/// 3D max pooling layout, see [max_pool2d].
+pub fn max_pool3d<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    pool_dims: (usize, usize, usize),
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let windows = pool3d_windows(&values[0], padding, stride, pool_dims, true)?;
+    let max = max_of_windows(config, region, &windows, offset)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(max.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_max_pool =
+                ref_max_pool3d(&int_input, padding, stride, pool_dims)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(max.get_inner()?), ref_max_pool)
+        }
+    };
+
+    Ok(max)
+}
+
+/// 3D average pooling layout. The window elements are summed pairwise and the sum is then divided
+/// by the number of elements in each window using a division lookup, or with a remainder
+/// ([div_rem]) if `remainder` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn avg_pool3d<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    pool_dims: (usize, usize, usize),
+    remainder: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let windows = pool3d_windows(&values[0], padding, stride, pool_dims, false)?;
+
+    let mut summed = windows[0].clone();
+    for window in windows[1..].iter() {
+        summed = pairwise(
+            config,
+            region,
+            &[summed, window.clone()],
+            offset,
+            BaseOp::Add,
+        )?;
+    }
+
+    let denom = pool_dims.0 * pool_dims.1 * pool_dims.2;
+    divide(config, region, summed, denom, remainder, offset)
+}
+
+/// 3D convolution layout. Each output depth slice is laid out as a 2D [conv] over the input
+/// channels stacked with their depth window, using the kernel reshaped to `O x (C * K_d) x K_h x K_w`.
+pub fn conv3d<F: FieldExt + TensorType>(
     config: &mut BaseConfig<F>,
     region: &mut Region<F>,
     values: &[ValTensor<F>],
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (image, kernel) = (values[0].clone(), values[1].clone());
+
+    if (image.dims().len() != 4)
+        || (kernel.dims().len() != 5)
+        || (image.dims()[0] != kernel.dims()[1])
+    {
+        return Err(Box::new(TensorError::DimMismatch("conv3d".to_string())));
+    }
+
+    let (output_channels, input_channels, kernel_depth, kernel_height, kernel_width) = (
+        kernel.dims()[0],
+        kernel.dims()[1],
+        kernel.dims()[2],
+        kernel.dims()[3],
+        kernel.dims()[4],
+    );
+    let (image_depth, image_height, image_width) =
+        (image.dims()[1], image.dims()[2], image.dims()[3]);
+
+    // only the depth is padded here, conv pads the spatial dims
+    let padded_image = pad3d(&image.get_inner_tensor()?, (padding.0, 0, 0))?;
+    let depth_slides = (image_depth + 2 * padding.0 - kernel_depth) / stride.0 + 1;
+
+    let mut stacked_kernel = kernel.clone();
+    stacked_kernel.reshape(&[
+        output_channels,
+        input_channels * kernel_depth,
+        kernel_height,
+        kernel_width,
+    ])?;
+
+    let mut res = vec![];
+    for d in 0..depth_slides {
+        let ds = d * stride.0;
+        let mut slice: ValTensor<F> = padded_image
+            .get_slice(&[0..input_channels, ds..(ds + kernel_depth)])?
+            .into();
+        slice.reshape(&[input_channels * kernel_depth, image_height, image_width])?;
+
+        let mut conv_values = vec![slice, stacked_kernel.clone()];
+        if values.len() == 3 {
+            conv_values.push(values[2].clone());
+        }
+        res.push(
+            conv(
+                config,
+                region,
+                &conv_values,
+                (padding.1, padding.2),
+                (stride.1, stride.2),
+                ConvLowering::Direct,
+                offset,
+            )?
+            .get_inner_tensor()?,
+        );
+    }
+
+    // interleave the output depth slices into O x D' x H' x W'
+    let (vert_slides, horz_slides) = (res[0].dims()[1], res[0].dims()[2]);
+    let mut output = Tensor::new(
+        None,
+        &[output_channels, depth_slides, vert_slides, horz_slides],
+    )?;
+    for (d, r) in res.iter().enumerate() {
+        for o in 0..output_channels {
+            for j in 0..vert_slides {
+                for k in 0..horz_slides {
+                    output.set(&[o, d, j, k], r.get(&[o, j, k]));
+                }
+            }
+        }
+    }
+
+    Ok(output.into())
+}
+
+/// Average pooling layout. The windows are summed using [sumpool] and the sum is then divided by the
+/// number of elements in each window using a division lookup, or with a remainder ([div_rem]) if
+/// `remainder` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn avg_pool2d<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
     padding: (usize, usize),
     stride: (usize, usize),
+    pool_dims: (usize, usize),
+    remainder: bool,
     offset: &mut usize,
 ) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let summed = sumpool(config, region, values, padding, stride, pool_dims, offset)?;
+    divide(
+        config,
+        region,
+        summed,
+        pool_dims.0 * pool_dims.1,
+        remainder,
+        offset,
+    )
+}
+
+/// The operands of the matmul a conv is lowered to. For a [ConvLowering::Direct] lowering these
+/// are the multi-channel blocked toeplitz matrix of the kernel, with the tiled bias appended to
+/// its rows, and the flattened padded image, padded with a row of ones. For an
+/// [ConvLowering::Im2col] lowering these are the flattened kernel, with the bias appended to its
+/// rows, and the windows of the image given by [im2col], padded with a row of ones.
+/// Also returns the dims of the output.
+fn conv_operands<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    lowering: ConvLowering,
+) -> Result<(ValTensor<F>, ValTensor<F>, [usize; 3]), Box<dyn Error>> {
+    if matches!(lowering, ConvLowering::Winograd { .. }) {
+        // the outputs of a winograd conv aren't the outputs of a matmul
+        return Err(Box::new(CircuitError::UnsupportedOp));
+    }
     let has_bias = values.len() == 3;
     let (image, kernel) = (values[0].clone(), values[1].clone());
 
@@ -586,7 +1140,7 @@ pub fn conv<F: FieldExt + TensorType>(
     let image_dims = image.dims();
     let kernel_dims = kernel.dims();
 
-    let (output_channels, _input_channels, kernel_height, kernel_width) = (
+    let (output_channels, input_channels, kernel_height, kernel_width) = (
         kernel_dims[0],
         kernel_dims[1],
         kernel_dims[2],
@@ -600,6 +1154,36 @@ pub fn conv<F: FieldExt + TensorType>(
     let vert_slides = (padded_height - kernel_height) / stride.0 + 1;
     let horz_slides = (padded_width - kernel_width) / stride.1 + 1;
 
+    if matches!(lowering, ConvLowering::Im2col) {
+        let mut flat_kernel = kernel.clone();
+        flat_kernel.reshape(&[
+            output_channels,
+            input_channels * kernel_height * kernel_width,
+        ])?;
+        let mut patches: ValTensor<F> = im2col(
+            &image.get_inner_tensor()?,
+            (kernel_height, kernel_width),
+            padding,
+            stride,
+        )?
+        .into();
+
+        let params = if has_bias {
+            let mut bias = values[2].clone();
+            if (bias.dims().len() != 1) || (bias.dims()[0] != output_channels) {
+                return Err(Box::new(TensorError::DimMismatch("conv bias".to_string())));
+            }
+            bias.reshape(&[output_channels, 1])?;
+
+            patches.pad_row_ones()?;
+            flat_kernel.append_to_row(bias)?
+        } else {
+            flat_kernel
+        };
+
+        return Ok((params, patches, [output_channels, vert_slides, horz_slides]));
+    }
+
     let mut padded_image = image.clone();
     padded_image.pad(padding)?;
     padded_image.flatten();
@@ -616,7 +1200,7 @@ pub fn conv<F: FieldExt + TensorType>(
         stride.1,
     )?;
 
-    let mut res = if has_bias {
+    let (params, input) = if has_bias {
         let mut tiled_bias = values[2].clone();
         if (tiled_bias.dims().len() != 1) || (tiled_bias.dims()[0] != kernel.dims()[0]) {
             return Err(Box::new(TensorError::DimMismatch("conv bias".to_string())));
@@ -625,17 +1209,37 @@ pub fn conv<F: FieldExt + TensorType>(
         tiled_bias.flatten();
         tiled_bias.reshape(&[tiled_bias.dims()[0], 1])?;
 
-        affine(
-            config,
-            region,
-            &[padded_image, expanded_kernel, tiled_bias],
-            offset,
-        )?
+        padded_image.pad_row_ones()?;
+        (expanded_kernel.append_to_row(tiled_bias)?, padded_image)
     } else {
-        matmul(config, region, &[expanded_kernel, padded_image], offset)?
+        (expanded_kernel, padded_image)
     };
 
-    res.reshape(&[output_channels, vert_slides, horz_slides])?;
+    Ok((params, input, [output_channels, vert_slides, horz_slides]))
+}
+
+/// Convolution accumulated layout. The conv is lowered to a [matmul] of the operands given by
+/// [conv_operands], or laid out with [winograd_conv] for a Winograd lowering.
+pub fn conv<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    lowering: ConvLowering,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let res = match lowering {
+        ConvLowering::Winograd { tile } => {
+            winograd_conv(config, region, values, padding, stride, tile, offset)?
+        }
+        _ => {
+            let (params, input, dims) = conv_operands(values, padding, stride, lowering)?;
+            let mut res = matmul(config, region, &[params, input], offset)?;
+            res.reshape(&dims)?;
+            res
+        }
+    };
 
     if matches!(&config.check_mode, CheckMode::SAFE) {
         // during key generation this will be 0 so we use this as a flag to check
@@ -666,17 +1270,360 @@ pub fn conv<F: FieldExt + TensorType>(
 
     Ok(res)
 }
-/// Power accumulated layout
-pub fn pow<F: FieldExt + TensorType>(
+
+/// Winograd F(`tile`, 3) convolution layout, for 3x3 kernels with a stride of 1. The padded image
+/// is split into overlapping tiles with [winograd_tiles], which are taken to the Winograd domain
+/// along with the kernel, using the transforms of [winograd_transforms]. There the tiles of every
+/// output channel are the elementwise products of the tiles and kernels of the input channels,
+/// summed over the channels, which are taken back to tiles of outputs by the output transform.
+/// Each transform is laid out as a pair of [einsum]s, and the products as a batched [matmul] over
+/// the elements of the tiles. As the kernel transform is scaled up to integers, the outputs are
+/// those of the conv multiplied by a known factor, which is divided back out with
+/// [exact_division].
+pub fn winograd_conv<F: FieldExt + TensorType>(
     config: &mut BaseConfig<F>,
     region: &mut Region<F>,
-    values: &[ValTensor<F>; 1],
-    exponent: u32,
+    values: &[ValTensor<F>],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    tile: usize,
     offset: &mut usize,
 ) -> Result<ValTensor<F>, Box<dyn Error>> {
-    let mut t = values[0].clone();
+    let (image, kernel) = (values[0].clone(), values[1].clone());
 
-    for _ in 1..exponent {
+    if (image.dims().len() != 3)
+        || (kernel.dims().len() != 4)
+        || (image.dims()[0] != kernel.dims()[1])
+        || (kernel.dims()[2..] != [3, 3])
+        || (stride != (1, 1))
+    {
+        return Err(Box::new(TensorError::DimMismatch(
+            "winograd conv".to_string(),
+        )));
+    }
+    let output_channels = kernel.dims()[0];
+
+    let mut padded_image = image.clone();
+    padded_image.pad(padding)?;
+    let (out_height, out_width) = (padded_image.dims()[1] - 2, padded_image.dims()[2] - 2);
+    let tiles: ValTensor<F> = winograd_tiles(&padded_image.get_inner_tensor()?, tile, 3)?.into();
+    let horz_tiles = (out_width + tile - 1) / tile;
+
+    let (input_transform, kernel_transform, output_transform, factor) = winograd_transforms(tile)?;
+    let constant = |t: Tensor<i128>| -> ValTensor<F> {
+        t.map(|x| ValType::Constant(i128_to_felt::<F>(x))).into()
+    };
+    let (input_transform, kernel_transform, output_transform) = (
+        constant(input_transform),
+        constant(kernel_transform),
+        constant(output_transform),
+    );
+
+    // B^T d B for every tile d of every channel
+    let transformed_tiles = einsum(
+        config,
+        region,
+        &[input_transform.clone(), tiles],
+        "xi,ctij->ctxj",
+        offset,
+    )?;
+    let transformed_tiles = einsum(
+        config,
+        region,
+        &[transformed_tiles, input_transform],
+        "ctxj,yj->ctxy",
+        offset,
+    )?;
+    // G g G^T for every input and output channel
+    let transformed_kernel = einsum(
+        config,
+        region,
+        &[kernel_transform.clone(), kernel],
+        "xi,kcij->kcxj",
+        offset,
+    )?;
+    let transformed_kernel = einsum(
+        config,
+        region,
+        &[transformed_kernel, kernel_transform],
+        "kcxj,yj->kcxy",
+        offset,
+    )?;
+    // the elementwise products, summed over the input channels
+    let products = einsum(
+        config,
+        region,
+        &[transformed_kernel, transformed_tiles],
+        "kcxy,ctxy->ktxy",
+        offset,
+    )?;
+    // A^T m A for every tile m of every output channel
+    let output_tiles = einsum(
+        config,
+        region,
+        &[output_transform.clone(), products],
+        "px,ktxy->ktpy",
+        offset,
+    )?;
+    let output_tiles = einsum(
+        config,
+        region,
+        &[output_tiles, output_transform],
+        "ktpy,qy->ktpq",
+        offset,
+    )?;
+    let output_tiles = exact_division(config, region, &output_tiles, factor, offset)?;
+
+    // stitch the tiles back together, dropping the outputs of the overhanging tiles
+    let output_tiles = output_tiles.get_inner_tensor()?;
+    let mut output = Tensor::new(None, &[output_channels, out_height, out_width])?;
+    for k in 0..output_channels {
+        for i in 0..out_height {
+            for j in 0..out_width {
+                let (y, x) = (i / tile, j / tile);
+                output.set(
+                    &[k, i, j],
+                    output_tiles.get(&[k, y * horz_tiles + x, i % tile, j % tile]),
+                );
+            }
+        }
+    }
+    let output: ValTensor<F> = output.into();
+
+    match values.get(2) {
+        Some(bias) => {
+            if (bias.dims().len() != 1) || (bias.dims()[0] != output_channels) {
+                return Err(Box::new(TensorError::DimMismatch("conv bias".to_string())));
+            }
+            let mut tiled_bias = bias.clone();
+            tiled_bias.repeat_rows(out_height * out_width)?;
+            tiled_bias.reshape(output.dims())?;
+            pairwise(config, region, &[output, tiled_bias], offset, BaseOp::Add)
+        }
+        None => Ok(output),
+    }
+}
+
+/// Divides `x`, every element of which is a multiple of the integer `denom`, by `denom`. The
+/// quotients are witnessed and laid out in a mult gate with the constant `denom`, whose output is
+/// copied from `x`, such that `quotient * denom = x` is constrained without a lookup.
+fn exact_division<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    x: &ValTensor<F>,
+    denom: i128,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let integer_evals: Tensor<i128> = x
+        .get_int_evals()
+        .map_err(|e| {
+            error!("{}", e);
+            halo2_proofs::plonk::Error::Synthesis
+        })?
+        .into_iter()
+        .into();
+
+    // for key generation integer_evals will be empty and we need to return a set of unassigned values
+    let mut quotient: Tensor<Value<F>> = match integer_evals.len() {
+        0 => Tensor::from((0..x.len()).map(|_| Value::unknown())),
+        _ => integer_evals.map(|x| Value::known(i128_to_felt(x / denom))),
+    };
+    quotient.reshape(x.dims());
+    let mut denom = constants(std::iter::repeat(denom).take(x.len()));
+    denom.reshape(x.dims())?;
+
+    let mut output = config.inputs[0].assign(region, *offset, &quotient.into())?;
+    config.inputs[1].assign(region, *offset, &denom)?;
+    config.output.assign(region, *offset, x)?;
+
+    for i in 0..x.len() {
+        let (x, y) = config.inputs[0].cartesian_coord(*offset + i);
+        config
+            .selectors
+            .get(&(BaseOp::Mult, x))
+            .unwrap()
+            .enable(region, y)?;
+    }
+
+    *offset += x.len();
+
+    output.reshape(x.dims());
+
+    Ok(ValTensor::from(output))
+}
+
+/// Convolution layout with a relu (dividing by `scale`) fused into it. The conv is lowered to the
+/// same matmul as in [conv], laid out with [matmul_lookup] such that the relu of each output is
+/// looked up straight from its accumulated dot product, rather than from a copy of it in the
+/// lookup input column.
+#[allow(clippy::too_many_arguments)]
+pub fn conv_relu<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    scale: usize,
+    lowering: ConvLowering,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (params, input, dims) = conv_operands(values, padding, stride, lowering)?;
+    let nl = LookupOp::ReLU { scale };
+
+    let mut res = matmul_lookup(config, region, &[params, input], &nl, offset)?;
+    res.reshape(&dims)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation the outputs are unknown, such that there are no evals
+        let output_evals = res.get_int_evals()?;
+        if !output_evals.is_empty() {
+            let safe_conv: ValTensor<F> = non_accum_conv(
+                &values
+                    .iter()
+                    .map(|x| x.get_inner().unwrap())
+                    .collect::<Vec<Tensor<_>>>(),
+                padding,
+                stride,
+            )
+            .map_err(|e| {
+                error!("{}", e);
+                halo2_proofs::plonk::Error::Synthesis
+            })?
+            .into();
+            let safe_relu = nl.f(safe_conv.get_int_evals()?.into_iter().into())?;
+
+            assert_eq!(output_evals, safe_relu.to_vec())
+        }
+    }
+
+    Ok(res)
+}
+
+/// Grouped convolution layout. The image channels and kernels are split into `group` groups
+/// which are each laid out as a [conv], such that a depthwise convolution only lays out the
+/// per-channel matmuls rather than a dense kernel.
+#[allow(clippy::too_many_arguments)]
+pub fn grouped_conv<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    group: usize,
+    lowering: ConvLowering,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if group == 1 {
+        return conv(config, region, values, padding, stride, lowering, offset);
+    }
+
+    let (image, kernel) = (values[0].clone(), values[1].clone());
+
+    if (image.dims().len() != 3)
+        || (kernel.dims().len() != 4)
+        || (group == 0)
+        || (image.dims()[0] != kernel.dims()[1] * group)
+        || (kernel.dims()[0] % group != 0)
+    {
+        return Err(Box::new(TensorError::DimMismatch(
+            "grouped conv".to_string(),
+        )));
+    }
+
+    let input_channels_per_group = kernel.dims()[1];
+    let output_channels_per_group = kernel.dims()[0] / group;
+
+    let mut res = vec![];
+    for g in 0..group {
+        let in_range = g * input_channels_per_group..(g + 1) * input_channels_per_group;
+        let out_range = g * output_channels_per_group..(g + 1) * output_channels_per_group;
+        let mut group_values = vec![
+            image.get_slice(&[in_range])?,
+            kernel.get_slice(&[out_range.clone()])?,
+        ];
+        if values.len() == 3 {
+            group_values.push(values[2].get_slice(&[out_range])?);
+        }
+        res.push(conv(
+            config,
+            region,
+            &group_values,
+            padding,
+            stride,
+            lowering,
+            offset,
+        )?);
+    }
+
+    let mut dims = res[0].dims().to_vec();
+    dims[0] *= group;
+    let mut output = res[1..].iter().fold(res[0].clone(), |acc, elem| {
+        acc.concat(elem.clone()).unwrap()
+    });
+    output.reshape(&dims)?;
+
+    Ok(output)
+}
+
+/// Deconvolution (transposed convolution) layout. The image is expanded using
+/// [deconv_image] and laid out as an unpadded, stride 1, [conv] with the flipped kernel.
+pub fn deconv<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    padding: (usize, usize),
+    output_padding: (usize, usize),
+    stride: (usize, usize),
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (image, kernel) = (values[0].clone(), values[1].clone());
+
+    if (image.dims().len() != 3)
+        || (kernel.dims().len() != 4)
+        || (image.dims()[0] != kernel.dims()[0])
+    {
+        return Err(Box::new(TensorError::DimMismatch("deconv".to_string())));
+    }
+
+    let kernel_shape = (kernel.dims()[2], kernel.dims()[3]);
+
+    let expanded_image: ValTensor<F> = deconv_image(
+        &image.get_inner_tensor()?,
+        kernel_shape,
+        padding,
+        output_padding,
+        stride,
+    )?
+    .into();
+    let flipped_kernel: ValTensor<F> = deconv_kernel(&kernel.get_inner_tensor()?)?.into();
+
+    let mut conv_values = vec![expanded_image, flipped_kernel];
+    if values.len() == 3 {
+        conv_values.push(values[2].clone());
+    }
+
+    conv(
+        config,
+        region,
+        &conv_values,
+        (0, 0),
+        (1, 1),
+        ConvLowering::Direct,
+        offset,
+    )
+}
+
+/// Power accumulated layout
+pub fn pow<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    exponent: u32,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut t = values[0].clone();
+
+    for _ in 1..exponent {
         t = pairwise(
             config,
             region,
@@ -821,6 +1768,16 @@ pub fn reshape<F: FieldExt + TensorType>(
     Ok(t)
 }
 
+/// Dummy (no contraints) transpose layout, which permutes the axes of the input.
+pub fn transpose<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    perm: &[usize],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut t = values[0].clone();
+    t.permute(perm)?;
+    Ok(t)
+}
+
 /// Identity constraint. Usually used to constrain an instance column to an advice so the returned cells / values can be operated upon.
 pub fn identity<F: FieldExt + TensorType>(
     config: &mut BaseConfig<F>,
@@ -873,6 +1830,33 @@ pub fn scale_and_shift<F: FieldExt + TensorType>(
     Ok(res)
 }
 
+/// Layout for a matmul followed by the addition of a bias, which is laid out as an affine on the
+/// transposed inputs, such that the bias is accumulated along with the product.
+pub fn biased_matmul<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 3],
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut input, mut kernel, mut bias) =
+        (values[0].clone(), values[1].clone(), values[2].clone());
+    if input.dims().len() != 2 || kernel.dims().len() != 2 {
+        return Err(Box::new(CircuitError::DimMismatch(
+            "biased matmul layout".to_string(),
+        )));
+    }
+    let (rows, cols) = (input.dims()[0], kernel.dims()[1]);
+    // (x w + b)^T = w^T x^T + b
+    input.transpose_2d()?;
+    kernel.transpose_2d()?;
+    bias.flatten();
+
+    let mut res = affine(config, region, &[input, kernel, bias], offset)?;
+    res.reshape(&[cols, rows])?;
+    res.transpose_2d()?;
+    Ok(res)
+}
+
 /// Layout for range check.
 pub fn range_check<F: FieldExt + TensorType>(
     config: &mut BaseConfig<F>,
@@ -957,75 +1941,3282 @@ pub fn nonlinearity<F: FieldExt + TensorType>(
     Ok(ValTensor::from(output))
 }
 
-/// PrElu layout
-pub fn prelu<F: FieldExt + TensorType>(
+/// Limbed lookup layout. The quotient of the input by 2^shift is assigned and looked up against
+/// the table of `inner`, which also bounds it, and the remainder of the input minus the
+/// recomposed quotient is checked to lie in [0, 2^shift).
+pub fn limbed_nonlinearity<F: FieldExt + TensorType>(
     config: &mut BaseConfig<F>,
     region: &mut Region<F>,
-    values: &[ValTensor<F>; 2],
-    scale: usize,
+    values: &[ValTensor<F>; 1],
+    inner: LookupOp,
+    shift: usize,
     offset: &mut usize,
 ) -> Result<ValTensor<F>, Box<dyn Error>> {
-    let mut slopes = values[1].clone();
-    if slopes.len() != 1 && slopes.len() != values[0].dims()[0] {
-        return Err(
-            "slope must be a scalar or a vector of length equal to the number of channels".into(),
-        );
-    }
-
-    let diff = (values[0].len()) / slopes.len();
-    slopes.repeat_rows(diff)?;
-    slopes.reshape(values[0].dims())?;
-
-    // relu(x)
-    let relu = nonlinearity(
+    let (quotient, remainder) = quotient_remainder(config, region, &values[0], 1 << shift, offset)?;
+    let output = nonlinearity(config, region, &[quotient], inner, offset)?;
+    nonlinearity(
         config,
         region,
-        &[values[0].clone()],
-        LookupOp::ReLU { scale },
+        &[remainder],
+        LookupOp::Range { bits: shift },
         offset,
     )?;
-    // -x
-    let neg_x = neg(config, region, &[values[0].clone()], offset)?;
-    // relu(-x)
-    let relu_neg_x = nonlinearity(config, region, &[neg_x], LookupOp::ReLU { scale }, offset)?;
-    // relu(-x) * slope
 
-    let scaled_relu_neg_x = pairwise(config, region, &[relu_neg_x, slopes], offset, BaseOp::Mult)?;
+    Ok(output)
+}
 
-    let prelu = pairwise(
+/// Division (and remainder) layout. The quotient of the input by `denom` is assigned and looked
+/// up against the table of a division by 1, which bounds it, and the remainder of the input minus
+/// the recomposed quotient is checked to lie in [0, denom) by range checking both the remainder
+/// and `denom - 1` minus the remainder. The quotient is then the only one consistent with the
+/// input. `denom`, its half and `denom - 1` are constants, so can't be substituted by the prover.
+pub fn div_rem<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    denom: i128,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let x = &values[0];
+    let bits = LookupOp::remainder_bits(denom);
+    let (quotient, remainder) = quotient_remainder(config, region, x, denom, offset)?;
+    let output = nonlinearity(
         config,
         region,
-        &[relu, scaled_relu_neg_x],
+        &[quotient],
+        LookupOp::Div {
+            denom: eq_float::F32(1.0),
+        },
+        offset,
+    )?;
+    let mut largest = constants(std::iter::repeat(denom - 1).take(x.len()));
+    largest.reshape(x.dims())?;
+    let slack = pairwise(
+        config,
+        region,
+        &[largest, remainder.clone()],
         offset,
         BaseOp::Sub,
     )?;
+    for r in [remainder, slack] {
+        nonlinearity(config, region, &[r], LookupOp::Range { bits }, offset)?;
+    }
 
-    if matches!(&config.check_mode, CheckMode::SAFE) {
-        // during key generation this will be 0 so we use this as a flag to check
-        // TODO: this isn't very safe and would be better to get the phase directly
-        let is_assigned = !Into::<Tensor<i32>>::into(prelu.get_inner()?)
-            .iter()
-            .all(|&x| x == 0);
-        if is_assigned {
-            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
-            int_input.reshape(values[0].dims());
-            let ref_prelu = ref_prelu(
-                &int_input,
-                scale,
-                &values[1]
-                    .get_int_evals()?
-                    .into_iter()
-                    .map(|e| e as f32)
-                    .collect_vec(),
-            )
-            .map(|e| e as i32);
+    Ok(output)
+}
 
-            assert_eq!(
-                Into::<Tensor<i32>>::into(prelu.get_inner()?),
-                Into::<Tensor<i32>>::into(ref_prelu),
+/// Divides `x` by the integer `denom`, with a remainder ([div_rem]) if `remainder` is set or
+/// otherwise by a single division lookup.
+fn divide<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    x: ValTensor<F>,
+    denom: usize,
+    remainder: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    match LookupOp::division(denom, remainder) {
+        LookupOp::DivRem { denom } => div_rem(config, region, &[x], denom, offset),
+        op => nonlinearity(config, region, &[x], op, offset),
+    }
+}
+
+/// Assigns the quotient of `x` by `denom` (rounding half up) and lays out the remainder
+/// `x + denom / 2 - quotient * denom`, which is left to be bounded by the caller.
+fn quotient_remainder<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    x: &ValTensor<F>,
+    denom: i128,
+    offset: &mut usize,
+) -> Result<(ValTensor<F>, ValTensor<F>), Box<dyn Error>> {
+    let half = denom / 2;
+
+    let integer_evals: Tensor<i128> = x
+        .get_int_evals()
+        .map_err(|e| {
+            error!("{}", e);
+            halo2_proofs::plonk::Error::Synthesis
+        })?
+        .into_iter()
+        .into();
+
+    // for key generation integer_evals will be empty and we need to return a set of unassigned values
+    let mut quotient: Tensor<Value<F>> = match integer_evals.len() {
+        0 => Tensor::from((0..x.len()).map(|_| Value::unknown())),
+        _ => integer_evals.map(|x| Value::known(i128_to_felt((x + half).div_euclid(denom)))),
+    };
+    quotient.reshape(x.dims());
+    // the quotient is assigned once and copied into both the lookup and the recomposition
+    let quotient = identity(config, region, &[quotient.into()], offset)?;
+
+    // `denom` and its half are constants, such that the prover can't recompose the input with
+    // another divisor
+    let constant = |c: i128| -> Result<ValTensor<F>, Box<dyn Error>> {
+        let mut c = constants(std::iter::repeat(c).take(x.len()));
+        c.reshape(x.dims())?;
+        Ok(c)
+    };
+    let shifted = pairwise(
+        config,
+        region,
+        &[x.clone(), constant(half)?],
+        offset,
+        BaseOp::Add,
+    )?;
+    let recomposed = pairwise(
+        config,
+        region,
+        &[quotient.clone(), constant(denom)?],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let remainder = pairwise(config, region, &[shifted, recomposed], offset, BaseOp::Sub)?;
+
+    Ok((quotient, remainder))
+}
+
+/// PrElu layout
+pub fn prelu<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut slopes = values[1].clone();
+    if slopes.len() != 1 && slopes.len() != values[0].dims()[0] {
+        return Err(
+            "slope must be a scalar or a vector of length equal to the number of channels".into(),
+        );
+    }
+
+    let diff = (values[0].len()) / slopes.len();
+    slopes.repeat_rows(diff)?;
+    slopes.reshape(values[0].dims())?;
+
+    // relu(x)
+    let relu = nonlinearity(
+        config,
+        region,
+        &[values[0].clone()],
+        LookupOp::ReLU { scale },
+        offset,
+    )?;
+    // -x
+    let neg_x = neg(config, region, &[values[0].clone()], offset)?;
+    // relu(-x)
+    let relu_neg_x = nonlinearity(config, region, &[neg_x], LookupOp::ReLU { scale }, offset)?;
+    // relu(-x) * slope
+
+    let scaled_relu_neg_x = pairwise(config, region, &[relu_neg_x, slopes], offset, BaseOp::Mult)?;
+
+    let prelu = pairwise(
+        config,
+        region,
+        &[relu, scaled_relu_neg_x],
+        offset,
+        BaseOp::Sub,
+    )?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(prelu.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_prelu = ref_prelu(
+                &int_input,
+                scale,
+                &values[1]
+                    .get_int_evals()?
+                    .into_iter()
+                    .map(|e| e as f32)
+                    .collect_vec(),
+            )
+            .map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(prelu.get_inner()?),
+                Into::<Tensor<i32>>::into(ref_prelu),
             )
         }
     };
 
     Ok(prelu)
 }
+
+/// LSTM layout, unrolled over the sequence length. The gate pre-activations are laid out as
+/// matmuls, the gate activations as lookups, and the products of the cell recurrence are divided
+/// back down to the scale of the input with a division lookup.
+pub fn lstm<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 4],
+    hidden_size: usize,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (x, w, r) = (values[0].clone(), values[1].clone(), values[2].clone());
+    if x.dims().len() != 2 || x.dims()[0] == 0 {
+        return Err(Box::new(TensorError::DimMismatch("lstm".to_string())));
+    }
+    let h = hidden_size;
+    let (seq_len, input_size) = (x.dims()[0], x.dims()[1]);
+    let mut b = values[3].clone();
+    b.reshape(&[4 * h, 1])?;
+
+    let div = LookupOp::Div {
+        denom: eq_float::F32(scale as f32),
+    };
+    // gates are ordered as input, output, forget, cell
+    let gate_ops = [
+        LookupOp::Sigmoid {
+            scales: (scale * scale, scale),
+        },
+        LookupOp::Sigmoid {
+            scales: (scale * scale, scale),
+        },
+        LookupOp::Sigmoid {
+            scales: (scale * scale, scale),
+        },
+        LookupOp::Tanh {
+            scales: (scale * scale, scale),
+        },
+    ];
+
+    let mut hidden: Option<ValTensor<F>> = None;
+    let mut cell: Option<ValTensor<F>> = None;
+    let mut outputs = vec![];
+    for t in 0..seq_len {
+        let mut x_t = x.get_slice(&[t..t + 1])?;
+        x_t.reshape(&[input_size, 1])?;
+        let mut gates = matmul(config, region, &[w.clone(), x_t], offset)?;
+        // the initial hidden and cell states are zero, so the first step skips the recurrence
+        if let Some(hidden) = &hidden {
+            let recurrent = matmul(config, region, &[r.clone(), hidden.clone()], offset)?;
+            gates = pairwise(config, region, &[gates, recurrent], offset, BaseOp::Add)?;
+        }
+        gates = pairwise(config, region, &[gates, b.clone()], offset, BaseOp::Add)?;
+
+        let mut activated = vec![];
+        for (i, op) in gate_ops.iter().enumerate() {
+            let gate = gates.get_slice(&[i * h..(i + 1) * h])?;
+            activated.push(nonlinearity(config, region, &[gate], op.clone(), offset)?);
+        }
+        let (input_gate, output_gate, forget_gate, cell_gate) = (
+            activated[0].clone(),
+            activated[1].clone(),
+            activated[2].clone(),
+            activated[3].clone(),
+        );
+
+        let mut c = pairwise(
+            config,
+            region,
+            &[input_gate, cell_gate],
+            offset,
+            BaseOp::Mult,
+        )?;
+        if let Some(cell) = &cell {
+            let forgotten = pairwise(
+                config,
+                region,
+                &[forget_gate, cell.clone()],
+                offset,
+                BaseOp::Mult,
+            )?;
+            c = pairwise(config, region, &[forgotten, c], offset, BaseOp::Add)?;
+        }
+        let c = nonlinearity(config, region, &[c], div.clone(), offset)?;
+
+        let tanh_c = nonlinearity(
+            config,
+            region,
+            &[c.clone()],
+            LookupOp::Tanh {
+                scales: (scale, scale),
+            },
+            offset,
+        )?;
+        let h_t = pairwise(config, region, &[output_gate, tanh_c], offset, BaseOp::Mult)?;
+        let h_t = nonlinearity(config, region, &[h_t], div.clone(), offset)?;
+
+        outputs.push(h_t.clone());
+        hidden = Some(h_t);
+        cell = Some(c);
+    }
+
+    let mut output = outputs[0].clone();
+    for h_t in outputs[1..].iter() {
+        output = output.concat(h_t.clone())?;
+    }
+    output.reshape(&[seq_len, h])?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_lstm = ref_lstm(&int_inputs, hidden_size, scale)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_lstm)
+        }
+    };
+
+    Ok(output)
+}
+
+/// GRU layout, unrolled over the sequence length. Mirrors [lstm], with the products of the
+/// update and reset gates divided back down to the scale of the input with a division lookup.
+pub fn gru<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    hidden_size: usize,
+    scale: usize,
+    linear_before_reset: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if values.len() != 4 && values.len() != 5 {
+        return Err(Box::new(TensorError::DimMismatch("gru inputs".to_string())));
+    }
+    let (x, w, r) = (values[0].clone(), values[1].clone(), values[2].clone());
+    if x.dims().len() != 2 || x.dims()[0] == 0 {
+        return Err(Box::new(TensorError::DimMismatch("gru".to_string())));
+    }
+    let h = hidden_size;
+    let (seq_len, input_size) = (x.dims()[0], x.dims()[1]);
+    let mut b = values[3].clone();
+    b.reshape(&[6 * h, 1])?;
+    let (w_b, r_b) = (b.get_slice(&[0..3 * h])?, b.get_slice(&[3 * h..6 * h])?);
+    let recurrent_rows = if linear_before_reset { 3 * h } else { 2 * h };
+    let (r_rows, r_b_rows) = (
+        r.get_slice(&[0..recurrent_rows])?,
+        r_b.get_slice(&[0..recurrent_rows])?,
+    );
+
+    let div = LookupOp::Div {
+        denom: eq_float::F32(scale as f32),
+    };
+    let sigmoid = LookupOp::Sigmoid {
+        scales: (scale * scale, scale),
+    };
+
+    let mut hidden = match values.get(4) {
+        Some(h_0) => {
+            let mut h_0 = h_0.clone();
+            h_0.reshape(&[h, 1])?;
+            Some(h_0)
+        }
+        None => None,
+    };
+    let mut outputs = vec![];
+    for t in 0..seq_len {
+        let mut x_t = x.get_slice(&[t..t + 1])?;
+        x_t.reshape(&[input_size, 1])?;
+        let gates_x = matmul(config, region, &[w.clone(), x_t], offset)?;
+        let gates_x = pairwise(config, region, &[gates_x, w_b.clone()], offset, BaseOp::Add)?;
+        // without an initial hidden state the first step skips the recurrence
+        let gates_h = match &hidden {
+            Some(hidden) => {
+                let gates_h = matmul(config, region, &[r_rows.clone(), hidden.clone()], offset)?;
+                pairwise(
+                    config,
+                    region,
+                    &[gates_h, r_b_rows.clone()],
+                    offset,
+                    BaseOp::Add,
+                )?
+            }
+            None => r_b_rows.clone(),
+        };
+
+        let mut activated = vec![];
+        for i in 0..2 {
+            let gate = pairwise(
+                config,
+                region,
+                &[
+                    gates_x.get_slice(&[i * h..(i + 1) * h])?,
+                    gates_h.get_slice(&[i * h..(i + 1) * h])?,
+                ],
+                offset,
+                BaseOp::Add,
+            )?;
+            activated.push(nonlinearity(
+                config,
+                region,
+                &[gate],
+                sigmoid.clone(),
+                offset,
+            )?);
+        }
+        let (update_gate, reset_gate) = (activated[0].clone(), activated[1].clone());
+
+        let candidate = if linear_before_reset {
+            let reset = pairwise(
+                config,
+                region,
+                &[reset_gate, gates_h.get_slice(&[2 * h..3 * h])?],
+                offset,
+                BaseOp::Mult,
+            )?;
+            let reset = nonlinearity(config, region, &[reset], div.clone(), offset)?;
+            pairwise(
+                config,
+                region,
+                &[gates_x.get_slice(&[2 * h..3 * h])?, reset],
+                offset,
+                BaseOp::Add,
+            )?
+        } else {
+            let mut candidate = pairwise(
+                config,
+                region,
+                &[
+                    gates_x.get_slice(&[2 * h..3 * h])?,
+                    r_b.get_slice(&[2 * h..3 * h])?,
+                ],
+                offset,
+                BaseOp::Add,
+            )?;
+            if let Some(hidden) = &hidden {
+                let reset = pairwise(
+                    config,
+                    region,
+                    &[reset_gate, hidden.clone()],
+                    offset,
+                    BaseOp::Mult,
+                )?;
+                let reset = nonlinearity(config, region, &[reset], div.clone(), offset)?;
+                let recurrent = matmul(
+                    config,
+                    region,
+                    &[r.get_slice(&[2 * h..3 * h])?, reset],
+                    offset,
+                )?;
+                candidate = pairwise(config, region, &[candidate, recurrent], offset, BaseOp::Add)?;
+            }
+            candidate
+        };
+        let candidate = nonlinearity(
+            config,
+            region,
+            &[candidate],
+            LookupOp::Tanh {
+                scales: (scale * scale, scale),
+            },
+            offset,
+        )?;
+
+        // h_t = (1 - z) * n + z * h_{t-1}
+        let h_t = match &hidden {
+            Some(hidden) => {
+                let diff = pairwise(
+                    config,
+                    region,
+                    &[hidden.clone(), candidate.clone()],
+                    offset,
+                    BaseOp::Sub,
+                )?;
+                let update = pairwise(config, region, &[update_gate, diff], offset, BaseOp::Mult)?;
+                let update = nonlinearity(config, region, &[update], div.clone(), offset)?;
+                pairwise(config, region, &[candidate, update], offset, BaseOp::Add)?
+            }
+            None => {
+                let update = pairwise(
+                    config,
+                    region,
+                    &[update_gate, candidate.clone()],
+                    offset,
+                    BaseOp::Mult,
+                )?;
+                let update = nonlinearity(config, region, &[update], div.clone(), offset)?;
+                pairwise(config, region, &[candidate, update], offset, BaseOp::Sub)?
+            }
+        };
+
+        outputs.push(h_t.clone());
+        hidden = Some(h_t);
+    }
+
+    let mut output = outputs[0].clone();
+    for h_t in outputs[1..].iter() {
+        output = output.concat(h_t.clone())?;
+    }
+    output.reshape(&[seq_len, h])?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_gru =
+                ref_gru(&int_inputs, hidden_size, scale, linear_before_reset)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_gru)
+        }
+    };
+
+    Ok(output)
+}
+
+/// RNN layout, unrolled over the sequence length, with the tanh activation laid out as a lookup.
+pub fn rnn<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    hidden_size: usize,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if values.len() != 4 && values.len() != 5 {
+        return Err(Box::new(TensorError::DimMismatch("rnn inputs".to_string())));
+    }
+    let (x, w, r) = (values[0].clone(), values[1].clone(), values[2].clone());
+    if x.dims().len() != 2 || x.dims()[0] == 0 {
+        return Err(Box::new(TensorError::DimMismatch("rnn".to_string())));
+    }
+    let h = hidden_size;
+    let (seq_len, input_size) = (x.dims()[0], x.dims()[1]);
+    let mut b = values[3].clone();
+    b.reshape(&[h, 1])?;
+
+    let mut hidden = match values.get(4) {
+        Some(h_0) => {
+            let mut h_0 = h_0.clone();
+            h_0.reshape(&[h, 1])?;
+            Some(h_0)
+        }
+        None => None,
+    };
+    let mut outputs = vec![];
+    for t in 0..seq_len {
+        let mut x_t = x.get_slice(&[t..t + 1])?;
+        x_t.reshape(&[input_size, 1])?;
+        let mut pre = matmul(config, region, &[w.clone(), x_t], offset)?;
+        if let Some(hidden) = &hidden {
+            let recurrent = matmul(config, region, &[r.clone(), hidden.clone()], offset)?;
+            pre = pairwise(config, region, &[pre, recurrent], offset, BaseOp::Add)?;
+        }
+        pre = pairwise(config, region, &[pre, b.clone()], offset, BaseOp::Add)?;
+        let h_t = nonlinearity(
+            config,
+            region,
+            &[pre],
+            LookupOp::Tanh {
+                scales: (scale * scale, scale),
+            },
+            offset,
+        )?;
+
+        outputs.push(h_t.clone());
+        hidden = Some(h_t);
+    }
+
+    let mut output = outputs[0].clone();
+    for h_t in outputs[1..].iter() {
+        output = output.concat(h_t.clone())?;
+    }
+    output.reshape(&[seq_len, h])?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_rnn = ref_rnn(&int_inputs, hidden_size, scale)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_rnn)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Softmax layout over the last axis. The exponentials are laid out as a lookup, and each row is
+/// normalized by the reciprocal of its sum, computed at the square of the output scale by a
+/// second lookup and divided back down with a division lookup.
+pub fn softmax<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    scales: (usize, usize),
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].clone();
+    let n = match input.dims().last() {
+        Some(n) if *n > 0 => *n,
+        _ => return Err(Box::new(TensorError::DimMismatch("softmax".to_string()))),
+    };
+    let (scale_input, scale_output) = scales;
+
+    let mut exps = nonlinearity(
+        config,
+        region,
+        &[input.clone()],
+        LookupOp::Exp {
+            scales: (scale_input, scale_output),
+        },
+        offset,
+    )?;
+    let num_rows = exps.len() / n;
+    exps.reshape(&[num_rows, n])?;
+
+    let mut rows = vec![];
+    for r in 0..num_rows {
+        let mut row = exps.get_slice(&[r..r + 1])?;
+        row.reshape(&[n])?;
+        let denom = sum(config, region, &[row.clone()], offset)?;
+        let denom = nonlinearity(
+            config,
+            region,
+            &[denom],
+            LookupOp::Recip {
+                scales: (scale_output, scale_output * scale_output),
+            },
+            offset,
+        )?;
+        let row = pairwise(config, region, &[row, denom], offset, BaseOp::Mult)?;
+        rows.push(nonlinearity(
+            config,
+            region,
+            &[row],
+            LookupOp::Div {
+                denom: eq_float::F32((scale_output * scale_output) as f32),
+            },
+            offset,
+        )?);
+    }
+
+    let mut output = rows[0].clone();
+    for row in rows[1..].iter() {
+        output = output.concat(row.clone())?;
+    }
+    output.reshape(input.dims())?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = input.get_int_evals()?.into_iter().into();
+            int_input.reshape(input.dims());
+            let ref_softmax = ref_softmax(&int_input, scales)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_softmax)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Scaled dot-product attention layout. The scores are laid out as an [einsum] of the queries
+/// and keys, so the keys are never transposed, and the `1 / sqrt(d)` factor is folded into the
+/// input scale of the [softmax].
+pub fn attention<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 3],
+    scales: (usize, usize),
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let scores = einsum(
+        config,
+        region,
+        &[values[0].clone(), values[1].clone()],
+        "ik,jk->ij",
+        offset,
+    )?;
+    let probs = softmax(config, region, &[scores], scales, offset)?;
+    matmul(config, region, &[probs, values[2].clone()], offset)
+}
+
+/// Gather layout for constant indices, which only rearranges the input's cells.
+pub fn gather<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    axis: usize,
+    indices: &[usize],
+    index_dims: &[usize],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    let indices = Tensor::new(Some(indices), index_dims)?;
+    Ok(ref_gather(&input, axis, &indices)?.into())
+}
+
+/// One-hot encodes a single fixed point index over `len` positions, by comparing it to every
+/// position `j * scale`, and to its negative counterpart `(j - len) * scale` such that negative
+/// indices wrap as in ONNX, with a zero-check lookup. The positions are constants, and indices
+/// matching no position encode to zeros.
+fn one_hot<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    index: ValTensor<F>,
+    len: usize,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (len_i, scale_i) = (len as i128, scale as i128);
+    let positions = constants((0..len_i).chain(-len_i..0).map(|j| j * scale_i));
+    let diff = pairwise(config, region, &[positions, index], offset, BaseOp::Sub)?;
+    let is_zero = nonlinearity(config, region, &[diff], LookupOp::IsZero, offset)?;
+    let (positive, negative) = (
+        is_zero.get_slice(&[0..len])?,
+        is_zero.get_slice(&[len..2 * len])?,
+    );
+    pairwise(config, region, &[positive, negative], offset, BaseOp::Add)
+}
+
+/// One-hot encodes the slice of the leading axes `dims` indexed by a fixed point `index` (one
+/// element per axis), as the outer product of the [one_hot] encodings of its elements. Indices
+/// with an element out of range encode to zeros.
+fn slice_one_hot<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    index: &ValTensor<F>,
+    dims: &[usize],
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut encoded = one_hot(
+        config,
+        region,
+        index.get_slice(&[0..1])?,
+        dims[0],
+        scale,
+        offset,
+    )?;
+    for (i, d) in dims.iter().enumerate().skip(1) {
+        let mut axis = one_hot(
+            config,
+            region,
+            index.get_slice(&[i..i + 1])?,
+            *d,
+            scale,
+            offset,
+        )?;
+        let n = encoded.len();
+        encoded.reshape(&[n, 1])?;
+        encoded.repeat_rows(*d)?;
+        encoded.flatten();
+        axis.tile(n)?;
+        axis.flatten();
+        encoded = pairwise(config, region, &[encoded, axis], offset, BaseOp::Mult)?;
+    }
+    Ok(encoded)
+}
+
+/// Padding layout for any per-axis padding amounts and [PadFill], which only rearranges the
+/// input's cells.
+pub fn pad_with_fill<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    pads: &[(usize, usize)],
+    fill: &PadFill,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    let constant = match fill {
+        PadFill::Constant(c) => i128_to_felt::<F>(*c),
+        _ => F::zero(),
+    };
+    // the fill is a constant, which the prover can't substitute
+    Ok(ref_pad_with_fill(&input, pads, fill, ValType::Constant(constant))?.into())
+}
+
+/// Tile layout, which only rearranges the input's cells.
+pub fn tile<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    repeats: &[usize],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    Ok(ref_tile(&input, repeats)?.into())
+}
+
+/// Expand layout, which only rearranges the input's cells.
+pub fn expand<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    shape: &[usize],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    Ok(ref_expand(&input, shape)?.into())
+}
+
+/// Slice layout, which only rearranges the input's cells.
+pub fn slice<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    starts: &[i64],
+    ends: &[i64],
+    steps: &[i64],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    Ok(ref_slice(&input, starts, ends, steps)?.into())
+}
+
+/// DepthToSpace layout, which only rearranges the input's cells.
+pub fn depth_to_space<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    blocksize: usize,
+    mode: &DepthToSpaceMode,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    Ok(ref_depth_to_space(&input, blocksize, mode)?.into())
+}
+
+/// SpaceToDepth layout, which only rearranges the input's cells.
+pub fn space_to_depth<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    blocksize: usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    Ok(ref_space_to_depth(&input, blocksize)?.into())
+}
+
+/// Lays out the constants `values` as a flat tensor, bound to fixed cells when assigned.
+fn constants<F: FieldExt + TensorType>(values: impl Iterator<Item = i128>) -> ValTensor<F> {
+    Tensor::from(values.map(|v| ValType::Constant(i128_to_felt::<F>(v)))).into()
+}
+
+/// Dynamic lookup layout. Lays out the (key, value) pairs of `table` in the table columns, below
+/// the tables laid out before it, then constrains each (key, value) pair of `lookups` to be one of
+/// the pairs of the tables, returning the looked up values. The keys of a table are constants,
+/// assigned to fixed cells, and should be offset by a tag unique to it (eg. the offset it is laid
+/// out at), past which the keys looked up in it are constrained to fall short of 2^64, such that
+/// pairs can't be looked up in the tables of other ops.
+fn dynamic_lookup<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    lookups: &[ValTensor<F>; 2],
+    table: &[ValTensor<F>; 2],
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let dynamic = config
+        .dynamic_lookup
+        .clone()
+        .ok_or(CircuitError::LookupInstantiation)?;
+    let table_row = dynamic.table_rows.get();
+    if table_row + table[0].len() > dynamic.table_keys.col_size() {
+        return Err(Box::new(CircuitError::DynamicTableTooLong(
+            table_row + table[0].len(),
+            dynamic.table_keys.col_size(),
+        )));
+    }
+    dynamic.table_keys.assign(region, table_row, &table[0])?;
+    dynamic.table_values.assign(region, table_row, &table[1])?;
+    for y in table_row..table_row + table[0].len() {
+        dynamic.table_selector.enable(region, y)?;
+    }
+    dynamic.table_rows.set(table_row + table[0].len());
+
+    dynamic.keys.assign(region, *offset, &lookups[0])?;
+    let looked_up = dynamic.values.assign(region, *offset, &lookups[1])?;
+    for i in 0..lookups[0].len() {
+        let (x, y) = dynamic.keys.cartesian_coord(*offset + i);
+        dynamic.input_selectors[x].enable(region, y)?;
+    }
+    *offset += lookups[0].len();
+
+    Ok(looked_up.into())
+}
+
+/// Constrains every element of `values` to be nonnegative (and within the table), by looking each
+/// element up as both the input and the output of a relu.
+fn nonnegative<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    offset: &mut usize,
+) -> Result<(), Box<dyn Error>> {
+    let x = &values[0];
+    config.lookup_input.assign(region, *offset, x)?;
+    config.lookup_output.assign(region, *offset, x)?;
+    for i in 0..x.len() {
+        let (x, y) = config.lookup_input.cartesian_coord(*offset + i);
+        config
+            .lookup_selectors
+            .get(&(LookupOp::ReLU { scale: 1 }, x))
+            .ok_or(CircuitError::LookupInstantiation)?
+            .enable(region, y)?;
+    }
+    *offset += x.len();
+    Ok(())
+}
+
+/// Embedding layout. The entries of the table are keyed by their (fixed point) position, the row
+/// of every token id is witnessed and bounded by the vocabulary, the key of every output element
+/// is computed from its row, and the output elements are witnessed and looked up with their keys
+/// in the keyed table. Ids matching no row can't be proven.
+pub fn embedding<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (table, ids) = (values[0].clone(), values[1].clone());
+    if table.dims().len() != 2 {
+        return Err(Box::new(TensorError::DimMismatch("embedding".to_string())));
+    }
+    let (vocab_size, embedding_dim) = (table.dims()[0], table.dims()[1]);
+
+    let mut flat_ids = ids.clone();
+    flat_ids.flatten();
+    let num_ids = flat_ids.len();
+    let tag = (*offset as i128) << 64;
+
+    // for key generation the evaluations will be empty and the rows are left unknown
+    let (id_evals, table_evals) = (flat_ids.get_int_evals()?, table.get_int_evals()?);
+    let rows: Tensor<Value<F>> = match id_evals.len() == num_ids {
+        true => id_evals
+            .iter()
+            .map(|id| Value::known(i128_to_felt(id.div_euclid(scale as i128))))
+            .into(),
+        false => Tensor::from((0..num_ids).map(|_| Value::unknown())),
+    };
+    let rows = identity(config, region, &[rows.into()], offset)?;
+
+    // the row j of each id j * scale lies within the table, such that the keys looked up fall
+    // short of 2^64 past the tag
+    nonnegative(config, region, &[rows.clone()], offset)?;
+    let last = constants(std::iter::repeat(vocab_size as i128 - 1).take(num_ids));
+    let remaining = pairwise(config, region, &[last, rows.clone()], offset, BaseOp::Sub)?;
+    nonnegative(config, region, &[remaining], offset)?;
+    let scaled_rows = pairwise(
+        config,
+        region,
+        &[
+            rows.clone(),
+            constants(std::iter::repeat(scale as i128).take(num_ids)),
+        ],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let assigned_ids = identity(config, region, &[flat_ids.clone()], offset)?;
+    for (id, scaled_row) in assigned_ids
+        .get_inner_tensor()?
+        .iter()
+        .zip(scaled_rows.get_inner_tensor()?.iter())
+    {
+        match (id, scaled_row) {
+            (ValType::PrevAssigned(id), ValType::PrevAssigned(scaled_row)) => {
+                region.constrain_equal(id.cell(), scaled_row.cell())?
+            }
+            _ => return Err(Box::new(TensorError::WrongMethod)),
+        }
+    }
+
+    // the element k of row j is keyed (j * embedding_dim + k) * scale
+    let repeated_rows: Tensor<ValType<F>> = rows
+        .get_inner_tensor()?
+        .iter()
+        .flat_map(|row| vec![row.clone(); embedding_dim])
+        .into();
+    let row_keys = pairwise(
+        config,
+        region,
+        &[
+            repeated_rows.into(),
+            constants(
+                std::iter::repeat((embedding_dim * scale) as i128).take(num_ids * embedding_dim),
+            ),
+        ],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let element_keys = constants(
+        (0..num_ids).flat_map(|_| (0..embedding_dim).map(move |k| tag + (k * scale) as i128)),
+    );
+    let keys = pairwise(
+        config,
+        region,
+        &[row_keys, element_keys],
+        offset,
+        BaseOp::Add,
+    )?;
+    let table_keys = constants((0..vocab_size * embedding_dim).map(|e| tag + (e * scale) as i128));
+    let mut table_values = table.clone();
+    table_values.flatten();
+
+    // for key generation the evaluations will be empty and the output is left unknown
+    let looked_up: Tensor<Value<F>> =
+        match id_evals.len() == num_ids && table_evals.len() == table.len() {
+            true => ref_embedding(
+                &Tensor::new(Some(&table_evals), table.dims())?,
+                &Tensor::new(Some(&id_evals), &[num_ids])?,
+                scale,
+            )?
+            .iter()
+            .map(|e| Value::known(i128_to_felt(*e)))
+            .into(),
+            false => Tensor::from((0..num_ids * embedding_dim).map(|_| Value::unknown())),
+        };
+
+    let mut output = dynamic_lookup(
+        config,
+        region,
+        &[keys, looked_up.into()],
+        &[table_keys, table_values],
+        offset,
+    )?;
+    let mut output_dims = ids.dims().to_vec();
+    output_dims.push(embedding_dim);
+    output.reshape(&output_dims)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_embedding =
+                ref_embedding(&int_inputs[0], &int_inputs[1], scale)?.map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_embedding
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// GatherElements layout. The gathered axis is moved last, and every index is one-hot encoded
+/// over the axis (see [one_hot]) and [dot]-ed with the row of the input it indexes into. This
+/// constrains every output element to be the input element at its index, and indices out of
+/// range gather zeros.
+pub fn gather_elements<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    axis: usize,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut input, mut indices) = (values[0].clone(), values[1].clone());
+    let rank = input.dims().len();
+    if axis >= rank || indices.dims().len() != rank {
+        return Err(Box::new(TensorError::DimMismatch(
+            "gather elements".to_string(),
+        )));
+    }
+
+    let order: Vec<usize> = (0..rank).filter(|d| *d != axis).chain([axis]).collect();
+    input.permute(&order)?;
+    indices.permute(&order)?;
+    let (len, num_indices) = (input.dims()[rank - 1], indices.dims()[rank - 1]);
+    let num_rows = input.len() / len;
+    if input.dims()[..rank - 1] != indices.dims()[..rank - 1] {
+        return Err(Box::new(TensorError::DimMismatch(
+            "gather elements".to_string(),
+        )));
+    }
+    let permuted_dims = indices.dims().to_vec();
+    indices.flatten();
+
+    let mut gathered = vec![];
+    for r in 0..num_rows {
+        let mut row = input.get_slice(&[r..r + 1])?;
+        row.reshape(&[len])?;
+        for i in 0..num_indices {
+            let index = indices.get_slice(&[r * num_indices + i..r * num_indices + i + 1])?;
+            let one_hot = one_hot(config, region, index, len, scale, offset)?;
+            gathered.push(dot(config, region, &[one_hot, row.clone()], offset)?);
+        }
+    }
+    let mut output = gathered[0].clone();
+    for g in gathered[1..].iter() {
+        output = output.concat(g.clone())?;
+    }
+    output.reshape(&permuted_dims)?;
+    let inverse: Vec<usize> = (0..rank)
+        .map(|d| order.iter().position(|o| *o == d).unwrap())
+        .collect();
+    output.permute(&inverse)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_gather_elements =
+                ref_gather_elements(&int_inputs[0], &int_inputs[1], axis, scale)?.map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_gather_elements
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// GatherND layout (with no batch dimensions). Each index (the last axis of the indices) is
+/// one-hot encoded over the slices of the input's leading axes (see [slice_one_hot]) and
+/// [matmul]-ed with the input's slices. Indices out of range gather slices of zeros.
+pub fn gather_nd<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut input, mut indices) = (values[0].clone(), values[1].clone());
+    let (dims, index_dims) = (input.dims().to_vec(), indices.dims().to_vec());
+    let k = match index_dims.last() {
+        Some(k) if *k > 0 && *k <= dims.len() => *k,
+        _ => return Err(Box::new(TensorError::DimMismatch("gather nd".to_string()))),
+    };
+    let num_slices: usize = dims[..k].iter().product();
+    let slice_len: usize = dims[k..].iter().product();
+    input.reshape(&[num_slices, slice_len])?;
+    indices.flatten();
+
+    let mut gathered = vec![];
+    for m in 0..indices.len() / k {
+        let index = indices.get_slice(&[m * k..(m + 1) * k])?;
+        let mut one_hot = slice_one_hot(config, region, &index, &dims[..k], scale, offset)?;
+        one_hot.reshape(&[1, num_slices])?;
+        gathered.push(matmul(config, region, &[one_hot, input.clone()], offset)?);
+    }
+    let mut output = gathered[0].clone();
+    for g in gathered[1..].iter() {
+        output = output.concat(g.clone())?;
+    }
+    let mut output_dims = [&index_dims[..index_dims.len() - 1], &dims[k..]].concat();
+    if output_dims.is_empty() {
+        output_dims.push(1);
+    }
+    output.reshape(&output_dims)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_gather_nd =
+                ref_gather_nd(&int_inputs[0], &int_inputs[1], scale)?.map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_gather_nd
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// ScatterElements layout. The scattered axis is moved last, and every index is one-hot encoded
+/// over the axis (see [one_hot]). Each update is then written into the row it indexes into as
+/// `row + one_hot * (update - row)`, in order, so that when indices collide the last update
+/// wins. Updates with indices out of range are dropped.
+pub fn scatter_elements<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 3],
+    axis: usize,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut input, mut indices, mut updates) =
+        (values[0].clone(), values[1].clone(), values[2].clone());
+    let rank = input.dims().len();
+    if axis >= rank || indices.dims().len() != rank || updates.dims() != indices.dims() {
+        return Err(Box::new(TensorError::DimMismatch(
+            "scatter elements".to_string(),
+        )));
+    }
+
+    let order: Vec<usize> = (0..rank).filter(|d| *d != axis).chain([axis]).collect();
+    input.permute(&order)?;
+    indices.permute(&order)?;
+    updates.permute(&order)?;
+    if input.dims()[..rank - 1] != indices.dims()[..rank - 1] {
+        return Err(Box::new(TensorError::DimMismatch(
+            "scatter elements".to_string(),
+        )));
+    }
+    let permuted_dims = input.dims().to_vec();
+    let (len, num_indices) = (input.dims()[rank - 1], indices.dims()[rank - 1]);
+    let num_rows = input.len() / len;
+    indices.flatten();
+    updates.flatten();
+
+    let mut rows = vec![];
+    for r in 0..num_rows {
+        let mut row = input.get_slice(&[r..r + 1])?;
+        row.reshape(&[len])?;
+        for i in 0..num_indices {
+            let t = r * num_indices + i;
+            let index = indices.get_slice(&[t..t + 1])?;
+            let update = updates.get_slice(&[t..t + 1])?;
+            let one_hot = one_hot(config, region, index, len, scale, offset)?;
+            let diff = pairwise(config, region, &[update, row.clone()], offset, BaseOp::Sub)?;
+            let masked = pairwise(config, region, &[one_hot, diff], offset, BaseOp::Mult)?;
+            row = pairwise(config, region, &[row, masked], offset, BaseOp::Add)?;
+        }
+        rows.push(row);
+    }
+    let mut output = rows[0].clone();
+    for r in rows[1..].iter() {
+        output = output.concat(r.clone())?;
+    }
+    output.reshape(&permuted_dims)?;
+    let inverse: Vec<usize> = (0..rank)
+        .map(|d| order.iter().position(|o| *o == d).unwrap())
+        .collect();
+    output.permute(&inverse)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_scatter_elements =
+                ref_scatter_elements(&int_inputs[0], &int_inputs[1], &int_inputs[2], axis, scale)?
+                    .map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_scatter_elements
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// ScatterND layout. Each index (the last axis of the indices) is one-hot encoded over the
+/// slices of the input's leading axes (see [slice_one_hot]) and repeated over the slice. Each
+/// update slice is then written into the input as `input + one_hot * (update - input)`, in
+/// order, so that when indices collide the last update wins. Updates with indices out of range
+/// are dropped.
+pub fn scatter_nd<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 3],
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut output, mut indices, mut updates) =
+        (values[0].clone(), values[1].clone(), values[2].clone());
+    let (dims, index_dims) = (output.dims().to_vec(), indices.dims().to_vec());
+    let k = match index_dims.last() {
+        Some(k) if *k > 0 && *k <= dims.len() => *k,
+        _ => return Err(Box::new(TensorError::DimMismatch("scatter nd".to_string()))),
+    };
+    let num_slices: usize = dims[..k].iter().product();
+    let slice_len: usize = dims[k..].iter().product();
+    if updates.len() != (indices.len() / k) * slice_len {
+        return Err(Box::new(TensorError::DimMismatch("scatter nd".to_string())));
+    }
+    output.flatten();
+    indices.flatten();
+    updates.flatten();
+
+    for m in 0..indices.len() / k {
+        let index = indices.get_slice(&[m * k..(m + 1) * k])?;
+        let mut one_hot = slice_one_hot(config, region, &index, &dims[..k], scale, offset)?;
+        one_hot.reshape(&[num_slices, 1])?;
+        one_hot.repeat_rows(slice_len)?;
+        one_hot.flatten();
+        let mut update = updates.get_slice(&[m * slice_len..(m + 1) * slice_len])?;
+        update.tile(num_slices)?;
+        update.flatten();
+
+        let diff = pairwise(
+            config,
+            region,
+            &[update, output.clone()],
+            offset,
+            BaseOp::Sub,
+        )?;
+        let masked = pairwise(config, region, &[one_hot, diff], offset, BaseOp::Mult)?;
+        output = pairwise(config, region, &[output, masked], offset, BaseOp::Add)?;
+    }
+    output.reshape(&dims)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_scatter_nd =
+                ref_scatter_nd(&int_inputs[0], &int_inputs[1], &int_inputs[2], scale)?
+                    .map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_scatter_nd
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// TopK layout. The selected axis is moved last, and `k` bubble passes of compare-exchanges are
+/// run over it for all rows at once, where each compare-exchange is `(a + relu(b - a), b -
+/// relu(b - a))`. The i-th pass bubbles the i-th largest (or smallest) element into position
+/// `i`, so the output is constrained to be sorted and to hold the top `k` elements.
+pub fn topk<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    k: usize,
+    axis: usize,
+    largest: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut input = values[0].clone();
+    let rank = input.dims().len();
+    if axis >= rank || k == 0 || k > input.dims()[axis] {
+        return Err(Box::new(TensorError::DimMismatch("topk".to_string())));
+    }
+
+    let order: Vec<usize> = (0..rank).filter(|d| *d != axis).chain([axis]).collect();
+    input.permute(&order)?;
+    let mut output_dims = input.dims().to_vec();
+    let len = output_dims[rank - 1];
+    let num_rows = input.len() / len;
+    output_dims[rank - 1] = k;
+
+    // one tensor per position along the axis, holding that position of every row
+    input.reshape(&[num_rows, len])?;
+    input.permute(&[1, 0])?;
+    let mut columns = vec![];
+    for j in 0..len {
+        let mut column = input.get_slice(&[j..j + 1])?;
+        column.reshape(&[num_rows])?;
+        columns.push(column);
+    }
+
+    for i in 0..k {
+        for j in (i..len - 1).rev() {
+            let (a, b) = (columns[j].clone(), columns[j + 1].clone());
+            // relu(b - a)
+            let diff = pairwise(config, region, &[b.clone(), a.clone()], offset, BaseOp::Sub)?;
+            let relu = nonlinearity(config, region, &[diff], LookupOp::ReLU { scale: 1 }, offset)?;
+            // max(a, b) and min(a, b)
+            let max = pairwise(config, region, &[a, relu.clone()], offset, BaseOp::Add)?;
+            let min = pairwise(config, region, &[b, relu], offset, BaseOp::Sub)?;
+            if largest {
+                (columns[j], columns[j + 1]) = (max, min);
+            } else {
+                (columns[j], columns[j + 1]) = (min, max);
+            }
+        }
+    }
+
+    let mut output = columns[0].clone();
+    for c in columns[1..k].iter() {
+        output = output.concat(c.clone())?;
+    }
+    output.reshape(&[k, num_rows])?;
+    output.permute(&[1, 0])?;
+    output.reshape(&output_dims)?;
+    let inverse: Vec<usize> = (0..rank)
+        .map(|d| order.iter().position(|o| *o == d).unwrap())
+        .collect();
+    output.permute(&inverse)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_topk = ref_topk(&int_input, k, axis, largest)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_topk)
+        }
+    };
+
+    Ok(output)
+}
+
+/// ArgMax (or ArgMin) layout. The reduced axis is moved last, and the index `i` and maximum `m` of
+/// every row are witnessed. `m` is looked up at `i` in the row, keyed by position, and checked to
+/// be at least every entry of the row, and strictly greater than the entries before `i`, such
+/// that `i` is the first position of the maximum. `i` is checked to lie within the row. The
+/// bound of the row, the positions and the keys are all constants, so the prover can't move
+/// `i` by substituting them.
+pub fn argmax<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    axis: usize,
+    keep_dims: bool,
+    largest: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut input = values[0].clone();
+    let rank = input.dims().len();
+    if axis >= rank {
+        return Err(Box::new(TensorError::DimMismatch("argmax".to_string())));
+    }
+
+    let order: Vec<usize> = (0..rank).filter(|d| *d != axis).chain([axis]).collect();
+    input.permute(&order)?;
+    let mut output_dims = input.dims().to_vec();
+    let len = output_dims[rank - 1];
+    let num_rows = input.len() / len;
+    output_dims[rank - 1] = 1;
+
+    // one tensor per position along the axis, holding that position of every row
+    input.reshape(&[num_rows, len])?;
+    let mut rows = input.clone();
+    rows.flatten();
+    input.permute(&[1, 0])?;
+    let mut columns = vec![];
+    for j in 0..len {
+        let mut column = input.get_slice(&[j..j + 1])?;
+        column.reshape(&[num_rows])?;
+        columns.push(column);
+    }
+
+    // for key generation the evaluations will be empty and the index is left unknown
+    let evals = rows.get_int_evals()?;
+    let (index, extremum): (Tensor<Value<F>>, Tensor<Value<F>>) = match evals.len() == rows.len() {
+        true => {
+            let mut first = vec![];
+            for row in evals.chunks(len) {
+                let mut best = 0;
+                for (j, x) in row.iter().enumerate() {
+                    if (largest && *x > row[best]) || (!largest && *x < row[best]) {
+                        best = j;
+                    }
+                }
+                first.push((best, row[best]));
+            }
+            (
+                first
+                    .iter()
+                    .map(|(j, _)| Value::known(i128_to_felt(*j as i128)))
+                    .into(),
+                first
+                    .iter()
+                    .map(|(_, x)| Value::known(i128_to_felt(*x)))
+                    .into(),
+            )
+        }
+        false => (
+            Tensor::from((0..num_rows).map(|_| Value::unknown())),
+            Tensor::from((0..num_rows).map(|_| Value::unknown())),
+        ),
+    };
+    let index = identity(config, region, &[index.into()], offset)?;
+    let extremum = identity(config, region, &[extremum.into()], offset)?;
+
+    // the index lies within the row
+    nonnegative(config, region, &[index.clone()], offset)?;
+    let last = constants(std::iter::repeat(len as i128 - 1).take(num_rows));
+    let remaining = pairwise(config, region, &[last, index.clone()], offset, BaseOp::Sub)?;
+    nonnegative(config, region, &[remaining], offset)?;
+
+    // the extremum is the entry at the index of its row
+    let tag = (*offset as i128) << 64;
+    let row_keys = constants((0..num_rows).map(|r| tag + (r * len) as i128));
+    let keys = pairwise(
+        config,
+        region,
+        &[index.clone(), row_keys],
+        offset,
+        BaseOp::Add,
+    )?;
+    let table_keys = constants((0..num_rows * len).map(|e| tag + e as i128));
+    dynamic_lookup(
+        config,
+        region,
+        &[keys, extremum.clone()],
+        &[table_keys, rows],
+        offset,
+    )?;
+
+    // the extremum bounds every entry, strictly so the entries before the index
+    for (j, column) in columns.iter().enumerate() {
+        let diff = match largest {
+            true => [extremum.clone(), column.clone()],
+            false => [column.clone(), extremum.clone()],
+        };
+        let diff = pairwise(config, region, &diff, offset, BaseOp::Sub)?;
+        let position = constants(std::iter::repeat(j as i128).take(num_rows));
+        let after = pairwise(
+            config,
+            region,
+            &[index.clone(), position],
+            offset,
+            BaseOp::Sub,
+        )?;
+        let is_before = nonlinearity(
+            config,
+            region,
+            &[after],
+            LookupOp::Compare {
+                comparison: Comparison::Greater,
+            },
+            offset,
+        )?;
+        let slack = pairwise(config, region, &[diff, is_before], offset, BaseOp::Sub)?;
+        nonnegative(config, region, &[slack], offset)?;
+    }
+
+    let mut output = index;
+    output.reshape(&output_dims)?;
+    let inverse: Vec<usize> = (0..rank)
+        .map(|d| order.iter().position(|o| *o == d).unwrap())
+        .collect();
+    output.permute(&inverse)?;
+    if !keep_dims {
+        let mut reduced_dims = output.dims().to_vec();
+        reduced_dims.remove(axis);
+        if reduced_dims.is_empty() {
+            reduced_dims.push(1);
+        }
+        output.reshape(&reduced_dims)?;
+    }
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_argmax = ref_argmax(&int_input, axis, keep_dims, largest)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_argmax)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Nearest neighbour resize layout, which only rearranges the input's cells.
+pub fn resize_nearest<F: FieldExt + TensorType>(
+    values: &[ValTensor<F>; 1],
+    indices: &[Vec<usize>],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    Ok(ref_resize_nearest(&input, indices)?.into())
+}
+
+/// Linear resize layout. Every output element is a weighted sum of the (at most `2^rank`) input
+/// elements it interpolates between. For every tap, the input elements are gathered into a
+/// tensor of the output's shape, multiplied by the fixed point weights of that tap, and summed.
+pub fn resize_linear<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    taps: &[Vec<(usize, usize, usize)>],
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let input = values[0].get_inner_tensor()?;
+    if taps.len() != input.dims().len() {
+        return Err(Box::new(TensorError::DimMismatch("resize".to_string())));
+    }
+    let output_dims: Vec<usize> = taps.iter().map(|t| t.len()).collect();
+    let output_taps = resize_linear_taps(input.dims(), taps, scale);
+    let num_taps = output_taps.iter().map(|t| t.len()).max().unwrap_or(0);
+
+    let mut output: Option<ValTensor<F>> = None;
+    for t in 0..num_taps {
+        // elements with fewer taps are padded with zero weights
+        let mut gathered: ValTensor<F> = Tensor::from(
+            output_taps
+                .iter()
+                .map(|taps| input[taps.get(t).map(|(i, _)| *i).unwrap_or(0)].clone()),
+        )
+        .into();
+        gathered.reshape(&output_dims)?;
+        let mut weights = constants(
+            output_taps
+                .iter()
+                .map(|taps| taps.get(t).map(|(_, w)| *w).unwrap_or(0)),
+        );
+        weights.reshape(&output_dims)?;
+
+        let weighted = pairwise(config, region, &[weights, gathered], offset, BaseOp::Mult)?;
+        output = Some(match output {
+            Some(output) => pairwise(config, region, &[output, weighted], offset, BaseOp::Add)?,
+            None => weighted,
+        });
+    }
+    let output = match output {
+        Some(output) => output,
+        None => return Err(Box::new(TensorError::DimMismatch("resize".to_string()))),
+    };
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_resize = ref_resize_linear(&int_input, taps, scale)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_resize)
+        }
+    };
+
+    Ok(output)
+}
+
+/// RoiAlign layout. Every region of interest is sampled from its batch with the [resize_linear]
+/// layout, passing the channels through, and the samples of every output bin are then pooled
+/// with the [avg_pool2d] or [max_pool2d] layouts.
+pub fn roi_align<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    rois: &[RoiTaps],
+    max: bool,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let dims = values[0].dims().to_vec();
+    let rank = dims.len();
+    if !(rank == 3 || rank == 4) || rois.is_empty() {
+        return Err(Box::new(TensorError::DimMismatch("roi align".to_string())));
+    }
+    let (c, h, w) = (dims[rank - 3], dims[rank - 2], dims[rank - 1]);
+    let num_batches = values[0].len() / (c * h * w);
+    let (grid_h, grid_w) = rois[0].grid;
+    let output_dims = (rois[0].rows.len() / grid_h, rois[0].cols.len() / grid_w);
+
+    let mut image = values[0].clone();
+    image.reshape(&[num_batches, c, h, w])?;
+    let mut outputs = vec![];
+    for roi in rois {
+        if roi.batch >= num_batches {
+            return Err(Box::new(TensorError::DimMismatch("roi align".to_string())));
+        }
+        let mut plane = image.get_slice(&[roi.batch..roi.batch + 1])?;
+        plane.reshape(&[c, h, w])?;
+        // the channels are passed through with their full weight
+        let taps = vec![
+            (0..c).map(|i| (i, i, scale)).collect(),
+            roi.rows.clone(),
+            roi.cols.clone(),
+        ];
+        let samples = resize_linear(config, region, &[plane], &taps, scale, offset)?;
+        let pooled = if max {
+            max_pool2d(
+                config,
+                region,
+                &[samples],
+                (0, 0),
+                roi.grid,
+                roi.grid,
+                offset,
+            )?
+        } else {
+            avg_pool2d(
+                config,
+                region,
+                &[samples],
+                (0, 0),
+                roi.grid,
+                roi.grid,
+                false,
+                offset,
+            )?
+        };
+        outputs.push(pooled);
+    }
+
+    let mut output = outputs[0].clone();
+    for pooled in outputs[1..].iter() {
+        output = output.concat(pooled.clone())?;
+    }
+    output.reshape(&[rois.len(), c, output_dims.0, output_dims.1])?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_roi_align = ref_roi_align(&int_input, rois, max, scale)?.map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_roi_align
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// GridSample layout. The normalized coordinates of every point are unnormalized with fixed
+/// point arithmetic and a halving lookup (and clipped to the edges for `border` padding). Bilinear
+/// interpolation is then separable: the weight of every row and column is the tent function of
+/// its distance to the point, applied with a lookup, and every sample is `wy^T X wx`, laid out as
+/// a [matmul] of the row weights with the input, a multiplication by the column weights, and a
+/// [matmul] with ones to sum the columns.
+pub fn grid_sample<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    align_corners: bool,
+    border: bool,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (dims, grid_dims) = (values[0].dims().to_vec(), values[1].dims().to_vec());
+    let rank = dims.len();
+    if !(rank == 3 || rank == 4) || grid_dims.len() != rank || grid_dims[rank - 1] != 2 {
+        return Err(Box::new(TensorError::DimMismatch(
+            "grid sample".to_string(),
+        )));
+    }
+    let (c, h, w) = (dims[rank - 3], dims[rank - 2], dims[rank - 1]);
+    let (out_h, out_w) = (grid_dims[rank - 3], grid_dims[rank - 2]);
+    let num_batches = values[0].len() / (c * h * w);
+    let num_points = out_h * out_w;
+    if values[1].len() != num_batches * num_points * 2 {
+        return Err(Box::new(TensorError::DimMismatch(
+            "grid sample".to_string(),
+        )));
+    }
+
+    let m = scale as i128;
+    let constant = |v: i128| -> ValTensor<F> { constants(std::iter::once(v)) };
+    let halve = LookupOp::Div {
+        denom: eq_float::F32(2.0),
+    };
+    let tent = LookupOp::Tent { scale };
+
+    let mut image = values[0].clone();
+    image.reshape(&[num_batches, c, h, w])?;
+    let mut points = values[1].clone();
+    points.reshape(&[num_batches, num_points, 2])?;
+
+    let mut outputs = vec![];
+    for n in 0..num_batches {
+        let mut coords = points.get_slice(&[n..n + 1])?;
+        coords.reshape(&[num_points, 2])?;
+        coords.permute(&[1, 0])?;
+
+        // the weights of every position along the width, then the height, for every point
+        let mut weights = vec![];
+        for (d, len) in [(0, w), (1, h)] {
+            let mut g = coords.get_slice(&[d..d + 1])?;
+            g.reshape(&[num_points])?;
+            let shifted = pairwise(config, region, &[g, constant(m)], offset, BaseOp::Add)?;
+            let x = match align_corners {
+                true => pairwise(
+                    config,
+                    region,
+                    &[shifted, constant(len as i128 - 1)],
+                    offset,
+                    BaseOp::Mult,
+                )?,
+                false => {
+                    let x = pairwise(
+                        config,
+                        region,
+                        &[shifted, constant(len as i128)],
+                        offset,
+                        BaseOp::Mult,
+                    )?;
+                    pairwise(config, region, &[x, constant(m)], offset, BaseOp::Sub)?
+                }
+            };
+            let mut x = nonlinearity(config, region, &[x], halve.clone(), offset)?;
+            if border {
+                x = clip(
+                    config,
+                    region,
+                    &[x, constant(0), constant((len as i128 - 1) * m)],
+                    true,
+                    true,
+                    offset,
+                )?;
+            }
+
+            x.tile(len)?;
+            x.reshape(&[len, num_points])?;
+            x.permute(&[1, 0])?;
+            let mut positions = constants((0..num_points * len).map(|i| (i % len) as i128 * m));
+            positions.reshape(&[num_points, len])?;
+            let diff = pairwise(config, region, &[x, positions], offset, BaseOp::Sub)?;
+            weights.push(nonlinearity(config, region, &[diff], tent.clone(), offset)?);
+        }
+        let (mut wx, wy) = (weights[0].clone(), weights[1].clone());
+
+        // [H, C * W]
+        let mut plane = image.get_slice(&[n..n + 1])?;
+        plane.reshape(&[c, h, w])?;
+        plane.permute(&[1, 0, 2])?;
+        plane.reshape(&[h, c * w])?;
+        let mut rows = matmul(config, region, &[wy, plane], offset)?;
+        rows.reshape(&[num_points, c, w])?;
+
+        wx.tile(c)?;
+        wx.reshape(&[c, num_points, w])?;
+        wx.permute(&[1, 0, 2])?;
+        let mut weighted = pairwise(config, region, &[rows, wx], offset, BaseOp::Mult)?;
+        weighted.reshape(&[num_points * c, w])?;
+        let mut ones = constants(std::iter::repeat(1).take(w));
+        ones.reshape(&[w, 1])?;
+        let mut samples = matmul(config, region, &[weighted, ones], offset)?;
+        samples.reshape(&[num_points, c])?;
+        samples.permute(&[1, 0])?;
+        outputs.push(nonlinearity(
+            config,
+            region,
+            &[samples],
+            LookupOp::Div {
+                denom: eq_float::F32(scale as f32),
+            },
+            offset,
+        )?);
+    }
+
+    let mut output = outputs[0].clone();
+    for samples in outputs[1..].iter() {
+        output = output.concat(samples.clone())?;
+    }
+    match rank {
+        4 => output.reshape(&[num_batches, c, out_h, out_w])?,
+        _ => output.reshape(&[c, out_h, out_w])?,
+    };
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_grid_sample =
+                ref_grid_sample(&int_inputs[0], &int_inputs[1], align_corners, border, scale)?
+                    .map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_grid_sample
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// Tree ensemble layout. Every branch compares its feature column to the threshold with the
+/// [compare] layout, and the rows reaching a node are tracked as a 0 / 1 tensor: the rows reaching
+/// a branch and passing its test reach its true node, and the remaining ones its false node. The
+/// weights of every leaf are multiplied by the rows reaching it and added to their targets, so
+/// every tree costs a handful of constraints per node and row, whatever path the rows take.
+pub fn tree_ensemble<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    ensemble: &TreeEnsemble,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let dims = values[0].dims().to_vec();
+    let num_features = dims[dims.len() - 1];
+    if !ensemble.is_valid(num_features) || ensemble.base_values.is_empty() {
+        return Err(Box::new(TensorError::DimMismatch(
+            "tree ensemble".to_string(),
+        )));
+    }
+    let num_rows = values[0].len() / num_features;
+    let num_targets = ensemble.base_values.len();
+    let constant = |v: i128| -> ValTensor<F> { constants(std::iter::once(v)) };
+
+    // one tensor per feature, holding that feature of every row
+    let mut input = values[0].clone();
+    input.reshape(&[num_rows, num_features])?;
+    input.permute(&[1, 0])?;
+    let mut features = vec![];
+    for f in 0..num_features {
+        let mut feature = input.get_slice(&[f..f + 1])?;
+        feature.reshape(&[num_rows])?;
+        features.push(feature);
+    }
+
+    let mut outputs: Vec<Option<ValTensor<F>>> = vec![None; num_targets];
+    for tree in ensemble.trees.iter() {
+        // the rows reaching every node, where the root is reached by all of them
+        let mut reach: Vec<Option<ValTensor<F>>> = vec![None; tree.len()];
+        for (n, node) in tree.iter().enumerate() {
+            // nodes which aren't branched to are never reached
+            if n > 0 && reach[n].is_none() {
+                continue;
+            }
+            match node {
+                TreeNode::Branch {
+                    feature,
+                    threshold,
+                    comparison,
+                    true_node,
+                    false_node,
+                } => {
+                    let holds = compare(
+                        config,
+                        region,
+                        &[features[*feature].clone(), constant(*threshold)],
+                        *comparison,
+                        offset,
+                    )?;
+                    let (passed, failed) = match &reach[n] {
+                        Some(r) => {
+                            let passed = pairwise(
+                                config,
+                                region,
+                                &[r.clone(), holds],
+                                offset,
+                                BaseOp::Mult,
+                            )?;
+                            let failed = pairwise(
+                                config,
+                                region,
+                                &[r.clone(), passed.clone()],
+                                offset,
+                                BaseOp::Sub,
+                            )?;
+                            (passed, failed)
+                        }
+                        None => {
+                            let failed = pairwise(
+                                config,
+                                region,
+                                &[constant(1), holds.clone()],
+                                offset,
+                                BaseOp::Sub,
+                            )?;
+                            (holds, failed)
+                        }
+                    };
+                    reach[*true_node] = Some(passed);
+                    reach[*false_node] = Some(failed);
+                }
+                TreeNode::Leaf { weights } => {
+                    for (target, weight) in weights {
+                        let weighted = match &reach[n] {
+                            Some(r) => pairwise(
+                                config,
+                                region,
+                                &[r.clone(), constant(*weight)],
+                                offset,
+                                BaseOp::Mult,
+                            )?,
+                            // a tree which is a single leaf adds its weights to every row
+                            None => {
+                                let mut w = constants(std::iter::repeat(*weight).take(num_rows));
+                                w.reshape(&[num_rows])?;
+                                w
+                            }
+                        };
+                        outputs[*target] = Some(match outputs[*target].take() {
+                            Some(o) => {
+                                pairwise(config, region, &[o, weighted], offset, BaseOp::Add)?
+                            }
+                            None => weighted,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let mut targets = vec![];
+    for (o, base) in outputs.into_iter().zip(ensemble.base_values.iter()) {
+        targets.push(match o {
+            Some(o) => pairwise(config, region, &[o, constant(*base)], offset, BaseOp::Add)?,
+            None => {
+                let mut b = constants(std::iter::repeat(*base).take(num_rows));
+                b.reshape(&[num_rows])?;
+                b
+            }
+        });
+    }
+    let mut output = targets[0].clone();
+    for t in targets[1..].iter() {
+        output = output.concat(t.clone())?;
+    }
+    output.reshape(&[num_targets, num_rows])?;
+    output.permute(&[1, 0])?;
+    let mut output_dims = dims.clone();
+    output_dims[dims.len() - 1] = num_targets;
+    output.reshape(&output_dims)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_tree_ensemble = ref_tree_ensemble(&int_input, ensemble)?.map(|e| e as i32);
+
+            assert_eq!(
+                Into::<Tensor<i32>>::into(output.get_inner()?),
+                ref_tree_ensemble
+            )
+        }
+    };
+
+    Ok(output)
+}
+
+/// Reduce layout. The reduced axes are moved last so that every row holds the elements of an
+/// output. Sums (and means) [sum] every row, dividing by the number of elements with a lookup
+/// for means. Maxima, minima and products are chained over the positions of the rows for all
+/// rows at once, with `max(m, x) = m + relu(x - m)`, `min(m, x) = m - relu(m - x)`, and a
+/// multiplication followed by a rescaling lookup respectively.
+pub fn reduce<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    axes: &[usize],
+    keep_dims: bool,
+    reduction: &Reduction,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut input = values[0].clone();
+    let dims = input.dims().to_vec();
+    if axes.is_empty() || axes.iter().any(|axis| *axis >= dims.len()) {
+        return Err(Box::new(TensorError::DimMismatch("reduce".to_string())));
+    }
+
+    let order: Vec<usize> = (0..dims.len())
+        .filter(|d| !axes.contains(d))
+        .chain((0..dims.len()).filter(|d| axes.contains(d)))
+        .collect();
+    input.permute(&order)?;
+    let len: usize = axes.iter().map(|axis| dims[*axis]).product();
+    let num_rows = input.len() / len;
+    input.reshape(&[num_rows, len])?;
+
+    let mut output = match reduction {
+        Reduction::Sum | Reduction::Mean { .. } => {
+            let mut sums = vec![];
+            for r in 0..num_rows {
+                let mut row = input.get_slice(&[r..r + 1])?;
+                row.reshape(&[len])?;
+                sums.push(sum(config, region, &[row], offset)?);
+            }
+            let mut output = sums[0].clone();
+            for s in sums[1..].iter() {
+                output = output.concat(s.clone())?;
+            }
+            match reduction {
+                Reduction::Mean { denom, remainder } => {
+                    divide(config, region, output, *denom, *remainder, offset)?
+                }
+                _ => output,
+            }
+        }
+        _ => {
+            // one tensor per position along the rows, holding that position of every row
+            input.permute(&[1, 0])?;
+            let mut output = input.get_slice(&[0..1])?;
+            output.reshape(&[num_rows])?;
+            for j in 1..len {
+                let mut column = input.get_slice(&[j..j + 1])?;
+                column.reshape(&[num_rows])?;
+                output = match reduction {
+                    Reduction::Max => {
+                        let diff = pairwise(
+                            config,
+                            region,
+                            &[column, output.clone()],
+                            offset,
+                            BaseOp::Sub,
+                        )?;
+                        let relu = nonlinearity(
+                            config,
+                            region,
+                            &[diff],
+                            LookupOp::ReLU { scale: 1 },
+                            offset,
+                        )?;
+                        pairwise(config, region, &[output, relu], offset, BaseOp::Add)?
+                    }
+                    Reduction::Min => {
+                        let diff = pairwise(
+                            config,
+                            region,
+                            &[output.clone(), column],
+                            offset,
+                            BaseOp::Sub,
+                        )?;
+                        let relu = nonlinearity(
+                            config,
+                            region,
+                            &[diff],
+                            LookupOp::ReLU { scale: 1 },
+                            offset,
+                        )?;
+                        pairwise(config, region, &[output, relu], offset, BaseOp::Sub)?
+                    }
+                    Reduction::Prod { scale } => {
+                        let product =
+                            pairwise(config, region, &[output, column], offset, BaseOp::Mult)?;
+                        nonlinearity(
+                            config,
+                            region,
+                            &[product],
+                            LookupOp::Div {
+                                denom: eq_float::F32(*scale as f32),
+                            },
+                            offset,
+                        )?
+                    }
+                    _ => unreachable!(),
+                };
+            }
+            output
+        }
+    };
+
+    let mut output_dims: Vec<usize> = dims
+        .iter()
+        .enumerate()
+        .filter_map(|(d, len)| match axes.contains(&d) {
+            true if keep_dims => Some(1),
+            true => None,
+            false => Some(*len),
+        })
+        .collect();
+    if output_dims.is_empty() {
+        output_dims.push(1);
+    }
+    output.reshape(&output_dims)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_reduce = ref_reduce(&int_input, axes, keep_dims, reduction)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_reduce)
+        }
+    };
+
+    Ok(output)
+}
+
+/// CumSum layout. The summed axis is moved last, and the running sums of all rows are chained
+/// with one addition per position along the axis. For exclusive sums, the first running sum is
+/// the (constrained) zero `x - x` and every output lags the running sum by one position.
+pub fn cumsum<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    axis: usize,
+    exclusive: bool,
+    reverse: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut input = values[0].clone();
+    let rank = input.dims().len();
+    if axis >= rank {
+        return Err(Box::new(TensorError::DimMismatch("cumsum".to_string())));
+    }
+
+    let order: Vec<usize> = (0..rank).filter(|d| *d != axis).chain([axis]).collect();
+    input.permute(&order)?;
+    let output_dims = input.dims().to_vec();
+    let len = output_dims[rank - 1];
+    let num_rows = input.len() / len;
+
+    // one tensor per position along the axis, holding that position of every row
+    input.reshape(&[num_rows, len])?;
+    input.permute(&[1, 0])?;
+    let mut columns = vec![];
+    for j in 0..len {
+        let mut column = input.get_slice(&[j..j + 1])?;
+        column.reshape(&[num_rows])?;
+        columns.push(column);
+    }
+    if reverse {
+        columns.reverse();
+    }
+
+    let mut sums = vec![];
+    let mut acc = match exclusive {
+        true => pairwise(
+            config,
+            region,
+            &[columns[0].clone(), columns[0].clone()],
+            offset,
+            BaseOp::Sub,
+        )?,
+        false => columns[0].clone(),
+    };
+    sums.push(acc.clone());
+    for j in 1..len {
+        let column = match exclusive {
+            true => columns[j - 1].clone(),
+            false => columns[j].clone(),
+        };
+        acc = pairwise(config, region, &[acc, column], offset, BaseOp::Add)?;
+        sums.push(acc.clone());
+    }
+    if reverse {
+        sums.reverse();
+    }
+
+    let mut output = sums[0].clone();
+    for s in sums[1..].iter() {
+        output = output.concat(s.clone())?;
+    }
+    output.reshape(&[len, num_rows])?;
+    output.permute(&[1, 0])?;
+    output.reshape(&output_dims)?;
+    let inverse: Vec<usize> = (0..rank)
+        .map(|d| order.iter().position(|o| *o == d).unwrap())
+        .collect();
+    output.permute(&inverse)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let ref_cumsum = ref_cumsum(&int_input, axis, exclusive, reverse)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_cumsum)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Clip layout, for bounds given as (scalar) inputs that follow the clipped input. The lower
+/// bound is applied with `max(x, lo) = lo + relu(x - lo)` and the upper bound with
+/// `min(x, hi) = x - relu(x - hi)`.
+pub fn clip<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    lower: bool,
+    upper: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if values.len() != 1 + lower as usize + upper as usize {
+        return Err(Box::new(TensorError::DimMismatch("clip".to_string())));
+    }
+
+    let mut output = values[0].clone();
+    let mut bounds = values[1..].iter();
+    if lower {
+        let lo = bounds.next().unwrap().clone();
+        let diff = pairwise(config, region, &[output, lo.clone()], offset, BaseOp::Sub)?;
+        let relu = nonlinearity(config, region, &[diff], LookupOp::ReLU { scale: 1 }, offset)?;
+        output = pairwise(config, region, &[relu, lo], offset, BaseOp::Add)?;
+    }
+    if upper {
+        let hi = bounds.next().unwrap().clone();
+        let diff = pairwise(config, region, &[output.clone(), hi], offset, BaseOp::Sub)?;
+        let relu = nonlinearity(config, region, &[diff], LookupOp::ReLU { scale: 1 }, offset)?;
+        output = pairwise(config, region, &[output, relu], offset, BaseOp::Sub)?;
+    }
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_input: Tensor<i128> = values[0].get_int_evals()?.into_iter().into();
+            int_input.reshape(values[0].dims());
+            let mut int_bounds = values[1..]
+                .iter()
+                .map(|b| b.get_int_evals().map(|evals| evals[0]));
+            let min = match lower {
+                true => Some(int_bounds.next().unwrap()?),
+                false => None,
+            };
+            let max = match upper {
+                true => Some(int_bounds.next().unwrap()?),
+                false => None,
+            };
+            let ref_clip = ref_clip(&int_input, min, max).map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_clip)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Comparison layout. The inputs are subtracted, and the difference is compared to zero with a
+/// lookup, giving 1 where the comparison holds and 0 elsewhere.
+pub fn compare<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    comparison: Comparison,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let diff = pairwise(config, region, values, offset, BaseOp::Sub)?;
+    let output = nonlinearity(
+        config,
+        region,
+        &[diff],
+        LookupOp::Compare { comparison },
+        offset,
+    )?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(values[0].get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_compare =
+                ref_compare(&int_inputs[0], &int_inputs[1], &comparison)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_compare)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Where layout, selecting `y + c * (x - y)` for a condition `c` of 0s and 1s.
+pub fn select<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 3],
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (condition, x, y) = (values[0].clone(), values[1].clone(), values[2].clone());
+    let diff = pairwise(config, region, &[x, y.clone()], offset, BaseOp::Sub)?;
+    let selected = pairwise(config, region, &[condition, diff], offset, BaseOp::Mult)?;
+    let output = pairwise(config, region, &[selected, y], offset, BaseOp::Add)?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_select =
+                ref_select(&int_inputs[0], &int_inputs[1], &int_inputs[2])?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_select)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Boolean logic layout. Every input is negated with a zero-check lookup, which constrains the
+/// negations to 0 or 1 (any non-zero input being true). `Not` is the negation itself, and with
+/// negations `p` and `q`, `And` is `is_zero(p + q)`, `Or` is `is_zero(p * q)` and `Xor` is
+/// `(p - q)^2`.
+pub fn logic<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    logic: Logic,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let num_inputs = match logic {
+        Logic::Not => 1,
+        _ => 2,
+    };
+    if values.len() != num_inputs {
+        return Err(Box::new(TensorError::DimMismatch("logic".to_string())));
+    }
+
+    let mut negations = vec![];
+    for v in values.iter() {
+        negations.push(nonlinearity(
+            config,
+            region,
+            &[v.clone()],
+            LookupOp::IsZero,
+            offset,
+        )?);
+    }
+
+    let output = match logic {
+        Logic::Not => negations[0].clone(),
+        Logic::And => {
+            let sum = pairwise(
+                config,
+                region,
+                &[negations[0].clone(), negations[1].clone()],
+                offset,
+                BaseOp::Add,
+            )?;
+            nonlinearity(config, region, &[sum], LookupOp::IsZero, offset)?
+        }
+        Logic::Or => {
+            let product = pairwise(
+                config,
+                region,
+                &[negations[0].clone(), negations[1].clone()],
+                offset,
+                BaseOp::Mult,
+            )?;
+            nonlinearity(config, region, &[product], LookupOp::IsZero, offset)?
+        }
+        Logic::Xor => {
+            let diff = pairwise(
+                config,
+                region,
+                &[negations[0].clone(), negations[1].clone()],
+                offset,
+                BaseOp::Sub,
+            )?;
+            pairwise(config, region, &[diff.clone(), diff], offset, BaseOp::Mult)?
+        }
+    };
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(negations[0].get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_logic = ref_logic(&int_inputs, &logic)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_logic)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Max (or Min) layout, for any number of inputs. Every next input is compared with the running
+/// extremum, and selected where it is greater (or less), as in the [compare] and [select]
+/// layouts.
+pub fn extremum<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    largest: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if values.is_empty() {
+        return Err(Box::new(TensorError::DimMismatch("extremum".to_string())));
+    }
+    let comparison = match largest {
+        true => Comparison::Greater,
+        false => Comparison::Less,
+    };
+
+    let mut output = values[0].clone();
+    for v in values[1..].iter() {
+        let condition = compare(
+            config,
+            region,
+            &[v.clone(), output.clone()],
+            comparison,
+            offset,
+        )?;
+        output = select(config, region, &[condition, v.clone(), output], offset)?;
+    }
+
+    Ok(output)
+}
+
+/// Sign (or absolute value) layout. The sign `s` of every element `x` is witnessed, and a sign
+/// gate constrains `s` to -1, 0 or 1, lays out `|x| = s * x` and checks that `x = s * |x|`, such
+/// that `s` is only 0 where `x` is. `|x| - s^2` is then checked to be nonnegative with a relu
+/// lookup, which rules out both the wrong sign and a sign of -1 or 1 for an `x` of 0.
+///
+/// The relu table spans the full range of the lookups, as the check needs to cover every
+/// magnitude of `x`: its 2^bits rows are shared with the other ops looking it up, but are paid
+/// for by the sign alone in circuits without any. Checking the magnitude against a small table
+/// instead would take a decomposition of `|x|` into limbs, at several cells per limb.
+pub fn sign<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 1],
+    abs: bool,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let x = &values[0];
+
+    let integer_evals: Tensor<i128> = x
+        .get_int_evals()
+        .map_err(|e| {
+            error!("{}", e);
+            halo2_proofs::plonk::Error::Synthesis
+        })?
+        .into_iter()
+        .into();
+
+    // for key generation integer_evals will be empty and we need to return a set of unassigned values
+    let mut signs: Tensor<Value<F>> = match integer_evals.len() {
+        0 => Tensor::from((0..x.len()).map(|_| Value::unknown())),
+        _ => integer_evals.map(|x| Value::known(i128_to_felt(x.signum()))),
+    };
+    signs.reshape(x.dims());
+    // the signs are assigned once and copied into the sign gate and the square
+    let signs = identity(config, region, &[signs.into()], offset)?;
+
+    let magnitudes = pairwise(
+        config,
+        region,
+        &[signs.clone(), x.clone()],
+        offset,
+        BaseOp::Sign,
+    )?;
+    let squares = pairwise(
+        config,
+        region,
+        &[signs.clone(), signs.clone()],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let slack = pairwise(
+        config,
+        region,
+        &[magnitudes.clone(), squares],
+        offset,
+        BaseOp::Sub,
+    )?;
+    nonnegative(config, region, &[slack], offset)?;
+
+    let output = match abs {
+        true => magnitudes,
+        false => signs,
+    };
+
+    if matches!(&config.check_mode, CheckMode::SAFE) && !integer_evals.is_empty() {
+        let mut int_input = integer_evals;
+        int_input.reshape(x.dims());
+        let expected = match abs {
+            true => ref_abs(&int_input),
+            false => ref_sign(&int_input),
+        };
+        assert_eq!(
+            Into::<Tensor<i32>>::into(output.get_inner()?),
+            expected.map(|e| e as i32)
+        )
+    };
+
+    Ok(output)
+}
+
+/// Bitwise layout, for unsigned integers of `bits` bits in fixed point at `scale`. Every input is
+/// decomposed into its bits with [bit_decomposition], and the integer bits of the output are laid
+/// out from those of the inputs: `a * b` for `And`, `a + b - a * b` for `Or` and
+/// `a + b - 2 * a * b` for `Xor`, or the shifted bits of the input. The output is then recomposed
+/// from its bits, with constant weights, such that the fractional bits of the inputs are dropped.
+pub fn bitwise<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    bitwise: Bitwise,
+    bits: usize,
+    scale: usize,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let num_inputs = match bitwise {
+        Bitwise::ShiftLeft(_) | Bitwise::ShiftRight(_) => 1,
+        _ => 2,
+    };
+    if values.len() != num_inputs {
+        return Err(Box::new(TensorError::DimMismatch("bitwise".to_string())));
+    }
+
+    // the ops are commutative, and a single element input is broadcast as the second one
+    let mut inputs = values.to_vec();
+    inputs.sort_by_key(|v| std::cmp::Reverse(v.len()));
+    let mut decomposed = vec![];
+    for input in inputs.iter() {
+        let input_bits = bit_decomposition(config, region, input, bits + scale, offset)?;
+        decomposed.push(input_bits[scale..].to_vec());
+    }
+
+    let output_bits: Vec<Option<ValTensor<F>>> = match bitwise {
+        Bitwise::ShiftLeft(k) => (0..bits)
+            .map(|j| j.checked_sub(k).map(|i| decomposed[0][i].clone()))
+            .collect(),
+        Bitwise::ShiftRight(k) => (0..bits)
+            .map(|j| decomposed[0].get(j + k).cloned())
+            .collect(),
+        op => {
+            let mut output_bits = vec![];
+            for (a, b) in decomposed[0].iter().zip(decomposed[1].iter()) {
+                let product = pairwise(
+                    config,
+                    region,
+                    &[a.clone(), b.clone()],
+                    offset,
+                    BaseOp::Mult,
+                )?;
+                let bit = match op {
+                    Bitwise::And => product,
+                    _ => {
+                        let sum =
+                            pairwise(config, region, &[a.clone(), b.clone()], offset, BaseOp::Add)?;
+                        let carry = match op {
+                            Bitwise::Xor => pairwise(
+                                config,
+                                region,
+                                &[product, constants(std::iter::once(2))],
+                                offset,
+                                BaseOp::Mult,
+                            )?,
+                            _ => product,
+                        };
+                        pairwise(config, region, &[sum, carry], offset, BaseOp::Sub)?
+                    }
+                };
+                output_bits.push(Some(bit));
+            }
+            output_bits
+        }
+    };
+
+    let mut output: Option<ValTensor<F>> = None;
+    for (j, bit) in output_bits.into_iter().enumerate() {
+        if let Some(bit) = bit {
+            let term = pairwise(
+                config,
+                region,
+                &[bit, constants(std::iter::once(1 << (j + scale)))],
+                offset,
+                BaseOp::Mult,
+            )?;
+            output = Some(match output {
+                Some(sum) => pairwise(config, region, &[sum, term], offset, BaseOp::Add)?,
+                None => term,
+            });
+        }
+    }
+    // every bit may have been shifted out
+    let output = match output {
+        Some(output) => output,
+        None => {
+            let mut zeros = constants(std::iter::repeat(0).take(inputs[0].len()));
+            zeros.reshape(inputs[0].dims())?;
+            zeros
+        }
+    };
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(values[0].get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in inputs.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_bitwise = ref_bitwise(&int_inputs, &bitwise, bits, scale)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_bitwise)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Decomposes every element of `x` into `num_bits` bits, least significant first, constrained to
+/// be its binary expansion. The quotients of `x` by 2^i are witnessed, and every bit is laid out
+/// as the difference between a quotient and twice the next one (by a constant 2), which is
+/// checked to be 0 or 1 against a [LookupOp::Range] table of a single bit, as is the last
+/// quotient. Elements outside of [0, 2^num_bits) can't be decomposed.
+fn bit_decomposition<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    x: &ValTensor<F>,
+    num_bits: usize,
+    offset: &mut usize,
+) -> Result<Vec<ValTensor<F>>, Box<dyn Error>> {
+    let integer_evals: Tensor<i128> = x
+        .get_int_evals()
+        .map_err(|e| {
+            error!("{}", e);
+            halo2_proofs::plonk::Error::Synthesis
+        })?
+        .into_iter()
+        .into();
+
+    let bit = LookupOp::Range { bits: 1 };
+    let mut bits = vec![];
+    let mut quotient = x.clone();
+    for i in 1..num_bits {
+        // for key generation integer_evals will be empty and we need to return a set of unassigned values
+        let mut next: Tensor<Value<F>> = match integer_evals.len() {
+            0 => Tensor::from((0..x.len()).map(|_| Value::unknown())),
+            _ => integer_evals.map(|x| Value::known(i128_to_felt(x >> i))),
+        };
+        next.reshape(x.dims());
+        // the quotient is assigned once and copied into the bit below it and the next one
+        let next = identity(config, region, &[next.into()], offset)?;
+        let twice = pairwise(
+            config,
+            region,
+            &[next.clone(), constants(std::iter::once(2))],
+            offset,
+            BaseOp::Mult,
+        )?;
+        let diff = pairwise(config, region, &[quotient, twice], offset, BaseOp::Sub)?;
+        bits.push(nonlinearity(config, region, &[diff], bit.clone(), offset)?);
+        quotient = next;
+    }
+    bits.push(nonlinearity(config, region, &[quotient], bit, offset)?);
+
+    Ok(bits)
+}
+
+/// NonMaxSuppression layout, for a single batch with a bounded number of boxes. Every pair of
+/// boxes is laid out as an `N x N` grid, on which the IoU test `inter * (1 + t) > t * (a_i + a_j)`
+/// and the score order (ties broken by box order) are constrained with comparison lookups. The
+/// greedy selection is then unrolled into `N - 1` passes, where a valid box is kept if no kept
+/// box that comes before it overlaps it, each pass settling at least one more box. The kept boxes
+/// are ranked by the number of kept boxes before them, and every output slot picks the box of
+/// its rank with a zero-check, as rows of `[batch, class, box]` (or -1 where no box is left).
+pub fn non_max_suppression<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>; 2],
+    params: &NmsParams,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (box_dims, score_dims) = (values[0].dims().to_vec(), values[1].dims().to_vec());
+    if box_dims.len() != 2
+        || box_dims[1] != 4
+        || score_dims.len() != 2
+        || score_dims[1] != box_dims[0]
+    {
+        return Err(Box::new(TensorError::DimMismatch(
+            "non max suppression".to_string(),
+        )));
+    }
+    let (num_classes, n) = (score_dims[0], box_dims[0]);
+    let max_boxes = params.max_boxes.min(n);
+    let (m, t) = (params.scale as i128, params.iou_threshold);
+
+    let constant = |v: i128| -> ValTensor<F> { constants(std::iter::once(v)) };
+    let constants = |vs: Vec<i128>, dims: &[usize]| -> Result<ValTensor<F>, Box<dyn Error>> {
+        let mut c: ValTensor<F> = Tensor::from(
+            vs.into_iter()
+                .map(|v| ValType::Constant(i128_to_felt::<F>(v))),
+        )
+        .into();
+        c.reshape(dims)?;
+        Ok(c)
+    };
+    // a [N] tensor as a [N, N] grid, with (i, j) holding entry j
+    let columns = |v: &ValTensor<F>| -> Result<ValTensor<F>, Box<dyn Error>> {
+        let mut grid = v.clone();
+        grid.tile(n)?;
+        grid.reshape(&[n, n])?;
+        Ok(grid)
+    };
+    // a [N] tensor as a [N, N] grid, with (i, j) holding entry i
+    let rows = |v: &ValTensor<F>| -> Result<ValTensor<F>, Box<dyn Error>> {
+        let mut grid = columns(v)?;
+        grid.permute(&[1, 0])?;
+        Ok(grid)
+    };
+    let div = LookupOp::Div {
+        denom: eq_float::F32(params.scale as f32),
+    };
+    let greater = LookupOp::Compare {
+        comparison: Comparison::Greater,
+    };
+
+    let mut coords = values[0].clone();
+    coords.permute(&[1, 0])?;
+    let mut b = vec![];
+    for i in 0..4 {
+        let mut coord = coords.get_slice(&[i..i + 1])?;
+        coord.reshape(&[n])?;
+        b.push(coord);
+    }
+    // the lower and upper corners of every box
+    let (lo, hi) = if params.center_point_box {
+        // doubled, so as to stay integers
+        let mut lo = vec![];
+        let mut hi = vec![];
+        for (center, size) in [(&b[0], &b[2]), (&b[1], &b[3])] {
+            let doubled = pairwise(
+                config,
+                region,
+                &[center.clone(), constant(2)],
+                offset,
+                BaseOp::Mult,
+            )?;
+            lo.push(pairwise(
+                config,
+                region,
+                &[doubled.clone(), size.clone()],
+                offset,
+                BaseOp::Sub,
+            )?);
+            hi.push(pairwise(
+                config,
+                region,
+                &[doubled, size.clone()],
+                offset,
+                BaseOp::Add,
+            )?);
+        }
+        (lo, hi)
+    } else {
+        let mut lo = vec![];
+        let mut hi = vec![];
+        for (x, y) in [(&b[0], &b[2]), (&b[1], &b[3])] {
+            lo.push(extremum(
+                config,
+                region,
+                &[x.clone(), y.clone()],
+                false,
+                offset,
+            )?);
+            hi.push(extremum(
+                config,
+                region,
+                &[x.clone(), y.clone()],
+                true,
+                offset,
+            )?);
+        }
+        (lo, hi)
+    };
+
+    let mut sides = vec![];
+    let mut overlap_sides = vec![];
+    for d in 0..2 {
+        sides.push(pairwise(
+            config,
+            region,
+            &[hi[d].clone(), lo[d].clone()],
+            offset,
+            BaseOp::Sub,
+        )?);
+        // relu(min(hi_i, hi_j) - max(lo_i, lo_j))
+        let upper = extremum(
+            config,
+            region,
+            &[rows(&hi[d])?, columns(&hi[d])?],
+            false,
+            offset,
+        )?;
+        let lower = extremum(
+            config,
+            region,
+            &[rows(&lo[d])?, columns(&lo[d])?],
+            true,
+            offset,
+        )?;
+        let diff = pairwise(config, region, &[upper, lower], offset, BaseOp::Sub)?;
+        overlap_sides.push(nonlinearity(
+            config,
+            region,
+            &[diff],
+            LookupOp::ReLU { scale: 1 },
+            offset,
+        )?);
+    }
+    let area = pairwise(
+        config,
+        region,
+        &[sides[0].clone(), sides[1].clone()],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let area = nonlinearity(config, region, &[area], div.clone(), offset)?;
+    let inter = pairwise(
+        config,
+        region,
+        &[overlap_sides[0].clone(), overlap_sides[1].clone()],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let inter = nonlinearity(config, region, &[inter], div.clone(), offset)?;
+
+    // inter * (1 + t) - t * (a_i + a_j) > 0
+    let lhs = pairwise(
+        config,
+        region,
+        &[inter, constant(m + t)],
+        offset,
+        BaseOp::Mult,
+    )?;
+    let areas = pairwise(
+        config,
+        region,
+        &[rows(&area)?, columns(&area)?],
+        offset,
+        BaseOp::Add,
+    )?;
+    let rhs = pairwise(config, region, &[areas, constant(t)], offset, BaseOp::Mult)?;
+    let diff = pairwise(config, region, &[lhs, rhs], offset, BaseOp::Sub)?;
+    let diff = nonlinearity(config, region, &[diff], div, offset)?;
+    let overlaps = nonlinearity(config, region, &[diff], greater.clone(), offset)?;
+
+    // breaks ties in score, with (i, j) being 1 where j < i
+    let ties = constants(
+        (0..n * n).map(|k| ((k % n) < (k / n)) as i128).collect(),
+        &[n, n],
+    )?;
+    let positions = constants((1..=n as i128).collect(), &[n])?;
+
+    let mut rows_out = vec![];
+    for c in 0..num_classes {
+        let mut scores = values[1].get_slice(&[c..c + 1])?;
+        scores.reshape(&[n])?;
+
+        // whether box j comes before box i, 2 * (s_j - s_i) + (j < i) > 0
+        let diff = pairwise(
+            config,
+            region,
+            &[columns(&scores)?, rows(&scores)?],
+            offset,
+            BaseOp::Sub,
+        )?;
+        let diff = pairwise(config, region, &[diff, constant(2)], offset, BaseOp::Mult)?;
+        let diff = pairwise(config, region, &[diff, ties.clone()], offset, BaseOp::Add)?;
+        let before = nonlinearity(config, region, &[diff], greater.clone(), offset)?;
+        let suppresses = pairwise(
+            config,
+            region,
+            &[before.clone(), overlaps.clone()],
+            offset,
+            BaseOp::Mult,
+        )?;
+        let mut suppresses_t = suppresses;
+        suppresses_t.permute(&[1, 0])?;
+        let mut before_t = before;
+        before_t.permute(&[1, 0])?;
+
+        let valid = match params.score_threshold {
+            Some(threshold) => {
+                let diff = pairwise(
+                    config,
+                    region,
+                    &[scores.clone(), constant(threshold)],
+                    offset,
+                    BaseOp::Sub,
+                )?;
+                nonlinearity(config, region, &[diff], greater.clone(), offset)?
+            }
+            None => constants(vec![1; n], &[n])?,
+        };
+
+        // every pass settles at least the next box in score order
+        let mut keep = valid.clone();
+        for _ in 1..n {
+            keep.reshape(&[1, n])?;
+            let mut suppressed = matmul(config, region, &[keep, suppresses_t.clone()], offset)?;
+            suppressed.reshape(&[n])?;
+            let not_suppressed =
+                nonlinearity(config, region, &[suppressed], LookupOp::IsZero, offset)?;
+            keep = pairwise(
+                config,
+                region,
+                &[valid.clone(), not_suppressed],
+                offset,
+                BaseOp::Mult,
+            )?;
+        }
+
+        keep.reshape(&[1, n])?;
+        let mut ranks = matmul(config, region, &[keep.clone(), before_t], offset)?;
+        ranks.reshape(&[n])?;
+        keep.reshape(&[n])?;
+
+        for r in 0..max_boxes {
+            let diff = pairwise(
+                config,
+                region,
+                &[ranks.clone(), constant(r as i128)],
+                offset,
+                BaseOp::Sub,
+            )?;
+            let is_rank = nonlinearity(config, region, &[diff], LookupOp::IsZero, offset)?;
+            let selected = pairwise(
+                config,
+                region,
+                &[keep.clone(), is_rank],
+                offset,
+                BaseOp::Mult,
+            )?;
+            let filled = sum(config, region, &[selected.clone()], offset)?;
+            let index = dot(config, region, &[positions.clone(), selected], offset)?;
+
+            let batch = pairwise(
+                config,
+                region,
+                &[filled.clone(), constant(1)],
+                offset,
+                BaseOp::Sub,
+            )?;
+            let class = pairwise(
+                config,
+                region,
+                &[filled, constant(c as i128 + 1)],
+                offset,
+                BaseOp::Mult,
+            )?;
+            let class = pairwise(config, region, &[class, constant(1)], offset, BaseOp::Sub)?;
+            let index = pairwise(config, region, &[index, constant(1)], offset, BaseOp::Sub)?;
+            rows_out.extend([batch, class, index]);
+        }
+    }
+
+    let mut output = rows_out[0].clone();
+    for row in rows_out[1..].iter() {
+        output = output.concat(row.clone())?;
+    }
+    output.reshape(&[num_classes * max_boxes, 3])?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_nms =
+                ref_non_max_suppression(&int_inputs[0], &int_inputs[1], params)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_nms)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Lays out each op of a [Subgraph] in turn on the same config, returning the subgraph's outputs.
+pub fn subgraph<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    body: &Subgraph,
+    offset: &mut usize,
+) -> Result<Vec<ValTensor<F>>, Box<dyn Error>> {
+    body.walk(
+        values,
+        |values, dims| {
+            // constants of the body are bound to fixed cells when assigned
+            let constant = Tensor::new(Some(values), dims)?;
+            Ok(constant
+                .map(|x| ValType::Constant(i128_to_felt::<F>(x)))
+                .into())
+        },
+        |node, inputs| -> Result<ValTensor<F>, Box<dyn Error>> {
+            config
+                .layout(region, &inputs, offset, node.op.clone())?
+                .ok_or_else(|| CircuitError::UnsupportedOp.into())
+        },
+    )
+}
+
+/// If-else layout. Both branches are laid out, and their outputs are selected between by the
+/// (boolean) condition, which is the first of the values.
+pub fn if_else<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    then_branch: &Subgraph,
+    else_branch: &Subgraph,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let then_output = subgraph(config, region, values, then_branch, offset)?;
+    let else_output = subgraph(config, region, values, else_branch, offset)?;
+    let (x, y) = match (then_output.first(), else_output.first()) {
+        (Some(x), Some(y)) => (x.clone(), y.clone()),
+        _ => return Err(Box::new(TensorError::DimMismatch("if".to_string()))),
+    };
+    // the selection is checked against its reference by the select layout
+    select(config, region, &[values[0].clone(), x, y], offset)
+}
+
+/// Scan layout. The body is unrolled over every iteration, slicing the scanned inputs along their
+/// axes and concatenating the body's outputs.
+pub fn scan<F: FieldExt + TensorType>(
+    config: &mut BaseConfig<F>,
+    region: &mut Region<F>,
+    values: &[ValTensor<F>],
+    params: &ScanParams,
+    offset: &mut usize,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let output = params.unroll(
+        values,
+        |v, axis, i| -> Result<ValTensor<F>, Box<dyn Error>> {
+            let indices = v
+                .dims()
+                .iter()
+                .enumerate()
+                .map(|(d, n)| if d == axis { i..i + 1 } else { 0..*n })
+                .collect_vec();
+            v.get_slice(&indices)
+        },
+        |vs, axis| -> Result<ValTensor<F>, Box<dyn Error>> {
+            let mut dims = vs[0].dims().to_vec();
+            let mut stacked = vs[0].clone();
+            for v in vs[1..].iter() {
+                stacked = stacked.concat(v.clone())?;
+            }
+            stacked.reshape(&[&[vs.len()], &dims[..]].concat())?;
+            let mut order = (1..=dims.len()).collect_vec();
+            order.insert(axis, 0);
+            stacked.permute(&order)?;
+            dims[axis] *= vs.len();
+            stacked.reshape(&dims)?;
+            Ok(stacked)
+        },
+        |body, inputs| subgraph(config, region, inputs, body, offset),
+    )?;
+
+    if matches!(&config.check_mode, CheckMode::SAFE) {
+        // during key generation this will be 0 so we use this as a flag to check
+        // TODO: this isn't very safe and would be better to get the phase directly
+        let is_assigned = !Into::<Tensor<i32>>::into(output.get_inner()?)
+            .iter()
+            .all(|&x| x == 0);
+        if is_assigned {
+            let mut int_inputs = vec![];
+            for v in values.iter() {
+                let mut int_input: Tensor<i128> = v.get_int_evals()?.into_iter().into();
+                int_input.reshape(v.dims());
+                int_inputs.push(int_input);
+            }
+            let ref_scan = params.f(&int_inputs)?.map(|e| e as i32);
+
+            assert_eq!(Into::<Tensor<i32>>::into(output.get_inner()?), ref_scan)
+        }
+    };
+
+    Ok(output)
+}
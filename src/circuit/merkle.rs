@@ -0,0 +1,157 @@
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use serde::{Deserialize, Serialize};
+
+use super::poseidon::{PoseidonConfig, PoseidonSpec};
+use crate::tensor::{TensorType, ValType};
+
+/// The path from a leaf of a Merkle tree to its root, as given in data files.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MerklePath {
+    /// The index of the leaf, whose bits (least significant first) are set where the path
+    /// enters a node from its right child.
+    pub index: usize,
+    /// The siblings of the nodes along the path (leaf first), as the hex of their big endian
+    /// encodings.
+    pub siblings: Vec<String>,
+}
+
+impl MerklePath {
+    /// Reads the siblings of the path as field elements. Returns `None` if any isn't the
+    /// encoding of a field element, or if the index is out of a tree of the path's depth.
+    pub fn siblings<F: FieldExt>(&self) -> Option<Vec<F>> {
+        if self.siblings.len() < usize::BITS as usize && self.index >> self.siblings.len() != 0 {
+            return None;
+        }
+        self.siblings
+            .iter()
+            .map(|s| {
+                let mut bytes = hex::decode(s.trim_start_matches("0x")).ok()?;
+                let mut repr = F::Repr::default();
+                if bytes.len() > repr.as_ref().len() {
+                    return None;
+                }
+                bytes.reverse();
+                repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+                Option::from(F::from_repr(repr))
+            })
+            .collect()
+    }
+}
+
+/// The root of the Merkle tree with `leaf` at `index`, given the `siblings` along its path. Each
+/// node is the Poseidon hash of its left and right children.
+pub fn merkle_root<F: FieldExt>(leaf: F, index: usize, siblings: &[F]) -> F {
+    let spec = PoseidonSpec::<F>::new();
+    siblings
+        .iter()
+        .enumerate()
+        .fold(leaf, |node, (level, sibling)| match (index >> level) & 1 {
+            1 => spec.hash(&[*sibling, node]),
+            _ => spec.hash(&[node, *sibling]),
+        })
+}
+
+/// Configuration for a Merkle chip, which proves that a value is a leaf of a Merkle tree of
+/// Poseidon hashes. Each level of the path orders a node and its sibling, by a bit of the index
+/// of the leaf, before they are hashed by the Poseidon chip.
+#[derive(Clone, Debug)]
+pub struct MerkleConfig<F: FieldExt> {
+    node: Column<Advice>,
+    sibling: Column<Advice>,
+    bit: Column<Advice>,
+    q_swap: Selector,
+    poseidon: PoseidonConfig<F>,
+}
+
+impl<F: FieldExt + TensorType> MerkleConfig<F> {
+    /// Configures the chip's swap gate, hashing with the given Poseidon chip.
+    pub fn configure(meta: &mut ConstraintSystem<F>, poseidon: PoseidonConfig<F>) -> Self {
+        let (node, sibling, bit) = (
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        );
+        meta.enable_equality(node);
+        meta.enable_equality(sibling);
+        let q_swap = meta.selector();
+
+        // the next row holds the left and right children, swapped when the bit is set
+        meta.create_gate("merkle swap", |meta| {
+            let q = meta.query_selector(q_swap);
+            let node_cur = meta.query_advice(node, Rotation::cur());
+            let sibling_cur = meta.query_advice(sibling, Rotation::cur());
+            let bit = meta.query_advice(bit, Rotation::cur());
+            let left = meta.query_advice(node, Rotation::next());
+            let right = meta.query_advice(sibling, Rotation::next());
+            let one = Expression::Constant(F::one());
+            let swapped = bit.clone() * (sibling_cur.clone() - node_cur.clone());
+            Constraints::with_selector(
+                q,
+                vec![
+                    bit.clone() * (one - bit),
+                    left - (node_cur.clone() + swapped.clone()),
+                    right - (sibling_cur - swapped),
+                ],
+            )
+        });
+
+        MerkleConfig {
+            node,
+            sibling,
+            bit,
+            q_swap,
+            poseidon,
+        }
+    }
+
+    /// Hashes `leaf` up the path given by `index` and `siblings`, returning the root of the tree.
+    pub fn layout(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        leaf: &AssignedCell<F, F>,
+        index: usize,
+        siblings: &[F],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        let mut node = leaf.clone();
+        for (level, sibling) in siblings.iter().enumerate() {
+            let bit = F::from(((index >> level) & 1) as u64);
+            let (left, right) = layouter.assign_region(
+                || "merkle level",
+                |mut region| {
+                    self.q_swap.enable(&mut region, 0)?;
+                    let cur = node.copy_advice(|| "node", &mut region, self.node, 0)?;
+                    region.assign_advice(
+                        || "sibling",
+                        self.sibling,
+                        0,
+                        || Value::known(*sibling),
+                    )?;
+                    region.assign_advice(|| "bit", self.bit, 0, || Value::known(bit))?;
+                    let swapped = cur.value().map(|c| bit * (*sibling - c));
+                    let left = region.assign_advice(
+                        || "left",
+                        self.node,
+                        1,
+                        || cur.value().copied() + swapped,
+                    )?;
+                    let right = region.assign_advice(
+                        || "right",
+                        self.sibling,
+                        1,
+                        || Value::known(*sibling) - swapped,
+                    )?;
+                    Ok((left, right))
+                },
+            )?;
+            let children = [ValType::PrevAssigned(left), ValType::PrevAssigned(right)];
+            let (_, digest) = self.poseidon.layout(layouter, &children)?;
+            node = digest;
+        }
+        Ok(node)
+    }
+}
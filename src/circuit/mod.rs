@@ -1,6 +1,18 @@
 /// Layouts for specific functions (composed of base ops)
 pub mod layouts;
 
+/// A Poseidon hash chip, used to commit to private values
+pub mod poseidon;
+
+/// A Merkle chip, used to prove that committed values are leaves of a tree of Poseidon hashes
+pub mod merkle;
+
+/// An ECDSA chip, used to check signatures over private values
+pub mod ecdsa;
+
+/// A SHA-256 hash chip, used to commit to private values as the EVM would
+pub mod sha256;
+
 ///
 pub mod table;
 
@@ -26,10 +38,17 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     fieldutils::{i128_to_felt, i32_to_felt},
-    tensor::{self, Tensor, TensorError, TensorType, ValTensor, VarTensor},
+    tensor::{
+        self,
+        ops::{
+            Bitwise, Comparison, DepthToSpaceMode, Logic, NmsParams, PadFill, Reduction, RoiTaps,
+            TreeEnsemble, TreeNode,
+        },
+        Tensor, TensorError, TensorType, ValTensor, VarTensor,
+    },
 };
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::BTreeMap,
     error::Error,
     fmt,
@@ -55,6 +74,9 @@ pub enum CircuitError {
     /// This operation is unsupported
     #[error("unsupported operation in graph")]
     UnsupportedOp,
+    /// The witnessed tables don't fit in their column
+    #[error("the witnessed tables take {0} rows, but their column holds {1}")]
+    DynamicTableTooLong(usize, usize),
 }
 
 #[allow(missing_docs)]
@@ -69,6 +91,7 @@ pub enum BaseOp {
     Sum,
     Neg,
     Range { tol: i32 },
+    Sign,
 }
 
 #[allow(missing_docs)]
@@ -92,6 +115,54 @@ impl From<String> for CheckMode {
     }
 }
 
+/// An enum representing how convolutions are lowered to the matmuls they are laid out as
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default, Copy,
+)]
+pub enum ConvLowering {
+    /// a matmul of the blocked toeplitz matrix of the kernel and the flattened image, with a
+    /// single dot product per output
+    #[default]
+    Direct,
+    /// a matmul of the flattened kernel and the windows of the image
+    /// (see [crate::tensor::ops::im2col]), whose dot products only span the kernel's window
+    /// rather than the whole image
+    Im2col,
+    /// a Winograd F(tile, 3) convolution of 3x3 kernels with a stride of 1, for tiles of 2 or 4,
+    /// which multiplies `(tile + 2)^2` rather than `9 * tile^2` values per tile of outputs (see
+    /// [crate::tensor::ops::winograd_transforms])
+    Winograd {
+        /// the size of the (square) tiles of outputs
+        tile: usize,
+    },
+    /// whichever of the above lays out the fewest cells, picked per conv when the model is loaded
+    Auto,
+}
+
+impl From<String> for ConvLowering {
+    fn from(value: String) -> Self {
+        match value.to_lowercase().as_str() {
+            "direct" => ConvLowering::Direct,
+            "im2col" => ConvLowering::Im2col,
+            "winograd-2" => ConvLowering::Winograd { tile: 2 },
+            "winograd-4" => ConvLowering::Winograd { tile: 4 },
+            "auto" => ConvLowering::Auto,
+            _ => panic!("not a valid conv lowering"),
+        }
+    }
+}
+
+impl fmt::Display for ConvLowering {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvLowering::Direct => write!(f, "direct"),
+            ConvLowering::Im2col => write!(f, "im2col"),
+            ConvLowering::Winograd { tile } => write!(f, "winograd-{}", tile),
+            ConvLowering::Auto => write!(f, "auto"),
+        }
+    }
+}
+
 /// Matches a [BaseOp] to an operation over inputs
 impl BaseOp {
     /// forward func
@@ -111,6 +182,7 @@ impl BaseOp {
             BaseOp::Sub => a - b,
             BaseOp::Mult => a * b,
             BaseOp::Range { .. } => b,
+            BaseOp::Sign => a * b,
         }
     }
 
@@ -124,6 +196,7 @@ impl BaseOp {
             BaseOp::Mult => "MULT",
             BaseOp::Sum => "SUM",
             BaseOp::Range { .. } => "RANGE",
+            BaseOp::Sign => "SIGN",
         }
     }
     fn query_offset_rng(&self) -> (i32, usize) {
@@ -136,6 +209,7 @@ impl BaseOp {
             BaseOp::Mult => (0, 1),
             BaseOp::Sum => (-1, 2),
             BaseOp::Range { .. } => (0, 1),
+            BaseOp::Sign => (0, 1),
         }
     }
     fn num_inputs(&self) -> usize {
@@ -148,6 +222,7 @@ impl BaseOp {
             BaseOp::Mult => 2,
             BaseOp::Sum => 1,
             BaseOp::Range { .. } => 1,
+            BaseOp::Sign => 2,
         }
     }
     fn constraint_idx(&self) -> usize {
@@ -159,6 +234,7 @@ impl BaseOp {
             BaseOp::Sub => 0,
             BaseOp::Mult => 0,
             BaseOp::Range { .. } => 0,
+            BaseOp::Sign => 0,
             BaseOp::Sum => 1,
         }
     }
@@ -177,6 +253,14 @@ pub enum LookupOp {
     Div {
         denom: eq_float::F32,
     },
+    IntDiv {
+        denom: i128,
+        scale: usize,
+    },
+    Mod {
+        denom: i128,
+        fmod: bool,
+    },
     ReLU {
         scale: usize,
     },
@@ -197,6 +281,90 @@ pub enum LookupOp {
     Tanh {
         scales: (usize, usize),
     },
+    Exp {
+        scales: (usize, usize),
+    },
+    Recip {
+        scales: (usize, usize),
+    },
+    Ln {
+        scales: (usize, usize),
+    },
+    Rsqrt {
+        scales: (usize, usize),
+    },
+    Erf {
+        scales: (usize, usize),
+    },
+    Pow {
+        scales: (usize, usize),
+        exponent: eq_float::F32,
+    },
+    IsZero,
+    Compare {
+        comparison: Comparison,
+    },
+    Floor {
+        scale: usize,
+    },
+    Ceil {
+        scale: usize,
+    },
+    Round {
+        scale: usize,
+    },
+    Tent {
+        scale: usize,
+    },
+    MaxPool2D {
+        padding: (usize, usize),
+        stride: (usize, usize),
+        pool_dims: (usize, usize),
+    },
+    AvgPool2D {
+        padding: (usize, usize),
+        stride: (usize, usize),
+        pool_dims: (usize, usize),
+        remainder: bool,
+    },
+    MaxPool3D {
+        padding: (usize, usize, usize),
+        stride: (usize, usize, usize),
+        pool_dims: (usize, usize, usize),
+    },
+    AvgPool3D {
+        padding: (usize, usize, usize),
+        stride: (usize, usize, usize),
+        pool_dims: (usize, usize, usize),
+        remainder: bool,
+    },
+    /// An element-wise lookup whose table is centered on `zero_point` rather than on 0, such that
+    /// inputs of a skewed range (eg. nonnegative ones) make use of the whole table.
+    ZeroPoint {
+        inner: Box<LookupOp>,
+        zero_point: i128,
+    },
+    /// Checks that its input lies in [0, 2^bits), against a table of its own `bits`.
+    Range {
+        bits: usize,
+    },
+    /// An element-wise lookup of the input divided by 2^shift (rounding half up), laid out by
+    /// decomposing the input into the quotient and a remainder of `shift` bits. The quotient is
+    /// looked up against the table of `inner` and the remainder against a [LookupOp::Range]
+    /// table, such that inputs spanning `bits + shift` bits need two small tables rather than
+    /// one of 2^(bits + shift) values.
+    Limbed {
+        inner: Box<LookupOp>,
+        shift: usize,
+    },
+    /// An element-wise division by the integer `denom` (rounding half up), laid out by
+    /// decomposing the input into the quotient and a remainder in [0, denom). The quotient is
+    /// looked up against the table of a division by 1, which bounds it, and both the remainder and
+    /// `denom - 1` minus the remainder against a [LookupOp::Range] table, such that the quotient
+    /// is the only one consistent with the input rather than a value trusted from the prover.
+    DivRem {
+        denom: i128,
+    },
 }
 
 impl LookupOp {
@@ -207,6 +375,12 @@ impl LookupOp {
                 &x,
                 f32::from(*denom),
             )),
+            LookupOp::IntDiv { denom, scale } => {
+                Ok(tensor::ops::nonlinearities::int_div(&x, *denom, *scale))
+            }
+            LookupOp::Mod { denom, fmod } => {
+                Ok(tensor::ops::nonlinearities::modulo(&x, *denom, *fmod))
+            }
             LookupOp::ReLU { scale } => {
                 Ok(tensor::ops::nonlinearities::leakyrelu(&x, *scale, 0_f32))
             }
@@ -227,25 +401,538 @@ impl LookupOp {
             LookupOp::Tanh { scales } => {
                 Ok(tensor::ops::nonlinearities::tanh(&x, scales.0, scales.1))
             }
+            LookupOp::Exp { scales } => {
+                Ok(tensor::ops::nonlinearities::exp(&x, scales.0, scales.1))
+            }
+            LookupOp::Recip { scales } => {
+                Ok(tensor::ops::nonlinearities::recip(&x, scales.0, scales.1))
+            }
+            LookupOp::Ln { scales } => Ok(tensor::ops::nonlinearities::ln(&x, scales.0, scales.1)),
+            LookupOp::Rsqrt { scales } => {
+                Ok(tensor::ops::nonlinearities::rsqrt(&x, scales.0, scales.1))
+            }
+            LookupOp::Erf { scales } => {
+                Ok(tensor::ops::nonlinearities::erf(&x, scales.0, scales.1))
+            }
+            LookupOp::Pow { scales, exponent } => Ok(tensor::ops::nonlinearities::pow(
+                &x, scales.0, scales.1, exponent.0,
+            )),
+            LookupOp::IsZero => Ok(tensor::ops::nonlinearities::is_zero(&x)),
+            LookupOp::Compare { comparison } => {
+                Ok(tensor::ops::nonlinearities::compare_to_zero(&x, comparison))
+            }
+            LookupOp::Floor { scale } => Ok(tensor::ops::nonlinearities::floor(&x, *scale)),
+            LookupOp::Ceil { scale } => Ok(tensor::ops::nonlinearities::ceil(&x, *scale)),
+            LookupOp::Round { scale } => Ok(tensor::ops::nonlinearities::round(&x, *scale)),
+            LookupOp::Tent { scale } => Ok(tensor::ops::nonlinearities::tent(&x, *scale)),
+            LookupOp::MaxPool2D {
+                padding,
+                stride,
+                pool_dims,
+            } => tensor::ops::max_pool2d(&x, *padding, *stride, *pool_dims),
+            LookupOp::AvgPool2D {
+                padding,
+                stride,
+                pool_dims,
+                remainder,
+            } => LookupOp::division(pool_dims.0 * pool_dims.1, *remainder)
+                .f(tensor::ops::sumpool(&x, *padding, *stride, *pool_dims)?),
+            LookupOp::MaxPool3D {
+                padding,
+                stride,
+                pool_dims,
+            } => tensor::ops::max_pool3d(&x, *padding, *stride, *pool_dims),
+            LookupOp::AvgPool3D {
+                padding,
+                stride,
+                pool_dims,
+                remainder,
+            } => LookupOp::division(pool_dims.0 * pool_dims.1 * pool_dims.2, *remainder)
+                .f(tensor::ops::sumpool3d(&x, *padding, *stride, *pool_dims)?),
+            LookupOp::ZeroPoint { inner, .. } => inner.f(x),
+            LookupOp::Range { .. } => Ok(x),
+            LookupOp::Limbed { inner, shift } => {
+                let (half, base) = (1i128 << (shift - 1), 1i128 << shift);
+                inner.f(x.map(|x| (x + half).div_euclid(base)))
+            }
+            LookupOp::DivRem { denom } => Ok(x.map(|x| (x + denom / 2).div_euclid(*denom))),
         }
     }
 
-    fn as_str(&self) -> &'static str {
+    /// The name of the op, which also names its table.
+    pub fn as_str(&self) -> &'static str {
         match self {
             LookupOp::Div { .. } => "DIV",
+            LookupOp::IntDiv { .. } => "INT_DIV",
+            LookupOp::Mod { .. } => "MOD",
             LookupOp::ReLU { .. } => "RELU",
             LookupOp::LeakyReLU { .. } => "LEAKY_RELU",
             LookupOp::PReLU { .. } => "PRELU",
             LookupOp::Sigmoid { .. } => "SIGMOID",
             LookupOp::Sqrt { .. } => "SQRT",
             LookupOp::Tanh { .. } => "TANH",
+            LookupOp::Exp { .. } => "EXP",
+            LookupOp::Recip { .. } => "RECIP",
+            LookupOp::Ln { .. } => "LN",
+            LookupOp::Rsqrt { .. } => "RSQRT",
+            LookupOp::Erf { .. } => "ERF",
+            LookupOp::Pow { .. } => "POW",
+            LookupOp::IsZero => "IS_ZERO",
+            LookupOp::Compare { .. } => "COMPARE",
+            LookupOp::Floor { .. } => "FLOOR",
+            LookupOp::Ceil { .. } => "CEIL",
+            LookupOp::Round { .. } => "ROUND",
+            LookupOp::Tent { .. } => "TENT",
+            LookupOp::MaxPool2D { .. } => "MAX_POOL2D",
+            LookupOp::AvgPool2D { .. } => "AVG_POOL2D",
+            LookupOp::MaxPool3D { .. } => "MAX_POOL3D",
+            LookupOp::AvgPool3D { .. } => "AVG_POOL3D",
+            LookupOp::ZeroPoint { inner, .. } => inner.as_str(),
+            LookupOp::Range { .. } => "RANGE",
+            LookupOp::Limbed { .. } => "LIMBED",
+            LookupOp::DivRem { .. } => "DIV_REM",
+        }
+    }
+
+    /// The value the op's table is centered on.
+    pub fn zero_point(&self) -> i128 {
+        match self {
+            LookupOp::ZeroPoint { zero_point, .. } => *zero_point,
+            LookupOp::Range { bits } => 1 << (bits - 1),
+            _ => 0,
         }
     }
 
     /// a value which is always in the table
     pub fn default_pair<F: FieldExt>(&self) -> (F, F) {
-        let x = vec![0_i128].into_iter().into();
-        (F::zero(), i128_to_felt(self.f(x).unwrap()[0]))
+        let zero_point = self.zero_point();
+        let x = vec![zero_point].into_iter().into();
+        (
+            i128_to_felt(zero_point),
+            i128_to_felt(self.f(x).unwrap()[0]),
+        )
+    }
+
+    /// The tables the op is looked up against. Pooling and PReLU are composed of simpler lookups,
+    /// and limbed lookups look up their quotient and remainder in separate tables.
+    pub fn table_ops(&self) -> Vec<LookupOp> {
+        match self {
+            LookupOp::PReLU { scale, .. } => vec![LookupOp::ReLU { scale: *scale }],
+            LookupOp::MaxPool2D { .. } | LookupOp::MaxPool3D { .. } => {
+                vec![LookupOp::ReLU { scale: 1 }]
+            }
+            LookupOp::AvgPool2D {
+                pool_dims,
+                remainder,
+                ..
+            } => LookupOp::division(pool_dims.0 * pool_dims.1, *remainder).table_ops(),
+            LookupOp::AvgPool3D {
+                pool_dims,
+                remainder,
+                ..
+            } => {
+                LookupOp::division(pool_dims.0 * pool_dims.1 * pool_dims.2, *remainder).table_ops()
+            }
+            LookupOp::Limbed { inner, shift } => inner
+                .table_ops()
+                .into_iter()
+                .chain([LookupOp::Range { bits: *shift }])
+                .collect(),
+            LookupOp::DivRem { denom } => vec![
+                LookupOp::Div {
+                    denom: eq_float::F32(1.0),
+                },
+                LookupOp::Range {
+                    bits: Self::remainder_bits(*denom),
+                },
+            ],
+            op => vec![op.clone()],
+        }
+    }
+
+    /// The division by the integer `denom`, with a remainder ([LookupOp::DivRem]) if `remainder`
+    /// is set or otherwise by a single lookup.
+    pub fn division(denom: usize, remainder: bool) -> LookupOp {
+        match remainder && denom > 1 {
+            true => LookupOp::DivRem {
+                denom: denom as i128,
+            },
+            false => LookupOp::Div {
+                denom: eq_float::F32(denom as f32),
+            },
+        }
+    }
+
+    /// The number of bits of the [LookupOp::Range] table checking the remainders of a division
+    /// by `denom`, which lie in [0, denom).
+    pub fn remainder_bits(denom: i128) -> usize {
+        (i128::BITS - (denom - 1).leading_zeros()) as usize
+    }
+
+    /// The number of bits of the op's table, given the `bits` of the model's tables.
+    pub fn table_bits(&self, bits: usize) -> usize {
+        match self {
+            LookupOp::Range { bits } => *bits,
+            _ => bits,
+        }
+    }
+
+    /// Number of cells laid out to produce an output of length `len`
+    pub fn circuit_len(&self, input_shapes: Vec<Vec<usize>>, len: usize) -> usize {
+        match self {
+            // each window comparison lays out a sub, a relu lookup, and an add
+            LookupOp::MaxPool2D { pool_dims, .. } => 3 * len * (pool_dims.0 * pool_dims.1 - 1),
+            LookupOp::MaxPool3D { pool_dims, .. } => {
+                3 * len * (pool_dims.0 * pool_dims.1 * pool_dims.2 - 1)
+            }
+            // each window element is added pairwise before the division
+            LookupOp::AvgPool3D {
+                pool_dims,
+                remainder,
+                ..
+            } => {
+                let window = pool_dims.0 * pool_dims.1 * pool_dims.2;
+                len * (window - 1) + LookupOp::division(window, *remainder).circuit_len(vec![], len)
+            }
+            // the sumpool is laid out before the division
+            LookupOp::AvgPool2D {
+                padding,
+                stride,
+                pool_dims,
+                remainder,
+            } => {
+                let layout_shape = Op::SumPool {
+                    padding: *padding,
+                    stride: *stride,
+                    kernel_shape: *pool_dims,
+                }
+                .circuit_shapes(input_shapes);
+                layout_shape.last().unwrap()
+                    + LookupOp::division(pool_dims.0 * pool_dims.1, *remainder)
+                        .circuit_len(vec![], len)
+            }
+            // the quotient is assigned and looked up, recomposed with an add, a mult and a sub,
+            // and the remainder is range checked
+            LookupOp::Limbed { .. } => 6 * len,
+            // as for limbed lookups, with the slack of the remainder subtracted and range checked
+            LookupOp::DivRem { .. } => 8 * len,
+            _ => len,
+        }
+    }
+}
+
+/// A reference to a value within a [Subgraph].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SubgraphInput {
+    /// The k-th input of the subgraph
+    Input(usize),
+    /// The output of the n-th node of the subgraph
+    Node(usize),
+    /// A constant folded into the subgraph
+    Const {
+        /// flattened values
+        values: Vec<i128>,
+        /// dims of the constant
+        dims: Vec<usize>,
+    },
+}
+
+/// A single op within a [Subgraph].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubgraphNode {
+    /// the op applied
+    pub op: OpKind,
+    /// the op's inputs
+    pub inputs: Vec<SubgraphInput>,
+    /// dims of the op's output
+    pub out_dims: Vec<usize>,
+}
+
+/// A topologically ordered graph of ops, used for the bodies of control flow ops.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Subgraph {
+    /// the subgraph's ops
+    pub nodes: Vec<SubgraphNode>,
+    /// the subgraph's outputs
+    pub outputs: Vec<SubgraphInput>,
+}
+
+impl Subgraph {
+    fn resolve<T: Clone>(
+        input: &SubgraphInput,
+        inputs: &[T],
+        results: &[T],
+        constant: impl Fn(&[i128], &[usize]) -> Result<T, TensorError>,
+    ) -> Result<T, TensorError> {
+        match input {
+            SubgraphInput::Input(k) => inputs.get(*k).cloned(),
+            SubgraphInput::Node(n) => results.get(*n).cloned(),
+            SubgraphInput::Const { values, dims } => Some(constant(values, dims)?),
+        }
+        .ok_or_else(|| TensorError::DimMismatch("subgraph".to_string()))
+    }
+
+    /// Evaluates each op of the subgraph in turn. Calls `node` on each op and its resolved inputs,
+    /// and `constant` to materialize folded constants.
+    pub fn walk<T: Clone, E: From<TensorError>>(
+        &self,
+        inputs: &[T],
+        constant: impl Fn(&[i128], &[usize]) -> Result<T, TensorError>,
+        mut node: impl FnMut(&SubgraphNode, Vec<T>) -> Result<T, E>,
+    ) -> Result<Vec<T>, E> {
+        let mut results: Vec<T> = vec![];
+        for n in self.nodes.iter() {
+            let values = n
+                .inputs
+                .iter()
+                .map(|i| Self::resolve(i, inputs, &results, &constant))
+                .collect::<Result<Vec<_>, _>>()?;
+            results.push(node(n, values)?);
+        }
+        Ok(self
+            .outputs
+            .iter()
+            .map(|o| Self::resolve(o, inputs, &results, &constant))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Matches each op of the subgraph to an operation in the `tensor::ops` module.
+    pub fn f(&self, inputs: &[Tensor<i128>]) -> Result<Vec<Tensor<i128>>, TensorError> {
+        self.walk(
+            inputs,
+            |values, dims| Tensor::new(Some(values), dims),
+            |node, values| match &node.op {
+                OpKind::Poly(op) => op.f(values),
+                OpKind::Lookup(op) => op.f(values[0].clone()),
+                _ => Err(TensorError::WrongMethod),
+            },
+        )
+    }
+
+    /// Whether any of the subgraph's ops looks up values in tables witnessed from its inputs
+    pub fn requires_dynamic_lookup(&self) -> bool {
+        self.nodes
+            .iter()
+            .any(|node| matches!(&node.op, OpKind::Poly(op) if op.requires_dynamic_lookup()))
+    }
+
+    /// The lookup tables the subgraph's ops are looked up against
+    pub fn required_lookups(&self) -> Vec<LookupOp> {
+        self.nodes
+            .iter()
+            .flat_map(|node| match &node.op {
+                OpKind::Poly(op) => op.required_lookups(),
+                OpKind::Lookup(op) => op.table_ops(),
+                _ => vec![],
+            })
+            .unique()
+            .collect()
+    }
+
+    fn dims(&self, input: &SubgraphInput, input_shapes: &[Vec<usize>]) -> Vec<usize> {
+        match input {
+            SubgraphInput::Input(k) => input_shapes[*k].clone(),
+            SubgraphInput::Node(n) => self.nodes[*n].out_dims.clone(),
+            SubgraphInput::Const { dims, .. } => dims.clone(),
+        }
+    }
+
+    /// Dims of the subgraph's outputs
+    pub fn output_shapes(&self, input_shapes: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        self.outputs
+            .iter()
+            .map(|o| self.dims(o, input_shapes))
+            .collect()
+    }
+
+    /// Number of cells laid out by the subgraph's ops
+    pub fn circuit_len(&self, input_shapes: &[Vec<usize>]) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| {
+                let in_dims = node
+                    .inputs
+                    .iter()
+                    .map(|i| self.dims(i, input_shapes))
+                    .collect_vec();
+                match &node.op {
+                    OpKind::Poly(op) => *op.circuit_shapes(in_dims).last().unwrap(),
+                    OpKind::Lookup(op) => {
+                        op.circuit_len(in_dims, node.out_dims.iter().product::<usize>())
+                    }
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+}
+
+/// How an input of a [ScanParams] body is bound at each iteration.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ScanInput {
+    /// The whole of the op's input at this index, at every iteration
+    Full(usize),
+    /// A state carried across iterations, initialized from the op's input at this index.
+    /// States are the body's leading outputs, in order.
+    State(usize),
+    /// One slice along `axis` of the op's input at `slot` per iteration, keeping the axis with a
+    /// length of 1
+    Scan {
+        /// index of the op's input
+        slot: usize,
+        /// the axis scanned over
+        axis: usize,
+        /// whether to scan from the last slice
+        reverse: bool,
+    },
+}
+
+/// The output of a [ScanParams] op.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ScanOutput {
+    /// The value of this body output after the last iteration
+    Last(usize),
+    /// This body output at each iteration, concatenated along `axis`
+    Stacked {
+        /// index of the body output
+        output: usize,
+        /// the axis the iterations are concatenated along
+        axis: usize,
+        /// whether to stack from the last slice
+        reverse: bool,
+    },
+}
+
+/// A body unrolled over a fixed number of iterations.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScanParams {
+    /// the body applied at each iteration
+    pub body: Subgraph,
+    /// how the body's inputs are bound
+    pub inputs: Vec<ScanInput>,
+    /// the op's output
+    pub output: ScanOutput,
+    /// the number of iterations
+    pub iterations: usize,
+}
+
+impl ScanParams {
+    /// Number of states carried across iterations
+    pub fn num_states(&self) -> usize {
+        self.inputs
+            .iter()
+            .filter(|i| matches!(i, ScanInput::State(_)))
+            .count()
+    }
+
+    /// The slice index of the i-th iteration
+    pub fn step(&self, i: usize, reverse: bool) -> usize {
+        match reverse {
+            true => self.iterations - 1 - i,
+            false => i,
+        }
+    }
+
+    /// Dims of the body's inputs, given the dims of the op's inputs
+    pub fn body_shapes(&self, input_shapes: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        self.inputs
+            .iter()
+            .map(|i| match i {
+                ScanInput::Full(slot) | ScanInput::State(slot) => input_shapes[*slot].clone(),
+                ScanInput::Scan { slot, axis, .. } => {
+                    let mut dims = input_shapes[*slot].clone();
+                    dims[*axis] = 1;
+                    dims
+                }
+            })
+            .collect()
+    }
+
+    /// Unrolls the body over the op's inputs. `slice` takes a slice along an axis, and `stack`
+    /// concatenates per-iteration outputs along an axis.
+    pub fn unroll<T: Clone, E: From<TensorError>>(
+        &self,
+        inputs: &[T],
+        slice: impl Fn(&T, usize, usize) -> Result<T, E>,
+        stack: impl Fn(&[T], usize) -> Result<T, E>,
+        mut body: impl FnMut(&Subgraph, &[T]) -> Result<Vec<T>, E>,
+    ) -> Result<T, E> {
+        let num_states = self.num_states();
+        let mut states = self
+            .inputs
+            .iter()
+            .filter_map(|i| match i {
+                ScanInput::State(slot) => Some(inputs[*slot].clone()),
+                _ => None,
+            })
+            .collect_vec();
+        let mut stacked = vec![];
+        let mut last = vec![];
+        for i in 0..self.iterations {
+            let mut state_iter = states.iter();
+            let body_inputs = self
+                .inputs
+                .iter()
+                .map(|input| match input {
+                    ScanInput::Full(slot) => Ok(inputs[*slot].clone()),
+                    ScanInput::State(_) => Ok(state_iter.next().unwrap().clone()),
+                    ScanInput::Scan {
+                        slot,
+                        axis,
+                        reverse,
+                    } => slice(&inputs[*slot], *axis, self.step(i, *reverse)),
+                })
+                .collect::<Result<Vec<_>, E>>()?;
+            let outputs = body(&self.body, &body_inputs)?;
+            if outputs.len() < num_states {
+                return Err(TensorError::DimMismatch("scan".to_string()).into());
+            }
+            states = outputs[..num_states].to_vec();
+            if let ScanOutput::Stacked { output, .. } = &self.output {
+                stacked.push(outputs[*output].clone());
+            }
+            last = outputs;
+        }
+        match &self.output {
+            ScanOutput::Last(output) => last
+                .get(*output)
+                .cloned()
+                .ok_or_else(|| TensorError::DimMismatch("scan".to_string()).into()),
+            ScanOutput::Stacked { axis, reverse, .. } => {
+                if *reverse {
+                    stacked.reverse();
+                }
+                stack(&stacked, *axis)
+            }
+        }
+    }
+
+    /// Matches the unrolled body to operations in the `tensor::ops` module.
+    pub fn f(&self, inputs: &[Tensor<i128>]) -> Result<Tensor<i128>, TensorError> {
+        self.unroll(
+            inputs,
+            |t, axis, i| {
+                let indices = t
+                    .dims()
+                    .iter()
+                    .enumerate()
+                    .map(|(d, n)| if d == axis { i..i + 1 } else { 0..*n })
+                    .collect_vec();
+                t.get_slice(&indices)
+            },
+            |ts, axis| {
+                let mut dims = ts[0].dims().to_vec();
+                let values = ts.iter().flat_map(|t| t.iter().cloned()).collect_vec();
+                let mut res = Tensor::new(Some(&values), &[&[ts.len()], &dims[..]].concat())?;
+                let mut order = (1..=dims.len()).collect_vec();
+                order.insert(axis, 0);
+                res = res.permute(&order)?;
+                dims[axis] *= ts.len();
+                res.reshape(&dims);
+                Ok(res)
+            },
+            |body, values| body.f(values),
+        )
     }
 }
 
@@ -255,10 +942,29 @@ impl LookupOp {
 pub enum Op {
     Dot,
     Matmul,
+    Einsum(String),
     Affine,
+    BiasedMatmul,
     Conv {
         padding: (usize, usize),
         stride: (usize, usize),
+        group: usize,
+        lowering: ConvLowering,
+    },
+    ConvReLU {
+        padding: (usize, usize),
+        stride: (usize, usize),
+        scale: usize,
+        lowering: ConvLowering,
+    },
+    Conv3D {
+        padding: (usize, usize, usize),
+        stride: (usize, usize, usize),
+    },
+    DeConv {
+        padding: (usize, usize),
+        output_padding: (usize, usize),
+        stride: (usize, usize),
     },
     SumPool {
         padding: (usize, usize),
@@ -283,6 +989,145 @@ pub enum Op {
         scale: Vec<(usize, usize)>,
     },
     RangeCheck(i32),
+    LSTM {
+        hidden_size: usize,
+        scale: usize,
+    },
+    GRU {
+        hidden_size: usize,
+        scale: usize,
+        linear_before_reset: bool,
+    },
+    RNN {
+        hidden_size: usize,
+        scale: usize,
+    },
+    Softmax {
+        scales: (usize, usize),
+    },
+    Attention {
+        scales: (usize, usize),
+    },
+    Gather {
+        axis: usize,
+        indices: Vec<usize>,
+        index_dims: Vec<usize>,
+    },
+    Embedding {
+        scale: usize,
+    },
+    GatherElements {
+        axis: usize,
+        scale: usize,
+    },
+    GatherND {
+        scale: usize,
+    },
+    ScatterElements {
+        axis: usize,
+        scale: usize,
+    },
+    ScatterND {
+        scale: usize,
+    },
+    TopK {
+        k: usize,
+        axis: usize,
+        largest: bool,
+    },
+    ArgMax {
+        axis: usize,
+        keep_dims: bool,
+    },
+    ArgMin {
+        axis: usize,
+        keep_dims: bool,
+    },
+    ResizeNearest {
+        indices: Vec<Vec<usize>>,
+    },
+    ResizeLinear {
+        taps: Vec<Vec<(usize, usize, usize)>>,
+        scale: usize,
+    },
+    PadWithFill {
+        pads: Vec<(usize, usize)>,
+        fill: PadFill,
+    },
+    Tile {
+        repeats: Vec<usize>,
+    },
+    Expand {
+        shape: Vec<usize>,
+    },
+    Slice {
+        starts: Vec<i64>,
+        ends: Vec<i64>,
+        steps: Vec<i64>,
+    },
+    Transpose {
+        perm: Vec<usize>,
+    },
+    DepthToSpace {
+        blocksize: usize,
+        mode: DepthToSpaceMode,
+    },
+    SpaceToDepth {
+        blocksize: usize,
+    },
+    Reduce {
+        reduction: Reduction,
+        axes: Vec<usize>,
+        keep_dims: bool,
+    },
+    CumSum {
+        axis: usize,
+        exclusive: bool,
+        reverse: bool,
+    },
+    Clip {
+        lower: bool,
+        upper: bool,
+    },
+    Compare {
+        comparison: Comparison,
+    },
+    Where,
+    Logic {
+        logic: Logic,
+    },
+    Max,
+    Min,
+    Sign,
+    Abs,
+    Bitwise {
+        bitwise: Bitwise,
+        bits: usize,
+        scale: usize,
+    },
+    NonMaxSuppression {
+        params: NmsParams,
+    },
+    RoiAlign {
+        rois: Vec<RoiTaps>,
+        max: bool,
+        scale: usize,
+    },
+    GridSample {
+        align_corners: bool,
+        border: bool,
+        scale: usize,
+    },
+    TreeEnsemble {
+        ensemble: TreeEnsemble,
+    },
+    If {
+        then_branch: Subgraph,
+        else_branch: Subgraph,
+    },
+    Scan {
+        params: ScanParams,
+    },
 }
 
 impl Op {
@@ -303,7 +1148,74 @@ impl Op {
             Op::GlobalSumPool => unreachable!("should be handled by sumpool"),
             Op::ScaleAndShift => input_shapes.iter().map(|x| x.iter().product()).collect(),
             Op::BatchNorm => input_shapes.iter().map(|x| x.iter().product()).collect(),
-            Op::Conv { padding, stride } => {
+            Op::Conv {
+                padding,
+                stride,
+                group,
+                lowering,
+            } if *group > 1 => {
+                let image_dims = &input_shapes[0];
+                let kernel_dims = &input_shapes[1];
+
+                // each group is laid out as an independent conv
+                let op = Op::Conv {
+                    padding: *padding,
+                    stride: *stride,
+                    group: 1,
+                    lowering: *lowering,
+                };
+                let output_len = op.circuit_shapes(vec![
+                    vec![image_dims[0] / group, image_dims[1], image_dims[2]],
+                    vec![
+                        kernel_dims[0] / group,
+                        kernel_dims[1],
+                        kernel_dims[2],
+                        kernel_dims[3],
+                    ],
+                ]);
+
+                vec![*output_len.last().unwrap() * group; 2]
+            }
+            Op::Conv {
+                padding,
+                lowering: ConvLowering::Winograd { tile },
+                ..
+            } => {
+                let (image_dims, kernel_dims) = (&input_shapes[0], &input_shapes[1]);
+                let (output_channels, input_channels) = (kernel_dims[0], kernel_dims[1]);
+                let out_height = image_dims[1] + 2 * padding.0 - 2;
+                let out_width = image_dims[2] + 2 * padding.1 - 2;
+                let num_tiles = ((out_height + tile - 1) / tile) * ((out_width + tile - 1) / tile);
+                let alpha = tile + 2;
+
+                // each transform is a pair of einsums, laid out as matmuls of M x K x N elements
+                let input_transform = 2 * input_channels * num_tiles * alpha.pow(3);
+                let kernel_transform = output_channels * input_channels * alpha * 3 * (3 + alpha);
+                let products = output_channels * input_channels * num_tiles * alpha * alpha;
+                let output_transform = output_channels * num_tiles * tile * alpha * (alpha + tile);
+                // the exact division of each output, and the addition of the bias
+                let division = output_channels * num_tiles * tile * tile;
+                let bias = match input_shapes.len() {
+                    3 => output_channels * out_height * out_width,
+                    _ => 0,
+                };
+
+                vec![
+                    input_transform
+                        + kernel_transform
+                        + products
+                        + output_transform
+                        + division
+                        + bias;
+                    2
+                ]
+            }
+            Op::Conv {
+                padding,
+                stride,
+                lowering,
+                ..
+            } => {
                 let image_dims = &input_shapes[0];
                 let kernel_dims = &input_shapes[1];
 
@@ -322,18 +1234,121 @@ impl Op {
                 let vert_slides = (padded_height - kernel_height) / stride.0 + 1;
                 let horz_slides = (padded_width - kernel_width) / stride.1 + 1;
 
-                let input_shapes = vec![
-                    vec![
-                        output_channels * vert_slides * horz_slides,
-                        (padded_height * padded_width * image_dims[0] + 1),
+                let input_shapes = match lowering {
+                    ConvLowering::Im2col => {
+                        let patch_len = image_dims[0] * kernel_height * kernel_width + 1;
+                        vec![
+                            vec![output_channels, patch_len],
+                            vec![patch_len, vert_slides * horz_slides],
+                        ]
+                    }
+                    _ => vec![
+                        vec![
+                            output_channels * vert_slides * horz_slides,
+                            (padded_height * padded_width * image_dims[0] + 1),
+                        ],
+                        vec![(padded_height * padded_width * image_dims[0] + 1), 1],
                     ],
-                    vec![(padded_height * padded_width * image_dims[0] + 1), 1],
-                ];
+                };
                 let op = Op::Matmul;
                 let output_len = op.circuit_shapes(input_shapes);
 
                 vec![*output_len.last().unwrap(); 2]
             }
+            Op::ConvReLU {
+                padding,
+                stride,
+                lowering,
+                ..
+            } => {
+                let (image_dims, kernel_dims) = (&input_shapes[0], &input_shapes[1]);
+                let slides = |len: usize, kernel: usize, padding: usize, stride: usize| {
+                    (len + 2 * padding - kernel) / stride + 1
+                };
+                let output_len = kernel_dims[0]
+                    * slides(image_dims[1], kernel_dims[2], padding.0, stride.0)
+                    * slides(image_dims[2], kernel_dims[3], padding.1, stride.1);
+
+                let op = Op::Conv {
+                    padding: *padding,
+                    stride: *stride,
+                    group: 1,
+                    lowering: *lowering,
+                };
+                let conv_len = op.circuit_shapes(input_shapes);
+
+                // the conv, then a row per output holding its relu
+                vec![*conv_len.last().unwrap() + output_len; 2]
+            }
+            Op::Conv3D { padding, stride } => {
+                let image_dims = &input_shapes[0];
+                let kernel_dims = &input_shapes[1];
+
+                let (output_channels, input_channels, kernel_depth, kernel_height, kernel_width) = (
+                    kernel_dims[0],
+                    kernel_dims[1],
+                    kernel_dims[2],
+                    kernel_dims[3],
+                    kernel_dims[4],
+                );
+
+                let depth_slides = (image_dims[1] + 2 * padding.0 - kernel_depth) / stride.0 + 1;
+
+                // each output depth slice is laid out as a 2D conv over the stacked depth window
+                let op = Op::Conv {
+                    padding: (padding.1, padding.2),
+                    stride: (stride.1, stride.2),
+                    group: 1,
+                    lowering: ConvLowering::Direct,
+                };
+                let output_len = op.circuit_shapes(vec![
+                    vec![input_channels * kernel_depth, image_dims[2], image_dims[3]],
+                    vec![
+                        output_channels,
+                        input_channels * kernel_depth,
+                        kernel_height,
+                        kernel_width,
+                    ],
+                ]);
+
+                vec![*output_len.last().unwrap() * depth_slides; 2]
+            }
+            Op::DeConv {
+                padding,
+                output_padding,
+                stride,
+            } => {
+                let image_dims = &input_shapes[0];
+                let kernel_dims = &input_shapes[1];
+
+                let (input_channels, output_channels, kernel_height, kernel_width) = (
+                    kernel_dims[0],
+                    kernel_dims[1],
+                    kernel_dims[2],
+                    kernel_dims[3],
+                );
+
+                // a deconv is laid out as an unpadded, stride 1, conv over the expanded image
+                let expanded_height =
+                    (image_dims[1] - 1) * stride.0 + 2 * kernel_height - 1 - 2 * padding.0
+                        + output_padding.0;
+                let expanded_width =
+                    (image_dims[2] - 1) * stride.1 + 2 * kernel_width - 1 - 2 * padding.1
+                        + output_padding.1;
+
+                let op = Op::Conv {
+                    padding: (0, 0),
+                    stride: (1, 1),
+                    group: 1,
+                    lowering: ConvLowering::Direct,
+                };
+                let output_len = op.circuit_shapes(vec![
+                    vec![image_dims[0], expanded_height, expanded_width],
+                    vec![output_channels, input_channels, kernel_height, kernel_width],
+                ]);
+
+                vec![*output_len.last().unwrap(); 2]
+            }
             Op::SumPool {
                 padding,
                 stride,
@@ -372,8 +1387,316 @@ impl Op {
 
                 vec![output_len; 2]
             }
+            Op::BiasedMatmul => {
+                // laid out as an affine, with the bias appended to the kernel
+                let output_len = input_shapes[0][0] * input_shapes[1][1] * (input_shapes[1][0] + 1);
+                vec![output_len; 2]
+            }
+            Op::Einsum(equation) => {
+                // laid out as a batched matmul, which assigns B x M x K x N elements
+                let output_len = tensor::ops::EinsumIndices::new(equation)
+                    .and_then(|indices| indices.sizes(&input_shapes))
+                    .map(|sizes| sizes.values().product())
+                    .unwrap_or(0);
+
+                vec![output_len; 2]
+            }
             Op::Rescaled { inner, .. } => inner.circuit_shapes(input_shapes),
             Op::RangeCheck(..) => input_shapes.iter().map(|x| x.iter().product()).collect(),
+            Op::LSTM { hidden_size, .. } => {
+                let (seq_len, input_size) = (input_shapes[0][0], input_shapes[0][1]);
+                let h = *hidden_size;
+                // the first step has no recurrent matmul nor forget gate product
+                let first_step = 4 * h * input_size + 13 * h;
+                let step = 4 * h * (input_size + h) + 19 * h;
+
+                vec![first_step + seq_len.saturating_sub(1) * step; 2]
+            }
+            Op::GRU { hidden_size, .. } => {
+                let (seq_len, input_size) = (input_shapes[0][0], input_shapes[0][1]);
+                let h = *hidden_size;
+                // without an initial hidden state the first step has no recurrent matmul
+                let step = 3 * h * (input_size + h) + 18 * h;
+                let first_step = match input_shapes.len() {
+                    5 => step,
+                    _ => 3 * h * input_size + 14 * h,
+                };
+
+                vec![first_step + seq_len.saturating_sub(1) * step; 2]
+            }
+            Op::RNN { hidden_size, .. } => {
+                let (seq_len, input_size) = (input_shapes[0][0], input_shapes[0][1]);
+                let h = *hidden_size;
+                let step = h * (input_size + h) + 3 * h;
+                let first_step = match input_shapes.len() {
+                    5 => step,
+                    _ => h * input_size + 2 * h,
+                };
+
+                vec![first_step + seq_len.saturating_sub(1) * step; 2]
+            }
+            Op::Softmax { .. } => {
+                let len: usize = input_shapes[0].iter().product();
+                let rows = len / input_shapes[0].last().copied().unwrap_or(1).max(1);
+                // exponentials, their sums, products and divisions, and a reciprocal per row
+                vec![4 * len + rows; 2]
+            }
+            Op::Attention { .. } => {
+                let (seq_len, d) = (input_shapes[0][0], input_shapes[0][1]);
+                let d_v = input_shapes[2][1];
+                let scores_len = seq_len * seq_len;
+                // the scores and output matmuls, and the softmax over the scores
+                vec![scores_len * (d + d_v) + 4 * scores_len + seq_len; 2]
+            }
+            Op::Gather { .. } => vec![0; 2],
+            Op::Embedding { .. } => {
+                let embedding_dim = input_shapes[0][1];
+                let num_ids: usize = input_shapes[1].iter().product();
+                // the bounded row of each id and its product with the scale, the key of each
+                // output element and its lookup, the keyed table taking rows of the table columns
+                vec![6 * num_ids + 3 * embedding_dim * num_ids; 2]
+            }
+            Op::GatherElements { axis, .. } => {
+                let len = input_shapes[0][*axis];
+                let num_indices: usize = input_shapes[1].iter().product();
+                // the one-hot encoding of each index over the positive and negative positions,
+                // and its dot product with the indexed row
+                vec![num_indices * len * 6; 2]
+            }
+            Op::GatherND { .. } => {
+                let index_dims = &input_shapes[1];
+                let k = index_dims[index_dims.len() - 1];
+                let num_slices: usize = input_shapes[0][..k].iter().product();
+                let slice_len: usize = input_shapes[0][k..].iter().product();
+                let num_indices = index_dims.iter().product::<usize>() / k;
+                let axes_len: usize = input_shapes[0][..k].iter().sum();
+                // the one-hot encoding of each element of an index, their outer product, and its
+                // matmul with the input
+                vec![num_indices * (5 * axes_len + num_slices * (k - 1 + slice_len)); 2]
+            }
+            Op::ScatterElements { axis, .. } => {
+                let len = input_shapes[0][*axis];
+                let num_indices: usize = input_shapes[1].iter().product();
+                // the one-hot encoding of each index over the positive and negative positions,
+                // and the write of its update into its row
+                vec![num_indices * len * 8; 2]
+            }
+            Op::ScatterND { .. } => {
+                let index_dims = &input_shapes[1];
+                let k = index_dims[index_dims.len() - 1];
+                let num_slices: usize = input_shapes[0][..k].iter().product();
+                let input_len: usize = input_shapes[0].iter().product();
+                let num_indices = index_dims.iter().product::<usize>() / k;
+                let axes_len: usize = input_shapes[0][..k].iter().sum();
+                // the one-hot encoding of each element of an index, their outer product, and the
+                // write of its update
+                vec![num_indices * (5 * axes_len + (k - 1) * num_slices + 3 * input_len); 2]
+            }
+            Op::TopK { k, axis, .. } => {
+                let len = input_shapes[0][*axis];
+                let num_rows = input_shapes[0].iter().product::<usize>() / len;
+                // k bubble passes, each compare-exchange taking a sub, a relu, an add and a sub
+                let num_exchanges: usize = (0..*k).map(|i| len - 1 - i).sum();
+                vec![num_rows * num_exchanges * 4; 2]
+            }
+            Op::ArgMax { axis, .. } | Op::ArgMin { axis, .. } => {
+                let len = input_shapes[0][*axis];
+                let num_rows = input_shapes[0].iter().product::<usize>() / len;
+                // the witnessed index and extremum, the bounds of the index, the lookup of the
+                // extremum at the index in its keyed row (which takes rows of the table columns),
+                // and its comparison to each entry
+                vec![num_rows * (5 * len + 7); 2]
+            }
+            Op::ResizeNearest { .. } => vec![0; 2],
+            Op::PadWithFill { .. } => vec![0; 2],
+            Op::Tile { .. } => vec![0; 2],
+            Op::Expand { .. } => vec![0; 2],
+            Op::Slice { .. } => vec![0; 2],
+            Op::Transpose { .. } => vec![0; 2],
+            Op::DepthToSpace { .. } => vec![0; 2],
+            Op::SpaceToDepth { .. } => vec![0; 2],
+            Op::Reduce {
+                reduction, axes, ..
+            } => {
+                let len: usize = axes.iter().map(|axis| input_shapes[0][*axis]).product();
+                let num_rows = input_shapes[0].iter().product::<usize>() / len;
+                match reduction {
+                    Reduction::Sum => vec![num_rows * len; 2],
+                    // the sums and then the divisions
+                    Reduction::Mean { denom, remainder } => {
+                        let division = LookupOp::division(*denom, *remainder);
+                        vec![num_rows * len + division.circuit_len(vec![], num_rows); 2]
+                    }
+                    // a sub, a relu and an add (or sub) per step of the chain
+                    Reduction::Max | Reduction::Min => vec![num_rows * 3 * (len - 1); 2],
+                    // a mult and a rescaling lookup per step of the chain
+                    Reduction::Prod { .. } => vec![num_rows * 2 * (len - 1); 2],
+                }
+            }
+            Op::CumSum {
+                axis, exclusive, ..
+            } => {
+                let len = input_shapes[0][*axis];
+                let num_rows = input_shapes[0].iter().product::<usize>() / len;
+                // an add per position along the axis, and a sub for the zero of exclusive sums
+                vec![num_rows * (len - 1 + *exclusive as usize); 2]
+            }
+            Op::Clip { lower, upper } => {
+                // a sub, a relu and an add (or sub) per bound
+                let num_bounds = *lower as usize + *upper as usize;
+                vec![3 * num_bounds * input_shapes[0].iter().product::<usize>(); 2]
+            }
+            Op::Compare { .. } => {
+                // a sub and a lookup per element
+                let len = input_shapes
+                    .iter()
+                    .map(|s| s.iter().product())
+                    .max()
+                    .unwrap();
+                vec![2 * len; 2]
+            }
+            Op::Where => {
+                // a sub, a mult and an add per element
+                let len = input_shapes
+                    .iter()
+                    .map(|s| s.iter().product())
+                    .max()
+                    .unwrap();
+                vec![3 * len; 2]
+            }
+            Op::Sign | Op::Abs => {
+                // the sign, the sign gate, the square of the sign, a sub and a lookup per element,
+                // the lookup being against the full relu table
+                vec![5 * input_shapes[0].iter().product::<usize>(); 2]
+            }
+            Op::Bitwise {
+                bitwise,
+                bits,
+                scale,
+            } => {
+                let len: usize = input_shapes
+                    .iter()
+                    .map(|s| s.iter().product())
+                    .max()
+                    .unwrap();
+                // a witness, a mult, a sub and a lookup per bit of every input element, but for
+                // the last bit which is only looked up
+                let decomposed: usize = input_shapes
+                    .iter()
+                    .map(|s| (4 * (bits + scale) - 3) * s.iter().product::<usize>())
+                    .sum();
+                // the ops combining the bits of the inputs, then a mult and an add per bit to
+                // recompose the output
+                let combined = match bitwise {
+                    Bitwise::And => 1,
+                    Bitwise::Or => 3,
+                    Bitwise::Xor => 4,
+                    Bitwise::ShiftLeft(_) | Bitwise::ShiftRight(_) => 0,
+                };
+                vec![decomposed + (combined + 2) * bits * len; 2]
+            }
+            Op::Max | Op::Min => {
+                // a sub, a lookup, a sub, a mult and an add per element of every next input
+                let len: usize = input_shapes
+                    .iter()
+                    .map(|s| s.iter().product())
+                    .max()
+                    .unwrap();
+                vec![5 * len * (input_shapes.len() - 1); 2]
+            }
+            Op::NonMaxSuppression { params } => {
+                // the pairwise IoU tests over an N x N grid, then per class the score order and
+                // one matmul per suppression pass, and the selection of every output slot
+                let n = input_shapes[0][0];
+                let num_classes = input_shapes[1][0];
+                let max_boxes = params.max_boxes.min(n);
+                let per_class = (n + 6) * n * n + max_boxes * 6 * n;
+                vec![30 * n * n + num_classes * per_class; 2]
+            }
+            Op::RoiAlign { rois, max, .. } => {
+                let channels = input_shapes[0][input_shapes[0].len() - 3];
+                let samples: usize = rois
+                    .iter()
+                    .map(|roi| channels * roi.rows.len() * roi.cols.len())
+                    .sum();
+                // a mult and an add for each of the (at most) 4 taps of every sample, then a
+                // sub, a lookup and an add per sample for max pooling, or the sumpool's
+                // accumulation and a lookup for average pooling
+                match max {
+                    true => vec![11 * samples; 2],
+                    false => vec![10 * samples; 2],
+                }
+            }
+            Op::TreeEnsemble { ensemble } => {
+                let num_features = input_shapes[0][input_shapes[0].len() - 1];
+                let num_rows = input_shapes[0].iter().product::<usize>() / num_features;
+                // a comparison and two ops per branch, and a mult and an add per leaf weight
+                let per_row: usize = ensemble
+                    .trees
+                    .iter()
+                    .flatten()
+                    .map(|node| match node {
+                        TreeNode::Branch { .. } => 4,
+                        TreeNode::Leaf { weights } => 2 * weights.len(),
+                    })
+                    .sum();
+                vec![num_rows * (per_row + ensemble.base_values.len()); 2]
+            }
+            Op::If {
+                then_branch,
+                else_branch,
+            } => {
+                // both branches are laid out, then a sub, a mult and an add select the output
+                let len: usize = then_branch
+                    .output_shapes(&input_shapes)
+                    .first()
+                    .map(|dims| dims.iter().product())
+                    .unwrap_or(0);
+                let branches =
+                    then_branch.circuit_len(&input_shapes) + else_branch.circuit_len(&input_shapes);
+                vec![branches + 3 * len; 2]
+            }
+            Op::Scan { params } => {
+                let body_shapes = params.body_shapes(&input_shapes);
+                vec![params.iterations * params.body.circuit_len(&body_shapes); 2]
+            }
+            Op::GridSample { .. } => {
+                let rank = input_shapes[0].len();
+                let (c, h, w) = (
+                    input_shapes[0][rank - 3],
+                    input_shapes[0][rank - 2],
+                    input_shapes[0][rank - 1],
+                );
+                let num_batches = input_shapes[0].iter().product::<usize>() / (c * h * w);
+                let num_points = input_shapes[1].iter().product::<usize>() / (2 * num_batches);
+                // the unnormalization and the weights of every point, then the matmul with the
+                // row weights, the multiplication by the column weights and the column sums
+                let per_point = 10 + 2 * (h + w) + 2 * c * h * w + 2 * c * w + c;
+                vec![num_batches * num_points * per_point; 2]
+            }
+            Op::Logic { logic } => {
+                let len = input_shapes
+                    .iter()
+                    .map(|s| s.iter().product())
+                    .max()
+                    .unwrap();
+                match logic {
+                    // a lookup per element
+                    Logic::Not => vec![len; 2],
+                    // two negations and two more ops per element
+                    _ => vec![4 * len; 2],
+                }
+            }
+            Op::ResizeLinear { taps, scale } => {
+                let num_taps = tensor::ops::resize_linear_taps(&input_shapes[0], taps, *scale)
+                    .iter()
+                    .map(|t| t.len())
+                    .max()
+                    .unwrap_or(0);
+                let output_len: usize = taps.iter().map(|t| t.len()).product();
+                // a mult and an add for every tap of every output element
+                vec![2 * num_taps * output_len; 2]
+            }
         };
         match shapes.last() {
             // add output
@@ -410,8 +1733,28 @@ impl Op {
             Op::BatchNorm => tensor::ops::scale_and_shift(&inputs),
             Op::ScaleAndShift => tensor::ops::scale_and_shift(&inputs),
             Op::Matmul => tensor::ops::matmul(&inputs),
+            Op::BiasedMatmul => tensor::ops::biased_matmul(&inputs),
+            Op::Einsum(equation) => tensor::ops::einsum(equation, &inputs),
             Op::Dot => tensor::ops::dot(&inputs.iter().collect()),
-            Op::Conv { padding, stride } => tensor::ops::convolution(&inputs, *padding, *stride),
+            Op::Conv {
+                padding,
+                stride,
+                group,
+                ..
+            } => tensor::ops::grouped_convolution(&inputs, *padding, *stride, *group),
+            Op::ConvReLU {
+                padding,
+                stride,
+                scale,
+                ..
+            } => LookupOp::ReLU { scale: *scale }
+                .f(tensor::ops::convolution(&inputs, *padding, *stride)?),
+            Op::Conv3D { padding, stride } => tensor::ops::conv3d(&inputs, *padding, *stride),
+            Op::DeConv {
+                padding,
+                output_padding,
+                stride,
+            } => tensor::ops::deconv(&inputs, *padding, *output_padding, *stride),
             Op::SumPool {
                 padding,
                 stride,
@@ -449,6 +1792,369 @@ impl Op {
             }
             Op::GlobalSumPool => unreachable!(),
             Op::RangeCheck(..) => Ok(inputs[0].clone()),
+            Op::LSTM { hidden_size, scale } => tensor::ops::lstm(&inputs, *hidden_size, *scale),
+            Op::GRU {
+                hidden_size,
+                scale,
+                linear_before_reset,
+            } => tensor::ops::gru(&inputs, *hidden_size, *scale, *linear_before_reset),
+            Op::RNN { hidden_size, scale } => tensor::ops::rnn(&inputs, *hidden_size, *scale),
+            Op::Softmax { scales } => tensor::ops::softmax(&inputs[0], *scales),
+            Op::Attention { scales } => tensor::ops::attention(&inputs, *scales),
+            Op::Gather {
+                axis,
+                indices,
+                index_dims,
+            } => tensor::ops::gather(
+                &inputs[0],
+                *axis,
+                &Tensor::new(Some(&indices[..]), index_dims)?,
+            ),
+            Op::Embedding { scale } => tensor::ops::embedding(&inputs[0], &inputs[1], *scale),
+            Op::GatherElements { axis, scale } => {
+                tensor::ops::gather_elements(&inputs[0], &inputs[1], *axis, *scale)
+            }
+            Op::GatherND { scale } => tensor::ops::gather_nd(&inputs[0], &inputs[1], *scale),
+            Op::ScatterElements { axis, scale } => {
+                tensor::ops::scatter_elements(&inputs[0], &inputs[1], &inputs[2], *axis, *scale)
+            }
+            Op::ScatterND { scale } => {
+                tensor::ops::scatter_nd(&inputs[0], &inputs[1], &inputs[2], *scale)
+            }
+            Op::TopK { k, axis, largest } => tensor::ops::topk(&inputs[0], *k, *axis, *largest),
+            Op::ArgMax { axis, keep_dims } => {
+                tensor::ops::argmax(&inputs[0], *axis, *keep_dims, true)
+            }
+            Op::ArgMin { axis, keep_dims } => {
+                tensor::ops::argmax(&inputs[0], *axis, *keep_dims, false)
+            }
+            Op::ResizeNearest { indices } => tensor::ops::resize_nearest(&inputs[0], indices),
+            Op::ResizeLinear { taps, scale } => {
+                tensor::ops::resize_linear(&inputs[0], taps, *scale)
+            }
+            Op::PadWithFill { pads, fill } => {
+                let constant = match fill {
+                    PadFill::Constant(c) => *c,
+                    _ => 0,
+                };
+                tensor::ops::pad_with_fill(&inputs[0], pads, fill, constant)
+            }
+            Op::Tile { repeats } => tensor::ops::tile(&inputs[0], repeats),
+            Op::Expand { shape } => tensor::ops::expand(&inputs[0], shape),
+            Op::Slice {
+                starts,
+                ends,
+                steps,
+            } => tensor::ops::slice(&inputs[0], starts, ends, steps),
+            Op::Transpose { perm } => inputs[0].permute(perm),
+            Op::DepthToSpace { blocksize, mode } => {
+                tensor::ops::depth_to_space(&inputs[0], *blocksize, mode)
+            }
+            Op::SpaceToDepth { blocksize } => tensor::ops::space_to_depth(&inputs[0], *blocksize),
+            Op::Reduce {
+                reduction,
+                axes,
+                keep_dims,
+            } => tensor::ops::reduce(&inputs[0], axes, *keep_dims, reduction),
+            Op::CumSum {
+                axis,
+                exclusive,
+                reverse,
+            } => tensor::ops::cumsum(&inputs[0], *axis, *exclusive, *reverse),
+            Op::Clip { lower, upper } => {
+                // the bounds follow the clipped input
+                let mut bounds = inputs[1..].iter().map(|b| b[0]);
+                let min = if *lower { bounds.next() } else { None };
+                let max = if *upper { bounds.next() } else { None };
+                Ok(tensor::ops::clip(&inputs[0], min, max))
+            }
+            Op::Compare { comparison } => tensor::ops::compare(&inputs[0], &inputs[1], comparison),
+            Op::Where => tensor::ops::select(&inputs[0], &inputs[1], &inputs[2]),
+            Op::Logic { logic } => tensor::ops::logic(&inputs, logic),
+            Op::Max => tensor::ops::extremum(&inputs, true),
+            Op::Min => tensor::ops::extremum(&inputs, false),
+            Op::Sign => Ok(tensor::ops::sign(&inputs[0])),
+            Op::Abs => Ok(tensor::ops::abs(&inputs[0])),
+            Op::Bitwise {
+                bitwise,
+                bits,
+                scale,
+            } => tensor::ops::bitwise(&inputs, bitwise, *bits, *scale),
+            Op::NonMaxSuppression { params } => {
+                tensor::ops::non_max_suppression(&inputs[0], &inputs[1], params)
+            }
+            Op::RoiAlign { rois, max, scale } => {
+                tensor::ops::roi_align(&inputs[0], rois, *max, *scale)
+            }
+            Op::TreeEnsemble { ensemble } => tensor::ops::tree_ensemble(&inputs[0], ensemble),
+            Op::If {
+                then_branch,
+                else_branch,
+            } => {
+                let then_output = then_branch.f(&inputs)?;
+                let else_output = else_branch.f(&inputs)?;
+                match (then_output.first(), else_output.first()) {
+                    (Some(x), Some(y)) => tensor::ops::select(&inputs[0], x, y),
+                    _ => Err(TensorError::DimMismatch("if".to_string())),
+                }
+            }
+            Op::Scan { params } => params.f(&inputs),
+            Op::GridSample {
+                align_corners,
+                border,
+                scale,
+            } => tensor::ops::grid_sample(&inputs[0], &inputs[1], *align_corners, *border, *scale),
+        }
+    }
+
+    /// Lookups which need to be configured on the same config for the op to be laid out.
+    pub fn required_lookups(&self) -> Vec<LookupOp> {
+        match self {
+            Op::LSTM { scale, .. } => vec![
+                LookupOp::Sigmoid {
+                    scales: (scale * scale, *scale),
+                },
+                LookupOp::Tanh {
+                    scales: (scale * scale, *scale),
+                },
+                LookupOp::Tanh {
+                    scales: (*scale, *scale),
+                },
+                LookupOp::Div {
+                    denom: eq_float::F32(*scale as f32),
+                },
+            ],
+            Op::GRU { scale, .. } => vec![
+                LookupOp::Sigmoid {
+                    scales: (scale * scale, *scale),
+                },
+                LookupOp::Tanh {
+                    scales: (scale * scale, *scale),
+                },
+                LookupOp::Div {
+                    denom: eq_float::F32(*scale as f32),
+                },
+            ],
+            Op::RNN { scale, .. } => vec![LookupOp::Tanh {
+                scales: (scale * scale, *scale),
+            }],
+            Op::Softmax { scales } | Op::Attention { scales } => vec![
+                LookupOp::Exp { scales: *scales },
+                LookupOp::Recip {
+                    scales: (scales.1, scales.1 * scales.1),
+                },
+                LookupOp::Div {
+                    denom: eq_float::F32((scales.1 * scales.1) as f32),
+                },
+            ],
+            Op::GatherElements { .. }
+            | Op::GatherND { .. }
+            | Op::ScatterElements { .. }
+            | Op::ScatterND { .. } => vec![LookupOp::IsZero],
+            Op::TopK { .. } | Op::Embedding { .. } => vec![LookupOp::ReLU { scale: 1 }],
+            Op::ArgMax { .. } | Op::ArgMin { .. } => vec![
+                LookupOp::ReLU { scale: 1 },
+                LookupOp::Compare {
+                    comparison: Comparison::Greater,
+                },
+            ],
+            Op::Clip { .. } => vec![LookupOp::ReLU { scale: 1 }],
+            Op::Compare { comparison } => vec![LookupOp::Compare {
+                comparison: *comparison,
+            }],
+            Op::Logic { .. } => vec![LookupOp::IsZero],
+            Op::Max => vec![LookupOp::Compare {
+                comparison: Comparison::Greater,
+            }],
+            Op::Min => vec![LookupOp::Compare {
+                comparison: Comparison::Less,
+            }],
+            Op::Sign | Op::Abs => vec![LookupOp::ReLU { scale: 1 }],
+            Op::Bitwise { .. } => vec![LookupOp::Range { bits: 1 }],
+            Op::RoiAlign { rois, max, .. } => match max {
+                true => vec![LookupOp::ReLU { scale: 1 }],
+                false => rois
+                    .iter()
+                    .map(|roi| LookupOp::Div {
+                        denom: eq_float::F32((roi.grid.0 * roi.grid.1) as f32),
+                    })
+                    .unique()
+                    .collect(),
+            },
+            Op::TreeEnsemble { ensemble } => ensemble
+                .trees
+                .iter()
+                .flatten()
+                .filter_map(|node| match node {
+                    TreeNode::Branch { comparison, .. } => Some(LookupOp::Compare {
+                        comparison: *comparison,
+                    }),
+                    TreeNode::Leaf { .. } => None,
+                })
+                .unique()
+                .collect(),
+            Op::If {
+                then_branch,
+                else_branch,
+            } => [
+                then_branch.required_lookups(),
+                else_branch.required_lookups(),
+            ]
+            .concat()
+            .into_iter()
+            .unique()
+            .collect(),
+            Op::Scan { params } => params.body.required_lookups(),
+            Op::GridSample { border, scale, .. } => {
+                let mut lookups = vec![
+                    LookupOp::Div {
+                        denom: eq_float::F32(2.0),
+                    },
+                    LookupOp::Tent { scale: *scale },
+                    LookupOp::Div {
+                        denom: eq_float::F32(*scale as f32),
+                    },
+                ];
+                if *border {
+                    lookups.push(LookupOp::ReLU { scale: 1 });
+                }
+                lookups.into_iter().unique().collect()
+            }
+            Op::NonMaxSuppression { params } => vec![
+                LookupOp::ReLU { scale: 1 },
+                LookupOp::Div {
+                    denom: eq_float::F32(params.scale as f32),
+                },
+                LookupOp::IsZero,
+                LookupOp::Compare {
+                    comparison: Comparison::Greater,
+                },
+                LookupOp::Compare {
+                    comparison: Comparison::Less,
+                },
+            ],
+            Op::Reduce { reduction, .. } => match reduction {
+                Reduction::Sum => vec![],
+                Reduction::Mean { denom, remainder } => {
+                    LookupOp::division(*denom, *remainder).table_ops()
+                }
+                Reduction::Max | Reduction::Min => vec![LookupOp::ReLU { scale: 1 }],
+                Reduction::Prod { scale } => vec![LookupOp::Div {
+                    denom: eq_float::F32(*scale as f32),
+                }],
+            },
+            Op::Rescaled { inner, .. } => inner.required_lookups(),
+            _ => vec![],
+        }
+    }
+
+    /// The lookups fused into the op's accumulation, which are looked up from the output column
+    /// of the base gates rather than from the lookup columns. See
+    /// [BaseConfig::configure_fused_lookup].
+    pub fn fused_lookups(&self) -> Vec<LookupOp> {
+        match self {
+            Op::ConvReLU { scale, .. } => vec![LookupOp::ReLU { scale: *scale }],
+            Op::Rescaled { inner, .. } => inner.fused_lookups(),
+            _ => vec![],
+        }
+    }
+
+    /// The op with its convolution (if any) lowered by `lowering`.
+    pub fn with_conv_lowering(&self, lowering: ConvLowering) -> Op {
+        match self {
+            Op::Conv {
+                padding,
+                stride,
+                group,
+                ..
+            } => Op::Conv {
+                padding: *padding,
+                stride: *stride,
+                group: *group,
+                lowering,
+            },
+            Op::ConvReLU {
+                padding,
+                stride,
+                scale,
+                ..
+            } => Op::ConvReLU {
+                padding: *padding,
+                stride: *stride,
+                scale: *scale,
+                lowering,
+            },
+            Op::Rescaled { inner, scale } => Op::Rescaled {
+                inner: Box::new(inner.with_conv_lowering(lowering)),
+                scale: scale.clone(),
+            },
+            op => op.clone(),
+        }
+    }
+
+    /// The lowering of the op's convolution, if it holds one.
+    pub fn conv_lowering(&self) -> Option<ConvLowering> {
+        match self {
+            Op::Conv { lowering, .. } | Op::ConvReLU { lowering, .. } => Some(*lowering),
+            Op::Rescaled { inner, .. } => inner.conv_lowering(),
+            _ => None,
+        }
+    }
+
+    /// The lowerings the op's convolution (if it holds one) can be laid out with, given the
+    /// shapes of its inputs. The Winograd lowerings only apply to convs of 3x3 kernels with a
+    /// stride of 1, which aren't fused with a relu.
+    pub fn conv_lowerings(&self, input_shapes: &[Vec<usize>]) -> Vec<ConvLowering> {
+        let lowerings = vec![ConvLowering::Direct, ConvLowering::Im2col];
+        match self {
+            Op::Conv { stride: (1, 1), .. }
+                if input_shapes
+                    .get(1)
+                    .map_or(false, |k| k.len() == 4 && k[2..] == [3, 3]) =>
+            {
+                [
+                    lowerings,
+                    vec![
+                        ConvLowering::Winograd { tile: 2 },
+                        ConvLowering::Winograd { tile: 4 },
+                    ],
+                ]
+                .concat()
+            }
+            Op::Conv { .. } | Op::ConvReLU { .. } => lowerings,
+            Op::Rescaled { inner, .. } => inner.conv_lowerings(input_shapes),
+            _ => vec![],
+        }
+    }
+
+    /// The number of cells the op lays out with its convolution (if it holds one) lowered by each
+    /// of the lowerings of [Op::conv_lowerings], as estimated by [Op::circuit_shapes].
+    pub fn conv_lowering_lens(&self, input_shapes: Vec<Vec<usize>>) -> Vec<(ConvLowering, usize)> {
+        self.conv_lowerings(&input_shapes)
+            .into_iter()
+            .map(|lowering| {
+                let len = *self
+                    .with_conv_lowering(lowering)
+                    .circuit_shapes(input_shapes.clone())
+                    .last()
+                    .unwrap();
+                (lowering, len)
+            })
+            .collect()
+    }
+
+    /// Whether the op looks up values in tables witnessed from its inputs, which need a dynamic
+    /// lookup to be configured on the same config for the op to be laid out.
+    pub fn requires_dynamic_lookup(&self) -> bool {
+        match self {
+            Op::Embedding { .. } | Op::ArgMax { .. } | Op::ArgMin { .. } => true,
+            Op::If {
+                then_branch,
+                else_branch,
+            } => then_branch.requires_dynamic_lookup() || else_branch.requires_dynamic_lookup(),
+            Op::Scan { params } => params.body.requires_dynamic_lookup(),
+            Op::Rescaled { inner, .. } => inner.requires_dynamic_lookup(),
+            _ => false,
         }
     }
 }
@@ -465,13 +2171,50 @@ impl fmt::Display for Op {
             Op::Sum => write!(f, "sum"),
             Op::Mult => write!(f, "mult"),
             Op::Matmul => write!(f, "matmul"),
+            Op::Einsum(equation) => write!(f, "einsum {}", equation),
             Op::Dot => write!(f, "dot"),
             Op::Pack(base, _) => write!(f, "pack with base {:?}", base),
             Op::Affine => write!(f, "affine"),
+            Op::BiasedMatmul => write!(f, "biased matmul"),
             Op::BatchNorm => write!(f, "batchnorm"),
             Op::ScaleAndShift => write!(f, "scale & shift"),
-            Op::Conv { padding, stride } => {
-                write!(f, "conv w/ padding: {:?}, stride: {:?}", padding, stride)
+            Op::Conv {
+                padding,
+                stride,
+                group,
+                lowering,
+            } => {
+                write!(
+                    f,
+                    "conv w/ padding: {:?}, stride: {:?}, group: {}, lowering: {}",
+                    padding, stride, group, lowering
+                )
+            }
+            Op::ConvReLU {
+                padding,
+                stride,
+                scale,
+                lowering,
+            } => {
+                write!(
+                    f,
+                    "conv relu w/ padding: {:?}, stride: {:?}, scale: {}, lowering: {}",
+                    padding, stride, scale, lowering
+                )
+            }
+            Op::Conv3D { padding, stride } => {
+                write!(f, "conv3d w/ padding: {:?}, stride: {:?}", padding, stride)
+            }
+            Op::DeConv {
+                padding,
+                output_padding,
+                stride,
+            } => {
+                write!(
+                    f,
+                    "deconv w/ padding: {:?}, output padding: {:?}, stride: {:?}",
+                    padding, output_padding, stride
+                )
             }
             Op::SumPool {
                 padding,
@@ -495,6 +2238,126 @@ impl fmt::Display for Op {
                 )
             }
             Op::RangeCheck(tol) => write!(f, "range check w/ tol {}", tol),
+            Op::LSTM { hidden_size, .. } => write!(f, "lstm w/ hidden size: {}", hidden_size),
+            Op::GRU { hidden_size, .. } => write!(f, "gru w/ hidden size: {}", hidden_size),
+            Op::RNN { hidden_size, .. } => write!(f, "rnn w/ hidden size: {}", hidden_size),
+            Op::Softmax { scales } => write!(f, "softmax w/ scales: {:?}", scales),
+            Op::Attention { scales } => write!(f, "attention w/ scales: {:?}", scales),
+            Op::Gather { axis, indices, .. } => {
+                write!(f, "gather w/ axis: {}, indices: {:?}", axis, indices)
+            }
+            Op::Embedding { .. } => write!(f, "embedding"),
+            Op::GatherElements { axis, .. } => write!(f, "gather elements w/ axis: {}", axis),
+            Op::GatherND { .. } => write!(f, "gather nd"),
+            Op::ScatterElements { axis, .. } => write!(f, "scatter elements w/ axis: {}", axis),
+            Op::ScatterND { .. } => write!(f, "scatter nd"),
+            Op::TopK { k, axis, largest } => {
+                write!(f, "topk w/ k: {}, axis: {}, largest: {}", k, axis, largest)
+            }
+            Op::ArgMax { axis, .. } => write!(f, "argmax w/ axis: {}", axis),
+            Op::ArgMin { axis, .. } => write!(f, "argmin w/ axis: {}", axis),
+            Op::ResizeNearest { .. } => write!(f, "resize (nearest)"),
+            Op::ResizeLinear { scale, .. } => write!(f, "resize (linear) w/ scale: {}", scale),
+            Op::PadWithFill { pads, fill } => write!(f, "padding: {:?}, fill: {:?}", pads, fill),
+            Op::Tile { repeats } => write!(f, "tile w/ repeats: {:?}", repeats),
+            Op::Expand { shape } => write!(f, "expand to {:?}", shape),
+            Op::Slice {
+                starts,
+                ends,
+                steps,
+            } => write!(
+                f,
+                "slice w/ starts: {:?}, ends: {:?}, steps: {:?}",
+                starts, ends, steps
+            ),
+            Op::Transpose { perm } => write!(f, "transpose w/ permutation: {:?}", perm),
+            Op::DepthToSpace { blocksize, mode } => write!(
+                f,
+                "depth to space w/ blocksize: {}, mode: {:?}",
+                blocksize, mode
+            ),
+            Op::SpaceToDepth { blocksize } => {
+                write!(f, "space to depth w/ blocksize: {}", blocksize)
+            }
+            Op::Reduce {
+                reduction,
+                axes,
+                keep_dims,
+            } => write!(
+                f,
+                "reduce {:?} w/ axes: {:?}, keep dims: {}",
+                reduction, axes, keep_dims
+            ),
+            Op::CumSum {
+                axis,
+                exclusive,
+                reverse,
+            } => write!(
+                f,
+                "cumsum w/ axis: {}, exclusive: {}, reverse: {}",
+                axis, exclusive, reverse
+            ),
+            Op::Clip { lower, upper } => {
+                write!(f, "clip w/ lower bound: {}, upper bound: {}", lower, upper)
+            }
+            Op::Compare { comparison } => write!(f, "compare: {:?}", comparison),
+            Op::Where => write!(f, "where"),
+            Op::Logic { logic } => write!(f, "logic: {:?}", logic),
+            Op::Max => write!(f, "max"),
+            Op::Min => write!(f, "min"),
+            Op::Sign => write!(f, "sign"),
+            Op::Abs => write!(f, "abs"),
+            Op::Bitwise {
+                bitwise,
+                bits,
+                scale,
+            } => write!(
+                f,
+                "bitwise: {:?} w/ bits: {}, scale: {}",
+                bitwise, bits, scale
+            ),
+            Op::NonMaxSuppression { params } => write!(
+                f,
+                "non max suppression w/ max boxes: {}, iou threshold: {}",
+                params.max_boxes, params.iou_threshold
+            ),
+            Op::TreeEnsemble { ensemble } => write!(
+                f,
+                "tree ensemble w/ trees: {}, targets: {}",
+                ensemble.trees.len(),
+                ensemble.base_values.len()
+            ),
+            Op::If {
+                then_branch,
+                else_branch,
+            } => write!(
+                f,
+                "if w/ then ops: {}, else ops: {}",
+                then_branch.nodes.len(),
+                else_branch.nodes.len()
+            ),
+            Op::Scan { params } => write!(
+                f,
+                "scan w/ body ops: {}, iterations: {}",
+                params.body.nodes.len(),
+                params.iterations
+            ),
+            Op::GridSample {
+                align_corners,
+                border,
+                scale,
+            } => write!(
+                f,
+                "grid sample w/ align corners: {}, border: {}, scale: {}",
+                align_corners, border, scale
+            ),
+            Op::RoiAlign { rois, max, scale } => write!(
+                f,
+                "roi align w/ rois: {}, max: {}, scale: {}",
+                rois.len(),
+                max,
+                scale
+            ),
         }
     }
 }
@@ -504,7 +2367,7 @@ impl fmt::Display for Op {
 // Eventually, though, we probably want to keep them and treat them directly (layouting and configuring
 // at each type of node)
 /// Enum of the different kinds of operations `ezkl` can support.
-#[derive(Clone, Debug, Default, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum OpKind {
     /// A nonlinearity
     Lookup(LookupOp),
@@ -537,7 +2400,79 @@ impl OpKind {
     /// Produce an OpKind from a `&str` onnx name  
     pub fn new(name: &str) -> Self {
         match name {
-            "Clip" => OpKind::Lookup(LookupOp::ReLU { scale: 1 }),
+            "Clip" => OpKind::Poly(Op::Clip {
+                lower: false,
+                upper: false,
+            }),
+            "Floor" => OpKind::Lookup(LookupOp::Floor { scale: 1 }),
+            "Greater" => OpKind::Poly(Op::Compare {
+                comparison: Comparison::Greater,
+            }),
+            "Less" => OpKind::Poly(Op::Compare {
+                comparison: Comparison::Less,
+            }),
+            "GreaterEqual" | "GreaterOrEqual" => OpKind::Poly(Op::Compare {
+                comparison: Comparison::GreaterOrEqual,
+            }),
+            "LessEqual" | "LessOrEqual" => OpKind::Poly(Op::Compare {
+                comparison: Comparison::LessOrEqual,
+            }),
+            "Equals" | "Equal" => OpKind::Poly(Op::Compare {
+                comparison: Comparison::Equal,
+            }),
+            "Iff" | "Where" => OpKind::Poly(Op::Where),
+            "Rem" => OpKind::Lookup(LookupOp::Mod {
+                denom: 1,
+                fmod: true,
+            }),
+            "RemInt" | "Mod" => OpKind::Lookup(LookupOp::Mod {
+                denom: 1,
+                fmod: false,
+            }),
+            "Max" => OpKind::Poly(Op::Max),
+            "Min" => OpKind::Poly(Op::Min),
+            "Sign" => OpKind::Poly(Op::Sign),
+            "Abs" => OpKind::Poly(Op::Abs),
+            "BitAnd" | "BitwiseAnd" => OpKind::Poly(Op::Bitwise {
+                bitwise: Bitwise::And,
+                bits: 0,
+                scale: 0,
+            }),
+            "BitOr" | "BitwiseOr" => OpKind::Poly(Op::Bitwise {
+                bitwise: Bitwise::Or,
+                bits: 0,
+                scale: 0,
+            }),
+            "BitXor" | "BitwiseXor" => OpKind::Poly(Op::Bitwise {
+                bitwise: Bitwise::Xor,
+                bits: 0,
+                scale: 0,
+            }),
+            "ShiftLeft" => OpKind::Poly(Op::Bitwise {
+                bitwise: Bitwise::ShiftLeft(0),
+                bits: 0,
+                scale: 0,
+            }),
+            "ShiftRight" => OpKind::Poly(Op::Bitwise {
+                bitwise: Bitwise::ShiftRight(0),
+                bits: 0,
+                scale: 0,
+            }),
+            "NonMaxSuppression" => OpKind::Poly(Op::NonMaxSuppression {
+                params: NmsParams {
+                    max_boxes: 1,
+                    iou_threshold: 0,
+                    score_threshold: None,
+                    center_point_box: false,
+                    scale: 1,
+                },
+            }),
+            "And" => OpKind::Poly(Op::Logic { logic: Logic::And }),
+            "Or" => OpKind::Poly(Op::Logic { logic: Logic::Or }),
+            "Xor" => OpKind::Poly(Op::Logic { logic: Logic::Xor }),
+            "Not" => OpKind::Poly(Op::Logic { logic: Logic::Not }),
+            "Ceil" => OpKind::Lookup(LookupOp::Ceil { scale: 1 }),
+            "Round" | "RoundHalfToEven" => OpKind::Lookup(LookupOp::Round { scale: 1 }),
             "Prelu" => OpKind::Lookup(LookupOp::PReLU {
                 scale: 1,
                 slopes: vec![],
@@ -549,6 +2484,11 @@ impl OpKind {
             "Sigmoid" => OpKind::Lookup(LookupOp::Sigmoid { scales: (1, 1) }),
             "Sqrt" => OpKind::Lookup(LookupOp::Sqrt { scales: (1, 1) }),
             "Tanh" => OpKind::Lookup(LookupOp::Tanh { scales: (1, 1) }),
+            "Exp" => OpKind::Lookup(LookupOp::Exp { scales: (1, 1) }),
+            "Ln" | "Log" => OpKind::Lookup(LookupOp::Ln { scales: (1, 1) }),
+            "Recip" | "Reciprocal" => OpKind::Lookup(LookupOp::Recip { scales: (1, 1) }),
+            "Rsqrt" => OpKind::Lookup(LookupOp::Rsqrt { scales: (1, 1) }),
+            "Erf" => OpKind::Lookup(LookupOp::Erf { scales: (1, 1) }),
             "Div" => OpKind::Lookup(LookupOp::Div {
                 denom: eq_float::F32(1.0),
             }),
@@ -559,16 +2499,37 @@ impl OpKind {
             "Mul" => OpKind::Poly(Op::Mult),
             "Gemm" => OpKind::Poly(Op::Affine),
             "MatMulInference" => OpKind::Poly(Op::Matmul),
+            "MatMulInteger" => OpKind::Poly(Op::Matmul),
+            "QuantizeLinear" => OpKind::Poly(Op::Identity),
+            "DequantizeLinear" => OpKind::Poly(Op::Identity),
+            // dropout is a no-op at inference
+            "Identity" | "Dropout" => OpKind::Poly(Op::Identity),
+            "EinSum" => OpKind::Poly(Op::Einsum(String::new())),
             "Dot" => OpKind::Poly(Op::Dot),
-            "Reduce<Sum>" => OpKind::Poly(Op::Sum),
+            "Reduce<Sum>" | "Reduce<Mean>" | "Reduce<Max>" | "Reduce<Min>" | "Reduce<Prod>" => {
+                OpKind::Poly(Op::Reduce {
+                    reduction: Reduction::Sum,
+                    axes: vec![],
+                    keep_dims: true,
+                })
+            }
             "Pow" => OpKind::Poly(Op::Pow(1)),
             "Conv" => OpKind::Poly(Op::Conv {
                 padding: (1, 1),
                 stride: (1, 1),
+                group: 1,
+                lowering: ConvLowering::Direct,
             }),
             "ConvHir" => OpKind::Poly(Op::Conv {
                 padding: (1, 1),
                 stride: (1, 1),
+                group: 1,
+                lowering: ConvLowering::Direct,
+            }),
+            "ConvTranspose" => OpKind::Poly(Op::DeConv {
+                padding: (1, 1),
+                output_padding: (0, 0),
+                stride: (1, 1),
             }),
             "SumPool" => OpKind::Poly(Op::SumPool {
                 padding: (1, 1),
@@ -576,10 +2537,82 @@ impl OpKind {
                 kernel_shape: (1, 1),
             }),
             "GlobalAvgPool" => OpKind::Poly(Op::GlobalSumPool),
+            "MaxPool" => OpKind::Lookup(LookupOp::MaxPool2D {
+                padding: (1, 1),
+                stride: (1, 1),
+                pool_dims: (1, 1),
+            }),
             "Pad" => OpKind::Poly(Op::Pad(0, 0)),
             "Reshape" => OpKind::Poly(Op::Reshape(Vec::new())),
             "Flatten" => OpKind::Poly(Op::Flatten(Vec::new())),
+            "Tile" => OpKind::Poly(Op::Tile { repeats: vec![] }),
+            "Expand" => OpKind::Poly(Op::Expand { shape: vec![] }),
+            "DepthToSpace" => OpKind::Poly(Op::DepthToSpace {
+                blocksize: 1,
+                mode: DepthToSpaceMode::DCR,
+            }),
+            "SpaceToDepth" => OpKind::Poly(Op::SpaceToDepth { blocksize: 1 }),
+            "PermuteAxes" | "Transpose" => OpKind::Poly(Op::Transpose { perm: vec![] }),
+            "StridedSlice" => OpKind::Poly(Op::Slice {
+                starts: vec![],
+                ends: vec![],
+                steps: vec![],
+            }),
             "BatchNorm" => OpKind::Poly(Op::BatchNorm),
+            "LSTM" => OpKind::Poly(Op::LSTM {
+                hidden_size: 0,
+                scale: 1,
+            }),
+            "GRU" => OpKind::Poly(Op::GRU {
+                hidden_size: 0,
+                scale: 1,
+                linear_before_reset: false,
+            }),
+            "RNN" => OpKind::Poly(Op::RNN {
+                hidden_size: 0,
+                scale: 1,
+            }),
+            "Softmax" | "LayerSoftmax" => OpKind::Poly(Op::Softmax { scales: (1, 1) }),
+            "Gather" => OpKind::Poly(Op::Gather {
+                axis: 0,
+                indices: vec![],
+                index_dims: vec![],
+            }),
+            "GatherElements" => OpKind::Poly(Op::GatherElements { axis: 0, scale: 1 }),
+            "GatherND" | "GatherNd" => OpKind::Poly(Op::GatherND { scale: 1 }),
+            "ScatterElements" => OpKind::Poly(Op::ScatterElements { axis: 0, scale: 1 }),
+            "ScatterND" | "ScatterNd" => OpKind::Poly(Op::ScatterND { scale: 1 }),
+            "TopK" | "Topk" => OpKind::Poly(Op::TopK {
+                k: 0,
+                axis: 0,
+                largest: true,
+            }),
+            "Reduce<ArgMax(false)>" => OpKind::Poly(Op::ArgMax {
+                axis: 0,
+                keep_dims: true,
+            }),
+            "Reduce<ArgMin(false)>" => OpKind::Poly(Op::ArgMin {
+                axis: 0,
+                keep_dims: true,
+            }),
+            "Resize" => OpKind::Poly(Op::ResizeNearest { indices: vec![] }),
+            "If" => OpKind::Poly(Op::If {
+                then_branch: Subgraph::default(),
+                else_branch: Subgraph::default(),
+            }),
+            "Scan" => OpKind::Poly(Op::Scan {
+                params: ScanParams {
+                    body: Subgraph::default(),
+                    inputs: vec![],
+                    output: ScanOutput::Last(0),
+                    iterations: 0,
+                },
+            }),
+            "CumSum" => OpKind::Poly(Op::CumSum {
+                axis: 0,
+                exclusive: false,
+                reverse: false,
+            }),
             c => {
                 warn!("{:?} is not currently supported", c);
                 OpKind::Unknown(c.to_string())
@@ -620,6 +2653,39 @@ impl fmt::Display for OpKind {
     }
 }
 
+/// Configuration of a lookup of (key, value) pairs into a table of pairs which is witnessed in the
+/// circuit, such as the entries of a tensor keyed by their position. The tables of every op are
+/// laid out one after the other in a column of their own, so no two pairs of tables share a row.
+#[derive(Clone, Debug)]
+pub struct DynamicLookup {
+    /// the columns of the keys of the looked up pairs
+    pub keys: VarTensor,
+    /// the columns of the values of the looked up pairs
+    pub values: VarTensor,
+    /// the fixed column of the keys of the tables, such that the prover can't rekey their pairs
+    pub table_keys: VarTensor,
+    /// the column of the values of the tables
+    pub table_values: VarTensor,
+    /// [Selector]s of the looked up pairs, per column
+    pub input_selectors: Vec<Selector>,
+    /// [Selector] of the pairs of the tables
+    pub table_selector: Selector,
+    /// the rows of the table columns taken by the tables laid out in the current region
+    pub table_rows: Rc<Cell<usize>>,
+}
+
+impl DynamicLookup {
+    /// Frees the rows of the table columns, for a region which lays out its tables from the top.
+    pub fn reset(&self) {
+        self.table_rows.set(0);
+    }
+}
+
+/// The lookup tables of a circuit, keyed by their op. Shared by the [BaseConfig]s of a circuit
+/// (see [BaseConfig::share_tables]) such that each table is configured and laid out once, however
+/// many configs and nodes look it up.
+pub type Tables<F> = Rc<RefCell<BTreeMap<LookupOp, Rc<RefCell<Table<F>>>>>>;
+
 /// Configuration for an accumulated arg.
 #[derive(Clone, Debug, Default)]
 pub struct BaseConfig<F: FieldExt + TensorType> {
@@ -637,8 +2703,12 @@ pub struct BaseConfig<F: FieldExt + TensorType> {
     pub selectors: BTreeMap<(BaseOp, usize), Selector>,
     /// [Selectors] generated when configuring the layer. We use a BTreeMap as we expect to configure many lookup ops.
     pub lookup_selectors: BTreeMap<(LookupOp, usize), Selector>,
-    /// [Table]
-    pub tables: BTreeMap<LookupOp, Rc<RefCell<Table<F>>>>,
+    /// [Selectors] of the lookups fused into accumulated ops, per column of the output.
+    pub fused_lookup_selectors: BTreeMap<(LookupOp, usize), Selector>,
+    /// [Table]s, possibly shared with other configs
+    pub tables: Tables<F>,
+    /// [DynamicLookup], if configured
+    pub dynamic_lookup: Option<DynamicLookup>,
     /// Activate sanity checks
     pub check_mode: CheckMode,
     _marker: PhantomData<F>,
@@ -663,6 +2733,13 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
         assert!(inputs[0].num_cols() == inputs[1].num_cols());
         assert!(inputs[0].num_cols() == output.num_cols());
 
+        // constants (eg. the positions indices are compared to) are copied from a fixed column,
+        // such that the prover can't substitute them
+        if meta.constants().is_empty() {
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+        }
+
         for i in 0..output.num_cols() {
             selectors.insert((BaseOp::Add, i), meta.selector());
             selectors.insert((BaseOp::Sub, i), meta.selector());
@@ -671,6 +2748,7 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
             selectors.insert((BaseOp::Mult, i), meta.selector());
             selectors.insert((BaseOp::Identity, i), meta.selector());
             selectors.insert((BaseOp::Range { tol }, i), meta.selector());
+            selectors.insert((BaseOp::Sign, i), meta.selector());
         }
 
         // Given a range R and a value v, returns the expression
@@ -714,6 +2792,19 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
                             res - expected_output[base_op.constraint_idx()].clone(),
                         )]
                     }
+                    // the output is the sign times the input, the sign is -1, 0 or 1, and the
+                    // input is the sign times the output, such that the sign is 0 only if the
+                    // input is
+                    BaseOp::Sign => {
+                        let (sign, x) = (qis[0].clone(), qis[1].clone());
+                        let abs = expected_output[0].clone();
+                        let one = Expression::Constant(<F as Field>::one());
+                        vec![
+                            abs.clone() - res,
+                            sign.clone() * (sign.clone() - one.clone()) * (sign.clone() + one),
+                            x - sign * abs,
+                        ]
+                    }
                     _ => vec![expected_output[base_op.constraint_idx()].clone() - res],
                 };
 
@@ -724,10 +2815,12 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
         Self {
             selectors,
             lookup_selectors: BTreeMap::new(),
+            fused_lookup_selectors: BTreeMap::new(),
             inputs: inputs.to_vec(),
             lookup_input: VarTensor::None,
             lookup_output: VarTensor::None,
-            tables: BTreeMap::new(),
+            tables: Tables::default(),
+            dynamic_lookup: None,
             output: output.clone(),
             check_mode,
             _marker: PhantomData,
@@ -743,15 +2836,11 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
         bits: usize,
         nl: &LookupOp,
     ) -> Result<(), Box<dyn Error>> {
+        if self.lookup_selectors.contains_key(&(nl.clone(), 0)) {
+            return Ok(());
+        }
         let mut selectors = BTreeMap::new();
-        let table =
-            if let std::collections::btree_map::Entry::Vacant(e) = self.tables.entry(nl.clone()) {
-                let table = Rc::new(RefCell::new(Table::<F>::configure(cs, bits, nl)));
-                e.insert(table.clone());
-                table
-            } else {
-                return Ok(());
-            };
+        let table = self.table(cs, bits, nl);
         for x in 0..input.num_cols() {
             let qlookup = cs.complex_selector();
             selectors.insert((nl.clone(), x), qlookup);
@@ -803,9 +2892,131 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
         Ok(())
     }
 
+    /// Configures a lookup of the values accumulated in the output column, fused into the ops
+    /// which accumulate them (eg. the relu of a conv). The input of the lookup is the output
+    /// column in the row above the selector and its output the output column in the selector's
+    /// row, such that the accumulated value needn't be copied to the lookup input column first.
+    /// The table is shared with the unfused lookups of the same op.
+    pub fn configure_fused_lookup(
+        &mut self,
+        cs: &mut ConstraintSystem<F>,
+        bits: usize,
+        nl: &LookupOp,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.fused_lookup_selectors.contains_key(&(nl.clone(), 0)) {
+            return Ok(());
+        }
+        let outputs = match &self.output {
+            VarTensor::Advice { inner, .. } => inner.clone(),
+            _ => return Err(Box::new(CircuitError::LookupInstantiation)),
+        };
+        let table = self.table(cs, bits, nl);
+        for (x, output) in outputs.iter().enumerate() {
+            let qlookup = cs.complex_selector();
+            self.fused_lookup_selectors.insert((nl.clone(), x), qlookup);
+            let _ = cs.lookup(nl.as_str(), |cs| {
+                let qlookup = cs.query_selector(qlookup);
+                let not_qlookup = Expression::Constant(<F as Field>::one()) - qlookup.clone();
+                let (default_x, default_y): (F, F) = nl.default_pair();
+                vec![
+                    (
+                        qlookup.clone() * cs.query_advice(*output, Rotation(-1))
+                            + not_qlookup.clone() * default_x,
+                        table.clone().borrow().table_input,
+                    ),
+                    (
+                        qlookup * cs.query_advice(*output, Rotation(0)) + not_qlookup * default_y,
+                        table.clone().borrow().table_output,
+                    ),
+                ]
+            });
+        }
+        Ok(())
+    }
+
+    /// Shares the tables of `other`, such that the lookups of either config use the same table
+    /// for an op. Must be called before any lookup of this config is configured.
+    pub fn share_tables(&mut self, other: &Self) {
+        assert!(self.tables.borrow().is_empty());
+        self.tables = other.tables.clone();
+    }
+
+    /// The table of `nl`, configured on first use such that every lookup of `nl` shares it.
+    fn table(
+        &mut self,
+        cs: &mut ConstraintSystem<F>,
+        bits: usize,
+        nl: &LookupOp,
+    ) -> Rc<RefCell<Table<F>>> {
+        self.tables
+            .borrow_mut()
+            .entry(nl.clone())
+            .or_insert_with(|| Rc::new(RefCell::new(Table::<F>::configure(cs, bits, nl))))
+            .clone()
+    }
+
+    /// Configures a dynamic lookup of (key, value) pairs laid out in `keys` and `values` into
+    /// tables of pairs laid out in a fixed column of keys and an advice column of values of their
+    /// own. Each column of the looked up pairs has a lookup of its own into the table columns.
+    pub fn configure_dynamic_lookup(
+        &mut self,
+        cs: &mut ConstraintSystem<F>,
+        keys: &VarTensor,
+        values: &VarTensor,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.dynamic_lookup.is_some() {
+            return Ok(());
+        }
+        let (key_cols, value_cols) = match (keys, values) {
+            (VarTensor::Advice { inner: k, .. }, VarTensor::Advice { inner: v, .. }) => (k, v),
+            _ => return Err(Box::new(CircuitError::LookupInstantiation)),
+        };
+        let (table_key_col, table_value_col) = (cs.fixed_column(), cs.advice_column());
+        // the values of a table are copied from the cells of the tensor it holds
+        cs.enable_equality(table_value_col);
+        let input_selectors = (0..key_cols.len())
+            .map(|_| cs.complex_selector())
+            .collect_vec();
+        let table_selector = cs.complex_selector();
+        for (x, q_input) in input_selectors.iter().enumerate() {
+            let _ = cs.lookup_any("dynamic lookup", |cs| {
+                let q_input = cs.query_selector(*q_input);
+                let q_table = cs.query_selector(table_selector);
+                vec![
+                    (
+                        q_input.clone() * cs.query_advice(key_cols[x], Rotation(0)),
+                        q_table.clone() * cs.query_fixed(table_key_col, Rotation(0)),
+                    ),
+                    (
+                        q_input * cs.query_advice(value_cols[x], Rotation(0)),
+                        q_table * cs.query_advice(table_value_col, Rotation(0)),
+                    ),
+                ]
+            });
+        }
+        self.dynamic_lookup = Some(DynamicLookup {
+            keys: keys.clone(),
+            values: values.clone(),
+            table_keys: VarTensor::Fixed {
+                inner: vec![table_key_col],
+                col_size: keys.col_size(),
+                capacity: keys.col_size(),
+            },
+            table_values: VarTensor::Advice {
+                inner: vec![table_value_col],
+                col_size: keys.col_size(),
+                capacity: keys.col_size(),
+            },
+            input_selectors,
+            table_selector,
+            table_rows: Rc::new(Cell::new(0)),
+        });
+        Ok(())
+    }
+
     /// layout_tables must be called before layout.
     pub fn layout_tables(&mut self, layouter: &mut impl Layouter<F>) -> Result<(), Box<dyn Error>> {
-        for (_, table) in &self.tables {
+        for table in self.tables.borrow().values() {
             if !table.borrow().is_assigned {
                 table.borrow_mut().layout(layouter)?;
             }
@@ -840,12 +3051,54 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
                 Op::Dot => layouts::dot(self, region, cp_values[..].try_into()?, offset)?,
                 Op::Sum => layouts::sum(self, region, cp_values[..].try_into()?, offset)?,
                 Op::Matmul => layouts::matmul(self, region, cp_values[..].try_into()?, offset)?,
+                Op::Einsum(equation) => {
+                    layouts::einsum(self, region, cp_values[..].try_into()?, &equation, offset)?
+                }
                 Op::Affine => layouts::affine(self, region, cp_values[..].try_into()?, offset)?,
-                Op::Conv { padding, stride } => layouts::conv(
+                Op::BiasedMatmul => {
+                    layouts::biased_matmul(self, region, cp_values[..].try_into()?, offset)?
+                }
+                Op::Conv {
+                    padding,
+                    stride,
+                    group,
+                    lowering,
+                } => layouts::grouped_conv(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    padding,
+                    stride,
+                    group,
+                    lowering,
+                    offset,
+                )?,
+                Op::ConvReLU {
+                    padding,
+                    stride,
+                    scale,
+                    lowering,
+                } => layouts::conv_relu(
+                    self, region, &cp_values, padding, stride, scale, lowering, offset,
+                )?,
+                Op::Conv3D { padding, stride } => layouts::conv3d(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    padding,
+                    stride,
+                    offset,
+                )?,
+                Op::DeConv {
+                    padding,
+                    output_padding,
+                    stride,
+                } => layouts::deconv(
                     self,
                     region,
                     cp_values[..].try_into()?,
                     padding,
+                    output_padding,
                     stride,
                     offset,
                 )?,
@@ -911,6 +3164,207 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
                 Op::RangeCheck(tol) => {
                     layouts::range_check(self, region, cp_values[..].try_into()?, offset, tol)?
                 }
+                Op::LSTM { hidden_size, scale } => layouts::lstm(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    hidden_size,
+                    scale,
+                    offset,
+                )?,
+                Op::GRU {
+                    hidden_size,
+                    scale,
+                    linear_before_reset,
+                } => layouts::gru(
+                    self,
+                    region,
+                    &cp_values,
+                    hidden_size,
+                    scale,
+                    linear_before_reset,
+                    offset,
+                )?,
+                Op::RNN { hidden_size, scale } => {
+                    layouts::rnn(self, region, &cp_values, hidden_size, scale, offset)?
+                }
+                Op::Softmax { scales } => {
+                    layouts::softmax(self, region, cp_values[..].try_into()?, scales, offset)?
+                }
+                Op::Attention { scales } => {
+                    layouts::attention(self, region, cp_values[..].try_into()?, scales, offset)?
+                }
+                Op::Gather {
+                    axis,
+                    indices,
+                    index_dims,
+                } => layouts::gather(cp_values[..].try_into()?, axis, &indices, &index_dims)?,
+                Op::Embedding { scale } => {
+                    layouts::embedding(self, region, cp_values[..].try_into()?, scale, offset)?
+                }
+                Op::GatherElements { axis, scale } => layouts::gather_elements(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    axis,
+                    scale,
+                    offset,
+                )?,
+                Op::GatherND { scale } => {
+                    layouts::gather_nd(self, region, cp_values[..].try_into()?, scale, offset)?
+                }
+                Op::ScatterElements { axis, scale } => layouts::scatter_elements(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    axis,
+                    scale,
+                    offset,
+                )?,
+                Op::ScatterND { scale } => {
+                    layouts::scatter_nd(self, region, cp_values[..].try_into()?, scale, offset)?
+                }
+                Op::TopK { k, axis, largest } => layouts::topk(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    k,
+                    axis,
+                    largest,
+                    offset,
+                )?,
+                Op::ArgMax { axis, keep_dims } => layouts::argmax(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    axis,
+                    keep_dims,
+                    true,
+                    offset,
+                )?,
+                Op::ArgMin { axis, keep_dims } => layouts::argmax(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    axis,
+                    keep_dims,
+                    false,
+                    offset,
+                )?,
+                Op::ResizeNearest { indices } => {
+                    layouts::resize_nearest(cp_values[..].try_into()?, &indices)?
+                }
+                Op::PadWithFill { pads, fill } => {
+                    layouts::pad_with_fill(cp_values[..].try_into()?, &pads, &fill)?
+                }
+                Op::Tile { repeats } => layouts::tile(cp_values[..].try_into()?, &repeats)?,
+                Op::Expand { shape } => layouts::expand(cp_values[..].try_into()?, &shape)?,
+                Op::Slice {
+                    starts,
+                    ends,
+                    steps,
+                } => layouts::slice(cp_values[..].try_into()?, &starts, &ends, &steps)?,
+                Op::Transpose { perm } => layouts::transpose(cp_values[..].try_into()?, &perm)?,
+                Op::DepthToSpace { blocksize, mode } => {
+                    layouts::depth_to_space(cp_values[..].try_into()?, blocksize, &mode)?
+                }
+                Op::SpaceToDepth { blocksize } => {
+                    layouts::space_to_depth(cp_values[..].try_into()?, blocksize)?
+                }
+                Op::Reduce {
+                    reduction,
+                    axes,
+                    keep_dims,
+                } => layouts::reduce(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    &axes,
+                    keep_dims,
+                    &reduction,
+                    offset,
+                )?,
+                Op::CumSum {
+                    axis,
+                    exclusive,
+                    reverse,
+                } => layouts::cumsum(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    axis,
+                    exclusive,
+                    reverse,
+                    offset,
+                )?,
+                Op::Clip { lower, upper } => {
+                    layouts::clip(self, region, &cp_values, lower, upper, offset)?
+                }
+                Op::Compare { comparison } => {
+                    layouts::compare(self, region, cp_values[..].try_into()?, comparison, offset)?
+                }
+                Op::Where => layouts::select(self, region, cp_values[..].try_into()?, offset)?,
+                Op::Logic { logic } => layouts::logic(self, region, &cp_values, logic, offset)?,
+                Op::Max => layouts::extremum(self, region, &cp_values, true, offset)?,
+                Op::Min => layouts::extremum(self, region, &cp_values, false, offset)?,
+                Op::Sign => layouts::sign(self, region, cp_values[..].try_into()?, false, offset)?,
+                Op::Abs => layouts::sign(self, region, cp_values[..].try_into()?, true, offset)?,
+                Op::Bitwise {
+                    bitwise,
+                    bits,
+                    scale,
+                } => layouts::bitwise(self, region, &cp_values, bitwise, bits, scale, offset)?,
+                Op::NonMaxSuppression { params } => layouts::non_max_suppression(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    &params,
+                    offset,
+                )?,
+                Op::TreeEnsemble { ensemble } => layouts::tree_ensemble(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    &ensemble,
+                    offset,
+                )?,
+                Op::If {
+                    then_branch,
+                    else_branch,
+                } => {
+                    layouts::if_else(self, region, &cp_values, &then_branch, &else_branch, offset)?
+                }
+                Op::Scan { params } => layouts::scan(self, region, &cp_values, &params, offset)?,
+                Op::GridSample {
+                    align_corners,
+                    border,
+                    scale,
+                } => layouts::grid_sample(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    align_corners,
+                    border,
+                    scale,
+                    offset,
+                )?,
+                Op::RoiAlign { rois, max, scale } => layouts::roi_align(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    &rois,
+                    max,
+                    scale,
+                    offset,
+                )?,
+                Op::ResizeLinear { taps, scale } => layouts::resize_linear(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    &taps,
+                    scale,
+                    offset,
+                )?,
                 Op::GlobalSumPool => unreachable!(),
             }),
             OpKind::Lookup(nl) => match nl {
@@ -921,6 +3375,77 @@ impl<F: FieldExt + TensorType> BaseConfig<F> {
                     scale,
                     offset,
                 )?),
+                LookupOp::MaxPool2D {
+                    padding,
+                    stride,
+                    pool_dims,
+                } => Some(layouts::max_pool2d(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    padding,
+                    stride,
+                    pool_dims,
+                    offset,
+                )?),
+                LookupOp::AvgPool2D {
+                    padding,
+                    stride,
+                    pool_dims,
+                    remainder,
+                } => Some(layouts::avg_pool2d(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    padding,
+                    stride,
+                    pool_dims,
+                    remainder,
+                    offset,
+                )?),
+                LookupOp::MaxPool3D {
+                    padding,
+                    stride,
+                    pool_dims,
+                } => Some(layouts::max_pool3d(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    padding,
+                    stride,
+                    pool_dims,
+                    offset,
+                )?),
+                LookupOp::AvgPool3D {
+                    padding,
+                    stride,
+                    pool_dims,
+                    remainder,
+                } => Some(layouts::avg_pool3d(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    padding,
+                    stride,
+                    pool_dims,
+                    remainder,
+                    offset,
+                )?),
+                LookupOp::Limbed { inner, shift } => Some(layouts::limbed_nonlinearity(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    *inner,
+                    shift,
+                    offset,
+                )?),
+                LookupOp::DivRem { denom } => Some(layouts::div_rem(
+                    self,
+                    region,
+                    cp_values[..].try_into()?,
+                    denom,
+                    offset,
+                )?),
                 _ => Some(layouts::nonlinearity(
                     self,
                     region,
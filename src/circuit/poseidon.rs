@@ -0,0 +1,377 @@
+use halo2_proofs::{
+    arithmetic::{Field, FieldExt},
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Fixed, Selector},
+    poly::Rotation,
+};
+use itertools::Itertools;
+
+use crate::tensor::{TensorType, ValType};
+
+/// The width of the Poseidon state.
+pub const WIDTH: usize = 3;
+/// The number of elements absorbed per permutation.
+pub const RATE: usize = WIDTH - 1;
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 57;
+
+/// The Grain LFSR used to generate the round constants of Poseidon, as in the reference
+/// implementation.
+struct Grain {
+    state: Vec<bool>,
+}
+
+impl Grain {
+    fn new(num_bits: usize) -> Self {
+        let mut state = vec![];
+        let mut push = |value: usize, len: usize| {
+            state.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+        };
+        // prime field, x^alpha sbox
+        push(1, 2);
+        push(0, 4);
+        push(num_bits, 12);
+        push(WIDTH, 12);
+        push(FULL_ROUNDS, 10);
+        push(PARTIAL_ROUNDS, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut grain = Grain { state };
+        for _ in 0..160 {
+            grain.next_bit();
+        }
+        grain
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.remove(0);
+        self.state.push(bit);
+        bit
+    }
+
+    /// Bits are output in pairs, the second bit of a pair being kept if the first is set.
+    fn next_output_bit(&mut self) -> bool {
+        loop {
+            let (keep, bit) = (self.next_bit(), self.next_bit());
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Samples field elements from their big endian bits, rejecting those out of the field.
+    fn next_field_element<F: FieldExt>(&mut self) -> F {
+        let num_bits = F::NUM_BITS as usize;
+        loop {
+            let bits = (0..num_bits).map(|_| self.next_output_bit()).collect_vec();
+            let mut repr = F::Repr::default();
+            for (i, bit) in bits.iter().rev().enumerate() {
+                if *bit {
+                    repr.as_mut()[i / 8] |= 1 << (i % 8);
+                }
+            }
+            if let Some(f) = Option::from(F::from_repr(repr)) {
+                return f;
+            }
+        }
+    }
+}
+
+/// The round constants and MDS matrix of a Poseidon permutation with an x^5 sbox.
+#[derive(Clone, Debug)]
+pub struct PoseidonSpec<F: FieldExt> {
+    round_constants: Vec<[F; WIDTH]>,
+    mds: [[F; WIDTH]; WIDTH],
+}
+
+impl<F: FieldExt> Default for PoseidonSpec<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FieldExt> PoseidonSpec<F> {
+    /// Generates the round constants with the Grain LFSR, and a Cauchy MDS matrix.
+    pub fn new() -> Self {
+        let mut grain = Grain::new(F::NUM_BITS as usize);
+        let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+            .map(|_| [(); WIDTH].map(|_| grain.next_field_element()))
+            .collect();
+        let mut mds = [[F::zero(); WIDTH]; WIDTH];
+        for (i, row) in mds.iter_mut().enumerate() {
+            for (j, m) in row.iter_mut().enumerate() {
+                *m = F::from((i + WIDTH + j) as u64).invert().unwrap();
+            }
+        }
+        PoseidonSpec {
+            round_constants,
+            mds,
+        }
+    }
+
+    /// The round constants added to the state before each round.
+    pub fn round_constants(&self) -> &[[F; WIDTH]] {
+        &self.round_constants
+    }
+
+    fn is_full_round(round: usize) -> bool {
+        round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + PARTIAL_ROUNDS
+    }
+
+    fn sbox(x: F) -> F {
+        x.square().square() * x
+    }
+
+    /// Applies a single round of the permutation to `state`.
+    pub fn round(&self, round: usize, state: &[F]) -> Vec<F> {
+        let rc = &self.round_constants[round];
+        let sboxed = state
+            .iter()
+            .zip(rc)
+            .enumerate()
+            .map(|(i, (s, c))| {
+                if i == 0 || Self::is_full_round(round) {
+                    Self::sbox(*s + c)
+                } else {
+                    *s + c
+                }
+            })
+            .collect_vec();
+        self.mds
+            .iter()
+            .map(|row| row.iter().zip(&sboxed).map(|(m, s)| *m * s).sum())
+            .collect()
+    }
+
+    /// Hashes `inputs` with a sponge whose capacity is initialized with the number of inputs.
+    pub fn hash(&self, inputs: &[F]) -> F {
+        let mut state = vec![F::zero(); WIDTH];
+        state[0] = F::from(inputs.len() as u64);
+        for chunk in Self::chunks(inputs) {
+            for (s, x) in state[1..].iter_mut().zip(chunk) {
+                *s += x;
+            }
+            for round in 0..self.round_constants.len() {
+                state = self.round(round, &state);
+            }
+        }
+        state[1]
+    }
+
+    /// The chunks absorbed by the sponge, which absorbs at least once.
+    fn chunks<T>(inputs: &[T]) -> Vec<&[T]> {
+        match inputs.is_empty() {
+            true => vec![inputs],
+            false => inputs.chunks(RATE).collect(),
+        }
+    }
+}
+
+/// Configuration for a Poseidon chip, which lays out a round of the permutation per row.
+#[derive(Clone, Debug)]
+pub struct PoseidonConfig<F: FieldExt> {
+    state: [Column<Advice>; WIDTH],
+    input: [Column<Advice>; RATE],
+    round_constants: [Column<Fixed>; WIDTH],
+    q_full: Selector,
+    q_partial: Selector,
+    q_absorb: Selector,
+    spec: PoseidonSpec<F>,
+}
+
+impl<F: FieldExt + TensorType> PoseidonConfig<F> {
+    /// Configures the chip's columns and gates.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let state = [(); WIDTH].map(|_| meta.advice_column());
+        let input = [(); RATE].map(|_| meta.advice_column());
+        let round_constants = [(); WIDTH].map(|_| meta.fixed_column());
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+        for column in state.iter().chain(input.iter()) {
+            meta.enable_equality(*column);
+        }
+        let (q_full, q_partial, q_absorb) = (meta.selector(), meta.selector(), meta.selector());
+        let spec = PoseidonSpec::new();
+
+        for (name, q, full) in [
+            ("full round", q_full, true),
+            ("partial round", q_partial, false),
+        ] {
+            meta.create_gate(name, |meta| {
+                let q = meta.query_selector(q);
+                let sboxed = (0..WIDTH)
+                    .map(|i| {
+                        let x = meta.query_advice(state[i], Rotation::cur())
+                            + meta.query_fixed(round_constants[i], Rotation::cur());
+                        if i == 0 || full {
+                            x.clone() * x.clone() * x.clone() * x.clone() * x
+                        } else {
+                            x
+                        }
+                    })
+                    .collect_vec();
+                let constraints = (0..WIDTH)
+                    .map(|i| {
+                        let mixed = sboxed
+                            .iter()
+                            .zip(spec.mds[i])
+                            .fold(Expression::Constant(F::zero()), |acc, (s, m)| {
+                                acc + s.clone() * Expression::Constant(m)
+                            });
+                        mixed - meta.query_advice(state[i], Rotation::next())
+                    })
+                    .collect_vec();
+                Constraints::with_selector(q, constraints)
+            });
+        }
+
+        meta.create_gate("absorb", |meta| {
+            let q = meta.query_selector(q_absorb);
+            let constraints = (0..WIDTH)
+                .map(|i| {
+                    let mut absorbed = meta.query_advice(state[i], Rotation::cur());
+                    if i > 0 {
+                        absorbed = absorbed + meta.query_advice(input[i - 1], Rotation::cur());
+                    }
+                    absorbed - meta.query_advice(state[i], Rotation::next())
+                })
+                .collect_vec();
+            Constraints::with_selector(q, constraints)
+        });
+
+        PoseidonConfig {
+            state,
+            input,
+            round_constants,
+            q_full,
+            q_partial,
+            q_absorb,
+            spec,
+        }
+    }
+
+    /// Hashes `inputs` in a region of their own, returning the cells the inputs are assigned to
+    /// along with the digest. Inputs which are already assigned are copied, such that the digest
+    /// commits to the cells they were assigned to.
+    pub fn layout(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        inputs: &[ValType<F>],
+    ) -> Result<(Vec<AssignedCell<F, F>>, AssignedCell<F, F>), Error> {
+        layouter.assign_region(
+            || "poseidon",
+            |mut region| {
+                let mut offset = 0;
+                let mut state = vec![];
+                for (i, column) in self.state.iter().enumerate() {
+                    let init = if i == 0 {
+                        F::from(inputs.len() as u64)
+                    } else {
+                        F::zero()
+                    };
+                    state.push(region.assign_advice_from_constant(
+                        || "initial state",
+                        *column,
+                        offset,
+                        init,
+                    )?);
+                }
+
+                let mut assigned = vec![];
+                for chunk in PoseidonSpec::<F>::chunks(inputs) {
+                    self.q_absorb.enable(&mut region, offset)?;
+                    let mut absorbed = vec![Value::known(F::zero()); RATE];
+                    for (j, column) in self.input.iter().enumerate() {
+                        match chunk.get(j) {
+                            Some(x) => {
+                                let cell = match x {
+                                    ValType::Value(v) => {
+                                        region.assign_advice(|| "input", *column, offset, || *v)?
+                                    }
+                                    ValType::AssignedValue(v) => region.assign_advice(
+                                        || "input",
+                                        *column,
+                                        offset,
+                                        || v.evaluate(),
+                                    )?,
+                                    ValType::PrevAssigned(v) => {
+                                        v.copy_advice(|| "input", &mut region, *column, offset)?
+                                    }
+                                    ValType::Constant(v) => region.assign_advice_from_constant(
+                                        || "input",
+                                        *column,
+                                        offset,
+                                        *v,
+                                    )?,
+                                };
+                                absorbed[j] = cell.value().copied();
+                                assigned.push(cell);
+                            }
+                            // the last chunk is padded with zeros
+                            None => {
+                                region.assign_advice_from_constant(
+                                    || "padding",
+                                    *column,
+                                    offset,
+                                    F::zero(),
+                                )?;
+                            }
+                        }
+                    }
+                    let mut values: Value<Vec<F>> = state
+                        .iter()
+                        .enumerate()
+                        .map(|(i, s)| match i {
+                            0 => s.value().map(|s| *s),
+                            _ => s.value().map(|s| *s) + absorbed[i - 1],
+                        })
+                        .collect();
+                    offset += 1;
+                    state = self.assign_state(&mut region, offset, &values)?;
+
+                    for (round, rc) in self.spec.round_constants.iter().enumerate() {
+                        if PoseidonSpec::<F>::is_full_round(round) {
+                            self.q_full.enable(&mut region, offset)?;
+                        } else {
+                            self.q_partial.enable(&mut region, offset)?;
+                        }
+                        for (column, c) in self.round_constants.iter().zip(rc) {
+                            region.assign_fixed(
+                                || "round constant",
+                                *column,
+                                offset,
+                                || Value::known(*c),
+                            )?;
+                        }
+                        values = values.map(|v| self.spec.round(round, &v));
+                        offset += 1;
+                        state = self.assign_state(&mut region, offset, &values)?;
+                    }
+                }
+                Ok((assigned, state[1].clone()))
+            },
+        )
+    }
+
+    fn assign_state(
+        &self,
+        region: &mut halo2_proofs::circuit::Region<F>,
+        offset: usize,
+        values: &Value<Vec<F>>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.state
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                region.assign_advice(
+                    || "state",
+                    *column,
+                    offset,
+                    || values.as_ref().map(|v| v[i]),
+                )
+            })
+            .collect()
+    }
+}
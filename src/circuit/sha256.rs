@@ -0,0 +1,511 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+use crate::fieldutils::felt_to_i128;
+use crate::tensor::{TensorType, ValType};
+
+/// The number of bits of a word.
+const WORD_BITS: usize = 32;
+/// The number of words of a block of the message.
+const BLOCK_WORDS: usize = 16;
+/// The number of words summed by the addition gate.
+const ADDENDS: usize = 5;
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The rotations (or shifts, when flagged) xored together by each of the sigma functions, in
+/// order Σ0, Σ1, σ0 and σ1.
+const SIGMAS: [[(usize, bool); 3]; 4] = [
+    [(2, false), (13, false), (22, false)],
+    [(6, false), (11, false), (25, false)],
+    [(7, false), (18, false), (3, true)],
+    [(17, false), (19, false), (10, true)],
+];
+const UPPER_SIGMA_0: usize = 0;
+const UPPER_SIGMA_1: usize = 1;
+const LOWER_SIGMA_0: usize = 2;
+const LOWER_SIGMA_1: usize = 3;
+
+/// The message hashed for `values`, each encoded as a big endian 64 bit two's complement integer
+/// (as with `abi.encodePacked` of `int64` values).
+pub fn encode(values: &[i128]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| (*v as i64).to_be_bytes())
+        .collect()
+}
+
+/// The SHA-256 digest of the encoding of `values`, as two field elements holding its upper and
+/// lower 128 bits.
+pub fn digest<F: FieldExt>(values: &[i128]) -> [F; 2] {
+    let digest = Sha256::digest(encode(values));
+    let half = |bytes: &[u8]| F::from_u128(u128::from_be_bytes(bytes.try_into().unwrap()));
+    [half(&digest[..16]), half(&digest[16..])]
+}
+
+fn sigma(x: u32, sigma: &[(usize, bool); 3]) -> u32 {
+    sigma.iter().fold(0, |acc, (n, shift)| match shift {
+        true => acc ^ (x >> n),
+        false => acc ^ x.rotate_right(*n as u32),
+    })
+}
+
+fn xor<F: FieldExt>(a: Expression<F>, b: Expression<F>) -> Expression<F> {
+    a.clone() + b.clone() - Expression::Constant(F::from(2u64)) * a * b
+}
+
+/// A 32 bit word assigned in the chip, along with its value.
+#[derive(Clone, Debug)]
+struct Word<F: FieldExt> {
+    cell: AssignedCell<F, F>,
+    value: Value<u32>,
+}
+
+/// Configuration for a SHA-256 chip. Every word is laid out on a row of its own along with its
+/// bits, such that the rotations of the sigma functions are rewirings of the bits, and additions
+/// modulo 2^32 are constrained on the words with a small carry.
+#[derive(Clone, Debug)]
+pub struct Sha256Config<F: FieldExt> {
+    word: Column<Advice>,
+    bits: [Column<Advice>; WORD_BITS],
+    carry: Column<Advice>,
+    q_word: Selector,
+    q_sigmas: [Selector; 4],
+    q_choose: Selector,
+    q_majority: Selector,
+    q_add: Selector,
+    q_encode: Selector,
+    q_pack: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt + TensorType> Sha256Config<F> {
+    /// Configures the chip's columns and gates.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let word = meta.advice_column();
+        meta.enable_equality(word);
+        let bits = [(); WORD_BITS].map(|_| meta.advice_column());
+        let carry = meta.advice_column();
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+        let q_word = meta.selector();
+        let q_sigmas = [(); 4].map(|_| meta.selector());
+        let (q_choose, q_majority) = (meta.selector(), meta.selector());
+        let (q_add, q_encode, q_pack) = (meta.selector(), meta.selector(), meta.selector());
+        let one = Expression::Constant(F::one());
+
+        // each word is the composition of its (boolean) bits, least significant first
+        meta.create_gate("word", |meta| {
+            let q = meta.query_selector(q_word);
+            let bits = bits.map(|b| meta.query_advice(b, Rotation::cur()));
+            let recomposed = bits
+                .iter()
+                .rev()
+                .fold(Expression::Constant(F::zero()), |acc, b| {
+                    acc * Expression::Constant(F::from(2u64)) + b.clone()
+                });
+            let constraints = bits
+                .iter()
+                .map(|b| b.clone() * (one.clone() - b.clone()))
+                .chain([recomposed - meta.query_advice(word, Rotation::cur())])
+                .collect_vec();
+            Constraints::with_selector(q, constraints)
+        });
+
+        for (q, sigma) in q_sigmas.iter().zip(SIGMAS) {
+            meta.create_gate("sigma", |meta| {
+                let q = meta.query_selector(*q);
+                let x = bits.map(|b| meta.query_advice(b, Rotation::cur()));
+                let out = bits.map(|b| meta.query_advice(b, Rotation::next()));
+                let constraints = (0..WORD_BITS)
+                    .map(|i| {
+                        let terms = sigma.iter().map(|(n, shift)| match (*shift, i + n) {
+                            (true, j) if j >= WORD_BITS => Expression::Constant(F::zero()),
+                            (_, j) => x[j % WORD_BITS].clone(),
+                        });
+                        terms.reduce(xor).unwrap() - out[i].clone()
+                    })
+                    .collect_vec();
+                Constraints::with_selector(q, constraints)
+            });
+        }
+
+        for (name, q, majority) in [("choose", q_choose, false), ("majority", q_majority, true)] {
+            meta.create_gate(name, |meta| {
+                let q = meta.query_selector(q);
+                let constraints = (0..WORD_BITS)
+                    .map(|i| {
+                        let [x, y, z, out] =
+                            [0, 1, 2, 3].map(|r| meta.query_advice(bits[i], Rotation(r)));
+                        let f = match majority {
+                            // xy + xz + yz - 2xyz
+                            true => {
+                                x.clone() * y.clone()
+                                    + x.clone() * z.clone()
+                                    + y.clone() * z.clone()
+                                    - Expression::Constant(F::from(2u64)) * x * y * z
+                            }
+                            // xy + (1 - x)z
+                            false => x.clone() * y + (one.clone() - x) * z,
+                        };
+                        f - out
+                    })
+                    .collect_vec();
+                Constraints::with_selector(q, constraints)
+            });
+        }
+
+        meta.create_gate("add", |meta| {
+            let q = meta.query_selector(q_add);
+            let sum = (0..ADDENDS)
+                .map(|r| meta.query_advice(word, Rotation(r as i32)))
+                .fold(Expression::Constant(F::zero()), |acc, w| acc + w);
+            let out = meta.query_advice(word, Rotation(ADDENDS as i32));
+            let c = meta.query_advice(carry, Rotation(ADDENDS as i32));
+            let range = (0..ADDENDS as u64).fold(one.clone(), |acc, k| {
+                acc * (c.clone() - Expression::Constant(F::from(k)))
+            });
+            Constraints::with_selector(
+                q,
+                [
+                    sum - out - c * Expression::Constant(F::from(1u64 << WORD_BITS)),
+                    range,
+                ],
+            )
+        });
+
+        // a 64 bit two's complement integer is split into its upper and lower words
+        meta.create_gate("encode", |meta| {
+            let q = meta.query_selector(q_encode);
+            let x = meta.query_advice(word, Rotation::cur());
+            let upper = meta.query_advice(word, Rotation::next());
+            let lower = meta.query_advice(word, Rotation(2));
+            let sign = meta.query_advice(bits[WORD_BITS - 1], Rotation::next());
+            Constraints::with_selector(
+                q,
+                [
+                    x - upper * Expression::Constant(F::from(1u64 << WORD_BITS)) - lower
+                        + sign * Expression::Constant(F::from_u128(1u128 << (2 * WORD_BITS))),
+                ],
+            )
+        });
+
+        // four words are packed big endian into 128 bits of the digest
+        meta.create_gate("pack", |meta| {
+            let q = meta.query_selector(q_pack);
+            let packed = (0..4)
+                .map(|r| meta.query_advice(word, Rotation(r)))
+                .fold(Expression::Constant(F::zero()), |acc, w| {
+                    acc * Expression::Constant(F::from(1u64 << WORD_BITS)) + w
+                });
+            Constraints::with_selector(q, [packed - meta.query_advice(word, Rotation(4))])
+        });
+
+        Sha256Config {
+            word,
+            bits,
+            carry,
+            q_word,
+            q_sigmas,
+            q_choose,
+            q_majority,
+            q_add,
+            q_encode,
+            q_pack,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Hashes the encoding of `inputs` (see [encode]) in a region of their own, returning the
+    /// cells the inputs are assigned to along with the upper and lower 128 bits of the digest.
+    /// Inputs which are already assigned are copied, such that the digest commits to the cells
+    /// they were assigned to.
+    pub fn layout(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        inputs: &[ValType<F>],
+    ) -> Result<(Vec<AssignedCell<F, F>>, [AssignedCell<F, F>; 2]), Error> {
+        layouter.assign_region(
+            || "sha256",
+            |mut region| {
+                let region = &mut region;
+                let mut offset = 0;
+                let mut assigned = vec![];
+                let mut message = vec![];
+                for x in inputs {
+                    let cell = match x {
+                        ValType::Value(v) => {
+                            region.assign_advice(|| "input", self.word, offset, || *v)?
+                        }
+                        ValType::AssignedValue(v) => {
+                            region.assign_advice(|| "input", self.word, offset, || v.evaluate())?
+                        }
+                        ValType::PrevAssigned(v) => {
+                            v.copy_advice(|| "input", region, self.word, offset)?
+                        }
+                        ValType::Constant(v) => {
+                            region.assign_advice_from_constant(|| "input", self.word, offset, *v)?
+                        }
+                    };
+                    self.q_encode.enable(region, offset)?;
+                    let value = cell.value().map(|x| felt_to_i128(*x) as i64 as u64);
+                    let upper =
+                        self.new_word(region, offset + 1, value.map(|v| (v >> 32) as u32))?;
+                    let lower = self.new_word(region, offset + 2, value.map(|v| v as u32))?;
+                    offset += 3;
+                    assigned.push(cell);
+                    message.extend([upper, lower]);
+                }
+
+                // the message is padded with a one bit, zeros, and its length in bits
+                let len = (message.len() * WORD_BITS) as u64;
+                let mut padding = vec![1 << (WORD_BITS - 1)];
+                while (message.len() + padding.len()) % BLOCK_WORDS != BLOCK_WORDS - 2 {
+                    padding.push(0);
+                }
+                padding.extend([(len >> 32) as u32, len as u32]);
+                for p in padding {
+                    message.push(self.constant_word(region, offset, p)?);
+                    offset += 1;
+                }
+
+                let mut state = vec![];
+                for s in INITIAL_STATE {
+                    state.push(self.constant_word(region, offset, s)?);
+                    offset += 1;
+                }
+                for block in message.chunks(BLOCK_WORDS) {
+                    state = self.compress(region, &mut offset, &state, block)?;
+                }
+
+                let mut digest = vec![];
+                for half in state.chunks(4) {
+                    self.q_pack.enable(region, offset)?;
+                    let mut packed = Value::known(0u128);
+                    for (r, word) in half.iter().enumerate() {
+                        self.copy_word(region, offset + r, word)?;
+                        packed = packed
+                            .zip(word.value)
+                            .map(|(p, w)| (p << WORD_BITS) + w as u128);
+                    }
+                    digest.push(region.assign_advice(
+                        || "digest",
+                        self.word,
+                        offset + 4,
+                        || packed.map(F::from_u128),
+                    )?);
+                    offset += 5;
+                }
+                Ok((assigned, [digest[0].clone(), digest[1].clone()]))
+            },
+        )
+    }
+
+    /// Compresses a `block` of the message into the `state`, returning the next state.
+    fn compress(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        state: &[Word<F>],
+        block: &[Word<F>],
+    ) -> Result<Vec<Word<F>>, Error> {
+        let mut schedule = block.to_vec();
+        for t in BLOCK_WORDS..ROUND_CONSTANTS.len() {
+            let s0 = self.sigma(region, offset, &schedule[t - 15], LOWER_SIGMA_0)?;
+            let s1 = self.sigma(region, offset, &schedule[t - 2], LOWER_SIGMA_1)?;
+            let w = self.add(
+                region,
+                offset,
+                &[&s1, &schedule[t - 7], &s0, &schedule[t - 16]],
+                0,
+            )?;
+            schedule.push(w);
+        }
+
+        let mut vars = state.to_vec();
+        for (t, k) in ROUND_CONSTANTS.iter().enumerate() {
+            let [a, b, c, d, e, f, g, h]: [Word<F>; 8] = vars.try_into().unwrap();
+            let s1 = self.sigma(region, offset, &e, UPPER_SIGMA_1)?;
+            let ch = self.choose(region, offset, [&e, &f, &g], false)?;
+            let t1 = self.add(region, offset, &[&h, &s1, &ch, &schedule[t]], *k)?;
+            let s0 = self.sigma(region, offset, &a, UPPER_SIGMA_0)?;
+            let maj = self.choose(region, offset, [&a, &b, &c], true)?;
+            let next_e = self.add(region, offset, &[&d, &t1], 0)?;
+            let next_a = self.add(region, offset, &[&t1, &s0, &maj], 0)?;
+            vars = vec![next_a, a, b, c, next_e, e, f, g];
+        }
+
+        state
+            .iter()
+            .zip(vars.iter())
+            .map(|(s, v)| self.add(region, offset, &[s, v], 0))
+            .collect()
+    }
+
+    fn assign_bits(
+        &self,
+        region: &mut Region<F>,
+        offset: usize,
+        value: Value<u32>,
+    ) -> Result<(), Error> {
+        self.q_word.enable(region, offset)?;
+        for (i, column) in self.bits.iter().enumerate() {
+            region.assign_advice(
+                || "bit",
+                *column,
+                offset,
+                || value.map(|v| F::from(((v >> i) & 1) as u64)),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn new_word(
+        &self,
+        region: &mut Region<F>,
+        offset: usize,
+        value: Value<u32>,
+    ) -> Result<Word<F>, Error> {
+        let cell = region.assign_advice(
+            || "word",
+            self.word,
+            offset,
+            || value.map(|v| F::from(v as u64)),
+        )?;
+        self.assign_bits(region, offset, value)?;
+        Ok(Word { cell, value })
+    }
+
+    fn constant_word(
+        &self,
+        region: &mut Region<F>,
+        offset: usize,
+        value: u32,
+    ) -> Result<Word<F>, Error> {
+        let cell = region.assign_advice_from_constant(
+            || "constant",
+            self.word,
+            offset,
+            F::from(value as u64),
+        )?;
+        self.assign_bits(region, offset, Value::known(value))?;
+        Ok(Word {
+            cell,
+            value: Value::known(value),
+        })
+    }
+
+    fn copy_word(
+        &self,
+        region: &mut Region<F>,
+        offset: usize,
+        word: &Word<F>,
+    ) -> Result<Word<F>, Error> {
+        let cell = word
+            .cell
+            .copy_advice(|| "word", region, self.word, offset)?;
+        self.assign_bits(region, offset, word.value)?;
+        Ok(Word {
+            cell,
+            value: word.value,
+        })
+    }
+
+    fn sigma(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        x: &Word<F>,
+        s: usize,
+    ) -> Result<Word<F>, Error> {
+        self.copy_word(region, *offset, x)?;
+        self.q_sigmas[s].enable(region, *offset)?;
+        let out = self.new_word(region, *offset + 1, x.value.map(|x| sigma(x, &SIGMAS[s])))?;
+        *offset += 2;
+        Ok(out)
+    }
+
+    /// Ch(x, y, z), or Maj(x, y, z) when `majority` is set.
+    fn choose(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        words: [&Word<F>; 3],
+        majority: bool,
+    ) -> Result<Word<F>, Error> {
+        for (r, word) in words.iter().enumerate() {
+            self.copy_word(region, *offset + r, word)?;
+        }
+        let value =
+            words[0].value.zip(words[1].value).zip(words[2].value).map(
+                |((x, y), z)| match majority {
+                    true => (x & y) ^ (x & z) ^ (y & z),
+                    false => (x & y) ^ (!x & z),
+                },
+            );
+        match majority {
+            true => self.q_majority.enable(region, *offset)?,
+            false => self.q_choose.enable(region, *offset)?,
+        }
+        let out = self.new_word(region, *offset + 3, value)?;
+        *offset += 4;
+        Ok(out)
+    }
+
+    /// The sum modulo 2^32 of `words` and of `constant`, which takes the place of the first of
+    /// the unused addends.
+    fn add(
+        &self,
+        region: &mut Region<F>,
+        offset: &mut usize,
+        words: &[&Word<F>],
+        constant: u32,
+    ) -> Result<Word<F>, Error> {
+        self.q_add.enable(region, *offset)?;
+        let mut sum = Value::known(constant as u64);
+        for r in 0..ADDENDS {
+            match (words.get(r), r == words.len()) {
+                (Some(word), _) => {
+                    self.copy_word(region, *offset + r, word)?;
+                    sum = sum + word.value.map(|v| v as u64);
+                }
+                (None, true) => {
+                    self.constant_word(region, *offset + r, constant)?;
+                }
+                (None, false) => {
+                    self.constant_word(region, *offset + r, 0)?;
+                }
+            }
+        }
+        let out = self.new_word(region, *offset + ADDENDS, sum.map(|s| s as u32))?;
+        region.assign_advice(
+            || "carry",
+            self.carry,
+            *offset + ADDENDS,
+            || sum.map(|s| F::from(s >> WORD_BITS)),
+        )?;
+        *offset += ADDENDS + 1;
+        Ok(out)
+    }
+}
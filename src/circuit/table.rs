@@ -50,8 +50,9 @@ impl<F: FieldExt> Table<F> {
         }
 
         let base = 2i128;
-        let smallest = -base.pow(self.bits as u32 - 1);
-        let largest = base.pow(self.bits as u32 - 1);
+        let zero_point = self.nonlinearity.zero_point();
+        let smallest = zero_point - base.pow(self.bits as u32 - 1);
+        let largest = zero_point + base.pow(self.bits as u32 - 1);
         let inputs = Tensor::from(smallest..largest);
         let evals = self.nonlinearity.f(inputs.clone())?;
 
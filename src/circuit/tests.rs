@@ -1,15 +1,152 @@
 use crate::circuit::*;
+use crate::fieldutils::i128_to_felt;
+use crate::tensor::ValType;
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Layouter, SimpleFloorPlanner, Value},
-    dev::MockProver,
-    plonk::{Circuit, ConstraintSystem, Error},
+    dev::{MockProver, VerifyFailure},
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
 };
 use halo2curves::pasta::pallas;
 use halo2curves::pasta::Fp as F;
 use rand::rngs::OsRng;
 use std::marker::PhantomData;
 
+/// A tensor of known values, of the given dims
+fn tensor(values: &[i128], dims: &[usize]) -> ValTensor<F> {
+    let mut t: ValTensor<F> =
+        Tensor::from(values.iter().map(|x| Value::known(i128_to_felt::<F>(*x)))).into();
+    t.reshape(dims).unwrap();
+    t
+}
+
+/// The columns and lookups of an [OpCircuit], shared by the ops of a test module.
+trait OpCircuitParams: Clone {
+    const K: usize;
+    const LEN: usize;
+    /// The bits of the lookup tables, as passed to [LookupOp::table_bits].
+    const BITS: usize = 4;
+    /// Whether the ops need a dynamic lookup.
+    const DYNAMIC: bool = false;
+    /// The lookups of the ops laid out.
+    fn lookups() -> Vec<LookupOp>;
+}
+
+/// Lays out a single op over `inputs` and constrains its output to the instance column. The
+/// `tampered` output, if any, is then assigned over the witness of the output cells, as a
+/// cheating prover could, such that only the constraints of the layout can reject it.
+#[derive(Clone)]
+struct OpCircuit<F: FieldExt + TensorType, P: OpCircuitParams> {
+    inputs: Vec<ValTensor<F>>,
+    op: OpKind,
+    tampered: Option<Vec<i128>>,
+    _marker: PhantomData<P>,
+}
+
+impl<F: FieldExt + TensorType, P: OpCircuitParams> OpCircuit<F, P> {
+    fn new(inputs: Vec<ValTensor<F>>, op: impl Into<OpKind>) -> Self {
+        OpCircuit {
+            inputs,
+            op: op.into(),
+            tampered: None,
+            _marker: PhantomData,
+        }
+    }
+
+    fn tampered(self, output: &[i128]) -> Self {
+        OpCircuit {
+            tampered: Some(output.to_vec()),
+            ..self
+        }
+    }
+}
+
+impl<F: FieldExt + TensorType, P: OpCircuitParams> Circuit<F> for OpCircuit<F, P> {
+    type Config = (BaseConfig<F>, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+        let a = VarTensor::new_advice(cs, P::K, P::LEN, true);
+        let b = VarTensor::new_advice(cs, P::K, P::LEN, true);
+        let output = VarTensor::new_advice(cs, P::K, P::LEN, true);
+        let mut config =
+            BaseConfig::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE, 0);
+        for nl in P::lookups() {
+            config
+                .configure_lookup(cs, &a, &b, nl.table_bits(P::BITS), &nl)
+                .unwrap();
+        }
+        if P::DYNAMIC {
+            config.configure_dynamic_lookup(cs, &a, &b).unwrap();
+        }
+        let instance = cs.instance_column();
+        cs.enable_equality(instance);
+        (config, instance)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.0.layout_tables(&mut layouter).unwrap();
+        let output = layouter.assign_region(
+            || "",
+            |mut region| {
+                if let Some(dynamic) = &config.0.dynamic_lookup {
+                    dynamic.reset();
+                }
+                let output = config
+                    .0
+                    .layout(&mut region, &self.inputs, &mut 0, self.op.clone())
+                    .map_err(|_| Error::Synthesis)?
+                    .ok_or(Error::Synthesis)?
+                    .get_inner_tensor()
+                    .map_err(|_| Error::Synthesis)?;
+                if let Some(tampered) = &self.tampered {
+                    for (e, x) in output.iter().zip(tampered) {
+                        if let ValType::PrevAssigned(cell) = e {
+                            let cell = cell.cell();
+                            region.assign_advice(
+                                || "tampered",
+                                cell.column.try_into().map_err(|_| Error::Synthesis)?,
+                                cell.row_offset,
+                                || Value::known(i128_to_felt::<F>(*x)),
+                            )?;
+                        }
+                    }
+                }
+                Ok(output)
+            },
+        )?;
+        for (row, e) in output.iter().enumerate() {
+            match e {
+                ValType::PrevAssigned(cell) => {
+                    layouter.constrain_instance(cell.cell(), config.1, row)?
+                }
+                _ => return Err(Error::Synthesis),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the mock prover on `circuit`, with `output` as its instance.
+fn prove<P: OpCircuitParams>(circuit: &OpCircuit<F, P>, output: &[i128]) -> MockProver<F> {
+    let output = output.iter().map(|x| i128_to_felt::<F>(*x)).collect();
+    MockProver::run(P::K as u32, circuit, vec![output]).unwrap()
+}
+
+/// Asserts that the circuit doesn't verify, with one of the failures being the `expected` one.
+fn assert_failure(prover: &MockProver<F>, expected: impl Fn(&VerifyFailure) -> bool) {
+    let failures = prover.verify().unwrap_err();
+    assert!(failures.iter().any(expected), "{:?}", failures);
+}
+
 #[cfg(test)]
 mod matmul {
     use super::*;
@@ -149,6 +286,78 @@ mod matmul_col_overflow {
     }
 }
 
+#[cfg(test)]
+mod einsum {
+    use super::*;
+
+    const K: usize = 9;
+    const LEN: usize = 64;
+
+    #[derive(Clone)]
+    struct EinsumCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 2],
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for EinsumCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::Einsum("bij,bkj->bki".to_string()).into(),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn einsumcircuit() {
+        // parameters
+        let mut a = Tensor::from((0..2 * 3 * 4).map(|i| Value::known(F::from((i + 1) as u64))));
+        a.reshape(&[2, 3, 4]);
+
+        let mut w = Tensor::from((0..2 * 2 * 4).map(|i| Value::known(F::from((i + 1) as u64))));
+        w.reshape(&[2, 2, 4]);
+
+        let circuit = EinsumCircuit::<F> {
+            inputs: [ValTensor::from(a), ValTensor::from(w)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
 #[cfg(test)]
 mod dot {
     use super::*;
@@ -722,6 +931,8 @@ mod conv {
     #[derive(Clone)]
     struct ConvCircuit<F: FieldExt + TensorType> {
         inputs: Vec<ValTensor<F>>,
+        stride: (usize, usize),
+        lowering: ConvLowering,
         _marker: PhantomData<F>,
     }
 
@@ -756,7 +967,9 @@ mod conv {
                                 &mut 0,
                                 Op::Conv {
                                     padding: (1, 1),
-                                    stride: (2, 2),
+                                    stride: self.stride,
+                                    group: 1,
+                                    lowering: self.lowering,
                                 }
                                 .into(),
                             )
@@ -799,6 +1012,8 @@ mod conv {
                 ValTensor::from(bias),
             ]
             .to_vec(),
+            stride: (2, 2),
+            lowering: ConvLowering::Direct,
             _marker: PhantomData,
         };
 
@@ -828,30 +1043,109 @@ mod conv {
 
         let circuit = ConvCircuit::<F> {
             inputs: [ValTensor::from(image), ValTensor::from(kernels)].to_vec(),
+            stride: (2, 2),
+            lowering: ConvLowering::Direct,
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn im2colconvcircuit() {
+        // parameters
+        let kernel_height = 3;
+        let kernel_width = 2;
+        let image_height = 5;
+        let image_width = 4;
+        let in_channels = 2;
+        let out_channels = 3;
+
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width).map(|i| Value::known(F::from(i as u64))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
+        let mut kernels = Tensor::from(
+            (0..{ out_channels * in_channels * kernel_height * kernel_width })
+                .map(|i| Value::known(F::from(i as u64))),
+        );
+        kernels.reshape(&[out_channels, in_channels, kernel_height, kernel_width]);
+
+        let bias = Tensor::from((0..{ out_channels }).map(|i| Value::known(F::from(i as u64))));
+
+        let circuit = ConvCircuit::<F> {
+            inputs: [
+                ValTensor::from(image),
+                ValTensor::from(kernels),
+                ValTensor::from(bias),
+            ]
+            .to_vec(),
+            stride: (2, 2),
+            lowering: ConvLowering::Im2col,
             _marker: PhantomData,
         };
 
         let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
+
+    #[test]
+    fn winogradconvcircuit() {
+        // parameters
+        let image_height = 5;
+        let image_width = 6;
+        let in_channels = 2;
+        let out_channels = 3;
+
+        for tile in [2, 4] {
+            let mut image = Tensor::from(
+                (0..in_channels * image_height * image_width)
+                    .map(|i| Value::known(F::from(i as u64))),
+            );
+            image.reshape(&[in_channels, image_height, image_width]);
+            let mut kernels = Tensor::from(
+                (0..{ out_channels * in_channels * 3 * 3 })
+                    .map(|i| Value::known(F::from(i as u64))),
+            );
+            kernels.reshape(&[out_channels, in_channels, 3, 3]);
+
+            let bias =
+                Tensor::from((0..{ out_channels }).map(|i| Value::known(F::from(i as u64))));
+
+            let circuit = ConvCircuit::<F> {
+                inputs: [
+                    ValTensor::from(image),
+                    ValTensor::from(kernels),
+                    ValTensor::from(bias),
+                ]
+                .to_vec(),
+                stride: (1, 1),
+                lowering: ConvLowering::Winograd { tile },
+                _marker: PhantomData,
+            };
+
+            let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+            prover.assert_satisfied();
+        }
+    }
 }
 
 #[cfg(test)]
-mod sumpool {
-    use halo2_proofs::arithmetic::Field;
-
+mod conv_relu {
     use super::*;
+    use crate::fieldutils::i128_to_felt;
 
-    const K: usize = 20;
-    const LEN: usize = 100;
+    const K: usize = 10;
+    const LEN: usize = 256;
 
     #[derive(Clone)]
-    struct ConvCircuit<F: FieldExt + TensorType> {
+    struct ConvReLUCircuit<F: FieldExt + TensorType> {
         inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for ConvCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for ConvReLUCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -860,10 +1154,15 @@ mod sumpool {
         }
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
-            let a = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
-            let b = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
-            let output = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
-            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+            let mut config = Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0);
+            // the relu is looked up from the output column
+            config
+                .configure_fused_lookup(cs, 8, &LookupOp::ReLU { scale: 1 })
+                .unwrap();
+            config
         }
 
         fn synthesize(
@@ -871,6 +1170,7 @@ mod sumpool {
             mut config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
             layouter
                 .assign_region(
                     || "",
@@ -880,10 +1180,11 @@ mod sumpool {
                                 &mut region,
                                 &self.inputs.clone(),
                                 &mut 0,
-                                Op::SumPool {
-                                    padding: (0, 0),
+                                Op::ConvReLU {
+                                    padding: (1, 1),
                                     stride: (1, 1),
-                                    kernel_shape: (3, 3),
+                                    scale: 1,
+                                    lowering: ConvLowering::Direct,
                                 }
                                 .into(),
                             )
@@ -896,19 +1197,35 @@ mod sumpool {
     }
 
     #[test]
-    fn sumpoolcircuit() {
-        let image_height = 5;
-        let image_width = 5;
-        let in_channels = 1;
+    fn convrelucircuit() {
+        let kernel_height = 2;
+        let kernel_width = 2;
+        let image_height = 3;
+        let image_width = 3;
+        let in_channels = 2;
+        let out_channels = 2;
 
         let mut image = Tensor::from(
             (0..in_channels * image_height * image_width)
-                .map(|_| Value::known(pallas::Base::random(OsRng))),
+                .map(|i| Value::known(i128_to_felt::<F>((i as i128 * 7) % 5 - 2))),
         );
         image.reshape(&[in_channels, image_height, image_width]);
+        let mut kernels = Tensor::from(
+            (0..{ out_channels * in_channels * kernel_height * kernel_width })
+                .map(|i| Value::known(i128_to_felt::<F>(i as i128 % 3 - 1))),
+        );
+        kernels.reshape(&[out_channels, in_channels, kernel_height, kernel_width]);
+        let bias = Tensor::from(
+            (0..out_channels).map(|i| Value::known(i128_to_felt::<F>(i as i128 - 1))),
+        );
 
-        let circuit = ConvCircuit::<F> {
-            inputs: [ValTensor::from(image)].to_vec(),
+        let circuit = ConvReLUCircuit::<F> {
+            inputs: [
+                ValTensor::from(image),
+                ValTensor::from(kernels),
+                ValTensor::from(bias),
+            ]
+            .to_vec(),
             _marker: PhantomData,
         };
 
@@ -918,19 +1235,22 @@ mod sumpool {
 }
 
 #[cfg(test)]
-mod add_w_shape_casting {
+mod grouped_conv {
+    use halo2_proofs::arithmetic::Field;
+
     use super::*;
 
-    const K: usize = 4;
-    const LEN: usize = 4;
+    const K: usize = 20;
+    const LEN: usize = 100;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 2],
+    struct ConvCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
+        group: usize,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for ConvCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -939,10 +1259,9 @@ mod add_w_shape_casting {
         }
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
-            let a = VarTensor::new_advice(cs, K, LEN, true);
-            let b = VarTensor::new_advice(cs, K, LEN, true);
-            let output = VarTensor::new_advice(cs, K, LEN, true);
-
+            let a = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let b = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let output = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
             Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
@@ -956,7 +1275,18 @@ mod add_w_shape_casting {
                     || "",
                     |mut region| {
                         config
-                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Add.into())
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::Conv {
+                                    padding: (1, 1),
+                                    stride: (1, 1),
+                                    group: self.group,
+                                    lowering: ConvLowering::Direct,
+                                }
+                                .into(),
+                            )
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -965,37 +1295,74 @@ mod add_w_shape_casting {
         }
     }
 
-    #[test]
-    fn addcircuit() {
+    fn run(in_channels: usize, out_channels: usize, group: usize) {
         // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let kernel_height = 3;
+        let kernel_width = 3;
+        let image_height = 4;
+        let image_width = 4;
 
-        let b = Tensor::from((0..1).map(|i| Value::known(F::from(i as u64 + 1))));
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width)
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
+        let mut kernels = Tensor::from(
+            (0..{ out_channels * (in_channels / group) * kernel_height * kernel_width })
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        kernels.reshape(&[
+            out_channels,
+            in_channels / group,
+            kernel_height,
+            kernel_width,
+        ]);
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a), ValTensor::from(b)],
+        let bias =
+            Tensor::from((0..{ out_channels }).map(|_| Value::known(pallas::Base::random(OsRng))));
+
+        let circuit = ConvCircuit::<F> {
+            inputs: [
+                ValTensor::from(image),
+                ValTensor::from(kernels),
+                ValTensor::from(bias),
+            ]
+            .to_vec(),
+            group,
             _marker: PhantomData,
         };
 
         let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
+
+    #[test]
+    fn depthwiseconvcircuit() {
+        run(3, 3, 3);
+    }
+
+    #[test]
+    fn groupedconvcircuit() {
+        run(4, 6, 2);
+    }
 }
 
 #[cfg(test)]
-mod add {
+mod deconv {
+    use halo2_proofs::arithmetic::Field;
+
     use super::*;
 
-    const K: usize = 4;
-    const LEN: usize = 4;
+    const K: usize = 20;
+    const LEN: usize = 100;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 2],
+    struct DeConvCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for DeConvCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1004,10 +1371,9 @@ mod add {
         }
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
-            let a = VarTensor::new_advice(cs, K, LEN, true);
-            let b = VarTensor::new_advice(cs, K, LEN, true);
-            let output = VarTensor::new_advice(cs, K, LEN, true);
-
+            let a = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let b = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let output = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
             Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
@@ -1021,7 +1387,17 @@ mod add {
                     || "",
                     |mut region| {
                         config
-                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Add.into())
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::DeConv {
+                                    padding: (1, 1),
+                                    output_padding: (1, 1),
+                                    stride: (2, 2),
+                                }
+                                .into(),
+                            )
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1031,14 +1407,36 @@ mod add {
     }
 
     #[test]
-    fn addcircuit() {
+    fn deconvcircuit() {
         // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let kernel_height = 3;
+        let kernel_width = 3;
+        let image_height = 3;
+        let image_width = 4;
+        let in_channels = 2;
+        let out_channels = 3;
 
-        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width)
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
+        let mut kernels = Tensor::from(
+            (0..{ in_channels * out_channels * kernel_height * kernel_width })
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        kernels.reshape(&[in_channels, out_channels, kernel_height, kernel_width]);
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a), ValTensor::from(b)],
+        let bias =
+            Tensor::from((0..{ out_channels }).map(|_| Value::known(pallas::Base::random(OsRng))));
+
+        let circuit = DeConvCircuit::<F> {
+            inputs: [
+                ValTensor::from(image),
+                ValTensor::from(kernels),
+                ValTensor::from(bias),
+            ]
+            .to_vec(),
             _marker: PhantomData,
         };
 
@@ -1048,19 +1446,21 @@ mod add {
 }
 
 #[cfg(test)]
-mod add_with_overflow {
+mod sumpool {
+    use halo2_proofs::arithmetic::Field;
+
     use super::*;
 
-    const K: usize = 4;
-    const LEN: usize = 50;
+    const K: usize = 20;
+    const LEN: usize = 100;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 2],
+    struct ConvCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for ConvCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1069,10 +1469,9 @@ mod add_with_overflow {
         }
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
-            let a = VarTensor::new_advice(cs, K, LEN, true);
-            let b = VarTensor::new_advice(cs, K, LEN, true);
-            let output = VarTensor::new_advice(cs, K, LEN, true);
-
+            let a = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let b = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let output = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
             Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
@@ -1086,7 +1485,17 @@ mod add_with_overflow {
                     || "",
                     |mut region| {
                         config
-                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Add.into())
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::SumPool {
+                                    padding: (0, 0),
+                                    stride: (1, 1),
+                                    kernel_shape: (3, 3),
+                                }
+                                .into(),
+                            )
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1096,14 +1505,19 @@ mod add_with_overflow {
     }
 
     #[test]
-    fn addcircuit() {
-        // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+    fn sumpoolcircuit() {
+        let image_height = 5;
+        let image_width = 5;
+        let in_channels = 1;
 
-        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width)
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a), ValTensor::from(b)],
+        let circuit = ConvCircuit::<F> {
+            inputs: [ValTensor::from(image)].to_vec(),
             _marker: PhantomData,
         };
 
@@ -1113,19 +1527,20 @@ mod add_with_overflow {
 }
 
 #[cfg(test)]
-mod sub {
+mod maxpool {
     use super::*;
+    use crate::fieldutils::i128_to_felt;
 
-    const K: usize = 4;
-    const LEN: usize = 4;
+    const K: usize = 10;
+    const LEN: usize = 512;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 2],
+    struct MaxPoolCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for MaxPoolCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1137,8 +1552,13 @@ mod sub {
             let a = VarTensor::new_advice(cs, K, LEN, true);
             let b = VarTensor::new_advice(cs, K, LEN, true);
             let output = VarTensor::new_advice(cs, K, LEN, true);
-
-            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+            let mut config =
+                Self::Config::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE, 0);
+            // max pooling is enforced using relu lookups
+            config
+                .configure_lookup(cs, &a, &b, 8, &LookupOp::ReLU { scale: 1 })
+                .unwrap();
+            config
         }
 
         fn synthesize(
@@ -1146,12 +1566,23 @@ mod sub {
             mut config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
             layouter
                 .assign_region(
                     || "",
                     |mut region| {
                         config
-                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Sub.into())
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                LookupOp::MaxPool2D {
+                                    padding: (1, 1),
+                                    stride: (2, 2),
+                                    pool_dims: (3, 3),
+                                }
+                                .into(),
+                            )
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1161,14 +1592,42 @@ mod sub {
     }
 
     #[test]
-    fn subcircuit() {
-        // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+    fn maxpoolcircuit() {
+        let image_height = 5;
+        let image_width = 5;
+        let in_channels = 2;
 
-        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width)
+                .map(|i| Value::known(i128_to_felt::<F>((i as i128 * 7) % 23 - 11))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a), ValTensor::from(b)],
+        let circuit = MaxPoolCircuit::<F> {
+            inputs: [ValTensor::from(image)].to_vec(),
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn maxpoolcircuit_negative() {
+        // the padded windows of a negative image have a negative max, which SAFE mode checks
+        // against the reference
+        let image_height = 5;
+        let image_width = 5;
+        let in_channels = 2;
+
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width)
+                .map(|i| Value::known(i128_to_felt::<F>(-((i as i128 * 7) % 23) - 1))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
+
+        let circuit = MaxPoolCircuit::<F> {
+            inputs: [ValTensor::from(image)].to_vec(),
             _marker: PhantomData,
         };
 
@@ -1178,19 +1637,20 @@ mod sub {
 }
 
 #[cfg(test)]
-mod mult {
+mod avgpool {
     use super::*;
+    use crate::fieldutils::i128_to_felt;
 
-    const K: usize = 4;
-    const LEN: usize = 4;
+    const K: usize = 10;
+    const LEN: usize = 512;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 2],
+    struct AvgPoolCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for AvgPoolCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1202,8 +1662,21 @@ mod mult {
             let a = VarTensor::new_advice(cs, K, LEN, true);
             let b = VarTensor::new_advice(cs, K, LEN, true);
             let output = VarTensor::new_advice(cs, K, LEN, true);
-
-            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+            let mut config =
+                Self::Config::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE, 0);
+            // the window sums are divided using a division lookup
+            config
+                .configure_lookup(
+                    cs,
+                    &a,
+                    &b,
+                    8,
+                    &LookupOp::Div {
+                        denom: eq_float::F32(4.0),
+                    },
+                )
+                .unwrap();
+            config
         }
 
         fn synthesize(
@@ -1211,12 +1684,24 @@ mod mult {
             mut config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
             layouter
                 .assign_region(
                     || "",
                     |mut region| {
                         config
-                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Mult.into())
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                LookupOp::AvgPool2D {
+                                    padding: (0, 0),
+                                    stride: (1, 1),
+                                    pool_dims: (2, 2),
+                                    remainder: false,
+                                }
+                                .into(),
+                            )
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1226,14 +1711,19 @@ mod mult {
     }
 
     #[test]
-    fn multcircuit() {
-        // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+    fn avgpoolcircuit() {
+        let image_height = 4;
+        let image_width = 4;
+        let in_channels = 2;
 
-        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let mut image = Tensor::from(
+            (0..in_channels * image_height * image_width)
+                .map(|i| Value::known(i128_to_felt::<F>((i as i128 * 7) % 23 - 11))),
+        );
+        image.reshape(&[in_channels, image_height, image_width]);
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a), ValTensor::from(b)],
+        let circuit = AvgPoolCircuit::<F> {
+            inputs: [ValTensor::from(image)].to_vec(),
             _marker: PhantomData,
         };
 
@@ -1243,19 +1733,21 @@ mod mult {
 }
 
 #[cfg(test)]
-mod pow {
+mod conv3d {
+    use halo2_proofs::arithmetic::Field;
+
     use super::*;
 
-    const K: usize = 8;
-    const LEN: usize = 4;
+    const K: usize = 20;
+    const LEN: usize = 100;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 1],
+    struct Conv3DCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for Conv3DCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1264,10 +1756,9 @@ mod pow {
         }
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
-            let a = VarTensor::new_advice(cs, K, LEN, true);
-            let b = VarTensor::new_advice(cs, K, LEN, true);
-            let output = VarTensor::new_advice(cs, K, LEN, true);
-
+            let a = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let b = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
+            let output = VarTensor::new_advice(cs, K, (LEN + 1) * LEN, true);
             Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
@@ -1281,7 +1772,16 @@ mod pow {
                     || "",
                     |mut region| {
                         config
-                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Pow(5).into())
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::Conv3D {
+                                    padding: (1, 1, 0),
+                                    stride: (2, 1, 1),
+                                }
+                                .into(),
+                            )
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1291,12 +1791,44 @@ mod pow {
     }
 
     #[test]
-    fn powcircuit() {
+    fn conv3dcircuit() {
         // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        let kernel_depth = 2;
+        let kernel_height = 2;
+        let kernel_width = 2;
+        let image_depth = 3;
+        let image_height = 3;
+        let image_width = 3;
+        let in_channels = 2;
+        let out_channels = 2;
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a)],
+        let mut image = Tensor::from(
+            (0..in_channels * image_depth * image_height * image_width)
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        image.reshape(&[in_channels, image_depth, image_height, image_width]);
+        let mut kernels = Tensor::from(
+            (0..{ out_channels * in_channels * kernel_depth * kernel_height * kernel_width })
+                .map(|_| Value::known(pallas::Base::random(OsRng))),
+        );
+        kernels.reshape(&[
+            out_channels,
+            in_channels,
+            kernel_depth,
+            kernel_height,
+            kernel_width,
+        ]);
+
+        let bias =
+            Tensor::from((0..{ out_channels }).map(|_| Value::known(pallas::Base::random(OsRng))));
+
+        let circuit = Conv3DCircuit::<F> {
+            inputs: [
+                ValTensor::from(image),
+                ValTensor::from(kernels),
+                ValTensor::from(bias),
+            ]
+            .to_vec(),
             _marker: PhantomData,
         };
 
@@ -1306,19 +1838,20 @@ mod pow {
 }
 
 #[cfg(test)]
-mod pack {
+mod maxpool3d {
     use super::*;
+    use crate::fieldutils::i128_to_felt;
 
-    const K: usize = 8;
-    const LEN: usize = 4;
+    const K: usize = 10;
+    const LEN: usize = 512;
 
     #[derive(Clone)]
-    struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 1],
+    struct MaxPoolCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValTensor<F>>,
         _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for MaxPoolCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1330,8 +1863,13 @@ mod pack {
             let a = VarTensor::new_advice(cs, K, LEN, true);
             let b = VarTensor::new_advice(cs, K, LEN, true);
             let output = VarTensor::new_advice(cs, K, LEN, true);
-
-            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+            let mut config =
+                Self::Config::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE, 0);
+            // max pooling is enforced using relu lookups
+            config
+                .configure_lookup(cs, &a, &b, 8, &LookupOp::ReLU { scale: 1 })
+                .unwrap();
+            config
         }
 
         fn synthesize(
@@ -1339,6 +1877,7 @@ mod pack {
             mut config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
             layouter
                 .assign_region(
                     || "",
@@ -1348,7 +1887,12 @@ mod pack {
                                 &mut region,
                                 &self.inputs.clone(),
                                 &mut 0,
-                                Op::Pack(2, 1).into(),
+                                LookupOp::MaxPool3D {
+                                    padding: (0, 1, 1),
+                                    stride: (1, 2, 2),
+                                    pool_dims: (2, 2, 2),
+                                }
+                                .into(),
                             )
                             .map_err(|_| Error::Synthesis)
                     },
@@ -1359,30 +1903,62 @@ mod pack {
     }
 
     #[test]
-    fn packcircuit() {
-        // parameters
-        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+    fn maxpool3dcircuit() {
+        let image_depth = 3;
+        let image_height = 4;
+        let image_width = 4;
+        let in_channels = 2;
 
-        let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a)],
+        let mut image = Tensor::from(
+            (0..in_channels * image_depth * image_height * image_width)
+                .map(|i| Value::known(i128_to_felt::<F>((i as i128 * 7) % 23 - 11))),
+        );
+        image.reshape(&[in_channels, image_depth, image_height, image_width]);
+
+        let circuit = MaxPoolCircuit::<F> {
+            inputs: [ValTensor::from(image)].to_vec(),
             _marker: PhantomData,
         };
 
         let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
-}
 
-#[cfg(test)]
-mod rescaled {
-    use super::*;
+    #[test]
+    fn maxpool3dcircuit_negative() {
+        // the padded windows of a negative image have a negative max, which SAFE mode checks
+        // against the reference
+        let image_depth = 3;
+        let image_height = 4;
+        let image_width = 4;
+        let in_channels = 2;
 
-    const K: usize = 8;
+        let mut image = Tensor::from(
+            (0..in_channels * image_depth * image_height * image_width)
+                .map(|i| Value::known(i128_to_felt::<F>(-((i as i128 * 7) % 23) - 1))),
+        );
+        image.reshape(&[in_channels, image_depth, image_height, image_width]);
+
+        let circuit = MaxPoolCircuit::<F> {
+            inputs: [ValTensor::from(image)].to_vec(),
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod add_w_shape_casting {
+    use super::*;
+
+    const K: usize = 4;
     const LEN: usize = 4;
 
     #[derive(Clone)]
     struct MyCircuit<F: FieldExt + TensorType> {
-        inputs: [ValTensor<F>; 1],
+        inputs: [ValTensor<F>; 2],
         _marker: PhantomData<F>,
     }
 
@@ -1412,16 +1988,7 @@ mod rescaled {
                     || "",
                     |mut region| {
                         config
-                            .layout(
-                                &mut region,
-                                &self.inputs.clone(),
-                                &mut 0,
-                                Op::Rescaled {
-                                    inner: Box::new(Op::Sum),
-                                    scale: vec![(0, 5)],
-                                }
-                                .into(),
-                            )
+                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Add.into())
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1431,13 +1998,14 @@ mod rescaled {
     }
 
     #[test]
-    fn rescaledcircuit() {
+    fn addcircuit() {
         // parameters
-        let mut a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
-        a.reshape(&[LEN, 1]);
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let b = Tensor::from((0..1).map(|i| Value::known(F::from(i as u64 + 1))));
 
         let circuit = MyCircuit::<F> {
-            inputs: [ValTensor::from(a)],
+            inputs: [ValTensor::from(a), ValTensor::from(b)],
             _marker: PhantomData,
         };
 
@@ -1447,12 +2015,11 @@ mod rescaled {
 }
 
 #[cfg(test)]
-mod matmul_relu {
+mod add {
     use super::*;
 
-    const K: usize = 18;
-    const LEN: usize = 32;
-    use crate::circuit::LookupOp;
+    const K: usize = 4;
+    const LEN: usize = 4;
 
     #[derive(Clone)]
     struct MyCircuit<F: FieldExt + TensorType> {
@@ -1460,14 +2027,8 @@ mod matmul_relu {
         _marker: PhantomData<F>,
     }
 
-    // A columnar ReLu MLP
-    #[derive(Clone)]
-    struct MyConfig<F: FieldExt + TensorType> {
-        base_config: BaseConfig<F>,
-    }
-
     impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
-        type Config = MyConfig<F>;
+        type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
         fn without_witnesses(&self) -> Self {
@@ -1479,14 +2040,7 @@ mod matmul_relu {
             let b = VarTensor::new_advice(cs, K, LEN, true);
             let output = VarTensor::new_advice(cs, K, LEN, true);
 
-            let mut base_config =
-                BaseConfig::configure(cs, &[a, b.clone()], &output, CheckMode::SAFE, 0);
-            // sets up a new relu table
-            base_config
-                .configure_lookup(cs, &b, &output, 16, &LookupOp::ReLU { scale: 1 })
-                .unwrap();
-
-            MyConfig { base_config }
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
         fn synthesize(
@@ -1494,44 +2048,28 @@ mod matmul_relu {
             mut config: Self::Config,
             mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
-            config.base_config.layout_tables(&mut layouter).unwrap();
-            layouter.assign_region(
-                || "",
-                |mut region| {
-                    let op = Op::Matmul;
-                    let mut offset = 0;
-                    let output = config
-                        .base_config
-                        .layout(&mut region, &self.inputs, &mut offset, op.into())
-                        .unwrap();
-                    let _output = config
-                        .base_config
-                        .layout(
-                            &mut region,
-                            &[output.unwrap()],
-                            &mut offset,
-                            LookupOp::ReLU { scale: 1 }.into(),
-                        )
-                        .unwrap();
-                    Ok(())
-                },
-            )?;
-
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Add.into())
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
             Ok(())
         }
     }
 
     #[test]
-    fn matmulrelucircuit() {
+    fn addcircuit() {
         // parameters
-        let mut a = Tensor::from((0..LEN * LEN).map(|_| Value::known(F::from(1))));
-        a.reshape(&[LEN, LEN]);
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
 
-        // parameters
-        let mut b = Tensor::from((0..LEN).map(|_| Value::known(F::from(1))));
-        b.reshape(&[LEN, 1]);
+        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
 
-        let circuit = MyCircuit {
+        let circuit = MyCircuit::<F> {
             inputs: [ValTensor::from(a), ValTensor::from(b)],
             _marker: PhantomData,
         };
@@ -1542,27 +2080,16 @@ mod matmul_relu {
 }
 
 #[cfg(test)]
-mod rangecheck {
-
-    use crate::tensor::Tensor;
-    use halo2_proofs::{
-        arithmetic::FieldExt,
-        circuit::{Layouter, SimpleFloorPlanner, Value},
-        dev::MockProver,
-        plonk::{Circuit, ConstraintSystem, Error},
-    };
-    use halo2curves::pasta::Fp;
-
-    const RANGE: usize = 8; // 3-bit value
-    const K: usize = 8;
-    const LEN: usize = 4;
-
+mod add_with_overflow {
     use super::*;
 
+    const K: usize = 4;
+    const LEN: usize = 50;
+
     #[derive(Clone)]
     struct MyCircuit<F: FieldExt + TensorType> {
-        input: ValTensor<F>,
-        output: ValTensor<F>,
+        inputs: [ValTensor<F>; 2],
+        _marker: PhantomData<F>,
     }
 
     impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
@@ -1578,7 +2105,7 @@ mod rangecheck {
             let b = VarTensor::new_advice(cs, K, LEN, true);
             let output = VarTensor::new_advice(cs, K, LEN, true);
 
-            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, RANGE as i32)
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
         fn synthesize(
@@ -1591,12 +2118,7 @@ mod rangecheck {
                     || "",
                     |mut region| {
                         config
-                            .layout(
-                                &mut region,
-                                &[self.input.clone(), self.output.clone()],
-                                &mut 0,
-                                Op::RangeCheck(RANGE as i32).into(),
-                            )
+                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Add.into())
                             .map_err(|_| Error::Synthesis)
                     },
                 )
@@ -1606,59 +2128,36 @@ mod rangecheck {
     }
 
     #[test]
-    #[allow(clippy::assertions_on_constants)]
-    fn test_range_check() {
-        let k = 4;
+    fn addcircuit() {
+        // parameters
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
 
-        // Successful cases
-        for i in 0..RANGE {
-            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(i as u64))]), &[1]).unwrap();
-            let out =
-                Tensor::new(Some(&[Value::<Fp>::known(Fp::from(i as u64 + 1))]), &[1]).unwrap();
-            let circuit = MyCircuit::<Fp> {
-                input: ValTensor::from(inp),
-                output: ValTensor::from(out),
-            };
-            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-            prover.assert_satisfied();
-        }
-        {
-            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(22_u64))]), &[1]).unwrap();
-            let out = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(0_u64))]), &[1]).unwrap();
-            let circuit = MyCircuit::<Fp> {
-                input: ValTensor::from(inp),
-                output: ValTensor::from(out),
-            };
-            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
-            match prover.verify() {
-                Ok(_) => {
-                    assert!(false)
-                }
-                Err(_) => {
-                    assert!(true)
-                }
-            }
-        }
+        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a), ValTensor::from(b)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
     }
 }
 
 #[cfg(test)]
-mod relu {
+mod sub {
     use super::*;
-    use halo2_proofs::{
-        arithmetic::FieldExt,
-        circuit::{Layouter, SimpleFloorPlanner, Value},
-        dev::MockProver,
-        plonk::{Circuit, ConstraintSystem, Error},
-    };
-    use halo2curves::pasta::Fp as F;
+
+    const K: usize = 4;
+    const LEN: usize = 4;
 
     #[derive(Clone)]
-    struct ReLUCircuit<F: FieldExt + TensorType> {
-        pub input: ValTensor<F>,
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 2],
+        _marker: PhantomData<F>,
     }
 
-    impl<F: FieldExt + TensorType> Circuit<F> for ReLUCircuit<F> {
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
         type Config = BaseConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
@@ -1667,56 +2166,2300 @@ mod relu {
         }
 
         fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
-            let advices = (0..2)
-                .map(|_| VarTensor::new_advice(cs, 4, 3, true))
-                .collect::<Vec<_>>();
-
-            let nl = LookupOp::ReLU { scale: 1 };
-
-            let mut config = BaseConfig::default();
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
 
-            config
-                .configure_lookup(cs, &advices[0], &advices[1], 2, &nl)
-                .unwrap();
-            config
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
         }
 
         fn synthesize(
             &self,
             mut config: Self::Config,
-            mut layouter: impl Layouter<F>, // layouter is our 'write buffer' for the circuit
+            mut layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
-            config.layout_tables(&mut layouter).unwrap();
             layouter
                 .assign_region(
                     || "",
                     |mut region| {
                         config
-                            .layout(
-                                &mut region,
-                                &[self.input.clone()],
-                                &mut 0,
-                                LookupOp::ReLU { scale: 1 }.into(),
-                            )
+                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Sub.into())
                             .map_err(|_| Error::Synthesis)
                     },
                 )
                 .unwrap();
-
             Ok(())
         }
     }
 
     #[test]
-    fn relucircuit() {
-        let input: Tensor<Value<F>> =
-            Tensor::new(Some(&[Value::<F>::known(F::from(1_u64)); 4]), &[4]).unwrap();
+    fn subcircuit() {
+        // parameters
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
 
-        let circuit = ReLUCircuit::<F> {
-            input: ValTensor::from(input),
+        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a), ValTensor::from(b)],
+            _marker: PhantomData,
         };
 
-        let prover = MockProver::run(4_u32, &circuit, vec![]).unwrap();
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
 }
+
+#[cfg(test)]
+mod mult {
+    use super::*;
+
+    const K: usize = 4;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 2],
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Mult.into())
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn multcircuit() {
+        // parameters
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let b = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a), ValTensor::from(b)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod pow {
+    use super::*;
+
+    const K: usize = 8;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 1],
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(&mut region, &self.inputs.clone(), &mut 0, Op::Pow(5).into())
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn powcircuit() {
+        // parameters
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod pack {
+    use super::*;
+
+    const K: usize = 8;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 1],
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::Pack(2, 1).into(),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn packcircuit() {
+        // parameters
+        let a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod rescaled {
+    use super::*;
+
+    const K: usize = 8;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 1],
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(
+                                &mut region,
+                                &self.inputs.clone(),
+                                &mut 0,
+                                Op::Rescaled {
+                                    inner: Box::new(Op::Sum),
+                                    scale: vec![(0, 5)],
+                                }
+                                .into(),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn rescaledcircuit() {
+        // parameters
+        let mut a = Tensor::from((0..LEN).map(|i| Value::known(F::from(i as u64 + 1))));
+        a.reshape(&[LEN, 1]);
+
+        let circuit = MyCircuit::<F> {
+            inputs: [ValTensor::from(a)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod matmul_relu {
+    use super::*;
+
+    const K: usize = 18;
+    const LEN: usize = 32;
+    use crate::circuit::LookupOp;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: [ValTensor<F>; 2],
+        _marker: PhantomData<F>,
+    }
+
+    // A columnar ReLu MLP
+    #[derive(Clone)]
+    struct MyConfig<F: FieldExt + TensorType> {
+        base_config: BaseConfig<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+
+            let mut base_config =
+                BaseConfig::configure(cs, &[a, b.clone()], &output, CheckMode::SAFE, 0);
+            // sets up a new relu table
+            base_config
+                .configure_lookup(cs, &b, &output, 16, &LookupOp::ReLU { scale: 1 })
+                .unwrap();
+
+            MyConfig { base_config }
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.base_config.layout_tables(&mut layouter).unwrap();
+            layouter.assign_region(
+                || "",
+                |mut region| {
+                    let op = Op::Matmul;
+                    let mut offset = 0;
+                    let output = config
+                        .base_config
+                        .layout(&mut region, &self.inputs, &mut offset, op.into())
+                        .unwrap();
+                    let _output = config
+                        .base_config
+                        .layout(
+                            &mut region,
+                            &[output.unwrap()],
+                            &mut offset,
+                            LookupOp::ReLU { scale: 1 }.into(),
+                        )
+                        .unwrap();
+                    Ok(())
+                },
+            )?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn matmulrelucircuit() {
+        // parameters
+        let mut a = Tensor::from((0..LEN * LEN).map(|_| Value::known(F::from(1))));
+        a.reshape(&[LEN, LEN]);
+
+        // parameters
+        let mut b = Tensor::from((0..LEN).map(|_| Value::known(F::from(1))));
+        b.reshape(&[LEN, 1]);
+
+        let circuit = MyCircuit {
+            inputs: [ValTensor::from(a), ValTensor::from(b)],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod rangecheck {
+
+    use crate::tensor::Tensor;
+    use halo2_proofs::{
+        arithmetic::FieldExt,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::pasta::Fp;
+
+    const RANGE: usize = 8; // 3-bit value
+    const K: usize = 8;
+    const LEN: usize = 4;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        input: ValTensor<F>,
+        output: ValTensor<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, RANGE as i32)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone(), self.output.clone()],
+                                &mut 0,
+                                Op::RangeCheck(RANGE as i32).into(),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
+    fn test_range_check() {
+        let k = 4;
+
+        // Successful cases
+        for i in 0..RANGE {
+            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(i as u64))]), &[1]).unwrap();
+            let out =
+                Tensor::new(Some(&[Value::<Fp>::known(Fp::from(i as u64 + 1))]), &[1]).unwrap();
+            let circuit = MyCircuit::<Fp> {
+                input: ValTensor::from(inp),
+                output: ValTensor::from(out),
+            };
+            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+            prover.assert_satisfied();
+        }
+        {
+            let inp = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(22_u64))]), &[1]).unwrap();
+            let out = Tensor::new(Some(&[Value::<Fp>::known(Fp::from(0_u64))]), &[1]).unwrap();
+            let circuit = MyCircuit::<Fp> {
+                input: ValTensor::from(inp),
+                output: ValTensor::from(out),
+            };
+            let prover = MockProver::run(k, &circuit, vec![]).unwrap();
+            match prover.verify() {
+                Ok(_) => {
+                    assert!(false)
+                }
+                Err(_) => {
+                    assert!(true)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod relu {
+    use super::*;
+    use halo2_proofs::{
+        arithmetic::FieldExt,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::pasta::Fp as F;
+
+    #[derive(Clone)]
+    struct ReLUCircuit<F: FieldExt + TensorType> {
+        pub input: ValTensor<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for ReLUCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = (0..2)
+                .map(|_| VarTensor::new_advice(cs, 4, 3, true))
+                .collect::<Vec<_>>();
+
+            let nl = LookupOp::ReLU { scale: 1 };
+
+            let mut config = BaseConfig::default();
+
+            config
+                .configure_lookup(cs, &advices[0], &advices[1], 2, &nl)
+                .unwrap();
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>, // layouter is our 'write buffer' for the circuit
+        ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone()],
+                                &mut 0,
+                                LookupOp::ReLU { scale: 1 }.into(),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn relucircuit() {
+        let input: Tensor<Value<F>> =
+            Tensor::new(Some(&[Value::<F>::known(F::from(1_u64)); 4]), &[4]).unwrap();
+
+        let circuit = ReLUCircuit::<F> {
+            input: ValTensor::from(input),
+        };
+
+        let prover = MockProver::run(4_u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod div_rem {
+    use super::*;
+
+    const DENOM: i128 = 7;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 8;
+        const LEN: usize = 4;
+
+        fn lookups() -> Vec<LookupOp> {
+            // the quotient and the remainder are looked up in tables of their own
+            (LookupOp::DivRem { denom: DENOM }).table_ops()
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        // the inputs span far more than the 4 bits of the quotient's table
+        let input = tensor(&[-50, -4, 3, 48], &[4]);
+        OpCircuit::new(vec![input], LookupOp::DivRem { denom: DENOM })
+    }
+
+    #[test]
+    fn divremcircuit() {
+        let prover = prove(&circuit(), &[-7, -1, 0, 7]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn divremcircuit_tampered() {
+        // the quotients by 8, which are within the table of the quotients by 7
+        let output = [-6, 0, 0, 6];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod sign {
+    use super::*;
+    use crate::fieldutils::i128_to_felt;
+
+    const K: usize = 6;
+    const LEN: usize = 5;
+
+    #[derive(Clone)]
+    struct SignCircuit<F: FieldExt + TensorType> {
+        input: ValTensor<F>,
+        op: Op,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for SignCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+            let mut config =
+                Self::Config::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE, 0);
+            config
+                .configure_lookup(cs, &a, &b, 4, &LookupOp::ReLU { scale: 1 })
+                .unwrap();
+            config
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.layout_tables(&mut layouter).unwrap();
+            layouter
+                .assign_region(
+                    || "",
+                    |mut region| {
+                        config
+                            .layout(
+                                &mut region,
+                                &[self.input.clone()],
+                                &mut 0,
+                                self.op.clone().into(),
+                            )
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    fn input() -> ValTensor<F> {
+        Tensor::from(
+            [-5, 0, 3, -1, 7]
+                .into_iter()
+                .map(|x| Value::known(i128_to_felt::<F>(x))),
+        )
+        .into()
+    }
+
+    #[test]
+    fn signcircuit() {
+        let circuit = SignCircuit::<F> {
+            input: input(),
+            op: Op::Sign,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn abscircuit() {
+        let circuit = SignCircuit::<F> {
+            input: input(),
+            op: Op::Abs,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod bitwise {
+    use super::*;
+    use crate::tensor::ops::Bitwise;
+
+    const BITS: usize = 8;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 9;
+        const LEN: usize = 4;
+
+        fn lookups() -> Vec<LookupOp> {
+            // the bits are checked against a table of a single bit
+            vec![LookupOp::Range { bits: 1 }]
+        }
+    }
+
+    fn circuit(inputs: Vec<ValTensor<F>>, bitwise: Bitwise) -> OpCircuit<F, Params> {
+        let op = Op::Bitwise {
+            bitwise,
+            bits: BITS,
+            scale: 0,
+        };
+        OpCircuit::new(inputs, op)
+    }
+
+    #[test]
+    fn xorcircuit() {
+        let inputs = vec![
+            tensor(&[12, 10, 255, 0], &[4]),
+            tensor(&[10, 3, 1, 7], &[4]),
+        ];
+        let prover = prove(&circuit(inputs, Bitwise::Xor), &[6, 9, 254, 7]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn shiftcircuit() {
+        // the top bits are shifted out of the width
+        let inputs = vec![tensor(&[12, 10, 255, 0], &[4])];
+        let prover = prove(&circuit(inputs, Bitwise::ShiftLeft(3)), &[96, 80, 248, 0]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn shiftcircuit_tampered() {
+        // the last bit shifted in from the left, as in a rotation
+        let output = [6, 133, 255, 0];
+        let inputs = vec![tensor(&[12, 11, 255, 0], &[4])];
+        let circuit = circuit(inputs, Bitwise::ShiftRight(1)).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod sha256 {
+    use super::*;
+    use crate::circuit::sha256::{digest, Sha256Config};
+    use crate::fieldutils::i128_to_felt;
+    use crate::tensor::ValType;
+    use halo2_proofs::plonk::{Column, Instance};
+
+    const K: u32 = 13;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValType<F>>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = (Sha256Config<F>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let instance = cs.instance_column();
+            cs.enable_equality(instance);
+            (Sha256Config::configure(cs), instance)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let (_, digest) = config.0.layout(&mut layouter, &self.inputs)?;
+            for (row, d) in digest.iter().enumerate() {
+                layouter.constrain_instance(d.cell(), config.1, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sha256circuit() {
+        // spans two blocks of the message
+        let values: Vec<i128> = (0..9).map(|i| i * 1000 - 4000).collect();
+        let circuit = MyCircuit::<F> {
+            inputs: values
+                .iter()
+                .map(|v| Value::known(i128_to_felt::<F>(*v)).into())
+                .collect(),
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![digest::<F>(&values).to_vec()]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn sha256circuit_wrong_digest() {
+        let values: Vec<i128> = vec![1, -2, 3];
+        let circuit = MyCircuit::<F> {
+            inputs: values
+                .iter()
+                .map(|v| Value::known(i128_to_felt::<F>(*v)).into())
+                .collect(),
+        };
+
+        let wrong = digest::<F>(&[1, -2, 4]).to_vec();
+        let prover = MockProver::run(K, &circuit, vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+mod merkle {
+    use super::*;
+    use crate::circuit::merkle::{merkle_root, MerkleConfig};
+    use crate::circuit::poseidon::PoseidonConfig;
+    use halo2_proofs::plonk::{Advice, Column, Instance};
+
+    const K: u32 = 10;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        leaf: F,
+        index: usize,
+        siblings: Vec<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = (MerkleConfig<F>, Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = cs.advice_column();
+            let instance = cs.instance_column();
+            cs.enable_equality(advice);
+            cs.enable_equality(instance);
+            let poseidon = PoseidonConfig::configure(cs);
+            (MerkleConfig::configure(cs, poseidon), advice, instance)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let leaf = layouter.assign_region(
+                || "leaf",
+                |mut region| {
+                    region.assign_advice(|| "leaf", config.1, 0, || Value::known(self.leaf))
+                },
+            )?;
+            let root = config
+                .0
+                .layout(&mut layouter, &leaf, self.index, &self.siblings)?;
+            layouter.constrain_instance(root.cell(), config.2, 0)
+        }
+    }
+
+    #[test]
+    fn merklecircuit() {
+        let siblings: Vec<F> = (1..4).map(|i| F::from(i * 7)).collect();
+        // enters the second level from the right
+        let circuit = MyCircuit::<F> {
+            leaf: F::from(42),
+            index: 2,
+            siblings: siblings.clone(),
+        };
+
+        let root = merkle_root(F::from(42), 2, &siblings);
+        let prover = MockProver::run(K, &circuit, vec![vec![root]]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn merklecircuit_wrong_index() {
+        let siblings: Vec<F> = (1..4).map(|i| F::from(i * 7)).collect();
+        let circuit = MyCircuit::<F> {
+            leaf: F::from(42),
+            index: 3,
+            siblings: siblings.clone(),
+        };
+
+        let root = merkle_root(F::from(42), 2, &siblings);
+        let prover = MockProver::run(K, &circuit, vec![vec![root]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+mod ecdsa {
+    use super::*;
+    use crate::circuit::ecdsa::EcdsaConfig;
+    use halo2_proofs::arithmetic::CurveAffine;
+    use halo2_proofs::plonk::{Advice, Column};
+    use halo2curves::group::{
+        ff::{Field, PrimeField},
+        Curve, Group,
+    };
+    use halo2curves::secp256k1::{Fq, Secp256k1, Secp256k1Affine};
+
+    const K: u32 = 20;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        message: F,
+        public_key: Secp256k1Affine,
+        signature: (Fq, Fq),
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = (EcdsaConfig<F>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = cs.advice_column();
+            cs.enable_equality(advice);
+            (EcdsaConfig::configure(cs), advice)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let message = layouter.assign_region(
+                || "message",
+                |mut region| {
+                    region.assign_advice(|| "message", config.1, 0, || Value::known(self.message))
+                },
+            )?;
+            config.0.layout(
+                &mut layouter,
+                &[message],
+                self.public_key,
+                Value::known(self.signature),
+            )
+        }
+    }
+
+    /// Signs the message hash `hash` with a random key, returning the public key and signature.
+    fn sign(hash: Fq) -> (Secp256k1Affine, (Fq, Fq)) {
+        let secret_key = Fq::random(OsRng);
+        let public_key = (Secp256k1::generator() * secret_key).to_affine();
+        let k = Fq::random(OsRng);
+        let point = (Secp256k1::generator() * k).to_affine();
+        let x = point.coordinates().unwrap().x().to_repr();
+        let r = Fq::from_repr(x).unwrap();
+        let s = k.invert().unwrap() * (hash + r * secret_key);
+        (public_key, (r, s))
+    }
+
+    #[test]
+    fn ecdsacircuit() {
+        let (public_key, signature) = sign(Fq::from(12345));
+        let circuit = MyCircuit::<F> {
+            message: F::from(12345),
+            public_key,
+            signature,
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn ecdsacircuit_wrong_message() {
+        let (public_key, signature) = sign(Fq::from(12345));
+        let circuit = MyCircuit::<F> {
+            message: F::from(12346),
+            public_key,
+            signature,
+        };
+
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
+#[cfg(test)]
+mod shared_tables {
+    use super::*;
+
+    const K: usize = 8;
+
+    #[test]
+    fn configs_sharing_tables_configure_each_table_once() {
+        let mut cs = ConstraintSystem::<F>::default();
+        let a = VarTensor::new_advice(&mut cs, K, 1, true);
+        let b = VarTensor::new_advice(&mut cs, K, 1, true);
+        let output = VarTensor::new_advice(&mut cs, K, 1, true);
+        let inputs = [a.clone(), b.clone()];
+        let mut config = BaseConfig::<F>::configure(&mut cs, &inputs, &output, CheckMode::SAFE, 0);
+        let mut other = BaseConfig::<F>::configure(&mut cs, &inputs, &output, CheckMode::SAFE, 0);
+        other.share_tables(&config);
+
+        let relu = LookupOp::ReLU { scale: 1 };
+        config.configure_lookup(&mut cs, &a, &b, 4, &relu).unwrap();
+        let fixed_columns = cs.num_fixed_columns();
+        other.configure_lookup(&mut cs, &a, &b, 4, &relu).unwrap();
+        other.configure_fused_lookup(&mut cs, 4, &relu).unwrap();
+        // the table's columns are fixed columns, so none are added for the other config
+        assert_eq!(cs.num_fixed_columns(), fixed_columns);
+        assert_eq!(config.tables.borrow().len(), 1);
+        assert!(std::rc::Rc::ptr_eq(&config.tables, &other.tables));
+    }
+}
+
+#[cfg(test)]
+mod gather {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 10;
+        const LEN: usize = 512;
+        const BITS: usize = 5;
+
+        fn lookups() -> Vec<LookupOp> {
+            vec![LookupOp::IsZero]
+        }
+    }
+
+    fn gather_elements_circuit() -> OpCircuit<F, Params> {
+        // the negative index gathers the last element of its row
+        let inputs = vec![
+            tensor(&[1, 2, 3, 4, 5, 6], &[2, 3]),
+            tensor(&[0, -1, 2, 1], &[2, 2]),
+        ];
+        OpCircuit::new(inputs, Op::GatherElements { axis: 1, scale: 1 })
+    }
+
+    fn gather_nd_circuit() -> OpCircuit<F, Params> {
+        let inputs = vec![
+            tensor(&[1, 2, 3, 4, 5, 6, 7, 8], &[2, 2, 2]),
+            tensor(&[1, 0, -1, -1, 0, 1], &[3, 2]),
+        ];
+        OpCircuit::new(inputs, Op::GatherND { scale: 1 })
+    }
+
+    #[test]
+    fn gatherelementscircuit() {
+        let prover = prove(&gather_elements_circuit(), &[1, 3, 6, 5]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn gatherelementscircuit_tampered() {
+        // the negative index gathering the element before the first of its row, ie. none
+        let output = [1, 0, 6, 5];
+        let circuit = gather_elements_circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+
+    #[test]
+    fn gatherndcircuit() {
+        let prover = prove(&gather_nd_circuit(), &[5, 6, 7, 8, 3, 4]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn gatherndcircuit_tampered() {
+        // the slices of the indices read in reverse
+        let output = [3, 4, 7, 8, 5, 6];
+        let circuit = gather_nd_circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+
+    #[test]
+    fn scatterndcircuit() {
+        let inputs = vec![
+            tensor(&[1, 2, 3, 4], &[2, 2]),
+            tensor(&[-1, 0], &[1, 2]),
+            tensor(&[9], &[1]),
+        ];
+        let circuit = OpCircuit::<F, Params>::new(inputs, Op::ScatterND { scale: 1 });
+        let prover = prove(&circuit, &[1, 2, 9, 4]);
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod constants {
+    use super::*;
+    use crate::fieldutils::i128_to_felt;
+    use crate::tensor::ValType;
+
+    const K: usize = 6;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct ConstantsCircuit<F: FieldExt + TensorType> {
+        values: ValTensor<F>,
+        // overwrites the first cell after assignment, as a cheating prover could
+        tampered: Option<F>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for ConstantsCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE, 0)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "",
+                |mut region| {
+                    config.inputs[0].assign(&mut region, 0, &self.values)?;
+                    if let (Some(v), VarTensor::Advice { inner, .. }) =
+                        (self.tampered, &config.inputs[0])
+                    {
+                        region.assign_advice(|| "", inner[0], 0, || Value::known(v))?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn values(constant: bool) -> ValTensor<F> {
+        Tensor::from([3, 3, -1, 3].into_iter().map(|x| {
+            let x = i128_to_felt::<F>(x);
+            match constant {
+                true => ValType::Constant(x),
+                false => Value::known(x).into(),
+            }
+        }))
+        .into()
+    }
+
+    #[test]
+    fn constantscircuit() {
+        let circuit = ConstantsCircuit::<F> {
+            values: values(true),
+            tampered: None,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn constantscircuit_tampered() {
+        // the repeated constants are copied from the first cell, so it can't be substituted either
+        let circuit = ConstantsCircuit::<F> {
+            values: values(true),
+            tampered: Some(F::from(4)),
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        assert_failure(&prover, |f| matches!(f, VerifyFailure::Permutation { .. }));
+    }
+
+    #[test]
+    fn valuescircuit_tampered() {
+        // values are free advice, which the prover can substitute
+        let circuit = ConstantsCircuit::<F> {
+            values: values(false),
+            tampered: Some(F::from(4)),
+        };
+
+        let prover = MockProver::run(K as u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
+}
+
+#[cfg(test)]
+mod argmax {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 9;
+        const LEN: usize = 256;
+        const DYNAMIC: bool = true;
+
+        fn lookups() -> Vec<LookupOp> {
+            argmax().required_lookups()
+        }
+    }
+
+    fn argmax() -> Op {
+        Op::ArgMax {
+            axis: 1,
+            keep_dims: false,
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        // the maximum of the first row is repeated, and its first position is the index
+        OpCircuit::new(vec![tensor(&[3, 7, 7, -2, -5, 0], &[2, 3])], argmax())
+    }
+
+    #[test]
+    fn argmaxcircuit() {
+        let prover = prove(&circuit(), &[1, 2]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn argmaxcircuit_wrong_index() {
+        // the last position of the repeated maximum
+        assert_failure(&prove(&circuit(), &[2, 2]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn argmaxcircuit_tampered_index() {
+        // the indices of the minima, claimed as those of the maxima. The index is copied into
+        // its range checks and lookups, which keep the honest one
+        let output = [0, 1];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod limbed {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 8;
+        const LEN: usize = 4;
+
+        fn lookups() -> Vec<LookupOp> {
+            limbed(2).table_ops()
+        }
+    }
+
+    fn limbed(shift: usize) -> LookupOp {
+        LookupOp::Limbed {
+            inner: Box::new(LookupOp::ReLU { scale: 1 }),
+            shift,
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        OpCircuit::new(vec![tensor(&[4, 13, -3, 21], &[4])], limbed(2))
+    }
+
+    #[test]
+    fn limbedcircuit() {
+        let prover = prove(&circuit(), &[1, 3, 0, 5]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn limbedcircuit_tampered() {
+        // the relu of the quotients by 8 rather than 4
+        let output = [1, 2, 0, 3];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod embedding {
+    use super::*;
+
+    const K: usize = 6;
+    const LEN: usize = 128;
+
+    #[derive(Clone)]
+    struct EmbeddingCircuit<F: FieldExt + TensorType> {
+        // the inputs of each embedding, laid out from the top of a column of its own
+        inputs: Vec<Vec<ValTensor<F>>>,
+        scale: usize,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for EmbeddingCircuit<F> {
+        type Config = (BaseConfig<F>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, LEN, true);
+            let b = VarTensor::new_advice(cs, K, LEN, true);
+            let output = VarTensor::new_advice(cs, K, LEN, true);
+            let mut config =
+                BaseConfig::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::SAFE, 0);
+            let nl = LookupOp::ReLU { scale: 1 };
+            config
+                .configure_lookup(cs, &a, &b, nl.table_bits(4), &nl)
+                .unwrap();
+            config.configure_dynamic_lookup(cs, &a, &b).unwrap();
+            let instance = cs.instance_column();
+            cs.enable_equality(instance);
+            (config, instance)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.0.layout_tables(&mut layouter).unwrap();
+            let output = layouter.assign_region(
+                || "",
+                |mut region| {
+                    if let Some(dynamic) = &config.0.dynamic_lookup {
+                        dynamic.reset();
+                    }
+                    let mut output = vec![];
+                    for (i, inputs) in self.inputs.iter().enumerate() {
+                        let embedded = config
+                            .0
+                            .layout(
+                                &mut region,
+                                inputs,
+                                &mut (i * config.0.output.col_size()),
+                                Op::Embedding { scale: self.scale }.into(),
+                            )
+                            .map_err(|_| Error::Synthesis)?;
+                        output.extend(embedded.unwrap().get_inner_tensor().unwrap().to_vec());
+                    }
+                    Ok(output)
+                },
+            )?;
+            for (row, e) in output.iter().enumerate() {
+                match e {
+                    ValType::PrevAssigned(cell) => {
+                        layouter.constrain_instance(cell.cell(), config.1, row)?
+                    }
+                    _ => return Err(Error::Synthesis),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn circuit() -> EmbeddingCircuit<F> {
+        EmbeddingCircuit::<F> {
+            inputs: vec![vec![
+                tensor(&[1, 2, 3, 4, 5, 6], &[3, 2]),
+                tensor(&[2, 0, 2], &[3]),
+            ]],
+            scale: 1,
+        }
+    }
+
+    #[test]
+    fn embeddingcircuit() {
+        let rows = [5, 6, 1, 2, 5, 6].map(F::from).to_vec();
+        let prover = MockProver::run(K as u32, &circuit(), vec![rows]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn embeddingcircuit_wrong_row() {
+        // the row of another id
+        let rows = [5, 6, 3, 4, 5, 6].map(F::from).to_vec();
+        let prover = MockProver::run(K as u32, &circuit(), vec![rows]).unwrap();
+        assert_failure(&prover, |f| matches!(f, VerifyFailure::Permutation { .. }));
+    }
+
+    #[test]
+    fn embeddingcircuit_two_tables() {
+        // the lookups of the two embeddings share their rows, in different columns
+        let circuit = EmbeddingCircuit::<F> {
+            inputs: vec![
+                vec![
+                    tensor(&[1, 2, 3, 4, 5, 6], &[3, 2]),
+                    tensor(&[2, 0, 2], &[3]),
+                ],
+                vec![
+                    tensor(&[7, 8, 9, 10, 11, 12], &[3, 2]),
+                    tensor(&[1, 1, 0], &[3]),
+                ],
+            ],
+            scale: 1,
+        };
+        let rows = [5, 6, 1, 2, 5, 6, 9, 10, 9, 10, 7, 8].map(F::from).to_vec();
+        let prover = MockProver::run(K as u32, &circuit, vec![rows]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn embeddingcircuit_id_between_rows() {
+        // at a scale of 2, the id 3 lies between the rows 1 and 2, and its keys would match the
+        // elements of row 1 shifted by one. It matches no row, so the witnessed output is zero
+        let circuit = EmbeddingCircuit::<F> {
+            inputs: vec![vec![
+                tensor(&[1, 2, 3, 4, 5, 6], &[3, 2]),
+                tensor(&[3], &[1]),
+            ]],
+            scale: 2,
+        };
+        let rows = [0, 0].map(F::from).to_vec();
+        let prover = MockProver::run(K as u32, &circuit, vec![rows]).unwrap();
+        assert_failure(&prover, |f| matches!(f, VerifyFailure::Permutation { .. }));
+    }
+}
+
+#[cfg(test)]
+mod control_flow {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 6;
+        const LEN: usize = 8;
+
+        fn lookups() -> Vec<LookupOp> {
+            vec![]
+        }
+    }
+
+    // a body applying `op` to its k-th input and a folded constant
+    fn body(op: Op, k: usize, constant: i128) -> SubgraphNode {
+        SubgraphNode {
+            op: OpKind::Poly(op),
+            inputs: vec![
+                SubgraphInput::Input(k),
+                SubgraphInput::Const {
+                    values: vec![constant; 2],
+                    dims: vec![1, 2],
+                },
+            ],
+            out_dims: vec![1, 2],
+        }
+    }
+
+    fn if_circuit() -> OpCircuit<F, Params> {
+        let op = Op::If {
+            then_branch: Subgraph {
+                nodes: vec![body(Op::Add, 1, 1)],
+                outputs: vec![SubgraphInput::Node(0)],
+            },
+            else_branch: Subgraph {
+                nodes: vec![body(Op::Mult, 1, 2)],
+                outputs: vec![SubgraphInput::Node(0)],
+            },
+        };
+        let inputs = vec![tensor(&[1, 1], &[1, 2]), tensor(&[3, 5], &[1, 2])];
+        OpCircuit::new(inputs, op)
+    }
+
+    // sums the scanned rows of the second input, scaled by a folded constant, into the first
+    fn scan_circuit() -> OpCircuit<F, Params> {
+        let op = Op::Scan {
+            params: ScanParams {
+                body: Subgraph {
+                    nodes: vec![
+                        body(Op::Mult, 1, 2),
+                        SubgraphNode {
+                            op: OpKind::Poly(Op::Add),
+                            inputs: vec![SubgraphInput::Input(0), SubgraphInput::Node(0)],
+                            out_dims: vec![1, 2],
+                        },
+                    ],
+                    outputs: vec![SubgraphInput::Node(1)],
+                },
+                inputs: vec![
+                    ScanInput::State(0),
+                    ScanInput::Scan {
+                        slot: 1,
+                        axis: 0,
+                        reverse: false,
+                    },
+                ],
+                output: ScanOutput::Last(0),
+                iterations: 3,
+            },
+        };
+        let inputs = vec![
+            tensor(&[1, 0], &[1, 2]),
+            tensor(&[1, 2, 3, 4, 5, 6], &[3, 2]),
+        ];
+        OpCircuit::new(inputs, op)
+    }
+
+    #[test]
+    fn ifcircuit() {
+        let prover = prove(&if_circuit(), &[4, 6]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn ifcircuit_wrong_branch() {
+        // the output of the else branch
+        assert_failure(&prove(&if_circuit(), &[6, 10]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn ifcircuit_tampered() {
+        // the output of a then branch adding another constant
+        let output = [5, 7];
+        let circuit = if_circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+
+    #[test]
+    fn scancircuit() {
+        let prover = prove(&scan_circuit(), &[19, 24]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn scancircuit_wrong_output() {
+        // the state after the second iteration
+        assert_failure(&prove(&scan_circuit(), &[9, 12]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn scancircuit_tampered() {
+        // the output of a body scaling by another constant
+        let output = [28, 36];
+        let circuit = scan_circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod topk {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 8;
+        const LEN: usize = 128;
+        const BITS: usize = 5;
+
+        fn lookups() -> Vec<LookupOp> {
+            topk().required_lookups()
+        }
+    }
+
+    fn topk() -> Op {
+        Op::TopK {
+            k: 2,
+            axis: 1,
+            largest: true,
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        let input = tensor(&[3, 1, 4, 1, 5, -2, 0, 6, 0, -7], &[2, 5]);
+        OpCircuit::new(vec![input], topk())
+    }
+
+    #[test]
+    fn topkcircuit() {
+        let prover = prove(&circuit(), &[5, 4, 6, 0]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn topkcircuit_wrong_output() {
+        // the largest elements out of order
+        assert_failure(&prove(&circuit(), &[4, 5, 6, 0]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn topkcircuit_tampered() {
+        // the smallest elements, claimed as the largest
+        let output = [1, 1, -7, -2];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod compare {
+    use super::*;
+    use crate::tensor::ops::Comparison;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 7;
+        const LEN: usize = 32;
+
+        fn lookups() -> Vec<LookupOp> {
+            greater().required_lookups()
+        }
+    }
+
+    fn greater() -> Op {
+        Op::Compare {
+            comparison: Comparison::Greater,
+        }
+    }
+
+    fn circuit(op: Op) -> OpCircuit<F, Params> {
+        let mut inputs = vec![tensor(&[3, -1, 2, 0], &[4]), tensor(&[1, 2, 2, -4], &[4])];
+        // the condition of where comes first
+        if op == Op::Where {
+            inputs.insert(0, tensor(&[1, 0, 1, 0], &[4]));
+        }
+        OpCircuit::new(inputs, op)
+    }
+
+    #[test]
+    fn comparecircuit() {
+        let prover = prove(&circuit(greater()), &[1, 0, 0, 1]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn comparecircuit_wrong_output() {
+        // equal elements compared as greater
+        assert_failure(&prove(&circuit(greater()), &[1, 0, 1, 1]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn comparecircuit_tampered() {
+        // the output of less, claimed as greater
+        let output = [0, 1, 0, 0];
+        let circuit = circuit(greater()).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+
+    #[test]
+    fn wherecircuit() {
+        let prover = prove(&circuit(Op::Where), &[3, 2, 2, -4]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn wherecircuit_wrong_output() {
+        // the elements of the other input
+        assert_failure(&prove(&circuit(Op::Where), &[1, -1, 2, 0]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn wherecircuit_tampered() {
+        // the condition read as selecting every element of the first input
+        let output = [3, -1, 2, 0];
+        let circuit = circuit(Op::Where).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod logic {
+    use super::*;
+    use crate::tensor::ops::Logic;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 7;
+        const LEN: usize = 32;
+
+        fn lookups() -> Vec<LookupOp> {
+            logic(Logic::And).required_lookups()
+        }
+    }
+
+    fn logic(logic: Logic) -> Op {
+        Op::Logic { logic }
+    }
+
+    fn circuit(op: Op) -> OpCircuit<F, Params> {
+        let inputs = vec![tensor(&[1, 0, 2, 0], &[4]), tensor(&[1, 1, 0, 0], &[4])];
+        OpCircuit::new(inputs, op)
+    }
+
+    #[test]
+    fn logiccircuit() {
+        for (l, output) in [
+            (Logic::And, [1, 0, 0, 0]),
+            (Logic::Or, [1, 1, 1, 0]),
+            (Logic::Xor, [0, 1, 1, 0]),
+        ] {
+            let prover = prove(&circuit(logic(l)), &output);
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn logiccircuit_wrong_output() {
+        // a non-zero input treated as false
+        assert_failure(&prove(&circuit(logic(Logic::Or)), &[1, 0, 0, 0]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn logiccircuit_tampered() {
+        // the output of or, claimed as and
+        let output = [1, 1, 1, 0];
+        let circuit = circuit(logic(Logic::And)).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+
+    #[test]
+    fn xorcircuit_tampered() {
+        // the output of xnor, claimed as xor
+        let output = [1, 0, 0, 1];
+        let circuit = circuit(logic(Logic::Xor)).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod modulo {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 7;
+        const LEN: usize = 32;
+
+        fn lookups() -> Vec<LookupOp> {
+            vec![modulo(false), modulo(true), int_div()]
+        }
+    }
+
+    fn modulo(fmod: bool) -> LookupOp {
+        LookupOp::Mod { denom: 3, fmod }
+    }
+
+    fn int_div() -> LookupOp {
+        LookupOp::IntDiv { denom: 3, scale: 1 }
+    }
+
+    fn circuit(op: LookupOp) -> OpCircuit<F, Params> {
+        OpCircuit::new(vec![tensor(&[7, -7, 5, -2], &[4])], op)
+    }
+
+    #[test]
+    fn modcircuit() {
+        for (op, output) in [
+            (modulo(false), [1, 2, 2, 1]),
+            (modulo(true), [1, -1, 2, -2]),
+            (int_div(), [2, -2, 1, 0]),
+        ] {
+            let prover = prove(&circuit(op), &output);
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn modcircuit_wrong_output() {
+        // the quotient rounded down rather than towards 0
+        assert_failure(&prove(&circuit(int_div()), &[2, -3, 1, -1]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn modcircuit_tampered() {
+        // the output of fmod, claimed as the remainder with the sign of the divisor
+        let output = [1, -1, 2, -2];
+        let circuit = circuit(modulo(false)).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod extremum {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 7;
+        const LEN: usize = 32;
+
+        fn lookups() -> Vec<LookupOp> {
+            let mut lookups = Op::Max.required_lookups();
+            lookups.extend(Op::Min.required_lookups());
+            lookups
+        }
+    }
+
+    fn circuit(op: Op) -> OpCircuit<F, Params> {
+        let inputs = vec![
+            tensor(&[3, -1, 2], &[3]),
+            tensor(&[1, 4, 2], &[3]),
+            tensor(&[0, 0, 5], &[3]),
+        ];
+        OpCircuit::new(inputs, op)
+    }
+
+    #[test]
+    fn extremumcircuit() {
+        for (op, output) in [(Op::Max, [3, 4, 5]), (Op::Min, [0, -1, 2])] {
+            let prover = prove(&circuit(op), &output);
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn extremumcircuit_wrong_output() {
+        // the maximum of the first two inputs only
+        assert_failure(&prove(&circuit(Op::Max), &[3, 4, 2]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn extremumcircuit_tampered() {
+        // the minimum, claimed as the maximum
+        let output = [0, -1, 2];
+        let circuit = circuit(Op::Max).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod nms {
+    use super::*;
+    use crate::tensor::ops::NmsParams;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 10;
+        const LEN: usize = 2048;
+        const BITS: usize = 8;
+
+        fn lookups() -> Vec<LookupOp> {
+            nms().required_lookups()
+        }
+    }
+
+    fn nms() -> Op {
+        Op::NonMaxSuppression {
+            params: NmsParams {
+                max_boxes: 3,
+                iou_threshold: 2,
+                score_threshold: None,
+                center_point_box: false,
+                scale: 4,
+            },
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        // two overlapping boxes (with an IoU of 0.6) and a separate one, at a multiplier of 4
+        let inputs = vec![
+            tensor(&[0, 0, 4, 4, 0, 1, 4, 5, 8, 8, 12, 12], &[3, 4]),
+            tensor(&[3, 2, 1], &[1, 3]),
+        ];
+        OpCircuit::new(inputs, nms())
+    }
+
+    #[test]
+    fn nmscircuit() {
+        let prover = prove(&circuit(), &[0, 0, 0, 0, 0, 2, -1, -1, -1]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn nmscircuit_wrong_output() {
+        // the overlapping box kept
+        assert_failure(&prove(&circuit(), &[0, 0, 0, 0, 0, 1, 0, 0, 2]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn nmscircuit_tampered() {
+        // the output of an IoU threshold of 0.75, which keeps the overlapping box
+        let output = [0, 0, 0, 0, 0, 1, 0, 0, 2];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod roi_align {
+    use super::*;
+    use crate::tensor::ops::RoiTaps;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 8;
+        const LEN: usize = 256;
+        const BITS: usize = 7;
+
+        fn lookups() -> Vec<LookupOp> {
+            let mut lookups = roi_align(false).required_lookups();
+            lookups.extend(roi_align(true).required_lookups());
+            lookups
+        }
+    }
+
+    // two samples, halfway between the rows of the first and last columns
+    fn roi_align(max: bool) -> Op {
+        Op::RoiAlign {
+            rois: vec![RoiTaps {
+                batch: 0,
+                rows: vec![(0, 1, 2)],
+                cols: vec![(0, 0, 4), (1, 1, 4)],
+                grid: (1, 2),
+            }],
+            max,
+            scale: 4,
+        }
+    }
+
+    fn circuit(max: bool) -> OpCircuit<F, Params> {
+        OpCircuit::new(vec![tensor(&[0, 4, 8, 12], &[1, 1, 2, 2])], roi_align(max))
+    }
+
+    #[test]
+    fn roialigncircuit() {
+        for (max, output) in [(false, 24), (true, 32)] {
+            let prover = prove(&circuit(max), &[output]);
+            prover.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn roialigncircuit_wrong_output() {
+        // the first sample rather than the average
+        assert_failure(&prove(&circuit(false), &[16]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn roialigncircuit_tampered() {
+        // the average of samples a quarter of the way down
+        let output = [16];
+        let circuit = circuit(false).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+
+    #[test]
+    fn roialigncircuit_tampered_max() {
+        // the first sample, claimed as the maximum
+        let output = [16];
+        let circuit = circuit(true).tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod grid_sample {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 10;
+        const LEN: usize = 1024;
+        const BITS: usize = 9;
+
+        fn lookups() -> Vec<LookupOp> {
+            grid_sample().required_lookups()
+        }
+    }
+
+    fn grid_sample() -> Op {
+        Op::GridSample {
+            align_corners: false,
+            border: false,
+            scale: 4,
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        // the center and the top left corner, at a multiplier of 4
+        let inputs = vec![
+            tensor(&[4, 8, 12, 16], &[1, 1, 2, 2]),
+            tensor(&[0, 0, -4, -4], &[1, 1, 2, 2]),
+        ];
+        OpCircuit::new(inputs, grid_sample())
+    }
+
+    #[test]
+    fn gridsamplecircuit() {
+        let prover = prove(&circuit(), &[40, 4]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn gridsamplecircuit_wrong_output() {
+        // the corner sampled without its zero padding
+        assert_failure(&prove(&circuit(), &[40, 16]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn gridsamplecircuit_tampered() {
+        // the output of aligned corners, which samples the corner pixel itself
+        let output = [40, 16];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::Lookup { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tree_ensemble {
+    use super::*;
+    use crate::tensor::ops::{Comparison, TreeEnsemble, TreeNode};
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl OpCircuitParams for Params {
+        const K: usize = 7;
+        const LEN: usize = 64;
+
+        fn lookups() -> Vec<LookupOp> {
+            stump().required_lookups()
+        }
+    }
+
+    // a stump on the second feature, x_1 <= 2
+    fn stump() -> Op {
+        let stump = vec![
+            TreeNode::Branch {
+                feature: 1,
+                threshold: 2,
+                comparison: Comparison::LessOrEqual,
+                true_node: 1,
+                false_node: 2,
+            },
+            TreeNode::Leaf {
+                weights: vec![(0, 10)],
+            },
+            TreeNode::Leaf {
+                weights: vec![(0, -10), (1, 5)],
+            },
+        ];
+        Op::TreeEnsemble {
+            ensemble: TreeEnsemble {
+                trees: vec![stump],
+                base_values: vec![1, 0],
+            },
+        }
+    }
+
+    fn circuit() -> OpCircuit<F, Params> {
+        OpCircuit::new(vec![tensor(&[7, 2, 7, 3], &[2, 2])], stump())
+    }
+
+    #[test]
+    fn treeensemblecircuit() {
+        let prover = prove(&circuit(), &[11, 0, -9, 5]);
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn treeensemblecircuit_wrong_output() {
+        // the second row sent down the true branch
+        assert_failure(&prove(&circuit(), &[11, 0, 11, 0]), |f| {
+            matches!(f, VerifyFailure::Permutation { .. })
+        });
+    }
+
+    #[test]
+    fn treeensemblecircuit_tampered() {
+        // the output of a threshold of 3, which sends the second row down the true branch
+        let output = [11, 0, 11, 0];
+        let circuit = circuit().tampered(&output);
+        assert_failure(&prove(&circuit, &output), |f| {
+            matches!(f, VerifyFailure::ConstraintNotSatisfied { .. })
+        });
+    }
+}
+
+#[cfg(test)]
+mod poseidon {
+    use super::*;
+    use crate::circuit::poseidon::{PoseidonConfig, PoseidonSpec};
+    use crate::tensor::ValType;
+    use halo2_proofs::plonk::{Column, Instance};
+    use halo2curves::bn256::Fr;
+    use halo2curves::group::ff::PrimeField;
+
+    const K: u32 = 8;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: FieldExt + TensorType> {
+        inputs: Vec<ValType<F>>,
+    }
+
+    impl<F: FieldExt + TensorType> Circuit<F> for MyCircuit<F> {
+        type Config = (PoseidonConfig<F>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let instance = cs.instance_column();
+            cs.enable_equality(instance);
+            (PoseidonConfig::configure(cs), instance)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let (_, digest) = config.0.layout(&mut layouter, &self.inputs)?;
+            layouter.constrain_instance(digest.cell(), config.1, 0)
+        }
+    }
+
+    // a field element from its big endian hex
+    fn felt(hex: &str) -> Fr {
+        let mut bytes = hex::decode(hex.trim_start_matches("0x")).unwrap();
+        bytes.reverse();
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+        Fr::from_repr(repr).unwrap()
+    }
+
+    #[test]
+    fn poseidon_round_constants() {
+        // the first round constants of the reference parameters for BN254, with a width of 3, 8
+        // full and 57 partial rounds
+        let spec = PoseidonSpec::<Fr>::new();
+        let expected = [
+            "0x0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e",
+            "0x00f1445235f2148c5986587169fc1bcd887b08d4d00868df5696fff40956e864",
+            "0x08dff3487e8ac99e1f29a058d0fa80b930c728730b7ab36ce879f3890ecf73f5",
+        ]
+        .map(felt);
+        assert_eq!(spec.round_constants()[0], expected);
+    }
+
+    #[test]
+    fn poseidon_digests() {
+        let spec = PoseidonSpec::<Fr>::new();
+        for (inputs, expected) in [
+            (
+                vec![],
+                "0x057b825de0c99a180fecd500e7f9a228185500fe67666986ae472643c63b327e",
+            ),
+            (
+                vec![1, 2],
+                "0x0c2ac43abe049c2ca5af1b7d97f6cb96616784079a342c013786753d73272ae1",
+            ),
+            (
+                vec![1, 2, 3],
+                "0x12ad7281cba1e3d40298eafd5941bfc2e9f21b11805d4ed0201cf3775a2b4da8",
+            ),
+        ] {
+            let inputs: Vec<Fr> = inputs.into_iter().map(Fr::from).collect();
+            assert_eq!(spec.hash(&inputs), felt(expected));
+        }
+    }
+
+    #[test]
+    fn poseidoncircuit() {
+        // spans two chunks of the sponge
+        let values: Vec<F> = (1..4).map(F::from).collect();
+        let circuit = MyCircuit::<F> {
+            inputs: values.iter().map(|v| Value::known(*v).into()).collect(),
+        };
+
+        let digest = PoseidonSpec::<F>::new().hash(&values);
+        let prover = MockProver::run(K, &circuit, vec![vec![digest]]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn poseidoncircuit_wrong_digest() {
+        let values: Vec<F> = (1..4).map(F::from).collect();
+        let circuit = MyCircuit::<F> {
+            inputs: values.iter().map(|v| Value::known(*v).into()).collect(),
+        };
+
+        let wrong = PoseidonSpec::<F>::new().hash(&[F::from(1), F::from(2), F::from(4)]);
+        let prover = MockProver::run(K, &circuit, vec![vec![wrong]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -17,6 +17,7 @@ pub fn value_muxer<F: FieldExt + TensorType>(
                 dims: _,
             } => val.map(|x| match x {
                 ValType::Value(x) => x,
+                ValType::Constant(x) => Value::known(x),
                 _ => unimplemented!(),
             }),
             _ => unimplemented!(),
@@ -4,13 +4,17 @@ use clap::{Args, Parser, Subcommand, ValueEnum};
 use ethereum_types::Address;
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::env;
 use std::error::Error;
 use std::fs::File;
 use std::io::{stdin, stdout, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use crate::circuit::CheckMode;
+use crate::circuit::ConvLowering;
+use crate::graph::Visibility;
 
 #[allow(missing_docs)]
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -28,6 +32,41 @@ impl std::fmt::Display for TranscriptType {
     }
 }
 
+/// What calibrate-settings optimizes for.
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CalibrationTarget {
+    /// The smallest circuit whose outputs are within the target error
+    Resources,
+    /// The most accurate circuit within the logrows budget
+    Accuracy,
+}
+impl std::fmt::Display for CalibrationTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+/// The hash committing to hashed inputs and outputs.
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum HashFunction {
+    /// Poseidon, the cheapest to prove
+    #[default]
+    Poseidon,
+    /// SHA-256 of the values as big endian 64 bit integers, as computed on the EVM
+    Sha256,
+}
+impl std::fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum StrategyType {
@@ -43,42 +82,302 @@ impl std::fmt::Display for StrategyType {
     }
 }
 
+/// A concrete shape for one of the model's inputs, written as `name:1x3x224x224`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InputShape {
+    /// The name of the input in the Onnx graph
+    pub name: String,
+    /// The input's dims
+    pub dims: Vec<usize>,
+}
+
+impl FromStr for InputShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, shape) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected name:shape, got {}", s))?;
+        let dims = shape
+            .split('x')
+            .map(|d| match d.trim().parse::<usize>() {
+                Ok(d) if d > 0 => Ok(d),
+                _ => Err(format!("invalid dim {} in {}", d, s)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(InputShape {
+            name: name.to_string(),
+            dims,
+        })
+    }
+}
+
+impl std::fmt::Display for InputShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dims: Vec<String> = self.dims.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}:{}", self.name, dims.join("x"))
+    }
+}
+
+/// The visibility of one of the model's inputs or outputs, written as `name:public`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TensorVisibility {
+    /// The name of the input or output in the Onnx graph
+    pub name: String,
+    /// The tensor's visibility
+    pub visibility: Visibility,
+}
+
+impl FromStr for TensorVisibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, visibility) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected name:visibility, got {}", s))?;
+        Ok(TensorVisibility {
+            name: name.to_string(),
+            visibility: visibility.parse()?,
+        })
+    }
+}
+
+impl std::fmt::Display for TensorVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.name, self.visibility)
+    }
+}
+
+/// The fixed point scale of a layer of the model, written as `name:scale`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LayerScale {
+    /// The name of the node in the Onnx graph the layer starts at
+    pub name: String,
+    /// The scale of the layer
+    pub scale: u32,
+}
+
+impl FromStr for LayerScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, scale) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected name:scale, got {}", s))?;
+        Ok(LayerScale {
+            name: name.to_string(),
+            scale: scale
+                .parse()
+                .map_err(|_| format!("{} is not a valid scale", scale))?,
+        })
+    }
+}
+
+impl std::fmt::Display for LayerScale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.name, self.scale)
+    }
+}
+
+/// A claim that an entry of each output of the model is at least a threshold, written as
+/// `index:threshold`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ThresholdClaim {
+    /// The index of the entry in the flattened output
+    pub index: usize,
+    /// The threshold the entry is claimed to be at least
+    pub threshold: f32,
+}
+
+impl FromStr for ThresholdClaim {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, threshold) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected index:threshold, got {}", s))?;
+        Ok(ThresholdClaim {
+            index: index
+                .parse()
+                .map_err(|_| format!("{} is not a valid index", index))?,
+            threshold: threshold
+                .parse()
+                .map_err(|_| format!("{} is not a valid threshold", threshold))?,
+        })
+    }
+}
+
+impl std::fmt::Display for ThresholdClaim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.index, self.threshold)
+    }
+}
+
 /// Parameters specific to a proving run
 #[derive(Debug, Args, Deserialize, Serialize, Clone)]
 pub struct RunArgs {
     /// The tolerance for error on model outputs
     #[arg(short = 'T', long, default_value = "0")]
     pub tolerance: usize,
+    /// The tolerance for error on model outputs in float units (eg. 0.01) rather than in fixed point units, such that the claimed public outputs need only be within epsilon of the computed outputs. Overrides the tolerance.
+    #[arg(long, value_name = "EPSILON", conflicts_with = "tolerance")]
+    #[serde(default)]
+    pub output_epsilon: Option<f32>,
     /// The denominator in the fixed point representation used when quantizing
     #[arg(short = 'S', long, default_value = "7")]
     pub scale: u32,
-    /// The number of bits used in lookup tables
+    /// The number of bits used in lookup tables, or 0 to use the smallest tables which fit the inputs of the lookups, as inferred from the input bound
     #[arg(short = 'B', long, default_value = "16")]
     pub bits: usize,
     /// The log_2 number of rows
     #[arg(short = 'K', long, default_value = "17")]
     pub logrows: u32,
+    /// The number of columns each of the advices of the model is laid out over, or 0 to use the fewest columns which fit the model at the given logrows. More columns are used if the model doesn't fit in that many. Chosen by calibrate-settings along with the logrows, when searching for the smallest logrows within a budget of columns.
+    #[arg(long, default_value = "0")]
+    #[serde(default)]
+    pub num_cols: usize,
     /// Flags whether inputs are public
     #[arg(long, default_value = "false")]
     pub public_inputs: bool,
+    /// Flags whether inputs are private to the prover, with only a Poseidon commitment to each of them being public
+    #[arg(long, default_value = "false", conflicts_with = "public_inputs")]
+    #[serde(default)]
+    pub hashed_inputs: bool,
     /// Flags whether outputs are public
     #[arg(long, default_value = "true", action = clap::ArgAction::Set)]
     pub public_outputs: bool,
-    /// Flags whether params are public
+    /// Flags whether outputs are private to the prover, with only a Poseidon commitment to each of them being public (overrides public-outputs)
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub hashed_outputs: bool,
+    /// The hash committing to hashed inputs and outputs, sha256 matching the commitments computed on the EVM (params are always committed to with Poseidon)
+    #[arg(long, default_value_t = HashFunction::Poseidon, value_enum)]
+    #[serde(default)]
+    pub hash_function: HashFunction,
+    /// The public key of a signer (as the hex of its uncompressed SEC1 encoding) whose secp256k1 signature over the digest of the inputs is verified in-circuit, such that the inputs are bound to data the signer attested to. The signature is read from the data file, and inputs can't be public.
+    #[arg(long, value_name = "PUBLIC_KEY")]
+    #[serde(default)]
+    pub signer: Option<String>,
+    /// Flags whether params are public, such that the verifying key commits to the params fed straight into the fixed column of the gates of ops without lookups (those of ops with lookups are witnessed as advice)
     #[arg(long, default_value = "false")]
     pub public_params: bool,
-    /// Base used to pack the public-inputs to the circuit. (value > 1) to pack instances as a single int.
+    /// Flags whether params are private to the prover, with only a Poseidon commitment to them being public
+    #[arg(long, default_value = "false", conflicts_with = "public_params")]
+    #[serde(default)]
+    pub hashed_params: bool,
+    /// The depth of the Merkle trees of Poseidon hashes which the digests of hashed inputs and params are proven to be leaves of, or 0 to expose the digests themselves. Each hashed input (and hashed params) then exposes the root of its tree in place of its digest, with the path to its leaf read from the data file.
+    #[arg(long, default_value = "0")]
+    #[serde(default)]
+    pub merkle_depth: usize,
+    /// Flags whether params are loaded into fixed columns ahead of the model and copied into its ops, such that the verifying key commits to all of them, including those of ops with lookups
+    #[arg(long, default_value = "false", conflicts_with_all = ["public_params", "hashed_params"])]
+    #[serde(default)]
+    pub fixed_params: bool,
+    /// Base used to pack the public-inputs to the circuit. (value > 1) to pack instances as a single int, or 0 to pick the largest safe base for the model's outputs and scale.
     /// Useful when verifying on the EVM. Note that this will often break for very long inputs. Use with caution, still experimental.
     #[arg(long, default_value = "1")]
     pub pack_base: u32,
     /// run sanity checks during calculations (safe or unsafe)
     #[arg(long, default_value = "safe")]
     pub check_mode: CheckMode,
+    /// Concrete shapes for the model's inputs as name:shape (eg. input:1x3x224x224), for models exported with symbolic dims. Symbolic dims of other inputs default to 1.
+    #[arg(long = "input-shape", value_name = "NAME:SHAPE")]
+    #[serde(default)]
+    pub input_shapes: Vec<InputShape>,
+    /// The number of samples proven at once. The model is laid out for each sample, and the inputs and outputs carry a leading batch dim.
+    #[arg(long, default_value = "1")]
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Names of intermediate tensors (or of the nodes producing them) to prove from, in place of the model's inputs. The nodes these depend on (eg. preprocessing) are left out of the circuit.
+    #[arg(long = "input-node", value_name = "NAME")]
+    #[serde(default)]
+    pub input_nodes: Vec<String>,
+    /// Names of intermediate tensors (or of the nodes producing them) to prove up to, in place of the model's outputs.
+    #[arg(long = "output-node", value_name = "NAME")]
+    #[serde(default)]
+    pub output_nodes: Vec<String>,
+    /// Flags whether to disable the fusion of common sequences of ops (eg. conv and batch norm, matmul and bias) into single nodes. Useful for debugging.
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub no_fusion: bool,
+    /// Visibility of individual inputs and outputs as name:visibility (eg. logits:public), where visibility is one of public, private or hashed. Overrides the flags for all inputs and outputs.
+    #[arg(long = "tensor-visibility", value_name = "NAME:VISIBILITY")]
+    #[serde(default)]
+    pub tensor_visibility: Vec<TensorVisibility>,
+    /// Scales of individual layers as name:scale (eg. conv1:10), where name is the Onnx node the layer starts at. The scale carries over to the nodes downstream of it until another layer's, and the activations are rebased wherever the scale changes.
+    #[arg(long = "layer-scale", value_name = "NAME:SCALE")]
+    #[serde(default)]
+    pub layer_scales: Vec<LayerScale>,
+    /// Output scales of nonlinearities as op:scale (eg. sigmoid:12), where op is one of sigmoid, tanh, exp, ln, sqrt, recip, rsqrt or erf. Their tables map inputs of any scale straight to the given scale, which carries over to the nodes downstream as with --layer-scale.
+    #[arg(long = "lookup-scale", value_name = "OP:SCALE")]
+    #[serde(default)]
+    pub lookup_scales: Vec<LayerScale>,
+    /// Flags whether the tables of lookups whose inputs are nonnegative (eg. the output of a relu) are centered on a zero-point, covering [0, 2^bits) rather than [-2^(bits - 1), 2^(bits - 1)).
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub asymmetric_lookups: bool,
+    /// Flags whether the divisions by a power of two which rescale the model's activations (and the relus fused with them) are laid out by decomposing their inputs into a quotient and a remainder, each looked up in a table of its own, rather than looking up the whole input in one table. Divisions by other integers (eg. averages) are decomposed in the same way, with the remainder constrained to lie in [0, denominator).
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub limb_lookups: bool,
+    /// How convolutions are lowered to matmuls: direct, as the product of the blocked toeplitz matrix of the kernel and the flattened image, such that each output is a single dot product spanning the whole image, im2col, as the product of the flattened kernel and the windows of the image, such that each dot product only spans a window, winograd-2 or winograd-4, as Winograd F(2, 3) or F(4, 3) convolutions of 3x3 kernels with a stride of 1 (other convs are lowered directly), or auto, to pick whichever lays out the fewest cells for each conv. The estimated cells of every lowering are listed in the report of each conv.
+    #[arg(long, default_value = "direct")]
+    #[serde(default)]
+    pub conv_lowering: ConvLowering,
+    /// Clips each of the float weights of the model to the given percentile (eg. 99.9) of its magnitudes, rather than quantizing up to the absolute max, such that rare outliers don't inflate the range of the activations.
+    #[arg(long, value_name = "PERCENTILE")]
+    #[serde(default)]
+    pub weight_clip_percentile: Option<f32>,
+    /// Replaces each output of the model with the index of its largest entry along the last axis (eg. the predicted class of a classifier), constrained in-circuit, such that only the decision is an output while the scores stay private.
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub argmax_outputs: bool,
+    /// Replaces each output of the model with the boolean claim that its entry at INDEX (in the flattened output) is at least THRESHOLD, constrained in-circuit, such that only the claim is an output while the value stays private. The threshold is part of the settings the proof is verified with, and requires fixed params, as private params (and public ones, which leave the params of comparisons as advice) would let the prover substitute it.
+    #[arg(
+        long,
+        value_name = "INDEX:THRESHOLD",
+        conflicts_with = "argmax_outputs"
+    )]
+    #[serde(default)]
+    pub threshold_claim: Option<ThresholdClaim>,
+    /// The largest magnitude of the (float) inputs of the model. When set, the largest magnitude of every intermediate value is bounded at load time, failing (with the offending node named) if any could overflow its lookup table or an i128.
+    #[arg(long, value_name = "MAGNITUDE")]
+    #[serde(default)]
+    pub input_bound: Option<f32>,
+    /// Names of nodes to strip from the model at load time (eg. a preprocessing Normalize), each being replaced by an identity of its first input.
+    #[arg(long = "strip-node", value_name = "NAME")]
+    #[serde(default)]
+    pub strip_nodes: Vec<String>,
+    /// Onnx op types (eg. Reshape) whose nodes are stripped from the model at load time, each being replaced by an identity of its first input.
+    #[arg(long = "strip-op", value_name = "OP_TYPE")]
+    #[serde(default)]
+    pub strip_ops: Vec<String>,
+    /// A GGUF file whose (F32, F16, Q8_0, Q4_0 or Q4_1) tensors replace the constants of the model with the same names.
+    #[arg(long, value_name = "PATH")]
+    #[serde(default)]
+    pub gguf_weights: Option<PathBuf>,
+    /// The maximum size, in MB, of the tensors loaded from the external data files of an Onnx model. External data files must lie in the model's directory.
+    #[arg(long, default_value = "4096")]
+    #[serde(default = "default_max_external_data_mb")]
+    pub max_external_data_mb: u64,
+}
+
+fn default_batch_size() -> usize {
+    1
+}
+
+fn default_max_external_data_mb() -> u64 {
+    4096
 }
 
 const EZKLCONF: &str = "EZKLCONF";
 const RUNARGS: &str = "RUNARGS";
 
+thread_local! {
+    /// Global arguments overriding those loaded by [Cli::create] on this thread, see [Cli::with_run_args].
+    static RUN_ARGS: RefCell<Option<RunArgs>> = RefCell::new(None);
+}
+
 #[allow(missing_docs)]
 #[derive(Parser, Debug, Clone, Deserialize, Serialize)]
 #[command(author, version, about, long_about = None)]
@@ -89,6 +388,19 @@ pub struct Cli {
     /// The tolerance for error on model outputs
     #[clap(flatten)]
     pub args: RunArgs,
+    /// Tracks the memory allocated by each phase of the command (loading the graph, keygen, witness generation and proving), and logs the peak heap and resident set size of each when the command ends
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub memory_report: bool,
+    /// Directory to cache params downsized to the logrows of the circuit in, keyed by the hash of the params they were downsized from, such that later runs load them without downsizing again. Nothing is cached without it
+    #[arg(long)]
+    #[serde(default)]
+    pub params_cache: Option<PathBuf>,
+    /// Path to save a trace of the time spent in each phase of the command to, as folded stacks which inferno or flamegraph.pl render to a flamegraph (requires the profiling feature)
+    #[cfg(feature = "profiling")]
+    #[arg(long)]
+    #[serde(default)]
+    pub trace_path: Option<PathBuf>,
 }
 
 impl Cli {
@@ -106,8 +418,31 @@ impl Cli {
     pub fn from_json(arg_json: &str) -> Result<Self, serde_json::Error> {
         serde_json::from_str(arg_json)
     }
-    /// Create an ezkl configuration: if there is an EZKLCONF env variable, parse its value, else read it from the command line.
+    /// Saves the global arguments to `path` as a .json file, which can be loaded with the RUNARGS environment variable.
+    pub fn save_run_args(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(File::create(path)?, &self.args)?;
+        Ok(())
+    }
+    /// Runs `f` with the global arguments loaded by [Cli::create] on this thread replaced by `args`. Circuits load their settings with [Cli::create] when configured, as configure takes no params, so this is how circuits are configured with arguments which differ from the command line (eg. the logrows picked by setup).
+    pub fn with_run_args<T>(args: &RunArgs, f: impl FnOnce() -> T) -> T {
+        struct Reset(Option<RunArgs>);
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                RUN_ARGS.with(|run_args| *run_args.borrow_mut() = self.0.take());
+            }
+        }
+        let _reset = Reset(RUN_ARGS.with(|run_args| run_args.replace(Some(args.clone()))));
+        f()
+    }
+    /// Create an ezkl configuration: if there is an EZKLCONF env variable, parse its value, else read it from the command line. The global arguments are replaced by those set with [Cli::with_run_args], if any.
     pub fn create() -> Result<Self, Box<dyn Error>> {
+        let mut cli = Self::load()?;
+        if let Some(args) = RUN_ARGS.with(|run_args| run_args.borrow().clone()) {
+            cli.args = args;
+        }
+        Ok(cli)
+    }
+    fn load() -> Result<Self, Box<dyn Error>> {
         match env::var(EZKLCONF) {
             Ok(path) => {
                 debug!("loading ezkl conf from {}", path);
@@ -127,8 +462,8 @@ impl Cli {
                     let args: RunArgs =
                         serde_json::from_str(&data).map_err(Box::<dyn Error>::from)?;
                     Ok(Cli {
-                        command: Cli::parse().command,
                         args,
+                        ..Cli::parse()
                     })
                 }
                 Err(_e) => Ok(Cli::parse()),
@@ -146,6 +481,12 @@ pub enum Commands {
         /// The path to the .onnx model file
         #[arg(short = 'M', long)]
         model: String,
+        /// The path to save a report of the cost of each node in the circuit to (optional), as csv if the path ends in .csv and as json otherwise
+        #[arg(long)]
+        report: Option<PathBuf>,
+        /// The path to the .json data file (optional), used to time the witness generation of each node in the report
+        #[arg(short = 'D', long)]
+        data: Option<String>,
     },
 
     #[cfg(feature = "render")]
@@ -163,6 +504,92 @@ pub enum Commands {
         output: String,
     },
 
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Renders the graph of the model to a DOT file (or to an SVG file if the output ends in .svg, which requires graphviz), each node being annotated with its op, scale, the number of variables it lays out and the lookup tables it uses
+    #[command(name = "render-graph", arg_required_else_help = true)]
+    RenderGraph {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: String,
+        /// Path to save the .dot (or .svg) render
+        #[arg(short = 'O', long)]
+        output: PathBuf,
+    },
+
+    /// Runs the float and quantized models over a directory of representative .json data files, measuring the dynamic range of each tensor, and chooses the smallest scale within the target error along with the bits and logrows that avoid overflowing the lookup tables
+    #[command(name = "calibrate-settings", arg_required_else_help = true)]
+    CalibrateSettings {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: String,
+        /// The path to a .json data file, or to a directory of .json data files, of representative inputs
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// Path to save the calibrated global arguments to, as a .json file which can be loaded with the RUNARGS environment variable
+        #[arg(short = 'O', long)]
+        settings_path: PathBuf,
+        /// Whether to choose the smallest circuit within the target error (resources), or the most accurate circuit within the bits and logrows budgets (accuracy)
+        #[arg(long, default_value_t = CalibrationTarget::Resources, value_enum)]
+        target: CalibrationTarget,
+        /// The largest absolute error of the dequantized outputs (relative to the float outputs) to tolerate, in resources mode
+        #[arg(long, default_value = "0.01")]
+        target_error: f32,
+        /// The largest scale to try
+        #[arg(long, default_value = "16")]
+        max_scale: u32,
+        /// The largest number of bits of the lookup tables to allow
+        #[arg(long, default_value = "24")]
+        max_bits: usize,
+        /// The largest logrows to allow (optional)
+        #[arg(long)]
+        max_logrows: Option<u32>,
+        /// The largest number of columns each of the advices of the model may be laid out over (optional). The smallest logrows at which the model fits in that many columns is chosen, trading rows for columns, rather than the smallest logrows fitting the lookup tables.
+        #[arg(long)]
+        max_cols: Option<usize>,
+        /// The percentile (eg. 99.9) of the activations the ranges are clipped to, and of the inputs of the lookups the tables need to cover, rather than the absolute max. Inputs with outliers past the tables can't be proven at the calibrated settings.
+        #[arg(long, default_value = "100")]
+        percentile: f32,
+        /// Path to save the measured ranges of each tensor and the statistics of each scale to (optional)
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+
+    /// Runs the float model and the quantized model over .json data, and reports the mean squared error, largest error and top-1 agreement of each of the quantized outputs relative to the float outputs
+    #[command(name = "verify-accuracy", arg_required_else_help = true)]
+    VerifyAccuracy {
+        /// The path to a .json data file, or to a directory of .json data files
+        #[arg(short = 'D', long)]
+        data: PathBuf,
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: String,
+        /// Path to save the report to as json (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Compares the circuits of two models (or of two manifests saved by `prove`), reporting the differences in their settings, ops, shapes, scales, number of variables and required logrows
+    #[command(arg_required_else_help = true)]
+    Diff {
+        /// The path to the first .onnx model file, or .json manifest
+        #[arg(short = 'M', long)]
+        model: String,
+        /// The path to the .onnx model file, or .json manifest, to compare the first one against
+        #[arg(long)]
+        other: String,
+    },
+
+    /// Estimates the rows, columns, lookup tables and required logrows of the circuit of a model, along with the size of its proofs and proving key and the time and memory to prove, without generating keys
+    #[command(arg_required_else_help = true)]
+    Estimate {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: String,
+        /// Path to save the estimate to as json (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+
     /// Runs a vanilla forward pass, produces a quantized output, and saves it to a .json file
     #[command(arg_required_else_help = true)]
     Forward {
@@ -175,6 +602,9 @@ pub enum Commands {
         /// Path to the new .json file
         #[arg(short = 'O', long)]
         output: String,
+        /// Path to dump the quantized output of every node to as json (optional), to compare against the activations of the float model
+        #[arg(long)]
+        dump_activations: Option<PathBuf>,
     },
 
     /// Generates a dummy SRS
@@ -230,6 +660,64 @@ pub enum Commands {
         // todo, optionally allow supplying proving key
     },
 
+    /// Loads model and data, and saves the vk and pk, such that proofs can be created on another machine without generating the keys again
+    #[command(arg_required_else_help = true)]
+    Setup {
+        /// The path to the .json data file, used to shape the circuit
+        #[arg(short = 'D', long)]
+        data: String,
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the params file
+        #[arg(long)]
+        params_path: PathBuf,
+        /// The path to output to the desired verfication key file
+        #[arg(long)]
+        vk_path: PathBuf,
+        /// The path to output the proving key to, compressed with gzip
+        #[arg(long)]
+        pk_path: PathBuf,
+        /// Picks the smallest logrows the circuit fits in, rather than using the given logrows. The chosen logrows are saved to the settings path, to be loaded with the RUNARGS environment variable by prove and verify.
+        #[arg(long, default_value = "false", requires = "settings_path")]
+        auto_logrows: bool,
+        /// The largest number of columns each of the advices of the model may be laid out over when picking the logrows (optional)
+        #[arg(long)]
+        max_cols: Option<usize>,
+        /// Path to save the global arguments the keys are generated with to, as a .json file which can be loaded with the RUNARGS environment variable (optional)
+        #[arg(long)]
+        settings_path: Option<PathBuf>,
+        /// The path to save the manifest of the model and settings the keys are generated for (optional), which verify checks proofs against without loading the model
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
+    },
+
+    /// Times keygen, witness generation, proving and verification of a model, and reports them along with the memory used by each and the sizes of the keys and proof, as json
+    #[command(arg_required_else_help = true)]
+    Bench {
+        /// The path to the .json data file, which should include both the network input (possibly private) and the network output (public input to the proof)
+        #[arg(short = 'D', long)]
+        data: String,
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long)]
+        model: PathBuf,
+        /// The path to the params file
+        #[arg(long)]
+        params_path: PathBuf,
+        /// The transcript type
+        #[arg(
+            long,
+            require_equals = true,
+            num_args = 0..=1,
+            default_value_t = TranscriptType::Blake,
+            value_enum
+        )]
+        transcript: TranscriptType,
+        /// Path to save the report to as json (optional)
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+
     /// Loads model and data, prepares vk and pk, and creates proof
     #[command(arg_required_else_help = true)]
     Prove {
@@ -265,7 +753,12 @@ pub enum Commands {
             value_enum
         )]
         strategy: StrategyType,
-        // todo, optionally allow supplying proving key
+        /// The path to save the manifest of the model and settings the proof is built for (optional)
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
+        /// The path to a proving key saved by `setup` (compressed or not), loaded rather than generating the keys (optional)
+        #[arg(long)]
+        pk_path: Option<PathBuf>,
     },
     #[cfg(not(target_arch = "wasm32"))]
     /// Creates an EVM verifier for a single proof
@@ -371,6 +864,9 @@ pub enum Commands {
             value_enum
         )]
         transcript: TranscriptType,
+        /// The path to the manifest the proof was built for (optional), saved by setup or prove. The model and settings are then checked against it by their hashes, without loading the model, and the settings which differ are reported
+        #[arg(long)]
+        manifest_path: Option<PathBuf>,
     },
 
     /// Verifies an aggregate proof, returning accept or reject
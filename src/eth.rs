@@ -600,3 +600,38 @@ pub fn fix_verifier_sol(input_file: PathBuf) -> Result<String, Box<dyn Error>> {
     writeln!(write, "}} return success; }} }}")?;
     Ok(contract)
 }
+
+/// Generates a Solidity library recovering the values of an output packed with `pack_base` at
+/// `scale` from its (verified) instance, mirroring [crate::tensor::ops::unpack]. The values are
+/// the quantized outputs, which are dequantized by dividing them by 2^(output scale).
+pub fn unpack_sol(pack_base: u32, scale: u32) -> Result<String, Box<dyn Error>> {
+    let digit = (pack_base as i128)
+        .checked_pow(scale + 1)
+        .ok_or("the packing base is too large for the scale")?;
+    Ok(format!(
+        "
+    library Unpack {{
+        uint256 constant FIELD_MODULUS = {};
+        int256 constant BASE = {};
+        int256 constant DIGIT = {};
+
+        function unpack(uint256 packed, uint256 len) internal pure returns (int256[] memory values) {{
+            int256 rest = packed > FIELD_MODULUS / 2 ? -int256(FIELD_MODULUS - packed) : int256(packed);
+            values = new int256[](len);
+            for (uint256 i = 0; i < len; i++) {{
+                // digits are balanced around 0, so that negative values can be recovered
+                int256 d = rest % DIGIT;
+                if (d < 0) d += DIGIT;
+                if (2 * d >= DIGIT) d -= DIGIT;
+                rest = (rest - d) / DIGIT;
+                // the first value is weighted by the base rather than by 1
+                values[i] = i == 0 ? d / BASE : d;
+            }}
+        }}
+    }}
+    ",
+        U256::from_little_endian((-Fr::one()).to_repr().as_slice()) + 1,
+        pack_base,
+        digit
+    ))
+}
@@ -1,22 +1,28 @@
+use crate::bench::{BenchReport, ByteCounter};
 use crate::circuit::CheckMode;
-use crate::commands::{Cli, Commands, StrategyType, TranscriptType};
+use crate::commands::{Cli, Commands, RunArgs, StrategyType, TranscriptType};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::eth::{
     deploy_verifier, fix_verifier_sol, get_ledger_signing_provider, get_provider,
-    get_wallet_signing_provider, send_proof, verify_proof_via_solidity,
+    get_wallet_signing_provider, send_proof, unpack_sol, verify_proof_via_solidity,
 };
-use crate::graph::{vector_to_quantized, Model, ModelCircuit};
+use crate::graph::accuracy::accuracy;
+use crate::graph::calibration::{calibrate, CalibrationOptions};
+use crate::graph::{
+    hash_file, save_report, vector_to_quantized, Mode, Model, ModelCircuit, ModelManifest,
+    VarVisibility,
+};
+use crate::memory;
 use crate::pfsys::evm::aggregation::{AggregationCircuit, PoseidonTranscript};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::pfsys::evm::{aggregation::gen_aggregation_evm_verifier, single::gen_evm_verifier};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::pfsys::evm::{evm_verify, DeploymentCode};
-#[cfg(feature = "render")]
 use crate::pfsys::prepare_model_circuit;
-use crate::pfsys::{create_keys, load_params, load_vk, save_params, Snark};
+use crate::pfsys::{create_keys, load_params, load_pk, load_vk, save_params, save_pk, Snark};
 use crate::pfsys::{
-    create_proof_circuit, gen_srs, prepare_data, prepare_model_circuit_and_public_input, save_vk,
-    verify_proof_circuit,
+    create_proof_circuit, dequantize_outputs, gen_srs, prepare_data, prepare_data_files,
+    prepare_model_circuit_and_public_input, save_vk, verify_proof_circuit,
 };
 #[cfg(not(target_arch = "wasm32"))]
 use ethers::providers::Middleware;
@@ -33,9 +39,7 @@ use halo2_proofs::poly::VerificationStrategy;
 use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
 use halo2_proofs::{dev::MockProver, poly::commitment::ParamsProver};
 use halo2curves::bn256::{Bn256, Fr, G1Affine};
-#[cfg(not(target_arch = "wasm32"))]
-use log::warn;
-use log::{info, trace};
+use log::{info, trace, warn};
 #[cfg(feature = "render")]
 use plotters::prelude::*;
 use snark_verifier::loader::native::NativeLoader;
@@ -46,6 +50,7 @@ use std::fs::read_to_string;
 use std::fs::File;
 #[cfg(not(target_arch = "wasm32"))]
 use std::io::Write;
+use std::path::{Path, PathBuf};
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::Arc;
 use std::time::Instant;
@@ -58,6 +63,96 @@ pub enum ExecutionError {
     /// Shape mismatch in a operation
     #[error("verification failed")]
     VerifyError(Vec<VerifyFailure>),
+    /// The model or settings differ from those the proof was built for
+    #[error("the model or settings differ from those the proof was built for: {0}")]
+    ManifestMismatch(String),
+}
+
+/// Checks that the model and settings supplied to verify a proof are those the proof was built for,
+/// returning the manifest of the proof. When the manifest the proof was built for (as saved by
+/// `setup` or `prove`) is supplied, the model and settings are checked against it by the hashes
+/// of their files, without loading the model, and the settings which differ are reported. Proofs
+/// without a manifest hash can't be checked, and are rejected.
+fn check_manifest(
+    proof: &Snark<Fr, G1Affine>,
+    model: &Path,
+    run_args: &RunArgs,
+    manifest_path: Option<PathBuf>,
+) -> Result<ModelManifest, Box<dyn Error>> {
+    let expected = proof.manifest_hash.as_ref().ok_or_else(|| {
+        ExecutionError::ManifestMismatch(
+            "the proof has no manifest hash to check the model and settings against".to_string(),
+        )
+    })?;
+    if let Some(path) = manifest_path {
+        let built_for = ModelManifest::load(&path)?;
+        if built_for.hash()? != *expected {
+            return Err(Box::new(ExecutionError::ManifestMismatch(format!(
+                "{} isn't the manifest of the proof",
+                path.display()
+            ))));
+        }
+        let differences = built_for.diff_files(model, run_args)?;
+        if !differences.is_empty() {
+            return Err(Box::new(ExecutionError::ManifestMismatch(format!(
+                "{} differ",
+                differences.join(", ")
+            ))));
+        }
+        info!("the model and settings match the manifest of the proof");
+        return Ok(built_for);
+    }
+    let manifest = ModelManifest::new(model, run_args)?;
+    if manifest.hash()? == *expected {
+        info!("the model and settings match the manifest of the proof");
+        return Ok(manifest);
+    }
+    Err(Box::new(ExecutionError::ManifestMismatch(format!(
+        "manifest hashes {} and {}",
+        manifest.hash()?,
+        expected
+    ))))
+}
+
+/// Loads the KZG params at `path`, downsized to `logrows`. Downsizing converts the SRS to the
+/// lagrange basis of the smaller domain, a fixed cost of each run. When a `cache` directory is
+/// given the downsized params are saved in it, keyed by the hash of the params file and by
+/// `logrows`, and later runs downsizing the same params load them from there directly. Nothing is
+/// written without a `cache`.
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+fn load_params_kzg(
+    path: &Path,
+    logrows: u32,
+    cache: Option<&Path>,
+) -> Result<ParamsKZG<Bn256>, Box<dyn Error>> {
+    let cached = match cache {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            Some(dir.join(format!("{}.k{}", hash_file(path)?, logrows)))
+        }
+        None => None,
+    };
+    if let Some(cached) = cached.as_ref().filter(|c| c.exists()) {
+        info!(
+            "loading params downsized to {} logrows from {:?}",
+            logrows, cached
+        );
+        return load_params::<KZGCommitmentScheme<Bn256>>(cached.clone());
+    }
+    let mut params: ParamsKZG<Bn256> = load_params::<KZGCommitmentScheme<Bn256>>(path.into())?;
+    if logrows < params.k() {
+        info!("downsizing params to {} logrows", logrows);
+        params.downsize(logrows);
+        if let Some(cached) = cached {
+            if let Err(e) = save_params::<KZGCommitmentScheme<Bn256>>(&cached, &params) {
+                warn!(
+                    "failed to cache the downsized params at {:?}: {}",
+                    cached, e
+                );
+            }
+        }
+    }
+    Ok(params)
 }
 
 /// helper function
@@ -157,6 +252,9 @@ pub fn create_proof_circuit_kzg<
 
 /// Run an ezkl command with given args
 pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    if cli.memory_report {
+        memory::enable();
+    }
     match cli.command {
         #[cfg(not(target_arch = "wasm32"))]
         Commands::SendProofEVM {
@@ -206,9 +304,120 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             let params = gen_srs::<KZGCommitmentScheme<Bn256>>(cli.args.logrows);
             save_params::<KZGCommitmentScheme<Bn256>>(&params_path, &params)?;
         }
-        Commands::Table { model: _ } => {
-            let om = Model::from_ezkl_conf(cli)?;
+        Commands::Table {
+            model: _,
+            ref report,
+            ref data,
+        } => {
+            let om = Model::from_ezkl_conf(cli.clone())?;
             info!("{}", Table::new(om.nodes.iter()));
+            if let Some(report) = report {
+                let inputs = match data {
+                    Some(data) => {
+                        let data = prepare_data(data.to_string())?;
+                        Some(prepare_model_circuit::<Fr>(&data, &om)?.inputs)
+                    }
+                    None => None,
+                };
+                let reports = om.report(inputs.as_deref())?;
+                save_report(&reports, report)?;
+            }
+        }
+        Commands::CalibrateSettings {
+            ref model,
+            ref data,
+            ref settings_path,
+            target,
+            target_error,
+            max_scale,
+            max_bits,
+            max_logrows,
+            max_cols,
+            percentile,
+            ref report,
+        } => {
+            let data = prepare_data_files(data)?;
+            let options = CalibrationOptions {
+                target,
+                target_error,
+                max_scale,
+                max_bits,
+                max_logrows,
+                max_cols,
+                percentile,
+            };
+            let calibration = calibrate(model, &data, &cli.args, &options)?;
+            serde_json::to_writer_pretty(File::create(settings_path)?, &calibration.run_args)?;
+            info!("saved calibrated settings to {}", settings_path.display());
+            if let Some(report) = report {
+                serde_json::to_writer_pretty(File::create(report)?, &calibration)?;
+            }
+        }
+        Commands::VerifyAccuracy {
+            ref data,
+            ref model,
+            ref output,
+        } => {
+            let report = accuracy(model, &prepare_data_files(data)?, &cli.args)?;
+            info!(
+                "accuracy of the quantized model at scale {} over {} samples",
+                report.scale, report.samples
+            );
+            for o in report.outputs.iter() {
+                let top1 = match o.top1_agreement {
+                    Some(a) => format!(", top-1 agreement {:.2}%", a * 100.0),
+                    None => String::new(),
+                };
+                info!(
+                    "{}: mse {:e}, max error {:e}{}",
+                    o.name, o.mse, o.max_error, top1
+                );
+            }
+            if let Some(output) = output {
+                serde_json::to_writer_pretty(File::create(output)?, &report)?;
+            }
+        }
+        Commands::Diff {
+            ref model,
+            ref other,
+        } => {
+            // models are loaded with the same settings, manifests hold their own
+            let load = |path: &str| match Path::new(path).extension().map_or(false, |e| e == "json")
+            {
+                true => ModelManifest::load(Path::new(path)),
+                false => ModelManifest::new(path, &cli.args),
+            };
+            let differences = load(model)?.compare(&load(other)?)?;
+            if differences.is_empty() {
+                info!("the circuits of {} and {} are the same", model, other);
+            }
+            for d in differences {
+                info!("{}", d);
+            }
+        }
+        Commands::Estimate {
+            ref model,
+            ref output,
+        } => {
+            let visibility = VarVisibility::from_args(cli.args.clone())?;
+            let model = Model::new(model, cli.args.clone(), Mode::Prove, visibility)?;
+            let estimate = model.estimate()?;
+            info!("{}", serde_json::to_string_pretty(&estimate)?);
+            if estimate.min_logrows > estimate.logrows {
+                warn!(
+                    "the circuit needs at least {} logrows, more than the {} it is configured for",
+                    estimate.min_logrows, estimate.logrows
+                );
+            }
+            if !estimate.uncounted_chips.is_empty() {
+                warn!(
+                    "the rows of the {} chips aren't counted, and can require more logrows",
+                    estimate.uncounted_chips.join(", ")
+                );
+            }
+            if let Some(output) = output {
+                serde_json::to_writer_pretty(File::create(output)?, &estimate)?;
+            }
         }
         #[cfg(feature = "render")]
         Commands::RenderCircuit {
@@ -217,7 +426,8 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             ref output,
         } => {
             let data = prepare_data(data.to_string())?;
-            let circuit = prepare_model_circuit::<Fr>(&data, &cli.args)?;
+            let model = Model::from_ezkl_conf(cli.clone())?;
+            let circuit = prepare_model_circuit::<Fr>(&data, &model)?;
             info!("Rendering circuit");
 
             // Create the area we want to draw on.
@@ -232,12 +442,45 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
                 .show_labels(false)
                 .render(cli.args.logrows, &circuit, &root)?;
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::RenderGraph {
+            model: _,
+            ref output,
+        } => {
+            let model = Model::from_ezkl_conf(cli.clone())?;
+            let dot = model.to_dot();
+            info!("rendering graph");
+            if output.extension().map_or(false, |e| e == "svg") {
+                let mut graphviz = std::process::Command::new("dot")
+                    .arg("-Tsvg")
+                    .arg("-o")
+                    .arg(output)
+                    .stdin(std::process::Stdio::piped())
+                    .spawn()?;
+                // the pipe is dropped once written to, so that graphviz can terminate
+                graphviz
+                    .stdin
+                    .take()
+                    .ok_or("failed to open the stdin of graphviz")?
+                    .write_all(dot.as_bytes())?;
+                if !graphviz.wait()?.success() {
+                    return Err("graphviz failed to render the graph".into());
+                }
+            } else {
+                let mut file = File::create(output)?;
+                file.write_all(dot.as_bytes())?;
+            }
+        }
         Commands::Forward {
             ref data,
             model,
             output,
+            dump_activations,
         } => {
-            let mut data = prepare_data(data.to_string())?;
+            let graph = Model::load_onnx(&model, &cli.args)?;
+            let output_names = Model::outlet_names(&graph, &graph.outputs);
+            let mut data = prepare_data(data.to_string())?
+                .order_by_names(&Model::outlet_names(&graph, &graph.inputs), &output_names)?;
 
             // quantize the supplied data using the provided scale.
             let mut model_inputs = vec![];
@@ -246,11 +489,24 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
                 model_inputs.push(t);
             }
 
-            let res = Model::forward(model, &model_inputs, cli.args)?;
+            let mut activations = vec![];
+            let res = Model::forward(
+                model,
+                &model_inputs,
+                cli.args,
+                dump_activations.as_ref().map(|_| &mut activations),
+            )?;
+            if let Some(path) = dump_activations {
+                info!("dumping the activations of {} nodes", activations.len());
+                serde_json::to_writer(&File::create(path)?, &activations)?;
+            }
 
             let float_res: Vec<Vec<f32>> = res.iter().map(|t| t.to_vec()).collect();
             trace!("forward pass output: {:?}", float_res);
             data.output_data = float_res;
+            if !data.input_names.is_empty() {
+                data.output_names = output_names;
+            }
 
             serde_json::to_writer(&File::create(output)?, &data)?;
         }
@@ -259,6 +515,7 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             let (circuit, public_inputs) =
                 prepare_model_circuit_and_public_input::<Fr>(&data, &cli)?;
             info!("Mock proof");
+            memory::enter_phase("mock proof");
 
             let prover = MockProver::run(cli.args.logrows, &circuit, public_inputs)
                 .map_err(Box::<dyn Error>::from)?;
@@ -280,12 +537,8 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
 
             let (_, public_inputs) = prepare_model_circuit_and_public_input::<Fr>(&data, &cli)?;
             let num_instance = public_inputs.iter().map(|x| x.len()).collect();
-            let mut params: ParamsKZG<Bn256> =
-                load_params::<KZGCommitmentScheme<Bn256>>(params_path.to_path_buf())?;
-            info!("downsizing params to {} logrows", cli.args.logrows);
-            if cli.args.logrows < params.k() {
-                params.downsize(cli.args.logrows);
-            }
+            let params =
+                load_params_kzg(params_path, cli.args.logrows, cli.params_cache.as_deref())?;
 
             let vk =
                 load_vk::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(vk_path.to_path_buf())?;
@@ -298,7 +551,12 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
                 let mut f = File::create(sol_code_path.as_ref().unwrap())?;
                 let _ = f.write(yul_code.as_bytes());
 
-                let output = fix_verifier_sol(sol_code_path.as_ref().unwrap().clone())?;
+                let mut output = fix_verifier_sol(sol_code_path.as_ref().unwrap().clone())?;
+                // consumers of packed outputs need to unpack them from the instances
+                let model = Model::from_ezkl_conf(cli.clone())?;
+                if model.run_args.pack_base > 1 {
+                    output += &unpack_sol(model.run_args.pack_base, model.run_args.scale)?;
+                }
 
                 let mut f = File::create(sol_code_path.as_ref().unwrap())?;
                 let _ = f.write(output.as_bytes());
@@ -322,32 +580,141 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             )?;
             deployment_code.save(deployment_code_path.as_ref().unwrap())?;
         }
-        Commands::Prove {
+        Commands::Setup {
+            ref data,
+            ref model,
+            ref params_path,
+            ref vk_path,
+            ref pk_path,
+            auto_logrows,
+            max_cols,
+            ref settings_path,
+            ref manifest_path,
+        } => {
+            let mut cli = cli.clone();
+            if auto_logrows {
+                let visibility = VarVisibility::from_args(cli.args.clone())?;
+                let model = Model::new(model, cli.args.clone(), Mode::Prove, visibility)?;
+                let (logrows, num_cols) = model.min_logrows(max_cols)?;
+                info!(
+                    "the circuit fits in {} logrows, with {} columns per advice",
+                    logrows, num_cols
+                );
+                let uncounted_chips = model.estimate_at(logrows)?.uncounted_chips;
+                if !uncounted_chips.is_empty() {
+                    warn!(
+                        "the rows of the {} chips aren't counted, and can require more logrows",
+                        uncounted_chips.join(", ")
+                    );
+                }
+                cli.args.logrows = logrows;
+                cli.args.num_cols = num_cols;
+            }
+            if let Some(settings_path) = settings_path {
+                cli.save_run_args(settings_path)?;
+                info!("saved settings to {}", settings_path.display());
+            }
+            let data = prepare_data(data.to_string())?;
+            let (circuit, _) = prepare_model_circuit_and_public_input::<Fr>(&data, &cli)?;
+            let params =
+                load_params_kzg(params_path, cli.args.logrows, cli.params_cache.as_deref())?;
+            let pk = Cli::with_run_args(&cli.args, || {
+                create_keys::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(&circuit, &params)
+            })
+            .map_err(Box::<dyn Error>::from)?;
+            save_vk::<KZGCommitmentScheme<Bn256>>(vk_path, pk.get_vk())?;
+            save_pk::<KZGCommitmentScheme<Bn256>>(pk_path, &pk)?;
+            if let Some(manifest_path) = manifest_path {
+                ModelManifest::new(model, &cli.args)?.save(manifest_path)?;
+                info!("saved the manifest to {}", manifest_path.display());
+            }
+        }
+        Commands::Bench {
             ref data,
             model: _,
+            ref params_path,
+            transcript,
+            ref output,
+        } => {
+            memory::enable();
+            let mut report = BenchReport::new(cli.args.clone());
+            let data = prepare_data(data.to_string())?;
+            let (circuit, public_inputs) = report.time("witness", || {
+                prepare_model_circuit_and_public_input::<Fr>(&data, &cli)
+            })?;
+            let params =
+                load_params_kzg(params_path, cli.args.logrows, cli.params_cache.as_deref())?;
+            let pk = report.time("keygen", || {
+                create_keys::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(&circuit, &params)
+            })?;
+            // the proof is verified once, and timed, below
+            let snark = report.time("proof", || {
+                create_proof_circuit_kzg(
+                    circuit,
+                    &params,
+                    public_inputs,
+                    &pk,
+                    transcript,
+                    KZGSingleStrategy::new(&params),
+                    CheckMode::UNSAFE,
+                )
+            })?;
+            memory::enter_phase("verify");
+            let verified = report.time("verify", || {
+                verify_proof_circuit_kzg(
+                    params.verifier_params(),
+                    snark.clone(),
+                    pk.get_vk(),
+                    transcript,
+                    KZGSingleStrategy::new(params.verifier_params()),
+                )
+            });
+            report.verified = verified.is_ok();
+            report.proof_size = snark.proof.len();
+            let mut counter = ByteCounter::default();
+            pk.get_vk()
+                .write(&mut counter, halo2_proofs::SerdeFormat::RawBytes)?;
+            report.vk_size = counter.0;
+            let mut counter = ByteCounter::default();
+            pk.write(&mut counter, halo2_proofs::SerdeFormat::RawBytes)?;
+            report.pk_size = counter.0;
+            report.finish();
+            info!("{}", serde_json::to_string_pretty(&report)?);
+            if let Some(output) = output {
+                serde_json::to_writer_pretty(File::create(output)?, &report)?;
+            }
+            verified?;
+        }
+        Commands::Prove {
+            ref data,
+            ref model,
             ref vk_path,
             ref proof_path,
             ref params_path,
             transcript,
             strategy,
+            ref manifest_path,
+            ref pk_path,
         } => {
             let data = prepare_data(data.to_string())?;
 
             let (circuit, public_inputs) = prepare_model_circuit_and_public_input(&data, &cli)?;
-            let mut params: ParamsKZG<Bn256> =
-                load_params::<KZGCommitmentScheme<Bn256>>(params_path.to_path_buf())?;
-            info!("downsizing params to {} logrows", cli.args.logrows);
-            if cli.args.logrows < params.k() {
-                params.downsize(cli.args.logrows);
-            }
-            let pk =
-                create_keys::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(&circuit, &params)
-                    .map_err(Box::<dyn Error>::from)?;
+            let params =
+                load_params_kzg(params_path, cli.args.logrows, cli.params_cache.as_deref())?;
+            let pk = match pk_path {
+                Some(pk_path) => load_pk::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(
+                    pk_path.to_path_buf(),
+                )?,
+                None => create_keys::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(
+                    &circuit, &params,
+                )
+                .map_err(Box::<dyn Error>::from)?,
+            };
             trace!("params computed");
 
             let now = Instant::now();
             // creates and verifies the proof
-            let snark = match strategy {
+            let mut snark = match strategy {
                 StrategyType::Single => {
                     let strategy = KZGSingleStrategy::new(&params);
                     create_proof_circuit_kzg(
@@ -376,6 +743,11 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
 
             info!("proof took {}", now.elapsed().as_secs());
 
+            let manifest = ModelManifest::new(model, &cli.args)?;
+            snark.manifest_hash = Some(manifest.hash()?);
+            if let Some(manifest_path) = manifest_path {
+                manifest.save(manifest_path)?;
+            }
             snark.save(proof_path)?;
             save_vk::<KZGCommitmentScheme<Bn256>>(vk_path, pk.get_vk())?;
         }
@@ -390,20 +762,13 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             transcript,
         } => {
             // the K used for the aggregation circuit
-            let mut params: ParamsKZG<Bn256> =
-                load_params::<KZGCommitmentScheme<Bn256>>(params_path.to_path_buf())?;
-            info!("downsizing params to {} logrows", cli.args.logrows);
-            if cli.args.logrows < params.k() {
-                params.downsize(cli.args.logrows);
-            }
+            let params =
+                load_params_kzg(params_path, cli.args.logrows, cli.params_cache.as_deref())?;
 
             let mut snarks = vec![];
             // the K used when generating the application snark proof. we assume K is homogenous across snarks to aggregate
-            let mut params_app = params.clone();
-            info!("downsizing app params to {} logrows", app_logrows);
-            if app_logrows < params.k() {
-                params_app.downsize(app_logrows);
-            }
+            let params_app =
+                load_params_kzg(params_path, app_logrows, cli.params_cache.as_deref())?;
 
             for (proof_path, vk_path) in aggregation_snarks.iter().zip(aggregation_vk_paths) {
                 let vk = load_vk::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(
@@ -440,21 +805,20 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             }
         }
         Commands::Verify {
-            model: _,
+            model,
             proof_path,
             vk_path,
             params_path,
             transcript,
+            manifest_path,
         } => {
-            let mut params: ParamsKZG<Bn256> =
-                load_params::<KZGCommitmentScheme<Bn256>>(params_path)?;
-            info!("downsizing params to {} logrows", cli.args.logrows);
-            if cli.args.logrows < params.k() {
-                params.downsize(cli.args.logrows);
-            }
+            let params =
+                load_params_kzg(&params_path, cli.args.logrows, cli.params_cache.as_deref())?;
 
             let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path, None, None)?;
+            let manifest = check_manifest(&proof, &model, &cli.args, manifest_path)?;
 
+            let instances = proof.instances.clone();
             let strategy = KZGSingleStrategy::new(params.verifier_params());
             let vk = load_vk::<KZGCommitmentScheme<Bn256>, Fr, ModelCircuit<Fr>>(vk_path)?;
             let result = verify_proof_circuit_kzg(
@@ -465,6 +829,17 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
                 strategy,
             );
             info!("verified: {}", result.is_ok());
+            if result.is_ok() {
+                let outputs = dequantize_outputs(&instances, &manifest)?;
+                let names = manifest
+                    .output_names
+                    .iter()
+                    .zip(&manifest.output_visibility)
+                    .filter(|(_, visibility)| visibility.is_public());
+                for ((name, _), output) in names.zip(outputs) {
+                    info!("output {}: {:?}", name, output);
+                }
+            }
         }
 
         Commands::VerifyAggr {
@@ -473,12 +848,8 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             params_path,
             transcript,
         } => {
-            let mut params: ParamsKZG<Bn256> =
-                load_params::<KZGCommitmentScheme<Bn256>>(params_path)?;
-            info!("downsizing params to {} logrows", cli.args.logrows);
-            if cli.args.logrows < params.k() {
-                params.downsize(cli.args.logrows);
-            }
+            let params =
+                load_params_kzg(&params_path, cli.args.logrows, cli.params_cache.as_deref())?;
 
             let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path, None, None)?;
 
@@ -515,5 +886,8 @@ pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
             info!("{}", hex::encode(proof.proof))
         }
     }
+    if cli.memory_report {
+        memory::log_summary();
+    }
     Ok(())
 }
@@ -0,0 +1,116 @@
+use super::calibration::{float_pass, load_samples, quantized_pass};
+use super::{Mode, Model, VarVisibility};
+use crate::commands::RunArgs;
+use crate::pfsys::ModelInput;
+use serde::Serialize;
+use std::error::Error;
+use std::path::Path;
+
+/// How closely one of the outputs of the quantized model tracks the float model's.
+#[derive(Clone, Debug, Serialize)]
+pub struct OutputAccuracy {
+    /// The name of the output in the Onnx graph
+    pub name: String,
+    /// The mean squared error of the dequantized output
+    pub mse: f32,
+    /// The largest absolute error of the dequantized output
+    pub max_error: f32,
+    /// The fraction of rows (along the last dim) whose largest entry is the same in both models,
+    /// for outputs with more than one entry per row
+    pub top1_agreement: Option<f32>,
+}
+
+/// The accuracy of the quantized model relative to the float model over a set of samples.
+#[derive(Clone, Debug, Serialize)]
+pub struct AccuracyReport {
+    /// The fixed point scale the model was quantized at
+    pub scale: u32,
+    /// The number of samples the models were run over
+    pub samples: usize,
+    /// The accuracy of each of the model's outputs
+    pub outputs: Vec<OutputAccuracy>,
+}
+
+/// The index of the largest entry of each row of `row_len` values.
+fn argmaxes(values: &[f32], row_len: usize) -> Vec<usize> {
+    values
+        .chunks(row_len)
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .fold((0, f32::NEG_INFINITY), |(i_max, max), (i, v)| {
+                    match *v > max {
+                        true => (i, *v),
+                        false => (i_max, max),
+                    }
+                })
+                .0
+        })
+        .collect()
+}
+
+/// Runs the float model (through tract) and the quantized model over `data`, and reports the
+/// mean squared error, largest error and top-1 agreement of each of the quantized outputs.
+pub fn accuracy(
+    model_path: impl AsRef<Path>,
+    data: &[ModelInput],
+    run_args: &RunArgs,
+) -> Result<AccuracyReport, Box<dyn Error>> {
+    // samples are evaluated one at a time
+    let sample_args = RunArgs {
+        batch_size: 1,
+        ..run_args.clone()
+    };
+    let visibility = VarVisibility::from_args(run_args.clone())?;
+    let model = Model::new(model_path, sample_args, Mode::Table, visibility)?;
+    let samples = load_samples(&model, data, run_args.batch_size)?;
+    let float = float_pass(&model, &samples)?;
+
+    let outputs = &model.model.outputs;
+    // the sum of squared errors, the number of values, the largest error, and the number of
+    // agreeing and total rows of each output
+    let mut stats = vec![(0f64, 0usize, 0f32, 0usize, 0usize); outputs.len()];
+    for (sample, float) in samples.iter().zip(float.iter()) {
+        let (quantized, _) = quantized_pass(&model, sample)?;
+        for ((o, q), s) in outputs.iter().zip(quantized.iter()).zip(stats.iter_mut()) {
+            let expected = float
+                .get(&o.node)
+                .ok_or("the float model's outputs should be floats")?;
+            for (x, y) in q.iter().zip(expected.iter()) {
+                let error = (x - y).abs();
+                s.0 += (error as f64).powi(2);
+                s.1 += 1;
+                s.2 = s.2.max(error);
+            }
+            let row_len = model.nodes[&o.node].out_dims.last().copied().unwrap_or(1);
+            if row_len > 1 {
+                let rows = argmaxes(q, row_len)
+                    .into_iter()
+                    .zip(argmaxes(expected, row_len));
+                for (a, b) in rows {
+                    s.3 += (a == b) as usize;
+                    s.4 += 1;
+                }
+            }
+        }
+    }
+
+    let outputs = model
+        .output_names()
+        .into_iter()
+        .zip(stats)
+        .map(
+            |(name, (squares, count, max_error, agreeing, rows))| OutputAccuracy {
+                name,
+                mse: (squares / count.max(1) as f64) as f32,
+                max_error,
+                top1_agreement: (rows > 0).then(|| agreeing as f32 / rows as f32),
+            },
+        )
+        .collect();
+    Ok(AccuracyReport {
+        scale: run_args.scale,
+        samples: samples.len(),
+        outputs,
+    })
+}
@@ -0,0 +1,422 @@
+use super::{scale_to_multiplier, vector_to_quantized, Mode, Model, ModelVars, VarVisibility};
+use crate::circuit::{LookupOp, OpKind};
+use crate::commands::{CalibrationTarget, RunArgs};
+use crate::pfsys::ModelInput;
+use crate::tensor::Tensor;
+use halo2curves::bn256::Fr;
+use itertools::Itertools;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use tract_onnx::prelude::{InferenceModelExt, OutletId, TVec, Tensor as TractTensor};
+use tract_onnx::tract_hir::infer::Factoid;
+
+/// The dynamic range of the output of a node over the calibration samples, in floating point.
+#[derive(Clone, Debug, Serialize)]
+pub struct TensorRange {
+    /// The node's unique identifier
+    pub idx: usize,
+    /// The name of the Onnx node the node was loaded from
+    pub name: String,
+    /// The smallest value of the node's output
+    pub min: f32,
+    /// The largest value of the node's output
+    pub max: f32,
+    /// The lower percentile of the node's output the range is clipped to
+    pub clipped_min: f32,
+    /// The upper percentile of the node's output the range is clipped to
+    pub clipped_max: f32,
+}
+
+/// How the circuit fares at a fixed point scale over the calibration samples.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScaleStats {
+    /// The fixed point scale
+    pub scale: u32,
+    /// The largest absolute error of the quantized outputs, relative to the float outputs
+    pub max_output_error: f32,
+    /// The number of bits the lookup tables need to cover the (clipped) inputs of the lookups
+    pub bits: usize,
+    /// The number of inputs of the lookups, over all samples, falling outside of the tables
+    pub clipped_lookups: usize,
+    /// The smallest logrows fitting the lookup tables and the instances (and the advices, within
+    /// the budget of columns)
+    pub logrows: u32,
+    /// The number of advice cells the model lays out
+    pub var_len: usize,
+    /// The number of columns each of the advices is laid out over at the logrows
+    pub num_cols: usize,
+}
+
+/// The options of a calibration.
+#[derive(Clone, Debug)]
+pub struct CalibrationOptions {
+    /// What the calibration optimizes for
+    pub target: CalibrationTarget,
+    /// The largest absolute error of the dequantized outputs to tolerate, in resources mode
+    pub target_error: f32,
+    /// The largest scale to try
+    pub max_scale: u32,
+    /// The largest number of bits of the lookup tables to allow
+    pub max_bits: usize,
+    /// The largest logrows to allow, if any
+    pub max_logrows: Option<u32>,
+    /// The largest number of columns of each of the advices to allow, if any
+    pub max_cols: Option<usize>,
+    /// The percentile of the activations (and of the inputs of the lookups) to cover
+    pub percentile: f32,
+}
+
+/// The settings chosen by calibration, along with the statistics they were chosen from.
+#[derive(Clone, Debug, Serialize)]
+pub struct Calibration {
+    /// The calibrated settings
+    pub run_args: RunArgs,
+    /// The dynamic range of each node's output
+    pub ranges: Vec<TensorRange>,
+    /// The statistics of each of the scales which were tried
+    pub scales: Vec<ScaleStats>,
+}
+
+/// The largest logrows searched for a layout of the advices.
+const MAX_LOGROWS: u32 = 28;
+
+/// The smallest number of bits of a lookup table covering values of magnitude up to `max`.
+fn bits_for(max: i128) -> usize {
+    // tables cover [-2^(bits - 1), 2^(bits - 1))
+    let mut bits = 1;
+    while max >= 1 << (bits - 1) {
+        bits += 1;
+    }
+    bits
+}
+
+/// Splits each sample of a batch (of `batch_size` samples, input major) into its own sample.
+pub fn split_samples(data: &ModelInput, batch_size: usize) -> Vec<Vec<Vec<f64>>> {
+    (0..batch_size)
+        .map(|b| {
+            data.input_data
+                .iter()
+                .map(|input| {
+                    let len = input.len() / batch_size;
+                    input[b * len..(b + 1) * len].to_vec()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Orders each of the `data` files as the inputs of `model` and splits their batches (of
+/// `batch_size` samples) into single samples.
+pub fn load_samples(
+    model: &Model,
+    data: &[ModelInput],
+    batch_size: usize,
+) -> Result<Vec<Vec<Vec<f64>>>, Box<dyn Error>> {
+    let mut samples = vec![];
+    for d in data {
+        let d = d.order_by_names(&model.input_names(), &model.output_names())?;
+        samples.extend(split_samples(&d, batch_size));
+    }
+    if samples.is_empty() {
+        return Err("at least one sample of data is needed".into());
+    }
+    Ok(samples)
+}
+
+/// Runs the float model over `samples`, returning the outputs of each of its (non constant)
+/// nodes for each sample.
+pub fn float_pass(
+    model: &Model,
+    samples: &[Vec<Vec<f64>>],
+) -> Result<Vec<BTreeMap<usize, Vec<f32>>>, Box<dyn Error>> {
+    let mut graph = model.model.clone();
+    let nodes = graph
+        .eval_order()?
+        .into_iter()
+        .filter(|n| graph.nodes[*n].op.name() != "Const")
+        .collect_vec();
+    graph.set_output_outlets(&nodes.iter().map(|n| OutletId::new(*n, 0)).collect_vec())?;
+    // the float model is fed the shapes tract expects, which may carry a batch dim
+    let shapes = graph
+        .inputs
+        .iter()
+        .map(|o| -> Result<Vec<usize>, Box<dyn Error>> {
+            let shape = graph
+                .outlet_fact(*o)?
+                .shape
+                .concretize()
+                .ok_or("the shapes of the model's inputs should be known")?;
+            Ok(shape
+                .iter()
+                .map(|d| d.to_usize())
+                .collect::<Result<_, _>>()?)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let plan = graph.into_typed()?.into_runnable()?;
+    samples
+        .iter()
+        .map(|sample| {
+            let inputs = sample
+                .iter()
+                .zip(shapes.iter())
+                .map(|(values, dims)| {
+                    let values = values.iter().map(|v| *v as f32).collect_vec();
+                    TractTensor::from_shape(dims, &values)
+                })
+                .collect::<Result<TVec<_>, _>>()?;
+            let outputs = plan.run(inputs)?;
+            Ok(nodes
+                .iter()
+                .zip(outputs.iter())
+                .filter_map(|(n, o)| {
+                    let values = o.cast_to::<f32>().ok()?.as_slice::<f32>().ok()?.to_vec();
+                    Some((*n, values))
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// Evaluates the quantized model over a sample, returning the dequantized outputs of the model
+/// along with a histogram of the number of bits the tables need to cover each of the inputs of its
+/// lookups.
+pub fn quantized_pass(
+    model: &Model,
+    sample: &[Vec<f64>],
+) -> Result<(Vec<Vec<f32>>, Vec<usize>), Box<dyn Error>> {
+    let shapes = model.input_shapes();
+    let inputs = sample
+        .iter()
+        .zip(shapes.iter())
+        .map(|(values, dims)| vector_to_quantized(values, dims, 0.0, model.run_args.scale))
+        .collect::<Result<Vec<Tensor<i128>>, _>>()?;
+    let mut results = Model::load_sample(&model.nodes, &model.model.inputs, &inputs, 0, 1)?;
+    Model::eval_nodes(&model.nodes, &mut results)?;
+    let mut lookup_bits = vec![0; 129];
+    for n in model.nodes.values() {
+        if let OpKind::Lookup(op) = &n.opkind {
+            // tables centered on a zero-point cover [0, 2^bits) for their nonnegative inputs, and
+            // limbed lookups (and divisions with a remainder) look up the quotient of their inputs
+            let divisor = match op {
+                LookupOp::ZeroPoint { .. } => 2,
+                LookupOp::Limbed { shift, .. } => 1 << shift,
+                LookupOp::DivRem { denom } => *denom,
+                _ => 1,
+            };
+            for x in n.inputs.iter().flat_map(|o| results[&o.node].iter()) {
+                lookup_bits[bits_for(x.abs() / divisor)] += 1;
+            }
+        }
+    }
+    let outputs = model
+        .model
+        .outputs
+        .iter()
+        .map(|o| {
+            let multiplier = scale_to_multiplier(model.nodes[&o.node].out_scale);
+            results
+                .get(&o.node)
+                .map(|t| t.iter().map(|x| *x as f32 / multiplier).collect())
+                .ok_or("the outputs of the model should be evaluated")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((outputs, lookup_bits))
+}
+
+/// Chooses the settings of the `stats` which best meet the `options`, out of those fitting the
+/// bits and logrows budgets.
+fn choose<'a>(
+    stats: &'a [ScaleStats],
+    options: &CalibrationOptions,
+) -> Result<&'a ScaleStats, Box<dyn Error>> {
+    let fitting = stats
+        .iter()
+        .filter(|s| s.bits <= options.max_bits)
+        .filter(|s| options.max_logrows.map_or(true, |k| s.logrows <= k))
+        .collect_vec();
+    let most_accurate = fitting
+        .iter()
+        .min_by(|a, b| a.max_output_error.total_cmp(&b.max_output_error))
+        .copied()
+        .ok_or_else(|| {
+            let logrows = match options.max_logrows {
+                Some(k) => format!(" and {} logrows", k),
+                None => String::new(),
+            };
+            format!(
+                "no scale fits the lookup tables of the model in {} bits{}",
+                options.max_bits, logrows
+            )
+        })?;
+    if options.target == CalibrationTarget::Accuracy {
+        return Ok(most_accurate);
+    }
+    match fitting
+        .iter()
+        .filter(|s| s.max_output_error <= options.target_error)
+        .min_by_key(|s| (s.logrows, s.var_len, s.scale))
+        .copied()
+    {
+        Some(s) => Ok(s),
+        None => {
+            warn!(
+                "no scale up to {} is within the target error of {}, using the most accurate one",
+                options.max_scale, options.target_error
+            );
+            Ok(most_accurate)
+        }
+    }
+}
+
+/// Calibrates the scale, bits and logrows of the [RunArgs] over representative `data`. The float
+/// model is run over the data to measure the dynamic range of each tensor, and the quantized
+/// model is then evaluated at scales up to `max_scale`, each along with the smallest bits and
+/// logrows that avoid overflowing its tables (and, with a `max_cols` budget, in which the advices
+/// fit in that many columns). In resources mode the smallest circuit whose outputs are within
+/// `target_error` of the float outputs is chosen (or the most accurate one, if none is within the
+/// target error), and in accuracy mode the most accurate circuit is chosen, out of those whose
+/// tables fit in `max_bits` and whose logrows fit in `max_logrows`. The number of columns the
+/// advices take at the chosen logrows is saved along with them, pinning the layout.
+/// With a `percentile` under 100, ranges are clipped to the percentile rather than the absolute
+/// max, and the tables only need to cover that percentile of the inputs of the lookups: the
+/// settings then trade the rare outliers (which can't be proven) for a finer scale.
+pub fn calibrate(
+    model_path: impl AsRef<Path>,
+    data: &[ModelInput],
+    run_args: &RunArgs,
+    options: &CalibrationOptions,
+) -> Result<Calibration, Box<dyn Error>> {
+    let percentile = options.percentile;
+    // samples are evaluated one at a time
+    let sample_args = RunArgs {
+        batch_size: 1,
+        ..run_args.clone()
+    };
+    let visibility = VarVisibility::from_args(run_args.clone())?;
+    let base = Model::new(
+        model_path.as_ref(),
+        sample_args.clone(),
+        Mode::Table,
+        visibility.clone(),
+    )?;
+    let samples = load_samples(&base, data, run_args.batch_size)?;
+    info!("calibrating over {} samples", samples.len());
+
+    let float = float_pass(&base, &samples)?;
+    let mut values: BTreeMap<usize, Vec<f32>> = BTreeMap::new();
+    for (idx, v) in float.iter().flat_map(|f| f.iter()) {
+        values.entry(*idx).or_default().extend(v);
+    }
+    let ranges = values
+        .into_iter()
+        .filter(|(_, v)| !v.is_empty())
+        .map(|(idx, mut v)| {
+            v.sort_by(f32::total_cmp);
+            TensorRange {
+                idx,
+                name: base.model.nodes[idx].name.clone(),
+                min: v[0],
+                max: v[v.len() - 1],
+                clipped_min: super::percentile(&v, 100.0 - percentile),
+                clipped_max: super::percentile(&v, percentile),
+            }
+        })
+        .collect_vec();
+
+    let instance_len = base
+        .instance_shapes()
+        .iter()
+        .map(|s| s.iter().product::<usize>())
+        .max()
+        .unwrap_or(0);
+    let mut stats = vec![];
+    for scale in 1..=options.max_scale {
+        let args = RunArgs {
+            scale,
+            ..sample_args.clone()
+        };
+        let model = Model::new(model_path.as_ref(), args, Mode::Table, visibility.clone())?;
+        let has_lookups = model
+            .nodes
+            .values()
+            .any(|n| matches!(n.opkind, OpKind::Lookup(_)));
+        let (mut max_output_error, mut lookup_bits) = (0f32, vec![0usize; 129]);
+        for (sample, float) in samples.iter().zip(float.iter()) {
+            let (outputs, sample_lookup_bits) = quantized_pass(&model, sample)?;
+            for (b, count) in lookup_bits.iter_mut().zip(sample_lookup_bits) {
+                *b += count;
+            }
+            for (output, o) in outputs.iter().zip(model.model.outputs.iter()) {
+                if let Some(expected) = float.get(&o.node) {
+                    for (x, y) in output.iter().zip(expected.iter()) {
+                        max_output_error = max_output_error.max((x - y).abs());
+                    }
+                }
+            }
+        }
+        // the tables cover all but the outliers past the percentile
+        let outliers =
+            lookup_bits.iter().sum::<usize>() as f64 * (100.0 - percentile as f64) / 100.0;
+        let (mut bits, mut clipped_lookups) = (lookup_bits.len() - 1, 0);
+        while bits > 1 && (clipped_lookups + lookup_bits[bits]) as f64 <= outliers {
+            clipped_lookups += lookup_bits[bits];
+            bits -= 1;
+        }
+        if !has_lookups {
+            bits = run_args.bits;
+        }
+        // the instances and tables need to fit in a column, along with the blinding rows
+        let mut logrows = (instance_len + 10).next_power_of_two().trailing_zeros();
+        if has_lookups {
+            logrows = logrows.max(bits as u32 + 1);
+        }
+        let var_len = model.total_var_len();
+        // the advices need to fit in the budget of columns, which can take more rows
+        let (logrows, num_cols) = ModelVars::<Fr>::fit(
+            var_len,
+            logrows,
+            MAX_LOGROWS,
+            options.max_cols.unwrap_or(usize::MAX),
+        )
+        .ok_or_else(|| {
+            format!(
+                "the {} variables of the model don't fit in {} columns at any logrows",
+                var_len,
+                options.max_cols.unwrap_or(usize::MAX)
+            )
+        })?;
+        info!(
+            "scale {}: max output error {}, {} bits ({} lookups clipped), {} logrows, {} variables over {} columns",
+            scale, max_output_error, bits, clipped_lookups, logrows, var_len, num_cols
+        );
+        stats.push(ScaleStats {
+            scale,
+            max_output_error,
+            bits,
+            clipped_lookups,
+            logrows,
+            var_len,
+            num_cols,
+        });
+    }
+
+    let chosen = choose(&stats, options)?;
+    info!(
+        "calibrated settings: -S {} -B {} -K {} --num-cols {}",
+        chosen.scale, chosen.bits, chosen.logrows, chosen.num_cols
+    );
+    Ok(Calibration {
+        run_args: RunArgs {
+            scale: chosen.scale,
+            bits: chosen.bits,
+            logrows: chosen.logrows,
+            num_cols: chosen.num_cols,
+            ..run_args.clone()
+        },
+        ranges,
+        scales: stats,
+    })
+}
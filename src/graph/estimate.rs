@@ -0,0 +1,259 @@
+use super::{Model, ModelVars};
+use halo2_proofs::arithmetic::best_multiexp;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::poly::EvaluationDomain;
+use halo2curves::bn256::{Fr, G1Affine};
+use halo2curves::group::ff::Field;
+use halo2curves::group::prime::PrimeCurveAffine;
+use itertools::Itertools;
+use rand::rngs::OsRng;
+use serde::Serialize;
+use std::error::Error;
+use std::time::Instant;
+
+/// The bytes of a (compressed) point of the commitments of a proof, and of a field element.
+const ELEMENT_BYTES: usize = 32;
+/// The log2 size of the multiexp and FFT timed to extrapolate the proving time from.
+const SAMPLE_K: u32 = 12;
+/// The largest logrows searched for a circuit to fit in.
+const MAX_LOGROWS: u32 = 28;
+
+/// The estimated size and cost of the circuit of a model, derived from its configuration alone,
+/// without generating keys.
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitEstimate {
+    /// The logrows the circuit is configured for
+    pub logrows: u32,
+    /// The number of advice columns
+    pub advice_columns: usize,
+    /// The number of fixed columns
+    pub fixed_columns: usize,
+    /// The number of instance columns
+    pub instance_columns: usize,
+    /// The number of selectors (which keygen packs into fixed columns)
+    pub selectors: usize,
+    /// The number of lookup arguments
+    pub lookups: usize,
+    /// The largest degree of the constraints
+    pub degree: usize,
+    /// The number of rows reserved for blinding at the bottom of each column
+    pub blinding_rows: usize,
+    /// The number of advice cells the model lays out
+    pub var_len: usize,
+    /// The number of columns each of the model's advices is laid out over
+    pub model_columns: usize,
+    /// The number of rows the model's advices take, in the columns they're laid out over
+    pub advice_rows: usize,
+    /// The number of rows of the longest instance
+    pub instance_rows: usize,
+    /// The lookup tables of the circuit
+    pub lookup_tables: Vec<String>,
+    /// The number of rows of each of the lookup tables
+    pub table_rows: usize,
+    /// The smallest logrows fitting the advices (in as many columns as at `logrows`), the tables
+    /// and the instances, along with the blinding rows
+    pub min_logrows: u32,
+    /// The chips whose rows aren't counted in the rows above
+    pub uncounted_chips: Vec<String>,
+    /// The estimated size of a proof, in bytes
+    pub proof_size: usize,
+    /// The estimated size of the proving key, in bytes
+    pub pk_size: usize,
+    /// The estimated peak memory of proving, in bytes, dominated by the proving key and the
+    /// extended cosets of the advices
+    pub prove_memory: usize,
+    /// The number of points of the multiexps committing to the columns of a proof
+    pub msm_points: usize,
+    /// The number of elements of the FFTs over the extended domain of a proof
+    pub fft_elements: usize,
+    /// A rough estimate of the time to prove, in seconds, extrapolated from a multiexp and an
+    /// FFT timed on this machine
+    pub prove_time_secs: f64,
+}
+
+impl Model {
+    /// Estimates the size and cost of the model's circuit, by configuring it in a fresh
+    /// [ConstraintSystem] at the model's logrows. The rows of the advices, tables and instances
+    /// are counted from the model, and the sizes of the keys and proofs follow the layout of
+    /// halo2's PLONK with KZG (GWC) openings. No keys are generated.
+    pub fn estimate(&self) -> Result<CircuitEstimate, Box<dyn Error>> {
+        self.estimate_at(self.run_args.logrows)
+    }
+
+    /// Searches for the smallest logrows the model's circuit fits in, with each of its advices
+    /// laid out over at most `max_cols` columns (if any), returning the logrows along with the
+    /// number of columns the advices take. The rows of the hash and signature chips aren't
+    /// counted (see [CircuitEstimate::uncounted_chips]).
+    pub fn min_logrows(&self, max_cols: Option<usize>) -> Result<(u32, usize), Box<dyn Error>> {
+        // the tables and instances take as many rows at any logrows
+        let estimate = self.estimate_at(self.run_args.logrows)?;
+        let rows = estimate.table_rows.max(estimate.instance_rows) + estimate.blinding_rows;
+        let lower = rows.next_power_of_two().trailing_zeros();
+        // skips the logrows at which the advices can't fit in the budget of columns, without
+        // configuring the circuit
+        let (lower, _) = ModelVars::<Fr>::fit(
+            estimate.var_len,
+            lower,
+            MAX_LOGROWS,
+            max_cols.unwrap_or(usize::MAX),
+        )
+        .ok_or_else(|| format!("the circuit doesn't fit in {} logrows", MAX_LOGROWS))?;
+        for logrows in lower..=MAX_LOGROWS {
+            let estimate = self.estimate_at(logrows)?;
+            let fits_cols = max_cols.map_or(true, |c| estimate.model_columns <= c);
+            if fits_cols && estimate.min_logrows <= logrows {
+                return Ok((logrows, estimate.model_columns));
+            }
+        }
+        Err(format!("the circuit doesn't fit in {} logrows", MAX_LOGROWS).into())
+    }
+
+    /// Estimates the size and cost of the model's circuit at `logrows` (see [Model::estimate]).
+    pub fn estimate_at(&self, logrows: u32) -> Result<CircuitEstimate, Box<dyn Error>> {
+        let var_len = self.total_var_len();
+        let instance_shapes = self.instance_shapes();
+        let mut cs = ConstraintSystem::<Fr>::default();
+        let mut vars = ModelVars::new(
+            &mut cs,
+            logrows as usize,
+            self.run_args.num_cols,
+            var_len,
+            instance_shapes.clone(),
+            self.visibility.clone(),
+        );
+        let model_cols = vars.advices.first().map_or(1, |a| a.num_cols()).max(1);
+        let config = self.configure(&mut cs, &mut vars)?;
+
+        let mut uncounted_chips = vec![];
+        if config.poseidon.is_some() {
+            uncounted_chips.push("poseidon".to_string());
+        }
+        if config.sha256.is_some() {
+            uncounted_chips.push("sha256".to_string());
+        }
+        if config.merkle.is_some() {
+            uncounted_chips.push("merkle".to_string());
+        }
+        if config.ecdsa.is_some() {
+            uncounted_chips.push("ecdsa".to_string());
+        }
+
+        let lookup_tables = self
+            .nodes
+            .values()
+            .flat_map(|n| self.node_lookups(n))
+            .unique()
+            .map(|l| format!("{:?}", l))
+            .collect_vec();
+        let table_rows = match lookup_tables.is_empty() {
+            true => 0,
+            false => 1 << self.run_args.bits,
+        };
+        // values spanning several columns duplicate a row in each
+        let advice_rows = (var_len + model_cols - 1) / model_cols + 1;
+        let instance_rows = instance_shapes
+            .iter()
+            .map(|s| s.iter().product::<usize>())
+            .max()
+            .unwrap_or(0);
+        let blinding_rows = cs.blinding_factors() + 1;
+        let rows = advice_rows.max(table_rows).max(instance_rows) + blinding_rows;
+        let min_logrows = rows.next_power_of_two().trailing_zeros();
+
+        let n = 1usize << logrows;
+        let degree = cs.degree();
+        let advice_columns = cs.num_advice_columns();
+        let fixed_columns = cs.num_fixed_columns();
+        let instance_columns = cs.num_instance_columns();
+        let selectors = cs.num_selectors();
+        let lookups = cs.lookups().len();
+        let permutation_columns = cs.permutation().get_columns().len();
+        // the permutation argument is split into products of (degree - 2) columns each
+        let permutation_chunks = match permutation_columns {
+            0 => 0,
+            p => (p + degree - 3) / (degree - 2),
+        };
+        // the quotient is split into (degree - 1) pieces, over an extended domain of 2^(k + e)
+        let quotient_pieces = degree - 1;
+        let extension = quotient_pieces.next_power_of_two();
+
+        // commitments to the advices, the permuted inputs, tables and products of the lookups,
+        // the products of the permutation, the random polynomial and the pieces of the quotient
+        let committed = advice_columns + 3 * lookups + permutation_chunks;
+        let commitments = committed + 1 + quotient_pieces;
+        // evaluations at the queried rotations, of the permutation's sigmas and products, of the
+        // lookups' products and permuted columns, and of the random polynomial
+        let evaluations = cs.advice_queries().len()
+            + cs.fixed_queries().len()
+            + permutation_columns
+            + 3 * permutation_chunks
+            + 5 * lookups
+            + 1;
+        let rotations = cs
+            .advice_queries()
+            .iter()
+            .map(|(_, r)| r.0)
+            .chain(cs.fixed_queries().iter().map(|(_, r)| r.0))
+            .chain(cs.instance_queries().iter().map(|(_, r)| r.0))
+            .chain([0, 1, -1])
+            .unique()
+            .count();
+        let proof_size = ELEMENT_BYTES * (commitments + evaluations + rotations);
+
+        // the fixed columns (and the sigmas of the permutation) are held as values, coefficients
+        // and extended cosets, along with the cosets of l_0, l_last and l_active_row
+        let pk_elements = (fixed_columns + selectors + permutation_columns) * n * (2 + extension)
+            + 3 * n * extension;
+        let pk_size = ELEMENT_BYTES * pk_elements;
+        let prove_memory = pk_size + ELEMENT_BYTES * committed * n * (2 + extension);
+
+        let msm_points = commitments * n;
+        let fft_elements =
+            (committed + fixed_columns + permutation_columns) * n * extension + quotient_pieces * n;
+        let (msm_rate, fft_rate) = sample_rates();
+        let extended_k = (logrows + extension.trailing_zeros()) as f64;
+        let prove_time_secs =
+            msm_points as f64 * msm_rate + fft_elements as f64 * extended_k * fft_rate;
+
+        Ok(CircuitEstimate {
+            logrows,
+            advice_columns,
+            fixed_columns,
+            instance_columns,
+            selectors,
+            lookups,
+            degree,
+            blinding_rows,
+            var_len,
+            model_columns: model_cols,
+            advice_rows,
+            instance_rows,
+            lookup_tables,
+            table_rows,
+            min_logrows,
+            uncounted_chips,
+            proof_size,
+            pk_size,
+            prove_memory,
+            msm_points,
+            fft_elements,
+            prove_time_secs,
+        })
+    }
+}
+
+/// Times a multiexp of 2^SAMPLE_K points and an FFT of 2^SAMPLE_K elements on this machine,
+/// returning the seconds per point of the multiexp and per element and level of the FFT.
+fn sample_rates() -> (f64, f64) {
+    let n = 1 << SAMPLE_K;
+    let scalars = (0..n).map(|_| Fr::random(OsRng)).collect_vec();
+    let bases = vec![G1Affine::generator(); n];
+    let now = Instant::now();
+    let _ = best_multiexp(&scalars, &bases);
+    let msm_rate = now.elapsed().as_secs_f64() / n as f64;
+    let domain = EvaluationDomain::<Fr>::new(1, SAMPLE_K);
+    let now = Instant::now();
+    let _ = domain.lagrange_to_coeff(domain.lagrange_from_vec(scalars));
+    let fft_rate = now.elapsed().as_secs_f64() / (n * SAMPLE_K as usize) as f64;
+    (msm_rate, fft_rate)
+}
@@ -0,0 +1,134 @@
+use super::GraphError;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use tract_onnx::pb::{tensor_proto::DataLocation, GraphProto, ModelProto, TensorProto};
+
+/// Resolves the path of an external data file relative to `model_dir`, rejecting locations which
+/// escape the directory (absolute paths, `..` components or symlinks pointing out of it).
+fn sandboxed_path(model_dir: &Path, location: &str) -> Result<PathBuf, GraphError> {
+    let relative = Path::new(location);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(GraphError::ExternalData(format!(
+            "{} should be relative to the model's directory",
+            location
+        )));
+    }
+    let dir = model_dir
+        .canonicalize()
+        .map_err(|e| GraphError::ExternalData(format!("{}: {}", model_dir.display(), e)))?;
+    let path = dir
+        .join(relative)
+        .canonicalize()
+        .map_err(|e| GraphError::ExternalData(format!("{}: {}", location, e)))?;
+    if !path.starts_with(&dir) {
+        return Err(GraphError::ExternalData(format!(
+            "{} resolves outside of the model's directory",
+            location
+        )));
+    }
+    Ok(path)
+}
+
+/// Loads the data of a tensor stored externally into its raw data, counting the bytes read in
+/// `total` which may not exceed `max_bytes`.
+fn resolve_tensor(
+    tensor: &mut TensorProto,
+    model_dir: &Path,
+    max_bytes: u64,
+    total: &mut u64,
+) -> Result<(), GraphError> {
+    if tensor.data_location != DataLocation::External as i32 {
+        return Ok(());
+    }
+    let entry = |key: &str| {
+        tensor
+            .external_data
+            .iter()
+            .find(|e| e.key == key)
+            .map(|e| e.value.clone())
+    };
+    let parse = |key: &str| -> Result<Option<u64>, GraphError> {
+        entry(key)
+            .map(|v| {
+                v.parse::<u64>().map_err(|_| {
+                    GraphError::ExternalData(format!("{} of {} is not a number", key, tensor.name))
+                })
+            })
+            .transpose()
+    };
+    let location = entry("location").ok_or_else(|| {
+        GraphError::ExternalData(format!("{} has no external data location", tensor.name))
+    })?;
+    let offset = parse("offset")?.unwrap_or(0);
+    let length = parse("length")?;
+
+    let path = sandboxed_path(model_dir, &location)?;
+    let io_err = |e: std::io::Error| GraphError::ExternalData(format!("{}: {}", location, e));
+    let mut file = File::open(&path).map_err(io_err)?;
+    let file_len = file.metadata().map_err(io_err)?.len();
+    let length = length.unwrap_or(file_len.saturating_sub(offset));
+    if offset
+        .checked_add(length)
+        .map_or(true, |end| end > file_len)
+    {
+        return Err(GraphError::ExternalData(format!(
+            "{} reads past the end of {}",
+            tensor.name, location
+        )));
+    }
+    *total += length;
+    if *total > max_bytes {
+        return Err(GraphError::ExternalData(format!(
+            "the external data of the model exceeds the limit of {} bytes",
+            max_bytes
+        )));
+    }
+
+    let mut data = vec![0u8; length as usize];
+    file.seek(SeekFrom::Start(offset)).map_err(io_err)?;
+    file.read_exact(&mut data).map_err(io_err)?;
+    tensor.raw_data = data;
+    tensor.external_data.clear();
+    tensor.data_location = DataLocation::Default as i32;
+    Ok(())
+}
+
+fn resolve_graph(
+    graph: &mut GraphProto,
+    model_dir: &Path,
+    max_bytes: u64,
+    total: &mut u64,
+) -> Result<(), GraphError> {
+    for tensor in graph.initializer.iter_mut() {
+        resolve_tensor(tensor, model_dir, max_bytes, total)?;
+    }
+    // constants and the bodies of control flow ops can hold external tensors too
+    for attribute in graph.node.iter_mut().flat_map(|n| n.attribute.iter_mut()) {
+        for tensor in attribute.t.iter_mut().chain(attribute.tensors.iter_mut()) {
+            resolve_tensor(tensor, model_dir, max_bytes, total)?;
+        }
+        for subgraph in attribute.g.iter_mut().chain(attribute.graphs.iter_mut()) {
+            resolve_graph(subgraph, model_dir, max_bytes, total)?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads the tensors of `model` which are stored in external data files into the model. The
+/// files are resolved relative to `model_dir` and may not lie outside of it, and at most
+/// `max_bytes` of external data are loaded.
+pub fn resolve_external_data(
+    model: &mut ModelProto,
+    model_dir: &Path,
+    max_bytes: u64,
+) -> Result<(), GraphError> {
+    let mut total = 0;
+    if let Some(graph) = model.graph.as_mut() {
+        resolve_graph(graph, model_dir, max_bytes, &mut total)?;
+    }
+    Ok(())
+}
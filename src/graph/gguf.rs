@@ -0,0 +1,301 @@
+use super::{f16_to_f32, GraphError};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use tract_onnx::prelude::{Arc, Graph, InferenceFact, Tensor as TractTensor};
+use tract_onnx::tract_hir::{internal::InferenceOp, tract_core::ops::konst::Const};
+
+/// The number of values in a quantization block.
+const BLOCK_LEN: usize = 32;
+/// The alignment of tensor data, unless overridden by the `general.alignment` metadata.
+const DEFAULT_ALIGNMENT: usize = 32;
+
+/// A tensor of a GGUF file, dequantized to floats.
+#[derive(Clone, Debug)]
+pub struct GgufTensor {
+    /// The dims of the tensor, outermost first
+    pub dims: Vec<usize>,
+    /// The dequantized values of the tensor
+    pub values: Vec<f32>,
+    /// The scales of the quantization blocks of the tensor (empty for float tensors)
+    pub block_scales: Vec<f32>,
+}
+
+impl GgufTensor {
+    /// The smallest fixed point scale at which the step between the quantized values of each
+    /// block maps onto at least one fixed point unit, such that distinct quantized values remain
+    /// distinct once the tensor is quantized by ezkl. `None` for float tensors.
+    pub fn min_scale(&self) -> Option<u32> {
+        let min_step = self
+            .block_scales
+            .iter()
+            .map(|d| d.abs())
+            .filter(|d| *d > 0.0)
+            .fold(f32::INFINITY, f32::min);
+        match min_step.is_finite() {
+            true => Some((-min_step.log2()).ceil().max(0.0) as u32),
+            false => None,
+        }
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+fn truncated() -> GraphError {
+    GraphError::MisformedParams("truncated gguf file".to_string())
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], GraphError> {
+        let end = self.pos.checked_add(n).ok_or_else(truncated)?;
+        let bytes = self.buf.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// The number of bytes left to read.
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    fn u32(&mut self) -> Result<u32, GraphError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<usize, GraphError> {
+        usize::try_from(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+            .map_err(|_| truncated())
+    }
+
+    fn string(&mut self) -> Result<String, GraphError> {
+        let len = self.u64()?;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).to_string())
+    }
+
+    /// Reads a metadata value of type `value_type`, returning it if it is an integer.
+    fn value(&mut self, value_type: u32) -> Result<Option<usize>, GraphError> {
+        Ok(match value_type {
+            0 | 1 | 7 => Some(self.bytes(1)?[0] as usize),
+            2 | 3 => Some(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()) as usize),
+            4 | 5 => Some(self.u32()? as usize),
+            6 => {
+                self.bytes(4)?;
+                None
+            }
+            8 => {
+                self.string()?;
+                None
+            }
+            9 => {
+                let (element_type, len) = (self.u32()?, self.u64()?);
+                for _ in 0..len {
+                    self.value(element_type)?;
+                }
+                None
+            }
+            10 | 11 => Some(self.u64()?),
+            12 => {
+                self.bytes(8)?;
+                None
+            }
+            t => {
+                return Err(GraphError::MisformedParams(format!(
+                    "unknown gguf metadata type {}",
+                    t
+                )))
+            }
+        })
+    }
+}
+
+/// Dequantizes `len` values of ggml type `ggml_type` from `data`, returning them along with the
+/// scales of their quantization blocks.
+fn dequantize(ggml_type: u32, data: &[u8], len: usize) -> Result<(Vec<f32>, Vec<f32>), GraphError> {
+    let half = |b: &[u8]| f16_to_f32(u16::from_le_bytes([b[0], b[1]]));
+    let block_bytes = match ggml_type {
+        0 => 4,
+        1 => 2,
+        // Q4_0: f16 scale, 16 bytes of nibbles
+        2 => 18,
+        // Q4_1: f16 scale, f16 min, 16 bytes of nibbles
+        3 => 20,
+        // Q8_0: f16 scale, 32 bytes
+        8 => 34,
+        t => {
+            return Err(GraphError::MisformedParams(format!(
+                "ggml type {} is not supported, only F32, F16, Q4_0, Q4_1 and Q8_0 are",
+                t
+            )))
+        }
+    };
+    let num_blocks = match ggml_type {
+        0 | 1 => len,
+        _ => len / BLOCK_LEN + usize::from(len % BLOCK_LEN != 0),
+    };
+    // checked against the data before allocating, as the length is read from the file
+    let data = num_blocks
+        .checked_mul(block_bytes)
+        .and_then(|n| data.get(..n))
+        .ok_or_else(|| GraphError::MisformedParams("truncated gguf tensor data".to_string()))?;
+    let (mut values, mut scales) = (Vec::with_capacity(len), vec![]);
+    for block in data.chunks_exact(block_bytes) {
+        match ggml_type {
+            0 => values.push(f32::from_le_bytes(block.try_into().unwrap())),
+            1 => values.push(half(block)),
+            2 | 3 => {
+                let d = half(&block[0..2]);
+                let (min, nibbles) = match ggml_type {
+                    2 => (-8.0 * d, &block[2..]),
+                    _ => (half(&block[2..4]), &block[4..]),
+                };
+                // the low nibbles hold the first half of the block
+                values.extend(nibbles.iter().map(|q| (q & 0xf) as f32 * d + min));
+                values.extend(nibbles.iter().map(|q| (q >> 4) as f32 * d + min));
+                scales.push(d);
+            }
+            _ => {
+                let d = half(&block[0..2]);
+                values.extend(block[2..].iter().map(|q| *q as i8 as f32 * d));
+                scales.push(d);
+            }
+        }
+    }
+    values.truncate(len);
+    Ok((values, scales))
+}
+
+/// Loads and dequantizes the F32, F16, Q8_0, Q4_0 and Q4_1 tensors of the GGUF file at `path`,
+/// keyed by name.
+pub fn load_gguf(path: &Path) -> Result<BTreeMap<String, GgufTensor>, Box<dyn Error>> {
+    let buf = std::fs::read(path)?;
+    let mut reader = Reader { buf: &buf, pos: 0 };
+    if reader.bytes(4)? != b"GGUF" {
+        return Err(Box::new(GraphError::MisformedParams(
+            "not a gguf file".to_string(),
+        )));
+    }
+    let version = reader.u32()?;
+    if version < 2 {
+        return Err(Box::new(GraphError::MisformedParams(format!(
+            "gguf version {} is not supported",
+            version
+        ))));
+    }
+    let (num_tensors, num_metadata) = (reader.u64()?, reader.u64()?);
+    let mut alignment = DEFAULT_ALIGNMENT;
+    for _ in 0..num_metadata {
+        let key = reader.string()?;
+        let value_type = reader.u32()?;
+        let value = reader.value(value_type)?;
+        if let ("general.alignment", Some(a)) = (key.as_str(), value) {
+            if a == 0 {
+                return Err(Box::new(GraphError::MisformedParams(
+                    "the gguf alignment should be positive".to_string(),
+                )));
+            }
+            alignment = a;
+        }
+    }
+
+    let mut infos = vec![];
+    for _ in 0..num_tensors {
+        let name = reader.string()?;
+        let rank = reader.u32()? as usize;
+        if rank > reader.remaining() / 8 {
+            return Err(Box::new(truncated()));
+        }
+        // ggml dims are innermost first
+        let mut dims = (0..rank)
+            .map(|_| reader.u64())
+            .collect::<Result<Vec<_>, _>>()?;
+        dims.reverse();
+        let (ggml_type, offset) = (reader.u32()?, reader.u64()?);
+        infos.push((name, dims, ggml_type, offset));
+    }
+    let data_start = reader
+        .pos
+        .checked_add(alignment - 1)
+        .map(|end| end / alignment * alignment)
+        .ok_or_else(truncated)?;
+
+    let mut tensors = BTreeMap::new();
+    for (name, dims, ggml_type, offset) in infos {
+        let out_of_bounds =
+            || GraphError::MisformedParams(format!("gguf tensor {} is out of bounds", name));
+        let data = data_start
+            .checked_add(offset)
+            .and_then(|start| buf.get(start..))
+            .ok_or_else(out_of_bounds)?;
+        let len = dims
+            .iter()
+            .try_fold(1usize, |len, d| len.checked_mul(*d))
+            .ok_or_else(out_of_bounds)?;
+        let (values, block_scales) = dequantize(ggml_type, data, len)?;
+        tensors.insert(
+            name,
+            GgufTensor {
+                dims,
+                values,
+                block_scales,
+            },
+        );
+    }
+    Ok(tensors)
+}
+
+/// Substitutes the values of the constants of `model` with the tensors of the same name of the
+/// GGUF file at `path`, warning when `scale` is too coarse to tell the quantized values of a
+/// tensor's blocks apart.
+pub fn substitute_weights(
+    model: &mut Graph<InferenceFact, Box<dyn InferenceOp>>,
+    path: &Path,
+    scale: u32,
+) -> Result<(), Box<dyn Error>> {
+    let tensors = load_gguf(path)?;
+    let mut substituted = 0;
+    for (name, tensor) in tensors.iter() {
+        let node = match model.nodes.iter_mut().find(|n| &n.name == name) {
+            Some(node) => node,
+            None => continue,
+        };
+        let konst: &Const = match node.op.as_any().downcast_ref() {
+            Some(c) => c,
+            None => {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "gguf tensor {} should replace a constant",
+                    name
+                ))))
+            }
+        };
+        if konst.0.shape() != tensor.dims.as_slice() {
+            return Err(Box::new(GraphError::MisformedParams(format!(
+                "gguf tensor {} has dims {:?}, the model expects {:?}",
+                name,
+                tensor.dims,
+                konst.0.shape()
+            ))));
+        }
+        if let Some(min_scale) = tensor.min_scale() {
+            if min_scale > scale {
+                warn!(
+                    "the blocks of gguf tensor {} need a scale of {} to be represented exactly, quantized values will be merged at scale {}",
+                    name, min_scale, scale
+                );
+            }
+        }
+        let values = TractTensor::from_shape(&tensor.dims, &tensor.values)?;
+        node.outputs[0].fact = InferenceFact::from(values.clone());
+        node.op = Box::new(Const(Arc::new(values)));
+        substituted += 1;
+    }
+    info!(
+        "substituted {} of the {} gguf tensors into the model",
+        substituted,
+        tensors.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,507 @@
+use super::onnx_builder::{
+    float_attr, int_attr, ints_attr, permute, string_attr, value_info, Emitter,
+};
+use super::GraphError;
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use tract_onnx::pb::ModelProto;
+
+/// The weights of a layer keyed by name (eg. kernel, bias), along with their dims.
+type Weights = BTreeMap<String, (Vec<f32>, Vec<i64>)>;
+
+fn misformed(msg: String) -> Box<dyn Error> {
+    Box::new(GraphError::MisformedParams(msg))
+}
+
+/// Reads the datasets nested in `group`, keyed by their name without the `:0` suffix.
+fn read_weights(group: &hdf5::Group, weights: &mut Weights) -> Result<(), Box<dyn Error>> {
+    for name in group.member_names()? {
+        match group.group(&name) {
+            Ok(g) => read_weights(&g, weights)?,
+            Err(_) => {
+                let dataset = group.dataset(&name)?;
+                let dims = dataset.shape().iter().map(|d| *d as i64).collect();
+                weights.insert(
+                    name.trim_end_matches(":0").to_string(),
+                    (dataset.read_raw::<f32>()?, dims),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The dims of an input layer, whose batch dim is symbolic.
+fn input_dims(config: &Json) -> Result<Vec<i64>, Box<dyn Error>> {
+    let shape = config
+        .get("batch_input_shape")
+        .or_else(|| config.get("batch_shape"))
+        .and_then(|s| s.as_array())
+        .ok_or_else(|| misformed("keras input layer has no batch_input_shape".to_string()))?;
+    Ok(shape.iter().map(|d| d.as_i64().unwrap_or(-1)).collect())
+}
+
+fn int_pair(config: &Json, key: &str, default: i64) -> Vec<i64> {
+    match config.get(key) {
+        Some(Json::Array(a)) => a.iter().map(|d| d.as_i64().unwrap_or(default)).collect(),
+        Some(Json::Number(n)) => vec![n.as_i64().unwrap_or(default); 2],
+        _ => vec![default; 2],
+    }
+}
+
+/// The ONNX `auto_pad` of a keras padding, keras' `same` padding putting any odd padding last.
+fn auto_pad(config: &Json) -> &'static str {
+    match config.get("padding").and_then(|p| p.as_str()) {
+        Some("same") => "SAME_UPPER",
+        _ => "VALID",
+    }
+}
+
+/// Applies the keras activation called `activation` to `x`.
+fn activation(
+    e: &mut Emitter,
+    x: String,
+    activation: &str,
+    output: &str,
+) -> Result<String, Box<dyn Error>> {
+    let output = Some(output);
+    Ok(match activation {
+        "linear" => e.node("Identity", vec![x], vec![], output),
+        "relu" => e.node("Relu", vec![x], vec![], output),
+        "relu6" => e.clip(x, 0.0, 6.0, output),
+        "sigmoid" => e.node("Sigmoid", vec![x], vec![], output),
+        "tanh" => e.node("Tanh", vec![x], vec![], output),
+        "softmax" => e.node("Softmax", vec![x], vec![int_attr("axis", -1)], output),
+        a => {
+            return Err(misformed(format!(
+                "keras activation {} is not supported",
+                a
+            )))
+        }
+    })
+}
+
+/// Translates a keras layer of class `class` applied to `inputs` into ONNX nodes, whose output
+/// is named `output`.
+fn layer(
+    e: &mut Emitter,
+    class: &str,
+    config: &Json,
+    inputs: Vec<String>,
+    weights: &Weights,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    if config.get("data_format").and_then(|d| d.as_str()) == Some("channels_first") {
+        return Err(misformed(format!(
+            "keras layer {} is channels first, only channels last layers are supported",
+            output
+        )));
+    }
+    let x = inputs
+        .first()
+        .cloned()
+        .ok_or_else(|| misformed(format!("keras layer {} has no input", output)))?;
+    let weight = |name: &str| {
+        weights
+            .get(name)
+            .cloned()
+            .ok_or_else(|| misformed(format!("keras layer {} has no {} weight", output, name)))
+    };
+    let act = config
+        .get("activation")
+        .and_then(|a| a.as_str())
+        .unwrap_or("linear");
+    let use_bias = config
+        .get("use_bias")
+        .and_then(|b| b.as_bool())
+        .unwrap_or(true);
+
+    match class {
+        "Dense" => {
+            // keras kernels are [in, out] and apply to the last axis
+            let (kernel, dims) = weight("kernel")?;
+            let kernel = e.float_initializer(&format!("{}/kernel", output), kernel, dims);
+            let mut y = e.node("MatMul", vec![x, kernel], vec![], None);
+            if use_bias {
+                let (bias, dims) = weight("bias")?;
+                let bias = e.float_initializer(&format!("{}/bias", output), bias, dims);
+                y = e.node("Add", vec![y, bias], vec![], None);
+            }
+            activation(e, y, act, output)?;
+        }
+        "Conv2D" | "DepthwiseConv2D" => {
+            let (kernel, dims) = weight(match class {
+                "Conv2D" => "kernel",
+                _ => "depthwise_kernel",
+            })?;
+            // keras kernels are [h, w, in, out] ([h, w, in, multiplier] for depthwise convs)
+            let (kernel, dims, group) = match class {
+                "Conv2D" => {
+                    let groups = config.get("groups").and_then(|g| g.as_i64()).unwrap_or(1);
+                    let (kernel, dims) = permute(&kernel, &dims, [3, 2, 0, 1]);
+                    (kernel, dims, groups)
+                }
+                _ => {
+                    let flat = [dims[0], dims[1], dims[2] * dims[3], 1];
+                    let (kernel, dims_) = permute(&kernel, &flat, [2, 3, 0, 1]);
+                    (kernel, dims_, dims[2])
+                }
+            };
+            let kernel = e.float_initializer(&format!("{}/kernel", output), kernel, dims);
+            let mut conv_inputs = vec![e.to_nchw(x), kernel];
+            if use_bias {
+                let (bias, dims) = weight("bias")?;
+                conv_inputs.push(e.float_initializer(&format!("{}/bias", output), bias, dims));
+            }
+            let y = e.node(
+                "Conv",
+                conv_inputs,
+                vec![
+                    ints_attr("strides", int_pair(config, "strides", 1)),
+                    ints_attr("dilations", int_pair(config, "dilation_rate", 1)),
+                    string_attr("auto_pad", auto_pad(config)),
+                    int_attr("group", group),
+                ],
+                None,
+            );
+            let y = e.to_nhwc(y);
+            activation(e, y, act, output)?;
+        }
+        "MaxPooling2D" | "AveragePooling2D" => {
+            let pool_size = int_pair(config, "pool_size", 2);
+            let strides = match config.get("strides") {
+                Some(Json::Null) | None => pool_size.clone(),
+                _ => int_pair(config, "strides", 1),
+            };
+            let op_type = match class {
+                "MaxPooling2D" => "MaxPool",
+                _ => "AveragePool",
+            };
+            let x = e.to_nchw(x);
+            let y = e.node(
+                op_type,
+                vec![x],
+                vec![
+                    ints_attr("kernel_shape", pool_size),
+                    ints_attr("strides", strides),
+                    string_attr("auto_pad", auto_pad(config)),
+                ],
+                None,
+            );
+            let y = e.to_nhwc(y);
+            e.node("Identity", vec![y], vec![], Some(output));
+        }
+        "GlobalAveragePooling2D" | "GlobalMaxPooling2D" => {
+            let op_type = match class {
+                "GlobalAveragePooling2D" => "ReduceMean",
+                _ => "ReduceMax",
+            };
+            let keepdims = config.get("keepdims").and_then(|k| k.as_bool()) == Some(true);
+            e.node(
+                op_type,
+                vec![x],
+                vec![
+                    ints_attr("axes", vec![1, 2]),
+                    int_attr("keepdims", keepdims as i64),
+                ],
+                Some(output),
+            );
+        }
+        "Flatten" => {
+            e.node("Flatten", vec![x], vec![int_attr("axis", 1)], Some(output));
+        }
+        "Reshape" => {
+            let target = config
+                .get("target_shape")
+                .and_then(|t| t.as_array())
+                .ok_or_else(|| misformed(format!("keras layer {} has no target_shape", output)))?;
+            // the batch dim is carried over
+            let shape = std::iter::once(0)
+                .chain(target.iter().map(|d| d.as_i64().unwrap_or(-1)))
+                .collect();
+            let shape = e.int_initializer(shape);
+            e.node("Reshape", vec![x, shape], vec![], Some(output));
+        }
+        "ZeroPadding2D" => {
+            let ((top, bottom), (left, right)) = match config.get("padding") {
+                Some(Json::Number(n)) => {
+                    let p = n.as_i64().unwrap_or(0);
+                    ((p, p), (p, p))
+                }
+                Some(Json::Array(a)) => {
+                    let pair = |v: &Json| match v {
+                        Json::Array(p) => (
+                            p.first().and_then(|d| d.as_i64()).unwrap_or(0),
+                            p.get(1).and_then(|d| d.as_i64()).unwrap_or(0),
+                        ),
+                        v => (v.as_i64().unwrap_or(0), v.as_i64().unwrap_or(0)),
+                    };
+                    (
+                        a.first().map(pair).unwrap_or((0, 0)),
+                        a.get(1).map(pair).unwrap_or((0, 0)),
+                    )
+                }
+                _ => ((1, 1), (1, 1)),
+            };
+            let pads = e.int_initializer(vec![0, top, left, 0, 0, bottom, right, 0]);
+            e.node("Pad", vec![x, pads], vec![], Some(output));
+        }
+        "Dropout" => {
+            e.node("Identity", vec![x], vec![], Some(output));
+        }
+        "Activation" => {
+            activation(e, x, act, output)?;
+        }
+        "Softmax" => {
+            let axis = config.get("axis").and_then(|a| a.as_i64()).unwrap_or(-1);
+            e.node(
+                "Softmax",
+                vec![x],
+                vec![int_attr("axis", axis)],
+                Some(output),
+            );
+        }
+        "ReLU" => {
+            let slope = config
+                .get("negative_slope")
+                .and_then(|s| s.as_f64())
+                .unwrap_or(0.0);
+            let max_value = config.get("max_value").and_then(|m| m.as_f64());
+            match (max_value, slope != 0.0) {
+                (None, false) => e.node("Relu", vec![x], vec![], Some(output)),
+                (Some(max), false) => e.clip(x, 0.0, max as f32, Some(output)),
+                (None, true) => e.node(
+                    "LeakyRelu",
+                    vec![x],
+                    vec![float_attr("alpha", slope as f32)],
+                    Some(output),
+                ),
+                (Some(_), true) => {
+                    return Err(misformed(format!(
+                        "keras layer {} is a leaky and capped relu, which is not supported",
+                        output
+                    )))
+                }
+            };
+        }
+        "LeakyReLU" => {
+            let alpha = config
+                .get("alpha")
+                .or_else(|| config.get("negative_slope"))
+                .and_then(|a| a.as_f64())
+                .unwrap_or(0.3);
+            e.node(
+                "LeakyRelu",
+                vec![x],
+                vec![float_attr("alpha", alpha as f32)],
+                Some(output),
+            );
+        }
+        "BatchNormalization" => {
+            // folded into a scale and shift of the last axis
+            let epsilon = config
+                .get("epsilon")
+                .and_then(|eps| eps.as_f64())
+                .unwrap_or(1e-3) as f32;
+            let (mean, dims) = weight("moving_mean")?;
+            let (variance, _) = weight("moving_variance")?;
+            let gamma = weight("gamma")
+                .map(|g| g.0)
+                .unwrap_or_else(|_| vec![1.0; mean.len()]);
+            let beta = weight("beta")
+                .map(|b| b.0)
+                .unwrap_or_else(|_| vec![0.0; mean.len()]);
+            let scale: Vec<f32> = gamma
+                .iter()
+                .zip(variance.iter())
+                .map(|(g, v)| g / (v + epsilon).sqrt())
+                .collect();
+            let shift = beta
+                .iter()
+                .zip(mean.iter().zip(scale.iter()))
+                .map(|(b, (m, s))| b - m * s)
+                .collect();
+            let scale = e.float_initializer(&format!("{}/scale", output), scale, dims.clone());
+            let shift = e.float_initializer(&format!("{}/shift", output), shift, dims);
+            let y = e.node("Mul", vec![x, scale], vec![], None);
+            e.node("Add", vec![y, shift], vec![], Some(output));
+        }
+        "Add" | "Subtract" | "Multiply" => {
+            let op_type = match class {
+                "Add" => "Add",
+                "Subtract" => "Sub",
+                _ => "Mul",
+            };
+            let mut y = x;
+            for (i, input) in inputs.iter().enumerate().skip(1) {
+                let last = i == inputs.len() - 1;
+                y = e.node(
+                    op_type,
+                    vec![y, input.clone()],
+                    vec![],
+                    last.then_some(output),
+                );
+            }
+        }
+        "Concatenate" => {
+            let axis = config.get("axis").and_then(|a| a.as_i64()).unwrap_or(-1);
+            e.node("Concat", inputs, vec![int_attr("axis", axis)], Some(output));
+        }
+        c => {
+            return Err(misformed(format!(
+                "keras layer {} ({}) is not supported",
+                output, c
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// The names of the layers feeding a functional model's layer (keras 2 format).
+fn inbound_layers(layer: &Json) -> Result<Vec<String>, Box<dyn Error>> {
+    let nodes = layer
+        .get("inbound_nodes")
+        .and_then(|n| n.as_array())
+        .cloned()
+        .unwrap_or_default();
+    if nodes.len() > 1 {
+        return Err(misformed(
+            "keras layers which are called more than once are not supported".to_string(),
+        ));
+    }
+    nodes
+        .first()
+        .and_then(|n| n.as_array())
+        .map(|n| {
+            n.iter()
+                .map(|inbound| {
+                    inbound
+                        .get(0)
+                        .and_then(|l| l.as_str())
+                        .map(|l| l.to_string())
+                        .ok_or_else(|| {
+                            misformed("keras inbound nodes should be in the keras 2 format".into())
+                        })
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| Ok(vec![]))
+}
+
+fn layer_names(layers: &Json, key: &str) -> Vec<String> {
+    layers
+        .get(key)
+        .and_then(|l| l.as_array())
+        .map(|l| {
+            l.iter()
+                .filter_map(|l| l.get(0).and_then(|n| n.as_str()).map(|n| n.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Translates a sequential or functional keras model saved in the HDF5 format into an ONNX model,
+/// such that it is loaded into the same graph representation as ONNX models are. Activations
+/// stay in the channels last layout of keras, and are transposed to channels first around
+/// convolutions and pools.
+pub fn to_onnx(path: &Path) -> Result<ModelProto, Box<dyn Error>> {
+    let file = hdf5::File::open(path)?;
+    let attr = file.attr("model_config")?;
+    let config: String = match attr.read_scalar::<hdf5::types::VarLenUnicode>() {
+        Ok(c) => c.to_string(),
+        Err(_) => attr.read_scalar::<hdf5::types::VarLenAscii>()?.to_string(),
+    };
+    let config: Json = serde_json::from_str(&config)?;
+    let class = config["class_name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let model_config = &config["config"];
+    let name = model_config["name"].as_str().unwrap_or("keras").to_string();
+    let layers = match &model_config["layers"] {
+        Json::Array(l) => l.clone(),
+        // older sequential configs are a list of layers
+        _ => model_config.as_array().cloned().unwrap_or_default(),
+    };
+    let weights_group = match file.group("model_weights") {
+        Ok(g) => g,
+        Err(_) => file.group("/")?,
+    };
+
+    let mut e = Emitter::default();
+    let (mut inputs, mut outputs) = (vec![], vec![]);
+    let mut previous: Option<String> = None;
+    for l in layers.iter() {
+        let layer_class = l["class_name"].as_str().unwrap_or_default();
+        let layer_config = &l["config"];
+        let layer_name = layer_config["name"]
+            .as_str()
+            .or_else(|| l["name"].as_str())
+            .ok_or_else(|| misformed("keras layer has no name".to_string()))?
+            .to_string();
+        let mut weights = Weights::new();
+        if let Ok(g) = weights_group.group(&layer_name) {
+            read_weights(&g, &mut weights)?;
+        }
+
+        let layer_inputs = match class.as_str() {
+            "Sequential" => match &previous {
+                Some(p) => vec![p.clone()],
+                None => {
+                    // the first layer of a sequential model may carry the input shape
+                    let input = match layer_class {
+                        "InputLayer" => layer_name.clone(),
+                        _ => "input".to_string(),
+                    };
+                    inputs.push(value_info(
+                        &input,
+                        Some(input_dims(layer_config)?.as_slice()),
+                    ));
+                    vec![input]
+                }
+            },
+            _ => inbound_layers(l)?,
+        };
+        if layer_class == "InputLayer" {
+            if class != "Sequential" {
+                inputs.push(value_info(
+                    &layer_name,
+                    Some(input_dims(layer_config)?.as_slice()),
+                ));
+            }
+        } else {
+            layer(
+                &mut e,
+                layer_class,
+                layer_config,
+                layer_inputs,
+                &weights,
+                &layer_name,
+            )?;
+        }
+        previous = Some(layer_name);
+    }
+
+    match class.as_str() {
+        "Sequential" => {
+            if let Some(p) = previous {
+                outputs.push(value_info(&p, None));
+            }
+        }
+        _ => {
+            outputs = layer_names(model_config, "output_layers")
+                .iter()
+                .map(|o| value_info(o, None))
+                .collect();
+            let declared = layer_names(model_config, "input_layers");
+            if !declared.is_empty() {
+                // inputs are ordered as declared by the model
+                inputs.sort_by_key(|i| declared.iter().position(|d| *d == i.name));
+            }
+        }
+    }
+
+    Ok(e.build(&name, "ezkl keras importer", inputs, outputs))
+}
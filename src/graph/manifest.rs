@@ -0,0 +1,281 @@
+use super::{Mode, Model, VarVisibility, Visibility};
+use crate::commands::RunArgs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// A manifest of the model and settings a circuit was built from. Proofs carry the hash of the
+/// manifest they were built for, such that verifying a proof against a different model or
+/// settings can be caught.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModelManifest {
+    /// The SHA-256 hash of the .onnx file, hex encoded
+    pub model_hash: String,
+    /// The SHA-256 hash of the GGUF weights substituted into the model (if any), hex encoded
+    #[serde(default)]
+    pub weights_hash: Option<String>,
+    /// The [RunArgs] the circuit was built with
+    pub run_args: RunArgs,
+    /// The visibility of the inputs, params and outputs of the model
+    pub visibility: VarVisibility,
+    /// The fixed point scales of the outputs of the model
+    pub output_scales: Vec<u32>,
+    /// The number of public (or hashed) inputs of the model, which precede its outputs in the
+    /// instances of a proof
+    #[serde(default)]
+    pub public_inputs: usize,
+    /// The names of the outputs of the model
+    #[serde(default)]
+    pub output_names: Vec<String>,
+    /// The dims of the outputs of the model
+    #[serde(default)]
+    pub output_shapes: Vec<Vec<usize>>,
+    /// The visibility of each of the outputs of the model
+    #[serde(default)]
+    pub output_visibility: Vec<Visibility>,
+    /// The ops of the model, in node order
+    pub ops: Vec<String>,
+    /// The dims of the outputs of the nodes of the model, in node order
+    #[serde(default)]
+    pub shapes: Vec<Vec<usize>>,
+    /// The fixed point scales of the outputs of the nodes of the model, in node order
+    #[serde(default)]
+    pub scales: Vec<u32>,
+    /// The number of variables the circuit lays out
+    #[serde(default)]
+    pub var_len: usize,
+    /// The smallest logrows which fit the lookup tables of the circuit (0 if it uses none)
+    #[serde(default)]
+    pub min_logrows: u32,
+}
+
+/// The SHA-256 hash of the file at `path`, hex encoded. The file is streamed through the hasher,
+/// as it may be as large as a set of params.
+pub fn hash_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+impl ModelManifest {
+    /// Creates the manifest of the .onnx model at `path`, loaded with `run_args`.
+    pub fn new(path: impl AsRef<Path>, run_args: &RunArgs) -> Result<Self, Box<dyn Error>> {
+        let model_hash = hash_file(path.as_ref())?;
+        let weights_hash = run_args
+            .gguf_weights
+            .as_deref()
+            .map(hash_file)
+            .transpose()?;
+        let visibility = VarVisibility::from_args(run_args.clone())?;
+        let model = Model::new(path, run_args.clone(), Mode::Prove, visibility.clone())?;
+        Ok(ModelManifest {
+            model_hash,
+            weights_hash,
+            // with the bits and pack base chosen for the model
+            run_args: model.run_args.clone(),
+            visibility,
+            output_scales: model.get_output_scales(),
+            public_inputs: model
+                .input_visibility()
+                .iter()
+                .filter(|v| v.is_public() || v.is_hashed())
+                .count(),
+            output_names: model.output_names(),
+            output_shapes: model.output_shapes(),
+            output_visibility: model.output_visibility(),
+            ops: model.nodes.values().map(|n| n.opkind.to_string()).collect(),
+            shapes: model.nodes.values().map(|n| n.out_dims.clone()).collect(),
+            scales: model.nodes.values().map(|n| n.out_scale).collect(),
+            var_len: model.total_var_len(),
+            // tables of 2^bits values need more rows than they hold, for the blinding rows
+            min_logrows: match model
+                .nodes
+                .values()
+                .any(|n| !model.node_lookups(n).is_empty())
+            {
+                true => model.run_args.bits as u32 + 1,
+                false => 0,
+            },
+        })
+    }
+
+    /// The SHA-256 hash of the json serialized manifest, hex encoded.
+    pub fn hash(&self) -> Result<String, Box<dyn Error>> {
+        let serialized = serde_json::to_string(&self)?;
+        Ok(hex::encode(Sha256::digest(serialized.as_bytes())))
+    }
+
+    /// The names of the fields of the manifest which differ from those of `other`.
+    pub fn diff(&self, other: &ModelManifest) -> Result<Vec<String>, Box<dyn Error>> {
+        let (lhs, rhs) = (serde_json::to_value(self)?, serde_json::to_value(other)?);
+        let mut fields = vec![];
+        if let (Some(lhs), Some(rhs)) = (lhs.as_object(), rhs.as_object()) {
+            for (name, value) in lhs {
+                match (name.as_str(), value, &rhs[name]) {
+                    // the run args are compared one by one
+                    ("run_args", serde_json::Value::Object(l), serde_json::Value::Object(r)) => {
+                        fields.extend(
+                            l.iter()
+                                .filter(|(arg, v)| r.get(*arg) != Some(*v))
+                                .map(|(arg, _)| format!("run_args.{}", arg)),
+                        );
+                    }
+                    (_, l, r) if l != r => fields.push(name.clone()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The names of the fields of the manifest which differ from the .onnx model at `path` and
+    /// `run_args`, comparing the hashes of the model and weights files rather than loading the
+    /// model. The run args which loading the model derives are skipped: bits and pack bases of 0,
+    /// which are picked for the model, and the tolerance when an output epsilon sets it.
+    pub fn diff_files(
+        &self,
+        path: impl AsRef<Path>,
+        run_args: &RunArgs,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut fields = vec![];
+        if hash_file(path.as_ref())? != self.model_hash {
+            fields.push("model_hash".to_string());
+        }
+        let weights_hash = run_args
+            .gguf_weights
+            .as_deref()
+            .map(hash_file)
+            .transpose()?;
+        if weights_hash != self.weights_hash {
+            fields.push("weights_hash".to_string());
+        }
+        let derived = |arg: &str, v: &serde_json::Value| match arg {
+            "bits" | "pack_base" => v.as_u64() == Some(0),
+            "tolerance" => run_args.output_epsilon.is_some(),
+            _ => false,
+        };
+        let (lhs, rhs) = (
+            serde_json::to_value(run_args)?,
+            serde_json::to_value(&self.run_args)?,
+        );
+        if let (Some(lhs), Some(rhs)) = (lhs.as_object(), rhs.as_object()) {
+            fields.extend(
+                lhs.iter()
+                    .filter(|(arg, v)| !derived(arg, v) && rhs.get(*arg) != Some(*v))
+                    .map(|(arg, _)| format!("run_args.{}", arg)),
+            );
+        }
+        Ok(fields)
+    }
+
+    /// Describes the differences between the circuit of the manifest and that of `other`, one per
+    /// line: in the model, settings, ops, shapes and scales of each node, number of variables and
+    /// required logrows.
+    pub fn compare(&self, other: &ModelManifest) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut lines = vec![];
+        if self.model_hash != other.model_hash {
+            lines.push(format!(
+                "model hash: {} -> {}",
+                self.model_hash, other.model_hash
+            ));
+        }
+        if self.weights_hash != other.weights_hash {
+            lines.push(format!(
+                "weights hash: {:?} -> {:?}",
+                self.weights_hash, other.weights_hash
+            ));
+        }
+        let (lhs, rhs) = (
+            serde_json::to_value(&self.run_args)?,
+            serde_json::to_value(&other.run_args)?,
+        );
+        if let (Some(lhs), Some(rhs)) = (lhs.as_object(), rhs.as_object()) {
+            for (arg, l) in lhs {
+                let r = rhs.get(arg).cloned().unwrap_or_default();
+                if *l != r {
+                    lines.push(format!("run_args.{}: {} -> {}", arg, l, r));
+                }
+            }
+        }
+        if self.ops.len() != other.ops.len() {
+            lines.push(format!("nodes: {} -> {}", self.ops.len(), other.ops.len()));
+        }
+        for i in 0..self.ops.len().max(other.ops.len()) {
+            let describe = |m: &ModelManifest| {
+                (
+                    m.ops.get(i).cloned(),
+                    m.shapes.get(i).cloned(),
+                    m.scales.get(i).copied(),
+                )
+            };
+            let ((lop, lshape, lscale), (rop, rshape, rscale)) = (describe(self), describe(other));
+            match (lop, rop) {
+                (Some(l), Some(r)) => {
+                    if l != r {
+                        lines.push(format!("node {}: op {} -> {}", i, l, r));
+                    }
+                    if lshape != rshape {
+                        lines.push(format!(
+                            "node {} ({}): dims {:?} -> {:?}",
+                            i,
+                            r,
+                            lshape.unwrap_or_default(),
+                            rshape.unwrap_or_default()
+                        ));
+                    }
+                    if lscale != rscale {
+                        lines.push(format!(
+                            "node {} ({}): scale {} -> {}",
+                            i,
+                            r,
+                            lscale.unwrap_or_default(),
+                            rscale.unwrap_or_default()
+                        ));
+                    }
+                }
+                (Some(l), None) => lines.push(format!("node {}: {} removed", i, l)),
+                (None, Some(r)) => lines.push(format!("node {}: {} added", i, r)),
+                (None, None) => {}
+            }
+        }
+        if self.output_scales != other.output_scales {
+            lines.push(format!(
+                "output scales: {:?} -> {:?}",
+                self.output_scales, other.output_scales
+            ));
+        }
+        if self.var_len != other.var_len {
+            lines.push(format!(
+                "variables: {} -> {} (x{:.2})",
+                self.var_len,
+                other.var_len,
+                other.var_len as f64 / self.var_len.max(1) as f64
+            ));
+        }
+        if self.min_logrows != other.min_logrows {
+            lines.push(format!(
+                "required logrows: {} -> {}",
+                self.min_logrows, other.min_logrows
+            ));
+        }
+        Ok(lines)
+    }
+
+    /// Saves the manifest as json to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let serialized = serde_json::to_string_pretty(&self)?;
+        let mut file = File::create(path)?;
+        file.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads a json serialized manifest from `path`.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut data = String::new();
+        File::open(path)?.read_to_string(&mut data)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+}
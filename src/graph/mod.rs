@@ -1,10 +1,33 @@
 /// Helper functions
 pub mod utilities;
 pub use utilities::*;
+/// Reports of the accuracy of the quantized model relative to the float model.
+pub mod accuracy;
+/// Calibration of the scale, bits and logrows of a circuit over representative data.
+pub mod calibration;
+/// Estimates of the size and cost of the circuit of a model, without generating keys.
+pub mod estimate;
+/// Resolution of the tensors of Onnx models stored in external data files.
+pub mod external_data;
+/// Loading of GGUF quantized weights into the constants of a model.
+pub mod gguf;
+/// A front-end translating Keras HDF5 models into the Onnx graphs the models are loaded from.
+#[cfg(feature = "keras")]
+pub mod keras;
+/// A manifest of the model and settings a circuit was built from, which proofs commit to.
+pub mod manifest;
 /// Crate for defining a computational graph and building a ZK-circuit from it.
 pub mod model;
 /// Inner elements of a computational graph that represent a single operation / constraints.
 pub mod node;
+/// Builders for the Onnx graphs other model formats are translated into.
+#[cfg(any(feature = "tflite", feature = "keras"))]
+pub mod onnx_builder;
+/// Reports of the cost of each of the nodes of a model in the circuit.
+pub mod report;
+/// A front-end translating TFLite models into the Onnx graphs the models are loaded from.
+#[cfg(feature = "tflite")]
+pub mod tflite;
 /// Representations of a computational graph's variables.
 pub mod vars;
 
@@ -12,14 +35,18 @@ use crate::circuit::OpKind;
 use crate::tensor::TensorType;
 use crate::tensor::{Tensor, ValTensor};
 use anyhow::Result;
+pub use estimate::*;
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Layouter, SimpleFloorPlanner, Value},
     plonk::{Circuit, ConstraintSystem, Error as PlonkError},
 };
+use halo2curves::secp256k1::Fq;
 use log::{info, trace};
+pub use manifest::*;
 pub use model::*;
 pub use node::*;
+pub use report::*;
 use std::marker::PhantomData;
 use thiserror::Error;
 pub use vars::*;
@@ -66,6 +93,15 @@ pub enum GraphError {
     /// Error when attempting to load a model
     #[error("failed to load model")]
     ModelLoad,
+    /// The model is in a format which cannot be loaded
+    #[error("{0} models cannot be loaded, export them to onnx first")]
+    UnsupportedFormat(String),
+    /// Error when resolving the tensors of a model stored in external data files
+    #[error("failed to load external data: {0}")]
+    ExternalData(String),
+    /// The values of a node could overflow
+    #[error("the values of node {0} could overflow: {1}")]
+    Overflow(String, String),
 }
 
 /// Defines the circuit for a computational graph / model loaded from a `.onnx` file.
@@ -73,6 +109,11 @@ pub enum GraphError {
 pub struct ModelCircuit<F: FieldExt> {
     /// Vector of input tensors to the model / graph of computations.
     pub inputs: Vec<Tensor<i128>>,
+    /// The signature over the digest of the inputs, when these are signed.
+    pub signature: Option<(Fq, Fq)>,
+    /// The index and siblings of the leaves of the hashed inputs and params, when these are
+    /// proven to be leaves of Merkle trees.
+    pub merkle_paths: Vec<(usize, Vec<F>)>,
     /// Represents the Field we are using.
     pub _marker: PhantomData<F>,
 }
@@ -85,6 +126,7 @@ impl<F: FieldExt + TensorType> Circuit<F> for ModelCircuit<F> {
         self.clone()
     }
 
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
         let model = Model::from_arg().expect("model should load from args");
 
@@ -97,6 +139,7 @@ impl<F: FieldExt + TensorType> Circuit<F> for ModelCircuit<F> {
         let mut vars = ModelVars::new(
             cs,
             model.run_args.logrows as usize,
+            model.run_args.num_cols,
             var_len,
             instance_shapes.clone(),
             model.visibility.clone(),
@@ -113,6 +156,7 @@ impl<F: FieldExt + TensorType> Circuit<F> for ModelCircuit<F> {
         model.configure(cs, &mut vars).unwrap()
     }
 
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     fn synthesize(
         &self,
         config: Self::Config,
@@ -124,10 +168,21 @@ impl<F: FieldExt + TensorType> Circuit<F> for ModelCircuit<F> {
             .iter()
             .map(|i| ValTensor::from(<Tensor<i128> as Into<Tensor<Value<F>>>>::into(i.clone())))
             .collect::<Vec<ValTensor<F>>>();
+        let signature = match self.signature {
+            Some(signature) => Value::known(signature),
+            None => Value::unknown(),
+        };
         trace!("Setting output in synthesize");
         config
             .model
-            .layout(config.clone(), &mut layouter, &inputs, &config.vars)
+            .layout(
+                config.clone(),
+                &mut layouter,
+                &inputs,
+                signature,
+                &self.merkle_paths,
+                &config.vars,
+            )
             .unwrap();
 
         Ok(())
@@ -1,16 +1,23 @@
 use super::node::*;
 use super::vars::*;
 use super::GraphError;
+use crate::circuit::ecdsa::{self, EcdsaConfig};
+use crate::circuit::merkle::MerkleConfig;
+use crate::circuit::poseidon::{PoseidonConfig, PoseidonSpec};
+use crate::circuit::sha256::Sha256Config;
 use crate::circuit::BaseConfig as PolyConfig;
 use crate::circuit::CheckMode;
+use crate::circuit::ConvLowering;
 use crate::circuit::LookupOp;
 use crate::circuit::Op as PolyOp;
 use crate::circuit::OpKind;
-use crate::commands::RunArgs;
 use crate::commands::{Cli, Commands};
-use crate::graph::scale_to_multiplier;
+use crate::commands::{HashFunction, LayerScale, RunArgs, ThresholdClaim};
+use crate::fieldutils::i128_to_felt;
+use crate::graph::{scale_to_multiplier, vector_to_quantized};
+use crate::tensor::ops::Reduction;
 use crate::tensor::TensorType;
-use crate::tensor::{Tensor, ValTensor, VarTensor};
+use crate::tensor::{Tensor, ValTensor, ValType, VarTensor};
 use anyhow::Context;
 //use clap::Parser;
 use anyhow::Error as AnyError;
@@ -18,22 +25,32 @@ use core::panic;
 use halo2_proofs::circuit::Region;
 use halo2_proofs::{
     arithmetic::FieldExt,
-    circuit::{Layouter, Value},
+    circuit::{AssignedCell, Layouter, Value},
     plonk::ConstraintSystem,
 };
+use halo2curves::secp256k1::Fq;
 use itertools::Itertools;
 use log::error;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
+use rayon::prelude::*;
+use serde::Serialize;
 use std::cell::RefCell;
 use std::cmp::max;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use tabled::Table;
 use tract_onnx;
-use tract_onnx::prelude::{Framework, Graph, InferenceFact, Node as OnnxNode, OutletId};
-use tract_onnx::tract_hir::internal::InferenceOp;
+use tract_onnx::pb::{
+    attribute_proto::AttributeType, tensor_proto::DataType, AttributeProto, ModelProto, NodeProto,
+    TensorProto, ValueInfoProto,
+};
+use tract_onnx::prelude::{
+    Arc, DatumType, Framework, Graph, InferenceFact, Node as OnnxNode, OutletId,
+    Tensor as TractTensor,
+};
+use tract_onnx::tract_hir::{infer::Factoid, internal::InferenceOp, tract_core::ops::konst::Const};
 /// Mode we're using the model in.
 #[derive(Clone, Debug)]
 pub enum Mode {
@@ -61,6 +78,23 @@ pub struct ModelConfig<F: FieldExt + TensorType> {
     pub packed_outputs: Vec<Rc<RefCell<PolyConfig<F>>>>,
     /// A wrapper for holding all columns that will be assigned to by the model
     pub vars: ModelVars<F>,
+    /// (optional) Poseidon chip committing to the params, inputs or outputs of the model which
+    /// are hashed
+    pub poseidon: Option<PoseidonConfig<F>>,
+    /// (optional) SHA-256 chip committing to the inputs or outputs of the model which are hashed,
+    /// when these are hashed with sha256
+    pub sha256: Option<Sha256Config<F>>,
+    /// (optional) Merkle chip proving the digests of the hashed inputs and params to be leaves of
+    /// Merkle trees
+    pub merkle: Option<MerkleConfig<F>>,
+    /// (optional) ECDSA chip verifying the signature over the inputs of the model, when these
+    /// are signed
+    pub ecdsa: Option<EcdsaConfig<F>>,
+    /// The params of the model, by node index, once assigned by the Poseidon chip
+    pub params: BTreeMap<usize, ValTensor<F>>,
+    /// The hashed inputs of the model, by index in the inputs of the circuit, once assigned by
+    /// the Poseidon chip
+    pub inputs: BTreeMap<usize, ValTensor<F>>,
 }
 
 /// A struct for loading from an Onnx file and converting a computational graph to a circuit.
@@ -78,6 +112,37 @@ pub struct Model {
     pub visibility: VarVisibility,
 }
 
+/// The quantized output of a node of the model for a single sample, as dumped by a forward pass.
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeActivation {
+    /// The node's unique identifier
+    pub idx: usize,
+    /// The name of the Onnx node the node was loaded from (if any)
+    pub name: String,
+    /// The op of the node
+    pub op: String,
+    /// The index of the sample in the batch
+    pub sample: usize,
+    /// The fixed point scale of the node's output
+    pub scale: u32,
+    /// The dims of the node's output
+    pub dims: Vec<usize>,
+    /// The quantized output of the node
+    pub quantized: Vec<i128>,
+    /// The output of the node, dequantized with its scale
+    pub dequantized: Vec<f32>,
+}
+
+/// Marks the nodes inserted where the scale of the model's layers changes, and precedes the scale
+/// the node rebases its input to.
+const REBASE_MARKER: &str = "/rebase_to_scale_";
+/// Suffixes the outputs which are replaced by the index of their largest entry.
+const ARGMAX_SUFFIX: &str = "/argmax";
+/// Suffixes the outputs which are replaced by a threshold claim on one of their entries.
+const THRESHOLD_SUFFIX: &str = "/at_least_threshold";
+/// The largest number of bits of the lookup tables which are chosen automatically.
+const MAX_LOOKUP_BITS: usize = 64;
+
 impl Model {
     /// Creates an `Model` from a specified path to an Onnx file.
     /// # Arguments
@@ -86,86 +151,1488 @@ impl Model {
     /// * `run_args` - [RunArgs]
     /// * `mode` - The [Mode] we're using the model in.
     /// * `visibility` - Which inputs to the model are public and private (params, inputs, outputs) using [VarVisibility].
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     pub fn new(
         path: impl AsRef<Path>,
         run_args: RunArgs,
         mode: Mode,
         visibility: VarVisibility,
     ) -> Result<Self, Box<dyn Error>> {
-        let model = tract_onnx::onnx()
-            .model_for_path(path)
-            .map_err(|_| GraphError::ModelLoad)?;
+        let model = Self::load_onnx(path, &run_args)?;
         info!("visibility: {}", visibility);
 
-        let mut nodes = BTreeMap::<usize, Node>::new();
-        for (i, n) in model.nodes.iter().enumerate() {
-            let n = Node::new(n.clone(), &mut nodes, run_args.scale, i)?;
-            nodes.insert(i, n);
-        }
-        let om = Model {
-            model: model.clone(),
+        let mut run_args = run_args;
+        let nodes = Self::load_nodes(&model, &mut run_args)?;
+        let mut om = Model {
+            model,
             run_args,
             nodes,
             mode,
             visibility,
         };
+        if om.run_args.pack_base == 0 {
+            om.run_args.pack_base = om.largest_safe_pack_base();
+            info!("packing outputs with base {}", om.run_args.pack_base);
+        }
+        if let Some(epsilon) = om.run_args.output_epsilon {
+            om.run_args.tolerance = om.epsilon_tolerance(epsilon);
+            info!(
+                "checking outputs with a tolerance of {} for an epsilon of {}",
+                om.run_args.tolerance, epsilon
+            );
+        }
 
         debug!("{}", Table::new(om.nodes.iter()).to_string());
 
+        om.check_visibility()?;
         Ok(om)
     }
 
+    /// The largest base the outputs can be packed with, such that the packed value of the longest
+    /// output (with room for its last value) fits in an i128. Falls back to 1 (no packing) if the
+    /// outputs are too long to be packed at all.
+    pub fn largest_safe_pack_base(&self) -> u32 {
+        let len = self
+            .output_shapes()
+            .iter()
+            .map(|s| s.iter().product::<usize>())
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let exponent = (len as u32).saturating_mul(self.run_args.scale + 1);
+        let fits = |base: u32| {
+            (base as i128)
+                .checked_pow(exponent)
+                .map_or(false, |max| max <= i128::MAX / 2)
+        };
+        let mut base = 2f64
+            .powf(126.0 / exponent as f64)
+            .floor()
+            .min(u32::MAX as f64) as u32;
+        while base > 1 && !fits(base) {
+            base -= 1;
+        }
+        if base < 2 {
+            warn!(
+                "the outputs are too long to be packed at scale {}",
+                self.run_args.scale
+            );
+            return 1;
+        }
+        base
+    }
+
+    /// The tolerance, in fixed point units, which keeps each of the public outputs within `epsilon`
+    /// of its claimed value. The tolerance is shared by all outputs, so it is that of the output
+    /// with the smallest scale.
+    pub fn epsilon_tolerance(&self, epsilon: f32) -> usize {
+        let tolerance = self
+            .get_output_scales()
+            .into_iter()
+            .zip(self.output_visibility())
+            .filter(|(_, visibility)| visibility.is_public())
+            .map(|(scale, _)| (epsilon.abs() * scale_to_multiplier(scale)).floor() as usize)
+            .min()
+            .unwrap_or(0);
+        if tolerance == 0 {
+            warn!(
+                "an epsilon of {} is below the resolution of the outputs, which are checked exactly",
+                epsilon
+            );
+        }
+        tolerance
+    }
+
+    /// Checks that the visibility overrides refer to the model's inputs and outputs, and that some
+    /// of the circuit's variables are public.
+    fn check_visibility(&self) -> Result<(), Box<dyn Error>> {
+        let (inputs, outputs) = (self.input_names(), self.output_names());
+        for (name, visibility) in self.visibility.tensors.iter() {
+            if !inputs.contains(name) && !outputs.contains(name) {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "the model has no input or output named {}",
+                    name
+                ))));
+            }
+            if *visibility == Visibility::Fixed {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "fixed visibility is only supported for params, got it for {}",
+                    name
+                ))));
+            }
+        }
+        if let Some(signer) = &self.run_args.signer {
+            if ecdsa::parse_public_key(signer).is_none() {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "the signer {} is not an uncompressed secp256k1 public key",
+                    signer
+                ))));
+            }
+            if self.input_visibility().iter().any(|v| v.is_public()) {
+                return Err(Box::new(GraphError::MisformedParams(
+                    "signed inputs can't be public".to_string(),
+                )));
+            }
+        }
+        if self.run_args.merkle_depth > 0 && self.run_args.hash_function == HashFunction::Sha256 {
+            return Err(Box::new(GraphError::MisformedParams(
+                "merkle trees are only supported over poseidon digests".to_string(),
+            )));
+        }
+        let params = &self.visibility.params;
+        if self.instance_shapes().is_empty() && !params.is_public() && !params.is_fixed() {
+            return Err(Box::new(GraphError::Visibility));
+        }
+        // the threshold of a claim is a param of a comparison, which is free advice unless fixed
+        if self.run_args.threshold_claim.is_some() && !params.is_fixed() {
+            return Err(Box::new(GraphError::MisformedParams(
+                "threshold claims require fixed params".to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Loads an Onnx file and concretizes the shapes of its inputs, which are then propagated
+    /// through the graph. Input dims are taken from the `input_shapes` of the [RunArgs] where
+    /// given, and symbolic dims (such as a batch size) otherwise default to 1. The `input_nodes`
+    /// and `output_nodes` of the [RunArgs] then cut the graph down to the selected subgraph.
+    /// # Arguments
+    ///
+    /// * `path` - A path to an Onnx file, or to a .tflite (.h5) file with the `tflite` (`keras`) feature.
+    /// * `run_args` - [RunArgs]
+    pub fn load_onnx(
+        path: impl AsRef<Path>,
+        run_args: &RunArgs,
+    ) -> Result<Graph<InferenceFact, Box<dyn InferenceOp>>, Box<dyn Error>> {
+        if run_args.batch_size == 0 {
+            return Err(Box::new(GraphError::MisformedParams(
+                "the batch size should be at least 1".to_string(),
+            )));
+        }
+        let onnx = tract_onnx::onnx();
+        let mut proto = match path.as_ref().extension() {
+            #[cfg(feature = "tflite")]
+            Some(ext) if ext == "tflite" => super::tflite::to_onnx(path.as_ref())?,
+            #[cfg(feature = "keras")]
+            Some(ext) if ext == "h5" || ext == "hdf5" => super::keras::to_onnx(path.as_ref())?,
+            // TorchScript archives hold python code run by the libtorch interpreter, which can't
+            // be mapped onto the graph without reimplementing it
+            Some(ext) if ext == "pt" || ext == "pth" => {
+                return Err(Box::new(GraphError::UnsupportedFormat(
+                    "TorchScript".to_string(),
+                )))
+            }
+            _ => {
+                let mut proto = onnx
+                    .proto_model_for_path(path.as_ref())
+                    .map_err(|_| GraphError::ModelLoad)?;
+                // weights stored in external data files are resolved next to the model
+                let model_dir = match path.as_ref().parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                super::external_data::resolve_external_data(
+                    &mut proto,
+                    &model_dir,
+                    run_args.max_external_data_mb * 1024 * 1024,
+                )?;
+                proto
+            }
+        };
+        Self::strip_nodes(&mut proto, &run_args.strip_nodes, &run_args.strip_ops)?;
+        Self::rebase_layer_scales(&mut proto, run_args)?;
+        if run_args.argmax_outputs || run_args.threshold_claim.is_some() {
+            // outputs picked among the intermediate nodes would bypass the claims
+            if !run_args.output_nodes.is_empty() {
+                return Err(Box::new(GraphError::MisformedParams(
+                    "claims on the outputs can't be made along with output nodes".to_string(),
+                )));
+            }
+        }
+        if run_args.argmax_outputs {
+            Self::argmax_outputs(&mut proto);
+        }
+        if let Some(claim) = &run_args.threshold_claim {
+            Self::threshold_outputs(&mut proto, claim);
+        }
+        let mut model = onnx
+            .model_for_proto_model(&proto)
+            .map_err(|_| GraphError::ModelLoad)?;
+        // the weights now live in the graph, so the protobuf's copy of them is released early
+        drop(proto);
+
+        if let Some(weights) = &run_args.gguf_weights {
+            super::gguf::substitute_weights(&mut model, weights, run_args.scale)?;
+        }
+        if let Some(percentile) = run_args.weight_clip_percentile {
+            Self::clip_weights(&mut model, percentile)?;
+        }
+
+        for shape in run_args.input_shapes.iter() {
+            if !model
+                .inputs
+                .iter()
+                .any(|o| model.nodes[o.node].name == shape.name)
+            {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "the model has no input named {}",
+                    shape.name
+                ))));
+            }
+        }
+
+        for (i, outlet) in model.inputs.clone().iter().enumerate() {
+            let input = &model.nodes[outlet.node];
+            let fact = &input.outputs[outlet.slot].fact;
+            let rank = fact.shape.rank().concretize();
+            let dims = match run_args.input_shapes.iter().find(|s| s.name == input.name) {
+                Some(shape) => {
+                    if rank.map_or(false, |r| r as usize != shape.dims.len()) {
+                        return Err(Box::new(GraphError::MisformedParams(format!(
+                            "input {} has rank {:?}, got shape {}",
+                            input.name, rank, shape
+                        ))));
+                    }
+                    shape.dims.clone()
+                }
+                // inputs of unknown rank are left to tract
+                None if rank.is_none() => continue,
+                None => fact
+                    .shape
+                    .dims()
+                    .map(|d| match d.concretize().map(|d| d.to_i64()) {
+                        Some(Ok(d)) if d > 0 => d as usize,
+                        _ => 1,
+                    })
+                    .collect_vec(),
+            };
+            let datum_type = fact.datum_type.concretize().unwrap_or(DatumType::F32);
+            debug!("input {} has shape {:?}", input.name, dims);
+            model.set_input_fact(i, InferenceFact::dt_shape(datum_type, dims))?;
+        }
+
+        if let Err(e) = model.analyse(false) {
+            warn!("failed to propagate input shapes through the model: {}", e);
+        }
+
+        if !run_args.input_nodes.is_empty() {
+            let mut inputs = vec![];
+            for name in run_args.input_nodes.iter() {
+                let outlet = Self::find_outlet(&model, name)?;
+                let fact = model.outlet_fact(outlet)?.clone();
+                if !fact.shape.is_concrete() {
+                    return Err(Box::new(GraphError::MisformedParams(format!(
+                        "the shape of {} is unknown, set the shapes of the model's inputs",
+                        name
+                    ))));
+                }
+                // the tensor is now fed by a new source, such that the nodes it depends on are
+                // dropped from the graph
+                let source = model.add_source(name.clone(), fact)?;
+                for inlet in model.outlet_successors(outlet).to_vec() {
+                    model.add_edge(source, inlet)?;
+                }
+                if let Some(output) = model.outputs.iter_mut().find(|o| **o == outlet) {
+                    *output = source;
+                }
+                inputs.push(source);
+            }
+            model.inputs = inputs;
+        }
+
+        if !run_args.output_nodes.is_empty() {
+            model.outputs = run_args
+                .output_nodes
+                .iter()
+                .map(|name| Self::find_outlet(&model, name))
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        Ok(model)
+    }
+
+    /// Bypasses the nodes of the Onnx graph called one of `names` or of one of the `op_types`,
+    /// replacing each with an identity of its first input, such that eg. preprocessing or
+    /// postprocessing nodes can be left out of the circuit without re-exporting the model.
+    fn strip_nodes(
+        proto: &mut ModelProto,
+        names: &[String],
+        op_types: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        if names.is_empty() && op_types.is_empty() {
+            return Ok(());
+        }
+        let graph = match proto.graph.as_mut() {
+            Some(g) => g,
+            None => return Ok(()),
+        };
+        let consumed: BTreeSet<String> = graph
+            .node
+            .iter()
+            .flat_map(|n| n.input.iter().cloned())
+            .chain(graph.output.iter().map(|o| o.name.clone()))
+            .collect();
+        for name in names {
+            if !graph.node.iter().any(|n| &n.name == name) {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "the model has no node named {} to strip",
+                    name
+                ))));
+            }
+        }
+        for node in graph.node.iter_mut() {
+            if !names.contains(&node.name) && !op_types.contains(&node.op_type) {
+                continue;
+            }
+            let input = match node.input.iter().find(|i| !i.is_empty()) {
+                Some(i) => i.clone(),
+                None => {
+                    return Err(Box::new(GraphError::MisformedParams(format!(
+                        "node {} has no input to pass through in its place",
+                        node.name
+                    ))))
+                }
+            };
+            if node.output.iter().skip(1).any(|o| consumed.contains(o)) {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "node {} has several outputs in use, and can't be stripped",
+                    node.name
+                ))));
+            }
+            info!("stripping node {} ({})", node.name, node.op_type);
+            node.op_type = "Identity".to_string();
+            node.domain = String::new();
+            node.input = vec![input];
+            node.output.truncate(1);
+            node.attribute.clear();
+        }
+        Ok(())
+    }
+
+    /// Clips each of the float weights of the model to the `percentile`-th percentile of its
+    /// magnitudes, such that rare outliers don't inflate the range of the activations.
+    fn clip_weights(
+        model: &mut Graph<InferenceFact, Box<dyn InferenceOp>>,
+        percentile: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        for node in model.nodes.iter_mut() {
+            let konst: &Const = match node.op.as_any().downcast_ref() {
+                Some(c) => c,
+                None => continue,
+            };
+            if konst.0.datum_type() != DatumType::F32 || konst.0.len() < 2 {
+                continue;
+            }
+            let values = konst.0.as_slice::<f32>()?;
+            let mut magnitudes = values.iter().map(|v| v.abs()).collect_vec();
+            magnitudes.sort_by(f32::total_cmp);
+            let bound = super::percentile(&magnitudes, percentile);
+            let clipped = magnitudes.iter().filter(|v| **v > bound).count();
+            if clipped == 0 {
+                continue;
+            }
+            debug!(
+                "clipping {} of the weights of {} to {}",
+                clipped, node.name, bound
+            );
+            let values = values.iter().map(|v| v.clamp(-bound, bound)).collect_vec();
+            let tensor = TractTensor::from_shape(konst.0.shape(), &values)?;
+            node.outputs[0].fact = InferenceFact::from(tensor.clone());
+            node.op = Box::new(Const(Arc::new(tensor)));
+        }
+        Ok(())
+    }
+
+    /// Inserts a rebase node (an identity to the float model) on each edge of the Onnx graph along
+    /// which the scale of the layers set in `layer_scales` changes. Each node takes the scale set
+    /// for it or its lookup op, or else the largest scale of its (non constant) inputs, and inputs
+    /// at another scale are rebased to the node's scale unless a lookup rescales them.
+    fn rebase_layer_scales(
+        proto: &mut ModelProto,
+        run_args: &RunArgs,
+    ) -> Result<(), Box<dyn Error>> {
+        let (layer_scales, scale) = (&run_args.layer_scales, run_args.scale);
+        if layer_scales.is_empty() && run_args.lookup_scales.is_empty() {
+            return Ok(());
+        }
+        let graph = match proto.graph.as_mut() {
+            Some(g) => g,
+            None => return Ok(()),
+        };
+        for layer in layer_scales {
+            if !graph.node.iter().any(|n| n.name == layer.name) {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "the model has no node named {} to set the scale of",
+                    layer.name
+                ))));
+            }
+        }
+        // initializers and constants are quantized at the scale of the node consuming them
+        let mut tensor_scales: BTreeMap<String, u32> = graph
+            .input
+            .iter()
+            .filter(|i| !graph.initializer.iter().any(|t| t.name == i.name))
+            .map(|i| (i.name.clone(), scale))
+            .collect();
+        let mut rebased = BTreeSet::new();
+        let mut nodes = Vec::with_capacity(graph.node.len());
+        for mut node in std::mem::take(&mut graph.node) {
+            let lookup_scale = Self::lookup_scale(&run_args.lookup_scales, &node.op_type);
+            let node_scale = match layer_scales.iter().find(|l| l.name == node.name) {
+                Some(layer) => layer.scale,
+                None => lookup_scale.unwrap_or_else(|| {
+                    node.input
+                        .iter()
+                        .filter_map(|i| tensor_scales.get(i))
+                        .max()
+                        .copied()
+                        .unwrap_or(scale)
+                }),
+            };
+            for input in node.input.iter_mut() {
+                // lookups map their inputs to their output scale themselves
+                if lookup_scale == Some(node_scale) {
+                    continue;
+                }
+                match tensor_scales.get(input) {
+                    Some(s) if *s != node_scale => {}
+                    _ => continue,
+                }
+                let name = format!("{}{}{}", input, REBASE_MARKER, node_scale);
+                if rebased.insert(name.clone()) {
+                    debug!("rebasing {} to scale {}", input, node_scale);
+                    nodes.push(NodeProto {
+                        input: vec![input.clone()],
+                        output: vec![name.clone()],
+                        name: name.clone(),
+                        op_type: "Identity".to_string(),
+                        ..Default::default()
+                    });
+                }
+                *input = name;
+            }
+            if node.op_type != "Constant" {
+                for output in node.output.iter() {
+                    tensor_scales.insert(output.clone(), node_scale);
+                }
+            }
+            nodes.push(node);
+        }
+        graph.node = nodes;
+        Ok(())
+    }
+
+    /// The output scale set for the nonlinearity called `op_name` (eg. Sigmoid), if any. Only
+    /// the lookups whose tables rescale their output can take a scale of their own.
+    fn lookup_scale(lookup_scales: &[LayerScale], op_name: &str) -> Option<u32> {
+        if lookup_scales.is_empty() {
+            return None;
+        }
+        match OpKind::new(op_name) {
+            OpKind::Lookup(
+                op @ (LookupOp::Sigmoid { .. }
+                | LookupOp::Tanh { .. }
+                | LookupOp::Exp { .. }
+                | LookupOp::Ln { .. }
+                | LookupOp::Sqrt { .. }
+                | LookupOp::Recip { .. }
+                | LookupOp::Rsqrt { .. }
+                | LookupOp::Erf { .. }),
+            ) => lookup_scales
+                .iter()
+                .find(|l| l.name.eq_ignore_ascii_case(op.as_str()))
+                .map(|l| l.scale),
+            _ => None,
+        }
+    }
+
+    /// Replaces each output of the Onnx graph with the index of its largest entry along the last
+    /// axis, such that the scores become private intermediate values and only the decision is an
+    /// output of the model.
+    fn argmax_outputs(proto: &mut ModelProto) {
+        let graph = match proto.graph.as_mut() {
+            Some(g) => g,
+            None => return,
+        };
+        let int_attr = |name: &str, i: i64| AttributeProto {
+            name: name.to_string(),
+            r#type: AttributeType::Int as i32,
+            i,
+            ..Default::default()
+        };
+        for output in graph.output.iter_mut() {
+            let name = format!("{}{}", output.name, ARGMAX_SUFFIX);
+            debug!("replacing output {} with its argmax", output.name);
+            graph.node.push(NodeProto {
+                input: vec![output.name.clone()],
+                output: vec![name.clone()],
+                name: name.clone(),
+                op_type: "ArgMax".to_string(),
+                attribute: vec![int_attr("axis", -1), int_attr("keepdims", 0)],
+                ..Default::default()
+            });
+            *output = ValueInfoProto {
+                name,
+                ..Default::default()
+            };
+        }
+    }
+
+    /// Replaces each output of the Onnx graph with the boolean claim that its entry at the claim's
+    /// index (in the flattened output) is at least the claim's threshold, such that the value
+    /// becomes a private intermediate value and only the claim is an output of the model.
+    fn threshold_outputs(proto: &mut ModelProto, claim: &ThresholdClaim) {
+        let graph = match proto.graph.as_mut() {
+            Some(g) => g,
+            None => return,
+        };
+        let tensor = |name: &str, data_type: DataType| TensorProto {
+            name: name.to_string(),
+            dims: vec![1],
+            data_type: data_type as i32,
+            ..Default::default()
+        };
+        let node = |op_type: &str, input: Vec<String>, output: &str| NodeProto {
+            input,
+            output: vec![output.to_string()],
+            name: output.to_string(),
+            op_type: op_type.to_string(),
+            ..Default::default()
+        };
+        for output in graph.output.iter_mut() {
+            let prefix = format!("{}{}", output.name, THRESHOLD_SUFFIX);
+            debug!("replacing output {} with the claim {}", output.name, claim);
+            let (shape, index, threshold) = (
+                format!("{}/shape", prefix),
+                format!("{}/index", prefix),
+                format!("{}/threshold", prefix),
+            );
+            graph.initializer.extend([
+                TensorProto {
+                    int64_data: vec![-1],
+                    ..tensor(&shape, DataType::Int64)
+                },
+                TensorProto {
+                    int64_data: vec![claim.index as i64],
+                    ..tensor(&index, DataType::Int64)
+                },
+                TensorProto {
+                    float_data: vec![claim.threshold],
+                    ..tensor(&threshold, DataType::Float)
+                },
+            ]);
+            let (flat, entry) = (format!("{}/flat", prefix), format!("{}/entry", prefix));
+            graph.node.extend([
+                node("Reshape", vec![output.name.clone(), shape], &flat),
+                node("Gather", vec![flat, index], &entry),
+                node("GreaterOrEqual", vec![entry, threshold], &prefix),
+            ]);
+            *output = ValueInfoProto {
+                name: prefix,
+                ..Default::default()
+            };
+        }
+    }
+
+    /// The scale each node of the graph is loaded at. Nodes take the scale set for their layer in
+    /// the [RunArgs], or else the largest scale of their inputs, rebase nodes take the scale they
+    /// rebase to, and constants take the scale of the node consuming them.
+    fn node_scales(
+        model: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        order: &[usize],
+        run_args: &RunArgs,
+    ) -> BTreeMap<usize, u32> {
+        let is_const = |i: &&usize| model.nodes[**i].op.name() == "Const";
+        let mut scales = BTreeMap::new();
+        for i in order.iter().filter(|i| !is_const(i)) {
+            let node = &model.nodes[*i];
+            let rebase_scale = node
+                .name
+                .rsplit_once(REBASE_MARKER)
+                .and_then(|(_, s)| s.parse().ok());
+            let lookup_scale = Self::lookup_scale(&run_args.lookup_scales, node.op.name().as_ref());
+            let scale = match run_args.layer_scales.iter().find(|l| l.name == node.name) {
+                Some(layer) => layer.scale,
+                None => rebase_scale.or(lookup_scale).unwrap_or_else(|| {
+                    node.inputs
+                        .iter()
+                        .filter_map(|o| scales.get(&o.node))
+                        .max()
+                        .copied()
+                        .unwrap_or(run_args.scale)
+                }),
+            };
+            scales.insert(*i, scale);
+        }
+        for i in order.iter().filter(is_const) {
+            let scale = model.nodes[*i]
+                .outputs
+                .iter()
+                .flat_map(|o| o.successors.iter())
+                .find_map(|s| scales.get(&s.node))
+                .copied()
+                .unwrap_or(run_args.scale);
+            scales.insert(*i, scale);
+        }
+        scales
+    }
+
+    /// Finds the tensor named `name` in the graph, either by its label or as the first output of
+    /// the node called `name`.
+    fn find_outlet(
+        model: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        name: &str,
+    ) -> Result<OutletId, Box<dyn Error>> {
+        model
+            .outlet_labels
+            .iter()
+            .find(|(_, label)| label.as_str() == name)
+            .map(|(o, _)| *o)
+            .or_else(|| {
+                model
+                    .nodes
+                    .iter()
+                    .find(|n| n.name == name)
+                    .map(|n| OutletId::new(n.id, 0))
+            })
+            .ok_or_else(|| {
+                Box::new(GraphError::MisformedParams(format!(
+                    "the model has no node or tensor named {}",
+                    name
+                ))) as Box<dyn Error>
+            })
+    }
+
+    /// Converts the nodes of the graph which the model's inputs and outputs depend on, and
+    /// optimizes them ahead of configuration: constant subexpressions are folded, no-op nodes
+    /// bypassed, common sequences of ops fused (unless disabled in the [RunArgs]), and unused nodes
+    /// removed. Nodes are visited in evaluation order, as sources added when cutting the graph come
+    /// after their consumers. Bits of 0 in the [RunArgs] are resolved to the smallest safe ones.
+    fn load_nodes(
+        model: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        run_args: &mut RunArgs,
+    ) -> Result<NodeGraph, Box<dyn Error>> {
+        let order = model
+            .inputs
+            .iter()
+            .map(|o| o.node)
+            .chain(model.eval_order()?)
+            .unique()
+            .collect_vec();
+        let scales = Self::node_scales(model, &order, run_args);
+        let mut nodes = BTreeMap::<usize, Node>::new();
+        for i in order {
+            let node = &model.nodes[i];
+            let mut n = match (node.name.contains(REBASE_MARKER), node.inputs.first()) {
+                (true, Some(input)) => {
+                    let input_node = nodes
+                        .get(&input.node)
+                        .ok_or(GraphError::MissingNode(input.node))?;
+                    Node::rebase(i, *input, input_node, scales[&i])
+                }
+                _ => Node::new(node.clone(), &mut nodes, scales[&i], i)?,
+            };
+            // the bounds of later nodes are inferred from those of the inputs
+            if let (true, Some(bound)) = (n.opkind.is_input(), run_args.input_bound) {
+                n.output_max = bound.abs() * scale_to_multiplier(n.out_scale);
+            }
+            // folding as nodes are converted lets later nodes use the folded values as params
+            if !model.outputs.iter().any(|o| o.node == i) {
+                n = Self::fold_constant(n, &nodes);
+            }
+            nodes.insert(i, n);
+        }
+        Self::bypass_nodes(&mut nodes, &model.outputs);
+        if !run_args.no_fusion {
+            Self::fuse_nodes(&mut nodes, &model.outputs, run_args)?;
+        }
+        Self::remove_unused_nodes(&mut nodes, &model.outputs);
+        if run_args.conv_lowering != ConvLowering::Direct {
+            Self::lower_convs(&mut nodes, run_args.conv_lowering);
+        }
+        if run_args.limb_lookups {
+            Self::limb_lookups(&mut nodes);
+        }
+        if run_args.bits == 0 {
+            run_args.bits = Self::smallest_safe_bits(model, &nodes, run_args)?;
+            info!("using lookup tables of {} bits", run_args.bits);
+        }
+        if run_args.asymmetric_lookups {
+            Self::center_lookup_tables(&mut nodes, run_args.bits);
+        }
+        if run_args.input_bound.is_some() {
+            Self::check_overflow(model, &nodes, run_args.bits)?;
+        }
+        Ok(nodes)
+    }
+
+    /// The smallest number of bits of the lookup tables such that the inputs of every lookup fit
+    /// in its table, as inferred from the bound of the inputs. Fails with the overflow of the
+    /// largest tables if even these don't fit.
+    fn smallest_safe_bits(
+        model: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        nodes: &NodeGraph,
+        run_args: &RunArgs,
+    ) -> Result<usize, Box<dyn Error>> {
+        if run_args.input_bound.is_none() {
+            return Err(Box::new(GraphError::MisformedParams(
+                "choosing the bits of the lookup tables requires an input bound".to_string(),
+            )));
+        }
+        let mut overflow = None;
+        for bits in 1..=MAX_LOOKUP_BITS {
+            let mut nodes = nodes.clone();
+            if run_args.asymmetric_lookups {
+                Self::center_lookup_tables(&mut nodes, bits);
+            }
+            match Self::check_overflow(model, &nodes, bits) {
+                Ok(()) => return Ok(bits),
+                Err(e) => overflow = Some(e),
+            }
+        }
+        Err(Box::new(overflow.unwrap()))
+    }
+
+    /// Checks that the largest magnitude of the values of each node, as inferred from the bound of
+    /// the inputs, fits in an i128, and that the inputs of each element-wise lookup fit in its
+    /// table, naming the first node which could overflow.
+    fn check_overflow(
+        model: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        nodes: &NodeGraph,
+        bits: usize,
+    ) -> Result<(), GraphError> {
+        let half_table = (1i128 << (bits - 1)) as f32;
+        for (i, node) in nodes.iter() {
+            let name = || model.nodes[*i].name.clone();
+            if !node.output_max.is_finite() || node.output_max >= i128::MAX as f32 {
+                return Err(GraphError::Overflow(
+                    name(),
+                    format!(
+                        "its values of up to {} don't fit in an i128",
+                        node.output_max
+                    ),
+                ));
+            }
+            // average pools look up the sums of their windows (or their quotient when divided
+            // with a remainder), which are larger than their inputs
+            let avg_pool = match &node.opkind {
+                OpKind::Lookup(LookupOp::AvgPool2D {
+                    pool_dims,
+                    remainder,
+                    ..
+                }) => Some((pool_dims.0 * pool_dims.1, *remainder)),
+                OpKind::Lookup(LookupOp::AvgPool3D {
+                    pool_dims,
+                    remainder,
+                    ..
+                }) => Some((pool_dims.0 * pool_dims.1 * pool_dims.2, *remainder)),
+                _ => None,
+            };
+            if let Some((window, remainder)) = avg_pool {
+                let input_max = node
+                    .inputs
+                    .first()
+                    .and_then(|o| nodes.get(&o.node))
+                    .map_or(0.0, |n| n.output_max);
+                let max = match LookupOp::division(window, remainder) {
+                    LookupOp::DivRem { denom } => input_max * window as f32 / denom as f32,
+                    _ => input_max * window as f32,
+                };
+                if max >= half_table {
+                    return Err(GraphError::Overflow(
+                        name(),
+                        format!(
+                            "its window sums of magnitude up to {} don't fit in its lookup table of {} bits, covering [{}, {})",
+                            max, bits, -half_table, half_table
+                        ),
+                    ));
+                }
+                continue;
+            }
+            // limbed lookups and divisions with a remainder look up the quotient of their inputs
+            let (op, divisor) = match &node.opkind {
+                OpKind::Lookup(LookupOp::Limbed { inner, shift }) => {
+                    (inner.as_ref(), scale_to_multiplier(*shift as u32))
+                }
+                OpKind::Lookup(op @ LookupOp::DivRem { denom }) => (op, *denom as f32),
+                OpKind::Lookup(op) => (op, 1.0),
+                // fused relus look up the accumulated outputs of the conv, which aren't a node
+                OpKind::Poly(PolyOp::ConvReLU { scale, .. }) => {
+                    let max = node.output_max * *scale as f32;
+                    if max >= half_table {
+                        return Err(GraphError::Overflow(
+                            name(),
+                            format!(
+                                "its conv outputs of magnitude up to {} don't fit in its lookup table of {} bits, covering [{}, {})",
+                                max, bits, -half_table, half_table
+                            ),
+                        ));
+                    }
+                    continue;
+                }
+                // signs check the magnitudes of their inputs against the relu table
+                OpKind::Poly(PolyOp::Sign | PolyOp::Abs) => {
+                    let max = node
+                        .inputs
+                        .first()
+                        .and_then(|o| nodes.get(&o.node))
+                        .map_or(0.0, |n| n.output_max);
+                    if max >= half_table {
+                        return Err(GraphError::Overflow(
+                            name(),
+                            format!(
+                                "its inputs of magnitude up to {} don't fit in the relu table of {} bits, covering [{}, {})",
+                                max, bits, -half_table, half_table
+                            ),
+                        ));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let nonnegative = match op {
+                // the tables of nonnegative inputs are centered on a zero-point
+                LookupOp::ZeroPoint { .. } => true,
+                // composite lookups don't look up their inputs directly
+                LookupOp::PReLU { .. }
+                | LookupOp::MaxPool2D { .. }
+                | LookupOp::MaxPool3D { .. } => continue,
+                _ => false,
+            };
+            let zero_point = op.zero_point() as f32;
+            let (smallest, largest) = (zero_point - half_table, zero_point + half_table);
+            for input in node.inputs.iter() {
+                let max = nodes.get(&input.node).map_or(0.0, |n| n.output_max) / divisor;
+                let min = if nonnegative { 0.0 } else { -max };
+                if min < smallest || max >= largest {
+                    return Err(GraphError::Overflow(
+                        name(),
+                        format!(
+                            "its inputs of magnitude up to {} don't fit in its lookup table of {} bits, covering [{}, {})",
+                            max, bits, smallest, largest
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the output of `node` is nonnegative, given which of the nodes before it are.
+    fn is_nonnegative(node: &Node, nonnegative: &BTreeMap<usize, bool>) -> bool {
+        let inputs_nonnegative = node
+            .inputs
+            .iter()
+            .all(|o| nonnegative.get(&o.node).copied().unwrap_or(false));
+        match &node.opkind {
+            OpKind::Const => node
+                .const_value
+                .as_ref()
+                .map_or(false, |v| v.iter().all(|x| *x >= 0)),
+            OpKind::Lookup(op) => match op {
+                LookupOp::ReLU { .. }
+                | LookupOp::Sigmoid { .. }
+                | LookupOp::Exp { .. }
+                | LookupOp::Sqrt { .. }
+                | LookupOp::Rsqrt { .. }
+                | LookupOp::IsZero
+                | LookupOp::Compare { .. } => true,
+                LookupOp::Div { denom } => denom.0 > 0.0 && inputs_nonnegative,
+                LookupOp::IntDiv { denom, .. } => *denom > 0 && inputs_nonnegative,
+                LookupOp::LeakyReLU { .. }
+                | LookupOp::MaxPool2D { .. }
+                | LookupOp::MaxPool3D { .. }
+                | LookupOp::AvgPool2D { .. }
+                | LookupOp::AvgPool3D { .. }
+                | LookupOp::Recip { .. }
+                | LookupOp::Limbed { .. }
+                | LookupOp::DivRem { .. }
+                | LookupOp::Floor { .. }
+                | LookupOp::Ceil { .. }
+                | LookupOp::Round { .. } => inputs_nonnegative,
+                _ => false,
+            },
+            OpKind::Poly(op) => {
+                let op = match op {
+                    PolyOp::Rescaled { inner, .. } => inner.as_ref(),
+                    op => op,
+                };
+                match op {
+                    PolyOp::Softmax { .. } | PolyOp::Abs | PolyOp::ConvReLU { .. } => true,
+                    PolyOp::Pow(exponent) if exponent % 2 == 0 => true,
+                    PolyOp::Identity
+                    | PolyOp::Reshape(_)
+                    | PolyOp::Flatten(_)
+                    | PolyOp::Pad(..)
+                    | PolyOp::SumPool { .. }
+                    | PolyOp::GlobalSumPool
+                    | PolyOp::Sum
+                    | PolyOp::Add
+                    | PolyOp::Mult
+                    | PolyOp::Pow(_) => inputs_nonnegative,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Lowers every conv (fused or not) by `lowering`, or by whichever of the lowerings it can be
+    /// laid out with lays out the fewest cells, as estimated from its dims, if `lowering` is
+    /// [ConvLowering::Auto]. Convs which can't be lowered by `lowering` (eg. a Winograd lowering of
+    /// a 5x5 kernel) are left as they are.
+    fn lower_convs(nodes: &mut NodeGraph, lowering: ConvLowering) {
+        let lowered = nodes
+            .iter()
+            .filter_map(|(i, node)| {
+                let op = match &node.opkind {
+                    OpKind::Poly(op) => op,
+                    _ => return None,
+                };
+                let in_dims = node
+                    .inputs
+                    .iter()
+                    .map(|input| nodes[&input.node].out_dims.clone())
+                    .collect_vec();
+                let lens = op.conv_lowering_lens(in_dims);
+                let (lowering, len) = match lowering {
+                    ConvLowering::Auto => lens.into_iter().min_by_key(|(_, len)| *len)?,
+                    lowering => lens.into_iter().find(|(l, _)| *l == lowering)?,
+                };
+                debug!(
+                    "lowering the conv of node {} {} ({} cells)",
+                    i, lowering, len
+                );
+                Some((*i, op.with_conv_lowering(lowering)))
+            })
+            .collect_vec();
+        for (i, op) in lowered {
+            nodes.get_mut(&i).unwrap().opkind = OpKind::Poly(op);
+        }
+    }
+
+    /// Replaces the divisions by a power of two 2^shift, which rescale the products of matmuls and
+    /// convs, and the relus they are fused into, by limbed lookups: the quotient is looked up in a
+    /// table covering the range of the output and the remainder in one of `shift` bits, such that
+    /// the tables needn't cover the wide range of the products. Divisions by other integers, and
+    /// those of average pools and means, are replaced by divisions with a remainder, which are
+    /// laid out in the same way.
+    fn limb_lookups(nodes: &mut NodeGraph) {
+        for (i, node) in nodes.iter_mut() {
+            match &mut node.opkind {
+                OpKind::Lookup(
+                    LookupOp::AvgPool2D { remainder, .. } | LookupOp::AvgPool3D { remainder, .. },
+                )
+                | OpKind::Poly(PolyOp::Reduce {
+                    reduction: Reduction::Mean { remainder, .. },
+                    ..
+                }) => {
+                    debug!("dividing the averages of node {} with a remainder", i);
+                    *remainder = true;
+                    continue;
+                }
+                _ => {}
+            }
+            let (inner, denom) = match &node.opkind {
+                OpKind::Lookup(LookupOp::Div { denom }) => (
+                    LookupOp::Div {
+                        denom: eq_float::F32(1.0),
+                    },
+                    denom.0,
+                ),
+                OpKind::Lookup(LookupOp::ReLU { scale }) => {
+                    (LookupOp::ReLU { scale: 1 }, *scale as f32)
+                }
+                _ => continue,
+            };
+            if denom < 2.0 || denom.fract() != 0.0 {
+                continue;
+            }
+            if !(denom as u64).is_power_of_two() {
+                if matches!(node.opkind, OpKind::Lookup(LookupOp::Div { .. })) {
+                    debug!("dividing node {} with a remainder of {}", i, denom);
+                    node.opkind = OpKind::Lookup(LookupOp::DivRem {
+                        denom: denom as i128,
+                    });
+                }
+                continue;
+            }
+            let shift = (denom as u64).trailing_zeros() as usize;
+            debug!("limbing the lookup of node {} with a shift of {}", i, shift);
+            node.opkind = OpKind::Lookup(LookupOp::Limbed {
+                inner: Box::new(inner),
+                shift,
+            });
+        }
+    }
+
+    /// Centers the tables of the element-wise lookups of nonnegative inputs (eg. of the output of
+    /// a relu) on a zero-point of 2^(bits - 1), such that they cover [0, 2^bits) rather than
+    /// wasting their negative half.
+    fn center_lookup_tables(nodes: &mut NodeGraph, bits: usize) {
+        let zero_point = 1i128 << (bits - 1);
+        let mut nonnegative = BTreeMap::new();
+        for (i, node) in nodes.iter_mut() {
+            let composite = matches!(
+                node.opkind,
+                OpKind::Lookup(
+                    LookupOp::PReLU { .. }
+                        | LookupOp::MaxPool2D { .. }
+                        | LookupOp::AvgPool2D { .. }
+                        | LookupOp::MaxPool3D { .. }
+                        | LookupOp::AvgPool3D { .. }
+                        | LookupOp::ZeroPoint { .. }
+                        | LookupOp::Limbed { .. }
+                        | LookupOp::DivRem { .. }
+                )
+            );
+            let inputs_nonnegative = !node.inputs.is_empty()
+                && node
+                    .inputs
+                    .iter()
+                    .all(|o| nonnegative.get(&o.node).copied().unwrap_or(false));
+            nonnegative.insert(*i, Self::is_nonnegative(node, &nonnegative));
+            let centered = match &node.opkind {
+                OpKind::Lookup(op) if !composite && inputs_nonnegative => {
+                    OpKind::Lookup(LookupOp::ZeroPoint {
+                        inner: Box::new(op.clone()),
+                        zero_point,
+                    })
+                }
+                _ => continue,
+            };
+            debug!("centering the table of node {} on {}", i, zero_point);
+            node.opkind = centered;
+        }
+    }
+
+    /// Folds a node whose inputs are all constants into a constant, such that it takes up no rows
+    /// in the circuit. Nodes which can't be evaluated are left as is.
+    fn fold_constant(node: Node, nodes: &NodeGraph) -> Node {
+        if node.inputs.is_empty() {
+            return node;
+        }
+        let mut values = vec![];
+        for o in node.inputs.iter() {
+            match nodes.get(&o.node) {
+                Some(Node {
+                    opkind: OpKind::Const,
+                    const_value: Some(value),
+                    ..
+                }) => values.push(value.clone()),
+                _ => return node,
+            }
+        }
+        let value = match &node.opkind {
+            OpKind::Poly(op) => op.f(values),
+            OpKind::Lookup(op) => op.f(values[0].clone()),
+            _ => return node,
+        };
+        let value = match value {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("failed to fold node {}: {}", node.idx, e);
+                return node;
+            }
+        };
+        debug!("folded node {} ({}) into a constant", node.idx, node.opkind);
+        let multiplier = scale_to_multiplier(node.out_scale);
+        Node {
+            opkind: OpKind::Const,
+            output_max: value.iter().map(|x| x.abs()).max().unwrap_or(0) as f32,
+            in_scale: node.out_scale,
+            inputs: vec![],
+            in_dims: vec![value.dims().to_vec()],
+            out_dims: value.dims().to_vec(),
+            raw_const_value: Some(value.map(|x| x as f32 / multiplier)),
+            const_value: Some(value),
+            ..node
+        }
+    }
+
+    /// Rewires the consumers of nodes which leave their input unchanged (identities, dropouts and
+    /// reshapes to the input's shape) to the node's input, and collapses chains of reshapes into a
+    /// single reshape. The model's outputs are kept, and bypassed nodes are left to be removed as
+    /// unused.
+    fn bypass_nodes(nodes: &mut NodeGraph, outputs: &[OutletId]) {
+        let is_reshape = |n: &Node| {
+            matches!(
+                n.opkind,
+                OpKind::Poly(PolyOp::Reshape(_) | PolyOp::Flatten(_))
+            )
+        };
+        let mut aliases = BTreeMap::<usize, OutletId>::new();
+        for i in nodes.keys().cloned().collect_vec() {
+            // safe as i is a key of the graph
+            let mut node = nodes.remove(&i).unwrap();
+            for o in node.inputs.iter_mut() {
+                if let Some(alias) = aliases.get(&o.node).filter(|_| o.slot == 0) {
+                    *o = *alias;
+                }
+            }
+            if let Some(input_node) = node.inputs.first().and_then(|o| nodes.get(&o.node)) {
+                // a reshape of a reshape only needs the first reshape's input
+                if is_reshape(&node) && is_reshape(input_node) && !input_node.inputs.is_empty() {
+                    node.inputs[0] = input_node.inputs[0];
+                    node.in_dims[0] = input_node.in_dims[0].clone();
+                }
+            }
+            if let Some(input_node) = node.inputs.first().and_then(|o| nodes.get(&o.node)) {
+                let unchanged = (is_reshape(&node)
+                    || matches!(node.opkind, OpKind::Poly(PolyOp::Identity)))
+                    && node.out_dims == input_node.out_dims
+                    && node.out_scale == input_node.out_scale;
+                if unchanged && !outputs.iter().any(|o| o.node == i) {
+                    debug!("bypassing node {} ({})", i, node.opkind);
+                    aliases.insert(i, node.inputs[0]);
+                }
+            }
+            nodes.insert(i, node);
+        }
+    }
+
+    /// Fuses common sequences of nodes into a single node which is laid out in one go: a batch norm
+    /// following a conv is folded into the conv's kernel and bias, the constant bias added to a
+    /// matmul is accumulated along with the product, the reciprocal of a square root is looked
+    /// up at once, and the relu of a conv is looked up from the conv's accumulation. Only nodes
+    /// which feed into nothing but the fused node are fused, and the nodes left unused are removed
+    /// afterwards. Relus aren't fused with [RunArgs::limb_lookups], such that they can be limbed.
+    fn fuse_nodes(
+        nodes: &mut NodeGraph,
+        outputs: &[OutletId],
+        run_args: &RunArgs,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut uses = BTreeMap::<usize, usize>::new();
+        for o in nodes.values().flat_map(|n| n.inputs.iter()).chain(outputs) {
+            *uses.entry(o.node).or_default() += 1;
+        }
+        for i in nodes.keys().cloned().collect_vec() {
+            let is_add = match &nodes[&i].opkind {
+                OpKind::Poly(PolyOp::Add) => true,
+                // adds are rescaled to homogenize the scales of their inputs
+                OpKind::Poly(PolyOp::Rescaled { inner, scale }) => {
+                    **inner == PolyOp::Add && scale.iter().all(|s| s.1 == 1)
+                }
+                _ => false,
+            };
+            let fused = if is_add {
+                Self::fuse_matmul_add(nodes, &uses, i)
+            } else if matches!(nodes[&i].opkind, OpKind::Poly(PolyOp::ScaleAndShift)) {
+                Self::fuse_conv_scale_and_shift(nodes, &uses, i)?
+            } else if matches!(nodes[&i].opkind, OpKind::Lookup(LookupOp::Recip { .. })) {
+                Self::fuse_sqrt_recip(nodes, &uses, i)
+            } else if matches!(nodes[&i].opkind, OpKind::Lookup(LookupOp::ReLU { .. }))
+                && !run_args.limb_lookups
+            {
+                Self::fuse_conv_relu(nodes, &uses, i)
+            } else {
+                false
+            };
+            if fused {
+                debug!("fused node {} into {}", i, nodes[&i].opkind);
+            }
+        }
+        Ok(())
+    }
+
+    /// Folds the scale and shift of a batch norm into the kernel and bias of the conv it follows.
+    /// The folded kernel is quantized such that the conv's output lands at the scale of the batch
+    /// norm's output, and the folded params replace the batch norm's params.
+    fn fuse_conv_scale_and_shift(
+        nodes: &mut NodeGraph,
+        uses: &BTreeMap<usize, usize>,
+        i: usize,
+    ) -> Result<bool, Box<dyn Error>> {
+        let node = &nodes[&i];
+        if node.inputs.len() != 3 || node.inputs.iter().any(|o| uses.get(&o.node) != Some(&1)) {
+            return Ok(false);
+        }
+        let (c, a, b) = (node.inputs[0], node.inputs[1], node.inputs[2]);
+        let conv = match nodes.get(&c.node) {
+            Some(n) if matches!(n.opkind, OpKind::Poly(PolyOp::Conv { .. })) => n,
+            _ => return Ok(false),
+        };
+        let raw = |o: Option<&OutletId>| {
+            o.and_then(|o| nodes.get(&o.node))
+                .and_then(|n| n.raw_const_value.as_ref())
+        };
+        let (kernel, a_raw, b_raw) = match (raw(conv.inputs.get(1)), raw(Some(&a)), raw(Some(&b))) {
+            (Some(k), Some(a), Some(b)) => (k, a, b),
+            _ => return Ok(false),
+        };
+        let bias = match conv.inputs.get(2) {
+            Some(o) => match raw(Some(o)) {
+                Some(bias) => Some(bias),
+                None => return Ok(false),
+            },
+            None => None,
+        };
+        // the scale and shift should be per output channel
+        let channels = kernel.dims()[0];
+        if a_raw.len() != channels || b_raw.len() != channels {
+            return Ok(false);
+        }
+        let x = conv.inputs[0];
+        let kernel_scale = match node.out_scale.checked_sub(nodes[&x.node].out_scale) {
+            Some(s) => s,
+            None => return Ok(false),
+        };
+
+        let per_channel = kernel.len() / channels;
+        let folded_kernel = kernel
+            .iter()
+            .enumerate()
+            .map(|(j, k)| k * a_raw[j / per_channel])
+            .collect_vec();
+        let folded_bias = (0..channels)
+            .map(|o| bias.map_or(0.0, |bias| bias[o]) * a_raw[o] + b_raw[o])
+            .collect_vec();
+        let kernel = Self::new_const_node(a.node, &folded_kernel, kernel.dims(), kernel_scale)?;
+        let bias = Self::new_const_node(b.node, &folded_bias, &[channels], node.out_scale)?;
+
+        let fused = Node {
+            idx: i,
+            opkind: conv.opkind.clone(),
+            inputs: vec![x, a, b],
+            in_dims: conv.in_dims.clone(),
+            out_dims: conv.out_dims.clone(),
+            in_scale: conv.in_scale,
+            out_scale: node.out_scale,
+            output_max: node.output_max,
+            ..Default::default()
+        };
+        nodes.insert(a.node, kernel);
+        nodes.insert(b.node, bias);
+        nodes.insert(i, fused);
+        Ok(true)
+    }
+
+    /// Replaces the addition of a constant bias to the output of a matmul by a single matmul which
+    /// accumulates the bias.
+    fn fuse_matmul_add(nodes: &mut NodeGraph, uses: &BTreeMap<usize, usize>, i: usize) -> bool {
+        let node = &nodes[&i];
+        if node.inputs.len() != 2 {
+            return false;
+        }
+        let is_matmul = |o: &OutletId| {
+            matches!(
+                nodes.get(&o.node).map(|n| &n.opkind),
+                Some(OpKind::Poly(PolyOp::Matmul))
+            )
+        };
+        let is_const = |o: &OutletId| nodes.get(&o.node).map_or(false, |n| n.opkind.is_const());
+        let (mm, bias) = match (node.inputs[0], node.inputs[1]) {
+            (m, b) if is_matmul(&m) && is_const(&b) => (m, b),
+            (b, m) if is_matmul(&m) && is_const(&b) => (m, b),
+            _ => return false,
+        };
+        let (matmul, bias_node) = (&nodes[&mm.node], &nodes[&bias.node]);
+        if uses.get(&mm.node) != Some(&1) || matmul.inputs.len() != 2 {
+            return false;
+        }
+        let (x, w) = (matmul.inputs[0], matmul.inputs[1]);
+        let (x_dims, w_dims) = (&nodes[&x.node].out_dims, &nodes[&w.node].out_dims);
+        if x_dims.len() != 2
+            || w_dims.len() != 2
+            || bias_node.out_dims.iter().product::<usize>() != w_dims[1]
+            || bias_node.out_scale != matmul.out_scale
+            || node.out_dims != matmul.out_dims
+        {
+            return false;
+        }
+
+        let fused = Node {
+            idx: i,
+            opkind: OpKind::Poly(PolyOp::BiasedMatmul),
+            inputs: vec![x, w, bias],
+            in_dims: vec![x_dims.clone(), w_dims.clone(), bias_node.out_dims.clone()],
+            out_dims: matmul.out_dims.clone(),
+            in_scale: matmul.in_scale,
+            out_scale: node.out_scale,
+            output_max: node.output_max,
+            ..Default::default()
+        };
+        nodes.insert(i, fused);
+        true
+    }
+
+    /// Replaces the reciprocal of a square root by a single rsqrt lookup, which saves a table and
+    /// the rounding of the intermediate square root.
+    fn fuse_sqrt_recip(nodes: &mut NodeGraph, uses: &BTreeMap<usize, usize>, i: usize) -> bool {
+        let node = &nodes[&i];
+        let (sqrt_idx, out_mult) = match (&node.opkind, node.inputs.as_slice()) {
+            (OpKind::Lookup(LookupOp::Recip { scales }), [input]) => (input.node, scales.1),
+            _ => return false,
+        };
+        let sqrt = match nodes.get(&sqrt_idx) {
+            Some(n) if uses.get(&sqrt_idx) == Some(&1) => n,
+            _ => return false,
+        };
+        let in_mult = match &sqrt.opkind {
+            OpKind::Lookup(LookupOp::Sqrt { scales }) => scales.0,
+            _ => return false,
+        };
+
+        let fused = Node {
+            idx: i,
+            opkind: OpKind::Lookup(LookupOp::Rsqrt {
+                scales: (in_mult, out_mult),
+            }),
+            inputs: sqrt.inputs.clone(),
+            in_dims: sqrt.in_dims.clone(),
+            out_dims: node.out_dims.clone(),
+            in_scale: sqrt.in_scale,
+            out_scale: node.out_scale,
+            output_max: out_mult as f32 * (in_mult as f32).sqrt(),
+            ..Default::default()
+        };
+        nodes.insert(i, fused);
+        true
+    }
+
+    /// Replaces a relu following an ungrouped conv by a single conv whose accumulated outputs are
+    /// looked up in the relu's table as they are laid out, saving the copy of every output of the
+    /// conv into the lookup columns. The batch norm a conv is followed by is folded first, such
+    /// that the relu of a conv, batch norm and relu block is fused too.
+    fn fuse_conv_relu(nodes: &mut NodeGraph, uses: &BTreeMap<usize, usize>, i: usize) -> bool {
+        let node = &nodes[&i];
+        let (conv_idx, scale) = match (&node.opkind, node.inputs.as_slice()) {
+            (OpKind::Lookup(LookupOp::ReLU { scale }), [input]) => (input.node, *scale),
+            _ => return false,
+        };
+        let conv = match nodes.get(&conv_idx) {
+            Some(n) if uses.get(&conv_idx) == Some(&1) => n,
+            _ => return false,
+        };
+        let (padding, stride, lowering) = match &conv.opkind {
+            OpKind::Poly(PolyOp::Conv {
+                padding,
+                stride,
+                group: 1,
+                lowering,
+            }) => (*padding, *stride, *lowering),
+            _ => return false,
+        };
+
+        let fused = Node {
+            idx: i,
+            opkind: OpKind::Poly(PolyOp::ConvReLU {
+                padding,
+                stride,
+                scale,
+                lowering,
+            }),
+            inputs: conv.inputs.clone(),
+            in_dims: conv.in_dims.clone(),
+            out_dims: node.out_dims.clone(),
+            in_scale: conv.in_scale,
+            out_scale: node.out_scale,
+            output_max: node.output_max,
+            ..Default::default()
+        };
+        nodes.insert(i, fused);
+        true
+    }
+
+    /// Creates a constant node holding `values` quantized at `scale`.
+    fn new_const_node(
+        idx: usize,
+        values: &[f32],
+        dims: &[usize],
+        scale: u32,
+    ) -> Result<Node, Box<dyn Error>> {
+        let value = vector_to_quantized(values, dims, 0f32, scale)?;
+        Ok(Node {
+            idx,
+            opkind: OpKind::Const,
+            in_dims: vec![dims.to_vec()],
+            out_dims: dims.to_vec(),
+            in_scale: scale,
+            out_scale: scale,
+            output_max: value.iter().map(|x| x.abs()).max().unwrap_or(0) as f32,
+            raw_const_value: Some(Tensor::new(Some(values), dims)?),
+            const_value: Some(value),
+            ..Default::default()
+        })
+    }
+
+    /// Removes the nodes which the model's outputs don't depend on, such as the decomposed ops of
+    /// a fused attention block. Input nodes are kept as they are indexed by the model's inputs.
+    fn remove_unused_nodes(nodes: &mut NodeGraph, outputs: &[OutletId]) {
+        let mut used = BTreeSet::new();
+        let mut stack = outputs.iter().map(|o| o.node).collect_vec();
+        while let Some(i) = stack.pop() {
+            if used.insert(i) {
+                if let Some(n) = nodes.get(&i) {
+                    stack.extend(n.inputs.iter().map(|o| o.node));
+                }
+            }
+        }
+        nodes.retain(|i, n| used.contains(i) || n.opkind.is_input());
+    }
+
     /// Runs a dummy forward pass on sample data !
     /// # Arguments
     ///
     /// * `path` - A path to an Onnx file.
     /// * `run_args` - [RunArgs]
+    /// * `activations` - (optional) collects the quantized output of every node, for each sample of the batch.
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     pub fn forward(
         model_path: impl AsRef<Path>,
         model_inputs: &[Tensor<i128>],
-        run_args: RunArgs,
+        mut run_args: RunArgs,
+        mut activations: Option<&mut Vec<NodeActivation>>,
     ) -> Result<Vec<Tensor<f32>>, Box<dyn Error>> {
-        let model = tract_onnx::onnx()
-            .model_for_path(model_path)
-            .map_err(|_| GraphError::ModelLoad)?;
+        let model = Self::load_onnx(model_path, &run_args)?;
         info!("running forward pass");
 
-        let mut nodes = BTreeMap::<usize, Node>::new();
-        for (i, n) in model.nodes.iter().enumerate() {
-            let n = Node::new(n.clone(), &mut nodes, run_args.scale, i)?;
-            nodes.insert(i, n);
-        }
+        let nodes = Self::load_nodes(&model, &mut run_args)?;
 
         debug!("{}", Table::new(nodes.clone()).to_string());
 
-        let mut results: BTreeMap<&usize, Tensor<i128>> = BTreeMap::new();
-        for (i, n) in nodes.iter() {
-            let mut inputs = vec![];
-            for i in n.inputs.iter() {
-                match results.get(&i.node) {
-                    Some(value) => inputs.push(value.clone()),
-                    None => return Err(Box::new(GraphError::MissingNode(i.node))),
-                }
+        let batch_size = run_args.batch_size;
+        let mut batch_outputs: Vec<Vec<f32>> = vec![vec![]; model.outputs.len()];
+        for b in 0..batch_size {
+            // inputs are loaded first, as they may come after their consumers in a cut graph
+            let mut results =
+                Self::load_sample(&nodes, &model.inputs, model_inputs, b, batch_size)?;
+            Self::eval_nodes(&nodes, &mut results)?;
+
+            if let Some(activations) = activations.as_mut() {
+                activations.extend(results.iter().map(|(i, t)| {
+                    let n = nodes.get(i).unwrap();
+                    let multiplier = scale_to_multiplier(n.out_scale);
+                    NodeActivation {
+                        idx: *i,
+                        name: model
+                            .nodes
+                            .get(*i)
+                            .map(|n| n.name.clone())
+                            .unwrap_or_default(),
+                        op: match &n.opkind {
+                            OpKind::Lookup(l) => format!("{:?}", l),
+                            opkind => opkind.to_string(),
+                        },
+                        sample: b,
+                        scale: n.out_scale,
+                        dims: t.dims().to_vec(),
+                        quantized: t.to_vec(),
+                        dequantized: t.iter().map(|x| (*x as f32) / multiplier).collect(),
+                    }
+                }));
             }
-            match &n.opkind {
-                OpKind::Lookup(op) => {
-                    // assert_eq!(inputs.len(), 1);
-                    results.insert(i, op.f(inputs[0].clone())?);
-                }
-                OpKind::Poly(op) => {
-                    results.insert(i, op.f(inputs)?);
-                }
-                OpKind::Input => {
-                    let mut t = model_inputs[*i].clone();
-                    t.reshape(&n.out_dims);
-                    results.insert(i, t);
-                }
-                OpKind::Const => {
-                    results.insert(i, n.const_value.as_ref().unwrap().clone());
-                }
-                _ => {
-                    panic!("unsupported op")
-                }
+
+            for (o, output) in model.outputs.iter().zip(batch_outputs.iter_mut()) {
+                let n = nodes.get(&o.node).unwrap();
+                let scale = scale_to_multiplier(n.out_scale);
+                output.extend(
+                    results
+                        .get(&o.node)
+                        .unwrap()
+                        .iter()
+                        .map(|x| (*x as f32) / scale),
+                );
             }
         }
 
@@ -174,29 +1641,152 @@ impl Model {
             "model outputs are nodes: {:?}",
             output_nodes.clone().map(|o| o.node).collect_vec()
         );
+        // batched outputs carry a leading batch dim
         let outputs = output_nodes
-            .map(|o| {
-                let n = nodes.get(&o.node).unwrap();
-                let scale = scale_to_multiplier(n.out_scale);
-                results
-                    .get(&o.node)
-                    .unwrap()
-                    .clone()
-                    .map(|x| (x as f32) / scale)
+            .zip(batch_outputs)
+            .map(|(o, values)| {
+                let dims = &nodes.get(&o.node).unwrap().out_dims;
+                let dims = match batch_size {
+                    1 => dims.clone(),
+                    _ => [&[batch_size], &dims[..]].concat(),
+                };
+                Tensor::new(Some(&values), &dims)
             })
-            .collect_vec();
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(outputs)
     }
 
+    /// Loads sample `b` of the batch from the inputs of the model, which hold the samples of the
+    /// batch one after the other, keyed by the index of their input node.
+    pub fn load_sample(
+        nodes: &NodeGraph,
+        input_outlets: &[OutletId],
+        model_inputs: &[Tensor<i128>],
+        b: usize,
+        batch_size: usize,
+    ) -> Result<BTreeMap<usize, Tensor<i128>>, Box<dyn Error>> {
+        let mut results = BTreeMap::new();
+        for (input, o) in model_inputs.iter().zip(input_outlets.iter()) {
+            let n = nodes.get(&o.node).unwrap();
+            let mut t = input.clone();
+            if batch_size > 1 {
+                let len = n.out_dims.iter().product::<usize>();
+                t.flatten();
+                t = t.get_slice(&[b * len..(b + 1) * len])?;
+            }
+            t.reshape(&n.out_dims);
+            results.insert(o.node, t);
+        }
+        Ok(results)
+    }
+
+    /// Evaluates a (non input) node on the values of its inputs.
+    pub fn eval_node(
+        n: &Node,
+        inputs: Vec<Tensor<i128>>,
+    ) -> Result<Tensor<i128>, Box<dyn Error + Send + Sync>> {
+        match &n.opkind {
+            OpKind::Lookup(op) => Ok(op.f(inputs[0].clone())?),
+            OpKind::Poly(op) => Ok(op.f(inputs)?),
+            OpKind::Const => Ok(n.const_value.as_ref().unwrap().clone()),
+            _ => {
+                panic!("unsupported op")
+            }
+        }
+    }
+
+    /// Groups the (non input) nodes into waves, such that the inputs of the nodes of each wave
+    /// are all outputs of earlier waves (or inputs of the model). The nodes of a wave don't
+    /// depend on each other, and can be evaluated in any order. The waves follow the input
+    /// edges of the nodes, rather than the order of their indices.
+    pub fn eval_waves(nodes: &NodeGraph) -> Result<Vec<Vec<usize>>, GraphError> {
+        // the number of (non input) nodes each node waits on, and the nodes waiting on each node
+        let mut pending: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut consumers: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (i, n) in nodes.iter().filter(|(_, n)| !n.opkind.is_input()) {
+            let deps: BTreeSet<usize> = n
+                .inputs
+                .iter()
+                .map(|o| o.node)
+                .filter(|d| nodes.get(d).map_or(false, |d| !d.opkind.is_input()))
+                .collect();
+            for d in deps.iter() {
+                consumers.entry(*d).or_default().push(*i);
+            }
+            pending.insert(*i, deps.len());
+        }
+        let mut wave: Vec<usize> = pending
+            .iter()
+            .filter(|(_, p)| **p == 0)
+            .map(|(i, _)| *i)
+            .collect();
+        let (mut waves, mut num_waved) = (vec![], 0);
+        while !wave.is_empty() {
+            let mut next = vec![];
+            for i in wave.iter() {
+                for c in consumers.get(i).into_iter().flatten() {
+                    let p = pending.get_mut(c).unwrap();
+                    *p -= 1;
+                    if *p == 0 {
+                        next.push(*c);
+                    }
+                }
+            }
+            num_waved += wave.len();
+            waves.push(std::mem::replace(&mut wave, next));
+        }
+        if num_waved < pending.len() {
+            return Err(GraphError::MisformedParams(
+                "the graph has a cycle, its nodes can't be evaluated in order".to_string(),
+            ));
+        }
+        Ok(waves)
+    }
+
+    /// Evaluates the (non input) nodes on the values of the inputs of the model in `results`,
+    /// inserting the output of each node. The nodes of each of the [Model::eval_waves] are
+    /// evaluated in parallel. Only this integer forward pass is parallel, the layout of the
+    /// nodes in the circuit is sequential.
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    pub fn eval_nodes(
+        nodes: &NodeGraph,
+        results: &mut BTreeMap<usize, Tensor<i128>>,
+    ) -> Result<(), Box<dyn Error>> {
+        for wave in Self::eval_waves(nodes)? {
+            let outputs = wave
+                .par_iter()
+                .map(|i| -> Result<_, Box<dyn Error + Send + Sync>> {
+                    let n = &nodes[i];
+                    let inputs = n
+                        .inputs
+                        .iter()
+                        .map(|o| {
+                            results
+                                .get(&o.node)
+                                .cloned()
+                                .ok_or(GraphError::MissingNode(o.node))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok((*i, Self::eval_node(n, inputs)?))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e as Box<dyn Error>)?;
+            results.extend(outputs);
+        }
+        Ok(())
+    }
+
     /// Creates a `Model` from parsed CLI arguments
     pub fn from_ezkl_conf(cli: Cli) -> Result<Self, Box<dyn Error>> {
         let visibility = VarVisibility::from_args(cli.args.clone())?;
         match cli.command {
-            Commands::Table { model } | Commands::Mock { model, .. } => {
+            Commands::Table { model, .. } | Commands::Mock { model, .. } => {
                 Model::new(model, cli.args, Mode::Mock, visibility)
             }
-            Commands::Prove { model, .. }
+            Commands::Setup { model, .. }
+            | Commands::Bench { model, .. }
+            | Commands::Prove { model, .. }
             | Commands::Verify { model, .. }
             | Commands::Aggregate { model, .. } => {
                 Model::new(model, cli.args, Mode::Prove, visibility)
@@ -209,6 +1799,10 @@ impl Model {
             Commands::RenderCircuit { model, .. } => {
                 Model::new(model, cli.args, Mode::Table, visibility)
             }
+            #[cfg(not(target_arch = "wasm32"))]
+            Commands::RenderGraph { model, .. } => {
+                Model::new(model, cli.args, Mode::Table, visibility)
+            }
             _ => panic!(),
         }
     }
@@ -226,6 +1820,7 @@ impl Model {
     ///
     /// * `meta` - Halo2 ConstraintSystem.
     /// * `advices` - A `VarTensor` holding columns of advices. Must be sufficiently large to configure all the nodes loaded in `self.nodes`.
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     pub fn configure<F: FieldExt + TensorType>(
         &self,
         meta: &mut ConstraintSystem<F>,
@@ -286,9 +1881,7 @@ impl Model {
                             self.run_args.check_mode,
                             self.run_args.tolerance as i32,
                         );
-                        base_gates.insert(false, Rc::new(RefCell::new(config)));
-                        // safe
-                        base_gates.get(&false).unwrap().clone()
+                        insert_base_gate(&mut base_gates, false, config)
                     }
                 };
                 let config = self.conf_lookup(base_config.clone(), node, meta, vars)?;
@@ -302,9 +1895,29 @@ impl Model {
             info!("packing outputs...");
             packed_outputs = self.output_ops(meta, vars, &mut base_gates);
         }
-        if self.visibility.output.is_public() {
+        if self.output_visibility().iter().any(|v| v.is_public()) {
             range_checks = self.output_ops(meta, vars, &mut base_gates);
         };
+        let hashed = self
+            .input_visibility()
+            .iter()
+            .chain(self.output_visibility().iter())
+            .any(|v| v.is_hashed())
+            // signed inputs are hashed into the message of the signature
+            || self.run_args.signer.is_some();
+        let sha256 = self.run_args.hash_function == HashFunction::Sha256;
+        let poseidon = ((hashed && !sha256) || self.visibility.params.is_hashed())
+            .then(|| PoseidonConfig::configure(meta));
+        let sha256 = (hashed && sha256).then(|| Sha256Config::configure(meta));
+        let merkle = poseidon
+            .clone()
+            .filter(|_| self.run_args.merkle_depth > 0)
+            .map(|poseidon| MerkleConfig::configure(meta, poseidon));
+        let ecdsa = self
+            .run_args
+            .signer
+            .is_some()
+            .then(|| EcdsaConfig::configure(meta));
 
         Ok(ModelConfig {
             configs: results,
@@ -312,6 +1925,12 @@ impl Model {
             range_checks,
             packed_outputs,
             vars: vars.clone(),
+            poseidon,
+            sha256,
+            merkle,
+            ecdsa,
+            params: BTreeMap::new(),
+            inputs: BTreeMap::new(),
         })
     }
 
@@ -327,15 +1946,14 @@ impl Model {
             let config = match base_gates.get(&false) {
                 Some(config) => config.clone(),
                 None => {
-                    let config = Rc::new(RefCell::new(PolyConfig::<F>::configure(
+                    let config = PolyConfig::<F>::configure(
                         meta,
                         &[vars.advices[0].clone(), vars.advices[1].clone()],
                         &vars.advices[2],
                         CheckMode::SAFE,
                         self.run_args.tolerance.try_into().unwrap(),
-                    )));
-                    base_gates.insert(false, config.clone());
-                    config
+                    );
+                    insert_base_gate(base_gates, false, config)
                 }
             };
             configs.push(config);
@@ -390,11 +2008,21 @@ impl Model {
 
         let input_idx = input_nodes.iter().map(|f| f.idx).collect_vec();
 
-        let fixed_flag = !input_nodes
-            .iter()
-            .filter(|f| f.opkind.is_const() && self.visibility.params.is_public())
-            .collect_vec()
-            .is_empty();
+        let (required_lookups, fused_lookups) = match &node.opkind {
+            OpKind::Poly(op) => (op.required_lookups(), op.fused_lookups()),
+            _ => (vec![], vec![]),
+        };
+
+        let dynamic = matches!(&node.opkind, OpKind::Poly(op) if op.requires_dynamic_lookup());
+
+        // ops which also apply lookups write intermediate values to their inputs, so these can't be fixed
+        let fixed_flag = required_lookups.is_empty()
+            && !dynamic
+            && !input_nodes
+                .iter()
+                .filter(|f| f.opkind.is_const() && self.visibility.params.is_public())
+                .collect_vec()
+                .is_empty();
 
         let config = match base_gates.get(&fixed_flag) {
             Some(config) => {
@@ -409,18 +2037,41 @@ impl Model {
                 };
                 // output node
                 let output = &vars.advices[2];
-                let config = Rc::new(RefCell::new(PolyConfig::configure(
+                let config = PolyConfig::configure(
                     meta,
                     inputs.into_iter().collect_vec()[..].try_into()?,
                     output,
                     CheckMode::SAFE,
                     self.run_args.tolerance.try_into().unwrap(),
-                )));
-                base_gates.insert(fixed_flag, config.clone());
-                config
+                );
+                insert_base_gate(base_gates, fixed_flag, config)
             }
         };
 
+        for op in required_lookups.iter() {
+            config.borrow_mut().configure_lookup(
+                meta,
+                &vars.advices[0],
+                &vars.advices[1],
+                op.table_bits(self.run_args.bits),
+                op,
+            )?;
+        }
+        for op in fused_lookups.iter() {
+            config.borrow_mut().configure_fused_lookup(
+                meta,
+                op.table_bits(self.run_args.bits),
+                op,
+            )?;
+        }
+        if dynamic {
+            config.borrow_mut().configure_dynamic_lookup(
+                meta,
+                &vars.advices[0],
+                &vars.advices[1],
+            )?;
+        }
+
         let config = NodeConfig::Op {
             config,
             inputs: input_idx,
@@ -453,24 +2104,23 @@ impl Model {
 
         let input_idx = input_nodes.iter().map(|f| f.idx).collect_vec();
 
-        let mut op = match &node.opkind {
-            OpKind::Lookup(l) => l.clone(),
+        let ops = match &node.opkind {
+            OpKind::Lookup(l) => l.table_ops(),
             c => {
                 return Err(Box::new(GraphError::WrongMethod(node.idx, c.clone())));
             }
         };
 
-        match op {
-            LookupOp::PReLU { scale, .. } => {
-                op = LookupOp::ReLU { scale };
-            }
-            _ => {}
+        for op in ops.iter() {
+            config.borrow_mut().configure_lookup(
+                meta,
+                input,
+                output,
+                op.table_bits(self.run_args.bits),
+                op,
+            )?;
         }
 
-        config
-            .borrow_mut()
-            .configure_lookup(meta, input, output, self.run_args.bits, &op)?;
-
         let config = NodeConfig::Op {
             config,
             inputs: input_idx,
@@ -485,22 +2135,22 @@ impl Model {
     /// * `config` - [ModelConfig] holding all node configs.
     /// * `layouter` - Halo2 Layouter.
     /// * `inputs` - The values to feed into the circuit.
+    /// * `signature` - The signature over the inputs, when these are signed.
+    /// * `merkle_paths` - The paths to the leaves of the hashed inputs and params, when these are
+    ///   leaves of Merkle trees.
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     pub fn layout<F: FieldExt + TensorType>(
         &self,
         mut config: ModelConfig<F>,
         layouter: &mut impl Layouter<F>,
         inputs: &[ValTensor<F>],
+        signature: Value<(Fq, Fq)>,
+        merkle_paths: &[(usize, Vec<F>)],
         vars: &ModelVars<F>,
     ) -> Result<(), Box<dyn Error>> {
         info!("model layout");
-        let mut results = BTreeMap::<usize, ValTensor<F>>::new();
-        for (i, input_value) in inputs.iter().enumerate() {
-            if self.visibility.input.is_public() {
-                results.insert(i, vars.instances[i].clone());
-            } else {
-                results.insert(i, input_value.clone());
-            }
-        }
+        // the inputs (and instances) hold each sample of the batch in turn, input major
+        let batch_size = self.run_args.batch_size;
 
         // layout any lookup tables
         let _: Vec<()> = config
@@ -513,88 +2163,390 @@ impl Model {
             })
             .collect::<Result<Vec<()>, _>>()?;
 
+        config.params = self.layout_params(&config, layouter, merkle_paths.last(), vars)?;
+        config.inputs = self.layout_hashed_inputs(&config, layouter, inputs, merkle_paths, vars)?;
+        config.inputs = self.layout_signed_inputs(&config, layouter, inputs, signature)?;
+
+        // the outputs of every sample, output major
+        let mut outputs = vec![];
         layouter.assign_region(
             || "model",
             |mut region| {
                 let mut offset: usize = 0;
-                for (idx, config) in config.configs.iter() {
-                    trace!("laying out offset {}", offset);
-                    if let Some(vt) = self
-                        .layout_config(&mut region, &mut results, config, &mut offset)
-                        .map_err(|e| {
-                            error!("{}", e);
-                            halo2_proofs::plonk::Error::Synthesis
-                        })?
-                    {
-                        // we get the max as for fused nodes this corresponds to the node output
-                        results.insert(*idx, vt);
-                        //only use with mock prover
-                        if matches!(self.mode, Mode::Mock) {
-                            trace!(
-                                "------------ output node {:?}: {:?}",
-                                idx,
-                                results.get(idx).unwrap().show()
-                            );
+                outputs = vec![];
+                // the tables of the dynamic lookups are laid out from the top of the region
+                for c in config.configs.values() {
+                    if let NodeConfig::Op { config, .. } = c {
+                        if let Some(dynamic) = &config.borrow().dynamic_lookup {
+                            dynamic.reset();
                         }
                     }
                 }
-
-                let output_nodes = self.model.outputs.iter();
-                info!(
-                    "model outputs are nodes: {:?}",
-                    output_nodes.clone().map(|o| o.node).collect_vec()
-                );
-                let mut outputs = output_nodes
-                    .map(|o| results.get(&o.node).unwrap().clone())
-                    .collect_vec();
-
-                // pack outputs if need be
-                for (i, packed_output) in config.packed_outputs.iter_mut().enumerate() {
-                    info!("packing outputs...");
-                    outputs[i] = packed_output
-                        .borrow_mut()
-                        .layout(
-                            &mut region,
-                            &outputs[i..i + 1],
-                            &mut offset,
-                            PolyOp::Pack(self.run_args.pack_base, self.run_args.scale).into(),
-                        )
-                        .map_err(|e| {
-                            error!("{}", e);
-                            halo2_proofs::plonk::Error::Synthesis
-                        })?
-                        .unwrap();
-                    // only use with mock prover
-                    if matches!(self.mode, Mode::Mock) {
-                        trace!("------------ packed output {:?}", outputs[i].show());
-                    }
+                for b in 0..batch_size {
+                    let sample =
+                        self.layout_sample(&mut config, &mut region, inputs, vars, b, &mut offset)?;
+                    outputs.push(sample);
                 }
-
-                let _ = config
-                    .range_checks
-                    .iter()
-                    .zip(outputs)
-                    .enumerate()
-                    .map(|(i, (range_check, output))| {
-                        let mut instance_offset = 0;
-                        if self.visibility.input.is_public() {
-                            instance_offset += inputs.len();
-                        };
-                        range_check.borrow_mut().layout(
-                            &mut region,
-                            &[output, vars.instances[instance_offset + i].clone()],
-                            &mut offset,
-                            PolyOp::RangeCheck(self.run_args.tolerance as i32).into(),
-                        )
-                    })
-                    .collect_vec();
                 Ok(())
             },
         )?;
+        self.layout_hashed_outputs(&config, layouter, &outputs, vars)?;
         info!("computing...");
         Ok(())
     }
 
+    /// Assigns the params of the model ahead of its region when these are hashed or fixed, such
+    /// that the ops of the model copy them from their assigned cells.
+    /// * Hashed params are assigned as the inputs of the Poseidon chip, and the digest (or the
+    ///   root of the Merkle tree it is a leaf of) is constrained to equal the last instance.
+    /// * Fixed params are assigned to fixed columns, such that the verifying key commits to them.
+    /// # Arguments
+    ///
+    /// * `config` - [ModelConfig] of the model.
+    /// * `layouter` - Halo2 Layouter.
+    /// * `merkle_path` - The path to the leaf of the params, when this is a leaf of a Merkle tree.
+    /// * `vars` - [ModelVars] for the model.
+    fn layout_params<F: FieldExt + TensorType>(
+        &self,
+        config: &ModelConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        merkle_path: Option<&(usize, Vec<F>)>,
+        vars: &ModelVars<F>,
+    ) -> Result<BTreeMap<usize, ValTensor<F>>, Box<dyn Error>> {
+        let params = self.params();
+        let mut assigned = BTreeMap::new();
+        if let (Some(poseidon), true) = (&config.poseidon, self.visibility.params.is_hashed()) {
+            let values: Vec<ValType<F>> = params
+                .values()
+                .flat_map(|p| p.iter().map(|x| Value::known(i128_to_felt::<F>(*x)).into()))
+                .collect_vec();
+            let (cells, mut digest) = poseidon.layout(layouter, &values)?;
+            if let (Some(merkle), Some((index, siblings))) = (&config.merkle, merkle_path) {
+                digest = merkle.layout(layouter, &digest, *index, siblings)?;
+            }
+            let mut cells = cells.into_iter();
+            for (i, param) in params {
+                let cells = cells
+                    .by_ref()
+                    .take(param.len())
+                    .map(ValType::from)
+                    .collect_vec();
+                let cells = Tensor::new(Some(&cells[..]), param.dims())?;
+                assigned.insert(i, cells.into());
+            }
+            // the digest is the last of the instances
+            let instance = match vars.instances.last() {
+                Some(ValTensor::Instance { inner, .. }) => *inner,
+                _ => return Err(Box::new(GraphError::Visibility)),
+            };
+            layouter.constrain_instance(digest.cell(), instance, 0)?;
+        } else if self.visibility.params.is_fixed() {
+            assigned = layouter.assign_region(
+                || "params",
+                |mut region| {
+                    let mut offset = 0;
+                    let mut assigned: BTreeMap<usize, ValTensor<F>> = BTreeMap::new();
+                    for (i, param) in params.iter() {
+                        let value: Tensor<Value<F>> = param.clone().into();
+                        let cells = vars.fixed[0].assign(&mut region, offset, &value.into())?;
+                        offset += cells.len();
+                        assigned.insert(*i, cells.map(ValType::from).into());
+                    }
+                    Ok(assigned)
+                },
+            )?;
+        }
+        Ok(assigned)
+    }
+
+    /// Hashes each sample of the hashed inputs ahead of the model's region, such that the ops of
+    /// the model copy them from the cells of the Poseidon chip. Returns the assigned inputs, by
+    /// index in the inputs of the circuit.
+    /// # Arguments
+    ///
+    /// * `config` - [ModelConfig] of the model.
+    /// * `layouter` - Halo2 Layouter.
+    /// * `inputs` - The inputs of every sample of the batch, input major.
+    /// * `merkle_paths` - The paths to the leaves of each sample of the hashed inputs in turn,
+    ///   when these are leaves of Merkle trees.
+    /// * `vars` - [ModelVars] for the model.
+    fn layout_hashed_inputs<F: FieldExt + TensorType>(
+        &self,
+        config: &ModelConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        inputs: &[ValTensor<F>],
+        merkle_paths: &[(usize, Vec<F>)],
+        vars: &ModelVars<F>,
+    ) -> Result<BTreeMap<usize, ValTensor<F>>, Box<dyn Error>> {
+        let batch_size = self.run_args.batch_size;
+        let mut merkle_paths = merkle_paths.iter();
+        let (input_instances, _) = self.instance_offsets();
+        let mut assigned = BTreeMap::new();
+        for (i, (visibility, instance)) in self
+            .input_visibility()
+            .iter()
+            .zip(input_instances)
+            .enumerate()
+        {
+            if let (true, Some(instance)) = (visibility.is_hashed(), instance) {
+                for b in 0..batch_size {
+                    let input = &inputs[i * batch_size + b];
+                    let cells = self.hash_to_instance(
+                        config,
+                        layouter,
+                        input,
+                        merkle_paths.next(),
+                        &vars.instances[instance + b],
+                    )?;
+                    let mut cells: Tensor<ValType<F>> = cells.into_iter().map(ValType::from).into();
+                    cells.reshape(input.dims());
+                    assigned.insert(i * batch_size + b, cells.into());
+                }
+            }
+        }
+        Ok(assigned)
+    }
+
+    /// Verifies the signature over the inputs ahead of the model's region, when these are signed.
+    /// Every sample of every input is hashed at once, and the signature is verified over the
+    /// digest, such that the ops of the model copy the inputs from the cells of the hash chip.
+    /// Returns the assigned inputs, by index in the inputs of the circuit.
+    /// # Arguments
+    ///
+    /// * `config` - [ModelConfig] of the model, holding any hashed inputs already assigned.
+    /// * `layouter` - Halo2 Layouter.
+    /// * `inputs` - The inputs of every sample of the batch, input major.
+    /// * `signature` - The signature over the digest of the inputs.
+    fn layout_signed_inputs<F: FieldExt + TensorType>(
+        &self,
+        config: &ModelConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        inputs: &[ValTensor<F>],
+        signature: Value<(Fq, Fq)>,
+    ) -> Result<BTreeMap<usize, ValTensor<F>>, Box<dyn Error>> {
+        let mut assigned = config.inputs.clone();
+        let (ecdsa, signer) = match (&config.ecdsa, &self.run_args.signer) {
+            (Some(ecdsa), Some(signer)) => (ecdsa, signer),
+            _ => return Ok(assigned),
+        };
+        let public_key = ecdsa::parse_public_key(signer)
+            .ok_or_else(|| GraphError::MisformedParams(signer.clone()))?;
+        // hashed inputs are copied from the cells they were hashed from
+        let mut values = vec![];
+        for (i, input) in inputs.iter().enumerate() {
+            let input = assigned.get(&i).unwrap_or(input);
+            values.extend(input.get_inner_tensor()?.into_iter());
+        }
+        let (cells, digest) = self.hash(config, layouter, &values)?;
+        ecdsa.layout(layouter, &digest, public_key, signature)?;
+        let mut cells = cells.into_iter();
+        for (i, input) in inputs.iter().enumerate() {
+            let mut input_cells: Tensor<ValType<F>> =
+                cells.by_ref().take(input.len()).map(ValType::from).into();
+            input_cells.reshape(input.dims());
+            assigned.insert(i, input_cells.into());
+        }
+        Ok(assigned)
+    }
+
+    /// Hashes each sample of the hashed outputs after the model's region, copying the outputs
+    /// from the cells the model assigned them to.
+    /// # Arguments
+    ///
+    /// * `config` - [ModelConfig] of the model.
+    /// * `layouter` - Halo2 Layouter.
+    /// * `outputs` - The outputs of each sample of the batch, as laid out by the model.
+    /// * `vars` - [ModelVars] for the model.
+    fn layout_hashed_outputs<F: FieldExt + TensorType>(
+        &self,
+        config: &ModelConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        outputs: &[Vec<ValTensor<F>>],
+        vars: &ModelVars<F>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (_, output_instances) = self.instance_offsets();
+        for (i, (visibility, instance)) in self
+            .output_visibility()
+            .iter()
+            .zip(output_instances)
+            .enumerate()
+        {
+            if let (true, Some(instance)) = (visibility.is_hashed(), instance) {
+                for (b, sample) in outputs.iter().enumerate() {
+                    self.hash_to_instance(
+                        config,
+                        layouter,
+                        &sample[i],
+                        None,
+                        &vars.instances[instance + b],
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Hashes `values` with the Poseidon (or SHA-256) chip and constrains the digest to equal
+    /// `instance`, returning the cells the values are assigned to. When given the path to the
+    /// leaf of the digest in a Merkle tree, the root of the tree is constrained instead.
+    fn hash_to_instance<F: FieldExt + TensorType>(
+        &self,
+        config: &ModelConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        values: &ValTensor<F>,
+        merkle_path: Option<&(usize, Vec<F>)>,
+        instance: &ValTensor<F>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Box<dyn Error>> {
+        let (cells, mut digest) = self.hash(config, layouter, &values.get_inner_tensor()?)?;
+        if let (Some(merkle), Some((index, siblings))) = (&config.merkle, merkle_path) {
+            digest = vec![merkle.layout(layouter, &digest[0], *index, siblings)?];
+        }
+        let instance = match instance {
+            ValTensor::Instance { inner, .. } => *inner,
+            _ => return Err(Box::new(GraphError::Visibility)),
+        };
+        for (row, digest) in digest.iter().enumerate() {
+            layouter.constrain_instance(digest.cell(), instance, row)?;
+        }
+        Ok(cells)
+    }
+
+    /// Hashes `values` with the Poseidon (or SHA-256) chip, returning the cells the values are
+    /// assigned to and the cells of the digest (one for Poseidon, and the upper and lower 128
+    /// bits for SHA-256).
+    fn hash<F: FieldExt + TensorType>(
+        &self,
+        config: &ModelConfig<F>,
+        layouter: &mut impl Layouter<F>,
+        values: &[ValType<F>],
+    ) -> Result<(Vec<AssignedCell<F, F>>, Vec<AssignedCell<F, F>>), Box<dyn Error>> {
+        match (&config.sha256, &config.poseidon) {
+            (Some(sha256), _) => {
+                let (cells, digest) = sha256.layout(layouter, values)?;
+                Ok((cells, digest.to_vec()))
+            }
+            (None, Some(poseidon)) => {
+                let (cells, digest) = poseidon.layout(layouter, values)?;
+                Ok((cells, vec![digest]))
+            }
+            (None, None) => Err(Box::new(GraphError::Visibility)),
+        }
+    }
+
+    /// Lays out the model over a single sample of the batch, and constrains its outputs, which
+    /// are returned.
+    /// # Arguments
+    ///
+    /// * `config` - [ModelConfig] of the model.
+    /// * `region` - Halo2 Region.
+    /// * `inputs` - The inputs of every sample of the batch, input major.
+    /// * `vars` - [ModelVars] for the model.
+    /// * `b` - The index of the sample in the batch.
+    /// * `offset` - Offset to assign.
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    fn layout_sample<F: FieldExt + TensorType>(
+        &self,
+        config: &mut ModelConfig<F>,
+        region: &mut Region<F>,
+        inputs: &[ValTensor<F>],
+        vars: &ModelVars<F>,
+        b: usize,
+        offset: &mut usize,
+    ) -> Result<Vec<ValTensor<F>>, halo2_proofs::plonk::Error> {
+        let batch_size = self.run_args.batch_size;
+        let num_inputs = inputs.len() / batch_size;
+        let (input_instances, output_instances) = self.instance_offsets();
+        let mut results = config.params.clone();
+        for (i, o) in self.model.inputs.iter().enumerate().take(num_inputs) {
+            let input = i * batch_size + b;
+            match (config.inputs.get(&input), input_instances[i]) {
+                (Some(hashed), _) => results.insert(o.node, hashed.clone()),
+                (None, Some(instance)) => {
+                    results.insert(o.node, vars.instances[instance + b].clone())
+                }
+                (None, None) => results.insert(o.node, inputs[input].clone()),
+            };
+        }
+
+        for (idx, config) in config.configs.iter() {
+            trace!("laying out offset {}", offset);
+            if let Some(vt) = self
+                .layout_config(region, &mut results, config, offset)
+                .map_err(|e| {
+                    error!("{}", e);
+                    halo2_proofs::plonk::Error::Synthesis
+                })?
+            {
+                // we get the max as for fused nodes this corresponds to the node output
+                results.insert(*idx, vt);
+                //only use with mock prover
+                if matches!(self.mode, Mode::Mock) {
+                    trace!(
+                        "------------ output node {:?}: {:?}",
+                        idx,
+                        results.get(idx).unwrap().show()
+                    );
+                }
+            }
+        }
+
+        let output_nodes = self.model.outputs.iter();
+        info!(
+            "model outputs are nodes: {:?}",
+            output_nodes.clone().map(|o| o.node).collect_vec()
+        );
+        let mut outputs = output_nodes
+            .map(|o| results.get(&o.node).unwrap().clone())
+            .collect_vec();
+
+        // pack outputs if need be
+        for (i, packed_output) in config.packed_outputs.iter_mut().enumerate() {
+            info!("packing outputs...");
+            outputs[i] = packed_output
+                .borrow_mut()
+                .layout(
+                    region,
+                    &outputs[i..i + 1],
+                    offset,
+                    PolyOp::Pack(self.run_args.pack_base, self.run_args.scale).into(),
+                )
+                .map_err(|e| {
+                    error!("{}", e);
+                    halo2_proofs::plonk::Error::Synthesis
+                })?
+                .unwrap();
+            // only use with mock prover
+            if matches!(self.mode, Mode::Mock) {
+                trace!("------------ packed output {:?}", outputs[i].show());
+            }
+        }
+
+        // only public outputs are checked against their instances, hashed ones being checked
+        // against their digests once the model is laid out
+        let _ = config
+            .range_checks
+            .iter()
+            .zip(outputs.clone())
+            .zip(output_instances)
+            .zip(self.output_visibility())
+            .filter_map(|(((range_check, output), instance), visibility)| {
+                match visibility.is_public() {
+                    true => instance.map(|instance| (range_check, output, instance)),
+                    false => None,
+                }
+            })
+            .map(|(range_check, output, instance)| {
+                range_check.borrow_mut().layout(
+                    region,
+                    &[output, vars.instances[instance + b].clone()],
+                    offset,
+                    PolyOp::RangeCheck(self.run_args.tolerance as i32).into(),
+                )
+            })
+            .collect_vec();
+        Ok(outputs)
+    }
+
     /// Assigns values to a single region, represented as a [NodeConfig].
     /// # Arguments
     ///
@@ -620,7 +2572,8 @@ impl Model {
                     .map(|i| {
                         let node = &self.nodes.get(i).unwrap();
                         match node.opkind {
-                            OpKind::Const => {
+                            // hashed and fixed params are already assigned, and are copied instead
+                            OpKind::Const if !inputs.contains_key(i) => {
                                 let val = node
                                     .const_value
                                     .clone()
@@ -669,6 +2622,48 @@ impl Model {
         Ok(self.model.output_outlets()?.to_vec())
     }
 
+    /// Returns the names of the computational graph's inputs
+    pub fn input_names(&self) -> Vec<String> {
+        Self::outlet_names(&self.model, &self.model.inputs)
+    }
+
+    /// Returns the names of the computational graph's outputs
+    pub fn output_names(&self) -> Vec<String> {
+        Self::outlet_names(&self.model, &self.model.outputs)
+    }
+
+    /// Returns the visibility of each of the computational graph's inputs
+    pub fn input_visibility(&self) -> Vec<Visibility> {
+        let names = self.input_names();
+        names
+            .iter()
+            .map(|n| self.visibility.of_input(n).clone())
+            .collect()
+    }
+
+    /// Returns the visibility of each of the computational graph's outputs
+    pub fn output_visibility(&self) -> Vec<Visibility> {
+        let names = self.output_names();
+        names
+            .iter()
+            .map(|n| self.visibility.of_output(n).clone())
+            .collect()
+    }
+
+    /// Returns the names of outlets of an Onnx graph, falling back to the names of their nodes
+    pub fn outlet_names(
+        model: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        outlets: &[OutletId],
+    ) -> Vec<String> {
+        outlets
+            .iter()
+            .map(|o| match model.outlet_label(*o) {
+                Some(label) => label.to_string(),
+                None => model.nodes[o.node].name.clone(),
+            })
+            .collect_vec()
+    }
+
     /// Returns the number of the computational graph's inputs
     pub fn num_inputs(&self) -> usize {
         let input_nodes = self.model.inputs.iter();
@@ -742,41 +2737,111 @@ impl Model {
         vec![num_inputs, num_outputs]
     }
 
-    /// Maximum number of input variables
-    pub fn total_var_len(&self) -> usize {
-        let mut maximum_var_len = 0;
-
-        let poly_ops: BTreeMap<&usize, &Node> = self
-            .nodes
+    /// Number of variables a node lays out, for a single sample of the batch
+    pub fn node_var_len(&self, node: &Node) -> usize {
+        let in_dims = node
+            .inputs
             .iter()
-            .filter(|(_, n)| n.opkind.is_poly())
-            .collect();
+            .map(|i| self.nodes.get(&i.node).unwrap().out_dims.clone())
+            .collect_vec();
+        match &node.opkind {
+            OpKind::Poly(p) => *p.circuit_shapes(in_dims).last().unwrap(),
+            OpKind::Lookup(l) => l.circuit_len(in_dims, node.out_dims.iter().product()),
+            _ => 0,
+        }
+    }
 
-        let _: Vec<_> = poly_ops
+    /// The lookup tables a node uses
+    pub fn node_lookups(&self, node: &Node) -> Vec<LookupOp> {
+        match &node.opkind {
+            OpKind::Poly(p) => [p.required_lookups(), p.fused_lookups()]
+                .concat()
+                .into_iter()
+                .unique()
+                .collect(),
+            OpKind::Lookup(l) => l.table_ops(),
+            _ => vec![],
+        }
+    }
+
+    /// Renders the graph of nodes in the DOT language, each node being annotated with its op, its
+    /// output scale and dims, the number of variables it lays out and the lookup tables it uses.
+    /// Nodes are shaded from green to red by the number of variables they lay out, relative to
+    /// the largest node.
+    pub fn to_dot(&self) -> String {
+        let escape = |s: String| s.replace('"', "\\\"").replace('\n', "\\n");
+        let max_var_len = self
+            .nodes
             .values()
-            .map(|n| match &n.opkind {
-                OpKind::Poly(p) => {
-                    let in_dims = n
-                        .inputs
-                        .iter()
-                        .map(|i| self.nodes.get(&i.node).unwrap().out_dims.clone());
-                    let layout_shape = p.circuit_shapes(in_dims.collect_vec());
-                    maximum_var_len += layout_shape.last().unwrap();
+            .map(|n| self.node_var_len(n))
+            .max()
+            .unwrap_or(0);
+        let tables = self
+            .nodes
+            .values()
+            .flat_map(|n| self.node_lookups(n))
+            .collect::<BTreeSet<_>>();
+
+        let mut dot = String::from("digraph model {\n");
+        dot.push_str(&format!(
+            "  label=\"total var len: {}, lookup tables: {} ({} rows each)\";\n",
+            self.total_var_len(),
+            tables.len(),
+            1_usize << self.run_args.bits
+        ));
+        dot.push_str("  node [shape=box, style=filled, fontname=\"monospace\"];\n");
+        for (idx, node) in self.nodes.iter() {
+            let var_len = self.node_var_len(node);
+            let op = match &node.opkind {
+                OpKind::Lookup(l) => format!("{:?}", l),
+                opkind => opkind.to_string(),
+            };
+            let lookups = self
+                .node_lookups(node)
+                .iter()
+                .map(|l| format!("{:?}", l))
+                .join(", ");
+            let label = format!(
+                "{}: {}\nscale: {}, dims: {:?}\nvar len: {}\nlookups: {}",
+                idx,
+                op,
+                node.out_scale,
+                node.out_dims,
+                var_len,
+                if lookups.is_empty() {
+                    "none"
+                } else {
+                    lookups.as_str()
                 }
-                _ => panic!(),
-            })
-            .collect();
+            );
+            // hue goes from green (0.33) for the cheapest nodes to red (0) for the largest
+            let hue = 0.33 * (1.0 - var_len as f64 / max_var_len.max(1) as f64);
+            dot.push_str(&format!(
+                "  n{} [label=\"{}\", fillcolor=\"{:.3} 0.4 1.0\"];\n",
+                idx,
+                escape(label),
+                hue
+            ));
+            for input in node.inputs.iter() {
+                if let Some(source) = self.nodes.get(&input.node) {
+                    dot.push_str(&format!(
+                        "  n{} -> n{} [label=\"{:?}\"];\n",
+                        input.node, idx, source.out_dims
+                    ));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
 
-        let lookup_ops: BTreeMap<&usize, &Node> = self
+    /// Maximum number of input variables
+    pub fn total_var_len(&self) -> usize {
+        let mut maximum_var_len = self
             .nodes
-            .iter()
-            .filter(|(_, n)| n.opkind.is_lookup())
-            .collect();
-
-        for op in lookup_ops {
-            let len = (*op.1.out_dims).iter().product::<usize>();
-            maximum_var_len += len;
-        }
+            .values()
+            .map(|n| self.node_var_len(n))
+            .sum::<usize>();
 
         let output_lens: usize = self
             .output_shapes()
@@ -793,26 +2858,102 @@ impl Model {
         if self.run_args.pack_base > 1 {
             maximum_var_len += output_lens;
         }
-        if matches!(self.visibility.output, Visibility::Public) {
+        let public_outputs = self.output_visibility().iter().any(|v| v.is_public());
+        if public_outputs {
             maximum_var_len += output_lens;
         }
-        if matches!(self.visibility.output, Visibility::Public) {
+        if public_outputs {
             maximum_var_len += input_lens;
         }
 
-        maximum_var_len
+        // the model is laid out once per sample of the batch, and fixed params once ahead of it
+        let mut params_len = 0;
+        if self.visibility.params.is_fixed() {
+            params_len = self.params().values().map(|p| p.len()).sum::<usize>();
+        }
+        maximum_var_len * self.run_args.batch_size + params_len
     }
 
-    /// Number of instances used by the circuit
+    /// Number of instances used by the circuit, the last of which is the commitment to the params
+    /// when these are hashed. Hashed inputs and outputs have a single instance holding the
+    /// commitment to each of their samples (split into its upper and lower 128 bits with sha256).
     pub fn instance_shapes(&self) -> Vec<Vec<usize>> {
-        // for now the number of instances corresponds to the number of graph / model outputs
-        let mut instance_shapes = vec![];
-        if self.visibility.input.is_public() {
-            instance_shapes.extend(self.input_shapes());
-        }
-        if self.visibility.output.is_public() {
-            instance_shapes.extend(self.output_shapes());
-        }
-        instance_shapes
+        // for now the number of instances corresponds to the number of public graph / model inputs and outputs
+        // each sample of the batch has its own instances, input (or output) major
+        let inputs = self.input_shapes().into_iter().zip(self.input_visibility());
+        let outputs = self
+            .output_shapes()
+            .into_iter()
+            .zip(self.output_visibility());
+        inputs
+            .chain(outputs)
+            .filter_map(|(shape, visibility)| match visibility {
+                Visibility::Public => Some(shape),
+                Visibility::Hashed => match self.run_args.hash_function {
+                    HashFunction::Poseidon => Some(vec![1]),
+                    HashFunction::Sha256 => Some(vec![2]),
+                },
+                _ => None,
+            })
+            .flat_map(|shape| vec![shape; self.run_args.batch_size])
+            .chain(self.visibility.params.is_hashed().then(|| vec![1]))
+            .collect_vec()
+    }
+
+    /// The values of the model's params (ie. its constant nodes), by node index
+    fn params(&self) -> BTreeMap<usize, Tensor<i128>> {
+        self.nodes
+            .iter()
+            .filter(|(_, n)| n.opkind.is_const())
+            .filter_map(|(i, n)| n.const_value.clone().map(|v| (*i, v)))
+            .collect()
+    }
+
+    /// The Poseidon commitment to the model's params, which is the last public instance when
+    /// params are hashed
+    pub fn params_digest<F: FieldExt>(&self) -> F {
+        let values = self
+            .params()
+            .values()
+            .flat_map(|p| p.iter().map(|x| i128_to_felt::<F>(*x)).collect_vec())
+            .collect_vec();
+        PoseidonSpec::<F>::new().hash(&values)
+    }
+
+    /// Index of the first instance of each of the graph's inputs and outputs, which is `None` for
+    /// those which are private. Public (or hashed) inputs come before public (or hashed) outputs,
+    /// and each sample of the batch has its own instance.
+    fn instance_offsets(&self) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let mut next = 0;
+        let mut offsets = |visibility: Vec<Visibility>| {
+            visibility
+                .iter()
+                .map(|v| {
+                    (v.is_public() || v.is_hashed()).then(|| {
+                        next += self.run_args.batch_size;
+                        next - self.run_args.batch_size
+                    })
+                })
+                .collect_vec()
+        };
+        let inputs = offsets(self.input_visibility());
+        let outputs = offsets(self.output_visibility());
+        (inputs, outputs)
+    }
+}
+
+/// Inserts the base gate config of `fixed_flag`, sharing the lookup tables of the configs
+/// inserted before it such that each table is configured once across the circuit, even where
+/// nodes with fixed and advice params look up the same op.
+fn insert_base_gate<F: FieldExt + TensorType>(
+    base_gates: &mut BTreeMap<bool, Rc<RefCell<PolyConfig<F>>>>,
+    fixed_flag: bool,
+    mut config: PolyConfig<F>,
+) -> Rc<RefCell<PolyConfig<F>>> {
+    if let Some(other) = base_gates.values().next() {
+        config.share_tables(&other.borrow());
     }
+    let config = Rc::new(RefCell::new(config));
+    base_gates.insert(fixed_flag, config.clone());
+    config
 }
@@ -1,9 +1,17 @@
-use super::utilities::{node_output_shapes, scale_to_multiplier, vector_to_quantized};
+use super::utilities::{
+    multiplier_to_scale, node_output_shapes, scale_to_multiplier, vector_to_quantized,
+};
 use crate::circuit::BaseConfig;
+use crate::circuit::ConvLowering;
 use crate::circuit::LookupOp;
 use crate::circuit::Op as PolyOp;
 use crate::circuit::OpKind;
+use crate::circuit::{ScanInput, ScanOutput, ScanParams, Subgraph, SubgraphInput, SubgraphNode};
 use crate::graph::GraphError;
+use crate::tensor::ops::{
+    expand_dims, gather_dims, slice_indices, Bitwise, DepthToSpaceMode, EinsumIndices, NmsParams,
+    PadFill, Reduction,
+};
 use crate::tensor::Tensor;
 use crate::tensor::TensorType;
 use anyhow::Result;
@@ -12,6 +20,7 @@ use halo2_proofs::arithmetic::FieldExt;
 use itertools::Itertools;
 use log::{info, trace, warn};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
@@ -19,18 +28,33 @@ use std::ops::Deref;
 use std::rc::Rc;
 use tabled::Tabled;
 use tract_onnx;
-use tract_onnx::prelude::{DatumType, InferenceFact, Node as OnnxNode, OutletId};
+use tract_onnx::ops::array::topk::Topk;
+use tract_onnx::ops::cumsum::CumSum;
+use tract_onnx::ops::d2s::{DepthToSpace, DepthToSpaceMode as TractDepthToSpaceMode};
+use tract_onnx::ops::logic::If;
+use tract_onnx::ops::math::clip::Clip11;
+use tract_onnx::ops::math::gemm::Gemm;
+use tract_onnx::ops::nn::conv_transpose::ConvTranspose;
+use tract_onnx::ops::nn::non_max_suppression::{BoxRepr, NonMaxSuppression as TractNms};
+use tract_onnx::ops::rec::gru::GRU;
+use tract_onnx::ops::resize::{CoordTransformer, Interpolator, Nearest, Resize};
+use tract_onnx::ops::s2d::SpaceToDepth;
+use tract_onnx::prelude::{DatumType, Graph, InferenceFact, Node as OnnxNode, OutletId};
 use tract_onnx::tract_hir::{
     infer::Factoid,
     internal::InferenceOp,
     ops::activations::LeakyRelu,
-    ops::array::{Pad, PadMode},
-    ops::cnn::{Conv, PoolSpec, SumPool},
+    ops::array::{Gather, GatherElements, Pad, PadMode, PermuteAxes, StridedSlice},
+    ops::cnn::{Conv, MaxPool, PoolSpec, SumPool},
     ops::expandable::Expansion,
-    ops::nn::DataFormat,
+    ops::nn::{DataFormat, Reduce, Reducer},
+    ops::scan::InferenceScan,
     tract_core::ops::{
+        array::{GatherNd, ScatterElements, ScatterNd},
         cnn::{conv::KernelFormat, PaddingSpec},
+        einsum::EinSum,
         konst::Const,
+        scan::{InputMapping, StateInitializer},
     },
 };
 
@@ -247,6 +271,70 @@ impl Node {
                         }
                     }
 
+                    LookupOp::Exp { .. } | LookupOp::Ln { .. } => {
+                        let input_node = &inputs[0];
+                        let in_mult = scale_to_multiplier(input_node.out_scale);
+                        let out_mult = scale_to_multiplier(scale);
+                        let scales = (in_mult as usize, out_mult as usize);
+                        let (op, output_max) = match s {
+                            LookupOp::Exp { .. } => (
+                                LookupOp::Exp { scales },
+                                out_mult * (input_node.output_max / in_mult).exp(),
+                            ),
+                            // the logarithm is largest in magnitude for the largest or the
+                            // smallest positive input
+                            _ => (
+                                LookupOp::Ln { scales },
+                                out_mult
+                                    * (input_node.output_max / in_mult)
+                                        .ln()
+                                        .abs()
+                                        .max(in_mult.ln()),
+                            ),
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Lookup(op),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale: scale,
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+
+                    LookupOp::Recip { .. } | LookupOp::Rsqrt { .. } | LookupOp::Erf { .. } => {
+                        let input_node = &inputs[0];
+                        let in_mult = scale_to_multiplier(input_node.out_scale);
+                        let out_mult = scale_to_multiplier(scale);
+                        let scales = (in_mult as usize, out_mult as usize);
+                        // the reciprocals are largest for the smallest nonzero input
+                        let (op, output_max) = match s {
+                            LookupOp::Recip { .. } => {
+                                (LookupOp::Recip { scales }, out_mult * in_mult)
+                            }
+                            LookupOp::Rsqrt { .. } => {
+                                (LookupOp::Rsqrt { scales }, out_mult * in_mult.sqrt())
+                            }
+                            _ => (LookupOp::Erf { scales }, out_mult),
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Lookup(op),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale: scale,
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+
                     LookupOp::ReLU { .. } => {
                         let input_node = &inputs[0];
                         let scale_diff = input_node.out_scale - scale;
@@ -369,36 +457,215 @@ impl Node {
                         let mut input_outlets = node.inputs.clone();
                         input_outlets.pop();
 
-                        let denom = inputs[1].raw_const_value.as_ref().unwrap()[0];
+                        let denom = inputs[1].const_scalar().ok_or(GraphError::NonConstantDiv)?;
 
-                        let scale_diff = input_node.out_scale - scale;
-                        // We can also consider adjusting the scale of all inputs and the output in a more custom way.
-                        let output_max: f32;
-                        if scale_diff > 0 {
-                            let mult = scale_to_multiplier(scale_diff);
-                            opkind = OpKind::Lookup(LookupOp::Div {
-                                denom: F32(denom * mult),
-                            }); // now the input will be scaled down to match
-                            output_max = input_node.output_max / (denom * mult);
+                        // integer division truncates, and keeps the quotient at the input's scale
+                        let is_integer = node.outputs[0]
+                            .fact
+                            .datum_type
+                            .concretize()
+                            .map_or(false, |dt| dt.is_integer());
+                        if is_integer {
+                            let mult = scale_to_multiplier(input_node.out_scale);
+                            let int_denom = (denom * mult).round() as i128;
+                            if int_denom == 0 {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "division by zero".to_string(),
+                                )));
+                            }
+
+                            Node {
+                                idx,
+                                opkind: OpKind::Lookup(LookupOp::IntDiv {
+                                    denom: int_denom,
+                                    scale: mult as usize,
+                                }),
+                                inputs: input_outlets,
+                                in_dims: vec![input_node.out_dims.clone()],
+                                out_dims: input_node.out_dims.clone(),
+                                in_scale: input_node.out_scale,
+                                out_scale: input_node.out_scale,
+                                output_max: input_node.output_max / denom.abs(),
+                                ..Default::default()
+                            }
                         } else {
-                            opkind = OpKind::Lookup(LookupOp::Div { denom: F32(denom) }); // now the input will be scaled down to match
-                            output_max = input_node.output_max / (denom);
+                            let scale_diff = input_node.out_scale - scale;
+                            // We can also consider adjusting the scale of all inputs and the output in a more custom way.
+                            let output_max: f32;
+                            if scale_diff > 0 {
+                                let mult = scale_to_multiplier(scale_diff);
+                                opkind = OpKind::Lookup(LookupOp::Div {
+                                    denom: F32(denom * mult),
+                                }); // now the input will be scaled down to match
+                                output_max = input_node.output_max / (denom * mult);
+                            } else {
+                                opkind = OpKind::Lookup(LookupOp::Div { denom: F32(denom) }); // now the input will be scaled down to match
+                                output_max = input_node.output_max / (denom);
+                            }
+
+                            Node {
+                                idx,
+                                opkind,
+                                inputs: input_outlets,
+                                in_dims: vec![input_node.out_dims.clone()],
+                                out_dims: input_node.out_dims.clone(),
+                                // in scale is the same as the input
+                                in_scale: input_node.out_scale,
+                                // same for the output scale
+                                out_scale: scale,
+                                output_max,
+                                ..Default::default()
+                            }
+                        }
+                    }
+                    LookupOp::Mod { fmod, .. } => {
+                        if (inputs[1].out_dims.clone() != [1])
+                            || !matches!(inputs[1].opkind, OpKind::Const)
+                        {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "mod needs a constant divisor".to_string(),
+                            )));
+                        }
+
+                        let input_node = &inputs[0];
+                        let mut input_outlets = node.inputs.clone();
+                        input_outlets.pop();
+
+                        // the divisor is brought to the input's scale, so the remainder is exact
+                        let denom = inputs[1].const_scalar().ok_or_else(|| {
+                            GraphError::MisformedParams("mod needs a constant divisor".to_string())
+                        })?;
+                        let mult = scale_to_multiplier(input_node.out_scale);
+                        let int_denom = (denom * mult).round() as i128;
+                        if int_denom == 0 {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "mod by zero".to_string(),
+                            )));
                         }
 
                         Node {
                             idx,
-                            opkind,
+                            opkind: OpKind::Lookup(LookupOp::Mod {
+                                denom: int_denom,
+                                fmod: *fmod,
+                            }),
                             inputs: input_outlets,
                             in_dims: vec![input_node.out_dims.clone()],
                             out_dims: input_node.out_dims.clone(),
-                            // in scale is the same as the input
                             in_scale: input_node.out_scale,
-                            // same for the output scale
-                            out_scale: scale,
-                            output_max,
+                            out_scale: input_node.out_scale,
+                            output_max: int_denom.abs() as f32,
+                            ..Default::default()
+                        }
+                    }
+                    LookupOp::MaxPool2D { .. } => {
+                        if let Some(maxpool_node) = node.op().downcast_ref::<MaxPool>() {
+                            if maxpool_node.pool_spec.kernel_shape.len() == 3 {
+                                let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                                return Self::new_pool3d(
+                                    idx,
+                                    node.inputs.clone(),
+                                    input_node,
+                                    &maxpool_node.pool_spec,
+                                    true,
+                                );
+                            }
+                        }
+                        // input_nodes come in all shapes and sizes we gotta homogenize, especially for 2D (single channel images)
+                        let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                        inputs[0] = Self::format_3d_inputs(input_node)?.clone();
+
+                        let input_node = &inputs[0];
+
+                        // Extract the padding and stride layer hyperparams
+                        let op = Box::new(node.op());
+                        let maxpool_node: &MaxPool = match op.downcast_ref() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+
+                        let pool_spec: &PoolSpec = &maxpool_node.pool_spec;
+
+                        // only support pytorch type formatting for now
+                        if pool_spec.data_format != DataFormat::NCHW {
+                            return Err(Box::new(GraphError::MissingParams(
+                                "data in wrong format".to_string(),
+                            )));
+                        }
+
+                        // strides default to 1 when left unspecified
+                        let stride = match &pool_spec.strides {
+                            Some(s) => s.to_vec(),
+                            None => vec![1; pool_spec.kernel_shape.len()],
+                        };
+                        let padding = match &pool_spec.padding {
+                            PaddingSpec::Explicit(p, _, _) => p,
+                            _ => {
+                                return Err(Box::new(GraphError::MissingParams(
+                                    "padding".to_string(),
+                                )));
+                            }
+                        };
+                        let kernel_shape = &pool_spec.kernel_shape;
+
+                        let (padding_h, padding_w, stride_h, stride_w) =
+                            (padding[0], padding[1], stride[0], stride[1]);
+                        let (kernel_height, kernel_width) = (kernel_shape[0], kernel_shape[1]);
+
+                        let input_channels = input_node.out_dims[0];
+                        let input_height = input_node.out_dims[1];
+                        let input_width = input_node.out_dims[2];
+
+                        let out_height =
+                            (input_height + 2 * padding_h - kernel_height) / stride_h + 1;
+                        let out_width = (input_width + 2 * padding_w - kernel_width) / stride_w + 1;
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Lookup(LookupOp::MaxPool2D {
+                                padding: (padding_h, padding_w),
+                                stride: (stride_h, stride_w),
+                                pool_dims: (kernel_height, kernel_width),
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: vec![input_channels, out_height, out_width],
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    LookupOp::Floor { .. } | LookupOp::Ceil { .. } | LookupOp::Round { .. } => {
+                        // rounds to whole numbers, so the output stays at the input's scale
+                        let input_node = &inputs[0];
+                        let mult = scale_to_multiplier(input_node.out_scale);
+                        let scale = mult as usize;
+                        let op = match s {
+                            LookupOp::Floor { .. } => LookupOp::Floor { scale },
+                            LookupOp::Ceil { .. } => LookupOp::Ceil { scale },
+                            _ => LookupOp::Round { scale },
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Lookup(op),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max + mult,
                             ..Default::default()
                         }
                     }
+                    _ => {
+                        return Err(Box::new(GraphError::MisformedParams(
+                            "lookup should not be configured here".to_string(),
+                        )));
+                    }
                 }
             }
             OpKind::Poly(ref s) => {
@@ -409,68 +676,222 @@ impl Node {
                         )));
                     }
                     PolyOp::Pad(..) => {
-                        let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
-                        // we only support padding for 3D images
-                        inputs[0] = Self::format_3d_inputs(input_node)?.clone();
-
                         let pad_node: &Pad = match node.op().downcast_ref::<Pad>() {
                             Some(b) => b,
                             None => {
                                 return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
                             }
                         };
-                        // we only support constant 0 padding
-                        if pad_node.mode
-                            != PadMode::Constant(tract_onnx::prelude::Arc::new(
+                        let padding_len = pad_node.pads.len();
+                        let is_zero_padding = pad_node.mode
+                            == PadMode::Constant(tract_onnx::prelude::Arc::new(
                                 tract_onnx::prelude::Tensor::zero::<f32>(&[])?,
-                            ))
+                            ));
+                        // symmetric zero padding of the height and width of an image
+                        let is_image_padding = is_zero_padding
+                            && padding_len >= 2
+                            && pad_node.pads.iter().enumerate().all(|(i, pad_params)| {
+                                (pad_params.0 == pad_params.1)
+                                    && ((i >= padding_len - 2) || (pad_params.0 == 0))
+                            });
+
+                        if is_image_padding && !inputs[0].opkind.is_const() {
+                            let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                            inputs[0] = Self::format_3d_inputs(input_node)?.clone();
+                            let input_node = &inputs[0];
+
+                            let (padding_h, padding_w) = (
+                                pad_node.pads[padding_len - 2].0,
+                                pad_node.pads[padding_len - 1].0,
+                            );
+
+                            let input_channels = input_node.out_dims[0];
+
+                            let out_height = input_node.out_dims[1] + 2 * padding_h;
+                            let out_width = input_node.out_dims[2] + 2 * padding_w;
+
+                            Node {
+                                idx,
+                                opkind: OpKind::Poly(PolyOp::Pad(padding_h, padding_w)),
+                                inputs: node.inputs.clone(),
+                                in_dims: vec![input_node.out_dims.clone()],
+                                out_dims: vec![input_channels, out_height, out_width],
+                                in_scale: input_node.out_scale,
+                                out_scale: input_node.out_scale,
+                                output_max: input_node.output_max,
+                                ..Default::default()
+                            }
+                        } else {
+                            let input_node = &inputs[0];
+                            let rank = input_node.out_dims.len();
+                            // the onnx pads include the batch dim, which the input's dims don't
+                            if padding_len < rank
+                                || pad_node.pads[..padding_len - rank]
+                                    .iter()
+                                    .any(|p| p.0 != 0 || p.1 != 0)
+                            {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "ezkl doesn't support padding the batch dimension".to_string(),
+                                )));
+                            }
+                            let pads = pad_node.pads[padding_len - rank..].to_vec();
+
+                            let mut output_max = input_node.output_max;
+                            let fill =
+                                match &pad_node.mode {
+                                    PadMode::Constant(c) => {
+                                        // the constant is quantized at the input's scale
+                                        let c = (c.cast_to_scalar::<f32>()?
+                                            * scale_to_multiplier(input_node.out_scale))
+                                        .round();
+                                        output_max = output_max.max(c.abs());
+                                        PadFill::Constant(c as i128)
+                                    }
+                                    PadMode::Reflect => {
+                                        if pads.iter().zip(&input_node.out_dims).any(
+                                            |((before, after), len)| before >= len || after >= len,
+                                        ) {
+                                            return Err(Box::new(GraphError::MisformedParams(
+                                            "reflect padding should be smaller than the padded axis"
+                                                .to_string(),
+                                        )));
+                                        }
+                                        PadFill::Reflect
+                                    }
+                                    PadMode::Edge => PadFill::Edge,
+                                };
+
+                            let out_dims = pads
+                                .iter()
+                                .zip(&input_node.out_dims)
+                                .map(|((before, after), len)| before + len + after)
+                                .collect_vec();
+
+                            Node {
+                                idx,
+                                opkind: OpKind::Poly(PolyOp::PadWithFill { pads, fill }),
+                                inputs: node.inputs.clone(),
+                                in_dims: vec![input_node.out_dims.clone()],
+                                out_dims,
+                                in_scale: input_node.out_scale,
+                                out_scale: input_node.out_scale,
+                                output_max,
+                                ..Default::default()
+                            }
+                        }
+                    }
+                    PolyOp::Dot => todo!(),
+                    PolyOp::Conv { .. } if inputs[1].out_dims.len() == 5 => {
+                        let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                        inputs[0] = Self::format_4d_inputs(input_node)?.clone();
+
+                        let (input_node, weight_node) = (&inputs[0], &inputs[1]);
+
+                        // Extract the padding and stride layer hyperparams
+                        let op = Box::new(node.op());
+
+                        let conv_node: &Conv = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+
+                        if (conv_node.data_format != DataFormat::NCHW)
+                            || (conv_node.kernel_fmt != KernelFormat::OIHW)
                         {
                             return Err(Box::new(GraphError::MisformedParams(
-                                "pad mode or pad type".to_string(),
+                                "data or kernel in wrong format".to_string(),
                             )));
                         }
 
-                        let padding_len = pad_node.pads.len();
+                        if conv_node.group.unwrap_or(1) != 1 {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "grouped 3d conv".to_string(),
+                            )));
+                        }
 
-                        // we only support symmetrical padding that affects the last 2 dims (height and width params)
-                        for (i, pad_params) in pad_node.pads.iter().enumerate() {
-                            if (i < padding_len - 2) && ((pad_params.0 != 0) || (pad_params.1 != 0))
-                            {
-                                return Err(Box::new(GraphError::MisformedParams(
-                                    "ezkl currently only supports padding height and width dimensions".to_string(),
+                        let stride = match conv_node.strides.clone() {
+                            Some(s) => s,
+                            None => {
+                                return Err(Box::new(GraphError::MissingParams(
+                                    "strides".to_string(),
                                 )));
                             }
-                            if pad_params.0 != pad_params.1 {
-                                return Err(Box::new(GraphError::MisformedParams(
-                                    "ezkl currently only supports symmetric padding".to_string(),
+                        };
+                        let padding = match &conv_node.padding {
+                            PaddingSpec::Explicit(p, _, _) => p,
+                            _ => {
+                                return Err(Box::new(GraphError::MissingParams(
+                                    "padding".to_string(),
                                 )));
                             }
+                        };
+
+                        if inputs.len() == 3 {
+                            let bias_node = &inputs[2];
+                            let scale_diff =
+                                weight_node.out_scale + input_node.out_scale - bias_node.out_scale;
+                            let mut bias_node = other_nodes.get_mut(&node.inputs[2].node).unwrap();
+                            bias_node = Self::scale_up_const_node(bias_node, scale + scale_diff)?;
+                            if (input_node.out_scale + weight_node.out_scale) != bias_node.out_scale
+                            {
+                                return Err(Box::new(GraphError::RescalingError(opkind)));
+                            }
                         }
 
-                        let (padding_h, padding_w) = (
-                            pad_node.pads[padding_len - 2].0,
-                            pad_node.pads[padding_len - 1].0,
-                        );
+                        let oidhw = weight_node.out_dims.clone();
+                        let (out_channels, _, kernel_depth, kernel_height, kernel_width) =
+                            (oidhw[0], oidhw[1], oidhw[2], oidhw[3], oidhw[4]);
 
-                        let input_channels = input_node.out_dims[0];
+                        let (input_depth, input_height, input_width) = (
+                            input_node.out_dims[1],
+                            input_node.out_dims[2],
+                            input_node.out_dims[3],
+                        );
 
-                        let out_height = input_node.out_dims[1] + 2 * padding_h;
-                        let out_width = input_node.out_dims[2] + 2 * padding_w;
+                        let out_depth =
+                            (input_depth + 2 * padding[0] - kernel_depth) / stride[0] + 1;
+                        let out_height =
+                            (input_height + 2 * padding[1] - kernel_height) / stride[1] + 1;
+                        let out_width =
+                            (input_width + 2 * padding[2] - kernel_width) / stride[2] + 1;
 
                         Node {
                             idx,
-                            opkind: OpKind::Poly(PolyOp::Pad(padding_h, padding_w)),
+                            opkind: OpKind::Poly(PolyOp::Conv3D {
+                                padding: (padding[0], padding[1], padding[2]),
+                                stride: (stride[0], stride[1], stride[2]),
+                            }),
                             inputs: node.inputs.clone(),
                             in_dims: vec![input_node.out_dims.clone()],
-                            out_dims: vec![input_channels, out_height, out_width],
+                            out_dims: vec![out_channels, out_depth, out_height, out_width],
                             in_scale: input_node.out_scale,
-                            out_scale: input_node.out_scale,
-                            output_max: input_node.output_max,
+                            out_scale: weight_node.out_scale + input_node.out_scale,
+                            output_max: input_node.output_max
+                                * weight_node.output_max
+                                * ((kernel_depth * kernel_height * kernel_width) as f32),
                             ..Default::default()
                         }
                     }
-                    PolyOp::Dot => todo!(),
+
                     PolyOp::Conv { .. } => {
+                        // QLinearConv is parsed as a Conv carrying its quantization params as inputs
+                        if inputs.len() >= 8 {
+                            node.inputs =
+                                Self::format_qlinear_conv_inputs(&node.inputs, other_nodes, scale)?;
+                            inputs = node
+                                .inputs
+                                .iter()
+                                .map(|i| other_nodes.get(&i.node).unwrap().clone())
+                                .collect();
+                        }
+
                         let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
                         inputs[0] = Self::format_3d_inputs(input_node)?.clone();
 
@@ -517,17 +938,25 @@ impl Node {
                         };
 
                         if inputs.len() == 3 {
-                            let bias_node = &inputs[2];
-                            let scale_diff =
-                                weight_node.out_scale + input_node.out_scale - bias_node.out_scale;
+                            // the bias of a quantized conv already sits at the accumulator scale
                             let mut bias_node = other_nodes.get_mut(&node.inputs[2].node).unwrap();
-                            bias_node = Self::scale_up_const_node(bias_node, scale + scale_diff)?;
+                            bias_node = Self::scale_up_const_node(
+                                bias_node,
+                                input_node.out_scale + weight_node.out_scale,
+                            )?;
                             if (input_node.out_scale + weight_node.out_scale) != bias_node.out_scale
                             {
                                 return Err(Box::new(GraphError::RescalingError(opkind)));
                             }
                         }
 
+                        let group = conv_node.group.unwrap_or(1);
+                        if input_node.out_dims[0] != weight_node.out_dims[1] * group {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "conv group".to_string(),
+                            )));
+                        }
+
                         let oihw = weight_node.out_dims.clone();
                         let (out_channels, _, kernel_height, kernel_width) =
                             (oihw[0], oihw[1], oihw[2], oihw[3]);
@@ -547,6 +976,8 @@ impl Node {
                             opkind: OpKind::Poly(PolyOp::Conv {
                                 padding: (padding_h, padding_w),
                                 stride: (stride_h, stride_w),
+                                group,
+                                lowering: ConvLowering::Direct,
                             }),
                             inputs: node.inputs.clone(),
                             in_dims: vec![input_node.out_dims.clone()],
@@ -560,23 +991,148 @@ impl Node {
                         }
                     }
 
-                    PolyOp::SumPool { .. } => {
-                        // input_nodes come in all shapes and sizes we gotta homogenize, especially for 2D (single channel images)
+                    PolyOp::DeConv { .. } => {
                         let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
                         inputs[0] = Self::format_3d_inputs(input_node)?.clone();
 
-                        let input_node = &inputs[0];
+                        let (input_node, weight_node) = (&inputs[0], &inputs[1]);
 
-                        // Extract the padding and stride layer hyperparams
+                        // Extract the padding, output padding and stride layer hyperparams
                         let op = Box::new(node.op());
-                        let sumpool_node: &SumPool = match op.downcast_ref() {
+
+                        let deconv_node: &ConvTranspose = match op.downcast_ref() {
                             Some(b) => b,
                             None => {
                                 return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
                             }
                         };
 
-                        let pool_spec: &PoolSpec = &sumpool_node.pool_spec;
+                        if deconv_node.group != 1 {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "grouped deconvolution".to_string(),
+                            )));
+                        }
+
+                        if let Some(dilations) = &deconv_node.dilations {
+                            if dilations.iter().any(|d| *d != 1) {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "dilated deconvolution".to_string(),
+                                )));
+                            }
+                        }
+
+                        let stride = match &deconv_node.strides {
+                            Some(s) => s.to_vec(),
+                            None => vec![1, 1],
+                        };
+                        let padding = match &deconv_node.padding_spec {
+                            PaddingSpec::Explicit(p, _, _) => p.to_vec(),
+                            PaddingSpec::Valid => vec![0, 0],
+                            _ => {
+                                return Err(Box::new(GraphError::MissingParams(
+                                    "padding".to_string(),
+                                )));
+                            }
+                        };
+                        let output_padding = match &deconv_node.adjustments {
+                            Some(a) => a.to_vec(),
+                            None => vec![0, 0],
+                        };
+
+                        if inputs.len() == 3 {
+                            let bias_node = &inputs[2];
+                            let scale_diff =
+                                weight_node.out_scale + input_node.out_scale - bias_node.out_scale;
+                            let mut bias_node = other_nodes.get_mut(&node.inputs[2].node).unwrap();
+                            bias_node = Self::scale_up_const_node(bias_node, scale + scale_diff)?;
+                            if (input_node.out_scale + weight_node.out_scale) != bias_node.out_scale
+                            {
+                                return Err(Box::new(GraphError::RescalingError(opkind)));
+                            }
+                        }
+
+                        // deconv kernels are laid out as (in channels, out channels, height, width)
+                        let iohw = weight_node.out_dims.clone();
+                        let (out_channels, kernel_height, kernel_width) =
+                            (iohw[1], iohw[2], iohw[3]);
+
+                        let (padding_h, padding_w, stride_h, stride_w) =
+                            (padding[0], padding[1], stride[0], stride[1]);
+                        let (output_padding_h, output_padding_w) =
+                            (output_padding[0], output_padding[1]);
+
+                        let input_height = input_node.out_dims[1];
+                        let input_width = input_node.out_dims[2];
+
+                        let out_height = (input_height - 1) * stride_h + kernel_height
+                            - 2 * padding_h
+                            + output_padding_h;
+                        let out_width = (input_width - 1) * stride_w + kernel_width - 2 * padding_w
+                            + output_padding_w;
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::DeConv {
+                                padding: (padding_h, padding_w),
+                                output_padding: (output_padding_h, output_padding_w),
+                                stride: (stride_h, stride_w),
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: vec![out_channels, out_height, out_width],
+                            in_scale: input_node.out_scale,
+                            out_scale: weight_node.out_scale + input_node.out_scale,
+                            output_max: input_node.output_max
+                                * weight_node.output_max
+                                * ((kernel_height * kernel_width) as f32),
+                            ..Default::default()
+                        }
+                    }
+
+                    PolyOp::SumPool { .. } => {
+                        if let Some(sumpool_node) = node.op().downcast_ref::<SumPool>() {
+                            if sumpool_node.pool_spec.kernel_shape.len() == 3 {
+                                if !sumpool_node.normalize {
+                                    return Err(Box::new(GraphError::UnsupportedOp));
+                                }
+                                // a single division lookup can't account for a varying number of
+                                // elements per window
+                                let padded = matches!(
+                                    &sumpool_node.pool_spec.padding,
+                                    PaddingSpec::Explicit(p, _, _) if p.iter().any(|p| *p > 0)
+                                );
+                                if !sumpool_node.count_include_pad && padded {
+                                    return Err(Box::new(GraphError::MisformedParams(
+                                        "average pooling with padding requires count_include_pad"
+                                            .to_string(),
+                                    )));
+                                }
+                                let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                                return Self::new_pool3d(
+                                    idx,
+                                    node.inputs.clone(),
+                                    input_node,
+                                    &sumpool_node.pool_spec,
+                                    false,
+                                );
+                            }
+                        }
+                        // input_nodes come in all shapes and sizes we gotta homogenize, especially for 2D (single channel images)
+                        let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                        inputs[0] = Self::format_3d_inputs(input_node)?.clone();
+
+                        let input_node = &inputs[0];
+
+                        // Extract the padding and stride layer hyperparams
+                        let op = Box::new(node.op());
+                        let sumpool_node: &SumPool = match op.downcast_ref() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+
+                        let pool_spec: &PoolSpec = &sumpool_node.pool_spec;
 
                         // only support pytorch type formatting for now
                         if pool_spec.data_format != DataFormat::NCHW {
@@ -585,7 +1141,11 @@ impl Node {
                             )));
                         }
 
-                        let stride = pool_spec.strides.clone().unwrap();
+                        // strides default to 1 when left unspecified
+                        let stride = match &pool_spec.strides {
+                            Some(s) => s.to_vec(),
+                            None => vec![1; pool_spec.kernel_shape.len()],
+                        };
                         let padding = match &pool_spec.padding {
                             PaddingSpec::Explicit(p, _, _) => p,
                             _ => {
@@ -608,20 +1168,47 @@ impl Node {
                             (input_height + 2 * padding_h - kernel_height) / stride_h + 1;
                         let out_width = (input_width + 2 * padding_w - kernel_width) / stride_w + 1;
 
+                        // AveragePool is parsed as a normalized SumPool
+                        let (opkind, output_max) = if sumpool_node.normalize {
+                            // a single division lookup can't account for a varying number of
+                            // elements per window
+                            if !sumpool_node.count_include_pad && (padding_h > 0 || padding_w > 0) {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "average pooling with padding requires count_include_pad"
+                                        .to_string(),
+                                )));
+                            }
+                            (
+                                OpKind::Lookup(LookupOp::AvgPool2D {
+                                    padding: (padding_h, padding_w),
+                                    stride: (stride_h, stride_w),
+                                    pool_dims: (kernel_height, kernel_width),
+                                    remainder: false,
+                                }),
+                                // the averages are bounded by the inputs, the window sums which
+                                // are looked up are bounded when sizing the lookups
+                                input_node.output_max,
+                            )
+                        } else {
+                            (
+                                OpKind::Poly(PolyOp::SumPool {
+                                    padding: (padding_h, padding_w),
+                                    stride: (stride_h, stride_w),
+                                    kernel_shape: (kernel_height, kernel_width),
+                                }),
+                                input_node.output_max * f32::powi(2.0, input_node.out_scale as i32),
+                            )
+                        };
+
                         Node {
                             idx,
-                            opkind: OpKind::Poly(PolyOp::SumPool {
-                                padding: (padding_h, padding_w),
-                                stride: (stride_h, stride_w),
-                                kernel_shape: (kernel_height, kernel_width),
-                            }),
+                            opkind,
                             inputs: node.inputs.clone(),
                             in_dims: vec![input_node.out_dims.clone()],
                             out_dims: vec![input_channels, out_height, out_width],
                             in_scale: input_node.out_scale,
                             out_scale: input_node.out_scale,
-                            output_max: input_node.output_max
-                                * f32::powi(2.0, input_node.out_scale as i32),
+                            output_max,
                             ..Default::default()
                         }
                     }
@@ -646,24 +1233,126 @@ impl Node {
 
                         Node {
                             idx,
-                            opkind: OpKind::Poly(PolyOp::SumPool {
+                            opkind: OpKind::Lookup(LookupOp::AvgPool2D {
                                 padding: (padding_h, padding_w),
                                 stride: (stride_h, stride_w),
-                                kernel_shape: (kernel_height, kernel_width),
+                                pool_dims: (kernel_height, kernel_width),
+                                remainder: false,
                             }),
                             inputs: node.inputs.clone(),
                             in_dims: vec![input_node.out_dims.clone()],
                             out_dims: vec![input_channels, out_height, out_width],
                             in_scale: input_node.out_scale,
                             out_scale: input_node.out_scale,
-                            output_max: input_node.output_max
-                                * (input_height as f32)
-                                * (input_width as f32),
+                            output_max: input_node.output_max,
                             ..Default::default()
                         }
                     }
 
+                    PolyOp::Einsum(_) => {
+                        let op = Box::new(node.op());
+
+                        let einsum_node: &EinSum = match op.downcast_ref() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+
+                        let in_dims = inputs.iter().map(|i| i.out_dims.clone()).collect_vec();
+                        let equation = Self::format_einsum_equation(
+                            &einsum_node.expr.to_string(),
+                            &in_dims,
+                            idx,
+                            &opkind,
+                        )?;
+
+                        let indices = EinsumIndices::new(&equation)?;
+                        if !indices.is_matmul() {
+                            return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                        }
+                        let sizes = indices.sizes(&in_dims)?;
+                        let contracted_len = indices
+                            .contracted
+                            .iter()
+                            .map(|c| sizes[c])
+                            .product::<usize>();
+
+                        let (a_node, b_node) = (&inputs[0], &inputs[1]);
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Einsum(equation)),
+                            inputs: node.inputs.clone(),
+                            out_dims: indices.output.iter().map(|c| sizes[c]).collect(),
+                            in_dims,
+                            in_scale: a_node.out_scale,
+                            out_scale: a_node.out_scale + b_node.out_scale,
+                            output_max: a_node.output_max
+                                * b_node.output_max
+                                * (contracted_len as f32),
+                            ..Default::default()
+                        }
+                    }
                     PolyOp::Matmul => {
+                        // MatMulInteger multiplies the integer codes of its inputs, so the zero
+                        // point of B is folded into the constant and the scale of A is dropped
+                        let is_integer = node.op().name() == "MatMulInteger";
+                        if is_integer {
+                            // the zero point of A would offset the products by multiples of the
+                            // column sums of B, which isn't folded in, so it has to be 0
+                            if let Some(a_zero_point) = inputs.get(2) {
+                                let is_zero = a_zero_point.opkind.is_const()
+                                    && a_zero_point
+                                        .const_value
+                                        .as_ref()
+                                        .map_or(false, |v| v.iter().all(|x| *x == 0));
+                                if !is_zero {
+                                    return Err(Box::new(GraphError::MisformedParams(
+                                        "MatMulInteger only supports a zero point of 0 for A"
+                                            .to_string(),
+                                    )));
+                                }
+                            }
+                            if inputs.len() == 4 {
+                                let zero_point = inputs[3].clone();
+                                let b_node = other_nodes.get_mut(&node.inputs[1].node).unwrap();
+                                Self::dequantize_const_node(b_node, None, Some(&zero_point), 0)?;
+                                inputs[1] = b_node.clone();
+                            }
+                            node.inputs.truncate(2);
+                            inputs.truncate(2);
+                        }
+
+                        // the q k^T of attention scores is folded into an einsum over the keys
+                        if let Some(k_outlet) = Self::transposed_input(&inputs[1], other_nodes) {
+                            let (a_node, k_node) =
+                                (&inputs[0], other_nodes.get(&k_outlet.node).unwrap());
+                            if a_node.out_dims.len() != 2
+                                || a_node.out_dims[1] != k_node.out_dims[1]
+                            {
+                                return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                            }
+                            let in_dim = a_node.out_dims[1];
+
+                            return Ok(Node {
+                                idx,
+                                opkind: OpKind::Poly(PolyOp::Einsum("ik,jk->ij".to_string())),
+                                inputs: vec![node.inputs[0], k_outlet],
+                                in_dims: vec![a_node.out_dims.clone(), k_node.out_dims.clone()],
+                                out_dims: vec![a_node.out_dims[0], k_node.out_dims[0]],
+                                in_scale: a_node.out_scale,
+                                out_scale: a_node.out_scale + k_node.out_scale,
+                                output_max: a_node.output_max * k_node.output_max * (in_dim as f32),
+                                ..Default::default()
+                            });
+                        }
+                        if let Some(attention_node) =
+                            Self::new_attention(idx, &node.inputs, &inputs, other_nodes)?
+                        {
+                            return Ok(attention_node);
+                        }
+
                         let (a_node, b_node) = (&inputs[0], &inputs[1]);
                         let a_dims = a_node.out_dims.clone();
                         let b_dims = b_node.out_dims.clone();
@@ -680,12 +1369,33 @@ impl Node {
                             in_dims: vec![vec![in_dim]],
                             out_dims: dims.clone(),
                             in_scale: a_node.out_scale,
-                            out_scale: a_node.out_scale + b_node.out_scale,
+                            out_scale: if is_integer {
+                                b_node.out_scale
+                            } else {
+                                a_node.out_scale + b_node.out_scale
+                            },
                             output_max: a_node.output_max * b_node.output_max * (in_dim as f32),
                             ..Default::default()
                         }
                     }
                     PolyOp::Affine | PolyOp::ScaleAndShift => {
+                        // fold the Gemm transposition flags and alpha / beta scalars into the inputs
+                        let op = Box::new(node.op());
+                        if let Some(b) = op.downcast_ref::<Box<dyn Expansion>>() {
+                            if let Some(gemm) = (*b).as_any().downcast_ref::<Gemm>() {
+                                Self::format_gemm_inputs(
+                                    gemm,
+                                    &node.inputs,
+                                    other_nodes,
+                                    idx,
+                                    &opkind,
+                                )?;
+                                for (i, input) in node.inputs.iter().enumerate() {
+                                    inputs[i] = other_nodes.get(&input.node).unwrap().clone();
+                                }
+                            }
+                        }
+
                         let (input_node, weight_node, bias_node) =
                             (&inputs[0], &inputs[1], &inputs[2]);
 
@@ -741,10 +1451,16 @@ impl Node {
                         inputs[2].raw_const_value = Some(b);
                         inputs[2].quantize_const_to_scale(out_scale)?;
 
+                        // the mean and variance are folded into the scale and shift
+                        inputs.truncate(3);
+                        for (outlet, input) in node.inputs[1..3].iter().zip(&inputs[1..3]) {
+                            other_nodes.insert(outlet.node, input.clone());
+                        }
+
                         Node {
                             idx,
                             opkind: OpKind::Poly(PolyOp::ScaleAndShift),
-                            inputs: node.inputs.clone(),
+                            inputs: node.inputs[0..3].to_vec(),
                             in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
                             out_dims: inputs[0].out_dims.clone(),
                             in_scale,
@@ -864,117 +1580,1889 @@ impl Node {
                             }
                         }
 
-                        Node {
-                            idx,
-                            opkind: OpKind::Poly(PolyOp::Pow(pow as u32)),
-                            inputs: node.inputs,
-                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
-                            out_dims: input_node.out_dims.clone(),
-                            in_scale: input_node.out_scale,
-                            out_scale: input_node.out_scale * (pow as u32),
-                            output_max: f32::powf(
-                                inputs
-                                    .iter()
-                                    .map(|input| input.output_max.ceil() as i128)
-                                    .max()
-                                    .unwrap() as f32,
-                                pow,
-                            ),
-                            ..Default::default()
+                        // positive integer powers are exact products, any other power is looked up
+                        if pow < 1.0 || pow.fract() != 0.0 {
+                            let in_mult = scale_to_multiplier(input_node.out_scale);
+                            let out_mult = scale_to_multiplier(scale);
+                            // negative powers are largest for the smallest positive input
+                            let output_max = out_mult
+                                * (input_node.output_max / in_mult)
+                                    .powf(pow)
+                                    .max(in_mult.powf(-pow));
+                            Node {
+                                idx,
+                                opkind: OpKind::Lookup(LookupOp::Pow {
+                                    scales: (in_mult as usize, out_mult as usize),
+                                    exponent: F32(pow),
+                                }),
+                                inputs: node.inputs,
+                                in_dims: vec![input_node.out_dims.clone()],
+                                out_dims: input_node.out_dims.clone(),
+                                in_scale: input_node.out_scale,
+                                out_scale: scale,
+                                output_max,
+                                ..Default::default()
+                            }
+                        } else {
+                            Node {
+                                idx,
+                                opkind: OpKind::Poly(PolyOp::Pow(pow as u32)),
+                                inputs: node.inputs,
+                                in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                                out_dims: input_node.out_dims.clone(),
+                                in_scale: input_node.out_scale,
+                                out_scale: input_node.out_scale * (pow as u32),
+                                output_max: f32::powf(
+                                    inputs
+                                        .iter()
+                                        .map(|input| input.output_max.ceil() as i128)
+                                        .max()
+                                        .unwrap() as f32,
+                                    pow,
+                                ),
+                                ..Default::default()
+                            }
                         }
                     }
-                    PolyOp::Rescaled { .. } => {
-                        return Err(Box::new(GraphError::RescalingError(opkind)));
-                    }
-                    PolyOp::Identity => {
-                        let input_node = &inputs[0];
-                        Node {
-                            idx,
-                            opkind,
-                            inputs: node.inputs.clone(),
-                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
-                            out_dims: input_node.out_dims.clone(),
-                            in_scale: input_node.out_scale,
-                            out_scale: input_node.out_scale,
-                            output_max: input_node.output_max,
-                            ..Default::default()
+                    PolyOp::LSTM { .. } => {
+                        // W and R are laid out as [num_directions, 4 * hidden_size, *]
+                        let (weight_node, recurrent_node) = (&inputs[1], &inputs[2]);
+                        if weight_node.out_dims.len() != 3 || weight_node.out_dims[0] != 1 {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "bidirectional lstm".to_string(),
+                            )));
                         }
-                    }
-                    PolyOp::Flatten(_) => {
-                        let input_node = &inputs[0];
-                        let new_dims: Vec<usize> =
-                            vec![inputs[0].out_dims.iter().product::<usize>()];
-                        Node {
-                            idx,
-                            opkind: OpKind::Poly(PolyOp::Flatten(new_dims.clone())),
-                            inputs: node.inputs.clone(),
-                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
-                            out_dims: new_dims,
-                            in_scale: input_node.out_scale,
-                            out_scale: input_node.out_scale,
-                            output_max: input_node.output_max,
-                            ..Default::default()
+                        let input_size = weight_node.out_dims[2];
+                        let hidden_size = recurrent_node.out_dims[2];
+
+                        // the input is a sequence of single batch elements
+                        let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                        let num_elems = input_node.out_dims.iter().product::<usize>();
+                        if num_elems % input_size != 0 {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
                         }
-                    }
-                    PolyOp::Reshape(_) => {
-                        let input_node = &inputs[0];
-                        let shape_const_node = &inputs[1];
-                        let shape_const = match shape_const_node.const_value.as_ref() {
-                            Some(sc) => sc,
+                        let seq_len = num_elems / input_size;
+                        input_node.out_dims = vec![seq_len, input_size];
+                        if input_node.out_scale != scale {
+                            return Err(Box::new(GraphError::RescalingError(opkind)));
+                        }
+
+                        // the optional inputs are identified by their shape
+                        let mut bias = None;
+                        for (i, n) in inputs.iter().enumerate().skip(3) {
+                            let is_zero = n.opkind.is_const()
+                                && n.const_value
+                                    .as_ref()
+                                    .map_or(false, |t| t.iter().all(|x| *x == 0));
+                            match n.out_dims.as_slice() {
+                                [1, d] if *d == 8 * hidden_size => bias = Some(i),
+                                // sequence lengths
+                                [1] if n
+                                    .const_value
+                                    .as_ref()
+                                    .map_or(false, |t| t[0] == seq_len as i128) => {}
+                                // initial hidden and cell states
+                                [1, 1, d] if *d == hidden_size && is_zero => {}
+                                _ => {
+                                    return Err(Box::new(GraphError::MisformedParams(
+                                        "lstm with peepholes or initial states".to_string(),
+                                    )));
+                                }
+                            }
+                        }
+                        let bias = match bias {
+                            Some(b) => b,
                             None => {
                                 return Err(Box::new(GraphError::MissingParams(
-                                    "shape constant".to_string(),
+                                    "lstm bias".to_string(),
                                 )));
                             }
                         };
 
-                        let mut shapes = &shape_const[0..];
-
-                        // we remove batch dims as we assume single elem batches
-                        if shapes[0] == -1 && shapes.len() > 1 {
-                            shapes = &shapes[1..];
-                        }
-
-                        let new_dims: Result<Vec<usize>, Box<dyn Error>> =
-                            if shapes.iter().all(|x| x > &0) {
-                                let mut res = vec![];
-                                for x in shapes.iter() {
-                                    if x <= &0 {
-                                        return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
-                                    }
-                                    res.push(*x as usize);
-                                }
-                                Ok(res)
-                            } else {
-                                let num_entries: usize = input_node.out_dims.iter().product();
-                                let explicit_prod: i128 =
-                                    shapes.iter().filter(|x| *x > &0).product();
-                                if explicit_prod <= 0 {
-                                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
-                                }
-                                let inferred = num_entries / (explicit_prod as usize);
-                                let mut new_dims: Vec<usize> = Vec::new();
-                                for i in shapes {
-                                    match i {
-                                        -1 => new_dims.push(inferred),
-                                        0 => continue,
-                                        x => new_dims.push(*x as usize),
-                                    }
+                        for (i, dims) in [(1, input_size), (2, hidden_size)] {
+                            let const_node = other_nodes.get_mut(&node.inputs[i].node).unwrap();
+                            let mut raw = match &const_node.raw_const_value {
+                                Some(raw) => raw.clone(),
+                                None => {
+                                    return Err(Box::new(GraphError::MisformedParams(
+                                        "lstm weights should be constants".to_string(),
+                                    )));
                                 }
-                                Ok(new_dims)
                             };
+                            raw.reshape(&[4 * hidden_size, dims]);
+                            Self::requantize_const_node(const_node, raw)?;
+                        }
 
-                        let new_dims = new_dims?;
+                        // the input and recurrent biases are summed at the scale of the gates
+                        let bias_node = other_nodes.get_mut(&node.inputs[bias].node).unwrap();
+                        let raw = match &bias_node.raw_const_value {
+                            Some(raw) => raw.clone(),
+                            None => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "lstm bias should be a constant".to_string(),
+                                )));
+                            }
+                        };
+                        let summed = raw
+                            .iter()
+                            .take(4 * hidden_size)
+                            .zip(raw.iter().skip(4 * hidden_size))
+                            .map(|(w_b, r_b)| w_b + r_b)
+                            .collect_vec();
+                        bias_node.out_scale = 2 * scale;
+                        Self::requantize_const_node(
+                            bias_node,
+                            Tensor::new(Some(&summed), &[4 * hidden_size])?,
+                        )?;
+
+                        let outlets = vec![
+                            node.inputs[0],
+                            node.inputs[1],
+                            node.inputs[2],
+                            node.inputs[bias],
+                        ];
+                        let in_dims = outlets
+                            .iter()
+                            .map(|i| other_nodes.get(&i.node).unwrap().out_dims.clone())
+                            .collect();
 
                         Node {
                             idx,
-                            opkind: OpKind::Poly(PolyOp::Reshape(new_dims.clone())),
-                            inputs: node.inputs[0..1].to_vec(),
-                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
-                            out_dims: new_dims,
-                            in_scale: input_node.out_scale,
-                            out_scale: input_node.out_scale,
-                            output_max: input_node.output_max,
+                            opkind: OpKind::Poly(PolyOp::LSTM {
+                                hidden_size,
+                                scale: scale_to_multiplier(scale) as usize,
+                            }),
+                            inputs: outlets,
+                            in_dims,
+                            out_dims: vec![seq_len, hidden_size],
+                            in_scale: scale,
+                            out_scale: scale,
+                            // the hidden state is bounded by the tanh of the cell state
+                            output_max: scale_to_multiplier(scale),
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::GRU { .. } | PolyOp::RNN { .. } => {
+                        let (name, num_gates) = match s {
+                            PolyOp::GRU { .. } => ("gru", 3),
+                            _ => ("rnn", 1),
+                        };
+                        // W and R are laid out as [num_directions, num_gates * hidden_size, *]
+                        let (weight_node, recurrent_node) = (&inputs[1], &inputs[2]);
+                        if weight_node.out_dims.len() != 3 || weight_node.out_dims[0] != 1 {
+                            return Err(Box::new(GraphError::MisformedParams(format!(
+                                "bidirectional {}",
+                                name
+                            ))));
+                        }
+                        let input_size = weight_node.out_dims[2];
+                        let hidden_size = recurrent_node.out_dims[2];
+                        let gates_size = num_gates * hidden_size;
+
+                        // the input is a sequence of single batch elements
+                        let input_node = other_nodes.get_mut(&node.inputs[0].node).unwrap();
+                        let num_elems = input_node.out_dims.iter().product::<usize>();
+                        if num_elems % input_size != 0 {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+                        let seq_len = num_elems / input_size;
+                        input_node.out_dims = vec![seq_len, input_size];
+                        if input_node.out_scale != scale {
+                            return Err(Box::new(GraphError::RescalingError(opkind)));
+                        }
+
+                        // the optional inputs are identified by their shape
+                        let (mut bias, mut initial_h) = (None, None);
+                        for (i, n) in inputs.iter().enumerate().skip(3) {
+                            match n.out_dims.as_slice() {
+                                [1, d] if *d == 2 * gates_size => bias = Some(i),
+                                // sequence lengths
+                                [1] if n
+                                    .const_value
+                                    .as_ref()
+                                    .map_or(false, |t| t[0] == seq_len as i128) => {}
+                                [1, 1, d] if *d == hidden_size => initial_h = Some(i),
+                                _ => {
+                                    return Err(Box::new(GraphError::MisformedParams(format!(
+                                        "{} with variable sequence lengths",
+                                        name
+                                    ))));
+                                }
+                            }
+                        }
+                        let bias = match bias {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::MissingParams(format!(
+                                    "{} bias",
+                                    name
+                                ))));
+                            }
+                        };
+
+                        for (i, dims) in [(1, input_size), (2, hidden_size)] {
+                            let const_node = other_nodes.get_mut(&node.inputs[i].node).unwrap();
+                            let mut raw = match &const_node.raw_const_value {
+                                Some(raw) => raw.clone(),
+                                None => {
+                                    return Err(Box::new(GraphError::MisformedParams(format!(
+                                        "{} weights should be constants",
+                                        name
+                                    ))));
+                                }
+                            };
+                            raw.reshape(&[gates_size, dims]);
+                            Self::requantize_const_node(const_node, raw)?;
+                        }
+
+                        // biases are at the scale of the gates, the gru keeps the input and
+                        // recurrent biases apart as the reset gate can apply to the latter
+                        let bias_node = other_nodes.get_mut(&node.inputs[bias].node).unwrap();
+                        let raw = match &bias_node.raw_const_value {
+                            Some(raw) => raw.clone(),
+                            None => {
+                                return Err(Box::new(GraphError::MisformedParams(format!(
+                                    "{} bias should be a constant",
+                                    name
+                                ))));
+                            }
+                        };
+                        let raw = match s {
+                            PolyOp::GRU { .. } => {
+                                let mut raw = raw;
+                                raw.reshape(&[2 * gates_size]);
+                                raw
+                            }
+                            _ => {
+                                let summed = raw
+                                    .iter()
+                                    .take(gates_size)
+                                    .zip(raw.iter().skip(gates_size))
+                                    .map(|(w_b, r_b)| w_b + r_b)
+                                    .collect_vec();
+                                Tensor::new(Some(&summed), &[gates_size])?
+                            }
+                        };
+                        bias_node.out_scale = 2 * scale;
+                        Self::requantize_const_node(bias_node, raw)?;
+
+                        let mut outlets = vec![
+                            node.inputs[0],
+                            node.inputs[1],
+                            node.inputs[2],
+                            node.inputs[bias],
+                        ];
+                        // the hidden state can be initialized from a constant or another node
+                        if let Some(i) = initial_h {
+                            let h_node = other_nodes.get_mut(&node.inputs[i].node).unwrap();
+                            if h_node.opkind.is_const() {
+                                let raw = h_node.raw_const_value.clone().unwrap();
+                                h_node.out_scale = scale;
+                                Self::requantize_const_node(h_node, raw)?;
+                            } else if h_node.out_scale != scale {
+                                return Err(Box::new(GraphError::RescalingError(opkind)));
+                            }
+                            outlets.push(node.inputs[i]);
+                        }
+                        let in_dims = outlets
+                            .iter()
+                            .map(|i| other_nodes.get(&i.node).unwrap().out_dims.clone())
+                            .collect();
+
+                        let scale_mult = scale_to_multiplier(scale) as usize;
+                        let op = match s {
+                            PolyOp::GRU { .. } => {
+                                let linear_before_reset = match node.op().downcast_ref::<GRU>() {
+                                    Some(gru) => gru.linear_before_reset,
+                                    None => {
+                                        return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                    }
+                                };
+                                PolyOp::GRU {
+                                    hidden_size,
+                                    scale: scale_mult,
+                                    linear_before_reset,
+                                }
+                            }
+                            _ => PolyOp::RNN {
+                                hidden_size,
+                                scale: scale_mult,
+                            },
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(op),
+                            inputs: outlets,
+                            in_dims,
+                            out_dims: vec![seq_len, hidden_size],
+                            in_scale: scale,
+                            out_scale: scale,
+                            // the hidden state is bounded by a tanh
+                            output_max: scale_to_multiplier(scale),
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Gather { .. } => {
+                        let (data_node, indices_node) = (&inputs[0], &inputs[1]);
+                        let op = Box::new(node.op());
+                        let gather_node: &Gather = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let axis = gather_node.to_type_op(data_node.out_dims.len()).axis;
+
+                        if data_node.opkind.is_const() && !indices_node.opkind.is_const() {
+                            // an embedding table indexed by token ids
+                            if axis != 0 || data_node.out_dims.len() != 2 {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "embeddings should gather the rows of a 2D table".to_string(),
+                                )));
+                            }
+                            let mut out_dims = indices_node.out_dims.clone();
+                            out_dims.push(data_node.out_dims[1]);
+
+                            Node {
+                                idx,
+                                opkind: OpKind::Poly(PolyOp::Embedding {
+                                    scale: scale_to_multiplier(indices_node.out_scale) as usize,
+                                }),
+                                inputs: node.inputs.clone(),
+                                in_dims: vec![
+                                    data_node.out_dims.clone(),
+                                    indices_node.out_dims.clone(),
+                                ],
+                                out_dims,
+                                in_scale: data_node.out_scale,
+                                out_scale: data_node.out_scale,
+                                output_max: data_node.output_max,
+                                ..Default::default()
+                            }
+                        } else if indices_node.opkind.is_const() && indices_node.out_scale == 0 {
+                            let axis_len = data_node.out_dims[axis] as i128;
+                            // negative indices count back from the end of the axis
+                            let indices = indices_node
+                                .const_value
+                                .as_ref()
+                                .unwrap()
+                                .iter()
+                                .map(|i| i.rem_euclid(axis_len) as usize)
+                                .collect_vec();
+                            let index_dims = indices_node.out_dims.clone();
+
+                            Node {
+                                idx,
+                                out_dims: gather_dims(&data_node.out_dims, axis, &index_dims),
+                                opkind: OpKind::Poly(PolyOp::Gather {
+                                    axis,
+                                    indices,
+                                    index_dims,
+                                }),
+                                inputs: vec![node.inputs[0]],
+                                in_dims: vec![data_node.out_dims.clone()],
+                                in_scale: data_node.out_scale,
+                                out_scale: data_node.out_scale,
+                                output_max: data_node.output_max,
+                                ..Default::default()
+                            }
+                        } else {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "gather needs constant indices or a constant table".to_string(),
+                            )));
+                        }
+                    }
+                    PolyOp::GatherElements { .. } => {
+                        // indices must be non-negative and in range, out of range indices gather zeros
+                        let (data_node, indices_node) = (&inputs[0], &inputs[1]);
+                        let gather_node: &GatherElements =
+                            match node.op().downcast_ref::<GatherElements>() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            };
+                        let rank = data_node.out_dims.len() as i64;
+                        let axis = (gather_node.axis + rank) % rank;
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::GatherElements {
+                                axis: axis as usize,
+                                scale: scale_to_multiplier(indices_node.out_scale) as usize,
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![
+                                data_node.out_dims.clone(),
+                                indices_node.out_dims.clone(),
+                            ],
+                            out_dims: indices_node.out_dims.clone(),
+                            in_scale: data_node.out_scale,
+                            out_scale: data_node.out_scale,
+                            output_max: data_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::GatherND { .. } => {
+                        // indices must be non-negative and in range, out of range indices gather zeros
+                        let (data_node, indices_node) = (&inputs[0], &inputs[1]);
+                        let gather_node: &GatherNd = match node.op().downcast_ref::<GatherNd>() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        if gather_node.batch_dims != 0 {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "gather nd with batch dims".to_string(),
+                            )));
+                        }
+                        let (dims, index_dims) = (&data_node.out_dims, &indices_node.out_dims);
+                        let k = index_dims[index_dims.len() - 1];
+                        if k == 0 || k > dims.len() {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "gather nd indices should index the input's leading axes"
+                                    .to_string(),
+                            )));
+                        }
+                        let mut out_dims =
+                            [&index_dims[..index_dims.len() - 1], &dims[k..]].concat();
+                        if out_dims.is_empty() {
+                            out_dims.push(1);
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::GatherND {
+                                scale: scale_to_multiplier(indices_node.out_scale) as usize,
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![dims.clone(), index_dims.clone()],
+                            out_dims,
+                            in_scale: data_node.out_scale,
+                            out_scale: data_node.out_scale,
+                            output_max: data_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::ScatterElements { .. } | PolyOp::ScatterND { .. } => {
+                        // indices must be non-negative and in range, out of range updates are
+                        // dropped and colliding updates are resolved by the last write
+                        let (data_node, indices_node, updates_node) =
+                            (&inputs[0], &inputs[1], &inputs[2]);
+                        if data_node.out_scale != updates_node.out_scale {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "scatter updates should be at the scale of the input".to_string(),
+                            )));
+                        }
+                        let index_scale = scale_to_multiplier(indices_node.out_scale) as usize;
+
+                        let op = match s {
+                            PolyOp::ScatterElements { .. } => {
+                                let scatter_node: &ScatterElements = match node
+                                    .op()
+                                    .downcast_ref::<ScatterElements>()
+                                {
+                                    Some(b) => b,
+                                    None => {
+                                        return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                    }
+                                };
+                                PolyOp::ScatterElements {
+                                    axis: scatter_node.axis,
+                                    scale: index_scale,
+                                }
+                            }
+                            _ => {
+                                if node.op().downcast_ref::<ScatterNd>().is_none() {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                                PolyOp::ScatterND { scale: index_scale }
+                            }
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(op),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![
+                                data_node.out_dims.clone(),
+                                indices_node.out_dims.clone(),
+                                updates_node.out_dims.clone(),
+                            ],
+                            out_dims: data_node.out_dims.clone(),
+                            in_scale: data_node.out_scale,
+                            out_scale: data_node.out_scale,
+                            output_max: data_node.output_max.max(updates_node.output_max),
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::TopK { .. } => {
+                        // only the values output is supported, the indices output is not
+                        let (input_node, k_node) = (&inputs[0], &inputs[1]);
+                        let op = Box::new(node.op());
+                        let topk_node: &Topk = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let rank = input_node.out_dims.len() as i64;
+                        let axis = ((topk_node.axis + rank) % rank) as usize;
+
+                        let k = match &k_node.const_value {
+                            Some(k) if k_node.out_scale == 0 && k.len() == 1 => k[0] as usize,
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "topk needs a constant k".to_string(),
+                                )));
+                            }
+                        };
+                        let mut out_dims = input_node.out_dims.clone();
+                        if k == 0 || k > out_dims[axis] {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "topk k should be within the axis".to_string(),
+                            )));
+                        }
+                        out_dims[axis] = k;
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::TopK {
+                                k,
+                                axis,
+                                largest: topk_node.largest,
+                            }),
+                            inputs: vec![node.inputs[0]],
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::ArgMax { .. } | PolyOp::ArgMin { .. } => {
+                        // the output is an integer index, so is at a scale of 0
+                        let input_node = &inputs[0];
+                        let op = Box::new(node.op());
+                        let reduce_node: &Reduce = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let rank = input_node.out_dims.len() as i64;
+                        let axis = match &reduce_node.axes {
+                            Some(axes) if axes.len() == 1 => ((axes[0] + rank) % rank) as usize,
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "argmax and argmin should reduce a single axis".to_string(),
+                                )));
+                            }
+                        };
+                        let keep_dims = reduce_node.keep_dims;
+
+                        let mut out_dims = input_node.out_dims.clone();
+                        if keep_dims {
+                            out_dims[axis] = 1;
+                        } else {
+                            out_dims.remove(axis);
+                            if out_dims.is_empty() {
+                                out_dims.push(1);
+                            }
+                        }
+
+                        let op = match s {
+                            PolyOp::ArgMax { .. } => PolyOp::ArgMax { axis, keep_dims },
+                            _ => PolyOp::ArgMin { axis, keep_dims },
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(op),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: 0,
+                            output_max: (input_node.out_dims[axis] - 1) as f32,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::ResizeNearest { .. } => {
+                        let input_node = &inputs[0];
+                        let resize_node: &Resize = match node.op().downcast_ref::<Resize>() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        // the onnx output shape includes the batch dim, which the input's doesn't
+                        let in_dims = input_node.out_dims.clone();
+                        let out_dims = match output_shapes.as_deref() {
+                            Some([Some(v)]) if v.len() >= in_dims.len() => {
+                                v[v.len() - in_dims.len()..].to_vec()
+                            }
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "resize needs a known output shape".to_string(),
+                                )));
+                            }
+                        };
+
+                        // maps an output position to its (fractional) input position
+                        let transform = |x: usize, len_in: usize, len_out: usize| -> f32 {
+                            let (x, ratio) = (x as f32, len_out as f32 / len_in as f32);
+                            match resize_node.coord_transformer {
+                                CoordTransformer::HalfPixel => (x + 0.5) / ratio - 0.5,
+                                CoordTransformer::PytorchHalfPixel if len_out > 1 => {
+                                    (x + 0.5) / ratio - 0.5
+                                }
+                                CoordTransformer::PytorchHalfPixel => 0.0,
+                                CoordTransformer::AlignCorners if len_out > 1 => {
+                                    x * (len_in - 1) as f32 / (len_out - 1) as f32
+                                }
+                                CoordTransformer::AlignCorners => 0.0,
+                                CoordTransformer::Asymmetric => x / ratio,
+                                CoordTransformer::TfHalfPixelForNn => (x + 0.5) / ratio,
+                            }
+                        };
+
+                        let op = match resize_node.interpolator {
+                            Interpolator::Nearest => {
+                                let indices = in_dims
+                                    .iter()
+                                    .zip(&out_dims)
+                                    .map(|(len_in, len_out)| {
+                                        (0..*len_out)
+                                            .map(|o| {
+                                                let x = transform(o, *len_in, *len_out);
+                                                let x = match resize_node.nearest {
+                                                    Nearest::Floor => x.floor(),
+                                                    Nearest::Ceil => x.ceil(),
+                                                    Nearest::RoundPreferFloor
+                                                        if x.fract() == 0.5 =>
+                                                    {
+                                                        x.floor()
+                                                    }
+                                                    Nearest::RoundPreferFloor
+                                                    | Nearest::RoundPreferCeil => x.round(),
+                                                };
+                                                x.clamp(0.0, (len_in - 1) as f32) as usize
+                                            })
+                                            .collect_vec()
+                                    })
+                                    .collect_vec();
+                                PolyOp::ResizeNearest { indices }
+                            }
+                            Interpolator::Linear => {
+                                // the interpolation weights are quantized at the model's scale
+                                let mult = scale_to_multiplier(scale);
+                                let taps = in_dims
+                                    .iter()
+                                    .zip(&out_dims)
+                                    .map(|(len_in, len_out)| {
+                                        (0..*len_out)
+                                            .map(|o| {
+                                                let x = transform(o, *len_in, *len_out)
+                                                    .clamp(0.0, (len_in - 1) as f32);
+                                                let i0 = x.floor() as usize;
+                                                let i1 = (i0 + 1).min(len_in - 1);
+                                                let w0 = ((1.0 - x.fract()) * mult).round();
+                                                (i0, i1, w0 as usize)
+                                            })
+                                            .collect_vec()
+                                    })
+                                    .collect_vec();
+                                PolyOp::ResizeLinear {
+                                    taps,
+                                    scale: mult as usize,
+                                }
+                            }
+                        };
+
+                        let (out_scale, output_max) = match op {
+                            PolyOp::ResizeLinear { .. } => (
+                                input_node.out_scale + scale,
+                                input_node.output_max * scale_to_multiplier(scale),
+                            ),
+                            _ => (input_node.out_scale, input_node.output_max),
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(op),
+                            inputs: vec![node.inputs[0]],
+                            in_dims: vec![in_dims],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale,
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Softmax { .. } => {
+                        // softmax is applied over the last axis
+                        let input_node = &inputs[0];
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Softmax {
+                                scales: (
+                                    scale_to_multiplier(input_node.out_scale) as usize,
+                                    scale_to_multiplier(scale) as usize,
+                                ),
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale: scale,
+                            // probabilities are at most 1
+                            output_max: scale_to_multiplier(scale),
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Rescaled { .. } => {
+                        return Err(Box::new(GraphError::RescalingError(opkind)));
+                    }
+                    PolyOp::Identity => {
+                        match node.op().name().as_ref() {
+                            "QuantizeLinear" => {
+                                return Self::new_quantize_linear(idx, &node.inputs, &inputs);
+                            }
+                            "DequantizeLinear" => {
+                                return Self::new_dequantize_linear(
+                                    idx,
+                                    &node.inputs,
+                                    &inputs,
+                                    scale,
+                                );
+                            }
+                            _ => {}
+                        }
+                        let input_node = &inputs[0];
+                        Node {
+                            idx,
+                            opkind,
+                            // the ratio and training mode of a dropout are dropped
+                            inputs: node.inputs[0..1].to_vec(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Flatten(_) => {
+                        let input_node = &inputs[0];
+                        let new_dims: Vec<usize> =
+                            vec![inputs[0].out_dims.iter().product::<usize>()];
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Flatten(new_dims.clone())),
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims: new_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Tile { .. } | PolyOp::Expand { .. } => {
+                        let (input_node, shape_const_node) = (&inputs[0], &inputs[1]);
+                        let shape_const = match shape_const_node.const_value.as_ref() {
+                            Some(sc) if sc.iter().all(|x| *x > 0) => {
+                                sc.iter().map(|x| *x as usize).collect_vec()
+                            }
+                            _ => {
+                                return Err(Box::new(GraphError::MissingParams(
+                                    "shape constant".to_string(),
+                                )));
+                            }
+                        };
+                        let rank = input_node.out_dims.len();
+
+                        let (op, out_dims) = match s {
+                            PolyOp::Tile { .. } => {
+                                // we remove batch dims as we assume single elem batches
+                                let repeats =
+                                    if shape_const.len() == rank + 1 && shape_const[0] == 1 {
+                                        shape_const[1..].to_vec()
+                                    } else {
+                                        shape_const
+                                    };
+                                if repeats.len() != rank {
+                                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                }
+                                let out_dims = input_node
+                                    .out_dims
+                                    .iter()
+                                    .zip(&repeats)
+                                    .map(|(d, r)| d * r)
+                                    .collect_vec();
+                                (PolyOp::Tile { repeats }, out_dims)
+                            }
+                            _ => {
+                                let mut out_dims =
+                                    match expand_dims(&input_node.out_dims, &shape_const) {
+                                        Some(d) => d,
+                                        None => {
+                                            return Err(Box::new(GraphError::InvalidDims(
+                                                idx, opkind,
+                                            )));
+                                        }
+                                    };
+                                // we remove batch dims as we assume single elem batches
+                                if out_dims.len() > rank && out_dims.len() > 1 && out_dims[0] == 1 {
+                                    out_dims.remove(0);
+                                }
+                                (
+                                    PolyOp::Expand {
+                                        shape: out_dims.clone(),
+                                    },
+                                    out_dims,
+                                )
+                            }
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(op),
+                            inputs: node.inputs[0..1].to_vec(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::DepthToSpace { .. } | PolyOp::SpaceToDepth { .. } => {
+                        let input_node = &inputs[0];
+                        if input_node.out_dims.len() != 3 {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+                        let (c, h, w) = (
+                            input_node.out_dims[0],
+                            input_node.out_dims[1],
+                            input_node.out_dims[2],
+                        );
+
+                        let (op, out_dims) = match s {
+                            PolyOp::DepthToSpace { .. } => {
+                                let d2s_node: &DepthToSpace = match node
+                                    .op()
+                                    .downcast_ref::<DepthToSpace>()
+                                {
+                                    Some(b) => b,
+                                    None => {
+                                        return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                    }
+                                };
+                                let b = d2s_node.blocksize;
+                                if c % (b * b) != 0 {
+                                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                }
+                                let mode = match d2s_node.mode {
+                                    TractDepthToSpaceMode::DCR => DepthToSpaceMode::DCR,
+                                    TractDepthToSpaceMode::CRD => DepthToSpaceMode::CRD,
+                                };
+                                (
+                                    PolyOp::DepthToSpace { blocksize: b, mode },
+                                    vec![c / (b * b), h * b, w * b],
+                                )
+                            }
+                            _ => {
+                                let s2d_node: &SpaceToDepth = match node
+                                    .op()
+                                    .downcast_ref::<SpaceToDepth>()
+                                {
+                                    Some(b) => b,
+                                    None => {
+                                        return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                    }
+                                };
+                                let b = s2d_node.blocksize;
+                                if h % b != 0 || w % b != 0 {
+                                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                }
+                                (
+                                    PolyOp::SpaceToDepth { blocksize: b },
+                                    vec![c * b * b, h / b, w / b],
+                                )
+                            }
+                        };
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(op),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Reduce { .. } => {
+                        let input_node = &inputs[0];
+                        let op = Box::new(node.op());
+                        let reduce_node: &Reduce = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let in_dims = input_node.out_dims.clone();
+                        let rank = in_dims.len();
+                        let keep_dims = reduce_node.keep_dims;
+
+                        let mut axes: Vec<usize> = match &reduce_node.axes {
+                            // no axes reduces over every axis
+                            None => (0..rank).collect(),
+                            Some(onnx_axes) => {
+                                // the onnx ranks include the batch dim, which our dims don't
+                                let out_rank = match output_shapes.as_deref() {
+                                    Some([Some(v)]) => v.len(),
+                                    _ => {
+                                        return Err(Box::new(GraphError::MisformedParams(
+                                            "reduce needs a known output shape".to_string(),
+                                        )));
+                                    }
+                                };
+                                let onnx_rank = match keep_dims {
+                                    true => out_rank,
+                                    false => out_rank + onnx_axes.len(),
+                                } as i64;
+                                let batch = (onnx_rank as usize).saturating_sub(rank);
+                                onnx_axes
+                                    .iter()
+                                    .map(|axis| ((axis + onnx_rank) % onnx_rank) as usize)
+                                    // the batch dim has length 1, so reducing it is a no-op
+                                    .filter(|axis| *axis >= batch)
+                                    .map(|axis| axis - batch)
+                                    .collect()
+                            }
+                        };
+                        axes.sort_unstable();
+                        axes.dedup();
+                        if axes.is_empty() || axes.iter().any(|axis| *axis >= rank) {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+                        let len: usize = axes.iter().map(|axis| in_dims[*axis]).product();
+
+                        let mult = scale_to_multiplier(input_node.out_scale);
+                        let (reduction, output_max) = match reduce_node.reducer {
+                            Reducer::Sum => (Reduction::Sum, input_node.output_max * len as f32),
+                            Reducer::Mean => (
+                                Reduction::Mean {
+                                    denom: len,
+                                    remainder: false,
+                                },
+                                input_node.output_max,
+                            ),
+                            Reducer::Max => (Reduction::Max, input_node.output_max),
+                            Reducer::Min => (Reduction::Min, input_node.output_max),
+                            Reducer::Prod => (
+                                Reduction::Prod {
+                                    scale: mult as usize,
+                                },
+                                // every product is rescaled back to the input's scale
+                                mult * (input_node.output_max / mult).powi(len as i32),
+                            ),
+                            _ => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+
+                        let mut out_dims: Vec<usize> = in_dims
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(d, len)| match axes.contains(&d) {
+                                true if keep_dims => Some(1),
+                                true => None,
+                                false => Some(*len),
+                            })
+                            .collect();
+                        if out_dims.is_empty() {
+                            out_dims.push(1);
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Reduce {
+                                reduction,
+                                axes,
+                                keep_dims,
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![in_dims],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Clip { .. } => {
+                        // the bounds are inputs from opset 11, rather than attributes
+                        let op = Box::new(node.op());
+                        let clip_node: &Clip11 = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::MisformedParams(
+                                        "clip bounds should be inputs (opset 11+)".to_string(),
+                                    )));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let (lower, upper) =
+                            (clip_node.input_min.is_some(), clip_node.input_max.is_some());
+                        if inputs.len() != 1 + lower as usize + upper as usize
+                            || inputs[1..].iter().any(|b| b.out_dims != [1])
+                        {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+
+                        opkind = Self::homogenize_input_scales(
+                            OpKind::Poly(PolyOp::Clip { lower, upper }),
+                            inputs.clone(),
+                        )?;
+                        let output_max =
+                            if let OpKind::Poly(PolyOp::Rescaled { scale, .. }) = &opkind {
+                                inputs
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(idx, n)| (scale[idx].1 as f32) * n.output_max)
+                                    .fold(0.0, f32::max)
+                            } else {
+                                return Err(Box::new(GraphError::RescalingError(opkind)));
+                            };
+
+                        Node {
+                            idx,
+                            opkind,
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims: inputs[0].out_dims.clone(),
+                            in_scale: inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                            out_scale: inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Compare { .. } => {
+                        // the output is a boolean, so is at a scale of 0
+                        opkind = Self::homogenize_input_scales(opkind, inputs.clone())?;
+                        let out_dims = inputs
+                            .iter()
+                            .map(|inp| inp.out_dims.clone())
+                            .max_by_key(|dims| dims.iter().product::<usize>())
+                            .unwrap();
+
+                        Node {
+                            idx,
+                            opkind,
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims,
+                            in_scale: inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                            out_scale: 0,
+                            output_max: 1.0,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Where => {
+                        let (condition_node, x_node, y_node) = (&inputs[0], &inputs[1], &inputs[2]);
+                        if condition_node.out_scale != 0 {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "where condition should be boolean".to_string(),
+                            )));
+                        }
+                        // only the selected values are brought to the same scale
+                        let out_scale = x_node.out_scale.max(y_node.out_scale);
+                        let multipliers = [x_node, y_node]
+                            .iter()
+                            .map(|n| scale_to_multiplier(out_scale - n.out_scale) as usize)
+                            .collect_vec();
+                        let out_dims = inputs
+                            .iter()
+                            .map(|inp| inp.out_dims.clone())
+                            .max_by_key(|dims| dims.iter().product::<usize>())
+                            .unwrap();
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Rescaled {
+                                inner: Box::new(PolyOp::Where),
+                                scale: vec![(0, 1), (1, multipliers[0]), (2, multipliers[1])],
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims,
+                            in_scale: out_scale,
+                            out_scale,
+                            output_max: (multipliers[0] as f32 * x_node.output_max)
+                                .max(multipliers[1] as f32 * y_node.output_max),
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Max | PolyOp::Min => {
+                        opkind = Self::homogenize_input_scales(opkind, inputs.clone())?;
+                        let output_max =
+                            if let OpKind::Poly(PolyOp::Rescaled { scale, .. }) = &opkind {
+                                inputs
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(idx, n)| (scale[idx].1 as f32) * n.output_max)
+                                    .fold(0.0, f32::max)
+                            } else {
+                                return Err(Box::new(GraphError::RescalingError(opkind)));
+                            };
+                        let out_dims = inputs
+                            .iter()
+                            .map(|inp| inp.out_dims.clone())
+                            .max_by_key(|dims| dims.iter().product::<usize>())
+                            .unwrap();
+
+                        Node {
+                            idx,
+                            opkind,
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims,
+                            in_scale: inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                            out_scale: inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Sign | PolyOp::Abs => {
+                        // the sign is -1, 0 or 1 whatever the scale of the input, so is at a
+                        // scale of 0, and the absolute value keeps the scale of the input
+                        let input_node = &inputs[0];
+                        let (out_scale, output_max) = match opkind {
+                            OpKind::Poly(PolyOp::Sign) => (0, 1.0),
+                            _ => (input_node.out_scale, input_node.output_max),
+                        };
+
+                        Node {
+                            idx,
+                            opkind,
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale,
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Bitwise { bitwise, .. } => {
+                        let bitwise = *bitwise;
+                        // the integers are as wide as their type, less the sign bit of signed
+                        // types, whose negative values aren't supported
+                        let bits = match node.outputs[0].fact.datum_type.concretize() {
+                            Some(dt) if dt.is_integer() => {
+                                8 * dt.size_of() - dt.is_signed() as usize
+                            }
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "bitwise ops need integer inputs".to_string(),
+                                )));
+                            }
+                        };
+                        let shift =
+                            matches!(bitwise, Bitwise::ShiftLeft(_) | Bitwise::ShiftRight(_));
+                        let scale = match shift {
+                            true => inputs[0].out_scale,
+                            false => inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                        };
+                        // the fractional bits are decomposed along with the integer ones
+                        if bits + scale as usize >= 127 {
+                            return Err(Box::new(GraphError::MisformedParams(format!(
+                                "bitwise ops on {} bits at a scale of {} overflow",
+                                bits, scale
+                            ))));
+                        }
+                        let output_max = scale_to_multiplier(scale) * 2f32.powi(bits as i32);
+
+                        match shift {
+                            true => {
+                                let (input_node, shift_node) = (&inputs[0], &inputs[1]);
+                                let k = match &shift_node.const_value {
+                                    Some(k)
+                                        if shift_node.out_scale == 0
+                                            && k.len() == 1
+                                            && k[0] >= 0 =>
+                                    {
+                                        k[0] as usize
+                                    }
+                                    _ => {
+                                        return Err(Box::new(GraphError::MisformedParams(
+                                            "bit shifts need a constant shift".to_string(),
+                                        )));
+                                    }
+                                };
+                                let bitwise = match bitwise {
+                                    Bitwise::ShiftLeft(_) => Bitwise::ShiftLeft(k),
+                                    _ => Bitwise::ShiftRight(k),
+                                };
+
+                                Node {
+                                    idx,
+                                    opkind: OpKind::Poly(PolyOp::Bitwise {
+                                        bitwise,
+                                        bits,
+                                        scale: scale as usize,
+                                    }),
+                                    // the shift is baked into the op
+                                    inputs: node.inputs[0..1].to_vec(),
+                                    in_dims: vec![input_node.out_dims.clone()],
+                                    out_dims: input_node.out_dims.clone(),
+                                    in_scale: scale,
+                                    out_scale: scale,
+                                    output_max,
+                                    ..Default::default()
+                                }
+                            }
+                            false => {
+                                opkind = Self::homogenize_input_scales(
+                                    OpKind::Poly(PolyOp::Bitwise {
+                                        bitwise,
+                                        bits,
+                                        scale: scale as usize,
+                                    }),
+                                    inputs.clone(),
+                                )?;
+                                let out_dims = inputs
+                                    .iter()
+                                    .map(|inp| inp.out_dims.clone())
+                                    .max_by_key(|dims| dims.iter().product::<usize>())
+                                    .unwrap();
+
+                                Node {
+                                    idx,
+                                    opkind,
+                                    inputs: node.inputs.clone(),
+                                    in_dims: inputs
+                                        .iter()
+                                        .map(|inp| inp.out_dims.clone())
+                                        .collect(),
+                                    out_dims,
+                                    in_scale: scale,
+                                    out_scale: scale,
+                                    output_max,
+                                    ..Default::default()
+                                }
+                            }
+                        }
+                    }
+                    PolyOp::Logic { .. } => {
+                        // any non-zero input is true whatever its scale, and the output is a
+                        // boolean, so is at a scale of 0
+                        let out_dims = inputs
+                            .iter()
+                            .map(|inp| inp.out_dims.clone())
+                            .max_by_key(|dims| dims.iter().product::<usize>())
+                            .unwrap();
+
+                        Node {
+                            idx,
+                            opkind,
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims,
+                            in_scale: inputs.iter().map(|input| input.out_scale).max().unwrap(),
+                            out_scale: 0,
+                            output_max: 1.0,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::CumSum { .. } => {
+                        let (input_node, axis_node) = (&inputs[0], &inputs[1]);
+                        let op = Box::new(node.op());
+                        let cumsum_node: &CumSum = match op.downcast_ref::<Box<dyn Expansion>>() {
+                            Some(b) => match (*b).as_any().downcast_ref() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            },
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let axis = match &axis_node.const_value {
+                            Some(a) if axis_node.out_scale == 0 && a.len() == 1 => a[0] as i64,
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "cumsum needs a constant axis".to_string(),
+                                )));
+                            }
+                        };
+                        // the onnx rank includes the batch dim, which our dims don't
+                        let rank = input_node.out_dims.len();
+                        let onnx_rank = match output_shapes.as_deref() {
+                            Some([Some(v)]) => v.len(),
+                            _ => rank,
+                        };
+                        let batch = onnx_rank.saturating_sub(rank);
+                        let axis = ((axis + onnx_rank as i64) % onnx_rank as i64) as usize;
+                        if axis < batch || axis - batch >= rank {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::CumSum {
+                                axis: axis - batch,
+                                exclusive: cumsum_node.exclusive,
+                                reverse: cumsum_node.reverse,
+                            }),
+                            inputs: vec![node.inputs[0]],
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: input_node.out_dims.clone(),
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max
+                                * input_node.out_dims[axis - batch] as f32,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::NonMaxSuppression { .. } => {
+                        // a single batch, with a static number of output rows of which the
+                        // unused ones are -1, and the box indices at a scale of 0
+                        let (boxes_node, scores_node) = (&inputs[0], &inputs[1]);
+                        let nms_node: &TractNms = match node.op().downcast_ref::<TractNms>() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let (box_dims, score_dims) = (&boxes_node.out_dims, &scores_node.out_dims);
+                        if box_dims.len() != 2
+                            || box_dims[1] != 4
+                            || score_dims.len() != 2
+                            || score_dims[1] != box_dims[0]
+                        {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+                        let num_boxes = box_dims[0];
+
+                        let max_boxes = match inputs.get(2).map(|n| &n.raw_const_value) {
+                            Some(Some(k)) if k.len() == 1 && k[0] > 0.0 => k[0] as usize,
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "non max suppression needs a constant, positive max output boxes per class".to_string(),
+                                )));
+                            }
+                        };
+                        let iou_threshold = match inputs.get(3).map(|n| &n.raw_const_value) {
+                            Some(Some(t)) if t.len() == 1 => t[0],
+                            None => 0.0,
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "non max suppression needs a constant iou threshold"
+                                        .to_string(),
+                                )));
+                            }
+                        };
+                        let score_threshold = match inputs.get(4).map(|n| &n.raw_const_value) {
+                            Some(Some(t)) if t.len() == 1 => {
+                                Some((t[0] * scale_to_multiplier(scores_node.out_scale)).round()
+                                    as i128)
+                            }
+                            None => None,
+                            _ => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "non max suppression needs a constant score threshold"
+                                        .to_string(),
+                                )));
+                            }
+                        };
+                        let multiplier = scale_to_multiplier(boxes_node.out_scale);
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::NonMaxSuppression {
+                                params: NmsParams {
+                                    max_boxes,
+                                    iou_threshold: (iou_threshold * multiplier).round() as i128,
+                                    score_threshold,
+                                    center_point_box: matches!(
+                                        nms_node.center_point_box,
+                                        BoxRepr::CenterWidthHeight
+                                    ),
+                                    scale: multiplier as usize,
+                                },
+                            }),
+                            inputs: node.inputs[..2].to_vec(),
+                            in_dims: vec![box_dims.clone(), score_dims.clone()],
+                            out_dims: vec![score_dims[0] * max_boxes.min(num_boxes), 3],
+                            in_scale: boxes_node.out_scale,
+                            out_scale: 0,
+                            output_max: num_boxes as f32,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::If { .. } => {
+                        let if_node: &If = match node.op().downcast_ref::<If>() {
+                            Some(b) => b,
+                            None => {
+                                return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                            }
+                        };
+                        let condition = &inputs[0];
+                        if condition.out_scale != 0
+                            || condition.out_dims.iter().product::<usize>() != 1
+                        {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "if needs a single boolean condition".to_string(),
+                            )));
+                        }
+                        // the branches refer to the if's inputs directly
+                        let bind = |mapping: &[usize]| {
+                            mapping
+                                .iter()
+                                .map(|slot| (*slot, inputs[*slot].clone()))
+                                .collect_vec()
+                        };
+                        let (mut then_branch, then_outputs) = Self::new_subgraph(
+                            &if_node.then_body,
+                            bind(&if_node.then_input_mapping),
+                            scale,
+                        )?;
+                        let (mut else_branch, else_outputs) = Self::new_subgraph(
+                            &if_node.else_body,
+                            bind(&if_node.else_input_mapping),
+                            scale,
+                        )?;
+                        let (x, y) = match (&then_outputs[..], &else_outputs[..]) {
+                            ([x], [y]) if x.out_dims == y.out_dims => (x, y),
+                            _ => {
+                                return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                            }
+                        };
+
+                        // both branches are brought to the same scale, so either can be selected
+                        let out_scale = x.out_scale.max(y.out_scale);
+                        let mut output_max = 0f32;
+                        for (branch, output) in [(&mut then_branch, x), (&mut else_branch, y)] {
+                            let mult = scale_to_multiplier(out_scale - output.out_scale);
+                            output_max = output_max.max(output.output_max * mult);
+                            if output.out_scale < out_scale {
+                                let rescaled = SubgraphNode {
+                                    op: OpKind::Poly(PolyOp::Rescaled {
+                                        inner: Box::new(PolyOp::Identity),
+                                        scale: vec![(0, mult as usize)],
+                                    }),
+                                    inputs: branch.outputs.clone(),
+                                    out_dims: output.out_dims.clone(),
+                                };
+                                branch.outputs = vec![SubgraphInput::Node(branch.nodes.len())];
+                                branch.nodes.push(rescaled);
+                            }
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::If {
+                                then_branch,
+                                else_branch,
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims: x.out_dims.clone(),
+                            in_scale: out_scale,
+                            out_scale,
+                            output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Scan { .. } => {
+                        let scan_node: &InferenceScan =
+                            match node.op().downcast_ref::<InferenceScan>() {
+                                Some(b) => b,
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            };
+                        let body = &scan_node.body;
+
+                        let mut bound = vec![];
+                        let mut scan_inputs = vec![];
+                        let mut iterations = None;
+                        for (k, mapping) in scan_node.input_mapping.iter().enumerate() {
+                            let (slot, input) = match mapping {
+                                InputMapping::Full { slot } => (*slot, ScanInput::Full(*slot)),
+                                InputMapping::State {
+                                    initializer: StateInitializer::FromInput(slot),
+                                } => (*slot, ScanInput::State(*slot)),
+                                InputMapping::Scan { slot, axis, chunk } => {
+                                    let input_node = &inputs[*slot];
+                                    let rank = input_node.out_dims.len();
+                                    // the onnx rank includes the batch dim, which our dims might not
+                                    let onnx_rank = body
+                                        .input_fact(k)?
+                                        .shape
+                                        .rank()
+                                        .concretize()
+                                        .map_or(rank, |r| r as usize);
+                                    let batch = onnx_rank.saturating_sub(rank);
+                                    if chunk.abs() != 1 || *axis < batch || axis - batch >= rank {
+                                        return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                    }
+                                    let len = input_node.out_dims[axis - batch];
+                                    if *iterations.get_or_insert(len) != len {
+                                        return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                    }
+                                    (
+                                        *slot,
+                                        ScanInput::Scan {
+                                            slot: *slot,
+                                            axis: axis - batch,
+                                            reverse: *chunk < 0,
+                                        },
+                                    )
+                                }
+                                _ => {
+                                    return Err(Box::new(GraphError::MisformedParams(
+                                        "scan states should be initialized from inputs".to_string(),
+                                    )));
+                                }
+                            };
+                            let mut body_input = inputs[slot].clone();
+                            if let ScanInput::Scan { axis, .. } = input {
+                                body_input.out_dims[axis] = 1;
+                            }
+                            bound.push((k, body_input));
+                            scan_inputs.push(input);
+                        }
+                        let iterations = match iterations {
+                            Some(n) => n,
+                            None => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "scan needs a scanned input".to_string(),
+                                )));
+                            }
+                        };
+
+                        let (body_graph, outputs) = Self::new_subgraph(body, bound, scale)?;
+
+                        // states are fed back into the body, so they need to keep their dims and scale
+                        let states = scan_inputs.iter().filter_map(|i| match i {
+                            ScanInput::State(slot) => Some(&inputs[*slot]),
+                            _ => None,
+                        });
+                        for (state, output) in states.zip(&outputs) {
+                            if state.out_dims != output.out_dims
+                                || state.out_scale != output.out_scale
+                            {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "scan states should keep their dims and scale".to_string(),
+                                )));
+                            }
+                        }
+
+                        // only the first of the scan's outputs is supported
+                        let mut scan_output = None;
+                        for (j, m) in scan_node.output_mapping.iter().enumerate() {
+                            if m.last_value_slot == Some(0) {
+                                scan_output = Some(ScanOutput::Last(j));
+                            } else if m.full_slot == Some(0) {
+                                let rank = outputs[j].out_dims.len();
+                                let onnx_rank = body
+                                    .output_fact(j)?
+                                    .shape
+                                    .rank()
+                                    .concretize()
+                                    .map_or(rank, |r| r as usize);
+                                let batch = onnx_rank.saturating_sub(rank);
+                                if m.chunk.abs() != 1 || m.axis < batch || m.axis - batch >= rank {
+                                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                }
+                                scan_output = Some(ScanOutput::Stacked {
+                                    output: j,
+                                    axis: m.axis - batch,
+                                    reverse: m.chunk < 0,
+                                });
+                            }
+                        }
+                        let scan_output = match scan_output {
+                            Some(o) => o,
+                            None => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "scan has no output".to_string(),
+                                )));
+                            }
+                        };
+                        let output_node = match &scan_output {
+                            ScanOutput::Last(j) | ScanOutput::Stacked { output: j, .. } => {
+                                &outputs[*j]
+                            }
+                        };
+                        let mut out_dims = output_node.out_dims.clone();
+                        if let ScanOutput::Stacked { axis, .. } = &scan_output {
+                            out_dims[*axis] *= iterations;
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Scan {
+                                params: ScanParams {
+                                    body: body_graph,
+                                    inputs: scan_inputs,
+                                    output: scan_output,
+                                    iterations,
+                                },
+                            }),
+                            inputs: node.inputs.clone(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims,
+                            in_scale: output_node.out_scale,
+                            out_scale: output_node.out_scale,
+                            output_max: output_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Transpose { .. } => {
+                        let input_node = &inputs[0];
+                        let op = Box::new(node.op());
+                        let permute_node: &PermuteAxes =
+                            match op.downcast_ref::<Box<dyn Expansion>>() {
+                                Some(b) => match (*b).as_any().downcast_ref() {
+                                    Some(b) => b,
+                                    None => {
+                                        return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                    }
+                                },
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            };
+                        let rank = input_node.out_dims.len();
+                        // the onnx permutation includes the batch dim if the input's dims don't
+                        let perm = match &permute_node.axes {
+                            Some(axes) if axes.len() == rank + 1 && axes[0] == 0 => {
+                                axes[1..].iter().map(|a| a - 1).collect_vec()
+                            }
+                            Some(axes) if axes.len() == rank => axes.to_vec(),
+                            Some(_) => {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "ezkl doesn't support transposing the batch dimension"
+                                        .to_string(),
+                                )));
+                            }
+                            // no permutation reverses the axes
+                            None => (0..rank).rev().collect_vec(),
+                        };
+                        if !(0..rank).all(|i| perm.contains(&i)) {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Transpose { perm: perm.clone() }),
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims: perm.iter().map(|p| input_node.out_dims[*p]).collect(),
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Slice { .. } => {
+                        let input_node = &inputs[0];
+                        let op = Box::new(node.op());
+                        let slice_node: &StridedSlice =
+                            match op.downcast_ref::<Box<dyn Expansion>>() {
+                                Some(b) => match (*b).as_any().downcast_ref() {
+                                    Some(b) => b,
+                                    None => {
+                                        return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                    }
+                                },
+                                None => {
+                                    return Err(Box::new(GraphError::OpMismatch(idx, opkind)));
+                                }
+                            };
+
+                        // starts, ends, axes and steps are all runtime inputs, which must be constant
+                        let const_input = |i: usize| -> Result<Vec<i64>, Box<dyn Error>> {
+                            match inputs[i].const_value.as_ref() {
+                                Some(c) => Ok(c.iter().map(|x| *x as i64).collect()),
+                                None => Err(Box::new(GraphError::MissingParams(
+                                    "slice constant".to_string(),
+                                ))),
+                            }
+                        };
+                        let (starts, ends) = (const_input(1)?, const_input(2)?);
+                        let rank = input_node.out_dims.len();
+                        // the onnx rank includes the batch dim if the input's dims don't
+                        let onnx_rank = match output_shapes.as_deref() {
+                            Some([Some(v)]) => v.len(),
+                            _ => rank,
+                        };
+                        let axes = match slice_node.optional_axes_input {
+                            Some(i) => const_input(i)?,
+                            None => (0..starts.len() as i64).collect(),
+                        };
+                        let steps = match slice_node.optional_steps_input {
+                            Some(i) => const_input(i)?,
+                            None => vec![1; starts.len()],
+                        };
+                        if ends.len() != starts.len()
+                            || axes.len() != starts.len()
+                            || steps.len() != starts.len()
+                            || steps.contains(&0)
+                        {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+
+                        // axes that aren't sliced are kept whole
+                        let mut full_starts = vec![0; rank];
+                        let mut full_ends =
+                            input_node.out_dims.iter().map(|d| *d as i64).collect_vec();
+                        let mut full_steps = vec![1; rank];
+                        for (i, axis) in axes.iter().enumerate() {
+                            let axis = if *axis < 0 {
+                                axis + onnx_rank as i64
+                            } else {
+                                *axis
+                            };
+                            let axis = axis - (onnx_rank - rank) as i64;
+                            if axis < 0 || axis >= rank as i64 {
+                                return Err(Box::new(GraphError::MisformedParams(
+                                    "ezkl doesn't support slicing the batch dimension".to_string(),
+                                )));
+                            }
+                            let axis = axis as usize;
+                            full_starts[axis] = starts[i];
+                            full_ends[axis] = ends[i];
+                            full_steps[axis] = steps[i];
+                        }
+
+                        let out_dims = (0..rank)
+                            .map(|d| {
+                                slice_indices(
+                                    input_node.out_dims[d],
+                                    full_starts[d],
+                                    full_ends[d],
+                                    full_steps[d],
+                                )
+                                .len()
+                            })
+                            .collect_vec();
+                        if out_dims.contains(&0) {
+                            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                        }
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Slice {
+                                starts: full_starts,
+                                ends: full_ends,
+                                steps: full_steps,
+                            }),
+                            inputs: node.inputs[0..1].to_vec(),
+                            in_dims: vec![input_node.out_dims.clone()],
+                            out_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
+                            ..Default::default()
+                        }
+                    }
+                    PolyOp::Reshape(_) => {
+                        let input_node = &inputs[0];
+                        let shape_const_node = &inputs[1];
+                        let shape_const = match shape_const_node.const_value.as_ref() {
+                            Some(sc) => sc,
+                            None => {
+                                return Err(Box::new(GraphError::MissingParams(
+                                    "shape constant".to_string(),
+                                )));
+                            }
+                        };
+
+                        let mut shapes = &shape_const[0..];
+
+                        // we remove batch dims as we assume single elem batches
+                        if shapes[0] == -1 && shapes.len() > 1 {
+                            shapes = &shapes[1..];
+                        }
+
+                        let new_dims: Result<Vec<usize>, Box<dyn Error>> =
+                            if shapes.iter().all(|x| x > &0) {
+                                let mut res = vec![];
+                                for x in shapes.iter() {
+                                    if x <= &0 {
+                                        return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                    }
+                                    res.push(*x as usize);
+                                }
+                                Ok(res)
+                            } else {
+                                let num_entries: usize = input_node.out_dims.iter().product();
+                                let explicit_prod: i128 =
+                                    shapes.iter().filter(|x| *x > &0).product();
+                                if explicit_prod <= 0 {
+                                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                                }
+                                let inferred = num_entries / (explicit_prod as usize);
+                                let mut new_dims: Vec<usize> = Vec::new();
+                                for i in shapes {
+                                    match i {
+                                        -1 => new_dims.push(inferred),
+                                        0 => continue,
+                                        x => new_dims.push(*x as usize),
+                                    }
+                                }
+                                Ok(new_dims)
+                            };
+
+                        let new_dims = new_dims?;
+
+                        Node {
+                            idx,
+                            opkind: OpKind::Poly(PolyOp::Reshape(new_dims.clone())),
+                            inputs: node.inputs[0..1].to_vec(),
+                            in_dims: inputs.iter().map(|inp| inp.out_dims.clone()).collect(),
+                            out_dims: new_dims,
+                            in_scale: input_node.out_scale,
+                            out_scale: input_node.out_scale,
+                            output_max: input_node.output_max,
                             ..Default::default()
                         }
                     }
@@ -997,9 +3485,13 @@ impl Node {
 
                 match dt {
                     DatumType::F32 => {
-                        let vec = const_node.0.as_slice::<f32>().unwrap().to_vec();
-                        let raw: Tensor<f32> = Tensor::new(Some(&vec), &dims).unwrap();
-                        let t = vector_to_quantized(&vec, &dims, 0f32, scale).unwrap();
+                        // weights are quantized from the loaded tensor directly, without
+                        // intermediate copies. The graph and the raw values still hold them as
+                        // f32 alongside the quantized values, so weights aren't streamed
+                        let values = const_node.0.as_slice::<f32>()?;
+                        let t = vector_to_quantized(values, &dims, 0f32, scale)?;
+                        let mut raw: Tensor<f32> = values.iter().copied().collect();
+                        raw.reshape(&dims);
 
                         Node {
                             idx,
@@ -1018,9 +3510,13 @@ impl Node {
 
                     DatumType::I64 => {
                         // Generally a shape or hyperparam
-                        let vec = const_node.0.as_slice::<i64>().unwrap().to_vec();
-                        let cast: Vec<i128> = vec.iter().map(|x| *x as i128).collect();
-                        let t = Tensor::<i128>::new(Some(&cast), &dims).unwrap();
+                        let mut t: Tensor<i128> = const_node
+                            .0
+                            .as_slice::<i64>()?
+                            .iter()
+                            .map(|x| *x as i128)
+                            .collect();
+                        t.reshape(&dims);
 
                         Node {
                             idx,
@@ -1030,12 +3526,37 @@ impl Node {
                             out_dims: dims,
                             in_scale: scale,
                             out_scale: 0,
-                            output_max: cast.iter().map(|x| x.abs()).max().unwrap() as f32,
+                            output_max: t.iter().map(|x| x.abs()).max().unwrap() as f32,
                             const_value: Some(t),
                             raw_const_value: None,
                             ..Default::default()
                         }
                     }
+
+                    DatumType::I8 | DatumType::U8 | DatumType::I32 => {
+                        // Generally the integer codes, zero points or biases of a quantized model,
+                        // these are kept as is and dequantized by the consuming node
+                        let cast = const_node.0.cast_to::<i64>()?;
+                        let vec = cast.as_slice::<i64>()?;
+                        let mut t: Tensor<i128> = vec.iter().map(|x| *x as i128).collect();
+                        t.reshape(&dims);
+                        let mut raw: Tensor<f32> = vec.iter().map(|x| *x as f32).collect();
+                        raw.reshape(&dims);
+
+                        Node {
+                            idx,
+                            opkind,
+                            inputs: node.inputs.clone(),
+                            in_dims: vec![dims.clone()],
+                            out_dims: dims,
+                            in_scale: scale,
+                            out_scale: 0,
+                            output_max: t.iter().map(|x| x.abs()).max().unwrap() as f32,
+                            const_value: Some(t),
+                            raw_const_value: Some(raw),
+                            ..Default::default()
+                        }
+                    }
                     _ => todo!(),
                 }
             }
@@ -1080,7 +3601,13 @@ impl Node {
 
             OpKind::Unknown(_) => {
                 warn!("{:?} is unknown", opkind);
-                Node::default()
+                // the inputs are kept so that fused patterns can reach through unknown nodes
+                Node {
+                    idx,
+                    opkind,
+                    inputs: node.inputs.clone(),
+                    ..Default::default()
+                }
             }
             _ => {
                 return Err(Box::new(GraphError::UnsupportedOp));
@@ -1089,6 +3616,143 @@ impl Node {
         Ok(mn)
     }
 
+    /// Converts the body of a control flow op into a [Subgraph], returning it along with the nodes
+    /// of its outputs.
+    /// # Arguments:
+    /// * `body` - The body's graph.
+    /// * `bound` - For each of the body's inputs, the index of the subgraph input it refers to and the [Node] it is bound to.
+    /// * `scale` - The denominator in the fixed point representation.
+    fn new_subgraph(
+        body: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        bound: Vec<(usize, Node)>,
+        scale: u32,
+    ) -> Result<(Subgraph, Vec<Node>), Box<dyn Error>> {
+        if bound.len() != body.inputs.len() {
+            return Err(Box::new(GraphError::MisformedParams(
+                "subgraph inputs".to_string(),
+            )));
+        }
+        let mut nodes = BTreeMap::<usize, Node>::new();
+        let mut refs = BTreeMap::<usize, SubgraphInput>::new();
+        for (outlet, (slot, mut input)) in body.inputs.iter().zip(bound) {
+            input.idx = outlet.node;
+            nodes.insert(outlet.node, input);
+            refs.insert(outlet.node, SubgraphInput::Input(slot));
+        }
+
+        let mut subgraph = Subgraph::default();
+        for (i, n) in body.nodes.iter().enumerate() {
+            if nodes.contains_key(&i) {
+                continue;
+            }
+            let node = match n.op().name().as_ref() {
+                // tract adds and removes the scanned axis around scan bodies
+                "AddDims" | "RmDims" | "AddAxis" | "RmAxis" => {
+                    Self::new_unit_axis(body, n, &nodes, i)?
+                }
+                _ => Node::new(n.clone(), &mut nodes, scale, i)?,
+            };
+            match &node.opkind {
+                OpKind::Const => {
+                    let value = match &node.const_value {
+                        Some(v) => v,
+                        None => {
+                            return Err(Box::new(GraphError::MissingParams(
+                                "subgraph constant".to_string(),
+                            )));
+                        }
+                    };
+                    refs.insert(
+                        i,
+                        SubgraphInput::Const {
+                            values: value.iter().cloned().collect(),
+                            dims: value.dims().to_vec(),
+                        },
+                    );
+                }
+                OpKind::Poly(_) | OpKind::Lookup(_) => {
+                    let mut inputs = vec![];
+                    for o in node.inputs.iter() {
+                        match refs.get(&o.node) {
+                            Some(r) => inputs.push(r.clone()),
+                            None => return Err(Box::new(GraphError::MissingNode(o.node))),
+                        }
+                    }
+                    refs.insert(i, SubgraphInput::Node(subgraph.nodes.len()));
+                    subgraph.nodes.push(SubgraphNode {
+                        op: node.opkind.clone(),
+                        inputs,
+                        out_dims: node.out_dims.clone(),
+                    });
+                }
+                _ => return Err(Box::new(GraphError::UnsupportedOp)),
+            }
+            nodes.insert(i, node);
+        }
+
+        let mut outputs = vec![];
+        for o in body.outputs.iter() {
+            match (refs.get(&o.node), nodes.get(&o.node)) {
+                (Some(r), Some(n)) => {
+                    subgraph.outputs.push(r.clone());
+                    outputs.push(n.clone());
+                }
+                _ => return Err(Box::new(GraphError::MissingNode(o.node))),
+            }
+        }
+        Ok((subgraph, outputs))
+    }
+
+    /// Converts an op adding or removing unit axes within a subgraph into a reshape.
+    fn new_unit_axis(
+        body: &Graph<InferenceFact, Box<dyn InferenceOp>>,
+        node: &OnnxNode<InferenceFact, Box<dyn InferenceOp>>,
+        other_nodes: &BTreeMap<usize, Node>,
+        idx: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let outlet = node.inputs[0];
+        let input_node = match other_nodes.get(&outlet.node) {
+            Some(n) => n,
+            None => return Err(Box::new(GraphError::MissingNode(outlet.node))),
+        };
+        let opkind = OpKind::Poly(PolyOp::Reshape(vec![]));
+        let in_shape = node_output_shapes(&body.nodes[outlet.node])?
+            .get(outlet.slot)
+            .cloned()
+            .flatten();
+        let out_shape = node_output_shapes(node)?.first().cloned().flatten();
+        let out_dims = match (in_shape, out_shape) {
+            (Some(u), Some(v)) => {
+                // the onnx shapes include the batch dim, which our dims might not
+                let batch = u.len().saturating_sub(input_node.out_dims.len());
+                if v.len() < batch || v[..batch].iter().any(|d| *d != 1) {
+                    return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+                }
+                v[batch..].to_vec()
+            }
+            _ => {
+                return Err(Box::new(GraphError::MissingParams(
+                    "unit axis shapes".to_string(),
+                )));
+            }
+        };
+        if out_dims.iter().product::<usize>() != input_node.out_dims.iter().product::<usize>() {
+            return Err(Box::new(GraphError::InvalidDims(idx, opkind)));
+        }
+
+        Ok(Node {
+            idx,
+            opkind: OpKind::Poly(PolyOp::Reshape(out_dims.clone())),
+            inputs: vec![outlet],
+            in_dims: vec![input_node.out_dims.clone()],
+            out_dims,
+            in_scale: input_node.out_scale,
+            out_scale: input_node.out_scale,
+            output_max: input_node.output_max,
+            ..Default::default()
+        })
+    }
+
     /// Ensures all inputs to a node have the same fixed point denominator.
     fn homogenize_input_scales(
         opkind: OpKind,
@@ -1126,6 +3790,62 @@ impl Node {
         }
     }
 
+    /// Creates a node bringing the output of `input_node` to `scale`, where the scale of the
+    /// model's layers changes. Lowering the scale divides the input through a lookup, whereas
+    /// raising it multiplies the input by a constant.
+    pub fn rebase(idx: usize, input: OutletId, input_node: &Node, scale: u32) -> Node {
+        let (opkind, output_max) = match input_node.out_scale.cmp(&scale) {
+            Ordering::Greater => {
+                let mult = scale_to_multiplier(input_node.out_scale - scale);
+                (
+                    OpKind::Lookup(LookupOp::Div { denom: F32(mult) }),
+                    input_node.output_max / mult,
+                )
+            }
+            Ordering::Less => {
+                let mult = scale_to_multiplier(scale - input_node.out_scale);
+                (
+                    OpKind::Poly(PolyOp::Rescaled {
+                        inner: Box::new(PolyOp::Identity),
+                        scale: vec![(0, mult as usize)],
+                    }),
+                    input_node.output_max * mult,
+                )
+            }
+            Ordering::Equal => (OpKind::Poly(PolyOp::Identity), input_node.output_max),
+        };
+        info!(
+            "------ rebased node {:?} input {:?}: {:?} -> {:?}",
+            idx, input.node, input_node.out_scale, scale
+        );
+        Node {
+            idx,
+            opkind,
+            inputs: vec![input],
+            in_dims: vec![input_node.out_dims.clone()],
+            out_dims: input_node.out_dims.clone(),
+            in_scale: input_node.out_scale,
+            out_scale: scale,
+            output_max,
+            ..Default::default()
+        }
+    }
+
+    /// The first value of a constant node, as it is in the model: its raw value where it is kept,
+    /// or its quantized value brought back from its scale otherwise (eg. for int64 constants).
+    fn const_scalar(&self) -> Option<f32> {
+        if !self.opkind.is_const() {
+            return None;
+        }
+        if let Some(raw) = &self.raw_const_value {
+            return raw.iter().next().copied();
+        }
+        let mult = scale_to_multiplier(self.out_scale);
+        self.const_value
+            .as_ref()
+            .and_then(|v| v.iter().next().map(|x| *x as f32 / mult))
+    }
+
     fn quantize_const_to_scale(&mut self, scale: u32) -> Result<(), Box<dyn Error>> {
         if !self.opkind.is_const() {
             return Err(Box::new(GraphError::WrongMethod(
@@ -1165,6 +3885,561 @@ impl Node {
         Ok(node)
     }
 
+    /// Creates a 3D pooling node over a C x D x H x W input.
+    fn new_pool3d(
+        idx: usize,
+        node_inputs: Vec<OutletId>,
+        input_node: &mut Node,
+        pool_spec: &PoolSpec,
+        is_max: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let input_node = Self::format_4d_inputs(input_node)?;
+
+        // only support pytorch type formatting for now
+        if pool_spec.data_format != DataFormat::NCHW {
+            return Err(Box::new(GraphError::MissingParams(
+                "data in wrong format".to_string(),
+            )));
+        }
+
+        // strides default to 1 when left unspecified
+        let stride = match &pool_spec.strides {
+            Some(s) => s.to_vec(),
+            None => vec![1; pool_spec.kernel_shape.len()],
+        };
+        let padding = match &pool_spec.padding {
+            PaddingSpec::Explicit(p, _, _) => p,
+            _ => {
+                return Err(Box::new(GraphError::MissingParams("padding".to_string())));
+            }
+        };
+        let kernel_shape = &pool_spec.kernel_shape;
+
+        let padding = (padding[0], padding[1], padding[2]);
+        let stride = (stride[0], stride[1], stride[2]);
+        let pool_dims = (kernel_shape[0], kernel_shape[1], kernel_shape[2]);
+
+        let dims = &input_node.out_dims;
+        let out_depth = (dims[1] + 2 * padding.0 - pool_dims.0) / stride.0 + 1;
+        let out_height = (dims[2] + 2 * padding.1 - pool_dims.1) / stride.1 + 1;
+        let out_width = (dims[3] + 2 * padding.2 - pool_dims.2) / stride.2 + 1;
+
+        let opkind = if is_max {
+            LookupOp::MaxPool3D {
+                padding,
+                stride,
+                pool_dims,
+            }
+        } else {
+            LookupOp::AvgPool3D {
+                padding,
+                stride,
+                pool_dims,
+                remainder: false,
+            }
+        };
+
+        Ok(Node {
+            idx,
+            opkind: OpKind::Lookup(opkind),
+            inputs: node_inputs,
+            in_dims: vec![input_node.out_dims.clone()],
+            out_dims: vec![dims[0], out_depth, out_height, out_width],
+            in_scale: input_node.out_scale,
+            out_scale: input_node.out_scale,
+            output_max: input_node.output_max,
+            ..Default::default()
+        })
+    }
+
+    /// Formats 4d inputs if they have overspecified dims (casting nD -> 4D)
+    fn format_4d_inputs(node: &mut Node) -> Result<&mut Node, Box<dyn Error>> {
+        if node.opkind.is_const() {
+            return Err(Box::new(GraphError::WrongMethod(
+                node.idx,
+                node.opkind.clone(),
+            )));
+        };
+        if node.out_dims.len() > 4 {
+            let dims = &node.out_dims;
+            let channel_dims = &dims[..dims.len() - 4];
+            if channel_dims.iter().any(|d| *d != 1) {
+                return Err(Box::new(GraphError::InvalidDims(
+                    node.idx,
+                    node.opkind.clone(),
+                )));
+            }
+            node.out_dims = dims[dims.len() - 4..].to_vec();
+        }
+        if node.out_dims.len() != 4 {
+            return Err(Box::new(GraphError::InvalidDims(
+                node.idx,
+                node.opkind.clone(),
+            )));
+        }
+        Ok(node)
+    }
+
+    /// Casts the inputs of a Gemm node, `Y = alpha * A' * B' + beta * C`, to the `W x + b` form of
+    /// [PolyOp::Affine]. Transposed weights and the alpha / beta scalars are folded into the constant
+    /// weight and bias nodes, which are then re-quantized at their current scale.
+    fn format_gemm_inputs(
+        gemm: &Gemm,
+        node_inputs: &[OutletId],
+        other_nodes: &mut BTreeMap<usize, Node>,
+        idx: usize,
+        opkind: &OpKind,
+    ) -> Result<(), Box<dyn Error>> {
+        if node_inputs.len() != 3 {
+            return Err(Box::new(GraphError::MissingParams(
+                "gemm without a bias".to_string(),
+            )));
+        }
+
+        // A is a single row (or a single column if transposed) as the batch dim is dropped
+        let input_node = other_nodes.get_mut(&node_inputs[0].node).unwrap();
+        let dims = input_node.out_dims.clone();
+        input_node.out_dims = match (gemm.trans_a, dims.as_slice()) {
+            (_, [k]) => vec![*k],
+            (false, [1, k]) | (true, [k, 1]) => vec![*k],
+            _ => {
+                return Err(Box::new(GraphError::InvalidDims(idx, opkind.clone())));
+            }
+        };
+
+        let weight_node = other_nodes.get_mut(&node_inputs[1].node).unwrap();
+        if !gemm.trans_b || gemm.alpha != 1.0 {
+            let raw = match &weight_node.raw_const_value {
+                Some(raw) if raw.dims().len() == 2 => raw.clone(),
+                _ => {
+                    return Err(Box::new(GraphError::MisformedParams(
+                        "gemm weights should be 2D constants".to_string(),
+                    )));
+                }
+            };
+            // the affine layout expects weights of shape [out, in]
+            let raw = if gemm.trans_b {
+                raw
+            } else {
+                raw.permute(&[1, 0])?
+            };
+            let alpha = gemm.alpha;
+            Self::requantize_const_node(weight_node, raw.map(|x| x * alpha))?;
+        }
+
+        if gemm.beta != 1.0 {
+            let bias_node = other_nodes.get_mut(&node_inputs[2].node).unwrap();
+            let raw = match &bias_node.raw_const_value {
+                Some(raw) => raw.clone(),
+                None => {
+                    return Err(Box::new(GraphError::MisformedParams(
+                        "gemm bias should be a constant".to_string(),
+                    )));
+                }
+            };
+            let beta = gemm.beta;
+            Self::requantize_const_node(bias_node, raw.map(|x| x * beta))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the input of a transpose of a matrix.
+    fn transposed_input(node: &Node, other_nodes: &BTreeMap<usize, Node>) -> Option<OutletId> {
+        match &node.opkind {
+            OpKind::Poly(PolyOp::Transpose { perm }) if perm[..] == [1, 0] => {
+                let input = node.inputs.first()?;
+                match other_nodes.get(&input.node)?.out_dims.len() {
+                    2 => Some(*input),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Fuses `softmax(q k^T / c) v`, as exported from decomposed ONNX ops, into a single
+    /// attention node over the queries, keys and values. The division by `c` is folded into the
+    /// input scale of the softmax, and the decomposed nodes are pruned from the graph as they no
+    /// longer feed into its outputs.
+    fn new_attention(
+        idx: usize,
+        outlets: &[OutletId],
+        inputs: &[Node],
+        other_nodes: &BTreeMap<usize, Node>,
+    ) -> Result<Option<Node>, Box<dyn Error>> {
+        let (probs_node, v_node) = (&inputs[0], &inputs[1]);
+        let probs_scale = match &probs_node.opkind {
+            OpKind::Poly(PolyOp::Softmax { scales }) => scales.1,
+            _ => return Ok(None),
+        };
+
+        let mut scores_node = other_nodes.get(&probs_node.inputs[0].node).unwrap();
+        let mut score_scale = scale_to_multiplier(scores_node.out_scale);
+        if let OpKind::Lookup(LookupOp::Div { denom }) = &scores_node.opkind {
+            score_scale *= f32::from(*denom);
+            scores_node = other_nodes.get(&scores_node.inputs[0].node).unwrap();
+        }
+
+        // the scores should contract the last axis of the queries and keys
+        let equation = match &scores_node.opkind {
+            OpKind::Poly(PolyOp::Einsum(equation)) => equation,
+            _ => return Ok(None),
+        };
+        let indices = EinsumIndices::new(equation)?;
+        let is_scores = indices.inputs.len() == 2
+            && indices.inputs.iter().all(|i| i.len() == 2)
+            && indices.contracted.len() == 1
+            && indices.inputs.iter().all(|i| i[1] == indices.contracted[0])
+            && indices.output == [indices.inputs[0][0], indices.inputs[1][0]];
+        let v_dims = &v_node.out_dims;
+        if !is_scores || v_dims.len() != 2 || v_dims[0] != scores_node.out_dims[1] {
+            return Ok(None);
+        }
+
+        let (q_outlet, k_outlet) = (scores_node.inputs[0], scores_node.inputs[1]);
+        let in_dims = [q_outlet, k_outlet]
+            .iter()
+            .map(|o| other_nodes.get(&o.node).unwrap().out_dims.clone())
+            .chain(std::iter::once(v_dims.clone()))
+            .collect();
+
+        Ok(Some(Node {
+            idx,
+            opkind: OpKind::Poly(PolyOp::Attention {
+                scales: (score_scale.round() as usize, probs_scale),
+            }),
+            inputs: vec![q_outlet, k_outlet, outlets[1]],
+            in_dims,
+            out_dims: vec![scores_node.out_dims[0], v_dims[1]],
+            in_scale: scores_node.in_scale,
+            out_scale: probs_node.out_scale + v_node.out_scale,
+            // the output is a convex combination of the values
+            output_max: probs_scale as f32 * v_node.output_max,
+            ..Default::default()
+        }))
+    }
+
+    /// Replaces the values of a const node, quantizing them at the node's current scale.
+    fn requantize_const_node(node: &mut Node, raw: Tensor<f32>) -> Result<(), Box<dyn Error>> {
+        let t = vector_to_quantized(&raw, raw.dims(), 0f32, node.out_scale)?;
+        node.output_max = t.iter().map(|x| x.abs()).max().unwrap_or(0) as f32;
+        node.out_dims = raw.dims().to_vec();
+        node.in_dims = vec![raw.dims().to_vec()];
+        node.const_value = Some(t);
+        node.raw_const_value = Some(raw);
+        Ok(())
+    }
+
+    /// Creates a node for a QuantizeLinear op. Constants are folded into their integer codes.
+    /// Activations are carried through the circuit without their zero point, so quantizing moves
+    /// them onto the fixed point grid of the model's scale (rounded to the nearest power of 2).
+    /// Saturation to the range of the integer type is not constrained.
+    fn new_quantize_linear(
+        idx: usize,
+        node_inputs: &[OutletId],
+        inputs: &[Node],
+    ) -> Result<Self, Box<dyn Error>> {
+        let input_node = &inputs[0];
+
+        if input_node.opkind.is_const() {
+            // constants are folded into their integer codes, as for an integer initializer
+            let raw = match &input_node.raw_const_value {
+                Some(raw) => raw.clone(),
+                None => {
+                    return Err(Box::new(GraphError::MissingParams(
+                        "quantized constant".to_string(),
+                    )));
+                }
+            };
+            let scales = Self::per_channel_params(&inputs[1], raw.dims())?;
+            let zero_points = match inputs.get(2) {
+                Some(zp) => Self::per_channel_params(zp, raw.dims())?,
+                None => vec![0.0; raw.len()],
+            };
+            let codes = raw
+                .iter()
+                .zip(scales)
+                .zip(zero_points)
+                .map(|((x, s), zp)| (x / s).round() + zp)
+                .collect_vec();
+
+            let mut const_node = input_node.clone();
+            const_node.idx = idx;
+            const_node.inputs = vec![];
+            const_node.out_scale = 0;
+            Self::requantize_const_node(&mut const_node, Tensor::new(Some(&codes), raw.dims())?)?;
+            return Ok(const_node);
+        }
+
+        let out_scale = Self::quantization_scale(&inputs[1])?;
+        let in_scale = input_node.out_scale;
+        let (opkind, output_max) = match out_scale.cmp(&in_scale) {
+            Ordering::Equal => (OpKind::Poly(PolyOp::Identity), input_node.output_max),
+            Ordering::Less => {
+                let denom = scale_to_multiplier(in_scale - out_scale);
+                (
+                    OpKind::Lookup(LookupOp::Div { denom: F32(denom) }),
+                    input_node.output_max / denom,
+                )
+            }
+            Ordering::Greater => {
+                let mult = scale_to_multiplier(out_scale - in_scale);
+                (
+                    OpKind::Poly(PolyOp::Rescaled {
+                        inner: Box::new(PolyOp::Identity),
+                        scale: vec![(0, mult as usize)],
+                    }),
+                    input_node.output_max * mult,
+                )
+            }
+        };
+
+        Ok(Node {
+            idx,
+            opkind,
+            inputs: node_inputs[0..1].to_vec(),
+            in_dims: vec![input_node.out_dims.clone()],
+            out_dims: input_node.out_dims.clone(),
+            in_scale,
+            out_scale,
+            output_max,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a node for a DequantizeLinear op. Integer constants are folded into a const node,
+    /// whereas activations already hold their dequantized value and are passed through.
+    fn new_dequantize_linear(
+        idx: usize,
+        node_inputs: &[OutletId],
+        inputs: &[Node],
+        scale: u32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let input_node = &inputs[0];
+
+        if input_node.opkind.is_const() {
+            let mut const_node = input_node.clone();
+            const_node.idx = idx;
+            const_node.inputs = vec![];
+            Self::dequantize_const_node(&mut const_node, Some(&inputs[1]), inputs.get(2), scale)?;
+            return Ok(const_node);
+        }
+
+        Ok(Node {
+            idx,
+            opkind: OpKind::Poly(PolyOp::Identity),
+            inputs: node_inputs[0..1].to_vec(),
+            in_dims: vec![input_node.out_dims.clone()],
+            out_dims: input_node.out_dims.clone(),
+            in_scale: input_node.out_scale,
+            out_scale: input_node.out_scale,
+            output_max: input_node.output_max,
+            ..Default::default()
+        })
+    }
+
+    /// Folds the quantization params of a QLinearConv node into its weight and bias constants and
+    /// returns the (input, weight, bias) outlets of the equivalent [PolyOp::Conv]. The input and output
+    /// params are dropped: activations are carried at their own scale without a zero point, and the
+    /// output stays at the scale of the accumulator.
+    fn format_qlinear_conv_inputs(
+        node_inputs: &[OutletId],
+        other_nodes: &mut BTreeMap<usize, Node>,
+        scale: u32,
+    ) -> Result<Vec<OutletId>, Box<dyn Error>> {
+        // inputs are x, x_scale, x_zero_point, w, w_scale, w_zero_point, y_scale, y_zero_point, bias
+        let x_scale = other_nodes.get(&node_inputs[1].node).unwrap().clone();
+        let w_scale = other_nodes.get(&node_inputs[4].node).unwrap().clone();
+        let w_zero_point = other_nodes.get(&node_inputs[5].node).unwrap().clone();
+
+        let weight_node = other_nodes.get_mut(&node_inputs[3].node).unwrap();
+        Self::dequantize_const_node(weight_node, Some(&w_scale), Some(&w_zero_point), scale)?;
+
+        let mut outlets = vec![node_inputs[0], node_inputs[3]];
+        if let Some(bias) = node_inputs.get(8) {
+            // the bias is quantized with a scale of x_scale * w_scale and no zero point
+            let x_scale = match &x_scale.raw_const_value {
+                Some(raw) if raw.len() == 1 => raw[0],
+                _ => {
+                    return Err(Box::new(GraphError::MisformedParams(
+                        "qlinear conv input scale".to_string(),
+                    )));
+                }
+            };
+            let mut bias_scale = w_scale;
+            bias_scale.raw_const_value = bias_scale
+                .raw_const_value
+                .map(|raw| raw.map(|s| s * x_scale));
+
+            let bias_node = other_nodes.get_mut(&bias.node).unwrap();
+            Self::dequantize_const_node(bias_node, Some(&bias_scale), None, scale)?;
+            outlets.push(*bias);
+        }
+        Ok(outlets)
+    }
+
+    /// Gets the scale (log base 2) of a per-tensor quantization scale of the model, rounding it to
+    /// the nearest power of 2.
+    fn quantization_scale(scale_node: &Node) -> Result<u32, Box<dyn Error>> {
+        let raw = match &scale_node.raw_const_value {
+            Some(raw) if raw.len() == 1 => raw[0],
+            _ => {
+                return Err(Box::new(GraphError::MisformedParams(
+                    "quantization scale should be a constant scalar".to_string(),
+                )));
+            }
+        };
+        if raw <= 0.0 || raw > 1.0 {
+            return Err(Box::new(GraphError::MisformedParams(
+                "quantization scale should be in (0, 1]".to_string(),
+            )));
+        }
+        let mult = 1.0 / raw;
+        match multiplier_to_scale(mult) {
+            Some(s) => Ok(s),
+            None => {
+                let s = mult.log2().round() as u32;
+                warn!(
+                    "quantization scale {} is not a power of 2, rounding to 2^-{}",
+                    raw, s
+                );
+                Ok(s)
+            }
+        }
+    }
+
+    /// Dequantizes the integer codes of a const node, `(q - zero_point) * scale`. When every scale is
+    /// a power of 2 the codes are kept exact, shifted onto the finest of these scales, otherwise the
+    /// dequantized values are re-quantized at `default_scale`.
+    fn dequantize_const_node(
+        node: &mut Node,
+        scale_node: Option<&Node>,
+        zero_point: Option<&Node>,
+        default_scale: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        let codes = match &node.raw_const_value {
+            Some(raw) => raw.clone(),
+            None => {
+                return Err(Box::new(GraphError::MissingParams(
+                    "integer constant".to_string(),
+                )));
+            }
+        };
+        let dims = codes.dims().to_vec();
+
+        let zero_points = match zero_point {
+            Some(zp) => Self::per_channel_params(zp, &dims)?,
+            None => vec![0.0; codes.len()],
+        };
+        let scales = match scale_node {
+            Some(s) => Self::per_channel_params(s, &dims)?,
+            None => vec![1.0; codes.len()],
+        };
+
+        let codes = codes
+            .iter()
+            .zip(zero_points)
+            .map(|(q, zp)| q - zp)
+            .collect_vec();
+        let raw = Tensor::new(
+            Some(&codes.iter().zip(&scales).map(|(q, s)| q * s).collect_vec()),
+            &dims,
+        )?;
+
+        let exponents: Option<Vec<u32>> = scales
+            .iter()
+            .map(|s| multiplier_to_scale(1.0 / s))
+            .collect();
+        match exponents {
+            Some(exponents) => {
+                let out_scale = exponents.iter().max().copied().unwrap_or(0);
+                let t = codes
+                    .iter()
+                    .zip(exponents)
+                    .map(|(q, e)| (*q as i128) << (out_scale - e))
+                    .collect_vec();
+                let t = Tensor::new(Some(&t), &dims)?;
+                node.out_scale = out_scale;
+                node.output_max = t.iter().map(|x| x.abs()).max().unwrap_or(0) as f32;
+                node.const_value = Some(t);
+                node.raw_const_value = Some(raw);
+            }
+            None => {
+                warn!(
+                    "quantization scales of const node {} are not powers of 2, re-quantizing at scale {}",
+                    node.idx, default_scale
+                );
+                node.out_scale = default_scale;
+                Self::requantize_const_node(node, raw)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Broadcasts per-tensor or per-channel (along the first axis) quantization params to every
+    /// element of a tensor of shape `dims`.
+    fn per_channel_params(node: &Node, dims: &[usize]) -> Result<Vec<f32>, Box<dyn Error>> {
+        let params = match &node.raw_const_value {
+            Some(params) => params,
+            None => {
+                return Err(Box::new(GraphError::MisformedParams(
+                    "quantization params should be constants".to_string(),
+                )));
+            }
+        };
+        let num_elems = dims.iter().product::<usize>();
+        if params.len() == 1 {
+            Ok(vec![params[0]; num_elems])
+        } else if !dims.is_empty() && params.len() == dims[0] {
+            let channel_len = num_elems / dims[0];
+            Ok(params.iter().flat_map(|p| vec![*p; channel_len]).collect())
+        } else {
+            Err(Box::new(GraphError::MisformedParams(
+                "per-channel quantization params".to_string(),
+            )))
+        }
+    }
+
+    /// Drops the indices of an einsum equation which correspond to a batch dim that was removed from
+    /// the inputs.
+    fn format_einsum_equation(
+        equation: &str,
+        in_dims: &[Vec<usize>],
+        idx: usize,
+        opkind: &OpKind,
+    ) -> Result<String, Box<dyn Error>> {
+        let indices = EinsumIndices::new(equation)?;
+        if indices.inputs.len() != in_dims.len() {
+            return Err(Box::new(GraphError::InvalidDims(idx, opkind.clone())));
+        }
+
+        let mut inputs = vec![];
+        for (input, dims) in indices.inputs.iter().zip(in_dims) {
+            if input.len() == dims.len() + 1 {
+                inputs.push(input[1..].to_vec());
+            } else if input.len() == dims.len() {
+                inputs.push(input.clone());
+            } else {
+                return Err(Box::new(GraphError::InvalidDims(idx, opkind.clone())));
+            }
+        }
+        let output: String = indices
+            .output
+            .iter()
+            .filter(|c| inputs.iter().flatten().contains(c))
+            .collect();
+
+        Ok(format!(
+            "{}->{}",
+            inputs
+                .iter()
+                .map(|i| i.iter().collect::<String>())
+                .join(","),
+            output
+        ))
+    }
+
     /// Formats 3d inputs if they have under or overspecified dims (casting 2D -> 3D and nD -> 3D)
     fn format_3d_inputs(mut node: &mut Node) -> Result<&mut Node, Box<dyn Error>> {
         if node.opkind.is_const() {
@@ -0,0 +1,218 @@
+use tract_onnx::pb::{
+    attribute_proto::AttributeType, tensor_proto::DataType, tensor_shape_proto, type_proto,
+    AttributeProto, GraphProto, ModelProto, NodeProto, OperatorSetIdProto, TensorProto,
+    TensorShapeProto, TypeProto, ValueInfoProto,
+};
+
+/// The ONNX opset the translated models are expressed in.
+pub const OPSET: i64 = 13;
+
+/// Accumulates the nodes and initializers of an ONNX graph translated from another format.
+#[derive(Default)]
+pub struct Emitter {
+    nodes: Vec<NodeProto>,
+    initializers: Vec<TensorProto>,
+    count: usize,
+}
+
+/// An integer attribute.
+pub fn int_attr(name: &str, i: i64) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        r#type: AttributeType::Int as i32,
+        i,
+        ..Default::default()
+    }
+}
+
+/// An integer list attribute.
+pub fn ints_attr(name: &str, ints: Vec<i64>) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        r#type: AttributeType::Ints as i32,
+        ints,
+        ..Default::default()
+    }
+}
+
+/// A float attribute.
+pub fn float_attr(name: &str, f: f32) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        r#type: AttributeType::Float as i32,
+        f,
+        ..Default::default()
+    }
+}
+
+/// A string attribute.
+pub fn string_attr(name: &str, s: &str) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        r#type: AttributeType::String as i32,
+        s: s.as_bytes().to_vec(),
+        ..Default::default()
+    }
+}
+
+impl Emitter {
+    /// A fresh name starting with `prefix`.
+    pub fn fresh(&mut self, prefix: &str) -> String {
+        self.count += 1;
+        format!("{}_{}", prefix, self.count)
+    }
+
+    /// Adds a node with a single output, named `output` if given, and returns the output's name.
+    pub fn node(
+        &mut self,
+        op_type: &str,
+        inputs: Vec<String>,
+        attribute: Vec<AttributeProto>,
+        output: Option<&str>,
+    ) -> String {
+        let output = match output {
+            Some(o) => o.to_string(),
+            None => self.fresh(op_type),
+        };
+        self.nodes.push(NodeProto {
+            input: inputs,
+            output: vec![output.clone()],
+            name: output.clone(),
+            op_type: op_type.to_string(),
+            attribute,
+            ..Default::default()
+        });
+        output
+    }
+
+    /// Adds a float initializer called `name`, returning its name.
+    pub fn float_initializer(&mut self, name: &str, values: Vec<f32>, dims: Vec<i64>) -> String {
+        self.initializers.push(TensorProto {
+            name: name.to_string(),
+            dims,
+            data_type: DataType::Float as i32,
+            float_data: values,
+            ..Default::default()
+        });
+        name.to_string()
+    }
+
+    /// Adds a 1D int64 initializer, returning its (fresh) name.
+    pub fn int_initializer(&mut self, values: Vec<i64>) -> String {
+        let name = self.fresh("const");
+        self.initializers.push(TensorProto {
+            name: name.clone(),
+            dims: vec![values.len() as i64],
+            data_type: DataType::Int64 as i32,
+            int64_data: values,
+            ..Default::default()
+        });
+        name
+    }
+
+    /// Clips `x` to [`low`, `high`].
+    pub fn clip(&mut self, x: String, low: f32, high: f32, output: Option<&str>) -> String {
+        let low_name = self.fresh("clip_min");
+        let high_name = self.fresh("clip_max");
+        let low = self.float_initializer(&low_name, vec![low], vec![1]);
+        let high = self.float_initializer(&high_name, vec![high], vec![1]);
+        self.node("Clip", vec![x, low, high], vec![], output)
+    }
+
+    /// Transposes an NHWC tensor to NCHW.
+    pub fn to_nchw(&mut self, x: String) -> String {
+        self.node(
+            "Transpose",
+            vec![x],
+            vec![ints_attr("perm", vec![0, 3, 1, 2])],
+            None,
+        )
+    }
+
+    /// Transposes an NCHW tensor to NHWC.
+    pub fn to_nhwc(&mut self, x: String) -> String {
+        self.node(
+            "Transpose",
+            vec![x],
+            vec![ints_attr("perm", vec![0, 2, 3, 1])],
+            None,
+        )
+    }
+
+    /// Builds the ONNX model of the graph.
+    pub fn build(
+        self,
+        name: &str,
+        producer: &str,
+        inputs: Vec<ValueInfoProto>,
+        outputs: Vec<ValueInfoProto>,
+    ) -> ModelProto {
+        ModelProto {
+            ir_version: 7,
+            opset_import: vec![OperatorSetIdProto {
+                domain: String::new(),
+                version: OPSET,
+            }],
+            producer_name: producer.to_string(),
+            graph: Some(GraphProto {
+                name: name.to_string(),
+                node: self.nodes,
+                initializer: self.initializers,
+                input: inputs,
+                output: outputs,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Permutes the values of a 4D tensor of `dims` by `perm`, returning the permuted dims.
+pub fn permute(values: &[f32], dims: &[i64], perm: [usize; 4]) -> (Vec<f32>, Vec<i64>) {
+    let d = dims.iter().map(|d| *d as usize).collect::<Vec<_>>();
+    let new_dims = perm.map(|p| d[p]);
+    let strides = [d[1] * d[2] * d[3], d[2] * d[3], d[3], 1];
+    let mut permuted = Vec::with_capacity(values.len());
+    for i0 in 0..new_dims[0] {
+        for i1 in 0..new_dims[1] {
+            for i2 in 0..new_dims[2] {
+                for i3 in 0..new_dims[3] {
+                    let index = [i0, i1, i2, i3];
+                    let mut source = 0;
+                    for (k, p) in perm.iter().enumerate() {
+                        source += index[k] * strides[*p];
+                    }
+                    permuted.push(values[source]);
+                }
+            }
+        }
+    }
+    (permuted, new_dims.iter().map(|d| *d as i64).collect())
+}
+
+/// The value info of a float tensor, of shape `shape` if known. Negative dims are symbolic.
+pub fn value_info(name: &str, shape: Option<&[i64]>) -> ValueInfoProto {
+    let shape = shape.map(|s| TensorShapeProto {
+        dim: s
+            .iter()
+            .map(|d| tensor_shape_proto::Dimension {
+                value: Some(match *d {
+                    d if d < 0 => tensor_shape_proto::dimension::Value::DimParam("N".to_string()),
+                    d => tensor_shape_proto::dimension::Value::DimValue(d),
+                }),
+                ..Default::default()
+            })
+            .collect(),
+    });
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                elem_type: DataType::Float as i32,
+                shape,
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Model, Node};
+use crate::circuit::{LookupOp, Op, OpKind};
+use crate::tensor::Tensor;
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+/// The cost of a single node of the model in the circuit.
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeReport {
+    /// The node's unique identifier
+    pub idx: usize,
+    /// The name of the Onnx node the node was loaded from (if any)
+    pub name: String,
+    /// The op of the node
+    pub op: String,
+    /// The dims of the node's output
+    pub out_dims: Vec<usize>,
+    /// The fixed point scale of the node's output
+    pub out_scale: u32,
+    /// The number of advice cells the node lays out, for a single sample of the batch
+    pub advice_cells: usize,
+    /// The estimated number of advice cells a conv node lays out with each of the lowerings it
+    /// can be laid out with (see [crate::circuit::ConvLowering]), whichever it is lowered with
+    pub conv_lowering_cells: BTreeMap<String, usize>,
+    /// The (estimated) number of values looked up by the node
+    pub lookups: usize,
+    /// The lookup tables the node uses
+    pub lookup_tables: Vec<String>,
+    /// The number of inputs the node rescales, or 1 for divisions which rescale the node's input
+    pub rescales: usize,
+    /// The time taken to generate the node's witness, in microseconds, when data is supplied
+    pub witness_gen_us: Option<u128>,
+}
+
+impl Model {
+    /// Reports the cost of each of the nodes of the model in the circuit. The witness of each node
+    /// is timed over the first sample of `inputs` when these are supplied, which hold each sample
+    /// of the batch in turn, input major (as the inputs of the circuit do).
+    pub fn report(
+        &self,
+        inputs: Option<&[Tensor<i128>]>,
+    ) -> Result<Vec<NodeReport>, Box<dyn Error>> {
+        let timings = match inputs {
+            Some(inputs) => self.witness_timings(inputs)?,
+            None => BTreeMap::new(),
+        };
+        let reports = self
+            .nodes
+            .iter()
+            .map(|(idx, node)| {
+                let lookup_tables = self.node_lookups(node);
+                let out_len: usize = node.out_dims.iter().product();
+                let op = match &node.opkind {
+                    OpKind::Lookup(l) => format!("{:?}", l),
+                    opkind => opkind.to_string(),
+                };
+                let conv_lowering_cells = match &node.opkind {
+                    OpKind::Poly(p) => p
+                        .conv_lowering_lens(
+                            node.inputs
+                                .iter()
+                                .map(|i| self.nodes[&i.node].out_dims.clone())
+                                .collect(),
+                        )
+                        .into_iter()
+                        .map(|(lowering, len)| (lowering.to_string(), len))
+                        .collect(),
+                    _ => BTreeMap::new(),
+                };
+                NodeReport {
+                    idx: *idx,
+                    name: self
+                        .model
+                        .nodes
+                        .get(*idx)
+                        .map(|n| n.name.clone())
+                        .unwrap_or_default(),
+                    op,
+                    out_dims: node.out_dims.clone(),
+                    out_scale: node.out_scale,
+                    advice_cells: self.node_var_len(node),
+                    conv_lowering_cells,
+                    // composite ops are assumed to look up each of their outputs once per table
+                    lookups: out_len * lookup_tables.len(),
+                    lookup_tables: lookup_tables.iter().map(|l| format!("{:?}", l)).collect(),
+                    rescales: Self::node_rescales(node),
+                    witness_gen_us: timings.get(idx).copied(),
+                }
+            })
+            .collect();
+        Ok(reports)
+    }
+
+    fn node_rescales(node: &Node) -> usize {
+        match &node.opkind {
+            OpKind::Poly(Op::Rescaled { scale, .. }) => scale
+                .iter()
+                .filter(|(_, multiplier)| *multiplier > 1)
+                .count(),
+            OpKind::Lookup(
+                LookupOp::Div { .. } | LookupOp::Limbed { .. } | LookupOp::DivRem { .. },
+            ) => 1,
+            _ => 0,
+        }
+    }
+
+    /// Times the generation of the witness of each node over the first sample of `inputs`.
+    fn witness_timings(
+        &self,
+        inputs: &[Tensor<i128>],
+    ) -> Result<BTreeMap<usize, u128>, Box<dyn Error>> {
+        let first_samples = inputs
+            .iter()
+            .step_by(self.run_args.batch_size)
+            .cloned()
+            .collect_vec();
+        let mut results = Self::load_sample(&self.nodes, &self.model.inputs, &first_samples, 0, 1)?;
+        let mut timings = BTreeMap::new();
+        for (i, n) in self.nodes.iter().filter(|(_, n)| !n.opkind.is_input()) {
+            let inputs = n
+                .inputs
+                .iter()
+                .map(|i| results.get(&i.node).cloned())
+                .collect::<Option<Vec<_>>>()
+                .ok_or("the inputs of a node should be evaluated before it")?;
+            let now = Instant::now();
+            let output = Self::eval_node(n, inputs).map_err(|e| e as Box<dyn Error>)?;
+            timings.insert(*i, now.elapsed().as_micros());
+            results.insert(*i, output);
+        }
+        Ok(timings)
+    }
+}
+
+/// Saves node reports to `path`, as csv if the path ends in .csv and as json otherwise.
+pub fn save_report(reports: &[NodeReport], path: &Path) -> Result<(), Box<dyn Error>> {
+    let serialized = match path.extension().map_or(false, |e| e == "csv") {
+        true => {
+            // fields holding commas or quotes are quoted
+            let escape = |s: String| match s.contains(',') || s.contains('"') {
+                true => format!("\"{}\"", s.replace('"', "\"\"")),
+                false => s,
+            };
+            let mut csv = String::from(
+                "idx,name,op,out_dims,out_scale,advice_cells,conv_lowering_cells,lookups,lookup_tables,rescales,witness_gen_us\n",
+            );
+            for r in reports {
+                let fields = [
+                    r.idx.to_string(),
+                    r.name.clone(),
+                    r.op.clone(),
+                    r.out_dims.iter().join("x"),
+                    r.out_scale.to_string(),
+                    r.advice_cells.to_string(),
+                    r.conv_lowering_cells
+                        .iter()
+                        .map(|(lowering, cells)| format!("{}:{}", lowering, cells))
+                        .join(" "),
+                    r.lookups.to_string(),
+                    r.lookup_tables.join(" "),
+                    r.rescales.to_string(),
+                    r.witness_gen_us.map(|t| t.to_string()).unwrap_or_default(),
+                ];
+                csv.push_str(&fields.into_iter().map(escape).join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+        false => serde_json::to_string_pretty(reports)?,
+    };
+    let mut file = File::create(path)?;
+    file.write_all(serialized.as_bytes())?;
+    Ok(())
+}
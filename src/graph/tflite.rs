@@ -0,0 +1,615 @@
+use super::onnx_builder::{float_attr, int_attr, ints_attr, permute, value_info, Emitter};
+use super::GraphError;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use tract_onnx::pb::ModelProto;
+
+/// A table of a flatbuffer, whose fields are located through its vtable.
+#[derive(Clone, Copy)]
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+fn read<const N: usize>(buf: &[u8], pos: usize) -> Result<[u8; N], GraphError> {
+    buf.get(pos..pos + N)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| GraphError::MisformedParams("truncated tflite flatbuffer".to_string()))
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<usize, GraphError> {
+    Ok(u32::from_le_bytes(read(buf, pos)?) as usize)
+}
+
+impl<'a> Table<'a> {
+    fn root(buf: &'a [u8]) -> Result<Self, GraphError> {
+        Ok(Table {
+            buf,
+            pos: read_u32(buf, 0)?,
+        })
+    }
+
+    /// The position of field `i`, or `None` if the field is absent.
+    fn field(&self, i: usize) -> Result<Option<usize>, GraphError> {
+        let soffset = i32::from_le_bytes(read(self.buf, self.pos)?);
+        let vtable = (self.pos as i64 - soffset as i64) as usize;
+        let vtable_len = u16::from_le_bytes(read(self.buf, vtable)?) as usize;
+        if 4 + 2 * i >= vtable_len {
+            return Ok(None);
+        }
+        let offset = u16::from_le_bytes(read(self.buf, vtable + 4 + 2 * i)?) as usize;
+        Ok((offset != 0).then_some(self.pos + offset))
+    }
+
+    fn scalar<const N: usize>(&self, i: usize) -> Result<Option<[u8; N]>, GraphError> {
+        self.field(i)?.map(|p| read(self.buf, p)).transpose()
+    }
+
+    fn u8(&self, i: usize, default: u8) -> Result<u8, GraphError> {
+        Ok(self.scalar::<1>(i)?.map_or(default, |b| b[0]))
+    }
+
+    fn i32(&self, i: usize, default: i32) -> Result<i32, GraphError> {
+        Ok(self.scalar(i)?.map_or(default, i32::from_le_bytes))
+    }
+
+    fn u32(&self, i: usize, default: u32) -> Result<u32, GraphError> {
+        Ok(self.scalar(i)?.map_or(default, u32::from_le_bytes))
+    }
+
+    fn f32(&self, i: usize, default: f32) -> Result<f32, GraphError> {
+        Ok(self.scalar(i)?.map_or(default, f32::from_le_bytes))
+    }
+
+    /// Follows the offset stored in field `i`.
+    fn indirect(&self, i: usize) -> Result<Option<usize>, GraphError> {
+        self.field(i)?
+            .map(|p| Ok(p + read_u32(self.buf, p)?))
+            .transpose()
+    }
+
+    fn table(&self, i: usize) -> Result<Option<Table<'a>>, GraphError> {
+        Ok(self.indirect(i)?.map(|pos| Table { buf: self.buf, pos }))
+    }
+
+    /// The start and length of the vector in field `i`.
+    fn vector(&self, i: usize) -> Result<(usize, usize), GraphError> {
+        match self.indirect(i)? {
+            Some(v) => Ok((v + 4, read_u32(self.buf, v)?)),
+            None => Ok((0, 0)),
+        }
+    }
+
+    fn bytes(&self, i: usize) -> Result<&'a [u8], GraphError> {
+        let (start, len) = self.vector(i)?;
+        self.buf
+            .get(start..start + len)
+            .ok_or_else(|| GraphError::MisformedParams("truncated tflite flatbuffer".to_string()))
+    }
+
+    fn string(&self, i: usize) -> Result<String, GraphError> {
+        Ok(String::from_utf8_lossy(self.bytes(i)?).to_string())
+    }
+
+    fn tables(&self, i: usize) -> Result<Vec<Table<'a>>, GraphError> {
+        let (start, len) = self.vector(i)?;
+        (0..len)
+            .map(|j| {
+                let p = start + 4 * j;
+                Ok(Table {
+                    buf: self.buf,
+                    pos: p + read_u32(self.buf, p)?,
+                })
+            })
+            .collect()
+    }
+
+    fn vec<T, const N: usize>(&self, i: usize, f: fn([u8; N]) -> T) -> Result<Vec<T>, GraphError> {
+        let (start, len) = self.vector(i)?;
+        (0..len)
+            .map(|j| Ok(f(read(self.buf, start + N * j)?)))
+            .collect()
+    }
+}
+
+/// The subset of the TFLite builtin operators which are translated.
+mod builtin {
+    pub const ADD: i32 = 0;
+    pub const AVERAGE_POOL_2D: i32 = 1;
+    pub const CONCATENATION: i32 = 2;
+    pub const CONV_2D: i32 = 3;
+    pub const DEPTHWISE_CONV_2D: i32 = 4;
+    pub const DEQUANTIZE: i32 = 6;
+    pub const FULLY_CONNECTED: i32 = 9;
+    pub const LOGISTIC: i32 = 14;
+    pub const MAX_POOL_2D: i32 = 17;
+    pub const MUL: i32 = 18;
+    pub const RELU: i32 = 19;
+    pub const RELU6: i32 = 21;
+    pub const RESHAPE: i32 = 22;
+    pub const SOFTMAX: i32 = 25;
+    pub const TANH: i32 = 28;
+    pub const PAD: i32 = 34;
+    pub const TRANSPOSE: i32 = 39;
+    pub const MEAN: i32 = 40;
+    pub const SUB: i32 = 41;
+    pub const SQUEEZE: i32 = 43;
+    pub const EXP: i32 = 47;
+    pub const LOG: i32 = 73;
+    pub const SQRT: i32 = 75;
+    pub const RSQRT: i32 = 76;
+    pub const LEAKY_RELU: i32 = 98;
+    pub const QUANTIZE: i32 = 114;
+}
+
+/// A tensor of a TFLite subgraph.
+struct TfliteTensor {
+    name: String,
+    shape: Vec<i64>,
+    /// The (dequantized) values of the tensor, if it is a constant
+    data: Option<Vec<f32>>,
+}
+
+impl TfliteTensor {
+    fn new(tensor: Table, buffers: &[Table], i: usize) -> Result<Self, GraphError> {
+        let shape = tensor.vec(0, i32::from_le_bytes)?;
+        let name = match tensor.string(3)? {
+            name if name.is_empty() => format!("tensor_{}", i),
+            name => name,
+        };
+        let bytes = match buffers.get(tensor.u32(2, 0)? as usize) {
+            Some(buffer) => buffer.bytes(0)?,
+            None => &[],
+        };
+        let data = match bytes.is_empty() {
+            true => None,
+            false => Some(Self::dequantize(tensor, bytes, &shape)?),
+        };
+        Ok(TfliteTensor {
+            name,
+            shape: shape.into_iter().map(|d| d as i64).collect(),
+            data,
+        })
+    }
+
+    /// Decodes the values of a constant tensor, dequantizing those with quantization params.
+    fn dequantize(tensor: Table, bytes: &[u8], shape: &[i32]) -> Result<Vec<f32>, GraphError> {
+        let values: Vec<f32> = match tensor.u8(1, 0)? {
+            // float32
+            0 => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+            // int32
+            2 => bytes
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes(b.try_into().unwrap()) as f32)
+                .collect(),
+            // uint8
+            3 => bytes.iter().map(|b| *b as f32).collect(),
+            // int64
+            4 => bytes
+                .chunks_exact(8)
+                .map(|b| i64::from_le_bytes(b.try_into().unwrap()) as f32)
+                .collect(),
+            // int8
+            9 => bytes.iter().map(|b| *b as i8 as f32).collect(),
+            t => {
+                return Err(GraphError::MisformedParams(format!(
+                    "tflite tensor type {} is not supported",
+                    t
+                )))
+            }
+        };
+        let quantization = match tensor.table(4)? {
+            Some(q) => q,
+            None => return Ok(values),
+        };
+        let scales = quantization.vec(2, f32::from_le_bytes)?;
+        let zero_points = quantization.vec(3, i64::from_le_bytes)?;
+        if scales.is_empty() {
+            return Ok(values);
+        }
+        // per channel params apply along the quantized dimension
+        let axis = quantization.i32(6, 0)? as usize;
+        let stride: usize = shape.iter().skip(axis + 1).map(|d| *d as usize).product();
+        Ok(values
+            .iter()
+            .enumerate()
+            .map(|(j, v)| {
+                let channel = match scales.len() {
+                    1 => 0,
+                    n => (j / stride.max(1)) % n,
+                };
+                let zero_point = zero_points.get(channel).copied().unwrap_or(0) as f32;
+                (v - zero_point) * scales[channel]
+            })
+            .collect())
+    }
+}
+
+/// Applies a TFLite fused activation function to `x`.
+fn fused_activation(
+    e: &mut Emitter,
+    x: String,
+    activation: u8,
+    output: &str,
+) -> Result<String, GraphError> {
+    let output = Some(output);
+    Ok(match activation {
+        0 => e.node("Identity", vec![x], vec![], output),
+        1 => e.node("Relu", vec![x], vec![], output),
+        2 => e.clip(x, -1.0, 1.0, output),
+        3 => e.clip(x, 0.0, 6.0, output),
+        4 => e.node("Tanh", vec![x], vec![], output),
+        a => {
+            return Err(GraphError::MisformedParams(format!(
+                "tflite fused activation {} is not supported",
+                a
+            )))
+        }
+    })
+}
+
+/// The explicit ONNX pads (top, left, bottom, right) of a TFLite 2D window op, whose padding is
+/// either SAME (0) or VALID (1).
+fn pads(
+    padding: u8,
+    input: &[i64],
+    kernel: (i64, i64),
+    stride: (i64, i64),
+    dilation: (i64, i64),
+) -> Vec<i64> {
+    if padding != 0 || input.len() != 4 {
+        return vec![0; 4];
+    }
+    let total = |size: i64, k: i64, s: i64, d: i64| {
+        let out = (size + s - 1) / s;
+        ((out - 1) * s + (k - 1) * d + 1 - size).max(0)
+    };
+    let h = total(input[1], kernel.0, stride.0, dilation.0);
+    let w = total(input[2], kernel.1, stride.1, dilation.1);
+    vec![h / 2, w / 2, h - h / 2, w - w / 2]
+}
+
+/// Translates the first subgraph of a TFLite model into an ONNX model, such that it is loaded
+/// into the same graph representation as ONNX models are. Activations stay in the NHWC layout of
+/// TFLite, and are transposed to NCHW around convolutions and pools. Quantized weights are
+/// dequantized, and quantization ops become identities.
+pub fn to_onnx(path: &Path) -> Result<ModelProto, Box<dyn Error>> {
+    let buf = std::fs::read(path)?;
+    if buf.get(4..8) != Some(&b"TFL3"[..]) {
+        return Err(Box::new(GraphError::MisformedParams(
+            "not a tflite model (missing TFL3 identifier)".to_string(),
+        )));
+    }
+    let model = Table::root(&buf)?;
+    let opcodes = model
+        .tables(1)?
+        .iter()
+        .map(|c| Ok(c.i32(3, 0)?.max(c.u8(0, 0)? as i8 as i32)))
+        .collect::<Result<Vec<_>, GraphError>>()?;
+    let buffers = model.tables(4)?;
+    let subgraph = *model
+        .tables(2)?
+        .first()
+        .ok_or_else(|| GraphError::MisformedParams("tflite model has no subgraph".to_string()))?;
+
+    let tensors = subgraph
+        .tables(0)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, t)| TfliteTensor::new(t, &buffers, i))
+        .collect::<Result<Vec<_>, _>>()?;
+    // tensor names should be unique in the translated graph
+    let mut names: BTreeMap<String, usize> = BTreeMap::new();
+    let names = tensors
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let count = names.entry(t.name.clone()).or_insert(0);
+            *count += 1;
+            match *count {
+                1 => t.name.clone(),
+                _ => format!("{}_{}", t.name, i),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut e = Emitter::default();
+    for (t, name) in tensors.iter().zip(names.iter()) {
+        if let Some(data) = &t.data {
+            e.float_initializer(name, data.clone(), t.shape.clone());
+        }
+    }
+
+    for op in subgraph.tables(3)? {
+        let code = *opcodes.get(op.u32(0, 0)? as usize).ok_or_else(|| {
+            GraphError::MisformedParams("tflite op has an unknown opcode".to_string())
+        })?;
+        let op_inputs = op.vec(1, i32::from_le_bytes)?;
+        let op_outputs = op.vec(2, i32::from_le_bytes)?;
+        let options = op.table(4)?;
+        // absent options, as well as options fields equal to their defaults, are not serialized
+        let int = |i: usize, default: i32| -> Result<i64, GraphError> {
+            Ok(options.map_or(Ok(default), |o| o.i32(i, default))? as i64)
+        };
+        let byte = |i: usize| -> Result<u8, GraphError> { options.map_or(Ok(0), |o| o.u8(i, 0)) };
+
+        // optional inputs are marked by -1
+        let input = |j: usize| -> Option<usize> {
+            op_inputs
+                .get(j)
+                .and_then(|i| (*i >= 0).then_some(*i as usize))
+        };
+        let name = |j: usize| input(j).map(|i| names[i].clone()).unwrap_or_default();
+        let shape = |j: usize| {
+            input(j)
+                .map(|i| tensors[i].shape.clone())
+                .unwrap_or_default()
+        };
+        let data = |j: usize| input(j).and_then(|i| tensors[i].data.clone());
+        let ints = |j: usize| -> Result<Vec<i64>, GraphError> {
+            data(j)
+                .map(|d| d.iter().map(|v| *v as i64).collect())
+                .ok_or_else(|| {
+                    GraphError::MisformedParams("tflite op param should be constant".to_string())
+                })
+        };
+        let output = names[*op_outputs
+            .first()
+            .ok_or_else(|| GraphError::MisformedParams("tflite op has no output".to_string()))?
+            as usize]
+            .clone();
+
+        match code {
+            builtin::ADD | builtin::SUB | builtin::MUL => {
+                let op_type = match code {
+                    builtin::ADD => "Add",
+                    builtin::SUB => "Sub",
+                    _ => "Mul",
+                };
+                let y = e.node(op_type, vec![name(0), name(1)], vec![], None);
+                fused_activation(&mut e, y, byte(0)?, &output)?;
+            }
+            builtin::CONV_2D | builtin::DEPTHWISE_CONV_2D => {
+                let depthwise = code == builtin::DEPTHWISE_CONV_2D;
+                let (stride, dilation, activation) = match depthwise {
+                    false => ((int(2, 1)?, int(1, 1)?), (int(5, 1)?, int(4, 1)?), byte(3)?),
+                    true => ((int(2, 1)?, int(1, 1)?), (int(6, 1)?, int(5, 1)?), byte(4)?),
+                };
+                let (kernel, kernel_dims) = (
+                    data(1).ok_or_else(|| {
+                        GraphError::MisformedParams("tflite conv kernel should be constant".into())
+                    })?,
+                    shape(1),
+                );
+                // tflite kernels are [out, h, w, in] ([1, h, w, out] for depthwise convs)
+                let perm = if depthwise {
+                    [3, 0, 1, 2]
+                } else {
+                    [0, 3, 1, 2]
+                };
+                let (kernel, kernel_dims) = permute(&kernel, &kernel_dims, perm);
+                let kernel_name = e.fresh("kernel");
+                let kernel_name = e.float_initializer(&kernel_name, kernel, kernel_dims.clone());
+                let group = if depthwise {
+                    shape(0).last().copied().unwrap_or(1)
+                } else {
+                    1
+                };
+                let mut inputs = vec![e.to_nchw(name(0)), kernel_name];
+                if input(2).is_some() {
+                    inputs.push(name(2));
+                }
+                let pads = pads(
+                    byte(0)?,
+                    &shape(0),
+                    (kernel_dims[2], kernel_dims[3]),
+                    stride,
+                    dilation,
+                );
+                let y = e.node(
+                    "Conv",
+                    inputs,
+                    vec![
+                        ints_attr("strides", vec![stride.0, stride.1]),
+                        ints_attr("dilations", vec![dilation.0, dilation.1]),
+                        ints_attr("pads", pads),
+                        int_attr("group", group),
+                    ],
+                    None,
+                );
+                let y = e.to_nhwc(y);
+                fused_activation(&mut e, y, activation, &output)?;
+            }
+            builtin::AVERAGE_POOL_2D | builtin::MAX_POOL_2D => {
+                let stride = (int(2, 1)?, int(1, 1)?);
+                let kernel = (int(4, 1)?, int(3, 1)?);
+                let pads = pads(byte(0)?, &shape(0), kernel, stride, (1, 1));
+                let op_type = match code {
+                    builtin::MAX_POOL_2D => "MaxPool",
+                    _ => "AveragePool",
+                };
+                let x = e.to_nchw(name(0));
+                let y = e.node(
+                    op_type,
+                    vec![x],
+                    vec![
+                        ints_attr("kernel_shape", vec![kernel.0, kernel.1]),
+                        ints_attr("strides", vec![stride.0, stride.1]),
+                        ints_attr("pads", pads),
+                    ],
+                    None,
+                );
+                let y = e.to_nhwc(y);
+                fused_activation(&mut e, y, byte(5)?, &output)?;
+            }
+            builtin::FULLY_CONNECTED => {
+                let mut x = name(0);
+                if shape(0).len() != 2 {
+                    x = e.node("Flatten", vec![x], vec![int_attr("axis", 1)], None);
+                }
+                let mut inputs = vec![x, name(1)];
+                if input(2).is_some() {
+                    inputs.push(name(2));
+                }
+                // tflite weights are [out, in]
+                let y = e.node("Gemm", inputs, vec![int_attr("transB", 1)], None);
+                fused_activation(&mut e, y, byte(0)?, &output)?;
+            }
+            builtin::CONCATENATION => {
+                let inputs = (0..op_inputs.len()).map(name).collect();
+                let axis = int(0, 0)?;
+                let y = e.node("Concat", inputs, vec![int_attr("axis", axis)], None);
+                fused_activation(&mut e, y, byte(1)?, &output)?;
+            }
+            builtin::RESHAPE => {
+                let new_shape = match input(1) {
+                    Some(_) => ints(1)?,
+                    None => match options {
+                        Some(o) => o
+                            .vec(0, i32::from_le_bytes)?
+                            .iter()
+                            .map(|d| *d as i64)
+                            .collect(),
+                        None => {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "tflite reshape has no shape".to_string(),
+                            )))
+                        }
+                    },
+                };
+                let new_shape = e.int_initializer(new_shape);
+                e.node("Reshape", vec![name(0), new_shape], vec![], Some(&output));
+            }
+            builtin::SOFTMAX => {
+                let beta = options.map_or(Ok(1.0), |o| o.f32(0, 1.0))?;
+                let mut x = name(0);
+                if beta != 1.0 {
+                    let beta_name = e.fresh("beta");
+                    let beta = e.float_initializer(&beta_name, vec![beta], vec![1]);
+                    x = e.node("Mul", vec![x, beta], vec![], None);
+                }
+                e.node(
+                    "Softmax",
+                    vec![x],
+                    vec![int_attr("axis", -1)],
+                    Some(&output),
+                );
+            }
+            builtin::MEAN => {
+                let keep_dims = byte(0)? as i64;
+                e.node(
+                    "ReduceMean",
+                    vec![name(0)],
+                    vec![ints_attr("axes", ints(1)?), int_attr("keepdims", keep_dims)],
+                    Some(&output),
+                );
+            }
+            builtin::PAD => {
+                // tflite paddings are [[before, after]; rank], onnx pads are [befores.., afters..]
+                let paddings = ints(1)?;
+                let pads = paddings
+                    .iter()
+                    .step_by(2)
+                    .chain(paddings.iter().skip(1).step_by(2))
+                    .copied()
+                    .collect();
+                let pads = e.int_initializer(pads);
+                e.node("Pad", vec![name(0), pads], vec![], Some(&output));
+            }
+            builtin::TRANSPOSE => {
+                e.node(
+                    "Transpose",
+                    vec![name(0)],
+                    vec![ints_attr("perm", ints(1)?)],
+                    Some(&output),
+                );
+            }
+            builtin::SQUEEZE => {
+                let axes = match options {
+                    Some(o) => o
+                        .vec(0, i32::from_le_bytes)?
+                        .iter()
+                        .map(|d| *d as i64)
+                        .collect(),
+                    None => vec![],
+                };
+                let mut inputs = vec![name(0)];
+                if !axes.is_empty() {
+                    inputs.push(e.int_initializer(axes));
+                }
+                e.node("Squeeze", inputs, vec![], Some(&output));
+            }
+            builtin::LOGISTIC => {
+                e.node("Sigmoid", vec![name(0)], vec![], Some(&output));
+            }
+            builtin::TANH => {
+                e.node("Tanh", vec![name(0)], vec![], Some(&output));
+            }
+            builtin::EXP => {
+                e.node("Exp", vec![name(0)], vec![], Some(&output));
+            }
+            builtin::LOG => {
+                e.node("Log", vec![name(0)], vec![], Some(&output));
+            }
+            builtin::SQRT => {
+                e.node("Sqrt", vec![name(0)], vec![], Some(&output));
+            }
+            // fused into a single rsqrt lookup when the model is loaded
+            builtin::RSQRT => {
+                let x = e.node("Sqrt", vec![name(0)], vec![], None);
+                e.node("Reciprocal", vec![x], vec![], Some(&output));
+            }
+            builtin::RELU => {
+                e.node("Relu", vec![name(0)], vec![], Some(&output));
+            }
+            builtin::RELU6 => {
+                fused_activation(&mut e, name(0), 3, &output)?;
+            }
+            builtin::LEAKY_RELU => {
+                let alpha = options.map_or(Ok(0.0), |o| o.f32(0, 0.0))?;
+                e.node(
+                    "LeakyRelu",
+                    vec![name(0)],
+                    vec![float_attr("alpha", alpha)],
+                    Some(&output),
+                );
+            }
+            builtin::QUANTIZE | builtin::DEQUANTIZE => {
+                e.node("Identity", vec![name(0)], vec![], Some(&output));
+            }
+            c => {
+                return Err(Box::new(GraphError::MisformedParams(format!(
+                    "tflite builtin op {} is not supported",
+                    c
+                ))))
+            }
+        }
+    }
+
+    let inputs = subgraph
+        .vec(1, i32::from_le_bytes)?
+        .iter()
+        .map(|i| {
+            value_info(
+                &names[*i as usize],
+                Some(tensors[*i as usize].shape.as_slice()),
+            )
+        })
+        .collect();
+    let outputs = subgraph
+        .vec(2, i32::from_le_bytes)?
+        .iter()
+        .map(|i| value_info(&names[*i as usize], None))
+        .collect();
+
+    Ok(e.build(
+        &subgraph.string(4)?,
+        "ezkl tflite importer",
+        inputs,
+        outputs,
+    ))
+}
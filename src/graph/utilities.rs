@@ -4,25 +4,58 @@ use tract_onnx::prelude::{InferenceFact, Node};
 use tract_onnx::tract_hir::internal::InferenceOp;
 
 // Warning: currently ignores stride information
-/// Quantizes an iterable of f32s to a [Tensor] of i32s using a fixed point representation.
+/// Quantizes a slice of f32s (or f64s) to a [Tensor] of i128s using a fixed point representation.
 /// Arguments
 ///
 /// * `vec` - the vector to quantize.
 /// * `dims` - the dimensionality of the resulting [Tensor].
 /// * `shift` - offset used in the fixed point representation.
 /// * `scale` - `2^scale` used in the fixed point representation.
-pub fn vector_to_quantized(
-    vec: &[f32],
+pub fn vector_to_quantized<T: Copy + Into<f64>>(
+    vec: &[T],
     dims: &[usize],
     shift: f32,
     scale: u32,
 ) -> Result<Tensor<i128>, TensorError> {
-    let mult = scale_to_multiplier(scale);
-    let scaled: Vec<i128> = vec
+    if dims.iter().product::<usize>() != vec.len() {
+        return Err(TensorError::DimError);
+    }
+    // f64 data keeps its full precision through quantization
+    let mult = scale_to_multiplier(scale) as f64;
+    // values are quantized straight into the tensor, without an intermediate copy
+    let mut scaled: Tensor<i128> = vec
         .iter()
-        .map(|e| (mult * e + shift).round() as i128)
+        .map(|e| (mult * (*e).into() + shift as f64).round() as i128)
         .collect();
-    Tensor::new(Some(&scaled), dims)
+    scaled.reshape(dims);
+    Ok(scaled)
+}
+
+/// Converts the bits of an IEEE 754 half precision float to an f32.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        e => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(e - 15),
+    }
+}
+
+/// Converts the bits of a bfloat16 (the upper half of an f32) to an f32.
+pub fn bf16_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+/// The `p`-th percentile (in [0, 100]) of `sorted`, which should be sorted in ascending order.
+pub fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = p.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f32;
+    sorted[rank.round() as usize]
 }
 
 /// Converts a scale (log base 2) to a fixed point multiplier.
@@ -30,6 +63,16 @@ pub fn scale_to_multiplier(scale: u32) -> f32 {
     i32::pow(2, scale) as f32
 }
 
+/// Converts a fixed point multiplier to a scale (log base 2), if the multiplier is a power of 2.
+pub fn multiplier_to_scale(mult: f32) -> Option<u32> {
+    let scale = mult.log2();
+    if scale >= 0.0 && scale.fract() == 0.0 {
+        Some(scale as u32)
+    } else {
+        None
+    }
+}
+
 /// Gets the shape of a onnx node's outlets.
 pub fn node_output_shapes(
     node: &Node<InferenceFact, Box<dyn InferenceOp>>,
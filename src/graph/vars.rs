@@ -1,39 +1,71 @@
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::str::FromStr;
 
 use crate::commands::RunArgs;
 use crate::tensor::TensorType;
 use crate::tensor::{ValTensor, VarTensor};
 use halo2_proofs::{arithmetic::FieldExt, plonk::ConstraintSystem};
 use itertools::Itertools;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use super::GraphError;
 
 /// Label Enum to track whether model input, model parameters, and model output are public or private
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Visibility {
     /// Mark an item as private to the prover (not in the proof submitted for verification)
     Private,
     /// Mark an item as public (sent in the proof submitted for verification)
     Public,
+    /// Mark an item as private to the prover, with only a hash of it being public
+    Hashed,
+    /// Mark an item as fixed in the circuit, such that the verifying key commits to it
+    Fixed,
 }
 impl Visibility {
     #[allow(missing_docs)]
     pub fn is_public(&self) -> bool {
         matches!(&self, Visibility::Public)
     }
+    #[allow(missing_docs)]
+    pub fn is_hashed(&self) -> bool {
+        matches!(&self, Visibility::Hashed)
+    }
+    #[allow(missing_docs)]
+    pub fn is_fixed(&self) -> bool {
+        matches!(&self, Visibility::Fixed)
+    }
 }
 impl std::fmt::Display for Visibility {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Visibility::Private => write!(f, "private"),
             Visibility::Public => write!(f, "public"),
+            Visibility::Hashed => write!(f, "hashed"),
+            Visibility::Fixed => write!(f, "fixed"),
+        }
+    }
+}
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "private" => Ok(Visibility::Private),
+            "public" => Ok(Visibility::Public),
+            "hashed" => Ok(Visibility::Hashed),
+            "fixed" => Ok(Visibility::Fixed),
+            _ => Err(format!(
+                "expected public, private, hashed or fixed, got {}",
+                s
+            )),
         }
     }
 }
 
 /// Whether the model input, model parameters, and model output are Public or Private to the prover.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct VarVisibility {
     /// Input to the model or computational graph
     pub input: Visibility,
@@ -41,14 +73,21 @@ pub struct VarVisibility {
     pub params: Visibility,
     /// Output of the model or computational graph
     pub output: Visibility,
+    /// Overrides of the visibility of individual inputs and outputs, by name
+    #[serde(default)]
+    pub tensors: BTreeMap<String, Visibility>,
 }
 impl std::fmt::Display for VarVisibility {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "(inputs: {}, params: {}, outputs: {})",
+            "(inputs: {}, params: {}, outputs: {}",
             self.input, self.params, self.output
-        )
+        )?;
+        for (name, visibility) in self.tensors.iter() {
+            write!(f, ", {}: {}", name, visibility)?;
+        }
+        write!(f, ")")
     }
 }
 
@@ -58,28 +97,60 @@ impl VarVisibility {
     pub fn from_args(args: RunArgs) -> Result<Self, Box<dyn Error>> {
         let input_vis = if args.public_inputs {
             Visibility::Public
+        } else if args.hashed_inputs {
+            Visibility::Hashed
         } else {
             Visibility::Private
         };
         let params_vis = if args.public_params {
             Visibility::Public
+        } else if args.hashed_params {
+            Visibility::Hashed
+        } else if args.fixed_params {
+            Visibility::Fixed
         } else {
             Visibility::Private
         };
-        let output_vis = if args.public_outputs {
+        let output_vis = if args.hashed_outputs {
+            Visibility::Hashed
+        } else if args.public_outputs {
             Visibility::Public
         } else {
             Visibility::Private
         };
-        if !output_vis.is_public() & !params_vis.is_public() & !input_vis.is_public() {
+        let tensors: BTreeMap<String, Visibility> = args
+            .tensor_visibility
+            .into_iter()
+            .map(|t| (t.name, t.visibility))
+            .collect();
+        if !output_vis.is_public()
+            & !output_vis.is_hashed()
+            & !params_vis.is_public()
+            & !params_vis.is_hashed()
+            & !params_vis.is_fixed()
+            & !input_vis.is_public()
+            & !input_vis.is_hashed()
+            & !tensors.values().any(|v| v.is_public() || v.is_hashed())
+        {
             return Err(Box::new(GraphError::Visibility));
         }
         Ok(Self {
             input: input_vis,
             params: params_vis,
             output: output_vis,
+            tensors,
         })
     }
+
+    /// The visibility of the input named `name`, which defaults to that of all inputs.
+    pub fn of_input(&self, name: &str) -> &Visibility {
+        self.tensors.get(name).unwrap_or(&self.input)
+    }
+
+    /// The visibility of the output named `name`, which defaults to that of all outputs.
+    pub fn of_output(&self, name: &str) -> &Visibility {
+        self.tensors.get(name).unwrap_or(&self.output)
+    }
 }
 
 /// A wrapper for holding all columns that will be assigned to by a model.
@@ -94,19 +165,21 @@ pub struct ModelVars<F: FieldExt + TensorType> {
 }
 
 impl<F: FieldExt + TensorType> ModelVars<F> {
-    /// Allocate all columns that will be assigned to by a model.
+    /// Allocate all columns that will be assigned to by a model, with each of the advices laid
+    /// out over at least `num_cols` columns (0 to use the fewest columns fitting `var_len`).
     pub fn new(
         cs: &mut ConstraintSystem<F>,
         logrows: usize,
+        num_cols: usize,
         var_len: usize,
         instance_dims: Vec<Vec<usize>>,
         visibility: VarVisibility,
     ) -> Self {
         let advices = (0..3)
-            .map(|_| VarTensor::new_advice(cs, logrows, var_len, true))
+            .map(|_| VarTensor::new_advice_with_cols(cs, logrows, var_len, num_cols, true))
             .collect_vec();
         let mut fixed = vec![];
-        if visibility.params == Visibility::Public {
+        if visibility.params.is_public() || visibility.params.is_fixed() {
             fixed = (0..1)
                 .map(|_| VarTensor::new_fixed(cs, logrows, var_len, true))
                 .collect_vec();
@@ -121,4 +194,26 @@ impl<F: FieldExt + TensorType> ModelVars<F> {
             instances,
         }
     }
+
+    /// Searches for the smallest logrows, of at least `min_logrows` (and at most `max_logrows`),
+    /// at which `var_len` variables fit in at most `max_cols` columns of each advice, trading
+    /// rows for columns. Returns the logrows along with the number of columns they need.
+    pub fn fit(
+        var_len: usize,
+        min_logrows: u32,
+        max_logrows: u32,
+        max_cols: usize,
+    ) -> Option<(u32, usize)> {
+        // the columns are allocated before any gate, such that only the minimal blinding rows
+        // are reserved
+        let blinding_rows = ConstraintSystem::<F>::default().blinding_factors() + 1;
+        (min_logrows..=max_logrows).find_map(|logrows| {
+            let col_size = (1usize << logrows).checked_sub(blinding_rows)?;
+            if col_size == 0 {
+                return None;
+            }
+            let num_cols = VarTensor::cols_for(col_size, var_len);
+            (num_cols <= max_cols).then_some((logrows, num_cols))
+        })
+    }
 }
@@ -27,6 +27,8 @@
 //! A library for turning computational graphs, such as neural networks, into ZK-circuits.
 //!
 
+/// Benchmarks of the keygen, witness generation, proving and verification of a model.
+pub mod bench;
 /// Methods for configuring tensor operations and assigning values to them in a Halo2 circuit.
 pub mod circuit;
 /// CLI commands.
@@ -38,6 +40,8 @@ pub mod eth;
 pub mod execute;
 /// Utilities for converting from Halo2 Field types to integers (and vice-versa).
 pub mod fieldutils;
+/// Opt-in tracking of the memory used by each phase of a command.
+pub mod memory;
 /// Methods for loading onnx format models and automatically laying them out in
 /// a Halo2 circuit.
 #[cfg(feature = "onnx")]
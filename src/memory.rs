@@ -0,0 +1,154 @@
+use log::info;
+use serde::Serialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tabled::{Table, Tabled};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// The number of bytes currently allocated on the heap
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+/// The largest number of bytes allocated on the heap at once, since the current phase began
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+/// The number of bytes allocated on the heap, overall
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+static PHASES: Mutex<Vec<PhaseMemory>> = Mutex::new(Vec::new());
+/// The name of the current phase, along with the bytes allocated overall when it began
+static CURRENT_PHASE: Mutex<Option<(String, usize)>> = Mutex::new(None);
+
+/// A wrapper around the system allocator which counts the bytes allocated on the heap, once
+/// [enable] has been called. Installed as the `#[global_allocator]` of the ezkl binary, such
+/// that tracking memory is opt-in at runtime and costs a single atomic load otherwise.
+#[derive(Debug)]
+pub struct TrackingAllocator;
+
+#[allow(unsafe_code)]
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() && ENABLED.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() && ENABLED.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        if ENABLED.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() && ENABLED.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn record_alloc(size: usize) {
+    TOTAL.fetch_add(size, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn record_dealloc(size: usize) {
+    // memory allocated before tracking was enabled can be freed while tracking
+    let _ = CURRENT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| {
+        Some(c.saturating_sub(size))
+    });
+}
+
+/// The memory used by a phase of a command (eg. loading the graph, keygen, witness generation or
+/// proving).
+#[derive(Clone, Debug, Serialize, Tabled)]
+pub struct PhaseMemory {
+    /// The name of the phase
+    pub phase: String,
+    /// The number of bytes allocated on the heap over the phase
+    pub allocated: usize,
+    /// The largest number of bytes allocated on the heap at once over the phase
+    pub peak_heap: usize,
+    /// The peak resident set size of the process by the end of the phase, in bytes (0 where it
+    /// can't be read)
+    pub peak_rss: usize,
+}
+
+/// Starts counting the bytes allocated by the [TrackingAllocator].
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether memory is being tracked.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Ends the current phase, if any, and starts measuring the phase `name`. A no-op unless memory
+/// is being tracked.
+pub fn enter_phase(name: &str) {
+    if !is_enabled() {
+        return;
+    }
+    end_phase();
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+    *CURRENT_PHASE.lock().unwrap() = Some((name.to_string(), TOTAL.load(Ordering::Relaxed)));
+}
+
+/// Ends the current phase, if any, recording the memory it used.
+pub fn end_phase() {
+    if let Some((phase, start)) = CURRENT_PHASE.lock().unwrap().take() {
+        let phase = PhaseMemory {
+            phase,
+            allocated: TOTAL.load(Ordering::Relaxed) - start,
+            peak_heap: PEAK.load(Ordering::Relaxed),
+            peak_rss: peak_rss().unwrap_or(0),
+        };
+        PHASES.lock().unwrap().push(phase);
+    }
+}
+
+/// Ends the current phase, and returns the memory used by each of the phases so far.
+pub fn phases() -> Vec<PhaseMemory> {
+    end_phase();
+    PHASES.lock().unwrap().clone()
+}
+
+/// Logs a summary of the memory used by each of the phases so far, along with the peak resident
+/// set size of the process.
+pub fn log_summary() {
+    let phases = phases();
+    if !phases.is_empty() {
+        info!("memory used by phase:\n{}", Table::new(phases));
+    }
+    if let Some(rss) = peak_rss() {
+        info!("peak resident set size: {} MiB", rss >> 20);
+    }
+}
+
+/// The peak resident set size of the process, in bytes, where the OS reports it.
+pub fn peak_rss() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+        let kib = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+        Some(kib << 10)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
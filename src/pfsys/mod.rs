@@ -1,13 +1,22 @@
 /// EVM related proving and verification
 pub mod evm;
 
+use crate::circuit::ecdsa;
+use crate::circuit::merkle::{merkle_root, MerklePath};
+use crate::circuit::poseidon::PoseidonSpec;
+use crate::circuit::sha256;
 use crate::circuit::CheckMode;
-use crate::commands::{data_path, Cli, RunArgs};
+use crate::commands::{data_path, Cli, HashFunction};
 use crate::execute::ExecutionError;
-use crate::fieldutils::i128_to_felt;
-use crate::graph::{utilities::vector_to_quantized, Model, ModelCircuit};
-use crate::tensor::ops::pack;
+use crate::fieldutils::{felt_to_i128, i128_to_felt};
+use crate::graph::utilities::{bf16_to_f32, f16_to_f32, vector_to_quantized};
+use crate::graph::{scale_to_multiplier, Model, ModelCircuit, ModelManifest};
+use crate::memory;
+use crate::tensor::ops::{pack, unpack};
 use crate::tensor::{Tensor, TensorType};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::circuit::Value;
 use halo2_proofs::dev::MockProver;
@@ -25,12 +34,13 @@ use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use snark_verifier::system::halo2::{compile, Config};
 use snark_verifier::verifier::plonk::PlonkProtocol;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufReader, BufWriter, Cursor, Read, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Write};
 use std::marker::PhantomData;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 use thiserror::Error as thisError;
 
@@ -38,20 +48,207 @@ use thiserror::Error as thisError;
 /// Errors related to pfsys
 pub enum PfSysError {
     /// Packing exponent is too large
-    #[error("largest packing exponent exceeds max. try reducing the scale")]
+    #[error("largest packing exponent exceeds max. try reducing the scale or the pack base (or set it to 0 to pick one automatically)")]
     PackingExponent,
+    /// Data doesn't hold a whole number of samples
+    #[error("data doesn't split into a batch of {0} samples")]
+    BatchSize(usize),
+    /// Named data is missing one of the model's inputs or outputs
+    #[error("no data for the model's input or output named {0}")]
+    MissingData(String),
+    /// Named data doesn't match its declared shape or dtype
+    #[error("data for {0} doesn't match its shape or dtype")]
+    MisformedData(String),
 }
 
 /// The input tensor data and shape, and output data for the computational graph (model) as floats.
 /// For example, the input might be the image data for a neural network, and the output class scores.
+/// When proving a batch of samples, the data of each sample follows the previous one and the
+/// shapes carry a leading batch dim.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ModelInput {
-    /// Inputs to the model / computational graph.
-    pub input_data: Vec<Vec<f32>>,
+    /// Inputs to the model / computational graph, kept at double precision until they are
+    /// quantized.
+    pub input_data: Vec<Vec<f64>>,
     /// The shape of said inputs.
     pub input_shapes: Vec<Vec<usize>>,
     /// The expected output of the model (can be empty vectors if outputs are not being constrained).
     pub output_data: Vec<Vec<f32>>,
+    /// The names of the inputs in the Onnx graph, if the data is named rather than positional.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_names: Vec<String>,
+    /// The names of the outputs in the Onnx graph, if the data is named rather than positional.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_names: Vec<String>,
+    /// A secp256k1 signature over the digest of the inputs (as the hex of its r and s), when
+    /// the inputs are signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// The paths to the leaves of the hashed inputs (each of their samples in turn) and then of
+    /// the hashed params, when these are proven to be leaves of Merkle trees.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merkle_paths: Vec<MerklePath>,
+}
+
+impl ModelInput {
+    /// Orders named data as the model's inputs and outputs. Positional data is returned as is.
+    pub fn order_by_names(
+        &self,
+        input_names: &[String],
+        output_names: &[String],
+    ) -> Result<ModelInput, Box<dyn Error>> {
+        let position = |names: &[String], name: &String| {
+            names
+                .iter()
+                .position(|n| n == name)
+                .ok_or_else(|| Box::new(PfSysError::MissingData(name.clone())))
+        };
+        let mut ordered = self.clone();
+        if !self.input_names.is_empty() {
+            let mut input_data = vec![];
+            let mut input_shapes = vec![];
+            for name in input_names {
+                let i = position(&self.input_names, name)?;
+                input_data.push(self.input_data[i].clone());
+                input_shapes.push(self.input_shapes[i].clone());
+            }
+            ordered.input_data = input_data;
+            ordered.input_shapes = input_shapes;
+            ordered.input_names = input_names.to_vec();
+        }
+        if !self.output_names.is_empty() {
+            let mut output_data = vec![];
+            for name in output_names {
+                let i = position(&self.output_names, name)?;
+                output_data.push(self.output_data[i].clone());
+            }
+            ordered.output_data = output_data;
+            ordered.output_names = output_names.to_vec();
+        }
+        Ok(ordered)
+    }
+}
+
+/// The type of the data of a [NamedTensor]. All data is quantized from (double precision) floats,
+/// integer and boolean data must hold integer values, and half precision data holds the bits of
+/// its floats (eg. as exported with numpy's `.view(np.uint16)`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataType {
+    /// floating point data
+    #[default]
+    F32,
+    /// double precision floating point data
+    F64,
+    /// half precision floating point data, as the bits of each float
+    F16,
+    /// bfloat16 data, as the bits of each float
+    BF16,
+    /// integer data
+    I64,
+    /// boolean data, as 0 or 1
+    Bool,
+}
+
+/// The data of a single input or output of the model, with its shape.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NamedTensor {
+    /// The shape of the data.
+    pub shape: Vec<usize>,
+    /// The type of the data.
+    #[serde(default)]
+    pub dtype: DataType,
+    /// The flattened data.
+    pub data: Vec<f64>,
+}
+
+impl NamedTensor {
+    /// The values of the data, with half precision floats converted from their bits. Returns
+    /// `None` if the data doesn't hold valid values of its type.
+    pub fn values(&self) -> Option<Vec<f64>> {
+        let half = |to_f32: fn(u16) -> f32| -> Option<Vec<f64>> {
+            self.data
+                .iter()
+                .map(|x| {
+                    let is_bits = x.fract() == 0.0 && (0.0..=u16::MAX as f64).contains(x);
+                    let value = to_f32(is_bits.then_some(*x as u16)?);
+                    value.is_finite().then_some(value as f64)
+                })
+                .collect()
+        };
+        match self.dtype {
+            DataType::F32 | DataType::F64 => Some(self.data.clone()),
+            DataType::I64 => self
+                .data
+                .iter()
+                .all(|x| x.fract() == 0.0)
+                .then(|| self.data.clone()),
+            DataType::Bool => self
+                .data
+                .iter()
+                .all(|x| *x == 0.0 || *x == 1.0)
+                .then(|| self.data.clone()),
+            DataType::F16 => half(f16_to_f32),
+            DataType::BF16 => half(bf16_to_f32),
+        }
+    }
+}
+
+/// Model data keyed by the names of the inputs and outputs in the Onnx graph, for models with
+/// several differently shaped inputs.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NamedModelInput {
+    /// Inputs to the model / computational graph.
+    pub inputs: BTreeMap<String, NamedTensor>,
+    /// The expected outputs of the model (can be empty if outputs are not being constrained).
+    #[serde(default)]
+    pub outputs: BTreeMap<String, NamedTensor>,
+    /// A secp256k1 signature over the digest of the inputs (as the hex of its r and s), when
+    /// the inputs are signed.
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// The paths to the leaves of the hashed inputs (each of their samples in turn) and then of
+    /// the hashed params, when these are proven to be leaves of Merkle trees.
+    #[serde(default)]
+    pub merkle_paths: Vec<MerklePath>,
+}
+
+impl TryFrom<NamedModelInput> for ModelInput {
+    type Error = Box<dyn Error>;
+
+    fn try_from(named: NamedModelInput) -> Result<Self, Self::Error> {
+        let values = |tensors: &BTreeMap<String, NamedTensor>| {
+            tensors
+                .iter()
+                .map(|(name, t)| match t.values() {
+                    Some(v) if t.shape.iter().product::<usize>() == v.len() => Ok(v),
+                    _ => Err(PfSysError::MisformedData(name.clone())),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let input_data = values(&named.inputs)?;
+        let output_data = values(&named.outputs)?;
+        Ok(ModelInput {
+            input_data,
+            input_shapes: named.inputs.values().map(|t| t.shape.clone()).collect(),
+            output_data: output_data
+                .into_iter()
+                .map(|v| v.into_iter().map(|x| x as f32).collect())
+                .collect(),
+            input_names: named.inputs.into_keys().collect(),
+            output_names: named.outputs.into_keys().collect(),
+            signature: named.signature,
+            merkle_paths: named.merkle_paths,
+        })
+    }
+}
+
+/// The schemas model data can be given in.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ModelInputSchema {
+    Named(NamedModelInput),
+    Positional(ModelInput),
 }
 
 /// Defines the proof generated by a model / circuit suitably for serialization/deserialization.
@@ -62,6 +259,9 @@ pub struct Snarkbytes {
     pub instances: Vec<Vec<Vec<u8>>>,
     /// The generated proof, as a vector of bytes.
     pub proof: Vec<u8>,
+    /// The hash of the manifest of the model and settings the proof was built for.
+    #[serde(default)]
+    pub manifest_hash: Option<String>,
 }
 
 /// An application snark with proof and instance variables ready for aggregation (raw field element)
@@ -72,6 +272,8 @@ pub struct Snark<F: FieldExt + SerdeObject, C: CurveAffine> {
     pub instances: Vec<Vec<F>>,
     /// the proof
     pub proof: Vec<u8>,
+    /// the hash of the manifest of the model and settings the proof was built for
+    pub manifest_hash: Option<String>,
 }
 
 impl<F: FieldExt + SerdeObject, C: CurveAffine> Snark<F, C> {
@@ -81,6 +283,7 @@ impl<F: FieldExt + SerdeObject, C: CurveAffine> Snark<F, C> {
             protocol: Some(protocol),
             instances,
             proof,
+            manifest_hash: None,
         }
     }
 
@@ -94,6 +297,7 @@ impl<F: FieldExt + SerdeObject, C: CurveAffine> Snark<F, C> {
                 .map(|i| i.iter().map(|e| e.to_raw_bytes()).collect::<Vec<Vec<u8>>>())
                 .collect::<Vec<Vec<Vec<u8>>>>(),
             proof: self.proof.clone(),
+            manifest_hash: self.manifest_hash.clone(),
         };
 
         let serialized = serde_json::to_string(&self_i128).map_err(Box::<dyn Error>::from)?;
@@ -133,6 +337,7 @@ impl<F: FieldExt + SerdeObject, C: CurveAffine> Snark<F, C> {
                 protocol: None,
                 instances,
                 proof: snark_bytes.proof,
+                manifest_hash: snark_bytes.manifest_hash,
             })
         } else {
             let protocol = compile(
@@ -145,6 +350,7 @@ impl<F: FieldExt + SerdeObject, C: CurveAffine> Snark<F, C> {
                 protocol: Some(protocol),
                 instances,
                 proof: snark_bytes.proof,
+                manifest_hash: snark_bytes.manifest_hash,
             })
         }
     }
@@ -193,36 +399,69 @@ impl<F: FieldExt + SerdeObject, C: CurveAffine> From<Snark<F, C>> for SnarkWitne
 type CircuitInputs<F> = (ModelCircuit<F>, Vec<Vec<F>>);
 
 /// Initialize the model circuit and quantize the provided float inputs from the provided `ModelInput`.
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn prepare_model_circuit_and_public_input<F: FieldExt + TensorType>(
     data: &ModelInput,
     cli: &Cli,
 ) -> Result<CircuitInputs<F>, Box<dyn Error>> {
+    memory::enter_phase("graph load");
     let model = Model::from_ezkl_conf(cli.clone())?;
     let out_scales = model.get_output_scales();
-    let circuit = prepare_model_circuit(data, &cli.args)?;
+    let data = &data.order_by_names(&model.input_names(), &model.output_names())?;
+    // the witness itself is assigned when the circuit is synthesized, while proving
+    memory::enter_phase("witness");
+    let circuit = prepare_model_circuit(data, &model)?;
 
     // quantize the supplied data using the provided scale.
     // the ordering here is important, we want the inputs to come before the outputs
     // as they are configured in that order as Column<Instances>
     let mut public_inputs = vec![];
-    if model.visibility.input.is_public() {
-        for v in data.input_data.iter() {
-            let t = vector_to_quantized(v, &Vec::from([v.len()]), 0.0, model.run_args.scale)?;
+    // whether each of the public inputs is committed to by its hash, and whether that is a leaf
+    // of a Merkle tree
+    let mut hashed = vec![];
+    let mut leaves = vec![];
+    // each sample of a batch has its own instances
+    let batch_size = cli.args.batch_size;
+    let input_visibility = model.input_visibility();
+    let public_input_data = data
+        .input_data
+        .iter()
+        .zip(input_visibility)
+        .filter(|(_, visibility)| visibility.is_public() || visibility.is_hashed());
+    for (v, visibility) in public_input_data {
+        for sample in split_batch(v, batch_size)? {
+            let t = vector_to_quantized(
+                sample,
+                &Vec::from([sample.len()]),
+                0.0,
+                model.run_args.scale,
+            )?;
             public_inputs.push(t);
+            hashed.push(visibility.is_hashed());
+            leaves.push(visibility.is_hashed());
         }
     }
-    if model.visibility.output.is_public() {
-        for (idx, v) in data.output_data.iter().enumerate() {
-            let mut t = vector_to_quantized(v, &Vec::from([v.len()]), 0.0, out_scales[idx])?;
+    let output_visibility = model.output_visibility();
+    let public_output_data = data
+        .output_data
+        .iter()
+        .zip(out_scales)
+        .zip(output_visibility)
+        .filter(|(_, visibility)| visibility.is_public() || visibility.is_hashed());
+    for ((v, out_scale), visibility) in public_output_data {
+        for sample in split_batch(v, batch_size)? {
+            let mut t = vector_to_quantized(sample, &Vec::from([sample.len()]), 0.0, out_scale)?;
             let len = t.len();
-            if cli.args.pack_base > 1 {
-                let max_exponent = (((len - 1) as u32) * (cli.args.scale + 1)) as f64;
-                if max_exponent > (i128::MAX as f64).log(cli.args.pack_base as f64) {
+            if model.run_args.pack_base > 1 {
+                let max_exponent = (((len - 1) as u32) * (model.run_args.scale + 1)) as f64;
+                if max_exponent > (i128::MAX as f64).log(model.run_args.pack_base as f64) {
                     return Err(Box::new(PfSysError::PackingExponent));
                 }
-                t = pack(&t, cli.args.pack_base as i128, cli.args.scale)?;
+                t = pack(&t, model.run_args.pack_base as i128, model.run_args.scale)?;
             }
             public_inputs.push(t);
+            hashed.push(visibility.is_hashed());
+            leaves.push(false);
         }
     }
     info!(
@@ -234,39 +473,185 @@ pub fn prepare_model_circuit_and_public_input<F: FieldExt + TensorType>(
     );
     trace!("{:?}", public_inputs);
 
-    let pi_inner: Vec<Vec<F>> = public_inputs
+    // the paths of the leaves come in the order of the hashed inputs, and then of the params
+    let mut merkle_paths = circuit.merkle_paths.iter();
+    let mut root = |digest: F| match merkle_paths.next() {
+        Some((index, siblings)) => merkle_root(digest, *index, siblings),
+        None => digest,
+    };
+    let mut pi_inner: Vec<Vec<F>> = public_inputs
         .iter()
-        .map(|i| i.iter().map(|e| i128_to_felt::<F>(*e)).collect::<Vec<F>>())
+        .zip(hashed.into_iter().zip(leaves))
+        .map(|(i, (hashed, leaf))| {
+            let felts = i.iter().map(|e| i128_to_felt::<F>(*e)).collect::<Vec<F>>();
+            match (hashed, model.run_args.hash_function) {
+                (true, HashFunction::Poseidon) => {
+                    let digest = PoseidonSpec::<F>::new().hash(&felts);
+                    vec![if leaf { root(digest) } else { digest }]
+                }
+                (true, HashFunction::Sha256) => sha256::digest::<F>(i).to_vec(),
+                (false, _) => felts,
+            }
+        })
         .collect::<Vec<Vec<F>>>();
+    // the commitment to hashed params comes last
+    if model.visibility.params.is_hashed() {
+        pi_inner.push(vec![root(model.params_digest::<F>())]);
+    }
 
     Ok((circuit, pi_inner))
 }
 
-/// Initialize the model circuit
+/// Initialize the model circuit, mapping named data to the model's inputs by name.
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn prepare_model_circuit<F: FieldExt>(
     data: &ModelInput,
-    args: &RunArgs,
+    model: &Model,
 ) -> Result<ModelCircuit<F>, Box<dyn Error>> {
+    let args = &model.run_args;
+    let data = data.order_by_names(&model.input_names(), &model.output_names())?;
     // quantize the supplied data using the provided scale.
     let mut inputs: Vec<Tensor<i128>> = vec![];
     for (input, shape) in data.input_data.iter().zip(data.input_shapes.clone()) {
-        let t = vector_to_quantized(input, &shape, 0.0, args.scale)?;
-        inputs.push(t);
+        match args.batch_size {
+            1 => inputs.push(vector_to_quantized(input, &shape, 0.0, args.scale)?),
+            // each sample of the batch is a separate input to the circuit, dropping the batch dim
+            batch_size => {
+                if shape.first() != Some(&batch_size) {
+                    return Err(Box::new(PfSysError::BatchSize(batch_size)));
+                }
+                for sample in split_batch(input, batch_size)? {
+                    inputs.push(vector_to_quantized(sample, &shape[1..], 0.0, args.scale)?);
+                }
+            }
+        }
     }
 
+    let signature = match (&args.signer, &data.signature) {
+        (Some(_), Some(signature)) => Some(
+            ecdsa::parse_signature(signature)
+                .ok_or_else(|| Box::new(PfSysError::MisformedData("signature".to_string())))?,
+        ),
+        (Some(_), None) => return Err(Box::new(PfSysError::MissingData("signature".to_string()))),
+        (None, _) => None,
+    };
+
+    let merkle_paths = match args.merkle_depth {
+        0 => vec![],
+        depth => {
+            let leaves = model
+                .input_visibility()
+                .iter()
+                .filter(|v| v.is_hashed())
+                .count()
+                * args.batch_size
+                + model.visibility.params.is_hashed() as usize;
+            if data.merkle_paths.len() != leaves {
+                return Err(Box::new(PfSysError::MissingData(
+                    "merkle_paths".to_string(),
+                )));
+            }
+            data.merkle_paths
+                .iter()
+                .map(|path| match path.siblings::<F>() {
+                    Some(siblings) if siblings.len() == depth => Ok((path.index, siblings)),
+                    _ => Err(PfSysError::MisformedData("merkle_paths".to_string())),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
     Ok(ModelCircuit::<F> {
         inputs,
+        signature,
+        merkle_paths,
         _marker: PhantomData,
     })
 }
 
-/// Deserializes the required inputs to a model at path `datapath` to a [ModelInput] struct.
+/// Reads the public outputs of the model of `manifest` back from the `instances` of a proof, as
+/// floats, without loading the model. Packed outputs are unpacked, and each output is dequantized
+/// at its scale. Returns the data of each of the public outputs, with the samples of a batch
+/// following one another (as in [ModelInput]).
+pub fn dequantize_outputs<F: FieldExt>(
+    instances: &[Vec<F>],
+    manifest: &ModelManifest,
+) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+    let args = &manifest.run_args;
+    let mut instances = instances
+        .iter()
+        .skip(manifest.public_inputs * args.batch_size);
+    let public_outputs = manifest
+        .output_shapes
+        .iter()
+        .zip(&manifest.output_scales)
+        .zip(&manifest.output_visibility)
+        .filter(|(_, visibility)| visibility.is_public() || visibility.is_hashed());
+    let mut outputs = vec![];
+    for ((shape, out_scale), visibility) in public_outputs {
+        let len = shape.iter().product::<usize>();
+        let mult = scale_to_multiplier(*out_scale);
+        let mut output = vec![];
+        for _ in 0..args.batch_size {
+            let instance = instances
+                .next()
+                .ok_or_else(|| PfSysError::MisformedData("instances".to_string()))?;
+            // only the commitments to hashed outputs are public
+            if visibility.is_hashed() {
+                continue;
+            }
+            let mut t: Tensor<i128> = instance.iter().map(|x| felt_to_i128(*x)).collect();
+            if args.pack_base > 1 {
+                t = unpack(&t, args.pack_base as i128, args.scale, len)?;
+            }
+            output.extend(t.iter().map(|x| *x as f32 / mult));
+        }
+        if visibility.is_public() {
+            outputs.push(output);
+        }
+    }
+    Ok(outputs)
+}
+
+/// Splits the data of a batch into that of each of its samples.
+fn split_batch<T>(data: &[T], batch_size: usize) -> Result<Vec<&[T]>, Box<dyn Error>> {
+    if batch_size == 0 || data.len() % batch_size != 0 {
+        return Err(Box::new(PfSysError::BatchSize(batch_size)));
+    }
+    if data.is_empty() {
+        return Ok(vec![data; batch_size]);
+    }
+    Ok(data.chunks(data.len() / batch_size).collect())
+}
+
+/// Deserializes the required inputs to a model at path `datapath` to a [ModelInput] struct. The
+/// data can be positional, or named as a [NamedModelInput].
 pub fn prepare_data(datapath: String) -> Result<ModelInput, Box<dyn Error>> {
     let mut file = File::open(data_path(datapath)).map_err(Box::<dyn Error>::from)?;
     let mut data = String::new();
     file.read_to_string(&mut data)
         .map_err(Box::<dyn Error>::from)?;
-    serde_json::from_str(&data).map_err(Box::<dyn Error>::from)
+    match serde_json::from_str(&data).map_err(Box::<dyn Error>::from)? {
+        ModelInputSchema::Named(named) => named.try_into(),
+        ModelInputSchema::Positional(data) => Ok(data),
+    }
+}
+
+/// Loads the data of a .json data file, or of each of the .json data files of a directory (in the
+/// order of their paths).
+pub fn prepare_data_files(path: &Path) -> Result<Vec<ModelInput>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return Ok(vec![prepare_data(path.display().to_string())?]);
+    }
+    let mut paths = std::fs::read_dir(path)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|p| p.extension().map_or(false, |e| e == "json"));
+    paths.sort();
+    paths
+        .iter()
+        .map(|p| prepare_data(p.display().to_string()))
+        .collect()
 }
 
 /// Helper function for generating SRS. !!! Only use for testing
@@ -275,6 +660,7 @@ pub fn gen_srs<Scheme: CommitmentScheme>(k: u32) -> Scheme::ParamsProver {
 }
 
 /// Creates a [VerifyingKey] and [ProvingKey] for a [ModelCircuit] (`circuit`) with specific [CommitmentScheme] parameters (`params`).
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn create_keys<Scheme: CommitmentScheme, F: FieldExt + TensorType, C: Circuit<F>>(
     circuit: &C,
     params: &'_ Scheme::ParamsProver,
@@ -282,6 +668,7 @@ pub fn create_keys<Scheme: CommitmentScheme, F: FieldExt + TensorType, C: Circui
 where
     C: Circuit<Scheme::Scalar>,
 {
+    memory::enter_phase("keygen");
     //	Real proof
     let empty_circuit = <C as Circuit<F>>::without_witnesses(circuit);
 
@@ -297,6 +684,7 @@ where
 }
 
 /// a wrapper around halo2's create_proof
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn create_proof_circuit<
     'params,
     Scheme: CommitmentScheme,
@@ -321,9 +709,12 @@ where
     Scheme::ParamsVerifier: 'params,
     Scheme::Scalar: SerdeObject,
 {
+    memory::enter_phase("proof");
     // quickly mock prove as a sanity check
     if check_mode == CheckMode::SAFE {
         debug!("running mock prover");
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("mock_prover").entered();
         let prover = MockProver::run(params.k(), &circuit, instances.clone())
             .map_err(Box::<dyn Error>::from)?;
         prover
@@ -349,15 +740,19 @@ where
     trace!("instances {:?}", instances);
 
     let now = Instant::now();
-    create_proof::<Scheme, P, _, _, TW, _>(
-        params,
-        pk,
-        &[circuit],
-        pi_inner,
-        &mut rng,
-        &mut transcript,
-    )?;
-    let proof = transcript.finalize();
+    let proof = {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("create_proof").entered();
+        create_proof::<Scheme, P, _, _, TW, _>(
+            params,
+            pk,
+            &[circuit],
+            pi_inner,
+            &mut rng,
+            &mut transcript,
+        )?;
+        transcript.finalize()
+    };
     info!("Proof took {}", now.elapsed().as_secs());
 
     let checkable_pf = Snark::new(protocol, instances, proof);
@@ -378,6 +773,7 @@ where
 }
 
 /// A wrapper around halo2's verify_proof
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn verify_proof_circuit<
     'params,
     F: FieldExt,
@@ -425,7 +821,12 @@ where
         .map_err(Box::<dyn Error>::from)
 }
 
-/// Loads a [ProvingKey] at `path`.
+/// The magic bytes gzip streams start with. Uncompressed keys start with the big endian k of
+/// their verifying key, such that the two can't be confused.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Loads a [ProvingKey] at `path`, either compressed by [save_pk] or uncompressed.
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn load_pk<Scheme: CommitmentScheme, F: FieldExt + TensorType, C: Circuit<F>>(
     path: PathBuf,
 ) -> Result<ProvingKey<Scheme::Curve>, Box<dyn Error>>
@@ -437,11 +838,22 @@ where
     info!("loading proving key from {:?}", path);
     let f = File::open(path).map_err(Box::<dyn Error>::from)?;
     let mut reader = BufReader::new(f);
-    ProvingKey::<Scheme::Curve>::read::<_, C>(&mut reader, halo2_proofs::SerdeFormat::RawBytes)
-        .map_err(Box::<dyn Error>::from)
+    let compressed = reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+    let pk = match compressed {
+        true => ProvingKey::<Scheme::Curve>::read::<_, C>(
+            &mut BufReader::new(GzDecoder::new(reader)),
+            halo2_proofs::SerdeFormat::RawBytes,
+        ),
+        false => ProvingKey::<Scheme::Curve>::read::<_, C>(
+            &mut reader,
+            halo2_proofs::SerdeFormat::RawBytes,
+        ),
+    };
+    pk.map_err(Box::<dyn Error>::from)
 }
 
 /// Loads the [CommitmentScheme::ParamsVerifier] at `path`.
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn load_params<Scheme: CommitmentScheme>(
     path: PathBuf,
 ) -> Result<Scheme::ParamsVerifier, Box<dyn Error>> {
@@ -451,10 +863,14 @@ pub fn load_params<Scheme: CommitmentScheme>(
     Params::<'_, Scheme::Curve>::read(&mut reader).map_err(Box::<dyn Error>::from)
 }
 
-/// Saves a [ProvingKey] to `path`.
+/// Saves a [ProvingKey] to `path`, compressed with gzip. The fixed values of a model's circuit
+/// are mostly selectors and small constants, whose runs of zero bytes compress well, while the
+/// extended cosets of the fixed polynomials barely compress (and take the most space), so the
+/// fastest compression level is used.
+#[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
 pub fn save_pk<Scheme: CommitmentScheme>(
     path: &PathBuf,
-    vk: &ProvingKey<Scheme::Curve>,
+    pk: &ProvingKey<Scheme::Curve>,
 ) -> Result<(), io::Error>
 where
     Scheme::Curve: SerdeObject + CurveAffine,
@@ -462,9 +878,9 @@ where
 {
     info!("saving proving key 💾");
     let f = File::create(path)?;
-    let mut writer = BufWriter::new(f);
-    vk.write(&mut writer, halo2_proofs::SerdeFormat::RawBytes)?;
-    writer.flush()?;
+    let mut writer = GzEncoder::new(BufWriter::new(f), Compression::fast());
+    pk.write(&mut writer, halo2_proofs::SerdeFormat::RawBytes)?;
+    writer.finish()?.flush()?;
     Ok(())
 }
 
@@ -265,8 +265,7 @@ where
     Vec<I::Item>: FromIterator<I::Item>,
 {
     fn from(value: I) -> Tensor<I::Item> {
-        let data: Vec<I::Item> = value.collect::<Vec<I::Item>>();
-        Tensor::new(Some(&data), &[data.len()]).unwrap()
+        value.collect()
     }
 }
 
@@ -276,8 +275,12 @@ where
     Vec<T>: FromIterator<T>,
 {
     fn from_iter<I: IntoIterator<Item = T>>(value: I) -> Tensor<T> {
-        let data: Vec<I::Item> = value.into_iter().collect::<Vec<I::Item>>();
-        Tensor::new(Some(&data), &[data.len()]).unwrap()
+        // the collected values are moved into the tensor rather than copied
+        let inner: Vec<T> = value.into_iter().collect();
+        Tensor {
+            dims: vec![inner.len()],
+            inner,
+        }
     }
 }
 
@@ -658,6 +661,33 @@ impl<T: Clone + TensorType> Tensor<T> {
         Ok(())
     }
 
+    /// Permutes the axes of a tensor, such that axis `i` of the output is axis `order[i]` of the input.
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// let a = Tensor::<i32>::new(Some(&[1, 2, 3, 4, 5, 6]), &[1, 2, 3]).unwrap();
+    /// let expected = Tensor::<i32>::new(Some(&[1, 4, 2, 5, 3, 6]), &[3, 1, 2]).unwrap();
+    /// assert_eq!(a.permute(&[2, 0, 1]).unwrap(), expected);
+    /// ```
+    pub fn permute(&self, order: &[usize]) -> Result<Tensor<T>, TensorError> {
+        if (order.len() != self.dims().len()) || !(0..order.len()).all(|i| order.contains(&i)) {
+            return Err(TensorError::DimMismatch("permute".to_string()));
+        }
+        let new_dims = order.iter().map(|i| self.dims()[*i]).collect_vec();
+        let mut indices = Vec::new();
+        for i in new_dims.clone() {
+            indices.push(0..i);
+        }
+        let mut res = Vec::new();
+        for coord in indices.iter().cloned().multi_cartesian_product() {
+            let mut old_coord = vec![0; coord.len()];
+            for (i, o) in order.iter().enumerate() {
+                old_coord[*o] = coord[i];
+            }
+            res.push(self.get(&old_coord));
+        }
+        Tensor::new(Some(&res), &new_dims)
+    }
+
     /// Adds a row of ones
     /// ```
     /// use ezkl_lib::tensor::Tensor;
@@ -1,6 +1,8 @@
 use super::TensorError;
 use crate::tensor::{Tensor, TensorType};
 use itertools::Itertools;
+use std::collections::HashMap;
+use std::ops::Range;
 pub use std::ops::{Add, Div, Mul, Sub};
 
 /// Matrix multiplies two 2D tensors (and adds an offset).
@@ -160,6 +162,39 @@ pub fn matmul<T: TensorType + Mul<Output = T> + Add<Output = T>>(
     Ok(output)
 }
 
+/// Matrix multiplies two 2D tensors and adds a bias to each row of the product.
+/// # Arguments
+///
+/// * `inputs` - Vector of tensors holding in order: the [m, n] input, the [n, k] kernel, and the bias of length k.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::biased_matmul;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[1, 0, 2, 0, 1, 1]), &[2, 3]).unwrap();
+/// let b = Tensor::<i128>::new(Some(&[1, 2, 3]), &[3]).unwrap();
+/// let result = biased_matmul(&vec![x, k, b]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 4, 7, 4, 6, 13]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn biased_matmul<T: TensorType + Mul<Output = T> + Add<Output = T>>(
+    inputs: &[Tensor<T>],
+) -> Result<Tensor<T>, TensorError> {
+    if inputs.len() != 3 || inputs[0].dims().len() != 2 {
+        return Err(TensorError::DimMismatch("biased matmul".to_string()));
+    }
+    let mut output = matmul(&inputs[0..2])?;
+    let (bias, cols) = (&inputs[2], output.dims()[1]);
+    if bias.len() != cols {
+        return Err(TensorError::DimMismatch("biased matmul".to_string()));
+    }
+    for (i, o) in output.iter_mut().enumerate() {
+        *o = o.clone() + bias[i % cols].clone();
+    }
+    Ok(output)
+}
+
 /// Adds multiple tensors.
 /// # Arguments
 ///
@@ -444,6 +479,439 @@ pub fn convolution<T: TensorType + Mul<Output = T> + Add<Output = T>>(
     Ok(output)
 }
 
+/// Applies a grouped convolution over a 3D tensor of shape C x H x W (and adds a bias).
+/// The input channels and kernels are split into `group` groups which are convolved independently
+/// and the results concatenated along the channel axis. A depthwise convolution is the case where
+/// `group` equals the number of input channels.
+/// # Arguments
+///
+/// * `inputs` - A vector of tensors holding in order: input image, convolution kernel, convolution bias.
+/// * `padding` - Tuple of padding values in x and y directions.
+/// * `stride` - Tuple of stride values in x and y directions.
+/// * `group` - Number of groups to split the channels into.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::grouped_convolution;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4, 5, 6, 7, 8]),
+///     &[2, 2, 2],
+/// ).unwrap();
+/// let k = Tensor::<i128>::new(
+///     Some(&[1, 0, 0, 1, 0, 1, 1, 0]),
+///     &[2, 1, 2, 2],
+/// ).unwrap();
+/// let result = grouped_convolution::<i128>(&[x, k], (0, 0), (1, 1), 2).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[5, 13]), &[2, 1, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn grouped_convolution<T: TensorType + Mul<Output = T> + Add<Output = T>>(
+    inputs: &[Tensor<T>],
+    padding: (usize, usize),
+    stride: (usize, usize),
+    group: usize,
+) -> Result<Tensor<T>, TensorError> {
+    let (image, kernel) = (&inputs[0], &inputs[1]);
+
+    if (image.dims().len() != 3)
+        || (kernel.dims().len() != 4)
+        || (group == 0)
+        || (image.dims()[0] != kernel.dims()[1] * group)
+        || (kernel.dims()[0] % group != 0)
+    {
+        return Err(TensorError::DimMismatch("grouped conv".to_string()));
+    }
+
+    let input_channels_per_group = kernel.dims()[1];
+    let output_channels_per_group = kernel.dims()[0] / group;
+
+    let mut outputs = vec![];
+    for g in 0..group {
+        let in_range = g * input_channels_per_group..(g + 1) * input_channels_per_group;
+        let out_range = g * output_channels_per_group..(g + 1) * output_channels_per_group;
+        let mut group_inputs = vec![
+            image.get_slice(&[in_range])?,
+            kernel.get_slice(&[out_range.clone()])?,
+        ];
+        if inputs.len() == 3 {
+            group_inputs.push(inputs[2].get_slice(&[out_range])?);
+        }
+        outputs.push(convolution(&group_inputs, padding, stride)?);
+    }
+
+    let mut dims = outputs[0].dims().to_vec();
+    dims[0] *= group;
+    let mut output = Tensor::new(Some(&outputs), &[group])?.combine()?;
+    output.reshape(&dims);
+    Ok(output)
+}
+
+/// Applies a transposed convolution (deconvolution) over a 3D tensor of shape C x H x W (and adds a bias).
+/// The kernel is expected in the onnx layout `C_in x C_out x K_h x K_w`.
+/// # Arguments
+///
+/// * `inputs` - A vector of tensors holding in order: input image, deconvolution kernel, deconvolution bias.
+/// * `padding` - Tuple of padding values in x and y directions.
+/// * `output_padding` - Tuple of additional padding added to the bottom and right of the output.
+/// * `stride` - Tuple of stride values in x and y directions.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::deconv;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4]),
+///     &[1, 2, 2],
+/// ).unwrap();
+/// let k = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4]),
+///     &[1, 1, 2, 2],
+/// ).unwrap();
+/// let result = deconv::<i128>(&[x, k], (0, 0), (0, 0), (2, 2)).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[1, 2, 2, 4, 3, 4, 6, 8, 3, 6, 4, 8, 9, 12, 12, 16]),
+///     &[1, 4, 4],
+/// ).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn deconv<T: TensorType + Mul<Output = T> + Add<Output = T>>(
+    inputs: &[Tensor<T>],
+    padding: (usize, usize),
+    output_padding: (usize, usize),
+    stride: (usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    let (image, kernel) = (&inputs[0], &inputs[1]);
+
+    if (image.dims().len() != 3)
+        || (kernel.dims().len() != 4)
+        || (image.dims()[0] != kernel.dims()[0])
+    {
+        return Err(TensorError::DimMismatch("deconv".to_string()));
+    }
+
+    let kernel_shape = (kernel.dims()[2], kernel.dims()[3]);
+    let expanded_image = deconv_image(image, kernel_shape, padding, output_padding, stride)?;
+    let flipped_kernel = deconv_kernel(kernel)?;
+
+    let mut conv_inputs = vec![expanded_image, flipped_kernel];
+    if inputs.len() == 3 {
+        conv_inputs.push(inputs[2].clone());
+    }
+    convolution(&conv_inputs, (0, 0), (1, 1))
+}
+
+/// Expands a 3D tensor of shape C x H x W such that a stride 1, unpadded, convolution over it
+/// is equivalent to a transposed convolution over the original tensor.
+/// Inserts `stride - 1` zeros between elements and pads the result by `kernel_shape - 1 - padding`
+/// (plus `output_padding` on the bottom and right).
+/// # Arguments
+///
+/// * `image` - Tensor.
+/// * `kernel_shape` - Tuple of kernel sizes in x and y directions.
+/// * `padding` - Tuple of padding values in x and y directions.
+/// * `output_padding` - Tuple of additional padding added to the bottom and right of the output.
+/// * `stride` - Tuple of stride values in x and y directions.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::deconv_image;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4]),
+///     &[1, 2, 2],
+/// ).unwrap();
+/// let result = deconv_image::<i128>(&x, (2, 2), (1, 1), (0, 0), (2, 2)).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[1, 0, 2, 0, 0, 0, 3, 0, 4]),
+///     &[1, 3, 3],
+/// ).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn deconv_image<T: TensorType>(
+    image: &Tensor<T>,
+    kernel_shape: (usize, usize),
+    padding: (usize, usize),
+    output_padding: (usize, usize),
+    stride: (usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    if image.dims().len() != 3 {
+        return Err(TensorError::DimMismatch("deconv image".to_string()));
+    }
+    if (padding.0 >= kernel_shape.0) || (padding.1 >= kernel_shape.1) {
+        return Err(TensorError::DimMismatch("deconv padding".to_string()));
+    }
+    let (channels, height, width) = (image.dims()[0], image.dims()[1], image.dims()[2]);
+
+    let (pad_h, pad_w) = (
+        kernel_shape.0 - 1 - padding.0,
+        kernel_shape.1 - 1 - padding.1,
+    );
+    let expanded_height = (height - 1) * stride.0 + 1 + 2 * pad_h + output_padding.0;
+    let expanded_width = (width - 1) * stride.1 + 1 + 2 * pad_w + output_padding.1;
+
+    let mut output = Tensor::<T>::new(None, &[channels, expanded_height, expanded_width])?;
+
+    for channel in 0..channels {
+        for row in 0..height {
+            for col in 0..width {
+                output.set(
+                    &[channel, pad_h + row * stride.0, pad_w + col * stride.1],
+                    image.get(&[channel, row, col]),
+                );
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Converts a transposed convolution kernel of shape `C_in x C_out x K_h x K_w` into the
+/// equivalent convolution kernel of shape `C_out x C_in x K_h x K_w`, flipping the spatial axes.
+/// # Arguments
+///
+/// * `kernel` - Tensor.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::deconv_kernel;
+///
+/// let k = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4, 5, 6, 7, 8]),
+///     &[2, 1, 2, 2],
+/// ).unwrap();
+/// let result = deconv_kernel::<i128>(&k).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[4, 3, 2, 1, 8, 7, 6, 5]),
+///     &[1, 2, 2, 2],
+/// ).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn deconv_kernel<T: TensorType>(kernel: &Tensor<T>) -> Result<Tensor<T>, TensorError> {
+    if kernel.dims().len() != 4 {
+        return Err(TensorError::DimMismatch("deconv kernel".to_string()));
+    }
+    let (input_channels, output_channels, kernel_height, kernel_width) = (
+        kernel.dims()[0],
+        kernel.dims()[1],
+        kernel.dims()[2],
+        kernel.dims()[3],
+    );
+
+    let mut output = Tensor::<T>::new(
+        None,
+        &[output_channels, input_channels, kernel_height, kernel_width],
+    )?;
+
+    for i in 0..input_channels {
+        for o in 0..output_channels {
+            for h in 0..kernel_height {
+                for w in 0..kernel_width {
+                    output.set(
+                        &[o, i, kernel_height - 1 - h, kernel_width - 1 - w],
+                        kernel.get(&[i, o, h, w]),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Rearranges the windows a convolution slides over a 3D tensor of shape C x H x W into the
+/// columns of a matrix of shape `(C * K_h * K_w) x (H' * W')`, such that the convolution is the
+/// product of the kernel, flattened to `C_out x (C * K_h * K_w)`, and this matrix.
+/// The tensor is padded with zeros by `padding`.
+/// # Arguments
+///
+/// * `image` - Tensor.
+/// * `kernel_shape` - Tuple of kernel sizes in x and y directions.
+/// * `padding` - Tuple of padding values in x and y directions.
+/// * `stride` - Tuple of stride values in x and y directions.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::im2col;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4, 5, 6]),
+///     &[1, 2, 3],
+/// ).unwrap();
+/// let result = im2col::<i128>(&x, (2, 2), (0, 0), (1, 1)).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[1, 2, 2, 3, 4, 5, 5, 6]),
+///     &[4, 2],
+/// ).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn im2col<T: TensorType>(
+    image: &Tensor<T>,
+    kernel_shape: (usize, usize),
+    padding: (usize, usize),
+    stride: (usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    if image.dims().len() != 3 {
+        return Err(TensorError::DimMismatch("im2col".to_string()));
+    }
+    let (channels, height, width) = (image.dims()[0], image.dims()[1], image.dims()[2]);
+    let (padded_height, padded_width) = (height + 2 * padding.0, width + 2 * padding.1);
+    if (kernel_shape.0 > padded_height) || (kernel_shape.1 > padded_width) {
+        return Err(TensorError::DimMismatch("im2col kernel".to_string()));
+    }
+
+    let vert_slides = (padded_height - kernel_shape.0) / stride.0 + 1;
+    let horz_slides = (padded_width - kernel_shape.1) / stride.1 + 1;
+
+    let mut output = Tensor::<T>::new(
+        None,
+        &[
+            channels * kernel_shape.0 * kernel_shape.1,
+            vert_slides * horz_slides,
+        ],
+    )?;
+
+    for channel in 0..channels {
+        for (i, j) in (0..kernel_shape.0).cartesian_product(0..kernel_shape.1) {
+            let patch_row = (channel * kernel_shape.0 + i) * kernel_shape.1 + j;
+            for (y, x) in (0..vert_slides).cartesian_product(0..horz_slides) {
+                let (row, col) = (y * stride.0 + i, x * stride.1 + j);
+                // the padding is left as zeros
+                if (row < padding.0)
+                    || (row >= padding.0 + height)
+                    || (col < padding.1)
+                    || (col >= padding.1 + width)
+                {
+                    continue;
+                }
+                output.set(
+                    &[patch_row, y * horz_slides + x],
+                    image.get(&[channel, row - padding.0, col - padding.1]),
+                );
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Splits a 3D tensor of shape C x H x W into the overlapping tiles a Winograd F(`tile`, `kernel`)
+/// convolution is computed over, giving a tensor of shape C x T x (tile + kernel - 1) x (tile +
+/// kernel - 1). The T tiles start every `tile` rows and columns, in row major order, and the tiles
+/// overhanging the bottom or right of the tensor are padded with zeros.
+/// # Arguments
+///
+/// * `image` - Tensor.
+/// * `tile` - The size of the output tiles.
+/// * `kernel` - The size of the (square) kernel.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::winograd_tiles;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]),
+///     &[1, 3, 4],
+/// ).unwrap();
+/// let result = winograd_tiles::<i128>(&x, 2, 2).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 5, 6, 7, 9, 10, 11, 3, 4, 0, 7, 8, 0, 11, 12, 0]),
+///     &[1, 2, 3, 3],
+/// ).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn winograd_tiles<T: TensorType>(
+    image: &Tensor<T>,
+    tile: usize,
+    kernel: usize,
+) -> Result<Tensor<T>, TensorError> {
+    if (image.dims().len() != 3) || (image.dims()[1] < kernel) || (image.dims()[2] < kernel) {
+        return Err(TensorError::DimMismatch("winograd tiles".to_string()));
+    }
+    let (channels, height, width) = (image.dims()[0], image.dims()[1], image.dims()[2]);
+    let alpha = tile + kernel - 1;
+
+    let vert_tiles = (height - kernel + 1 + tile - 1) / tile;
+    let horz_tiles = (width - kernel + 1 + tile - 1) / tile;
+
+    let mut output = Tensor::<T>::new(None, &[channels, vert_tiles * horz_tiles, alpha, alpha])?;
+
+    for channel in 0..channels {
+        for (y, x) in (0..vert_tiles).cartesian_product(0..horz_tiles) {
+            for (i, j) in (0..alpha).cartesian_product(0..alpha) {
+                let (row, col) = (y * tile + i, x * tile + j);
+                // the overhang is left as zeros
+                if (row >= height) || (col >= width) {
+                    continue;
+                }
+                output.set(
+                    &[channel, y * horz_tiles + x, i, j],
+                    image.get(&[channel, row, col]),
+                );
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// The integer transforms of a Winograd F(`tile`, 3) convolution, for output tiles of 2 or 4: the
+/// input transform `B^T` of shape (tile + 2) x (tile + 2), the kernel transform `G` of shape
+/// (tile + 2) x 3 and the output transform `A^T` of shape tile x (tile + 2). The fractional rows
+/// of `G` are scaled up to integers, such that the output tiles `A^T [(G g G^T) * (B^T d B)] A`
+/// are those of the convolution multiplied by the returned factor, which divides them exactly.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::ops::winograd_transforms;
+///
+/// let (input, kernel, output, factor) = winograd_transforms(2).unwrap();
+/// assert_eq!(input.dims(), &[4, 4]);
+/// assert_eq!(kernel.dims(), &[4, 3]);
+/// assert_eq!(output.dims(), &[2, 4]);
+/// assert_eq!(factor, 4);
+/// ```
+pub fn winograd_transforms(
+    tile: usize,
+) -> Result<(Tensor<i128>, Tensor<i128>, Tensor<i128>, i128), TensorError> {
+    match tile {
+        2 => Ok((
+            Tensor::new(
+                Some(&[1, 0, -1, 0, 0, 1, 1, 0, 0, -1, 1, 0, 0, 1, 0, -1]),
+                &[4, 4],
+            )?,
+            // G scaled by 2
+            Tensor::new(Some(&[2, 0, 0, 1, 1, 1, 1, -1, 1, 0, 0, 2]), &[4, 3])?,
+            Tensor::new(Some(&[1, 1, 1, 0, 0, 1, -1, -1]), &[2, 4])?,
+            4,
+        )),
+        4 => Ok((
+            Tensor::new(
+                Some(&[
+                    4, 0, -5, 0, 1, 0, 0, -4, -4, 1, 1, 0, 0, 4, -4, -1, 1, 0, 0, -2, -1, 2, 1, 0,
+                    0, 2, -1, -2, 1, 0, 0, 4, 0, -5, 0, 1,
+                ]),
+                &[6, 6],
+            )?,
+            // G scaled by 24
+            Tensor::new(
+                Some(&[6, 0, 0, -4, -4, -4, -4, 4, -4, 1, 2, 4, 1, -2, 4, 0, 0, 24]),
+                &[6, 3],
+            )?,
+            Tensor::new(
+                Some(&[
+                    1, 1, 1, 1, 1, 0, 0, 1, -1, 2, -2, 0, 0, 1, 1, 4, 4, 0, 0, 1, -1, 8, -8, 1,
+                ]),
+                &[4, 6],
+            )?,
+            576,
+        )),
+        _ => Err(TensorError::DimMismatch(format!(
+            "winograd tile of {}",
+            tile
+        ))),
+    }
+}
+
 /// Applies 2D sum pooling over a 3D tensor of shape C x H x W.
 /// # Arguments
 ///
@@ -531,6 +999,15 @@ pub fn sumpool<T: TensorType + Mul<Output = T> + Add<Output = T>>(
 /// let pooled = max_pool2d::<i128>(&x, (0, 0), (1, 1), (2, 2)).unwrap();
 /// let expected: Tensor<i128> = Tensor::<i128>::new(Some(&[5, 4, 4, 6]), &[1, 2, 2]).unwrap();
 /// assert_eq!(pooled, expected);
+///
+/// // the padding is never the max
+/// let x = Tensor::<i128>::new(Some(&[-5, -2, -3, -4]), &[1, 2, 2]).unwrap();
+/// let pooled = max_pool2d::<i128>(&x, (1, 1), (1, 1), (2, 2)).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[-5, -2, -2, -3, -2, -2, -3, -3, -4]),
+///     &[1, 3, 3],
+/// ).unwrap();
+/// assert_eq!(pooled, expected);
 /// ```
 pub fn max_pool2d<T: TensorType>(
     image: &Tensor<T>,
@@ -546,8 +1023,6 @@ pub fn max_pool2d<T: TensorType>(
     let input_channels = image_dims[0];
     let (image_height, image_width) = (image_dims[1], image_dims[2]);
 
-    let padded_image = pad::<T>(image, padding)?;
-
     let horz_slides = (image_height + 2 * padding.0 - pool_dims.0) / stride.0 + 1;
     let vert_slides = (image_width + 2 * padding.1 - pool_dims.1) / stride.1 + 1;
 
@@ -561,15 +1036,20 @@ pub fn max_pool2d<T: TensorType>(
         }
     };
 
+    // the padding is left out of the windows, as if the image were padded with -inf
+    let unpadded = |start: usize, pad: usize, kernel: usize, len: usize| {
+        start.saturating_sub(pad)..(start + kernel).min(len + pad) - pad
+    };
+
     for i in 0..input_channels {
         for j in 0..horz_slides {
-            let rs = j * stride.0;
+            let rs = unpadded(j * stride.0, padding.0, pool_dims.0, image_height);
             for k in 0..vert_slides {
-                let cs = k * stride.1;
+                let cs = unpadded(k * stride.1, padding.1, pool_dims.1, image_width);
                 output.set(
                     &[i, j, k],
-                    padded_image
-                        .get_slice(&[i..(i + 1), rs..(rs + pool_dims.0), cs..(cs + pool_dims.1)])?
+                    image
+                        .get_slice(&[i..(i + 1), rs.clone(), cs])?
                         .into_iter()
                         .fold(None, fmax)
                         .unwrap(),
@@ -580,30 +1060,467 @@ pub fn max_pool2d<T: TensorType>(
     Ok(output)
 }
 
-/// Dot product of two tensors.
+/// Applies 3D convolution over a 4D tensor of shape C x D x H x W (and adds a bias).
 /// # Arguments
 ///
-/// * `inputs` - Vector of tensors of length 2.
+/// * `inputs` - A vector of tensors holding in order: input volume, convolution kernel, convolution bias.
+/// * `padding` - Tuple of padding values in the depth, y, and x directions.
+/// * `stride` - Tuple of stride values in the depth, y, and x directions.
 /// # Examples
 /// ```
 /// use ezkl_lib::tensor::Tensor;
-/// use ezkl_lib::tensor::ops::dot;
+/// use ezkl_lib::tensor::ops::conv3d;
 ///
 /// let x = Tensor::<i128>::new(
-///     Some(&[5, 2, 3, 0, 4, -1, 3, 1, 6]),
-///     &[1, 3, 3],
+///     Some(&[1, 2, 3, 4, 5, 6, 7, 8]),
+///     &[1, 2, 2, 2],
 /// ).unwrap();
-/// let y = Tensor::<i128>::new(
-///     Some(&[5, 5, 10, -4, 2, -1, 2, 0, 1]),
-///     &[1, 3, 3],
+/// let k = Tensor::<i128>::new(
+///     Some(&[1, 0, 0, 0, 0, 0, 0, 1]),
+///     &[1, 1, 2, 2, 2],
 /// ).unwrap();
-/// assert_eq!(dot(&vec![&x, &y]).unwrap()[0], 86);
+/// let b = Tensor::<i128>::new(
+///     Some(&[1]),
+///     &[1],
+/// ).unwrap();
+/// let result = conv3d::<i128>(&[x, k, b], (0, 0, 0), (1, 1, 1)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[10]), &[1, 1, 1, 1]).unwrap();
+/// assert_eq!(result, expected);
 /// ```
-pub fn dot<T: TensorType + Mul<Output = T> + Add<Output = T>>(
-    inputs: &Vec<&Tensor<T>>,
+pub fn conv3d<T: TensorType + Mul<Output = T> + Add<Output = T>>(
+    inputs: &[Tensor<T>],
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
 ) -> Result<Tensor<T>, TensorError> {
-    if (inputs.len() != 2) || (inputs[0].clone().len() != inputs[1].clone().len()) {
-        return Err(TensorError::DimMismatch("dot".to_string()));
+    let has_bias = inputs.len() == 3;
+    let (image, kernel) = (inputs[0].clone(), inputs[1].clone());
+
+    if (image.dims().len() != 4)
+        || (kernel.dims().len() != 5)
+        || (image.dims()[0] != kernel.dims()[1])
+    {
+        return Err(TensorError::DimMismatch("conv3d".to_string()));
+    }
+
+    if has_bias {
+        let bias = inputs[2].clone();
+        if (bias.dims().len() != 1) || (bias.dims()[0] != kernel.dims()[0]) {
+            return Err(TensorError::DimMismatch("conv3d bias".to_string()));
+        }
+    }
+
+    let image_dims = image.dims();
+    let kernel_dims = kernel.dims();
+
+    let (output_channels, input_channels, kernel_depth, kernel_height, kernel_width) = (
+        kernel_dims[0],
+        kernel_dims[1],
+        kernel_dims[2],
+        kernel_dims[3],
+        kernel_dims[4],
+    );
+
+    let (image_depth, image_height, image_width) = (image_dims[1], image_dims[2], image_dims[3]);
+
+    let padded_image = pad3d::<T>(&image, padding)?;
+
+    let depth_slides = (image_depth + 2 * padding.0 - kernel_depth) / stride.0 + 1;
+    let vert_slides = (image_height + 2 * padding.1 - kernel_height) / stride.1 + 1;
+    let horz_slides = (image_width + 2 * padding.2 - kernel_width) / stride.2 + 1;
+
+    // calculate value of output
+    let mut output: Tensor<T> = Tensor::new(
+        None,
+        &[output_channels, depth_slides, vert_slides, horz_slides],
+    )
+    .unwrap();
+
+    for i in 0..output_channels {
+        for d in 0..depth_slides {
+            let ds = d * stride.0;
+            for j in 0..vert_slides {
+                let rs = j * stride.1;
+                for k in 0..horz_slides {
+                    let cs = k * stride.2;
+                    let mut res = dot(&vec![
+                        &kernel.get_slice(&[i..i + 1])?.clone(),
+                        &padded_image.get_slice(&[
+                            0..input_channels,
+                            ds..(ds + kernel_depth),
+                            rs..(rs + kernel_height),
+                            cs..(cs + kernel_width),
+                        ])?,
+                    ])?;
+
+                    if has_bias {
+                        // increment result by the bias
+                        res[0] = res[0].clone() + inputs[2][i].clone();
+                    }
+
+                    output.set(&[i, d, j, k], res[0].clone());
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Applies 3D sum pooling over a 4D tensor of shape C x D x H x W.
+/// # Arguments
+///
+/// * `image` - Tensor.
+/// * `padding` - Tuple of padding values in the depth, y, and x directions.
+/// * `stride` - Tuple of stride values in the depth, y, and x directions.
+/// * `pool_dims` - Tuple of pooling window size in the depth, y, and x directions.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::sumpool3d;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4, 5, 6, 7, 8]),
+///     &[1, 2, 2, 2],
+/// ).unwrap();
+/// let pooled = sumpool3d::<i128>(&x, (0, 0, 0), (1, 1, 1), (2, 1, 2)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[14, 22]), &[1, 1, 2, 1]).unwrap();
+/// assert_eq!(pooled, expected);
+/// ```
+pub fn sumpool3d<T: TensorType + Add<Output = T>>(
+    image: &Tensor<T>,
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    pool_dims: (usize, usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    pool3d(image, padding, stride, pool_dims, |window| {
+        Ok(sum(&window)?[0].clone())
+    })
+}
+
+/// Applies 3D max pooling over a 4D tensor of shape C x D x H x W.
+/// # Arguments
+///
+/// * `image` - Tensor.
+/// * `padding` - Tuple of padding values in the depth, y, and x directions.
+/// * `stride` - Tuple of stride values in the depth, y, and x directions.
+/// * `pool_dims` - Tuple of pooling window size in the depth, y, and x directions.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::max_pool3d;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, 2, 3, 4, 5, 6, 7, 8]),
+///     &[1, 2, 2, 2],
+/// ).unwrap();
+/// let pooled = max_pool3d::<i128>(&x, (0, 0, 0), (1, 1, 1), (2, 1, 2)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[6, 8]), &[1, 1, 2, 1]).unwrap();
+/// assert_eq!(pooled, expected);
+///
+/// // the padding is never the max
+/// let x = Tensor::<i128>::new(Some(&[-3, -1]), &[1, 1, 1, 2]).unwrap();
+/// let pooled = max_pool3d::<i128>(&x, (0, 0, 1), (1, 1, 1), (1, 1, 2)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[-3, -1, -1]), &[1, 1, 1, 3]).unwrap();
+/// assert_eq!(pooled, expected);
+/// ```
+pub fn max_pool3d<T: TensorType>(
+    image: &Tensor<T>,
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    pool_dims: (usize, usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    pool3d(image, padding, stride, pool_dims, |window| {
+        window
+            .iter()
+            .fold(None, |acc: Option<T>, x| match acc {
+                None => Some(x.clone()),
+                Some(a) => a.tmax(x),
+            })
+            .ok_or(TensorError::DimError)
+    })
+}
+
+/// Reduces the elements of the image within each pooling window of a C x D x H x W tensor using `f`.
+fn pool3d<T: TensorType>(
+    image: &Tensor<T>,
+    padding: (usize, usize, usize),
+    stride: (usize, usize, usize),
+    pool_dims: (usize, usize, usize),
+    f: impl Fn(Tensor<T>) -> Result<T, TensorError>,
+) -> Result<Tensor<T>, TensorError> {
+    if image.dims().len() != 4 {
+        return Err(TensorError::DimMismatch("pool3d".to_string()));
+    }
+    let image_dims = image.dims();
+
+    let channels = image_dims[0];
+    let (image_depth, image_height, image_width) = (image_dims[1], image_dims[2], image_dims[3]);
+
+    let depth_slides = (image_depth + 2 * padding.0 - pool_dims.0) / stride.0 + 1;
+    let vert_slides = (image_height + 2 * padding.1 - pool_dims.1) / stride.1 + 1;
+    let horz_slides = (image_width + 2 * padding.2 - pool_dims.2) / stride.2 + 1;
+
+    let mut output: Tensor<T> =
+        Tensor::new(None, &[channels, depth_slides, vert_slides, horz_slides]).unwrap();
+
+    // the padding is left out of the windows, which leaves their sums unchanged and is as if the
+    // image were padded with -inf for their max
+    let unpadded = |start: usize, pad: usize, kernel: usize, len: usize| {
+        start.saturating_sub(pad)..(start + kernel).min(len + pad) - pad
+    };
+
+    for i in 0..channels {
+        for d in 0..depth_slides {
+            let ds = unpadded(d * stride.0, padding.0, pool_dims.0, image_depth);
+            for j in 0..vert_slides {
+                let rs = unpadded(j * stride.1, padding.1, pool_dims.1, image_height);
+                for k in 0..horz_slides {
+                    let cs = unpadded(k * stride.2, padding.2, pool_dims.2, image_width);
+                    output.set(
+                        &[i, d, j, k],
+                        f(image.get_slice(&[i..(i + 1), ds.clone(), rs.clone(), cs])?)?,
+                    );
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// The indices of a two operand einsum equation, grouped by how they map onto a batched matmul.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EinsumIndices {
+    /// The indices of each input.
+    pub inputs: Vec<Vec<char>>,
+    /// The indices of the output.
+    pub output: Vec<char>,
+    /// Indices present in both inputs and the output.
+    pub batch: Vec<char>,
+    /// Indices only present in the first input and the output.
+    pub lhs_free: Vec<char>,
+    /// Indices present in both inputs but not the output, these are summed over.
+    pub contracted: Vec<char>,
+    /// Indices only present in the second input and the output.
+    pub rhs_free: Vec<char>,
+}
+
+impl EinsumIndices {
+    /// Parses an einsum equation, such as `bij,bjk->bik`. If the output is omitted (implicit mode)
+    /// it is given by the indices that appear exactly once, in alphabetical order.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::ops::EinsumIndices;
+    ///
+    /// let indices = EinsumIndices::new("bij,bjk->bik").unwrap();
+    /// assert_eq!(indices.batch, vec!['b']);
+    /// assert_eq!(indices.lhs_free, vec!['i']);
+    /// assert_eq!(indices.contracted, vec!['j']);
+    /// assert_eq!(indices.rhs_free, vec!['k']);
+    /// ```
+    pub fn new(equation: &str) -> Result<Self, TensorError> {
+        let equation: String = equation.chars().filter(|c| !c.is_whitespace()).collect();
+        if equation.contains("...") {
+            return Err(TensorError::DimMismatch(
+                "einsum ellipsis is not supported".to_string(),
+            ));
+        }
+        let (lhs, output) = match equation.split_once("->") {
+            Some((lhs, output)) => (lhs.to_string(), Some(output.chars().collect_vec())),
+            None => (equation.clone(), None),
+        };
+        let inputs = lhs
+            .split(',')
+            .map(|i| i.chars().collect_vec())
+            .collect_vec();
+        if inputs
+            .iter()
+            .flatten()
+            .chain(output.iter().flatten())
+            .any(|c| !c.is_ascii_alphabetic())
+        {
+            return Err(TensorError::DimMismatch("einsum equation".to_string()));
+        }
+
+        let output = match output {
+            Some(o) => o,
+            None => inputs
+                .iter()
+                .flatten()
+                .counts()
+                .into_iter()
+                .filter(|(_, count)| *count == 1)
+                .map(|(c, _)| *c)
+                .sorted()
+                .collect_vec(),
+        };
+
+        let (mut batch, mut lhs_free, mut contracted, mut rhs_free) =
+            (vec![], vec![], vec![], vec![]);
+        if inputs.len() == 2 {
+            for c in inputs.iter().flatten().unique() {
+                let (in_lhs, in_rhs, in_out) = (
+                    inputs[0].contains(c),
+                    inputs[1].contains(c),
+                    output.contains(c),
+                );
+                match (in_lhs, in_rhs, in_out) {
+                    (true, true, true) => batch.push(*c),
+                    (true, false, true) => lhs_free.push(*c),
+                    (true, true, false) => contracted.push(*c),
+                    (false, true, true) => rhs_free.push(*c),
+                    // an index that is summed out of a single operand
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(EinsumIndices {
+            inputs,
+            output,
+            batch,
+            lhs_free,
+            contracted,
+            rhs_free,
+        })
+    }
+
+    /// Whether the equation can be lowered to a single batched matmul.
+    pub fn is_matmul(&self) -> bool {
+        self.inputs.len() == 2
+            && self.inputs.iter().all(|i| i.iter().all_unique())
+            && self.output.iter().all_unique()
+            && self.batch.len() + self.lhs_free.len() + self.contracted.len()
+                == self.inputs[0].len()
+            && self.batch.len() + self.contracted.len() + self.rhs_free.len()
+                == self.inputs[1].len()
+    }
+
+    /// Maps each index to its size given the input shapes.
+    pub fn sizes(&self, input_shapes: &[Vec<usize>]) -> Result<HashMap<char, usize>, TensorError> {
+        if input_shapes.len() != self.inputs.len() {
+            return Err(TensorError::DimMismatch("einsum inputs".to_string()));
+        }
+        let mut sizes = HashMap::new();
+        for (indices, shape) in self.inputs.iter().zip(input_shapes) {
+            if indices.len() != shape.len() {
+                return Err(TensorError::DimMismatch("einsum input dims".to_string()));
+            }
+            for (c, d) in indices.iter().zip(shape) {
+                if *sizes.entry(*c).or_insert(*d) != *d {
+                    return Err(TensorError::DimMismatch(format!("einsum index {}", c)));
+                }
+            }
+        }
+        if self.output.iter().any(|c| !sizes.contains_key(c)) {
+            return Err(TensorError::DimMismatch("einsum output".to_string()));
+        }
+        Ok(sizes)
+    }
+}
+
+/// Evaluates an einsum equation over any number of tensors.
+/// # Arguments
+///
+/// * `equation` - The einsum equation, for instance `ij,jk->ik`.
+/// * `inputs` - The tensors to contract.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::einsum;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[2, 1, 2, 1, 1, 1]),
+///     &[2, 3],
+/// ).unwrap();
+/// let k = Tensor::<i128>::new(
+///     Some(&[2, 3, 2, 1, 1, 1]),
+///     &[3, 2],
+/// ).unwrap();
+/// let result = einsum::<i128>("ij,jk->ik", &[x.clone(), k]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[8, 9, 5, 5]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let trace = einsum::<i128>("ij->i", &[x]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[5, 3]), &[2]).unwrap();
+/// assert_eq!(trace, expected);
+/// ```
+pub fn einsum<T: TensorType + Mul<Output = T> + Add<Output = T>>(
+    equation: &str,
+    inputs: &[Tensor<T>],
+) -> Result<Tensor<T>, TensorError> {
+    let indices = EinsumIndices::new(equation)?;
+    let sizes = indices.sizes(&inputs.iter().map(|i| i.dims().to_vec()).collect_vec())?;
+
+    let summed = sizes
+        .keys()
+        .filter(|c| !indices.output.contains(*c))
+        .sorted()
+        .cloned()
+        .collect_vec();
+
+    let output_dims = indices.output.iter().map(|c| sizes[c]).collect_vec();
+    let mut output: Tensor<T> = Tensor::new(None, &output_dims)?;
+
+    let output_ranges = output_dims.iter().map(|d| 0..*d).collect_vec();
+    let summed_ranges = summed.iter().map(|c| 0..sizes[c]).collect_vec();
+
+    let coord_iter = |ranges: &Vec<Range<usize>>| -> Vec<Vec<usize>> {
+        if ranges.is_empty() {
+            vec![vec![]]
+        } else {
+            ranges
+                .iter()
+                .cloned()
+                .multi_cartesian_product()
+                .collect_vec()
+        }
+    };
+
+    for (i, out_coord) in coord_iter(&output_ranges).into_iter().enumerate() {
+        let mut res = T::zero().unwrap();
+        for summed_coord in coord_iter(&summed_ranges) {
+            let index_of = |c: &char| -> usize {
+                match indices.output.iter().position(|o| o == c) {
+                    Some(p) => out_coord[p],
+                    None => summed_coord[summed.iter().position(|s| s == c).unwrap()],
+                }
+            };
+            let mut prod = T::one().unwrap();
+            for (input, input_indices) in inputs.iter().zip(&indices.inputs) {
+                let coord = input_indices.iter().map(index_of).collect_vec();
+                prod = prod * input.get(&coord);
+            }
+            res = res + prod;
+        }
+        output[i] = res;
+    }
+
+    Ok(output)
+}
+
+/// Dot product of two tensors.
+/// # Arguments
+///
+/// * `inputs` - Vector of tensors of length 2.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::dot;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[5, 2, 3, 0, 4, -1, 3, 1, 6]),
+///     &[1, 3, 3],
+/// ).unwrap();
+/// let y = Tensor::<i128>::new(
+///     Some(&[5, 5, 10, -4, 2, -1, 2, 0, 1]),
+///     &[1, 3, 3],
+/// ).unwrap();
+/// assert_eq!(dot(&vec![&x, &y]).unwrap()[0], 86);
+/// ```
+pub fn dot<T: TensorType + Mul<Output = T> + Add<Output = T>>(
+    inputs: &Vec<&Tensor<T>>,
+) -> Result<Tensor<T>, TensorError> {
+    if (inputs.len() != 2) || (inputs[0].clone().len() != inputs[1].clone().len()) {
+        return Err(TensorError::DimMismatch("dot".to_string()));
     }
     let (a, b): (Tensor<T>, Tensor<T>) = (inputs[0].clone(), inputs[1].clone());
     let res = a
@@ -662,98 +1579,2534 @@ pub fn pad<T: TensorType>(
     Ok(output)
 }
 
-/// Packs a multi-dim tensor into a single elem tensor
+/// Pads a 4D tensor of shape `C x D x H x W` to a tensor of shape `C x (D + 2xPADDING) x (H + 2xPADDING) x (W + 2xPADDING)` using 0 values.
 /// # Arguments
 ///
-/// * `a` - Tensor.
-/// * `base` - Base to use when packing
-/// * `scale` - fixed point representation scale
+/// * `image` - Tensor.
+/// * `padding` - Tuple of padding values in the depth, y, and x directions.
 /// # Examples
 /// ```
 /// use ezkl_lib::tensor::Tensor;
-/// use ezkl_lib::tensor::ops::pack;
+/// use ezkl_lib::tensor::ops::pad3d;
 ///
 /// let x = Tensor::<i128>::new(
-///     Some(&[5, 2, 1]),
-///     &[1, 3],
+///     Some(&[1, 2]),
+///     &[1, 2, 1, 1],
 /// ).unwrap();
-/// let result = pack::<i128>(&x, 2, 2).unwrap();
+/// let result = pad3d::<i128>(&x, (1, 0, 0)).unwrap();
 /// let expected = Tensor::<i128>::new(
-///     Some(&[90]),
-///     &[1],
+///     Some(&[0, 1, 2, 0]),
+///     &[1, 4, 1, 1],
 /// ).unwrap();
 /// assert_eq!(result, expected);
 /// ```
-pub fn pack<T: TensorType>(a: &Tensor<T>, base: T, scale: u32) -> Result<Tensor<T>, TensorError>
-where
-    T: Add<Output = T>,
-    T: Mul<Output = T>,
-{
-    // base ^ (scale + tensor)
-    let mut output = T::zero().unwrap();
-    let base_tensor = Tensor::new(Some(&[base]), &[1])?;
-    for (i, a_i) in a.iter().enumerate() {
-        let pow_value = &base_tensor.pow((i as u32) * (scale + 1))?[0];
-        output = output + pow_value.clone() * a_i.clone();
+pub fn pad3d<T: TensorType>(
+    image: &Tensor<T>,
+    padding: (usize, usize, usize),
+) -> Result<Tensor<T>, TensorError> {
+    if image.dims().len() != 4 {
+        return Err(TensorError::DimMismatch("pad3d".to_string()));
     }
-    Tensor::new(Some(&[output]), &[1])
-}
-
-// ---------------------------------------------------------------------------------------------------------
-// -- nonlinear Functions ---------------------------------------------------------------------------------
-// ---------------------------------------------------------------------------------------------------------
-// ---------------------------------------------------------------------------------------------------------
-// ---------------------------------------------------------------------------------------------------------
-// ---------------------------------------------------------------------------------------------------------
+    let (channels, depth, height, width) = (
+        image.dims()[0],
+        image.dims()[1],
+        image.dims()[2],
+        image.dims()[3],
+    );
+    let padded_depth = depth + 2 * padding.0;
+    let padded_height = height + 2 * padding.1;
+    let padded_width = width + 2 * padding.2;
 
-/// Activation functions
-pub mod nonlinearities {
-    use super::*;
-    /// Elementwise applies sigmoid to a tensor of integers.
-    /// # Arguments
-    ///
-    /// * `a` - Tensor
-    /// * `scale_input` - Single value
-    /// * `scale_output` - Single value
-    /// # Examples
-    /// ```
-    /// use ezkl_lib::tensor::Tensor;
-    /// use ezkl_lib::tensor::ops::nonlinearities::sigmoid;
-    /// let x = Tensor::<i128>::new(
-    ///     Some(&[2, 15, 2, 1, 1, 0]),
-    ///     &[2, 3],
-    /// ).unwrap();
-    /// let result = sigmoid(&x, 1, 1);
-    /// let expected = Tensor::<i128>::new(Some(&[1, 1, 1, 1, 1, 1]), &[2, 3]).unwrap();
-    /// assert_eq!(result, expected);
-    /// ```
-    pub fn sigmoid(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
-        // calculate value of output
-        let mut output: Tensor<i128> = a.clone();
+    let mut output =
+        Tensor::<T>::new(None, &[channels, padded_depth, padded_height, padded_width]).unwrap();
 
-        for (i, a_i) in a.iter().enumerate() {
-            let kix = (*a_i as f32) / (scale_input as f32);
-            let fout = (scale_output as f32) / (1.0 + (-kix).exp());
-            let rounded = fout.round();
-            output[i] = rounded as i128;
+    for channel in 0..channels {
+        for d in 0..depth {
+            for row in 0..height {
+                for col in 0..width {
+                    output.set(
+                        &[channel, d + padding.0, row + padding.1, col + padding.2],
+                        image.get(&[channel, d, row, col]),
+                    );
+                }
+            }
         }
-        output
     }
 
-    /// Elementwise applies square root to a tensor of integers.
-    /// # Arguments
-    ///
-    /// * `a` - Tensor
-    /// * `scale_input` - Single value
-    /// * `scale_output` - Single value
-    /// # Examples
-    /// ```
-    /// use ezkl_lib::tensor::Tensor;
-    /// use ezkl_lib::tensor::ops::nonlinearities::sqrt;
-    /// let x = Tensor::<i128>::new(
-    ///     Some(&[4, 25, 8, 1, 1, 0]),
-    ///     &[2, 3],
-    /// ).unwrap();
+    Ok(output)
+}
+
+/// How the padded region of a tensor is filled.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PadFill {
+    /// Fills the padded region with a constant.
+    Constant(i128),
+    /// Mirrors the tensor at its borders, excluding the border itself.
+    Reflect,
+    /// Repeats the border of the tensor.
+    Edge,
+}
+
+/// Pads every axis of a tensor by the given amounts before and after it, filling the padded
+/// region as specified by `fill`.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `pads` - Padding before and after every axis.
+/// * `fill` - How the padded region is filled. Reflect padding must be smaller than the axis.
+/// * `constant` - The value used for [PadFill::Constant] padding.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{pad_with_fill, PadFill};
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3]), &[1, 3]).unwrap();
+/// let result = pad_with_fill(&x, &[(0, 0), (2, 1)], &PadFill::Reflect, 0).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3, 2, 1, 2, 3, 2]), &[1, 6]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = pad_with_fill(&x, &[(1, 0), (0, 2)], &PadFill::Edge, 0).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 3, 3, 3, 1, 2, 3, 3, 3]), &[2, 5]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = pad_with_fill(&x, &[(0, 1), (1, 0)], &PadFill::Constant(7), 7).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[7, 1, 2, 3, 7, 7, 7, 7]), &[2, 4]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn pad_with_fill<T: TensorType>(
+    a: &Tensor<T>,
+    pads: &[(usize, usize)],
+    fill: &PadFill,
+    constant: T,
+) -> Result<Tensor<T>, TensorError> {
+    let dims = a.dims();
+    if pads.len() != dims.len()
+        || (*fill == PadFill::Reflect
+            && pads
+                .iter()
+                .zip(dims)
+                .any(|((before, after), len)| before >= len || after >= len))
+    {
+        return Err(TensorError::DimMismatch("pad".to_string()));
+    }
+
+    let output_dims = pads
+        .iter()
+        .zip(dims)
+        .map(|((before, after), len)| before + len + after)
+        .collect_vec();
+    let values = output_dims
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product()
+        .map(|coord| {
+            let mut input_coord = vec![];
+            for (d, c) in coord.iter().enumerate() {
+                let (i, len) = (*c as i64 - pads[d].0 as i64, dims[d] as i64);
+                let i = match fill {
+                    _ if (0..len).contains(&i) => i,
+                    PadFill::Constant(_) => return constant.clone(),
+                    PadFill::Reflect if i < 0 => -i,
+                    PadFill::Reflect => 2 * (len - 1) - i,
+                    PadFill::Edge => i.clamp(0, len - 1),
+                };
+                input_coord.push(i as usize);
+            }
+            a.get(&input_coord)
+        })
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// Repeats a tensor along every axis, as in ONNX's `Tile`.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `repeats` - The number of repeats of every axis.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::tile;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let result = tile(&x, &[2, 1]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 1, 2, 3, 4]), &[4, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = tile(&x, &[1, 2]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 1, 2, 3, 4, 3, 4]), &[2, 4]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn tile<T: TensorType>(a: &Tensor<T>, repeats: &[usize]) -> Result<Tensor<T>, TensorError> {
+    let dims = a.dims();
+    if repeats.len() != dims.len() {
+        return Err(TensorError::DimMismatch("tile".to_string()));
+    }
+
+    let output_dims = dims.iter().zip(repeats).map(|(d, r)| d * r).collect_vec();
+    let values = output_dims
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product()
+        .map(|coord| {
+            let input_coord = coord.iter().zip(dims).map(|(c, d)| c % d).collect_vec();
+            a.get(&input_coord)
+        })
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// The dimensions a tensor of dimensions `dims` is broadcast to when expanded to `shape`, as in
+/// ONNX's `Expand`, if they are compatible.
+pub fn expand_dims(dims: &[usize], shape: &[usize]) -> Option<Vec<usize>> {
+    let rank = dims.len().max(shape.len());
+    let pad = |d: &[usize]| [vec![1; rank - d.len()], d.to_vec()].concat();
+    pad(dims)
+        .iter()
+        .zip(pad(shape))
+        .map(|(d, s)| match (d, s) {
+            (d, s) if *d == s || s == 1 => Some(*d),
+            (1, s) => Some(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Broadcasts a tensor to a shape, as in ONNX's `Expand`.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `shape` - The shape to broadcast to, following numpy's broadcasting rules.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::expand;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2]), &[2, 1]).unwrap();
+/// let result = expand(&x, &[2, 3]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 1, 1, 2, 2, 2]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = expand(&x, &[2, 1, 1]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 1, 2]), &[2, 2, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn expand<T: TensorType>(a: &Tensor<T>, shape: &[usize]) -> Result<Tensor<T>, TensorError> {
+    let output_dims = match expand_dims(a.dims(), shape) {
+        Some(d) => d,
+        None => return Err(TensorError::DimMismatch("expand".to_string())),
+    };
+    let offset = output_dims.len() - a.dims().len();
+
+    let values = output_dims
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product()
+        .map(|coord| {
+            let input_coord = coord[offset..]
+                .iter()
+                .zip(a.dims())
+                .map(|(c, d)| if *d == 1 { 0 } else { *c })
+                .collect_vec();
+            a.get(&input_coord)
+        })
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// The positions along an axis of length `len` that are kept by slicing it from `start` to
+/// `end` with `step`, following ONNX's `Slice`: negative starts and ends count back from the
+/// end of the axis, out of range starts and ends are clamped, and negative steps slice
+/// backwards.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::ops::slice_indices;
+///
+/// assert_eq!(slice_indices(5, 1, -1, 2), vec![1, 3]);
+/// assert_eq!(slice_indices(5, -1, i64::MIN, -2), vec![4, 2, 0]);
+/// assert_eq!(slice_indices(5, 2, i64::MAX, 1), vec![2, 3, 4]);
+/// ```
+pub fn slice_indices(len: usize, start: i64, end: i64, step: i64) -> Vec<usize> {
+    let len = len as i64;
+    let normalize = |i: i64| if i < 0 { i.saturating_add(len) } else { i };
+    let (start, end) = if step > 0 {
+        (normalize(start).clamp(0, len), normalize(end).clamp(0, len))
+    } else {
+        (
+            normalize(start).clamp(0, len - 1),
+            normalize(end).clamp(-1, len - 1),
+        )
+    };
+
+    let mut indices = vec![];
+    let mut i = start;
+    while (step > 0 && i < end) || (step < 0 && i > end) {
+        indices.push(i as usize);
+        i += step;
+    }
+    indices
+}
+
+/// Slices every axis of a tensor, as in ONNX's `Slice`. See [slice_indices] for how each axis
+/// is sliced.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `starts` - The start of every axis.
+/// * `ends` - The (exclusive) end of every axis.
+/// * `steps` - The step of every axis, which slices the axis backwards when negative.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::slice;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+/// let result = slice(&x, &[0, -1], &[2, i64::MIN], &[1, -1]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3, 2, 1, 6, 5, 4]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = slice(&x, &[-1, 0], &[2, 3], &[1, 2]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4, 6]), &[1, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn slice<T: TensorType>(
+    a: &Tensor<T>,
+    starts: &[i64],
+    ends: &[i64],
+    steps: &[i64],
+) -> Result<Tensor<T>, TensorError> {
+    let dims = a.dims();
+    if starts.len() != dims.len()
+        || ends.len() != dims.len()
+        || steps.len() != dims.len()
+        || steps.contains(&0)
+    {
+        return Err(TensorError::DimMismatch("slice".to_string()));
+    }
+
+    let indices = (0..dims.len())
+        .map(|d| slice_indices(dims[d], starts[d], ends[d], steps[d]))
+        .collect_vec();
+    let output_dims = indices.iter().map(|i| i.len()).collect_vec();
+    if output_dims.contains(&0) {
+        return Err(TensorError::DimMismatch("slice".to_string()));
+    }
+
+    let values = indices
+        .iter()
+        .map(|i| i.iter().cloned())
+        .multi_cartesian_product()
+        .map(|coord| a.get(&coord))
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// How the channels of a [depth_to_space] are split into blocks.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DepthToSpaceMode {
+    /// Depth-column-row: the block offset is the outer part of the channel index.
+    DCR,
+    /// Column-row-depth: the block offset is the inner part of the channel index.
+    CRD,
+}
+
+/// Rearranges blocks of channels of a `C x H x W` tensor into spatial blocks, giving a tensor of
+/// shape `C / (b * b) x (H * b) x (W * b)`, as in ONNX's `DepthToSpace`.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `blocksize` - The side `b` of the spatial blocks.
+/// * `mode` - How the channels are split into blocks.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{depth_to_space, DepthToSpaceMode};
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6, 7, 8]), &[8, 1, 1]).unwrap();
+/// let result = depth_to_space(&x, 2, &DepthToSpaceMode::DCR).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 3, 5, 7, 2, 4, 6, 8]), &[2, 2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = depth_to_space(&x, 2, &DepthToSpaceMode::CRD).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6, 7, 8]), &[2, 2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn depth_to_space<T: TensorType>(
+    a: &Tensor<T>,
+    blocksize: usize,
+    mode: &DepthToSpaceMode,
+) -> Result<Tensor<T>, TensorError> {
+    let b = blocksize;
+    if a.dims().len() != 3 || b == 0 || a.dims()[0] % (b * b) != 0 {
+        return Err(TensorError::DimMismatch("depth to space".to_string()));
+    }
+    let (c, h, w) = (a.dims()[0] / (b * b), a.dims()[1], a.dims()[2]);
+
+    let mut t = a.clone();
+    let mut output = match mode {
+        DepthToSpaceMode::DCR => {
+            t.reshape(&[b, b, c, h, w]);
+            t.permute(&[2, 3, 0, 4, 1])?
+        }
+        DepthToSpaceMode::CRD => {
+            t.reshape(&[c, b, b, h, w]);
+            t.permute(&[0, 3, 1, 4, 2])?
+        }
+    };
+    output.reshape(&[c, h * b, w * b]);
+    Ok(output)
+}
+
+/// Rearranges spatial blocks of a `C x H x W` tensor into channels, giving a tensor of shape
+/// `(C * b * b) x H / b x W / b`, as in ONNX's `SpaceToDepth`. This is the inverse of a
+/// [depth_to_space] in [DepthToSpaceMode::DCR] mode.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `blocksize` - The side `b` of the spatial blocks.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::space_to_depth;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 3, 5, 7, 2, 4, 6, 8]), &[2, 2, 2]).unwrap();
+/// let result = space_to_depth(&x, 2).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6, 7, 8]), &[8, 1, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn space_to_depth<T: TensorType>(
+    a: &Tensor<T>,
+    blocksize: usize,
+) -> Result<Tensor<T>, TensorError> {
+    let b = blocksize;
+    if a.dims().len() != 3 || b == 0 || a.dims()[1] % b != 0 || a.dims()[2] % b != 0 {
+        return Err(TensorError::DimMismatch("space to depth".to_string()));
+    }
+    let (c, h, w) = (a.dims()[0], a.dims()[1] / b, a.dims()[2] / b);
+
+    let mut t = a.clone();
+    t.reshape(&[c, h, b, w, b]);
+    let mut output = t.permute(&[2, 4, 0, 1, 3])?;
+    output.reshape(&[c * b * b, h, w]);
+    Ok(output)
+}
+
+/// The reduction applied by a [reduce].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Reduction {
+    /// Sums the reduced elements.
+    Sum,
+    /// Sums the reduced elements and divides by their number `denom`, rounding to the nearest
+    /// integer.
+    Mean {
+        /// The number of reduced elements.
+        denom: usize,
+        /// Whether the mean is rounded half up, as when divided with a remainder in-circuit,
+        /// rather than to the nearest integer.
+        remainder: bool,
+    },
+    /// Takes the largest of the reduced elements.
+    Max,
+    /// Takes the smallest of the reduced elements.
+    Min,
+    /// Multiplies the reduced elements, which are fixed point values with a multiplier of
+    /// `scale`, rescaling after every multiplication.
+    Prod {
+        /// Fixed point multiplier of the elements.
+        scale: usize,
+    },
+}
+
+/// Reduces a tensor over the given axes, as in ONNX's `ReduceSum`, `ReduceMean`, `ReduceMax`,
+/// `ReduceMin` and `ReduceProd`.
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `axes` - The axes to reduce.
+/// * `keep_dims` - Whether to keep the reduced axes, with a length of 1.
+/// * `reduction` - The reduction to apply.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{reduce, Reduction};
+///
+/// let x = Tensor::<i128>::new(Some(&[2, 1, 4, 3, 6, 5]), &[2, 3]).unwrap();
+/// let result = reduce(&x, &[1], false, &Reduction::Sum).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[7, 14]), &[2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = reduce(&x, &[0], true, &Reduction::Mean { denom: 2, remainder: false }).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3, 4, 5]), &[1, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = reduce(&x, &[0, 1], false, &Reduction::Max).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[6]), &[1]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = reduce(&x, &[1], false, &Reduction::Prod { scale: 2 }).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 23]), &[2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn reduce(
+    a: &Tensor<i128>,
+    axes: &[usize],
+    keep_dims: bool,
+    reduction: &Reduction,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = a.dims();
+    if axes.is_empty() || axes.iter().any(|axis| *axis >= dims.len()) {
+        return Err(TensorError::DimMismatch("reduce".to_string()));
+    }
+
+    // the reduced axes are moved last, so that every row holds the elements of an output
+    let order = (0..dims.len())
+        .filter(|d| !axes.contains(d))
+        .chain((0..dims.len()).filter(|d| axes.contains(d)))
+        .collect_vec();
+    let permuted = a.permute(&order)?;
+    let len: usize = axes.iter().map(|axis| dims[*axis]).product();
+
+    let values = permuted
+        .chunks(len)
+        .map(|row| match reduction {
+            Reduction::Sum => row.iter().sum(),
+            Reduction::Mean { denom, remainder } => {
+                let (sum, denom) = (row.iter().sum::<i128>(), *denom as i128);
+                match remainder {
+                    true => (sum + denom / 2).div_euclid(denom),
+                    false => ((sum as f32) / (denom as f32)).round() as i128,
+                }
+            }
+            Reduction::Max => *row.iter().max().unwrap(),
+            Reduction::Min => *row.iter().min().unwrap(),
+            Reduction::Prod { scale } => row[1..].iter().fold(row[0], |acc, x| {
+                ((acc * x) as f32 / (*scale as f32)).round() as i128
+            }),
+        })
+        .collect_vec();
+
+    let mut output_dims = dims
+        .iter()
+        .enumerate()
+        .filter_map(|(d, len)| match axes.contains(&d) {
+            true if keep_dims => Some(1),
+            true => None,
+            false => Some(*len),
+        })
+        .collect_vec();
+    if output_dims.is_empty() {
+        output_dims.push(1);
+    }
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// Packs a multi-dim tensor into a single elem tensor
+/// # Arguments
+///
+/// * `a` - Tensor.
+/// * `base` - Base to use when packing
+/// * `scale` - fixed point representation scale
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::pack;
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[5, 2, 1]),
+///     &[1, 3],
+/// ).unwrap();
+/// let result = pack::<i128>(&x, 2, 2).unwrap();
+/// let expected = Tensor::<i128>::new(
+///     Some(&[90]),
+///     &[1],
+/// ).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn pack<T: TensorType>(a: &Tensor<T>, base: T, scale: u32) -> Result<Tensor<T>, TensorError>
+where
+    T: Add<Output = T>,
+    T: Mul<Output = T>,
+{
+    // base ^ (scale + tensor)
+    let mut output = T::zero().unwrap();
+    let base_tensor = Tensor::new(Some(&[base]), &[1])?;
+    for (i, a_i) in a.iter().enumerate() {
+        let pow_value = &base_tensor.pow((i as u32) * (scale + 1))?[0];
+        output = output + pow_value.clone() * a_i.clone();
+    }
+    Tensor::new(Some(&[output]), &[1])
+}
+
+/// Unpacks a single elem tensor packed by [pack] into the `len` values it was packed from. Each
+/// value must be small enough (in magnitude) for it not to overflow into the next one.
+/// # Arguments
+///
+/// * `a` - Single elem tensor.
+/// * `base` - Base the values were packed with
+/// * `scale` - fixed point representation scale
+/// * `len` - Number of packed values
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{pack, unpack};
+///
+/// let x = Tensor::<i128>::new(
+///     Some(&[1, -3, 2]),
+///     &[3],
+/// ).unwrap();
+/// let packed = pack::<i128>(&x, 2, 2).unwrap();
+/// let result = unpack(&packed, 2, 2, 3).unwrap();
+/// assert_eq!(result, x);
+/// ```
+pub fn unpack(
+    a: &Tensor<i128>,
+    base: i128,
+    scale: u32,
+    len: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    if a.len() != 1 {
+        return Err(TensorError::DimMismatch("unpack".to_string()));
+    }
+    let digit = base.pow(scale + 1);
+    let mut packed = a[0];
+    let mut values = Vec::with_capacity(len);
+    for i in 0..len {
+        // digits are balanced around 0, so that negative values can be recovered
+        let mut d = packed.rem_euclid(digit);
+        if 2 * d >= digit {
+            d -= digit;
+        }
+        packed = (packed - d) / digit;
+        // as in pack, the first value is weighted by the base rather than by 1
+        values.push(if i == 0 { d / base } else { d });
+    }
+    Tensor::new(Some(&values), &[len])
+}
+
+/// Applies a single direction LSTM over a sequence of fixed point inputs, starting from zero
+/// hidden and cell states, and returns the hidden state at each step.
+/// # Arguments
+///
+/// * `inputs` - Vector of tensors of length 4: the `seq_len x input_size` input, the
+///   `4 * hidden_size x input_size` input weights, the `4 * hidden_size x hidden_size`
+///   recurrent weights and the summed `4 * hidden_size` bias, with gates ordered as input,
+///   output, forget, cell.
+/// * `hidden_size` - Size of the hidden state.
+/// * `scale` - Fixed point multiplier of the input, weights and hidden state (the bias uses its square).
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::lstm;
+///
+/// let x = Tensor::<i128>::new(Some(&[4, 8]), &[2, 1]).unwrap();
+/// let w = Tensor::<i128>::new(Some(&[4, 2, 4, -4]), &[4, 1]).unwrap();
+/// let r = Tensor::<i128>::new(Some(&[2, 2, 2, 2]), &[4, 1]).unwrap();
+/// let b = Tensor::<i128>::new(Some(&[0, 0, 0, 0]), &[4]).unwrap();
+/// let result = lstm(&[x, w, r, b], 1, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[-1, -2]), &[2, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn lstm(
+    inputs: &[Tensor<i128>],
+    hidden_size: usize,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    if inputs.len() != 4 {
+        return Err(TensorError::DimMismatch("lstm inputs".to_string()));
+    }
+    let (x, w, r) = (&inputs[0], &inputs[1], &inputs[2]);
+    let h = hidden_size;
+    if x.dims().len() != 2
+        || w.dims() != [4 * h, x.dims()[1]]
+        || r.dims() != [4 * h, h]
+        || inputs[3].len() != 4 * h
+    {
+        return Err(TensorError::DimMismatch("lstm".to_string()));
+    }
+    let (seq_len, input_size) = (x.dims()[0], x.dims()[1]);
+    let mut b = inputs[3].clone();
+    b.reshape(&[4 * h, 1]);
+
+    let mut hidden: Option<Tensor<i128>> = None;
+    let mut cell: Option<Tensor<i128>> = None;
+    let mut outputs = vec![];
+    for t in 0..seq_len {
+        let mut x_t = x.get_slice(&[t..t + 1])?;
+        x_t.reshape(&[input_size, 1]);
+        let mut gates = matmul(&[w.clone(), x_t])?;
+        if let Some(hidden) = &hidden {
+            gates = add(&[gates, matmul(&[r.clone(), hidden.clone()])?])?;
+        }
+        gates = add(&[gates, b.clone()])?;
+
+        let gate = |i: usize| gates.get_slice(&[i * h..(i + 1) * h]);
+        let input_gate = nonlinearities::sigmoid(&gate(0)?, scale * scale, scale);
+        let output_gate = nonlinearities::sigmoid(&gate(1)?, scale * scale, scale);
+        let forget_gate = nonlinearities::sigmoid(&gate(2)?, scale * scale, scale);
+        let cell_gate = nonlinearities::tanh(&gate(3)?, scale * scale, scale);
+
+        let mut c = mult(&[input_gate, cell_gate])?;
+        if let Some(cell) = &cell {
+            c = add(&[mult(&[forget_gate, cell.clone()])?, c])?;
+        }
+        let c = nonlinearities::const_div(&c, scale as f32);
+        let h_t = mult(&[output_gate, nonlinearities::tanh(&c, scale, scale)])?;
+        let h_t = nonlinearities::const_div(&h_t, scale as f32);
+
+        outputs.push(h_t.clone());
+        hidden = Some(h_t);
+        cell = Some(c);
+    }
+
+    let mut output = Tensor::new(Some(&outputs), &[seq_len])?.combine()?;
+    output.reshape(&[seq_len, h]);
+    Ok(output)
+}
+
+/// Applies a single direction GRU over a sequence of fixed point inputs and returns the hidden
+/// state at each step.
+/// # Arguments
+///
+/// * `inputs` - Vector of tensors of length 4 or 5: the `seq_len x input_size` input, the
+///   `3 * hidden_size x input_size` input weights, the `3 * hidden_size x hidden_size`
+///   recurrent weights, the `6 * hidden_size` input and recurrent biases, and an optional
+///   initial hidden state (zero if absent), with gates ordered as update, reset, hidden.
+/// * `hidden_size` - Size of the hidden state.
+/// * `scale` - Fixed point multiplier of the input, weights and hidden state (the bias uses its square).
+/// * `linear_before_reset` - Whether the reset gate is applied after the recurrent matmul.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::gru;
+///
+/// let x = Tensor::<i128>::new(Some(&[4, 8]), &[2, 1]).unwrap();
+/// let w = Tensor::<i128>::new(Some(&[-4, -4, 4]), &[3, 1]).unwrap();
+/// let r = Tensor::<i128>::new(Some(&[0, 4, 4]), &[3, 1]).unwrap();
+/// let b = Tensor::<i128>::new(Some(&[0, 0, 0, 0, 0, 0]), &[6]).unwrap();
+/// let result = gru(&[x, w, r, b], 1, 4, true).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 3]), &[2, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn gru(
+    inputs: &[Tensor<i128>],
+    hidden_size: usize,
+    scale: usize,
+    linear_before_reset: bool,
+) -> Result<Tensor<i128>, TensorError> {
+    if inputs.len() != 4 && inputs.len() != 5 {
+        return Err(TensorError::DimMismatch("gru inputs".to_string()));
+    }
+    let (x, w, r) = (&inputs[0], &inputs[1], &inputs[2]);
+    let h = hidden_size;
+    if x.dims().len() != 2
+        || w.dims() != [3 * h, x.dims()[1]]
+        || r.dims() != [3 * h, h]
+        || inputs[3].len() != 6 * h
+        || inputs.get(4).map_or(false, |h_0| h_0.len() != h)
+    {
+        return Err(TensorError::DimMismatch("gru".to_string()));
+    }
+    let (seq_len, input_size) = (x.dims()[0], x.dims()[1]);
+    let mut b = inputs[3].clone();
+    b.reshape(&[6 * h, 1]);
+    let (w_b, r_b) = (b.get_slice(&[0..3 * h])?, b.get_slice(&[3 * h..6 * h])?);
+    // when the reset gate is applied before the recurrent matmul, the hidden gate's recurrent
+    // matmul is computed separately
+    let recurrent_rows = if linear_before_reset { 3 * h } else { 2 * h };
+    let (r_rows, r_b_rows) = (
+        r.get_slice(&[0..recurrent_rows])?,
+        r_b.get_slice(&[0..recurrent_rows])?,
+    );
+
+    let mut hidden = inputs.get(4).cloned().map(|mut h_0| {
+        h_0.reshape(&[h, 1]);
+        h_0
+    });
+    let mut outputs = vec![];
+    for t in 0..seq_len {
+        let mut x_t = x.get_slice(&[t..t + 1])?;
+        x_t.reshape(&[input_size, 1]);
+        let gates_x = add(&[matmul(&[w.clone(), x_t])?, w_b.clone()])?;
+        let gates_h = match &hidden {
+            Some(hidden) => add(&[matmul(&[r_rows.clone(), hidden.clone()])?, r_b_rows.clone()])?,
+            None => r_b_rows.clone(),
+        };
+
+        let gate = |i: usize| -> Result<Tensor<i128>, TensorError> {
+            add(&[
+                gates_x.get_slice(&[i * h..(i + 1) * h])?,
+                gates_h.get_slice(&[i * h..(i + 1) * h])?,
+            ])
+        };
+        let update_gate = nonlinearities::sigmoid(&gate(0)?, scale * scale, scale);
+        let reset_gate = nonlinearities::sigmoid(&gate(1)?, scale * scale, scale);
+
+        let candidate = if linear_before_reset {
+            let reset = mult(&[reset_gate, gates_h.get_slice(&[2 * h..3 * h])?])?;
+            let reset = nonlinearities::const_div(&reset, scale as f32);
+            add(&[gates_x.get_slice(&[2 * h..3 * h])?, reset])?
+        } else {
+            let mut candidate = add(&[
+                gates_x.get_slice(&[2 * h..3 * h])?,
+                r_b.get_slice(&[2 * h..3 * h])?,
+            ])?;
+            if let Some(hidden) = &hidden {
+                let reset = mult(&[reset_gate, hidden.clone()])?;
+                let reset = nonlinearities::const_div(&reset, scale as f32);
+                let recurrent = matmul(&[r.get_slice(&[2 * h..3 * h])?, reset])?;
+                candidate = add(&[candidate, recurrent])?;
+            }
+            candidate
+        };
+        let candidate = nonlinearities::tanh(&candidate, scale * scale, scale);
+
+        // h_t = (1 - z) * n + z * h_{t-1}
+        let h_t = match &hidden {
+            Some(hidden) => {
+                let diff = sub(&[hidden.clone(), candidate.clone()])?;
+                let update = mult(&[update_gate, diff])?;
+                add(&[candidate, nonlinearities::const_div(&update, scale as f32)])?
+            }
+            None => {
+                let update = mult(&[update_gate, candidate.clone()])?;
+                sub(&[candidate, nonlinearities::const_div(&update, scale as f32)])?
+            }
+        };
+
+        outputs.push(h_t.clone());
+        hidden = Some(h_t);
+    }
+
+    let mut output = Tensor::new(Some(&outputs), &[seq_len])?.combine()?;
+    output.reshape(&[seq_len, h]);
+    Ok(output)
+}
+
+/// Applies a single direction tanh RNN over a sequence of fixed point inputs and returns the
+/// hidden state at each step.
+/// # Arguments
+///
+/// * `inputs` - Vector of tensors of length 4 or 5: the `seq_len x input_size` input, the
+///   `hidden_size x input_size` input weights, the `hidden_size x hidden_size` recurrent
+///   weights, the summed `hidden_size` bias, and an optional initial hidden state (zero if absent).
+/// * `hidden_size` - Size of the hidden state.
+/// * `scale` - Fixed point multiplier of the input, weights and hidden state (the bias uses its square).
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::rnn;
+///
+/// let x = Tensor::<i128>::new(Some(&[4, 8]), &[2, 1]).unwrap();
+/// let w = Tensor::<i128>::new(Some(&[-2]), &[1, 1]).unwrap();
+/// let r = Tensor::<i128>::new(Some(&[4]), &[1, 1]).unwrap();
+/// let b = Tensor::<i128>::new(Some(&[0]), &[1]).unwrap();
+/// let h_0 = Tensor::<i128>::new(Some(&[4]), &[1]).unwrap();
+/// let result = rnn(&[x, w, r, b, h_0], 1, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, -2]), &[2, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn rnn(
+    inputs: &[Tensor<i128>],
+    hidden_size: usize,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    if inputs.len() != 4 && inputs.len() != 5 {
+        return Err(TensorError::DimMismatch("rnn inputs".to_string()));
+    }
+    let (x, w, r) = (&inputs[0], &inputs[1], &inputs[2]);
+    let h = hidden_size;
+    if x.dims().len() != 2
+        || w.dims() != [h, x.dims()[1]]
+        || r.dims() != [h, h]
+        || inputs[3].len() != h
+        || inputs.get(4).map_or(false, |h_0| h_0.len() != h)
+    {
+        return Err(TensorError::DimMismatch("rnn".to_string()));
+    }
+    let (seq_len, input_size) = (x.dims()[0], x.dims()[1]);
+    let mut b = inputs[3].clone();
+    b.reshape(&[h, 1]);
+
+    let mut hidden = inputs.get(4).cloned().map(|mut h_0| {
+        h_0.reshape(&[h, 1]);
+        h_0
+    });
+    let mut outputs = vec![];
+    for t in 0..seq_len {
+        let mut x_t = x.get_slice(&[t..t + 1])?;
+        x_t.reshape(&[input_size, 1]);
+        let mut pre = matmul(&[w.clone(), x_t])?;
+        if let Some(hidden) = &hidden {
+            pre = add(&[pre, matmul(&[r.clone(), hidden.clone()])?])?;
+        }
+        pre = add(&[pre, b.clone()])?;
+        let h_t = nonlinearities::tanh(&pre, scale * scale, scale);
+
+        outputs.push(h_t.clone());
+        hidden = Some(h_t);
+    }
+
+    let mut output = Tensor::new(Some(&outputs), &[seq_len])?.combine()?;
+    output.reshape(&[seq_len, h]);
+    Ok(output)
+}
+
+/// Applies softmax over the last axis of a fixed point tensor. The exponentials are normalized by
+/// the reciprocal of their sum, which is computed at the square of the output scale.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `scales` - Fixed point multipliers of the input and the output.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::softmax;
+///
+/// let x = Tensor::<i128>::new(Some(&[0, 2, 2, 2]), &[2, 2]).unwrap();
+/// let result = softmax(&x, (2, 4)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 3, 2, 2]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn softmax(a: &Tensor<i128>, scales: (usize, usize)) -> Result<Tensor<i128>, TensorError> {
+    let (scale_input, scale_output) = scales;
+    let n = match a.dims().last() {
+        Some(n) if *n > 0 => *n,
+        _ => return Err(TensorError::DimMismatch("softmax".to_string())),
+    };
+
+    let exps = nonlinearities::exp(a, scale_input, scale_output);
+    let mut rows = vec![];
+    for r in 0..a.len() / n {
+        let row = Tensor::new(Some(&exps[r * n..(r + 1) * n]), &[n])?;
+        let denom = nonlinearities::recip(&sum(&row)?, scale_output, scale_output * scale_output);
+        let row = mult(&[row, denom])?;
+        rows.push(nonlinearities::const_div(
+            &row,
+            (scale_output * scale_output) as f32,
+        ));
+    }
+
+    let mut output = Tensor::new(Some(&rows), &[rows.len()])?.combine()?;
+    output.reshape(a.dims());
+    Ok(output)
+}
+
+/// Applies scaled dot-product attention, `softmax(q k^T) v`, to `seq_len x d` queries and keys
+/// and `seq_len x d_v` values. The `1 / sqrt(d)` factor is folded into the input scale of the
+/// softmax, and the output is at the product of the softmax and value scales.
+/// # Arguments
+///
+/// * `inputs` - Vector of tensors of length 3: the queries, keys and values.
+/// * `scales` - Fixed point multipliers of the attention scores and of the softmax output.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::attention;
+///
+/// let q = Tensor::<i128>::new(Some(&[2, 0]), &[2, 1]).unwrap();
+/// let k = Tensor::<i128>::new(Some(&[2, 0]), &[2, 1]).unwrap();
+/// let v = Tensor::<i128>::new(Some(&[4, 8]), &[2, 1]).unwrap();
+/// let result = attention(&[q, k, v], (4, 4)).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[20, 24]), &[2, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn attention(
+    inputs: &[Tensor<i128>],
+    scales: (usize, usize),
+) -> Result<Tensor<i128>, TensorError> {
+    if inputs.len() != 3 {
+        return Err(TensorError::DimMismatch("attention inputs".to_string()));
+    }
+    let scores = einsum("ik,jk->ij", &inputs[0..2])?;
+    let probs = softmax(&scores, scales)?;
+    matmul(&[probs, inputs[2].clone()])
+}
+
+/// Gathers the slices of a tensor at the given indices along an axis, as in ONNX's `Gather`.
+/// # Arguments
+///
+/// * `input` - Tensor
+/// * `axis` - The axis to gather along.
+/// * `indices` - The indices to gather, their dimensions replace the gathered axis.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::gather;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+/// let indices = Tensor::<usize>::new(Some(&[2, 0]), &[2]).unwrap();
+/// let result = gather(&x, 1, &indices).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3, 1, 6, 4]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let indices = Tensor::<usize>::new(Some(&[1]), &[1]).unwrap();
+/// let result = gather(&x, 0, &indices).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4, 5, 6]), &[1, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn gather<T: TensorType>(
+    input: &Tensor<T>,
+    axis: usize,
+    indices: &Tensor<usize>,
+) -> Result<Tensor<T>, TensorError> {
+    let dims = input.dims();
+    if axis >= dims.len() || indices.iter().any(|i| *i >= dims[axis]) {
+        return Err(TensorError::DimMismatch("gather".to_string()));
+    }
+
+    // move the gathered axis to the front, so each index selects a contiguous slice
+    let order = std::iter::once(axis)
+        .chain((0..dims.len()).filter(|d| *d != axis))
+        .collect_vec();
+    let permuted = input.permute(&order)?;
+    let slice_len = input.len() / dims[axis];
+    let mut values = vec![];
+    for i in indices.iter() {
+        values.extend(permuted[i * slice_len..(i + 1) * slice_len].iter().cloned());
+    }
+    let mut gathered_dims = vec![indices.len()];
+    gathered_dims.extend(order[1..].iter().map(|d| dims[*d]));
+    let gathered = Tensor::new(Some(&values), &gathered_dims)?;
+
+    let inverse = (0..dims.len())
+        .map(|d| order.iter().position(|o| *o == d).unwrap())
+        .collect_vec();
+    let mut output = gathered.permute(&inverse)?;
+    output.reshape(&gather_dims(dims, axis, indices.dims()));
+    Ok(output)
+}
+
+/// The dimensions of a [gather] of a tensor of dimensions `dims` along `axis`.
+pub fn gather_dims(dims: &[usize], axis: usize, index_dims: &[usize]) -> Vec<usize> {
+    let output_dims = [&dims[..axis], index_dims, &dims[axis + 1..]].concat();
+    if output_dims.is_empty() {
+        vec![1]
+    } else {
+        output_dims
+    }
+}
+
+/// Looks up the rows of an embedding table at the given token ids. The ids are fixed point
+/// values, so id `j` is expected as `j * scale`, and ids matching no row yield a row of zeros.
+/// # Arguments
+///
+/// * `table` - The `vocab_size x embedding_dim` embedding table.
+/// * `ids` - Tensor of token ids.
+/// * `scale` - Fixed point multiplier of the ids.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::embedding;
+///
+/// let table = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[3, 2]).unwrap();
+/// let ids = Tensor::<i128>::new(Some(&[8, 0, 4]), &[3]).unwrap();
+/// let result = embedding(&table, &ids, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[5, 6, 1, 2, 3, 4]), &[3, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn embedding(
+    table: &Tensor<i128>,
+    ids: &Tensor<i128>,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    if table.dims().len() != 2 {
+        return Err(TensorError::DimMismatch("embedding".to_string()));
+    }
+    let (vocab_size, embedding_dim) = (table.dims()[0], table.dims()[1]);
+
+    let mut values = vec![];
+    for id in ids.iter() {
+        match index_position(*id, vocab_size, scale) {
+            Some(j) => values.extend(table.get_slice(&[j..j + 1])?.iter().cloned()),
+            None => values.extend(vec![0; embedding_dim]),
+        }
+    }
+
+    let mut output_dims = ids.dims().to_vec();
+    output_dims.push(embedding_dim);
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// The position `j` of a fixed point index `j * scale`, if it is within `len`.
+fn index_position(index: i128, len: usize, scale: usize) -> Option<usize> {
+    (0..len).find(|j| (j * scale) as i128 == index)
+}
+
+/// The position `j` of a fixed point index `j * scale` within `len`, where negative indices
+/// `(j - len) * scale` wrap as in ONNX.
+fn wrapped_index_position(index: i128, len: usize, scale: usize) -> Option<usize> {
+    index_position(index, len, scale)
+        .or_else(|| index_position(index + (len * scale) as i128, len, scale))
+}
+
+/// The position of the slice of the leading axes `dims` indexed by a fixed point `index`, if
+/// every element of the index is within its axis (see [wrapped_index_position]).
+fn slice_position(index: &[i128], dims: &[usize], scale: usize) -> Option<usize> {
+    index.iter().zip(dims).try_fold(0, |position, (i, d)| {
+        Some(position * d + wrapped_index_position(*i, *d, scale)?)
+    })
+}
+
+/// Gathers the elements of a tensor along an axis at the given indices, as in ONNX's
+/// `GatherElements`. The indices are fixed point values, so index `j` is expected as
+/// `j * scale`, negative indices wrap, and indices out of range yield zeros.
+/// # Arguments
+///
+/// * `data` - Tensor
+/// * `indices` - Tensor of indices, of the same dimensions as `data` except along `axis`.
+/// * `axis` - The axis to gather along.
+/// * `scale` - Fixed point multiplier of the indices.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::gather_elements;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let indices = Tensor::<i128>::new(Some(&[0, -2, 2, 0]), &[2, 2]).unwrap();
+/// let result = gather_elements(&x, &indices, 1, 2).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 4, 3]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn gather_elements(
+    data: &Tensor<i128>,
+    indices: &Tensor<i128>,
+    axis: usize,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = data.dims();
+    if axis >= dims.len()
+        || indices.dims().len() != dims.len()
+        || (0..dims.len()).any(|d| d != axis && indices.dims()[d] != dims[d])
+    {
+        return Err(TensorError::DimMismatch("gather elements".to_string()));
+    }
+
+    let mut output = indices.clone();
+    let coords = indices
+        .dims()
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product();
+    for (i, mut coord) in coords.enumerate() {
+        output[i] = match wrapped_index_position(indices[i], dims[axis], scale) {
+            Some(j) => {
+                coord[axis] = j;
+                data.get(&coord)
+            }
+            None => 0,
+        };
+    }
+    Ok(output)
+}
+
+/// Gathers the slices of a tensor indexed by the last axis of `indices`, as in ONNX's `GatherND`
+/// with no batch dimensions. The indices are fixed point values, so index `j` is expected as
+/// `j * scale`, negative indices wrap, and indices out of range yield slices of zeros.
+/// # Arguments
+///
+/// * `data` - Tensor
+/// * `indices` - Tensor of indices, whose last axis indexes the leading axes of `data`.
+/// * `scale` - Fixed point multiplier of the indices.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::gather_nd;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let indices = Tensor::<i128>::new(Some(&[-1, 0]), &[2, 1]).unwrap();
+/// let result = gather_nd(&x, &indices, 1).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3, 4, 1, 2]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let indices = Tensor::<i128>::new(Some(&[1, 0]), &[1, 2]).unwrap();
+/// let result = gather_nd(&x, &indices, 1).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3]), &[1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn gather_nd(
+    data: &Tensor<i128>,
+    indices: &Tensor<i128>,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let (dims, index_dims) = (data.dims(), indices.dims());
+    let k = match index_dims.last() {
+        Some(k) if *k > 0 && *k <= dims.len() => *k,
+        _ => return Err(TensorError::DimMismatch("gather nd".to_string())),
+    };
+    let slice_len: usize = dims[k..].iter().product();
+
+    let mut values = vec![];
+    for m in 0..indices.len() / k {
+        match slice_position(&indices[m * k..(m + 1) * k], &dims[..k], scale) {
+            Some(j) => values.extend(data[j * slice_len..(j + 1) * slice_len].iter().cloned()),
+            None => values.extend(vec![0; slice_len]),
+        }
+    }
+
+    let mut output_dims = [&index_dims[..index_dims.len() - 1], &dims[k..]].concat();
+    if output_dims.is_empty() {
+        output_dims.push(1);
+    }
+    Tensor::new(Some(&values), &output_dims)
+}
+
+// ---------------------------------------------------------------------------------------------------------
+// -- nonlinear Functions ---------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------------------------------
+
+/// Scatters updates into a copy of a tensor along an axis at the given indices, as in ONNX's
+/// `ScatterElements`. The indices are fixed point values, so index `j` is expected as
+/// `j * scale`, negative indices wrap, and updates with indices out of range are dropped.
+/// Updates are written in order, so when several indices collide the last update wins.
+/// # Arguments
+///
+/// * `data` - Tensor
+/// * `indices` - Tensor of indices, of the same dimensions as `data` except along `axis`.
+/// * `updates` - Tensor of updates, of the same dimensions as `indices`.
+/// * `axis` - The axis to scatter along.
+/// * `scale` - Fixed point multiplier of the indices.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::scatter_elements;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let indices = Tensor::<i128>::new(Some(&[1, 1, 0, 0]), &[2, 2]).unwrap();
+/// let updates = Tensor::<i128>::new(Some(&[5, 6, 7, 8]), &[2, 2]).unwrap();
+/// let result = scatter_elements(&x, &indices, &updates, 1, 1).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 6, 8, 4]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn scatter_elements(
+    data: &Tensor<i128>,
+    indices: &Tensor<i128>,
+    updates: &Tensor<i128>,
+    axis: usize,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = data.dims();
+    if axis >= dims.len()
+        || indices.dims().len() != dims.len()
+        || (0..dims.len()).any(|d| d != axis && indices.dims()[d] != dims[d])
+        || updates.dims() != indices.dims()
+    {
+        return Err(TensorError::DimMismatch("scatter elements".to_string()));
+    }
+
+    let mut output = data.clone();
+    let coords = indices
+        .dims()
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product();
+    for (i, mut coord) in coords.enumerate() {
+        if let Some(j) = wrapped_index_position(indices[i], dims[axis], scale) {
+            coord[axis] = j;
+            let index = output.get_index(&coord);
+            output[index] = updates[i];
+        }
+    }
+    Ok(output)
+}
+
+/// Scatters updates into a copy of a tensor at the slices indexed by the last axis of
+/// `indices`, as in ONNX's `ScatterND`. The indices are fixed point values, so index `j` is
+/// expected as `j * scale`, negative indices wrap, and updates with indices out of range are
+/// dropped. Updates are written in order, so when several indices collide the last update wins.
+/// # Arguments
+///
+/// * `data` - Tensor
+/// * `indices` - Tensor of indices, whose last axis indexes the leading axes of `data`.
+/// * `updates` - Tensor of updates, one slice of `data` per index.
+/// * `scale` - Fixed point multiplier of the indices.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::scatter_nd;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let indices = Tensor::<i128>::new(Some(&[1]), &[1, 1]).unwrap();
+/// let updates = Tensor::<i128>::new(Some(&[5, 6]), &[1, 2]).unwrap();
+/// let result = scatter_nd(&x, &indices, &updates, 1).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 5, 6]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn scatter_nd(
+    data: &Tensor<i128>,
+    indices: &Tensor<i128>,
+    updates: &Tensor<i128>,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let (dims, index_dims) = (data.dims(), indices.dims());
+    let k = match index_dims.last() {
+        Some(k) if *k > 0 && *k <= dims.len() => *k,
+        _ => return Err(TensorError::DimMismatch("scatter nd".to_string())),
+    };
+    let slice_len: usize = dims[k..].iter().product();
+    if updates.len() != (indices.len() / k) * slice_len {
+        return Err(TensorError::DimMismatch("scatter nd".to_string()));
+    }
+
+    let mut output = data.clone();
+    for m in 0..indices.len() / k {
+        if let Some(j) = slice_position(&indices[m * k..(m + 1) * k], &dims[..k], scale) {
+            for s in 0..slice_len {
+                output[j * slice_len + s] = updates[m * slice_len + s];
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Returns the `k` largest (or smallest) elements of a tensor along an axis, sorted, as in
+/// ONNX's `TopK`.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `k` - The number of elements to keep.
+/// * `axis` - The axis to select along.
+/// * `largest` - Whether to keep the largest elements, otherwise the smallest ones are kept.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::topk;
+///
+/// let x = Tensor::<i128>::new(Some(&[3, 1, 2, 0, 5, 4]), &[2, 3]).unwrap();
+/// let result = topk(&x, 2, 1, true).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[3, 2, 5, 4]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = topk(&x, 2, 1, false).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 0, 4]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn topk(
+    a: &Tensor<i128>,
+    k: usize,
+    axis: usize,
+    largest: bool,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = a.dims();
+    if axis >= dims.len() || k > dims[axis] {
+        return Err(TensorError::DimMismatch("topk".to_string()));
+    }
+
+    let mut output_dims = dims.to_vec();
+    output_dims[axis] = k;
+    let mut output = Tensor::new(None, &output_dims)?;
+    let coords = output_dims
+        .iter()
+        .enumerate()
+        .map(|(d, len)| if d == axis { 0..1 } else { 0..*len })
+        .multi_cartesian_product();
+    for mut coord in coords {
+        let mut row = (0..dims[axis])
+            .map(|j| {
+                coord[axis] = j;
+                a.get(&coord)
+            })
+            .collect_vec();
+        if largest {
+            row.sort_by(|x, y| y.cmp(x));
+        } else {
+            row.sort();
+        }
+        for (j, v) in row.iter().take(k).enumerate() {
+            coord[axis] = j;
+            let index = output.get_index(&coord);
+            output[index] = *v;
+        }
+    }
+    Ok(output)
+}
+
+/// The parameters of a [non_max_suppression].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NmsParams {
+    /// The maximum number of boxes selected per class.
+    pub max_boxes: usize,
+    /// The IoU above which a box is suppressed, in fixed point.
+    pub iou_threshold: i128,
+    /// The score a box has to exceed to be selected, in fixed point.
+    pub score_threshold: Option<i128>,
+    /// Whether boxes are `[x_center, y_center, width, height]`, rather than two corners.
+    pub center_point_box: bool,
+    /// Fixed point multiplier of the boxes and thresholds.
+    pub scale: usize,
+}
+
+/// Greedily selects the highest scoring boxes that don't overlap a better box by more than the
+/// IoU threshold, for every class, as in ONNX's `NonMaxSuppression` for a single batch.
+///
+/// The output has a static shape of `[num_classes * max_boxes, 3]` rows of `[batch, class,
+/// box]`, the selected boxes of a class in descending score order. Slots left empty are rows of
+/// -1. Ties in score are broken by box order. Areas and intersections are rescaled (with
+/// rounding) to the fixed point of the boxes, as they are in circuit.
+/// # Arguments
+///
+/// * `boxes` - Tensor of shape `[num_boxes, 4]`.
+/// * `scores` - Tensor of shape `[num_classes, num_boxes]`.
+/// * `params` - The [NmsParams].
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{non_max_suppression, NmsParams};
+///
+/// // two overlapping boxes (with an IoU of 0.6) and a separate one, at a multiplier of 4
+/// let boxes = Tensor::<i128>::new(
+///     Some(&[0, 0, 4, 4, 0, 1, 4, 5, 8, 8, 12, 12]),
+///     &[3, 4],
+/// ).unwrap();
+/// let scores = Tensor::<i128>::new(Some(&[3, 2, 1]), &[1, 3]).unwrap();
+/// let mut params = NmsParams {
+///     max_boxes: 3,
+///     iou_threshold: 2,
+///     score_threshold: None,
+///     center_point_box: false,
+///     scale: 4,
+/// };
+/// let result = non_max_suppression(&boxes, &scores, &params).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 0, 0, 0, 0, 2, -1, -1, -1]), &[3, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// params.score_threshold = Some(1);
+/// let result = non_max_suppression(&boxes, &scores, &params).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 0, 0, -1, -1, -1, -1, -1, -1]), &[3, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn non_max_suppression(
+    boxes: &Tensor<i128>,
+    scores: &Tensor<i128>,
+    params: &NmsParams,
+) -> Result<Tensor<i128>, TensorError> {
+    let (box_dims, score_dims) = (boxes.dims(), scores.dims());
+    if box_dims.len() != 2
+        || box_dims[1] != 4
+        || score_dims.len() != 2
+        || score_dims[1] != box_dims[0]
+    {
+        return Err(TensorError::DimMismatch("non max suppression".to_string()));
+    }
+    let (num_classes, num_boxes) = (score_dims[0], box_dims[0]);
+    let max_boxes = params.max_boxes.min(num_boxes);
+    let (m, t) = (params.scale as i128, params.iou_threshold);
+    let rescale = |x: i128| ((x as f32) / (params.scale as f32)).round() as i128;
+
+    // the lower and upper corners of every box
+    let corners = (0..num_boxes)
+        .map(|i| {
+            let [a, b, c, d] = [0, 1, 2, 3].map(|col| boxes.get(&[i, col]));
+            if params.center_point_box {
+                // doubled, so as to stay integers
+                [2 * a - c, 2 * b - d, 2 * a + c, 2 * b + d]
+            } else {
+                [a.min(c), b.min(d), a.max(c), b.max(d)]
+            }
+        })
+        .collect_vec();
+    let areas = corners
+        .iter()
+        .map(|[lo0, lo1, hi0, hi1]| rescale((hi0 - lo0) * (hi1 - lo1)))
+        .collect_vec();
+    // whether box i overlaps box j by more than the threshold, inter * (1 + t) > t * (a_i + a_j)
+    let overlaps = |i: usize, j: usize| {
+        let (p, q) = (corners[i], corners[j]);
+        let h = (p[2].min(q[2]) - p[0].max(q[0])).max(0);
+        let w = (p[3].min(q[3]) - p[1].max(q[1])).max(0);
+        let inter = rescale(h * w);
+        rescale(inter * (m + t) - t * (areas[i] + areas[j])) > 0
+    };
+
+    let mut output = vec![];
+    for c in 0..num_classes {
+        let s = (0..num_boxes).map(|i| scores.get(&[c, i])).collect_vec();
+        // whether box j comes before box i
+        let before = |i: usize, j: usize| 2 * (s[j] - s[i]) + (j < i) as i128 > 0;
+        let valid = (0..num_boxes)
+            .map(|i| params.score_threshold.map_or(true, |thr| s[i] - thr > 0))
+            .collect_vec();
+
+        // every pass settles at least the next box in score order
+        let mut keep = valid.clone();
+        for _ in 1..num_boxes {
+            keep = (0..num_boxes)
+                .map(|i| {
+                    valid[i] && !(0..num_boxes).any(|j| keep[j] && before(i, j) && overlaps(i, j))
+                })
+                .collect_vec();
+        }
+
+        let ranks = (0..num_boxes)
+            .map(|i| (0..num_boxes).filter(|j| keep[*j] && before(i, *j)).count())
+            .collect_vec();
+        for r in 0..max_boxes {
+            let row = match (0..num_boxes).find(|i| keep[*i] && ranks[*i] == r) {
+                Some(i) => [0, c as i128, i as i128],
+                None => [-1, -1, -1],
+            };
+            output.extend(row);
+        }
+    }
+    Tensor::new(Some(&output), &[num_classes * max_boxes, 3])
+}
+
+/// Cumulative sum of a tensor along an axis, as in ONNX's `CumSum`.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `axis` - The axis to sum along.
+/// * `exclusive` - Whether every element is left out of its own sum.
+/// * `reverse` - Whether to sum from the end of the axis.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::cumsum;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4, 5, 6]), &[2, 3]).unwrap();
+/// let result = cumsum(&x, 1, false, false).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 3, 6, 4, 9, 15]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = cumsum(&x, 1, true, false).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 1, 3, 0, 4, 9]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = cumsum(&x, 1, false, true).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[6, 5, 3, 15, 11, 6]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = cumsum(&x, 0, true, true).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4, 5, 6, 0, 0, 0]), &[2, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn cumsum(
+    a: &Tensor<i128>,
+    axis: usize,
+    exclusive: bool,
+    reverse: bool,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = a.dims();
+    if axis >= dims.len() {
+        return Err(TensorError::DimMismatch("cumsum".to_string()));
+    }
+
+    let mut output = Tensor::new(None, dims)?;
+    let coords = dims
+        .iter()
+        .enumerate()
+        .map(|(d, len)| if d == axis { 0..1 } else { 0..*len })
+        .multi_cartesian_product();
+    for mut coord in coords {
+        let positions = match reverse {
+            true => (0..dims[axis]).rev().collect_vec(),
+            false => (0..dims[axis]).collect_vec(),
+        };
+        let mut acc = 0;
+        for j in positions {
+            coord[axis] = j;
+            let v = a.get(&coord);
+            let index = output.get_index(&coord);
+            if exclusive {
+                output[index] = acc;
+                acc += v;
+            } else {
+                acc += v;
+                output[index] = acc;
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Clamps every element of a tensor to the (optional) bounds, as in ONNX's `Clip`.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `min` - The lower bound, if any.
+/// * `max` - The upper bound, if any.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::clip;
+///
+/// let x = Tensor::<i128>::new(Some(&[-3, 1, 4, 8]), &[2, 2]).unwrap();
+/// let result = clip(&x, Some(0), Some(6));
+/// let expected = Tensor::<i128>::new(Some(&[0, 1, 4, 6]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = clip(&x, None, Some(2));
+/// let expected = Tensor::<i128>::new(Some(&[-3, 1, 2, 2]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn clip(a: &Tensor<i128>, min: Option<i128>, max: Option<i128>) -> Tensor<i128> {
+    a.map(|a_i| {
+        let a_i = min.map_or(a_i, |min| a_i.max(min));
+        max.map_or(a_i, |max| a_i.min(max))
+    })
+}
+
+/// The comparison applied by a [compare].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Comparison {
+    /// `a > b`
+    Greater,
+    /// `a < b`
+    Less,
+    /// `a >= b`
+    GreaterOrEqual,
+    /// `a <= b`
+    LessOrEqual,
+    /// `a == b`
+    Equal,
+}
+
+/// Elementwise compares two tensors, as in ONNX's `Greater`, `Less`, `GreaterOrEqual`,
+/// `LessOrEqual` and `Equal`, with 1 for true and 0 for false.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `b` - Tensor of the same shape, or of a single element.
+/// * `comparison` - The [Comparison] to apply.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{compare, Comparison};
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let y = Tensor::<i128>::new(Some(&[3, 2, 1, 4]), &[2, 2]).unwrap();
+/// let result = compare(&x, &y, &Comparison::Greater).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 0, 1, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = compare(&x, &y, &Comparison::LessOrEqual).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 1, 0, 1]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let two = Tensor::<i128>::new(Some(&[2]), &[1]).unwrap();
+/// let result = compare(&x, &two, &Comparison::Equal).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 1, 0, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn compare(
+    a: &Tensor<i128>,
+    b: &Tensor<i128>,
+    comparison: &Comparison,
+) -> Result<Tensor<i128>, TensorError> {
+    let diff = (a.clone() - b.clone())?;
+    Ok(nonlinearities::compare_to_zero(&diff, comparison))
+}
+
+/// Elementwise maximum (or minimum) of tensors, as in ONNX's `Max` and `Min`, selecting every
+/// next tensor where it compares greater (or less) than the running extremum.
+/// # Arguments
+///
+/// * `t` - Tensors of the same shape, or of a single element.
+/// * `largest` - Whether to take the maximum, otherwise the minimum is taken.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::extremum;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 5, 3, -4]), &[2, 2]).unwrap();
+/// let y = Tensor::<i128>::new(Some(&[2]), &[1]).unwrap();
+/// let result = extremum(&[x.clone(), y.clone()], true).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 5, 3, 2]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = extremum(&[x, y], false).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 2, 2, -4]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn extremum(t: &[Tensor<i128>], largest: bool) -> Result<Tensor<i128>, TensorError> {
+    let comparison = match largest {
+        true => Comparison::Greater,
+        false => Comparison::Less,
+    };
+    let mut output = t[0].clone();
+    for e in t[1..].iter() {
+        let condition = compare(e, &output, &comparison)?;
+        output = select(&condition, e, &output)?;
+    }
+    Ok(output)
+}
+
+/// The boolean operation applied by a [logic].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Logic {
+    /// `a && b`
+    And,
+    /// `a || b`
+    Or,
+    /// `a ^ b`
+    Xor,
+    /// `!a`
+    Not,
+}
+
+/// Elementwise applies a boolean operation, as in ONNX's `And`, `Or`, `Xor` and `Not`. Any
+/// non-zero element is true, and the output is 1 for true and 0 for false.
+/// # Arguments
+///
+/// * `inputs` - One tensor for [Logic::Not], two tensors (of the same shape, or of a single
+///   element) otherwise.
+/// * `logic` - The [Logic] to apply.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{logic, Logic};
+///
+/// let x = Tensor::<i128>::new(Some(&[0, 0, 1, 1]), &[2, 2]).unwrap();
+/// let y = Tensor::<i128>::new(Some(&[0, 1, 0, 1]), &[2, 2]).unwrap();
+/// let result = logic(&[x.clone(), y.clone()], &Logic::And).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 0, 0, 1]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = logic(&[x.clone(), y.clone()], &Logic::Or).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 1, 1, 1]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = logic(&[x.clone(), y], &Logic::Xor).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 1, 1, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = logic(&[x], &Logic::Not).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 1, 0, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn logic(inputs: &[Tensor<i128>], logic: &Logic) -> Result<Tensor<i128>, TensorError> {
+    let num_inputs = match logic {
+        Logic::Not => 1,
+        _ => 2,
+    };
+    if inputs.len() != num_inputs {
+        return Err(TensorError::DimMismatch("logic".to_string()));
+    }
+
+    // the negations are 0 or 1 whatever the inputs
+    let negations = inputs.iter().map(nonlinearities::is_zero).collect_vec();
+    match logic {
+        Logic::Not => Ok(negations[0].clone()),
+        Logic::And => Ok(nonlinearities::is_zero(&add(&negations)?)),
+        Logic::Or => Ok(nonlinearities::is_zero(&mult(&negations)?)),
+        Logic::Xor => {
+            let diff = sub(&negations)?;
+            mult(&[diff.clone(), diff])
+        }
+    }
+}
+
+/// The bitwise operation applied by a [bitwise].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Bitwise {
+    /// `a & b`
+    And,
+    /// `a | b`
+    Or,
+    /// `a ^ b`
+    Xor,
+    /// `a << shift`, dropping the bits shifted past the width.
+    ShiftLeft(usize),
+    /// `a >> shift`
+    ShiftRight(usize),
+}
+
+/// Elementwise applies a bitwise operation to unsigned integers of `bits` bits, as in ONNX's
+/// `BitShift`, `BitwiseAnd`, `BitwiseOr` and `BitwiseXor`. The integers are in fixed point at
+/// `scale`, such that the `scale` lowest bits of the elements are fractional and are dropped.
+/// # Arguments
+///
+/// * `inputs` - One tensor for shifts, two tensors (of the same shape, or of a single element)
+///   otherwise.
+/// * `bitwise` - The [Bitwise] operation to apply.
+/// * `bits` - The width of the integers.
+/// * `scale` - The number of fractional bits of the elements.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{bitwise, Bitwise};
+///
+/// let x = Tensor::<i128>::new(Some(&[12, 10, 255, 0]), &[2, 2]).unwrap();
+/// let y = Tensor::<i128>::new(Some(&[10]), &[1]).unwrap();
+/// let result = bitwise(&[x.clone(), y.clone()], &Bitwise::And, 8, 0).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[8, 10, 10, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = bitwise(&[x.clone(), y.clone()], &Bitwise::Or, 8, 0).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[14, 10, 255, 10]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = bitwise(&[x.clone(), y], &Bitwise::Xor, 8, 0).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[6, 0, 245, 10]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = bitwise(&[x.clone()], &Bitwise::ShiftLeft(3), 8, 0).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[96, 80, 248, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// // at a scale of 1, the elements are twice the integers
+/// let result = bitwise(&[x], &Bitwise::ShiftRight(2), 8, 1).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[2, 2, 62, 0]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn bitwise(
+    inputs: &[Tensor<i128>],
+    bitwise: &Bitwise,
+    bits: usize,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let mask = (1i128 << bits) - 1;
+    let shift = |x: i128, f: &dyn Fn(i128) -> i128| (f(x >> scale) & mask) << scale;
+    match bitwise {
+        Bitwise::ShiftLeft(k) | Bitwise::ShiftRight(k) => {
+            if inputs.len() != 1 {
+                return Err(TensorError::DimMismatch("bitwise".to_string()));
+            }
+            let (k, left) = (*k, matches!(bitwise, Bitwise::ShiftLeft(_)));
+            Ok(inputs[0].map(|x| match (left, k < bits) {
+                (_, false) => 0,
+                (true, true) => shift(x, &|x| x << k),
+                (false, true) => shift(x, &|x| x >> k),
+            }))
+        }
+        _ => {
+            let (a, b) = match inputs {
+                [a, b] if a.len() == b.len() || a.len() == 1 || b.len() == 1 => (a, b),
+                _ => return Err(TensorError::DimMismatch("bitwise".to_string())),
+            };
+            let dims = match a.len() >= b.len() {
+                true => a.dims(),
+                false => b.dims(),
+            };
+            let mut output: Tensor<i128> = (0..a.len().max(b.len()))
+                .map(|i| {
+                    let y = b[i % b.len()] >> scale;
+                    match bitwise {
+                        Bitwise::And => shift(a[i % a.len()], &|x| x & y),
+                        Bitwise::Or => shift(a[i % a.len()], &|x| x | y),
+                        _ => shift(a[i % a.len()], &|x| x ^ y),
+                    }
+                })
+                .into();
+            output.reshape(dims);
+            Ok(output)
+        }
+    }
+}
+
+/// Elementwise selects from `x` where the condition is non-zero and from `y` elsewhere, as in
+/// ONNX's `Where`.
+/// # Arguments
+///
+/// * `condition` - Tensor of 0s and 1s.
+/// * `x` - Tensor of the same shape, or of a single element.
+/// * `y` - Tensor of the same shape, or of a single element.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::select;
+///
+/// let c = Tensor::<i128>::new(Some(&[1, 0, 0, 1]), &[2, 2]).unwrap();
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let y = Tensor::<i128>::new(Some(&[-1]), &[1]).unwrap();
+/// let result = select(&c, &x, &y).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, -1, -1, 4]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn select(
+    condition: &Tensor<i128>,
+    x: &Tensor<i128>,
+    y: &Tensor<i128>,
+) -> Result<Tensor<i128>, TensorError> {
+    // y + c * (x - y)
+    let diff = (x.clone() - y.clone())?;
+    let selected = mult(&[condition.clone(), diff])?;
+    selected + y.clone()
+}
+
+/// Elementwise sign of a tensor, as in ONNX's `Sign`: -1 for negative elements, 0 for zeros and 1
+/// for positive elements.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::sign;
+///
+/// let x = Tensor::<i128>::new(Some(&[-3, 0, 2, -1]), &[2, 2]).unwrap();
+/// let result = sign(&x);
+/// let expected = Tensor::<i128>::new(Some(&[-1, 0, 1, -1]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn sign(a: &Tensor<i128>) -> Tensor<i128> {
+    a.map(|x| x.signum())
+}
+
+/// Elementwise absolute value of a tensor, as in ONNX's `Abs`.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::abs;
+///
+/// let x = Tensor::<i128>::new(Some(&[-3, 0, 2, -1]), &[2, 2]).unwrap();
+/// let result = abs(&x);
+/// let expected = Tensor::<i128>::new(Some(&[3, 0, 2, 1]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn abs(a: &Tensor<i128>) -> Tensor<i128> {
+    a.map(|x| x.abs())
+}
+
+/// Returns the index of the first largest (or smallest) element of a tensor along an axis, as in
+/// ONNX's `ArgMax` and `ArgMin`.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `axis` - The axis to reduce.
+/// * `keep_dims` - Whether to keep the reduced axis, with a length of 1.
+/// * `largest` - Whether to find the largest element, otherwise the smallest one is found.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::argmax;
+///
+/// let x = Tensor::<i128>::new(Some(&[3, 1, 3, 0, 5, 4]), &[2, 3]).unwrap();
+/// let result = argmax(&x, 1, false, true).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[0, 1]), &[2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = argmax(&x, 0, true, false).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 0, 0]), &[1, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn argmax(
+    a: &Tensor<i128>,
+    axis: usize,
+    keep_dims: bool,
+    largest: bool,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = a.dims();
+    if axis >= dims.len() {
+        return Err(TensorError::DimMismatch("argmax".to_string()));
+    }
+
+    let mut output_dims = dims.to_vec();
+    output_dims[axis] = 1;
+    let mut output = Tensor::new(None, &output_dims)?;
+    let coords = output_dims.iter().map(|d| 0..*d).multi_cartesian_product();
+    for (i, mut coord) in coords.enumerate() {
+        let mut index = 0;
+        for j in 1..dims[axis] {
+            coord[axis] = j;
+            let candidate = a.get(&coord);
+            coord[axis] = index;
+            let current = a.get(&coord);
+            if (largest && candidate > current) || (!largest && candidate < current) {
+                index = j;
+            }
+        }
+        output[i] = index as i128;
+    }
+
+    if !keep_dims {
+        output_dims.remove(axis);
+        if output_dims.is_empty() {
+            output_dims.push(1);
+        }
+        output.reshape(&output_dims);
+    }
+    Ok(output)
+}
+
+/// Resizes a tensor with nearest neighbour interpolation, as in ONNX's `Resize` in `nearest`
+/// mode.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `indices` - For every axis, the input position that each output position is read from.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::resize_nearest;
+///
+/// let x = Tensor::<i128>::new(Some(&[1, 2, 3, 4]), &[2, 2]).unwrap();
+/// let result = resize_nearest(&x, &[vec![0, 1], vec![0, 0, 1, 1]]).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[1, 1, 2, 2, 3, 3, 4, 4]), &[2, 4]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn resize_nearest<T: TensorType>(
+    a: &Tensor<T>,
+    indices: &[Vec<usize>],
+) -> Result<Tensor<T>, TensorError> {
+    if indices.len() != a.dims().len()
+        || indices
+            .iter()
+            .zip(a.dims())
+            .any(|(idx, len)| idx.iter().any(|i| i >= len))
+    {
+        return Err(TensorError::DimMismatch("resize".to_string()));
+    }
+
+    let output_dims = indices.iter().map(|idx| idx.len()).collect_vec();
+    let values = output_dims
+        .iter()
+        .map(|d| 0..*d)
+        .multi_cartesian_product()
+        .map(|coord| {
+            let input_coord = coord
+                .iter()
+                .enumerate()
+                .map(|(d, c)| indices[d][*c])
+                .collect_vec();
+            a.get(&input_coord)
+        })
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// For every output element of a linear resize, the input elements it interpolates between and
+/// their fixed point weights. The weights are the products of the weights of every axis,
+/// requantized to a multiplier of `scale`, and zero weights are dropped.
+/// # Arguments
+///
+/// * `dims` - Dimensions of the input.
+/// * `taps` - For every axis and output position, the two input positions it interpolates
+///   between, and the weight of the first at a multiplier of `scale`.
+/// * `scale` - Fixed point multiplier of the weights.
+pub fn resize_linear_taps(
+    dims: &[usize],
+    taps: &[Vec<(usize, usize, usize)>],
+    scale: usize,
+) -> Vec<Vec<(usize, i128)>> {
+    let strides = (0..dims.len())
+        .map(|d| dims[d + 1..].iter().product::<usize>())
+        .collect_vec();
+    let denom = (scale as f64).powi(dims.len() as i32 - 1);
+
+    taps.iter()
+        .map(|t| 0..t.len())
+        .multi_cartesian_product()
+        .map(|coord| {
+            coord
+                .iter()
+                .enumerate()
+                .map(|(d, c)| {
+                    let (i0, i1, w0) = taps[d][*c];
+                    [(i0, w0), (i1, scale - w0)]
+                })
+                .multi_cartesian_product()
+                .filter_map(|corner| {
+                    let index = corner.iter().zip(&strides).map(|((i, _), s)| i * s).sum();
+                    let weight = corner.iter().map(|(_, w)| *w as f64).product::<f64>() / denom;
+                    let weight = weight.round() as i128;
+                    (weight != 0).then_some((index, weight))
+                })
+                .collect_vec()
+        })
+        .collect_vec()
+}
+
+/// Resizes a tensor with linear interpolation, as in ONNX's `Resize` in `linear` mode. The
+/// interpolation weights are fixed point values with a multiplier of `scale`, so the output is
+/// at the scale of the input plus that of the weights.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `taps` - For every axis and output position, the two input positions it interpolates
+///   between, and the weight of the first at a multiplier of `scale`.
+/// * `scale` - Fixed point multiplier of the weights.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::resize_linear;
+///
+/// let x = Tensor::<i128>::new(Some(&[2, 6]), &[1, 2]).unwrap();
+/// let taps = vec![vec![(0, 0, 4)], vec![(0, 0, 4), (0, 1, 2), (1, 1, 4)]];
+/// let result = resize_linear(&x, &taps, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[8, 16, 24]), &[1, 3]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn resize_linear(
+    a: &Tensor<i128>,
+    taps: &[Vec<(usize, usize, usize)>],
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    if taps.len() != a.dims().len()
+        || taps.iter().zip(a.dims()).any(|(t, len)| {
+            t.iter()
+                .any(|(i0, i1, w0)| i0 >= len || i1 >= len || *w0 > scale)
+        })
+    {
+        return Err(TensorError::DimMismatch("resize".to_string()));
+    }
+
+    let output_dims = taps.iter().map(|t| t.len()).collect_vec();
+    let values = resize_linear_taps(a.dims(), taps, scale)
+        .iter()
+        .map(|t| t.iter().map(|(i, w)| a[*i] * w).sum())
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// How a region of interest is sampled by [roi_align].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RoiTaps {
+    /// The batch the region is in.
+    pub batch: usize,
+    /// The taps of the sampled rows, `grid.0` per output row, as in [resize_linear].
+    pub rows: Vec<(usize, usize, usize)>,
+    /// The taps of the sampled columns, `grid.1` per output column.
+    pub cols: Vec<(usize, usize, usize)>,
+    /// The number of samples per output bin along the height and width.
+    pub grid: (usize, usize),
+}
+
+/// The [RoiTaps] of a region of interest `[x1, y1, x2, y2]`, already multiplied by the spatial
+/// scale, as sampled by ONNX's `RoiAlign`. Samples outside of the input are clamped to its edges
+/// rather than being zero.
+/// # Arguments
+///
+/// * `roi` - The corners of the region, in input coordinates.
+/// * `batch` - The batch the region is in.
+/// * `input_dims` - The height and width of the input.
+/// * `output_dims` - The height and width of the pooled output.
+/// * `sampling_ratio` - The number of samples per output bin along each axis, or 0 for as many as
+///   the bin is wide.
+/// * `half_pixel` - Whether the region is shifted by half a pixel, as in the `half_pixel`
+///   coordinate transformation mode.
+/// * `scale` - Fixed point multiplier of the interpolation weights.
+pub fn roi_align_taps(
+    roi: [f32; 4],
+    batch: usize,
+    input_dims: (usize, usize),
+    output_dims: (usize, usize),
+    sampling_ratio: usize,
+    half_pixel: bool,
+    scale: usize,
+) -> RoiTaps {
+    let shift = if half_pixel { 0.5 } else { 0.0 };
+    let axis_taps = |start: f32, end: f32, len_in: usize, len_out: usize| {
+        let mut size = end - start;
+        if !half_pixel {
+            size = size.max(1.0);
+        }
+        let bin = size / len_out as f32;
+        let grid = match sampling_ratio {
+            0 => (bin.ceil() as usize).max(1),
+            r => r,
+        };
+        let taps = (0..len_out)
+            .flat_map(|o| {
+                (0..grid).map(move |g| {
+                    let x = start - shift + o as f32 * bin + (g as f32 + 0.5) * bin / grid as f32;
+                    let x = x.clamp(0.0, (len_in - 1) as f32);
+                    let i0 = x.floor() as usize;
+                    let i1 = (i0 + 1).min(len_in - 1);
+                    let w0 = ((1.0 - x.fract()) * scale as f32).round();
+                    (i0, i1, w0 as usize)
+                })
+            })
+            .collect_vec();
+        (taps, grid)
+    };
+    let (rows, grid_h) = axis_taps(roi[1], roi[3], input_dims.0, output_dims.0);
+    let (cols, grid_w) = axis_taps(roi[0], roi[2], input_dims.1, output_dims.1);
+    RoiTaps {
+        batch,
+        rows,
+        cols,
+        grid: (grid_h, grid_w),
+    }
+}
+
+/// Pools regions of interest of a `[N, C, H, W]` (or `[C, H, W]`) tensor into a fixed size, as in
+/// ONNX's `RoiAlign`. Every region is sampled with a [resize_linear], and every output bin is the
+/// average (with rounding) or the maximum of its samples. The output is `[num_rois, C, H', W']`,
+/// at the scale of the input plus that of the weights.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `rois` - The [RoiTaps] of every region.
+/// * `max` - Whether bins are the maximum rather than the average of their samples.
+/// * `scale` - Fixed point multiplier of the interpolation weights.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{roi_align, RoiTaps};
+///
+/// let x = Tensor::<i128>::new(Some(&[0, 4, 8, 12]), &[1, 1, 2, 2]).unwrap();
+/// // two samples, halfway down the first and last columns
+/// let roi = RoiTaps {
+///     batch: 0,
+///     rows: vec![(0, 1, 2)],
+///     cols: vec![(0, 0, 4), (1, 1, 4)],
+///     grid: (1, 2),
+/// };
+/// let result = roi_align(&x, &[roi.clone()], false, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[24]), &[1, 1, 1, 1]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = roi_align(&x, &[roi], true, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[32]), &[1, 1, 1, 1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn roi_align(
+    a: &Tensor<i128>,
+    rois: &[RoiTaps],
+    max: bool,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = a.dims();
+    let rank = dims.len();
+    if !(rank == 3 || rank == 4) || rois.is_empty() {
+        return Err(TensorError::DimMismatch("roi align".to_string()));
+    }
+    let (c, h, w) = (dims[rank - 3], dims[rank - 2], dims[rank - 1]);
+    let num_batches = a.len() / (c * h * w);
+    let (grid_h, grid_w) = rois[0].grid;
+    let output_dims = (rois[0].rows.len() / grid_h, rois[0].cols.len() / grid_w);
+    if rois.iter().any(|roi| {
+        roi.batch >= num_batches
+            || roi.rows.len() != output_dims.0 * roi.grid.0
+            || roi.cols.len() != output_dims.1 * roi.grid.1
+    }) {
+        return Err(TensorError::DimMismatch("roi align".to_string()));
+    }
+
+    let mut image = a.clone();
+    image.reshape(&[num_batches, c, h, w]);
+    let mut outputs = vec![];
+    for roi in rois {
+        let mut plane = image.get_slice(&[roi.batch..roi.batch + 1])?;
+        plane.reshape(&[c, h, w]);
+        // the channels are passed through with their full weight
+        let taps = vec![
+            (0..c).map(|i| (i, i, scale)).collect_vec(),
+            roi.rows.clone(),
+            roi.cols.clone(),
+        ];
+        let samples = resize_linear(&plane, &taps, scale)?;
+        let pooled = if max {
+            max_pool2d(&samples, (0, 0), roi.grid, roi.grid)?
+        } else {
+            nonlinearities::const_div(
+                &sumpool(&samples, (0, 0), roi.grid, roi.grid)?,
+                (roi.grid.0 * roi.grid.1) as f32,
+            )
+        };
+        outputs.push(pooled);
+    }
+    let mut output = Tensor::new(Some(&outputs), &[rois.len()])?.combine()?;
+    output.reshape(&[rois.len(), c, output_dims.0, output_dims.1]);
+    Ok(output)
+}
+
+/// Samples a `[N, C, H, W]` (or `[C, H, W]`) tensor at the points of a `[N, H', W', 2]` (or
+/// `[H', W', 2]`) grid of normalized `(x, y)` coordinates with bilinear interpolation, as in
+/// ONNX's `GridSample` in `bilinear` mode. The coordinates and weights are fixed point values with
+/// a multiplier of `scale`, and the output is at the scale of the input plus that of the grid.
+/// Out of bounds samples are zero padded, or clamped to the edges for `border` padding.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `grid` - Tensor of sampling points.
+/// * `align_corners` - Whether -1 and 1 are the centers of the corner pixels, rather than their
+///   outer edges.
+/// * `border` - Whether out of bounds samples take the value of the nearest edge.
+/// * `scale` - Fixed point multiplier of the grid.
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::grid_sample;
+///
+/// let x = Tensor::<i128>::new(Some(&[4, 8, 12, 16]), &[1, 1, 2, 2]).unwrap();
+/// // the center and the top left corner, at a multiplier of 4
+/// let grid = Tensor::<i128>::new(Some(&[0, 0, -4, -4]), &[1, 1, 2, 2]).unwrap();
+/// let result = grid_sample(&x, &grid, false, false, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[40, 4]), &[1, 1, 1, 2]).unwrap();
+/// assert_eq!(result, expected);
+///
+/// let result = grid_sample(&x, &grid, false, true, 4).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[40, 16]), &[1, 1, 1, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn grid_sample(
+    a: &Tensor<i128>,
+    grid: &Tensor<i128>,
+    align_corners: bool,
+    border: bool,
+    scale: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    let (dims, grid_dims) = (a.dims(), grid.dims());
+    let rank = dims.len();
+    if !(rank == 3 || rank == 4) || grid_dims.len() != rank || grid_dims[rank - 1] != 2 {
+        return Err(TensorError::DimMismatch("grid sample".to_string()));
+    }
+    let (c, h, w) = (dims[rank - 3], dims[rank - 2], dims[rank - 1]);
+    let (out_h, out_w) = (grid_dims[rank - 3], grid_dims[rank - 2]);
+    let num_batches = a.len() / (c * h * w);
+    let num_points = out_h * out_w;
+    if grid.len() != num_batches * num_points * 2 {
+        return Err(TensorError::DimMismatch("grid sample".to_string()));
+    }
+
+    let m = scale as i128;
+    let div = |x: i128, denom: f32| ((x as f32) / denom).round() as i128;
+    // the fixed point position of a normalized coordinate, along an axis of length len
+    let unnormalize = |g: i128, len: usize| {
+        let len = len as i128;
+        let x = match align_corners {
+            true => div((g + m) * (len - 1), 2.0),
+            false => div((g + m) * len - m, 2.0),
+        };
+        match border {
+            true => x.clamp(0, (len - 1) * m),
+            false => x,
+        }
+    };
+    // the interpolation weight of every position along an axis of length len
+    let weights = |x: i128, len: usize| {
+        (0..len)
+            .map(|k| (m - (x - k as i128 * m).abs()).max(0))
+            .collect_vec()
+    };
+
+    let mut output = vec![];
+    for n in 0..num_batches {
+        let points = (0..num_points)
+            .map(|p| {
+                let (gx, gy) = (
+                    grid[(n * num_points + p) * 2],
+                    grid[(n * num_points + p) * 2 + 1],
+                );
+                (
+                    weights(unnormalize(gy, h), h),
+                    weights(unnormalize(gx, w), w),
+                )
+            })
+            .collect_vec();
+        for ch in 0..c {
+            let plane = (n * c + ch) * h * w;
+            for (wy, wx) in points.iter() {
+                let sum: i128 = (0..h)
+                    .cartesian_product(0..w)
+                    .map(|(i, j)| wy[i] * a[plane + i * w + j] * wx[j])
+                    .sum();
+                output.push(div(sum, scale as f32));
+            }
+        }
+    }
+    let output_dims = match rank {
+        4 => vec![num_batches, c, out_h, out_w],
+        _ => vec![c, out_h, out_w],
+    };
+    Tensor::new(Some(&output), &output_dims)
+}
+
+/// A node of a decision tree in a [TreeEnsemble].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TreeNode {
+    /// Branches to `true_node` where the [Comparison] of the feature to the threshold holds, and
+    /// to `false_node` elsewhere. Both come after the branch in the tree.
+    Branch {
+        /// The compared feature.
+        feature: usize,
+        /// The threshold, at the scale of the features.
+        threshold: i128,
+        /// How the feature is compared to the threshold.
+        comparison: Comparison,
+        /// The node of the rows for which the comparison holds.
+        true_node: usize,
+        /// The node of the other rows.
+        false_node: usize,
+    },
+    /// Adds its weights to the targets of the rows which reach it.
+    Leaf {
+        /// Pairs of a target and the weight added to it.
+        weights: Vec<(usize, i128)>,
+    },
+}
+
+/// The decision trees of a [tree_ensemble], as in ONNX-ML's `TreeEnsembleRegressor` (and the
+/// scores of `TreeEnsembleClassifier`).
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TreeEnsemble {
+    /// The nodes of every tree, the first of which is the root.
+    pub trees: Vec<Vec<TreeNode>>,
+    /// The initial value of every target, at the scale of the leaf weights.
+    pub base_values: Vec<i128>,
+}
+
+impl TreeEnsemble {
+    /// Whether the trees only branch forward to their own nodes, compare existing features, and
+    /// add to existing targets.
+    pub fn is_valid(&self, num_features: usize) -> bool {
+        self.trees.iter().all(|tree| {
+            tree.iter().enumerate().all(|(n, node)| match node {
+                TreeNode::Branch {
+                    feature,
+                    true_node,
+                    false_node,
+                    ..
+                } => {
+                    *feature < num_features
+                        && [true_node, false_node]
+                            .iter()
+                            .all(|c| **c > n && **c < tree.len())
+                }
+                TreeNode::Leaf { weights } => weights
+                    .iter()
+                    .all(|(target, _)| *target < self.base_values.len()),
+            })
+        })
+    }
+}
+
+/// Evaluates an ensemble of decision trees on every row of features (the last axis) of a tensor.
+/// Every row goes down every tree, and the weights of the leaves it reaches are added to the base
+/// values of the targets, which replace the features in the output.
+/// # Arguments
+///
+/// * `a` - Tensor
+/// * `ensemble` - The [TreeEnsemble].
+/// # Examples
+/// ```
+/// use ezkl_lib::tensor::Tensor;
+/// use ezkl_lib::tensor::ops::{tree_ensemble, Comparison, TreeEnsemble, TreeNode};
+///
+/// // a stump on the second feature, x_1 <= 2
+/// let stump = vec![
+///     TreeNode::Branch {
+///         feature: 1,
+///         threshold: 2,
+///         comparison: Comparison::LessOrEqual,
+///         true_node: 1,
+///         false_node: 2,
+///     },
+///     TreeNode::Leaf { weights: vec![(0, 10)] },
+///     TreeNode::Leaf { weights: vec![(0, -10), (1, 5)] },
+/// ];
+/// let ensemble = TreeEnsemble {
+///     trees: vec![stump],
+///     base_values: vec![1, 0],
+/// };
+/// let x = Tensor::<i128>::new(Some(&[7, 2, 7, 3]), &[2, 2]).unwrap();
+/// let result = tree_ensemble(&x, &ensemble).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[11, 0, -9, 5]), &[2, 2]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn tree_ensemble(
+    a: &Tensor<i128>,
+    ensemble: &TreeEnsemble,
+) -> Result<Tensor<i128>, TensorError> {
+    let dims = a.dims();
+    let num_features = dims[dims.len() - 1];
+    if !ensemble.is_valid(num_features) || ensemble.base_values.is_empty() {
+        return Err(TensorError::DimMismatch("tree ensemble".to_string()));
+    }
+    let num_rows = a.len() / num_features;
+    let num_targets = ensemble.base_values.len();
+
+    let mut outputs = ensemble
+        .base_values
+        .iter()
+        .map(|b| vec![*b; num_rows])
+        .collect_vec();
+    for tree in ensemble.trees.iter() {
+        // whether every row reaches every node
+        let mut reach = vec![vec![0; num_rows]; tree.len()];
+        reach[0] = vec![1; num_rows];
+        for (n, node) in tree.iter().enumerate() {
+            match node {
+                TreeNode::Branch {
+                    feature,
+                    threshold,
+                    comparison,
+                    true_node,
+                    false_node,
+                } => {
+                    let diff: Tensor<i128> = (0..num_rows)
+                        .map(|r| a[r * num_features + feature] - threshold)
+                        .into();
+                    let holds = nonlinearities::compare_to_zero(&diff, comparison);
+                    for r in 0..num_rows {
+                        reach[*true_node][r] = reach[n][r] * holds[r];
+                        reach[*false_node][r] = reach[n][r] * (1 - holds[r]);
+                    }
+                }
+                TreeNode::Leaf { weights } => {
+                    for (target, weight) in weights {
+                        for r in 0..num_rows {
+                            outputs[*target][r] += reach[n][r] * weight;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut output_dims = dims.to_vec();
+    output_dims[dims.len() - 1] = num_targets;
+    let values = (0..num_rows)
+        .flat_map(|r| outputs.iter().map(move |o| o[r]))
+        .collect_vec();
+    Tensor::new(Some(&values), &output_dims)
+}
+
+/// Activation functions
+pub mod nonlinearities {
+    use super::*;
+    /// Elementwise applies sigmoid to a tensor of integers.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::sigmoid;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[2, 15, 2, 1, 1, 0]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let result = sigmoid(&x, 1, 1);
+    /// let expected = Tensor::<i128>::new(Some(&[1, 1, 1, 1, 1, 1]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn sigmoid(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
+        // calculate value of output
+        let mut output: Tensor<i128> = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            let kix = (*a_i as f32) / (scale_input as f32);
+            let fout = (scale_output as f32) / (1.0 + (-kix).exp());
+            let rounded = fout.round();
+            output[i] = rounded as i128;
+        }
+        output
+    }
+
+    /// Elementwise applies square root to a tensor of integers.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::sqrt;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[4, 25, 8, 1, 1, 0]),
+    ///     &[2, 3],
+    /// ).unwrap();
     /// let result = sqrt(&x, 1, 1);
     /// let expected = Tensor::<i128>::new(Some(&[2, 5, 3, 1, 1, 0]), &[2, 3]).unwrap();
     /// assert_eq!(result, expected);
@@ -771,6 +4124,416 @@ pub mod nonlinearities {
         output
     }
 
+    /// Elementwise maps zeros to 1 and any other value to 0.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::is_zero;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[0, 3, 0, -2]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = is_zero(&x);
+    /// let expected = Tensor::<i128>::new(Some(&[1, 0, 1, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn is_zero(a: &Tensor<i128>) -> Tensor<i128> {
+        a.map(|a_i| (a_i == 0) as i128)
+    }
+
+    /// Elementwise rounds down to a multiple of the fixed point multiplier, as in ONNX's `Floor`.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale` - Fixed point multiplier of the elements.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::floor;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[2, 3, 4, -3]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = floor(&x, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[0, 0, 4, -4]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn floor(a: &Tensor<i128>, scale: usize) -> Tensor<i128> {
+        let scale = scale as i128;
+        a.map(|a_i| a_i.div_euclid(scale) * scale)
+    }
+
+    /// Elementwise rounds up to a multiple of the fixed point multiplier, as in ONNX's `Ceil`.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale` - Fixed point multiplier of the elements.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::ceil;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[2, 3, 4, -3]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = ceil(&x, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[4, 4, 4, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn ceil(a: &Tensor<i128>, scale: usize) -> Tensor<i128> {
+        let scale = scale as i128;
+        a.map(|a_i| {
+            let q = a_i.div_euclid(scale) + (a_i.rem_euclid(scale) > 0) as i128;
+            q * scale
+        })
+    }
+
+    /// Elementwise rounds to the nearest multiple of the fixed point multiplier, with ties to
+    /// the even multiple, as in ONNX's `Round`.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale` - Fixed point multiplier of the elements.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::round;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[2, 3, 6, -3]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = round(&x, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[0, 4, 8, -4]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn round(a: &Tensor<i128>, scale: usize) -> Tensor<i128> {
+        let scale = scale as i128;
+        a.map(|a_i| {
+            let (q, r) = (a_i.div_euclid(scale), a_i.rem_euclid(scale));
+            let q = match (2 * r).cmp(&scale) {
+                std::cmp::Ordering::Less => q,
+                std::cmp::Ordering::Greater => q + 1,
+                std::cmp::Ordering::Equal => q + q.rem_euclid(2),
+            };
+            q * scale
+        })
+    }
+
+    /// Elementwise applies the tent function `max(0, 1 - |x|)`, which is the weight of a grid
+    /// point at a distance `x` in a linear interpolation.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale` - Fixed point multiplier of the elements.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::tent;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[-5, -1, 0, 3]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = tent(&x, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[0, 3, 4, 1]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn tent(a: &Tensor<i128>, scale: usize) -> Tensor<i128> {
+        let scale = scale as i128;
+        a.map(|a_i| (scale - a_i.abs()).max(0))
+    }
+
+    /// Elementwise compares to zero, with 1 for true and 0 for false.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `comparison` - The [super::Comparison] of each element to zero.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::Comparison;
+    /// use ezkl_lib::tensor::ops::nonlinearities::compare_to_zero;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[0, 3, 0, -2]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = compare_to_zero(&x, &Comparison::GreaterOrEqual);
+    /// let expected = Tensor::<i128>::new(Some(&[1, 1, 1, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn compare_to_zero(a: &Tensor<i128>, comparison: &super::Comparison) -> Tensor<i128> {
+        a.map(|a_i| {
+            (match comparison {
+                super::Comparison::Greater => a_i > 0,
+                super::Comparison::Less => a_i < 0,
+                super::Comparison::GreaterOrEqual => a_i >= 0,
+                super::Comparison::LessOrEqual => a_i <= 0,
+                super::Comparison::Equal => a_i == 0,
+            }) as i128
+        })
+    }
+
+    /// Elementwise divides integer values by a constant, truncating towards zero as in ONNX's
+    /// integer `Div`, and returns the quotients at the fixed point scale of the inputs.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor of integer values in fixed point.
+    /// * `denom` - The divisor in fixed point.
+    /// * `scale` - Fixed point multiplier of the elements.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::int_div;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[14, 6, -14, 4]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// // 7 / 3, 3 / 3, -7 / 3 and 2 / 3 at a fixed point multiplier of 2
+    /// let result = int_div(&x, 6, 2);
+    /// let expected = Tensor::<i128>::new(Some(&[4, 2, -4, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn int_div(a: &Tensor<i128>, denom: i128, scale: usize) -> Tensor<i128> {
+        a.map(|a_i| (a_i / denom) * scale as i128)
+    }
+
+    /// Elementwise takes the remainder of the division by a constant, as in ONNX's `Mod`. With
+    /// `fmod` the remainder has the sign of the dividend, otherwise that of the divisor. As the
+    /// divisor is in the same fixed point as the elements, the remainder is exact.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `denom` - The divisor in fixed point.
+    /// * `fmod` - Whether the remainder has the sign of the dividend.
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::modulo;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[7, -7, 7, -7]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = modulo(&x, 3, false);
+    /// let expected = Tensor::<i128>::new(Some(&[1, 2, 1, 2]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    ///
+    /// let result = modulo(&x, 3, true);
+    /// let expected = Tensor::<i128>::new(Some(&[1, -1, 1, -1]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    ///
+    /// let result = modulo(&x, -3, false);
+    /// let expected = Tensor::<i128>::new(Some(&[-2, -1, -2, -1]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn modulo(a: &Tensor<i128>, denom: i128, fmod: bool) -> Tensor<i128> {
+        a.map(|a_i| match fmod {
+            true => a_i % denom,
+            false => ((a_i % denom) + denom) % denom,
+        })
+    }
+
+    /// Elementwise applies exponential to a tensor of integers.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::exp;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[0, 2, 4, -2]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = exp(&x, 2, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[4, 11, 30, 1]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn exp(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
+        let mut output = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            let kix = (*a_i as f32) / (scale_input as f32);
+            let fout = (scale_output as f32) * kix.exp();
+            output[i] = fout.round() as i128;
+        }
+        output
+    }
+
+    /// Elementwise applies the natural logarithm to a tensor of integers, non-positive values are
+    /// clamped to the smallest positive fixed point value.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::ln;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[0, 2, 4, 8]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = ln(&x, 2, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[-3, 0, 3, 6]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn ln(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
+        let mut output = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            let kix = (*a_i).max(1) as f32 / (scale_input as f32);
+            let fout = (scale_output as f32) * kix.ln();
+            output[i] = fout.round() as i128;
+        }
+        output
+    }
+
+    /// Elementwise raises a tensor of integers to a (possibly fractional) power, non-finite
+    /// results (e.g. of negative values to fractional powers) are mapped to zero.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// * `exponent` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::pow;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[0, 2, 8, -2]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = pow(&x, 2, 4, 0.5);
+    /// let expected = Tensor::<i128>::new(Some(&[0, 4, 8, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn pow(
+        a: &Tensor<i128>,
+        scale_input: usize,
+        scale_output: usize,
+        exponent: f32,
+    ) -> Tensor<i128> {
+        let mut output = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            let kix = (*a_i as f32) / (scale_input as f32);
+            let fout = (scale_output as f32) * kix.powf(exponent);
+            output[i] = if fout.is_finite() {
+                fout.round() as i128
+            } else {
+                0
+            };
+        }
+        output
+    }
+
+    /// Elementwise applies the reciprocal to a tensor of integers, zero is mapped to zero.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::recip;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[1, 2, 4, 0]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = recip(&x, 2, 4);
+    /// let expected = Tensor::<i128>::new(Some(&[8, 4, 2, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn recip(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
+        let mut output = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            if *a_i == 0 {
+                output[i] = 0;
+                continue;
+            }
+            let fout = (scale_input * scale_output) as f32 / (*a_i as f32);
+            output[i] = fout.round() as i128;
+        }
+        output
+    }
+
+    /// Elementwise applies the reciprocal of the square root to a tensor of integers, mapping
+    /// nonpositive values to 0.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::rsqrt;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[4, 16, 1, 0]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = rsqrt(&x, 4, 8);
+    /// let expected = Tensor::<i128>::new(Some(&[8, 4, 16, 0]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn rsqrt(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
+        let mut output = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            if *a_i <= 0 {
+                output[i] = 0;
+                continue;
+            }
+            let kix = (*a_i as f32) / (scale_input as f32);
+            let fout = (scale_output as f32) / kix.sqrt();
+            output[i] = fout.round() as i128;
+        }
+        output
+    }
+
+    /// Elementwise applies the Gauss error function to a tensor of integers, as in ONNX's `Erf`.
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `scale_input` - Single value
+    /// * `scale_output` - Single value
+    /// # Examples
+    /// ```
+    /// use ezkl_lib::tensor::Tensor;
+    /// use ezkl_lib::tensor::ops::nonlinearities::erf;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[0, 4, -4, 16]),
+    ///     &[2, 2],
+    /// ).unwrap();
+    /// let result = erf(&x, 4, 100);
+    /// let expected = Tensor::<i128>::new(Some(&[0, 84, -84, 100]), &[2, 2]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn erf(a: &Tensor<i128>, scale_input: usize, scale_output: usize) -> Tensor<i128> {
+        let mut output = a.clone();
+
+        for (i, a_i) in a.iter().enumerate() {
+            let z = (*a_i as f64) / (scale_input as f64);
+            // Abramowitz and Stegun 7.1.26, which is within 1.5e-7 of erf
+            let t = 1.0 / (1.0 + 0.3275911 * z.abs());
+            let poly = t
+                * (0.254829592
+                    + t * (-0.284496736
+                        + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+            let erfz = (1.0 - poly * (-z * z).exp()).copysign(z);
+            output[i] = ((scale_output as f64) * erfz).round() as i128;
+        }
+        output
+    }
+
     /// Elementwise applies tanh activation to a tensor of integers.
     /// # Arguments
     ///
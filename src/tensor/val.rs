@@ -11,6 +11,9 @@ pub enum ValType<F: FieldExt + TensorType> {
     AssignedValue(Value<Assigned<F>>),
     /// previously assigned value
     PrevAssigned(AssignedCell<F, F>),
+    /// a constant of the circuit, which is bound to a fixed cell when assigned such that the
+    /// prover can't substitute it
+    Constant(F),
 }
 
 impl<F: FieldExt + TensorType> From<ValType<F>> for i32 {
@@ -46,6 +49,7 @@ impl<F: FieldExt + TensorType> From<ValType<F>> for i32 {
                 });
                 output
             }
+            ValType::Constant(v) => felt_to_i32(v),
         }
     }
 }
@@ -160,6 +164,9 @@ impl<F: FieldExt + TensorType> ValTensor<F> {
                     ValType::PrevAssigned(v) => v.value_field().map(|f| {
                         integer_evals.push(crate::fieldutils::felt_to_i128(f.evaluate()));
                     }),
+                    ValType::Constant(v) => Value::known(v).map(|f| {
+                        integer_evals.push(crate::fieldutils::felt_to_i128(f));
+                    }),
                 });
             }
             _ => return Err(Box::new(TensorError::WrongMethod)),
@@ -196,6 +203,18 @@ impl<F: FieldExt + TensorType> ValTensor<F> {
         Ok(())
     }
 
+    /// Calls `permute` on the inner tensor.
+    pub fn permute(&mut self, order: &[usize]) -> Result<(), TensorError> {
+        match self {
+            ValTensor::Value { inner: v, dims: d } => {
+                *v = v.permute(order)?;
+                *d = v.dims().to_vec();
+            }
+            ValTensor::Instance { .. } => return Err(TensorError::WrongMethod),
+        }
+        Ok(())
+    }
+
     /// Fetches the inner tensor as a [Tensor<Value<F>>]
     pub fn get_inner(&self) -> Result<Tensor<Value<F>>, TensorError> {
         warn!("using 'get_inner' in constraints can create soundness issues.");
@@ -204,11 +223,20 @@ impl<F: FieldExt + TensorType> ValTensor<F> {
                 ValType::Value(v) => v,
                 ValType::AssignedValue(v) => v.evaluate(),
                 ValType::PrevAssigned(v) => v.value_field().evaluate(),
+                ValType::Constant(v) => Value::known(v),
             }),
             ValTensor::Instance { .. } => return Err(TensorError::WrongMethod),
         })
     }
 
+    /// Fetches the inner tensor as a [Tensor<ValType<F>>], preserving any previously assigned cells.
+    pub fn get_inner_tensor(&self) -> Result<Tensor<ValType<F>>, TensorError> {
+        Ok(match self {
+            ValTensor::Value { inner: v, .. } => v.clone(),
+            ValTensor::Instance { .. } => return Err(TensorError::WrongMethod),
+        })
+    }
+
     /// Sets the [ValTensor]'s shape.
     pub fn reshape(&mut self, new_dims: &[usize]) -> Result<(), Box<dyn Error>> {
         match self {
@@ -1,4 +1,5 @@
 use crate::circuit::CheckMode;
+use std::collections::BTreeMap;
 
 use super::*;
 /// A wrapper around Halo2's `Column<Fixed>` or `Column<Advice>`.
@@ -49,13 +50,24 @@ impl VarTensor {
         logrows: usize,
         capacity: usize,
         equality: bool,
+    ) -> Self {
+        Self::new_advice_with_cols(cs, logrows, capacity, 0, equality)
+    }
+
+    /// Create a new VarTensor::Advice laid out over at least `num_cols` columns, such that a
+    /// layout chosen for a smaller logrows can be pinned. More columns are allocated if
+    /// `capacity` doesn't fit in `num_cols` columns.
+    pub fn new_advice_with_cols<F: FieldExt>(
+        cs: &mut ConstraintSystem<F>,
+        logrows: usize,
+        capacity: usize,
+        num_cols: usize,
+        equality: bool,
     ) -> Self {
         let base = 2u32;
         let max_rows = base.pow(logrows as u32) as usize - cs.blinding_factors() - 1;
 
-        let mut modulo = (capacity / max_rows) + 1;
-        // we add a buffer for duplicated rows (we get at most 1 duplicated row per column)
-        modulo = ((capacity + modulo)/ max_rows) + 1;
+        let modulo = Self::cols_for(max_rows, capacity).max(num_cols);
         let mut advices = vec![];
         for _ in 0..modulo {
             let col = cs.advice_column();
@@ -87,9 +99,7 @@ impl VarTensor {
     ) -> Self {
         let base = 2u32;
         let max_rows = base.pow(logrows as u32) as usize - cs.blinding_factors() - 1;
-        let mut modulo = (capacity / max_rows) + 1;
-         // we add a buffer for duplicated rows (we get at most 1 duplicated row per column)
-        modulo = ((capacity + modulo)/ max_rows) + 1;
+        let modulo = Self::cols_for(max_rows, capacity);
 
         let mut fixed = vec![];
         for _ in 0..modulo {
@@ -107,6 +117,13 @@ impl VarTensor {
         }
     }
 
+    /// The number of columns of `col_size` rows needed to hold `capacity` cells.
+    pub fn cols_for(col_size: usize, capacity: usize) -> usize {
+        let modulo = (capacity / col_size) + 1;
+        // we add a buffer for duplicated rows (we get at most 1 duplicated row per column)
+        ((capacity + modulo) / col_size) + 1
+    }
+
     /// Gets the dims of the object the VarTensor represents
     pub fn num_cols(&self) -> usize {
         match self {
@@ -210,36 +227,65 @@ impl VarTensor {
                 }
                 _ => Err(halo2_proofs::plonk::Error::Synthesis),
             },
-            ValTensor::Value { inner: v, .. } => v.enum_map(|coord, k| {
-                let (x, y) = self.cartesian_coord(offset + coord);
-                
-                match k {
-                    ValType::Value(v) => match &self {
-                        VarTensor::Fixed { inner: fixed, .. } => {
-                            region.assign_fixed(|| "k", fixed[x], y, || v)
-                        }
-                        VarTensor::Advice { inner: advices, .. } => {
-                            region.assign_advice(|| "k", advices[x], y, || v)
-                        }
-                        _ => unimplemented!(),
-                    },
-                    ValType::PrevAssigned(v) => match &self {
-                        VarTensor::Advice { inner: advices, .. } => {
-                            v.copy_advice(|| "k", region, advices[x], y)
-                        }
-                        _ => Err(halo2_proofs::plonk::Error::Synthesis),
-                    },
-                    ValType::AssignedValue(v) => match &self {
-                        VarTensor::Fixed { inner: fixed, .. } => region
-                            .assign_fixed(|| "k", fixed[x], y, || v)
-                            .map(|a| a.evaluate()),
-                        VarTensor::Advice { inner: advices, .. } => region
-                            .assign_advice(|| "k", advices[x], y, || v)
-                            .map(|a| a.evaluate()),
-                        _ => unimplemented!(),
-                    },
+            ValTensor::Value { inner: v, .. } => {
+                let mut constants = BTreeMap::new();
+                v.enum_map(|coord, k| {
+                    self.assign_value(region, offset + coord, k, &mut constants)
+                })
+            }
+        }
+    }
+
+    /// Assigns a single value to the cell at `linear_coord`. [ValType::Constant]s are copied from
+    /// the constants column, such that the prover can't substitute them, and repeated constants
+    /// (as found in `constants`) are copied from their first cell to save rows of that column.
+    fn assign_value<F: FieldExt + TensorType>(
+        &self,
+        region: &mut Region<'_, F>,
+        linear_coord: usize,
+        k: ValType<F>,
+        constants: &mut BTreeMap<F, AssignedCell<F, F>>,
+    ) -> Result<AssignedCell<F, F>, halo2_proofs::plonk::Error> {
+        let (x, y) = self.cartesian_coord(linear_coord);
+        match k {
+            ValType::Value(v) => match &self {
+                VarTensor::Fixed { inner: fixed, .. } => {
+                    region.assign_fixed(|| "k", fixed[x], y, || v)
+                }
+                VarTensor::Advice { inner: advices, .. } => {
+                    region.assign_advice(|| "k", advices[x], y, || v)
+                }
+                _ => unimplemented!(),
+            },
+            ValType::PrevAssigned(v) => match &self {
+                VarTensor::Advice { inner: advices, .. } => {
+                    v.copy_advice(|| "k", region, advices[x], y)
                 }
-            }),
+                _ => Err(halo2_proofs::plonk::Error::Synthesis),
+            },
+            ValType::AssignedValue(v) => match &self {
+                VarTensor::Fixed { inner: fixed, .. } => region
+                    .assign_fixed(|| "k", fixed[x], y, || v)
+                    .map(|a| a.evaluate()),
+                VarTensor::Advice { inner: advices, .. } => region
+                    .assign_advice(|| "k", advices[x], y, || v)
+                    .map(|a| a.evaluate()),
+                _ => unimplemented!(),
+            },
+            ValType::Constant(c) => match &self {
+                VarTensor::Fixed { inner: fixed, .. } => {
+                    region.assign_fixed(|| "k", fixed[x], y, || Value::known(c))
+                }
+                VarTensor::Advice { inner: advices, .. } => match constants.get(&c) {
+                    Some(cell) => cell.copy_advice(|| "k", region, advices[x], y),
+                    None => {
+                        let cell = region.assign_advice_from_constant(|| "k", advices[x], y, c)?;
+                        constants.insert(c, cell.clone());
+                        Ok(cell)
+                    }
+                },
+                _ => unimplemented!(),
+            },
         }
     }
 
@@ -258,38 +304,14 @@ impl VarTensor {
             ValTensor::Value { inner: v, dims } => {
                 // duplicates every nth element to adjust for column overflow               
                 let v = v.duplicate_every_n(self.col_size(), offset).unwrap();
+                let mut constants = BTreeMap::new();
                 let res = v.enum_map(|coord, k| {
                     let (x, y) = self.cartesian_coord(offset + coord);
                     if matches!(check_mode, CheckMode::SAFE) && x > 0 && y == 0 {
                         // assert that duplication occurred correctly
                         assert_eq!(Into::<i32>::into(k.clone()), Into::<i32>::into(v[coord - 1].clone()));
                     };
-                    match k {
-                        ValType::Value(v) => match &self {
-                            VarTensor::Fixed { inner: fixed, .. } => {
-                                region.assign_fixed(|| "k", fixed[x], y, || v)
-                            }
-                            VarTensor::Advice { inner: advices, .. } => {
-                                region.assign_advice(|| "k", advices[x], y, || v)
-                            }, 
-                            _ => unimplemented!(),
-                        },
-                        ValType::PrevAssigned(v) => match &self {
-                            VarTensor::Advice { inner: advices, .. } => {
-                                v.copy_advice(|| "k", region, advices[x], y)
-                            }
-                            _ => Err(halo2_proofs::plonk::Error::Synthesis),
-                        },
-                        ValType::AssignedValue(v) => match &self {
-                            VarTensor::Fixed { inner: fixed, .. } => region
-                                .assign_fixed(|| "k", fixed[x], y, || v)
-                                .map(|a| a.evaluate()),
-                            VarTensor::Advice { inner: advices, .. } => region
-                                .assign_advice(|| "k", advices[x], y, || v)
-                                .map(|a| a.evaluate()),
-                            _ => unimplemented!(),
-                        },
-                    }
+                    self.assign_value(region, offset + coord, k, &mut constants)
                 })?;
                 let mut non_duplicated_res = res.remove_every_n(self.col_size(), offset).unwrap();
                 
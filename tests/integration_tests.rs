@@ -183,9 +183,14 @@ macro_rules! test_func {
             use crate::mock_public_params;
             use crate::forward_pass;
             use crate::kzg_prove_and_verify;
+            use crate::kzg_setup_prove_and_verify;
+            use crate::kzg_bench;
             use crate::render_circuit;
             use crate::tutorial as run_tutorial;
 
+            /// Generating the params from scratch and benchmarking are slow, so the setup and the
+            /// bench are only run on a couple of small models
+            const TESTS_SMALL: [&str; 2] = ["1l_relu", "1l_mlp"];
 
             #[test]
             fn tutorial_() {
@@ -226,6 +231,20 @@ macro_rules! test_func {
 
             });
 
+            seq!(N in 0..=1 {
+
+            #(#[test_case(TESTS_SMALL[N])])*
+            fn kzg_setup_prove_and_verify_(test: &str) {
+                kzg_setup_prove_and_verify(test.to_string());
+            }
+
+            #(#[test_case(TESTS_SMALL[N])])*
+            fn kzg_bench_(test: &str) {
+                kzg_bench(test.to_string());
+            }
+
+            });
+
     }
     };
 }
@@ -308,12 +327,138 @@ macro_rules! test_neg_examples {
     };
 }
 
+macro_rules! test_claims {
+    () => {
+        #[cfg(test)]
+        mod claim_tests {
+            use crate::mock_claim;
+
+            // the outputs of 1l_relu are [0.0, 2.49, 0.58]
+
+            #[test]
+            fn mock_argmax_outputs_() {
+                let args = ["--argmax-outputs"];
+                mock_claim("1l_relu", "argmax", &args, vec![1.0], true);
+            }
+
+            #[test]
+            fn mock_argmax_outputs_wrong_class_() {
+                let args = ["--argmax-outputs"];
+                mock_claim("1l_relu", "argmax_wrong", &args, vec![2.0], false);
+            }
+
+            #[test]
+            fn mock_hashed_params_() {
+                let args = ["--hashed-params"];
+                let outputs = vec![0.28125, 0.6484375, 0.0, 0.0];
+                mock_claim("1l_mlp", "hashed_params", &args, outputs, true);
+            }
+
+            #[test]
+            fn mock_hashed_inputs_() {
+                let args = ["--hashed-inputs"];
+                let outputs = vec![0.0, 2.493845224380493, 0.5796360969543457];
+                mock_claim("1l_relu", "hashed_inputs", &args, outputs, true);
+            }
+
+            #[test]
+            fn mock_hashed_outputs_() {
+                let args = ["--hashed-outputs"];
+                let outputs = vec![0.0, 2.493845224380493, 0.5796360969543457];
+                mock_claim("1l_relu", "hashed_outputs", &args, outputs, true);
+            }
+
+            #[test]
+            fn mock_hashed_outputs_wrong_digest_() {
+                // the digest of other outputs
+                let args = ["--hashed-outputs"];
+                let outputs = vec![0.0, 2.6, 0.5796360969543457];
+                mock_claim("1l_relu", "hashed_outputs_wrong", &args, outputs, false);
+            }
+
+            #[test]
+            fn mock_output_epsilon_() {
+                // within 0.05 of the outputs
+                let args = ["--output-epsilon=0.05"];
+                let outputs = vec![0.0, 2.52, 0.58];
+                mock_claim("1l_relu", "epsilon", &args, outputs, true);
+            }
+
+            #[test]
+            fn mock_output_epsilon_wrong_output_() {
+                let args = ["--output-epsilon=0.05"];
+                let outputs = vec![0.0, 2.6, 0.58];
+                mock_claim("1l_relu", "epsilon_wrong", &args, outputs, false);
+            }
+
+            #[test]
+            fn mock_threshold_claim_() {
+                let args = ["--threshold-claim=1:2.0", "--fixed-params"];
+                mock_claim("1l_relu", "threshold", &args, vec![1.0], true);
+            }
+
+            #[test]
+            fn mock_threshold_claim_wrong_claim_() {
+                let args = ["--threshold-claim=1:2.0", "--fixed-params"];
+                mock_claim("1l_relu", "threshold_wrong", &args, vec![0.0], false);
+            }
+
+            #[test]
+            fn mock_threshold_claim_private_params_() {
+                // the threshold would be free advice
+                let args = ["--threshold-claim=1:2.0"];
+                mock_claim("1l_relu", "threshold_private", &args, vec![1.0], false);
+            }
+
+            #[test]
+            fn mock_threshold_claim_public_params_() {
+                // the threshold would be advice to the comparison
+                let args = ["--threshold-claim=1:2.0", "--public-params"];
+                mock_claim("1l_relu", "threshold_public", &args, vec![1.0], false);
+            }
+
+            // 1l_folded adds the folded [2, 4, 6] to its inputs [0.5, -3.0, 1.0], through an
+            // identity and a dropout which are bypassed
+
+            #[test]
+            fn mock_folded_() {
+                let outputs = vec![2.5, 1.0, 7.0];
+                mock_claim("1l_folded", "folded", &[], outputs, true);
+            }
+
+            #[test]
+            fn mock_folded_wrong_output_() {
+                // the outputs were the initializers added without their Mul
+                let outputs = vec![1.5, -1.0, 4.0];
+                mock_claim("1l_folded", "folded_wrong", &[], outputs, false);
+            }
+
+            // 1l_int_div_mod divides its int64 inputs [7, 10, 4] by an int64 2 and takes their
+            // remainder by an int64 3
+
+            #[test]
+            fn mock_int_div_mod_() {
+                let outputs = vec![0.0, 2.0, 2.0];
+                mock_claim("1l_int_div_mod", "int_div_mod", &[], outputs, true);
+            }
+
+            #[test]
+            fn mock_int_div_mod_wrong_output_() {
+                // the outputs were the division not truncated
+                let outputs = vec![0.5, 2.0, 2.0];
+                mock_claim("1l_int_div_mod", "int_div_mod_wrong", &[], outputs, false);
+            }
+        }
+    };
+}
+
 test_func!();
 test_func_aggr!();
 test_func_evm!();
 test_func_examples!();
 test_neg_examples!();
 test_packed_func!();
+test_claims!();
 
 // Mock prove (fast, but does not cover some potential issues)
 fn neg_mock(example_name: String, counter_example: String) {
@@ -446,6 +591,26 @@ fn mock(example_name: String) {
     assert!(status.success());
 }
 
+// Mock prove the example with its output data replaced by `outputs`, as claimed by the prover
+fn mock_claim(example_name: &str, tag: &str, args: &[&str], outputs: Vec<f32>, expected: bool) {
+    let data_path = format!("./examples/onnx/{}/input.json", example_name);
+    let mut data: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(data_path).unwrap()).unwrap();
+    data["output_data"] = serde_json::json!([outputs]);
+    let claim_path = format!("{}/{}.json", TEST_DIR.path().to_str().unwrap(), tag);
+    std::fs::write(&claim_path, data.to_string()).unwrap();
+
+    let model_path = format!("./examples/onnx/{}/network.onnx", example_name);
+    let mut cmd_args = vec!["--bits=16", "-K=17"];
+    cmd_args.extend(args);
+    cmd_args.extend(["mock", "-D", claim_path.as_str(), "-M", model_path.as_str()]);
+    let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+        .args(cmd_args)
+        .status()
+        .expect("failed to execute process");
+    assert_eq!(status.success(), expected);
+}
+
 // Mock prove (fast, but does not cover some potential issues)
 fn mock_packed_outputs(example_name: String) {
     let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
@@ -776,6 +941,148 @@ fn kzg_prove_and_verify(example_name: String) {
     assert!(status.success());
 }
 
+// setup-prove-verify, with the keys generated ahead of the proof
+fn kzg_bench(example_name: String) {
+    let report = format!(
+        "{}/{}_bench.json",
+        TEST_DIR.path().to_str().unwrap(),
+        example_name
+    );
+    let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+        .args([
+            "--bits=16",
+            "-K=17",
+            "bench",
+            "-D",
+            format!("./examples/onnx/{}/input.json", example_name).as_str(),
+            "-M",
+            format!("./examples/onnx/{}/network.onnx", example_name).as_str(),
+            &format!(
+                "--params-path={}/kzg17.params",
+                TEST_DIR.path().to_str().unwrap()
+            ),
+            "-O",
+            &report,
+        ])
+        .status()
+        .expect("failed to execute process");
+    assert!(status.success());
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+    assert_eq!(report["verified"], true);
+    assert_eq!(report["timings"].as_array().unwrap().len(), 4);
+}
+
+fn kzg_setup_prove_and_verify(example_name: String) {
+    let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+        .args([
+            "--bits=16",
+            "-K=17",
+            "setup",
+            "-D",
+            format!("./examples/onnx/{}/input.json", example_name).as_str(),
+            "-M",
+            format!("./examples/onnx/{}/network.onnx", example_name).as_str(),
+            "--vk-path",
+            &format!(
+                "{}/{}_setup.vk",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            "--pk-path",
+            &format!(
+                "{}/{}_setup.pk",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            &format!(
+                "--params-path={}/kzg17.params",
+                TEST_DIR.path().to_str().unwrap()
+            ),
+            "--manifest-path",
+            &format!(
+                "{}/{}_setup.manifest.json",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+        ])
+        .status()
+        .expect("failed to execute process");
+    assert!(status.success());
+    let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+        .args([
+            "--bits=16",
+            "-K=17",
+            "prove",
+            "-D",
+            format!("./examples/onnx/{}/input.json", example_name).as_str(),
+            "-M",
+            format!("./examples/onnx/{}/network.onnx", example_name).as_str(),
+            "--proof-path",
+            &format!(
+                "{}/{}_setup.pf",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            "--vk-path",
+            &format!(
+                "{}/{}_prove.vk",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            "--pk-path",
+            &format!(
+                "{}/{}_setup.pk",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            &format!(
+                "--params-path={}/kzg17.params",
+                TEST_DIR.path().to_str().unwrap()
+            ),
+            "--transcript=blake",
+            "--strategy=single",
+        ])
+        .status()
+        .expect("failed to execute process");
+    assert!(status.success());
+    let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+        .args([
+            "--bits=16",
+            "-K=17",
+            "verify",
+            "-M",
+            format!("./examples/onnx/{}/network.onnx", example_name).as_str(),
+            "--proof-path",
+            &format!(
+                "{}/{}_setup.pf",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            "--vk-path",
+            &format!(
+                "{}/{}_setup.vk",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            &format!(
+                "--params-path={}/kzg17.params",
+                TEST_DIR.path().to_str().unwrap()
+            ),
+            // checked against the manifest saved at setup
+            "--manifest-path",
+            &format!(
+                "{}/{}_setup.manifest.json",
+                TEST_DIR.path().to_str().unwrap(),
+                example_name
+            ),
+            "--transcript=blake",
+        ])
+        .status()
+        .expect("failed to execute process");
+    assert!(status.success());
+}
+
 // prove-serialize-verify, the usual full path
 fn kzg_evm_prove_and_verify(example_name: String, with_solidity: bool) {
     let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))